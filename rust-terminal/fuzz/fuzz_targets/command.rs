@@ -0,0 +1,12 @@
+// Arbitrary bytes, decoded the way output is, through the command parser
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../../src/core/command.rs"]
+#[allow(dead_code)]
+mod command;
+
+fuzz_target!(|data: &[u8]| {
+    let input = String::from_utf8_lossy(data);
+    let _ = command::CommandParser::new().parse(&input);
+});
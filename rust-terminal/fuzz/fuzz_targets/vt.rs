@@ -0,0 +1,23 @@
+// Arbitrary pty output through the VT parser: it must never panic or hang.
+// The first two bytes pick the screen size, the third where a resize falls
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+// nexterm is a binary crate, so the parser is compiled in from its source
+#[path = "../../src/core/vt.rs"]
+#[allow(dead_code)]
+mod vt;
+
+fuzz_target!(|data: &[u8]| {
+    let [rows, cols, cut, rest @ ..] = data else {
+        return;
+    };
+    let mut screen = vt::Screen::new(*rows as usize % 64, *cols as usize);
+    let (first, second) = rest.split_at((*cut as usize * rest.len()) / 256);
+    screen.feed(first);
+    screen.resize(*cols as usize % 64, *rows as usize);
+    screen.feed(second);
+    screen.view();
+    screen.live_lines();
+    screen.take_scrolled();
+});
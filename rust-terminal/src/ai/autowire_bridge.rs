@@ -1,10 +1,33 @@
 // AI Auto-Wiring Bridge - Connects Rust Terminal to Python Auto-Wiring System
-use anyhow::{Result, Context};
+// Services come from the native registry (ai::registry), with the Python
+// system's added by a provider when it's reachable.
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::process::{Command, Stdio};
-use std::io::Write;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tracing::{info, debug, warn};
 
+use crate::ai::autowire_daemon::Health;
+use crate::ai::proposals::Proposals;
+use crate::ai::protocol;
+use crate::ai::registry::{Agent, ServiceProvider, ServiceRegistry, AGENT_TAG};
+use crate::ai::review::{self, Finding};
+use crate::ai::routing::{self, Routing};
+use crate::ai::slo::Breach;
+use crate::ai::transport::{self, Transport};
+use crate::ai::cheatsheets::{with_cheatsheet, Cheatsheet};
+use crate::ai::fallbacks::Fallback;
+use crate::ai::completion_specs;
+use crate::core::actions::{Action, Target};
+use crate::core::command::CommandParser;
+use crate::core::operations::Progress;
+use crate::utils::clock;
+use crate::utils::error::NexTermError;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AutoWireResult {
     pub processed_command: String,
@@ -13,30 +36,49 @@ pub struct AutoWireResult {
     pub confidence: f32,
 }
 
+// Offline suggestions for one command, at most
+const OFFLINE_SUGGESTIONS: usize = 3;
+
+// Service listings are queried every frame; re-fetch at most this often
+const SERVICES_TTL: std::time::Duration = std::time::Duration::from_secs(5);
+
 pub struct AutoWireBridge {
-    python_available: bool,
+    // False when the transport couldn't be set up, e.g. python3 is missing
+    available: bool,
+    transport: Arc<Transport>,
+    registry: ServiceRegistry,
     cache: std::collections::HashMap<String, AutoWireResult>,
+    project_notes: Option<String>,
+    // Why the last process_command didn't get the worker's answer
+    fallback: Option<Fallback>,
 }
 
 impl AutoWireBridge {
     pub fn new() -> Result<Self> {
-        // Check Python availability
-        let python_check = Command::new("python3")
-            .args(&["--version"])
-            .output()
-            .is_ok();
+        let transport = transport::shared();
+        let available = transport.is_available();
+        info!("Auto-wiring over the {} transport (available: {})", transport.kind(), available);
 
-        info!("Python available: {}", python_check);
+        let registry = ServiceRegistry::builtin()?;
+        if available {
+            registry.add_provider(Arc::new(PythonServices::new(transport.clone())));
+        }
 
         Ok(Self {
-            python_available: python_check,
+            available,
+            transport,
+            registry,
             cache: std::collections::HashMap::new(),
+            project_notes: None,
+            fallback: None,
         })
     }
 
     pub async fn process_command(&mut self, command: &str) -> Result<Option<AutoWireResult>> {
-        if !self.python_available {
-            return Ok(None);
+        self.fallback = None;
+        if !self.available {
+            self.fallback = Some(Fallback::Unavailable);
+            return Ok(self.native_result(command));
         }
 
         // Check cache first
@@ -44,9 +86,25 @@ impl AutoWireBridge {
             debug!("Using cached auto-wire result for: {}", command);
             return Ok(Some(cached.clone()));
         }
+        // Not cached: they'd stay offline after the worker catches up
+        if self.transport.slo_breach().is_some() {
+            self.fallback = Some(Fallback::SloBreach);
+            return Ok(offline_result(command));
+        }
 
-        // Process through Python auto-wiring system
-        let result = self.call_python_autowire(command).await?;
+        // Process through the auto-wiring worker
+        let params = process_params(&self.transport, command, self.project_notes.as_deref());
+        let result = match self.transport.call("process_command", params).await {
+            Ok(reply) => parse_result(reply),
+            Err(e) => {
+                warn!("Auto-wire processing failed: {:#}", e);
+                self.fallback = Some(Fallback::from_error(&e));
+                None
+            }
+        };
+        if result.is_none() && self.fallback.is_none() {
+            self.fallback = Some(Fallback::BadReply);
+        }
 
         // Cache the result
         if let Some(ref res) = result {
@@ -56,141 +114,316 @@ impl AutoWireBridge {
         Ok(result)
     }
 
-    async fn call_python_autowire(&self, command: &str) -> Result<Option<AutoWireResult>> {
-        let python_script = format!(
-            r#"
-import sys
-import json
-sys.path.insert(0, '../src')
-
-try:
-    from core.autowire import get_autowire
-    from agents.base_agent import BaseAgent, AgentContext
-    
-    # Get auto-wire instance
-    autowire = get_autowire()
-    
-    # Simple command processing
-    command = {}
-    
-    # Check if it's a special command
-    suggestions = []
-    processed_command = command
-    
-    if command.startswith('ai '):
-        # AI-assisted command
-        suggestions.append("Using AI agent for processing")
-        processed_command = command[3:]  # Remove 'ai ' prefix
-    elif 'docker' in command:
-        suggestions.append("Docker agent available")
-    elif 'git' in command:
-        suggestions.append("Git agent available")
-    
-    result = {{
-        "processed_command": processed_command,
-        "suggestions": suggestions,
-        "agent_used": None,
-        "confidence": 0.8
-    }}
-    
-    print(json.dumps(result))
-except Exception as e:
-    print(json.dumps({{"error": str(e)}}), file=sys.stderr)
-"#,
-            serde_json::to_string(command)?
-        );
-
-        let output = Command::new("python3")
-            .arg("-c")
-            .arg(&python_script)
-            .output()
-            .context("Failed to execute Python auto-wire script")?;
-
-        if !output.status.success() {
-            warn!("Python auto-wire execution failed: {}", 
-                  String::from_utf8_lossy(&output.stderr));
-            return Ok(None);
-        }
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        if stdout.trim().is_empty() {
-            return Ok(None);
-        }
-
-        match serde_json::from_str::<AutoWireResult>(&stdout) {
-            Ok(result) => {
-                info!("Auto-wire result: confidence={}", result.confidence);
-                Ok(Some(result))
-            }
-            Err(e) => {
-                warn!("Failed to parse auto-wire result: {}", e);
-                Ok(None)
-            }
+    // Why the last process_command fell back, handed out once
+    pub fn take_fallback(&mut self) -> Option<Fallback> {
+        self.fallback.take()
+    }
+
+    // Blocking worker round-trip for one command; usable off the bridge (e.g. from
+    // spawn_blocking) since it needs no bridge state. `project_notes` is extra
+    // context whose relevant lines come back as suggestions; the cheatsheet of
+    // the tool being run is added to it.
+    pub fn run_autowire_script(command: &str, project_notes: Option<&str>) -> Result<Option<AutoWireResult>> {
+        if transport::shared().slo_breach().is_some() {
+            return Ok(offline_result(command));
         }
+        let transport = transport::shared();
+        let reply = transport.call_blocking("process_command", process_params(&transport, command, project_notes))?;
+        Ok(parse_result(reply))
     }
 
     pub fn service_count(&self) -> usize {
-        // Query auto-wiring system for service count
-        if !self.python_available {
-            return 0;
-        }
-
-        let python_script = r#"
-import sys
-sys.path.insert(0, '../src')
-try:
-    from core.autowire import get_autowire
-    autowire = get_autowire()
-    print(len(autowire.get_registry_info()))
-except:
-    print(0)
-"#;
-
-        Command::new("python3")
-            .arg("-c")
-            .arg(python_script)
-            .output()
-            .ok()
-            .and_then(|output| {
-                String::from_utf8(output.stdout)
-                    .ok()
-                    .and_then(|s| s.trim().parse().ok())
-            })
-            .unwrap_or(0)
+        self.list_services().len()
     }
 
+    // Native services, then the Python system's; never blocks
     pub fn list_services(&self) -> Vec<String> {
-        if !self.python_available {
-            return vec![];
-        }
+        self.registry.list()
+    }
+
+    pub fn service_details(&self, name: &str) -> Result<String> {
+        self.registry.details(name)
+    }
+
+    pub fn invoke_service(&self, name: &str) -> Result<String> {
+        self.registry.invoke(name)
+    }
+
+    pub fn restart_service(&self, name: &str) -> Result<String> {
+        self.registry.restart(name)
+    }
 
-        let python_script = r#"
-import sys
-import json
-sys.path.insert(0, '../src')
-try:
-    from core.autowire import get_autowire
-    autowire = get_autowire()
-    services = list(autowire.get_registry_info().keys())
-    print(json.dumps(services))
-except:
-    print("[]")
-"#;
-
-        Command::new("python3")
-            .arg("-c")
-            .arg(python_script)
-            .output()
-            .ok()
-            .and_then(|output| {
-                String::from_utf8(output.stdout).ok()
+    // A palette action per native agent, invoking it as the panel does
+    pub fn agent_actions(&self) -> Vec<Action> {
+        let capabilities = self.registry.capabilities();
+        self.registry
+            .with_tag(AGENT_TAG)
+            .into_iter()
+            .map(|name| {
+                let keywords = capabilities.iter().find(|(n, _)| *n == name).map(|(_, c)| c.clone()).unwrap_or_default();
+                Action::new(&format!("agent.{}", name), &format!("Invoke {}", name), Target::Service(name.clone()))
+                    .with_icon("🤖")
+                    .with_keywords(&keywords)
+                    .with_keywords(&["agent"])
             })
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default()
+            .collect()
+    }
+
+    // Which native service a command goes to, and why
+    pub fn route(&self, command: &str) -> Result<Routing> {
+        Ok(routing::route(&self.registry, &CommandParser::new().parse(command)?))
+    }
+
+    // Without Python: what the native agent the command is routed to suggests
+    fn native_result(&self, command: &str) -> Option<AutoWireResult> {
+        let routing = self.route(command).ok()?;
+        let chosen = routing.chosen()?;
+        let agent = self.registry.resolve_as::<Agent>(&chosen.service).ok()?;
+        Some(AutoWireResult {
+            processed_command: command.to_string(),
+            suggestions: agent.suggestions(routing.program.as_deref()?, OFFLINE_SUGGESTIONS),
+            agent_used: Some(chosen.service.clone()),
+            confidence: 0.5,
+        })
+    }
+
+    // `review`: findings for a diff, sent `max_tokens` at a time, a step of
+    // `progress` each
+    pub fn review(
+        &self,
+        diff: &str,
+        max_tokens: usize,
+        progress: Progress,
+    ) -> impl Future<Output = Result<Vec<Finding>>> + Send + 'static {
+        let (available, transport) = (self.available, self.transport.clone());
+        let chunks = review::chunks(diff, max_tokens * review::CHARS_PER_TOKEN);
+        async move {
+            if !available {
+                return Err(NexTermError::BridgeUnavailable("reviews need the auto-wiring system".to_string()).into());
+            }
+            // Answered after the handshake, so the features are known by then
+            transport.call("ping", json!({})).await?;
+            if !transport.supports(protocol::REVIEW) {
+                let reason = "the auto-wiring system doesn't review diffs".to_string();
+                return Err(NexTermError::BridgeUnavailable(reason).into());
+            }
+            progress.set_total(chunks.len() as u64);
+            let mut findings = Vec::new();
+            for (i, chunk) in chunks.into_iter().enumerate() {
+                progress.advance(format!("part {}", i + 1));
+                let reply = transport.call("review_diff", json!({ "diff": chunk })).await?;
+                findings.extend(serde_json::from_value::<Vec<Finding>>(reply)?);
+            }
+            review::sort(&mut findings);
+            Ok(findings)
+        }
+    }
+
+    // `scriptify --polish`: the script as the auto-wiring system tidies it
+    pub fn polish_script(&self, script: &str) -> impl Future<Output = Result<String>> + Send + 'static {
+        let (available, transport, script) = (self.available, self.transport.clone(), script.to_string());
+        async move {
+            if !available {
+                return Err(NexTermError::BridgeUnavailable("polishing needs the auto-wiring system".to_string()).into());
+            }
+            transport.call("ping", json!({})).await?;
+            if !transport.supports(protocol::SCRIPT_POLISH) {
+                let reason = "the auto-wiring system doesn't polish scripts".to_string();
+                return Err(NexTermError::BridgeUnavailable(reason).into());
+            }
+            let reply = transport.call("polish_script", json!({ "script": script })).await?;
+            Ok(reply.as_str().map(str::to_string).unwrap_or(script))
+        }
+    }
+
+    // `ai do`: actions proposed for `request`, given what the ones approved so
+    // far came to (`results`)
+    pub fn propose_actions(
+        &self,
+        request: &str,
+        cwd: &Path,
+        results: &[Value],
+    ) -> impl Future<Output = Result<Proposals>> + Send + 'static {
+        let (available, transport) = (self.available, self.transport.clone());
+        let params = json!({
+            "request": request,
+            "cwd": cwd,
+            "context": self.project_notes,
+            "results": results,
+        });
+        async move {
+            if !available {
+                return Err(NexTermError::BridgeUnavailable("`ai do` needs the auto-wiring system".to_string()).into());
+            }
+            transport.call("ping", json!({})).await?;
+            if !transport.supports(protocol::ACTIONS) {
+                let reason = "the auto-wiring system doesn't propose actions".to_string();
+                return Err(NexTermError::BridgeUnavailable(reason).into());
+            }
+            Ok(serde_json::from_value(transport.call("propose_actions", params).await?)?)
+        }
+    }
+
+    // State of the worker process, for the status bar
+    pub fn health(&self) -> Health {
+        self.transport.health()
+    }
+
+    // The local worker's recent output and restarts, for the autowire panel
+    pub fn worker_logs(&self) -> Vec<String> {
+        self.transport.logs()
+    }
+
+    // Set while the worker is too slow to wait on; suggestions are offline meanwhile
+    pub fn slo_breach(&self) -> Option<Breach> {
+        if !self.available {
+            return None;
+        }
+        self.transport.slo_breach()
     }
 
-    pub fn clear_cache(&mut self) {
+    // Stops the worker while the user is idle; the next request restarts it
+    pub fn suspend(&self) {
+        if self.available {
+            self.transport.suspend();
+        }
+    }
+
+    // Results depend on the notes, so cached ones are dropped when they change
+    pub fn set_project_notes(&mut self, notes: Option<String>) {
+        self.project_notes = notes;
         self.cache.clear();
+        // Scoped services (e.g. cheatsheets) are per project too
+        self.registry.clear_scoped();
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.available
+    }
+}
+
+// Last listing and when it was fetched
+type Listing = Option<(Instant, Vec<String>)>;
+
+// The Python system's services, as a registry provider
+struct PythonServices {
+    transport: Arc<Transport>,
+    // Refreshed off the UI thread
+    cache: Arc<Mutex<Listing>>,
+    refreshing: Arc<AtomicBool>,
+}
+
+impl PythonServices {
+    fn new(transport: Arc<Transport>) -> Self {
+        Self {
+            transport,
+            cache: Arc::new(Mutex::new(None)),
+            refreshing: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Returns the worker's text reply or its Python error
+    fn call(&self, method: &str, name: &str) -> Result<String> {
+        if !self.transport.supports(protocol::SERVICES) {
+            return Err(NexTermError::BridgeUnavailable("the auto-wiring system offers no services".to_string()).into());
+        }
+        let reply = self.transport.call_blocking(method, json!({ "name": name }))?;
+        Ok(reply.as_str().unwrap_or_default().trim().to_string())
+    }
+}
+
+impl ServiceProvider for PythonServices {
+    // Returns the last listing and asks the worker for a fresh one in the
+    // background once it is older than SERVICES_TTL (but doesn't wake a
+    // suspended worker for it)
+    fn services(&self) -> Vec<String> {
+        if !self.transport.supports(protocol::SERVICES) {
+            return vec![];
+        }
+
+        let (services, stale) = match &*self.cache.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some((fetched, services)) => (services.clone(), clock::since(*fetched) >= SERVICES_TTL),
+            None => (Vec::new(), true),
+        };
+        if stale && !self.transport.is_suspended() && !self.refreshing.swap(true, Ordering::SeqCst) {
+            let cache = self.cache.clone();
+            let refreshing = self.refreshing.clone();
+            let previous = services.clone();
+            let sent = self.transport.call_with("list_services", json!({}), move |reply| {
+                // A failed fetch keeps the old list until the next refresh
+                let services = reply
+                    .ok()
+                    .and_then(|value| serde_json::from_value(value).ok())
+                    .unwrap_or(previous);
+                if let Ok(mut cache) = cache.lock() {
+                    *cache = Some((clock::now(), services));
+                }
+                refreshing.store(false, Ordering::SeqCst);
+            });
+            if sent.is_err() {
+                self.refreshing.store(false, Ordering::SeqCst);
+            }
+        }
+        services
+    }
+
+    fn details(&self, name: &str) -> Result<String> {
+        self.call("service_details", name)
+    }
+
+    fn invoke(&self, name: &str) -> Result<String> {
+        self.call("invoke_service", name)
+    }
+
+    fn restart(&self, name: &str) -> Result<String> {
+        self.call("restart_service", name)
+    }
+}
+
+// Request parameters: the command plus project notes, its tool's cheatsheet
+// and the flags the tool documents, as far as the server takes them
+fn process_params(transport: &Transport, command: &str, project_notes: Option<&str>) -> Value {
+    let flags = transport
+        .supports(protocol::FLAG_HINTS)
+        .then(|| completion_specs::for_command(command))
+        .flatten()
+        .map(|(program, spec)| json!({ "program": program, "documented": spec.flags }));
+    let context = transport
+        .supports(protocol::PROJECT_NOTES)
+        .then(|| with_cheatsheet(command, project_notes))
+        .flatten();
+    json!({
+        "command": command,
+        "context": context,
+        "flags": flags,
+    })
+}
+
+// What can be suggested without the worker: the examples in the tool's cheatsheet
+fn offline_result(command: &str) -> Option<AutoWireResult> {
+    let mut suggestions = Cheatsheet::for_command(command)?.examples();
+    if suggestions.is_empty() {
+        return None;
+    }
+    suggestions.truncate(OFFLINE_SUGGESTIONS);
+    Some(AutoWireResult {
+        processed_command: command.to_string(),
+        suggestions,
+        agent_used: Some("offline".to_string()),
+        confidence: 0.0,
+    })
+}
+
+fn parse_result(reply: Value) -> Option<AutoWireResult> {
+    match serde_json::from_value::<AutoWireResult>(reply) {
+        Ok(result) => {
+            info!("Auto-wire result: confidence={}", result.confidence);
+            Some(result)
+        }
+        Err(e) => {
+            warn!("Failed to parse auto-wire result: {}", e);
+            None
+        }
     }
 }
 
@@ -0,0 +1,796 @@
+// Long-lived Python auto-wiring worker: one process per NexTerm, spoken to over
+// stdin/stdout with length-prefixed JSON-RPC frames (4-byte big-endian length,
+// then `{"id", "method", "params"}` / `{"id", "result" | "error"}`),
+// respawned with backoff when it dies, and stopped while the user is idle
+// until the next request. The worker runs under the configured interpreter
+// (or virtualenv) with the auto-wiring package on its path, and its output and
+// restarts are kept for the autowire panel.
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::ai::transport::{into_reply, Reply, TransportKind};
+use crate::utils::clock;
+use crate::utils::error::NexTermError;
+use crate::utils::rng::Rng;
+use crate::utils::supervisor;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoWireConfig {
+    // Requests without a reply after this long fail
+    pub timeout_ms: u64,
+    // Crashes in a row before the worker is left down
+    pub max_restarts: u32,
+    // Median and p95 round trips (ms) past which suggestions go offline for a
+    // while; 0 disables either check
+    pub slo_p50_ms: u64,
+    pub slo_p95_ms: u64,
+    // How the auto-wiring system is reached, see ai::transport
+    pub transport: TransportKind,
+    // Socket path or URL, for the remote transports
+    pub endpoint: Option<String>,
+    // Bearer token for the remote transports; "$VAR" reads it from the environment
+    pub auth_token: Option<String>,
+    // Interpreter for the stdio worker, or a virtualenv directory to use the
+    // python3 of; python3 from PATH when unset
+    pub python: Option<String>,
+    // Directory holding the auto-wiring package (core/autowire.py); looked
+    // for next to NexTerm's checkout when unset
+    pub package_path: Option<String>,
+    // Most of a diff `review` sends per request, in tokens
+    pub review_tokens: usize,
+    // Rewrites the worker is less sure of than this are left as typed (its
+    // suggestions are still shown); 0 accepts every rewrite
+    pub min_confidence: f32,
+}
+
+impl Default for AutoWireConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 2000,
+            max_restarts: 5,
+            slo_p50_ms: 400,
+            slo_p95_ms: 1500,
+            transport: TransportKind::Stdio,
+            endpoint: None,
+            auth_token: None,
+            python: None,
+            package_path: None,
+            review_tokens: 2000,
+            min_confidence: 0.0,
+        }
+    }
+}
+
+// A worker that stayed up this long starts counting crashes from zero again
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+// Doubled after each crash in a row, plus up to half again at random so
+// several NexTerms don't restart their workers in lockstep
+const RESTART_BACKOFF: Duration = Duration::from_millis(500);
+// Anything longer is a corrupt stream rather than a message
+const MAX_FRAME: usize = 64 << 20;
+// Worker output kept for the autowire panel
+const MAX_LOG_LINES: usize = 200;
+// The reply to a request that outlived the timeout; the transport turns it
+// into NexTermError::BridgeTimeout
+pub const TIMED_OUT: &str = "timed out";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Health {
+    Starting,
+    // `latency` is the round trip of the last answered request
+    Ready { pid: u32, latency: Option<Duration> },
+    Restarting { attempt: u32 },
+    // Stopped while idle; the next request starts it again
+    Suspended,
+    // A server reached through a remote transport
+    Remote { endpoint: String, latency: Option<Duration> },
+    Down(String),
+}
+
+struct Pending {
+    sent: Instant,
+    deadline: Instant,
+    reply: Reply,
+}
+
+pub struct AutoWireDaemon {
+    stdin: Mutex<Option<ChildStdin>>,
+    // Signalled with `stdin` once a worker is up
+    started: Condvar,
+    // Set while suspended; the supervisor waits on `resumed` for it to clear
+    suspended: Mutex<bool>,
+    resumed: Condvar,
+    // The current worker's exit was asked for, not a crash
+    stopping: AtomicBool,
+    pending: Mutex<HashMap<u64, Pending>>,
+    health: Mutex<Health>,
+    next_id: AtomicU64,
+    timeout_ms: AtomicU64,
+    max_restarts: AtomicU32,
+    interpreter: PathBuf,
+    package: Option<PathBuf>,
+    logs: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl AutoWireDaemon {
+    // The process-wide worker, started on first use with the config it was first asked for
+    pub fn shared(config: &AutoWireConfig) -> &'static AutoWireDaemon {
+        static DAEMON: OnceLock<AutoWireDaemon> = OnceLock::new();
+        let mut created = false;
+        let daemon = DAEMON.get_or_init(|| {
+            created = true;
+            AutoWireDaemon {
+                stdin: Mutex::new(None),
+                started: Condvar::new(),
+                suspended: Mutex::new(false),
+                resumed: Condvar::new(),
+                stopping: AtomicBool::new(false),
+                pending: Mutex::new(HashMap::new()),
+                health: Mutex::new(Health::Starting),
+                next_id: AtomicU64::new(1),
+                timeout_ms: AtomicU64::new(config.timeout_ms),
+                max_restarts: AtomicU32::new(config.max_restarts),
+                interpreter: interpreter(config),
+                package: package_dir(config),
+                logs: Arc::new(Mutex::new(VecDeque::new())),
+            }
+        });
+        // The first worker starts right away, so early requests don't race the thread
+        if created {
+            let worker = daemon.start_worker();
+            std::thread::spawn(move || daemon.supervise(worker));
+        }
+        daemon
+    }
+
+    // The worker's stderr and its starts, exits and restarts, oldest first
+    pub fn logs(&self) -> Vec<String> {
+        self.logs.lock().map(|l| l.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    fn log(&self, line: String) {
+        push_log(&self.logs, line);
+    }
+
+    // Cheap enough to poll every frame; also fails overdue requests, so a hung
+    // worker shows up as timeouts rather than callers waiting forever
+    pub fn health(&self) -> Health {
+        self.expire_overdue();
+        self.health.lock().map(|h| h.clone()).unwrap_or(Health::Starting)
+    }
+
+    // Stops the worker until the next request; its pending requests fail
+    pub fn suspend(&self) {
+        let Ok(mut stdin) = self.stdin.lock() else {
+            return;
+        };
+        if stdin.is_some() {
+            // Set before the pipe closes, so the supervisor sees why it exits
+            self.stopping.store(true, Ordering::SeqCst);
+            if let Ok(mut suspended) = self.suspended.lock() {
+                *suspended = true;
+            }
+            stdin.take();
+            info!("Auto-wiring worker suspended while idle");
+            self.log("suspended while idle".to_string());
+        }
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.lock().map(|s| *s).unwrap_or(false)
+    }
+
+    // Has the supervisor start a worker again; false when not suspended
+    fn resume(&self) -> bool {
+        let Ok(mut suspended) = self.suspended.lock() else {
+            return false;
+        };
+        if !std::mem::take(&mut *suspended) {
+            return false;
+        }
+        self.resumed.notify_all();
+        true
+    }
+
+    // Doesn't wait: `reply` runs on the worker's reader thread, or with an
+    // error once the request times out or the worker dies
+    pub fn call_with(
+        &self,
+        method: &str,
+        params: Value,
+        reply: Reply,
+    ) -> Result<()> {
+        self.send(method, params, reply).map(|_| ())
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.load(Ordering::Relaxed))
+    }
+
+    fn send(&self, method: &str, params: Value, reply: Reply) -> Result<u64> {
+        self.expire_overdue();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut stdin = self.stdin.lock().map_err(|_| anyhow!("auto-wiring worker lock poisoned"))?;
+        if stdin.is_none() && self.resume() {
+            stdin = self
+                .started
+                .wait_timeout_while(stdin, self.timeout(), |s| s.is_none())
+                .map_err(|_| anyhow!("auto-wiring worker lock poisoned"))?
+                .0;
+        }
+        let Some(pipe) = stdin.as_mut() else {
+            let reason = format!("worker not running ({})", describe(&self.health()));
+            return Err(NexTermError::BridgeUnavailable(reason).into());
+        };
+
+        // Registered before writing, so a fast reply can't arrive unclaimed
+        let now = clock::now();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(id, Pending { sent: now, deadline: now + self.timeout(), reply });
+        }
+        let request = json!({ "id": id, "method": method, "params": params });
+        if let Err(e) = write_frame(pipe, &request) {
+            self.forget(id);
+            bail!("auto-wiring worker unreachable: {}", e);
+        }
+        Ok(id)
+    }
+
+    fn forget(&self, id: u64) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&id);
+        }
+    }
+
+    fn expire_overdue(&self) {
+        let now = clock::now();
+        let overdue: Vec<Pending> = match self.pending.lock() {
+            Ok(mut pending) => {
+                let ids: Vec<u64> = pending.iter().filter(|(_, p)| p.deadline <= now).map(|(id, _)| *id).collect();
+                ids.iter().filter_map(|id| pending.remove(id)).collect()
+            }
+            Err(_) => return,
+        };
+        for request in overdue {
+            deliver(request, Err(TIMED_OUT.to_string()));
+        }
+    }
+
+    fn fail_pending(&self, reason: &str) {
+        let pending: Vec<Pending> = match self.pending.lock() {
+            Ok(mut pending) => pending.drain().map(|(_, p)| p).collect(),
+            Err(_) => return,
+        };
+        for request in pending {
+            deliver(request, Err(reason.to_string()));
+        }
+    }
+
+    fn set_health(&self, health: Health) {
+        if let Ok(mut current) = self.health.lock() {
+            *current = health;
+        }
+    }
+
+    fn start_worker(&self) -> Result<(Child, ChildStdout)> {
+        let (mut child, stdout) = self.spawn_worker()?;
+        info!("Auto-wiring worker started (pid {})", child.id());
+        self.log(format!("started {} (pid {})", self.interpreter.display(), child.id()));
+        self.set_health(Health::Ready { pid: child.id(), latency: None });
+        if let Ok(mut stdin) = self.stdin.lock() {
+            *stdin = child.stdin.take();
+            self.started.notify_all();
+        }
+        Ok((child, stdout))
+    }
+
+    // Reads the worker's replies and restarts it when it exits, until it keeps crashing
+    fn supervise(&self, mut worker: Result<(Child, ChildStdout)>) {
+        let mut crashes = 0;
+        let mut rng = Rng::new();
+        loop {
+            let (mut child, stdout) = match worker {
+                Ok(worker) => worker,
+                Err(e) => {
+                    warn!("Auto-wiring worker failed to start: {:#}", e);
+                    self.log(format!("failed to start: {:#}", e));
+                    self.set_health(Health::Down(format!("{:#}", e)));
+                    return;
+                }
+            };
+            let started = clock::now();
+            self.read_replies(stdout);
+
+            // EOF or a corrupt stream; either way this worker is done
+            if let Ok(mut stdin) = self.stdin.lock() {
+                stdin.take();
+            }
+            let _ = child.kill();
+            let status = child.wait();
+            if self.stopping.swap(false, Ordering::SeqCst) {
+                debug!("Auto-wiring worker stopped: {:?}", status);
+                self.fail_pending("auto-wiring worker suspended");
+                self.set_health(Health::Suspended);
+                // Until a request comes in; it may already have
+                if let Ok(suspended) = self.suspended.lock() {
+                    drop(self.resumed.wait_while(suspended, |s| *s));
+                }
+                info!("Auto-wiring worker resuming");
+                crashes = 0;
+                worker = self.start_worker();
+                continue;
+            }
+            warn!("Auto-wiring worker exited: {:?}", status);
+            self.log(match &status {
+                Ok(status) => format!("exited ({})", status),
+                Err(e) => format!("exited ({})", e),
+            });
+            self.fail_pending("auto-wiring worker exited");
+
+            if clock::since(started) >= STABLE_AFTER {
+                crashes = 0;
+            }
+            crashes += 1;
+            if crashes > self.max_restarts.load(Ordering::Relaxed) {
+                self.set_health(Health::Down(format!("crashed {} times in a row", crashes)));
+                self.log(format!("crashed {} times in a row, giving up", crashes));
+                return;
+            }
+            self.set_health(Health::Restarting { attempt: crashes });
+            let delay = restart_delay(crashes, &mut rng);
+            self.log(format!("restarting in {}ms (attempt {})", delay.as_millis(), crashes));
+            std::thread::sleep(delay);
+            worker = self.start_worker();
+        }
+    }
+
+    fn spawn_worker(&self) -> Result<(Child, ChildStdout)> {
+        let mut command = Command::new(&self.interpreter);
+        command.arg("-u").arg("-c").arg(WORKER);
+        if let Some(package) = &self.package {
+            command.env("NEXTERM_AUTOWIRE_PATH", package);
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("{} not available", self.interpreter.display()))?;
+        let stdout = child.stdout.take().context("worker has no stdout")?;
+        // The services' own logging, kept out of the UI but shown in the autowire panel
+        if let Some(stderr) = child.stderr.take() {
+            let logs = self.logs.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(|l| l.ok()) {
+                    debug!("autowire worker: {}", line);
+                    push_log(&logs, line);
+                }
+            });
+        }
+        Ok((child, stdout))
+    }
+
+    fn read_replies(&self, stdout: ChildStdout) {
+        let mut reader = BufReader::new(stdout);
+        loop {
+            let message = match read_frame(&mut reader) {
+                Ok(Some(message)) => message,
+                Ok(None) => return,
+                Err(e) => {
+                    warn!("Bad frame from auto-wiring worker: {}", e);
+                    return;
+                }
+            };
+            let Some(id) = message.get("id").and_then(Value::as_u64) else {
+                continue;
+            };
+            // Gone when the caller already gave up on it
+            let Some(request) = self.pending.lock().ok().and_then(|mut p| p.remove(&id)) else {
+                continue;
+            };
+            let elapsed = clock::since(request.sent);
+            if let Ok(mut health) = self.health.lock() {
+                if let Health::Ready { latency, .. } = &mut *health {
+                    *latency = Some(elapsed);
+                }
+            }
+            deliver(request, into_reply(&message));
+        }
+    }
+}
+
+fn restart_delay(crashes: u32, rng: &mut Rng) -> Duration {
+    let backoff = RESTART_BACKOFF * 2u32.saturating_pow(crashes - 1);
+    backoff + rng.jitter(backoff / 2)
+}
+
+// A panicking reply handler must not take the reader thread (or the main loop) with it
+fn deliver(request: Pending, reply: Result<Value, String>) {
+    if let Err(message) = supervisor::catch(|| (request.reply)(reply)) {
+        supervisor::fault("Auto-wiring reply handler", &message);
+    }
+}
+
+pub fn describe(health: &Health) -> String {
+    match health {
+        Health::Starting => "starting".to_string(),
+        Health::Ready { pid, .. } => format!("running as pid {}", pid),
+        Health::Restarting { attempt } => format!("restarting, attempt {}", attempt),
+        Health::Suspended => "suspended while idle".to_string(),
+        Health::Remote { endpoint, .. } => format!("connected to {}", endpoint),
+        Health::Down(reason) => format!("down: {}", reason),
+    }
+}
+
+// The interpreter the worker runs under; a virtualenv directory means its python3
+pub fn interpreter(config: &AutoWireConfig) -> PathBuf {
+    let Some(python) = &config.python else {
+        return PathBuf::from("python3");
+    };
+    let path = PathBuf::from(shellexpand::tilde(python).into_owned());
+    if path.is_dir() {
+        path.join("bin").join("python3")
+    } else {
+        path
+    }
+}
+
+// Where core/autowire.py lives: `package_path` as configured, else src/ of the
+// repository NexTerm is run from or was built in
+pub fn package_dir(config: &AutoWireConfig) -> Option<PathBuf> {
+    if let Some(path) = &config.package_path {
+        return Some(PathBuf::from(shellexpand::tilde(path).into_owned()));
+    }
+    let exe_dir = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf));
+    let candidates = [Some(PathBuf::from("../src")), Some(PathBuf::from("src")), exe_dir.map(|dir| dir.join("../../../src"))];
+    find_package(candidates.into_iter().flatten())
+}
+
+fn find_package(candidates: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+    candidates
+        .into_iter()
+        .find(|dir| dir.join("core").join("autowire.py").is_file())
+        .and_then(|dir| dir.canonicalize().ok())
+}
+
+fn push_log(logs: &Mutex<VecDeque<String>>, line: String) {
+    if let Ok(mut logs) = logs.lock() {
+        if logs.len() == MAX_LOG_LINES {
+            logs.pop_front();
+        }
+        logs.push_back(line);
+    }
+}
+
+pub fn write_frame(out: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = serde_json::to_vec(message)?;
+    out.write_all(&(body.len() as u32).to_be_bytes())?;
+    out.write_all(&body)?;
+    out.flush()
+}
+
+// None at a clean end of stream
+pub fn read_frame(input: &mut impl Read) -> io::Result<Option<Value>> {
+    let mut header = [0u8; 4];
+    match input.read_exact(&mut header) {
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    let len = u32::from_be_bytes(header) as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(ErrorKind::InvalidData, format!("frame of {} bytes", len)));
+    }
+    let mut body = vec![0; len];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+// The worker imports the auto-wiring package once and then answers requests
+// in order; what services print goes to stderr so it can't corrupt the frames
+const WORKER: &str = r#"
+import json
+import os
+import re
+import struct
+import sys
+
+if os.environ.get('NEXTERM_AUTOWIRE_PATH'):
+    sys.path.insert(0, os.environ['NEXTERM_AUTOWIRE_PATH'])
+frames_in = sys.stdin.buffer
+frames_out = sys.stdout.buffer
+sys.stdout = sys.stderr
+
+_autowire = None
+
+
+def autowire():
+    global _autowire
+    if _autowire is None:
+        from core.autowire import get_autowire
+        _autowire = get_autowire()
+    return _autowire
+
+
+def process_command(params):
+    command = params['command']
+    project_notes = params.get('context') or ''
+    suggestions = []
+    processed_command = command
+
+    if command.startswith('ai '):
+        # AI-assisted command
+        suggestions.append("Using AI agent for processing")
+        processed_command = command[3:]
+    elif 'docker' in command:
+        suggestions.append("Docker agent available")
+    elif 'git' in command:
+        suggestions.append("Git agent available")
+
+    # Project notes: surface lines mentioning words from the command
+    if project_notes:
+        words = [w for w in command.split() if len(w) > 2]
+        for line in project_notes.splitlines():
+            text = line.strip().lstrip('-*# ').strip()
+            if text and any(w in text for w in words):
+                suggestions.append("📝 " + text)
+                if len(suggestions) >= 5:
+                    break
+
+    # Flags the program documents (parsed from its --help / man page): point
+    # out ones it doesn't, up to the first argument (subcommands have their own)
+    flags = params.get('flags')
+    if flags:
+        known = {name for flag in flags['documented'] for name in flag['names']}
+        words = command.split()
+        start = next((i + 1 for i, w in enumerate(words) if w.rsplit('/', 1)[-1] == flags['program']), len(words))
+        for word in words[start:]:
+            if not word.startswith('-') or word == '-' or word == '--':
+                break
+            name = word.split('=', 1)[0]
+            if name in known:
+                continue
+            # Bundled short flags: -la is -l -a
+            if not name.startswith('--') and all('-' + c in known for c in name[1:]):
+                continue
+            suggestions.append("❓ %s isn't a documented %s flag" % (name, flags['program']))
+
+    return {
+        "processed_command": processed_command,
+        "suggestions": suggestions,
+        "agent_used": None,
+        "confidence": 0.8,
+    }
+
+
+# Kept in step with ai::protocol on the Rust side
+PROTOCOL_VERSION = 1
+MIN_PROTOCOL_VERSION = 1
+FEATURES = ['services', 'project_notes', 'flag_hints', 'review', 'script_polish']
+
+
+def handshake(params):
+    # Without the package there are no services, but suggestions still work
+    try:
+        autowire()
+        features = FEATURES
+    except ImportError as e:
+        print(f"auto-wiring package not found ({e}); services disabled", file=sys.stderr)
+        features = [f for f in FEATURES if f != 'services']
+    return {
+        'protocol_version': PROTOCOL_VERSION,
+        'min_protocol_version': MIN_PROTOCOL_VERSION,
+        'features': features,
+    }
+
+
+def list_services(params):
+    return list(autowire().get_registry_info().keys())
+
+
+def service_details(params):
+    name = params['name']
+    info = autowire().get_registry_info().get(name)
+    if info is None:
+        raise KeyError(f"Service '{name}' not found")
+    return json.dumps(info, indent=2)
+
+
+def invoke_service(params):
+    name = params['name']
+    instance = autowire().resolve(name)
+    return f"{name} -> {type(instance).__name__}"
+
+
+def restart_service(params):
+    name = params['name']
+    metadata = autowire()._registry.get(name)
+    if metadata is None:
+        raise KeyError(f"Service '{name}' not found")
+    metadata.instance = None
+    autowire().resolve(name)
+    return f"{name} restarted"
+
+
+HUNK = re.compile(r'^@@ -\d+(?:,\d+)? \+(\d+)')
+SECRET = re.compile(r'(?i)(api[_-]?key|secret|password|passwd|token)\w*["\']?\s*[:=]\s*["\'][^"\']{8,}["\']')
+DEBUG = ('dbg!(', 'console.log(', 'breakpoint()', 'pdb.set_trace(', 'debugger;')
+LONG_LINE = 120
+
+
+def review_line(text):
+    if text.startswith(('<<<<<<<', '>>>>>>>')) or text == '=======':
+        return 'error', "Merge conflict marker"
+    if SECRET.search(text):
+        return 'error', "Looks like a hard-coded secret"
+    if any(d in text for d in DEBUG):
+        return 'warning', "Debugging statement left in"
+    if '.unwrap()' in text:
+        return 'info', "unwrap() panics on error; handle or propagate it"
+    todo = re.search(r'\b(TODO|FIXME|XXX)\b', text)
+    if todo:
+        return 'info', "Unresolved " + todo.group(1)
+    if text != text.rstrip():
+        return 'info', "Trailing whitespace"
+    if len(text) > LONG_LINE:
+        return 'info', f"Line is {len(text)} characters long"
+    return None
+
+
+def review_diff(params):
+    # Added lines only, numbered on the new side from the hunk headers
+    findings = []
+    file, line, in_hunk = None, 0, False
+    for text in params['diff'].splitlines():
+        if text.startswith('diff --git '):
+            file, in_hunk = None, False
+        elif not in_hunk and text.startswith('+++ '):
+            path = text[4:]
+            file = path[2:] if path.startswith('b/') else None if path == '/dev/null' else path
+        elif text.startswith('@@'):
+            match = HUNK.match(text)
+            line, in_hunk = (int(match.group(1)) if match else 1), True
+        elif in_hunk and file and text.startswith('+'):
+            found = review_line(text[1:])
+            if found:
+                findings.append({'file': file, 'line': line, 'severity': found[0], 'message': found[1]})
+            line += 1
+        elif in_hunk and text.startswith(' '):
+            line += 1
+    return findings
+
+
+SHELL_BUILTINS = {'cd', 'echo', 'export', 'set', 'source', '.', ':', 'test', '[', 'true', 'false', 'exit', 'unset', 'printf', 'read'}
+
+
+def polish_script(params):
+    # Fails early, and says where: the tools the script needs are checked up
+    # front and a failing line is reported
+    lines = params['script'].splitlines()
+    tools = []
+    for line in lines:
+        words = line.split()
+        if not words or line.startswith(('#', ':')) or re.match(r'^\w+=\S*$', line):
+            continue
+        words = [w for w in words if not re.match(r'^\w+=', w) and w not in ('sudo', 'env', 'time', 'nohup')]
+        if words and words[0] not in SHELL_BUILTINS and '/' not in words[0] and words[0] not in tools:
+            tools.append(words[0])
+    at = next((i + 1 for i, line in enumerate(lines) if line.startswith('set -')), 1)
+    added = ['trap \'echo "$0: failed at line $LINENO" >&2\' ERR']
+    if tools:
+        added += [
+            'for tool in %s; do' % ' '.join(tools),
+            '    command -v "$tool" >/dev/null || { echo "$0 needs $tool" >&2; exit 1; }',
+            'done',
+        ]
+    return '\n'.join(lines[:at] + added + lines[at:]) + '\n'
+
+
+METHODS = {
+    'handshake': handshake,
+    'ping': lambda params: 'pong',
+    'process_command': process_command,
+    'list_services': list_services,
+    'service_details': service_details,
+    'invoke_service': invoke_service,
+    'restart_service': restart_service,
+    'review_diff': review_diff,
+    'polish_script': polish_script,
+}
+
+
+def read_exact(n):
+    data = b''
+    while len(data) < n:
+        chunk = frames_in.read(n - len(data))
+        if not chunk:
+            return None
+        data += chunk
+    return data
+
+
+while True:
+    header = read_exact(4)
+    if header is None:
+        break
+    body = read_exact(struct.unpack('>I', header)[0])
+    if body is None:
+        break
+    request = json.loads(body)
+    try:
+        method = METHODS[request['method']]
+        reply = {'id': request['id'], 'result': method(request.get('params') or {})}
+    except Exception as e:
+        reply = {'id': request['id'], 'error': f"{type(e).__name__}: {e}"}
+    data = json.dumps(reply).encode()
+    frames_out.write(struct.pack('>I', len(data)) + data)
+    frames_out.flush()
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_round_trip() {
+        let mut stream = Vec::new();
+        write_frame(&mut stream, &json!({ "id": 1, "method": "ping" })).unwrap();
+        write_frame(&mut stream, &json!({ "id": 2, "result": "pong" })).unwrap();
+
+        let mut input = stream.as_slice();
+        assert_eq!(read_frame(&mut input).unwrap().unwrap()["method"], "ping");
+        assert_eq!(read_frame(&mut input).unwrap().unwrap()["result"], "pong");
+        assert!(read_frame(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn finds_the_interpreter_and_package() {
+        let dir = std::env::temp_dir().join(format!("nexterm-worker-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("venv/bin")).unwrap();
+        std::fs::create_dir_all(dir.join("src/core")).unwrap();
+        std::fs::write(dir.join("src/core/autowire.py"), "").unwrap();
+
+        let venv = AutoWireConfig { python: Some(dir.join("venv").display().to_string()), ..Default::default() };
+        assert_eq!(interpreter(&venv), dir.join("venv/bin/python3"));
+        let binary = AutoWireConfig { python: Some("/usr/bin/python3.12".to_string()), ..Default::default() };
+        assert_eq!(interpreter(&binary), PathBuf::from("/usr/bin/python3.12"));
+        assert_eq!(interpreter(&AutoWireConfig::default()), PathBuf::from("python3"));
+
+        let found = find_package([dir.join("missing"), dir.join("venv"), dir.join("src")]);
+        assert_eq!(found, dir.join("src").canonicalize().ok());
+        assert_eq!(find_package([dir.join("venv")]), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_the_latest_log_lines() {
+        let logs = Mutex::new(VecDeque::new());
+        for i in 0..MAX_LOG_LINES + 5 {
+            push_log(&logs, format!("line {}", i));
+        }
+        let logs = logs.into_inner().unwrap();
+        assert_eq!(logs.len(), MAX_LOG_LINES);
+        assert_eq!(logs.front().map(String::as_str), Some("line 5"));
+    }
+
+    #[test]
+    fn restart_delay_doubles_with_bounded_jitter() {
+        let mut rng = Rng::seeded(7);
+        for crashes in 1..=4 {
+            let backoff = RESTART_BACKOFF * 2u32.pow(crashes - 1);
+            let delay = restart_delay(crashes, &mut rng);
+            assert!(delay >= backoff && delay <= backoff * 3 / 2, "{:?} for {} crashes", delay, crashes);
+        }
+        let replay: Vec<Duration> = (1..=4).map(|c| restart_delay(c, &mut Rng::seeded(7))).collect();
+        assert_eq!(replay, (1..=4).map(|c| restart_delay(c, &mut Rng::seeded(7))).collect::<Vec<_>>());
+    }
+}
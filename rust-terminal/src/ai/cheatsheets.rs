@@ -0,0 +1,108 @@
+// Personal per-tool cheatsheets (cheatsheets/<tool>.md) fed to the AI context
+use anyhow::{bail, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::utils::config::Config;
+
+// Wrappers that run another tool; the cheatsheet is for the tool they run
+const WRAPPERS: &[&str] = &["sudo", "env", "time", "nice", "nohup", "exec"];
+
+pub struct Cheatsheet {
+    pub content: String,
+}
+
+impl Cheatsheet {
+    pub fn load(tool: &str) -> Option<Self> {
+        let path = Self::path(tool).ok()?;
+        let content = fs::read_to_string(path).ok()?;
+        Some(Self { content })
+    }
+
+    // The cheatsheet of the tool a command line runs, if there is one
+    pub fn for_command(command: &str) -> Option<Self> {
+        let tool = Path::new(program(command)?).file_name()?.to_string_lossy();
+        Self::load(&tool)
+    }
+
+    // Where `cheat edit <tool>` should write; a new file starts from a template
+    pub fn path_for_edit(tool: &str) -> Result<PathBuf> {
+        let path = Self::path(tool)?;
+        if !path.exists() {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, template(tool))?;
+        }
+        Ok(path)
+    }
+
+    pub fn list() -> Vec<String> {
+        let mut tools: Vec<String> = fs::read_dir(Self::dir())
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter_map(|e| {
+                        let path = e.path();
+                        if path.extension()? != "md" {
+                            return None;
+                        }
+                        Some(path.file_stem()?.to_string_lossy().into_owned())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        tools.sort();
+        tools
+    }
+
+    // The example command lines: those written as `code` on a line of their own
+    pub fn examples(&self) -> Vec<String> {
+        self.content
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix('`')?.strip_suffix('`'))
+            .filter(|example| !example.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn dir() -> PathBuf {
+        Config::dir().join("cheatsheets")
+    }
+
+    fn path(tool: &str) -> Result<PathBuf> {
+        if tool.is_empty() || tool.contains('/') || tool.starts_with('.') {
+            bail!("Invalid tool name: {}", tool);
+        }
+        Ok(Self::dir().join(format!("{}.md", tool)))
+    }
+}
+
+// The program a command line runs, past wrappers and VAR=value assignments
+pub fn program(command: &str) -> Option<&str> {
+    program_in(command.split_whitespace())
+}
+
+// The same for a command already split into words
+pub fn program_in<'a>(words: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    words.into_iter().find(|w| !WRAPPERS.contains(w) && !w.contains('='))
+}
+
+// Project notes plus the cheatsheet of the tool being run, as one AI context
+pub fn with_cheatsheet(command: &str, project_notes: Option<&str>) -> Option<String> {
+    let cheat = Cheatsheet::for_command(command);
+    match (project_notes, cheat) {
+        (Some(notes), Some(cheat)) => Some(format!("{}\n\n{}", notes, cheat.content)),
+        (Some(notes), None) => Some(notes.to_string()),
+        (None, Some(cheat)) => Some(cheat.content),
+        (None, None) => None,
+    }
+}
+
+fn template(tool: &str) -> String {
+    format!(
+        "# {tool}\n\n\
+         > What {tool} is for, in one line.\n\n\
+         - Description of the example:\n\n  `{tool} --flag <arg>`\n"
+    )
+}
@@ -0,0 +1,343 @@
+// Community completion specs in Fig's JSON shape: a command's subcommands,
+// options and argument suggestions, installed per tool with `completions
+// install <tool>` from a URL, a file or the configured registry. Where one is
+// installed it takes over Tab completion for that tool from --help parsing.
+//
+//   { "name": "git", "subcommands": [{ "name": ["checkout", "co"],
+//     "options": [{ "name": ["-b"], "description": "...", "args": {} }],
+//     "args": { "suggestions": ["main", { "name": "dev" }] } }] }
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::ai::suggestions::{Completion, CompletionKind};
+use crate::core::operations::Progress;
+use crate::utils::{clock, durable, storage};
+
+const INDEX_FILE: &str = "installed.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompletionsConfig {
+    // URL of one spec, `{tool}` replaced by the tool's name; `completions
+    // install` needs an explicit source while unset
+    #[serde(default)]
+    pub registry: Option<String>,
+}
+
+// Fig allows a single value or a list in most places
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        match self {
+            OneOrMany::One(value) => std::slice::from_ref(value).iter(),
+            OneOrMany::Many(values) => values.iter(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.iter().len() == 0
+    }
+}
+
+impl<T> Default for OneOrMany<T> {
+    fn default() -> Self {
+        OneOrMany::Many(Vec::new())
+    }
+}
+
+// The root command of a spec is a subcommand too
+#[derive(Debug, Clone, Deserialize)]
+pub struct Subcommand {
+    pub name: OneOrMany<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub subcommands: Vec<Subcommand>,
+    #[serde(default)]
+    pub options: Vec<SpecOption>,
+    #[serde(default)]
+    pub args: OneOrMany<SpecArg>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpecOption {
+    pub name: OneOrMany<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    // Also valid after the subcommands below the one declaring it
+    #[serde(default, rename = "isPersistent")]
+    pub persistent: bool,
+    #[serde(default)]
+    pub args: OneOrMany<SpecArg>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SpecArg {
+    #[serde(default)]
+    pub suggestions: Vec<Suggestion>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Suggestion {
+    Text(String),
+    Item {
+        name: OneOrMany<String>,
+        #[serde(default)]
+        description: Option<String>,
+    },
+}
+
+impl Subcommand {
+    // Completions for `current`, the word being typed after the words before
+    // it (without the program name)
+    pub fn complete(&self, words: &[&str], current: &str) -> Vec<Completion> {
+        let mut command = self;
+        let mut persistent: Vec<&SpecOption> = self.options.iter().filter(|o| o.persistent).collect();
+        // Option whose value is the next word
+        let mut pending: Option<&SpecOption> = None;
+        for word in words {
+            if pending.take().is_some() {
+                continue;
+            }
+            if word.starts_with('-') {
+                let name = word.split('=').next().unwrap_or(word);
+                let option = command.options.iter().chain(persistent.iter().copied()).find(|o| o.has_name(name));
+                pending = option.filter(|o| !o.args.is_empty() && !word.contains('='));
+            } else if let Some(sub) = command.subcommands.iter().find(|s| s.name.iter().any(|n| n == word)) {
+                command = sub;
+                persistent.extend(sub.options.iter().filter(|o| o.persistent));
+            }
+        }
+
+        let mut completions = Vec::new();
+        if let Some(option) = pending {
+            suggest(&option.args, current, &mut completions);
+        } else if current.starts_with('-') {
+            for option in command.options.iter().chain(persistent) {
+                for name in option.name.iter().filter(|n| n.starts_with(current)) {
+                    if completions.iter().all(|c: &Completion| &c.text != name) {
+                        completions.push(Completion {
+                            text: name.clone(),
+                            description: option.description.clone().unwrap_or_default(),
+                            kind: CompletionKind::Flag,
+                        });
+                    }
+                }
+            }
+        } else {
+            for sub in &command.subcommands {
+                if let Some(name) = sub.name.iter().find(|n| n.starts_with(current)) {
+                    completions.push(Completion {
+                        text: name.clone(),
+                        description: sub.description.clone().unwrap_or_default(),
+                        kind: CompletionKind::Argument,
+                    });
+                }
+            }
+            suggest(&command.args, current, &mut completions);
+        }
+        completions
+    }
+}
+
+impl SpecOption {
+    fn has_name(&self, name: &str) -> bool {
+        self.name.iter().any(|n| n == name)
+    }
+}
+
+fn suggest(args: &OneOrMany<SpecArg>, current: &str, completions: &mut Vec<Completion>) {
+    for suggestion in args.iter().flat_map(|arg| &arg.suggestions) {
+        let (names, description) = match suggestion {
+            Suggestion::Text(text) => (std::slice::from_ref(text).iter(), None),
+            Suggestion::Item { name, description } => (name.iter(), description.as_ref()),
+        };
+        for name in names.filter(|n| n.starts_with(current)) {
+            completions.push(Completion {
+                text: name.clone(),
+                description: description.cloned().unwrap_or_default(),
+                kind: CompletionKind::Argument,
+            });
+        }
+    }
+}
+
+// Where an installed spec came from, for `completions update`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledSpec {
+    pub source: String,
+    // Unix seconds of the last install or change
+    pub updated: u64,
+}
+
+// Installed specs, parsed on first use and again when their file changes
+pub struct CommunitySpecs {
+    dir: PathBuf,
+    loaded: HashMap<String, (SystemTime, Subcommand)>,
+}
+
+impl CommunitySpecs {
+    pub fn new() -> Self {
+        Self {
+            dir: dir(),
+            loaded: HashMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, tool: &str) -> Option<&Subcommand> {
+        check_name(tool).ok()?;
+        let path = self.dir.join(format!("{}.json", tool));
+        let modified = path.metadata().and_then(|m| m.modified()).ok()?;
+        if self.loaded.get(tool).is_none_or(|(at, _)| *at != modified) {
+            let spec = durable::load_json(&path)?;
+            self.loaded.insert(tool.to_string(), (modified, spec));
+        }
+        self.loaded.get(tool).map(|(_, spec)| spec)
+    }
+}
+
+impl Default for CommunitySpecs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct SpecRegistry {
+    dir: PathBuf,
+    registry: Option<String>,
+}
+
+impl SpecRegistry {
+    pub fn new(config: &CompletionsConfig) -> Self {
+        Self {
+            dir: dir(),
+            registry: config.registry.clone(),
+        }
+    }
+
+    // Tool -> where its spec came from
+    pub fn list(&self) -> BTreeMap<String, InstalledSpec> {
+        durable::load_json(&self.dir.join(INDEX_FILE)).unwrap_or_default()
+    }
+
+    // `source` is a URL or a file; the registry's URL for `tool` without one
+    pub async fn install(&self, tool: &str, source: Option<&str>) -> Result<()> {
+        check_name(tool)?;
+        let source = match (source, &self.registry) {
+            (Some(source), _) => source.to_string(),
+            (None, Some(registry)) => registry.replace("{tool}", tool),
+            (None, None) => bail!("No source for {}: pass a URL or file, or set [completions] registry", tool),
+        };
+        let spec = fetch(&source).await?;
+        self.write(tool, &source, &spec)
+    }
+
+    // Re-fetches `tool`, or every installed spec, a step of `progress` each;
+    // returns the ones that changed
+    pub async fn update(&self, tool: Option<&str>, progress: &Progress) -> Result<Vec<String>> {
+        let installed = self.list();
+        if let Some(tool) = tool.filter(|t| !installed.contains_key(*t)) {
+            bail!("No completion spec installed for {}", tool);
+        }
+        let mut changed = Vec::new();
+        let updating: Vec<_> = installed.iter().filter(|(name, _)| tool.is_none_or(|t| t == name.as_str())).collect();
+        progress.set_total(updating.len() as u64);
+        for (name, entry) in updating {
+            progress.advance(name.as_str());
+            let spec = fetch(&entry.source).await.with_context(|| format!("Failed to update {}", name))?;
+            let current = std::fs::read_to_string(self.dir.join(format!("{}.json", name))).unwrap_or_default();
+            if spec != current {
+                self.write(name, &entry.source, &spec)?;
+                changed.push(name.clone());
+            }
+        }
+        Ok(changed)
+    }
+
+    pub fn remove(&self, tool: &str) -> Result<()> {
+        let mut installed = self.list();
+        if installed.remove(tool).is_none() {
+            bail!("No completion spec installed for {}", tool);
+        }
+        std::fs::remove_file(self.dir.join(format!("{}.json", tool)))?;
+        self.write_index(&installed)
+    }
+
+    fn write(&self, tool: &str, source: &str, spec: &str) -> Result<()> {
+        durable::write_atomic(&self.dir.join(format!("{}.json", tool)), spec.as_bytes())?;
+        let mut installed = self.list();
+        let updated = clock::unix_secs();
+        installed.insert(tool.to_string(), InstalledSpec { source: source.to_string(), updated });
+        self.write_index(&installed)
+    }
+
+    fn write_index(&self, installed: &BTreeMap<String, InstalledSpec>) -> Result<()> {
+        durable::write_atomic(&self.dir.join(INDEX_FILE), serde_json::to_string_pretty(installed)?.as_bytes())
+    }
+}
+
+fn dir() -> PathBuf {
+    storage::data_dir().join("completions").join("community")
+}
+
+fn check_name(tool: &str) -> Result<()> {
+    if tool.is_empty() || tool.contains('/') || tool.starts_with('.') || tool == "installed" {
+        bail!("Invalid tool name: {}", tool);
+    }
+    Ok(())
+}
+
+// The spec's text, refused unless it parses as one
+async fn fetch(source: &str) -> Result<String> {
+    let text = if source.starts_with("https://") || source.starts_with("http://") {
+        reqwest::get(source).await?.error_for_status()?.text().await?
+    } else {
+        let path = shellexpand::tilde(source).into_owned();
+        tokio::fs::read_to_string(&path).await.with_context(|| format!("Failed to read {}", path))?
+    };
+    serde_json::from_str::<Subcommand>(&text).with_context(|| format!("{} is not a completion spec", source))?;
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(completions: Vec<Completion>) -> Vec<String> {
+        completions.into_iter().map(|c| c.text).collect()
+    }
+
+    #[test]
+    fn walks_subcommands_options_and_args() {
+        let spec: Subcommand = serde_json::from_str(
+            r#"{
+                "name": "git",
+                "options": [{ "name": "--no-pager", "isPersistent": true }],
+                "subcommands": [
+                    { "name": ["checkout", "co"], "description": "Switch branches",
+                      "options": [{ "name": ["-b"], "args": { "name": "branch" } }],
+                      "args": { "suggestions": ["main", { "name": "dev", "description": "Dev branch" }] } },
+                    { "name": "commit", "options": [{ "name": ["-m", "--message"], "args": {} }] }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(texts(spec.complete(&[], "c")), vec!["checkout", "commit"]);
+        assert_eq!(texts(spec.complete(&["co"], "")), vec!["main", "dev"]);
+        assert_eq!(texts(spec.complete(&["checkout"], "-")), vec!["-b", "--no-pager"]);
+        assert_eq!(texts(spec.complete(&["commit"], "--m")), vec!["--message"]);
+        // The value of -b, then a positional argument again
+        assert!(spec.complete(&["checkout", "-b"], "").is_empty());
+        assert_eq!(texts(spec.complete(&["checkout", "-b", "x"], "d")), vec!["dev"]);
+    }
+}
@@ -0,0 +1,361 @@
+// Per-binary completion specs: the flags a program documents, with their
+// descriptions, parsed from its --help output or, when that says little, its
+// man page. Specs are cached as JSON under the data directory and re-parsed
+// when the binary's mtime changes. They feed the completion dropdown,
+// teaching-mode explanations and the AI context.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::ai::cheatsheets;
+use crate::core::preflight;
+use crate::utils::{durable, storage};
+
+// Killed after this: programs that ignore --help and wait on a tty or the network
+const HELP_TIMEOUT: Duration = Duration::from_secs(2);
+// --help output documenting fewer flags than this gets the man page tried too
+const MIN_HELP_FLAGS: usize = 3;
+// Programs whose --help prints help and does nothing else, for lookups the
+// user didn't ask for (teaching mode)
+const ANSWERS_HELP: &[&str] = &[
+    "ls", "cp", "mv", "rm", "mkdir", "rmdir", "ln", "chmod", "chown", "touch", "cat", "head", "tail", "grep", "sed",
+    "sort", "uniq", "wc", "cut", "tr", "find", "xargs", "du", "df", "tar", "gzip", "zip", "curl", "wget", "rsync",
+    "diff", "date", "env", "ps", "git", "cargo", "rustc", "rustup", "docker", "kubectl", "helm", "npm", "npx", "node",
+    "python3", "pip", "pip3", "make", "go", "terraform", "gh",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlagSpec {
+    // Spellings of the same flag: `-a`, `--all`
+    pub names: Vec<String>,
+    // Placeholder for its value, like FILE or [=WHEN]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arg: Option<String>,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionSpec {
+    pub binary: PathBuf,
+    // The binary's mtime when it was parsed, in Unix seconds
+    pub mtime: u64,
+    pub flags: Vec<FlagSpec>,
+}
+
+pub struct SpecCache {
+    dir: PathBuf,
+    // Keyed by binary file name
+    specs: HashMap<String, CompletionSpec>,
+}
+
+impl SpecCache {
+    pub fn new() -> Self {
+        Self {
+            dir: storage::data_dir().join("completions"),
+            specs: HashMap::new(),
+        }
+    }
+
+    // Spec of `program` (a name on PATH or a path), parsed now if there's none
+    // for this build of it. Runs `program --help`, so only for programs the
+    // user is completing or has already run.
+    pub fn get(&mut self, program: &str) -> Option<&CompletionSpec> {
+        let (binary, mtime, name) = locate(program)?;
+        if !self.specs.get(&name).is_some_and(|s| s.binary == binary && s.mtime == mtime) {
+            let path = self.dir.join(format!("{}.json", name));
+            let spec = match self.cached(program) {
+                Some(spec) => spec,
+                None => {
+                    let spec = CompletionSpec { flags: extract(&binary, &name), binary, mtime };
+                    let written = serde_json::to_vec(&spec)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|json| durable::write_atomic(&path, &json));
+                    if let Err(e) = written {
+                        warn!("Failed to cache flags of {}: {}", name, e);
+                    }
+                    spec
+                }
+            };
+            self.specs.insert(name.clone(), spec);
+        }
+        self.specs.get(&name)
+    }
+
+    // Spec of `program` from the cache, never running it; None when the binary
+    // changed since it was parsed
+    pub fn cached(&self, program: &str) -> Option<CompletionSpec> {
+        let (binary, mtime, name) = locate(program)?;
+        let fresh = |s: &CompletionSpec| s.binary == binary && s.mtime == mtime;
+        match self.specs.get(&name) {
+            Some(spec) if fresh(spec) => Some(spec.clone()),
+            _ => durable::load_json(&self.dir.join(format!("{}.json", name))).filter(fresh),
+        }
+    }
+}
+
+impl Default for SpecCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Documented flags of the program `command` runs, for the AI request; only
+// from specs already cached
+pub fn for_command(command: &str) -> Option<(String, CompletionSpec)> {
+    let program = cheatsheets::program(command)?;
+    let spec = SpecCache::new().cached(program)?;
+    let name = Path::new(program).file_name()?.to_string_lossy().into_owned();
+    Some((name, spec))
+}
+
+pub fn answers_help(program: &str) -> bool {
+    ANSWERS_HELP.contains(&program)
+}
+
+// Binary path, mtime and file name
+fn locate(program: &str) -> Option<(PathBuf, u64, String)> {
+    let binary = if program.contains('/') {
+        PathBuf::from(shellexpand::tilde(program).into_owned())
+    } else {
+        preflight::find_in_path(program)?
+    };
+    let mtime = binary.metadata().ok()?.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let name = binary.file_name()?.to_string_lossy().into_owned();
+    Some((binary, mtime, name))
+}
+
+fn extract(binary: &Path, name: &str) -> Vec<FlagSpec> {
+    let mut flags = run(Command::new(binary).arg("--help")).map(|help| parse(&help)).unwrap_or_default();
+    if flags.len() < MIN_HELP_FLAGS {
+        let mut man = Command::new("man");
+        man.arg(name).env("MANPAGER", "cat").env("PAGER", "cat").env("MANWIDTH", "120");
+        if let Some(page) = run(&mut man) {
+            let documented = parse(&strip_overstrike(&page));
+            if documented.len() > flags.len() {
+                flags = documented;
+            }
+        }
+    }
+    flags
+}
+
+// Stdout, or stderr for tools that print usage there; None after HELP_TIMEOUT
+fn run(command: &mut Command) -> Option<String> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    let mut stdout = child.stdout.take()?;
+    let mut stderr = child.stderr.take()?;
+    let out = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stdout.read_to_string(&mut text);
+        text
+    });
+    let err = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+    let deadline = Instant::now() + HELP_TIMEOUT;
+    while child.try_wait().ok()?.is_none() {
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let (out, err) = (out.join().ok()?, err.join().ok()?);
+    Some(if out.trim().is_empty() { err } else { out })
+}
+
+// Formatted man pages bold and underline with `c\bc` and `_\bc`
+fn strip_overstrike(page: &str) -> String {
+    let mut text = String::with_capacity(page.len());
+    for c in page.chars() {
+        if c == '\u{8}' {
+            text.pop();
+        } else {
+            text.push(c);
+        }
+    }
+    text
+}
+
+// Flag lines of --help output or a man page:
+//   -a, --all                  do not ignore entries starting with .
+//       --color[=WHEN]         colorize the output
+//   -o FILE   write to FILE
+// and the man page layout with the description on the lines below:
+//   -a, --all
+//          do not ignore entries starting with .
+// The first description of a flag wins; later mentions are examples. Flags
+// only named in a BSD-style synopsis (`usage: ssh [-46Aa] [-B bind_interface]`)
+// come last, without descriptions.
+pub fn parse(text: &str) -> Vec<FlagSpec> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut flags: Vec<FlagSpec> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if !trimmed.starts_with('-') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        let (head, description) = split_columns(trimmed);
+        let Some((names, arg)) = parse_head(head) else {
+            continue;
+        };
+        let description = match description {
+            "" => lines
+                .get(i + 1)
+                .filter(|next| {
+                    let rest = next.trim_start();
+                    !rest.is_empty() && !rest.starts_with('-') && next.len() - rest.len() > indent
+                })
+                .map(|next| next.trim().to_string())
+                .unwrap_or_default(),
+            description => description.to_string(),
+        };
+        if names.iter().any(|n| flags.iter().any(|f| f.names.contains(n))) {
+            continue;
+        }
+        flags.push(FlagSpec { names, arg, description });
+    }
+    for flag in synopsis(&lines) {
+        if flags.iter().all(|f| !f.names.contains(&flag.names[0])) {
+            flags.push(flag);
+        }
+    }
+    flags
+}
+
+// `[-abc]` and `[-o value]` groups of the `usage:` paragraph
+fn synopsis(lines: &[&str]) -> Vec<FlagSpec> {
+    let Some(start) = lines.iter().position(|l| l.trim_start().to_lowercase().starts_with("usage:")) else {
+        return Vec::new();
+    };
+    let usage = lines[start..].iter().take_while(|l| !l.trim().is_empty()).copied().collect::<Vec<_>>().join(" ");
+    let mut flags = Vec::new();
+    for group in usage.split('[').skip(1).filter_map(|g| g.split(']').next()) {
+        let mut words = group.split_whitespace();
+        let Some(bare) = words.next().and_then(|w| w.strip_prefix('-')) else {
+            continue;
+        };
+        if bare.is_empty() || bare.starts_with('-') || !bare.chars().all(|c| c.is_ascii_alphanumeric()) {
+            continue;
+        }
+        match words.next() {
+            // -o value
+            Some(value) if bare.len() == 1 => flags.push(FlagSpec {
+                names: vec![format!("-{}", bare)],
+                arg: Some(value.to_string()),
+                description: String::new(),
+            }),
+            Some(_) => {}
+            // -46Aa: one flag per letter
+            None => flags.extend(bare.chars().map(|c| FlagSpec {
+                names: vec![format!("-{}", c)],
+                arg: None,
+                description: String::new(),
+            })),
+        }
+    }
+    flags
+}
+
+// A tab or two spaces separate the flags from their description
+fn split_columns(line: &str) -> (&str, &str) {
+    match line.find("  ").into_iter().chain(line.find('\t')).min() {
+        Some(at) => (&line[..at], line[at..].trim()),
+        None => (line, ""),
+    }
+}
+
+// "-a, --all" / "--color[=WHEN]" / "-o FILE" into names and value placeholder;
+// None for prose that happens to start with a dash
+fn parse_head(head: &str) -> Option<(Vec<String>, Option<String>)> {
+    let mut names = Vec::new();
+    let mut arg = None;
+    for token in head.split([',', ' ']).filter(|t| !t.is_empty()) {
+        if token.starts_with('-') {
+            let (name, value) = match token.find(['=', '[', '<']) {
+                Some(at) => (&token[..at], Some(token[at..].trim_start_matches('='))),
+                None => (token, None),
+            };
+            let bare = name.trim_start_matches('-');
+            let valid = bare.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if bare.is_empty() || name.len() - bare.len() > 2 || !valid {
+                return None;
+            }
+            names.push(name.to_string());
+            if let Some(value) = value.filter(|v| !v.is_empty()) {
+                arg.get_or_insert_with(|| value.to_string());
+            }
+        } else if !names.is_empty() && arg.is_none() && is_placeholder(token) {
+            arg = Some(token.to_string());
+        } else {
+            return None;
+        }
+    }
+    (!names.is_empty()).then_some((names, arg))
+}
+
+fn is_placeholder(token: &str) -> bool {
+    token.starts_with(['<', '[', '{'])
+        || (token.chars().any(|c| c.is_ascii_uppercase())
+            && token.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_' || c == '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_help_and_man_layouts() {
+        let help = "\
+Usage: ls [OPTION]... [FILE]...
+  -a, --all                  do not ignore entries starting with .
+      --color[=WHEN]         color the output WHEN
+  -o FILE                    write to FILE
+  -w, --width=COLS           set output width to COLS
+  - dashes in prose don't count
+";
+        let flags = parse(help);
+        assert_eq!(flags.len(), 4);
+        assert_eq!(flags[0].names, vec!["-a", "--all"]);
+        assert_eq!(flags[0].description, "do not ignore entries starting with .");
+        assert_eq!(flags[1].arg.as_deref(), Some("[=WHEN]"));
+        assert_eq!(flags[2].arg.as_deref(), Some("FILE"));
+        assert_eq!(flags[3].arg.as_deref(), Some("COLS"));
+
+        let man = "\
+       -a, --all
+              do not ignore entries starting with .
+
+       -l     use a long listing format
+
+       Using -l again as an example.
+";
+        let flags = parse(&strip_overstrike(man));
+        assert_eq!(flags.len(), 2);
+        assert_eq!(flags[0].description, "do not ignore entries starting with .");
+        assert_eq!(flags[1].names, vec!["-l"]);
+        assert_eq!(strip_overstrike("N\u{8}NA\u{8}AM\u{8}ME\u{8}E"), "NAME");
+
+        let usage = "\
+usage: ssh [-46A] [-B bind_interface]
+           [-J [user@]host[:port]] destination [command [argument ...]]
+";
+        let flags = parse(usage);
+        let names: Vec<&str> = flags.iter().map(|f| f.names[0].as_str()).collect();
+        assert_eq!(names, vec!["-4", "-6", "-A", "-B", "-J"]);
+        assert_eq!(flags[3].arg.as_deref(), Some("bind_interface"));
+    }
+}
@@ -0,0 +1,231 @@
+// `ai summarize`: a markdown digest of recent history (projects touched,
+// notable failures, time spent per repository). Commands pass through the
+// terminal's redaction first, then the same secret masking as bug reports
+// (`NAME=value` assignments, URL credentials, auth headers).
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::utils::calendar;
+use crate::utils::history::HistoryItem;
+use crate::utils::report;
+
+// A pause longer than this between commands is time away, not time spent
+const IDLE_GAP: u64 = 15 * 60;
+const FAILURES_LISTED: usize = 5;
+const TOP_PER_PROJECT: usize = 3;
+// Interrupted with Ctrl+C rather than failed
+const INTERRUPTED: i32 = 130;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Project {
+    pub root: Option<PathBuf>,
+    pub commands: usize,
+    pub failed: usize,
+    pub active: Duration,
+    // Most run commands, most first
+    pub top: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub command: String,
+    pub exit_code: i32,
+    pub count: usize,
+    pub project: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Digest {
+    pub from: u64,
+    pub to: u64,
+    pub commands: usize,
+    // Most time spent first
+    pub projects: Vec<Project>,
+    // Most frequent first
+    pub failures: Vec<Failure>,
+}
+
+// "7d", "12h", "30m" or "2w"
+pub fn parse_since(text: &str) -> Option<Duration> {
+    let (split, _) = text.char_indices().last()?;
+    let (count, unit) = text.split_at(split);
+    let count: u64 = count.parse().ok()?;
+    let unit = match unit {
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => return None,
+    };
+    Some(Duration::from_secs(count.checked_mul(unit)?))
+}
+
+impl Digest {
+    // `items` oldest first, as History::since gives them; `redact` is the
+    // terminal's, applied to every command shown
+    pub fn build(items: &[HistoryItem], from: u64, to: u64, redact: impl Fn(&str) -> String) -> Self {
+        let mut projects: BTreeMap<Option<PathBuf>, (Project, BTreeMap<String, usize>)> = BTreeMap::new();
+        let mut failures: BTreeMap<(String, i32), Failure> = BTreeMap::new();
+
+        for (i, item) in items.iter().enumerate() {
+            let root = item.cwd.as_deref().map(project_root);
+            let command = report::redact_line(&redact(&item.command));
+            let (project, counts) = projects.entry(root.clone()).or_insert_with(|| {
+                (Project { root: root.clone(), commands: 0, failed: 0, active: Duration::ZERO, top: Vec::new() }, BTreeMap::new())
+            });
+            project.commands += 1;
+            *counts.entry(command.clone()).or_insert(0) += 1;
+            // Until the next command, if the user didn't step away in between
+            if let Some(next) = items.get(i + 1) {
+                let gap = next.timestamp.saturating_sub(item.timestamp);
+                project.active += Duration::from_secs(gap.min(IDLE_GAP));
+            }
+
+            match item.exit_code {
+                Some(code) if code != 0 && code != INTERRUPTED => {
+                    project.failed += 1;
+                    failures
+                        .entry((command.clone(), code))
+                        .or_insert_with(|| Failure { command, exit_code: code, count: 0, project: root })
+                        .count += 1;
+                }
+                _ => {}
+            }
+        }
+
+        let mut projects: Vec<Project> = projects
+            .into_values()
+            .map(|(mut project, counts)| {
+                let mut top: Vec<(String, usize)> = counts.into_iter().collect();
+                top.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                top.truncate(TOP_PER_PROJECT);
+                project.top = top;
+                project
+            })
+            .collect();
+        projects.sort_by(|a, b| b.active.cmp(&a.active).then(b.commands.cmp(&a.commands)));
+        let mut failures: Vec<Failure> = failures.into_values().collect();
+        failures.sort_by(|a, b| b.count.cmp(&a.count).then(a.command.cmp(&b.command)));
+        failures.truncate(FAILURES_LISTED);
+
+        Self { from, to, commands: items.len(), projects, failures }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let from = calendar::format_date(calendar::local_day(self.from));
+        let to = calendar::format_date(calendar::local_day(self.to));
+        let mut lines = vec![format!("# What I worked on, {} to {}", from, to), String::new()];
+        if self.commands == 0 {
+            lines.push("No commands recorded.".to_string());
+            return lines.join("\n");
+        }
+        let failed: usize = self.projects.iter().map(|p| p.failed).sum();
+        lines.push(format!("{} commands in {} projects, {} failed.", self.commands, self.projects.len(), failed));
+
+        lines.extend(["".to_string(), "## Projects".to_string(), String::new()]);
+        lines.push("| Project | Commands | Failed | Time spent |".to_string());
+        lines.push("|---|---:|---:|---:|".to_string());
+        for project in &self.projects {
+            lines.push(format!(
+                "| {} | {} | {} | {} |",
+                project_name(project.root.as_deref()),
+                project.commands,
+                project.failed,
+                format_duration(project.active)
+            ));
+        }
+
+        if !self.failures.is_empty() {
+            lines.extend(["".to_string(), "## Notable failures".to_string(), String::new()]);
+            for failure in &self.failures {
+                lines.push(format!(
+                    "- `{}` in {}: failed {}× (exit {})",
+                    failure.command,
+                    project_name(failure.project.as_deref()),
+                    failure.count,
+                    failure.exit_code
+                ));
+            }
+        }
+
+        lines.extend(["".to_string(), "## Most run".to_string(), String::new()]);
+        for project in &self.projects {
+            let top: Vec<String> = project.top.iter().map(|(command, n)| format!("`{}` ({})", command, n)).collect();
+            lines.push(format!("- {}: {}", project_name(project.root.as_deref()), top.join(", ")));
+        }
+        lines.push(String::new());
+        lines.push(format!("_Time spent counts the gaps between commands, up to {} minutes each._", IDLE_GAP / 60));
+        lines.join("\n")
+    }
+}
+
+// The repository a directory is in, or the directory itself
+fn project_root(dir: &Path) -> PathBuf {
+    dir.ancestors().find(|d| d.join(".git").exists()).unwrap_or(dir).to_path_buf()
+}
+
+fn project_name(root: Option<&Path>) -> String {
+    let Some(root) = root else {
+        return "(unknown directory)".to_string();
+    };
+    let home = std::env::var("HOME").ok().filter(|h| !h.is_empty());
+    match home.and_then(|home| root.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) => format!("~/{}", rest.display()),
+        None => root.display().to_string(),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, m) => format!("{}h {:02}m", h, m),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(command: &str, timestamp: u64, cwd: &str, exit_code: i32) -> HistoryItem {
+        HistoryItem { command: command.to_string(), timestamp, cwd: Some(PathBuf::from(cwd)), exit_code: Some(exit_code), runs: 1 }
+    }
+
+    #[test]
+    fn summarizes_projects_failures_and_time() {
+        assert_eq!(parse_since("7d"), Some(Duration::from_secs(7 * 86_400)));
+        assert_eq!(parse_since("30m"), Some(Duration::from_secs(1800)));
+        assert_eq!(parse_since("7"), None);
+        assert_eq!(parse_since("d"), None);
+        assert_eq!(parse_since("5é"), None);
+        assert_eq!(parse_since(""), None);
+        assert_eq!(parse_since(&format!("{}w", u64::MAX)), None);
+
+        let items = [
+            item("cargo test", 1_000, "/nowhere/a", 101),
+            item("cargo test", 1_300, "/nowhere/a", 101),
+            item("API_TOKEN=abc123 deploy", 1_600, "/nowhere/a", 0),
+            // Back after lunch: only 15 minutes count
+            item("ls", 9_000, "/nowhere/b", 0),
+            item("vim", 9_060, "/nowhere/b", INTERRUPTED),
+        ];
+        let digest = Digest::build(&items, 0, 9_060, |c| c.replace("deploy", "<env:DEPLOY>"));
+
+        assert_eq!(digest.commands, 5);
+        let a = &digest.projects[0];
+        assert_eq!((a.root.as_deref(), a.commands, a.failed), (Some(Path::new("/nowhere/a")), 3, 2));
+        assert_eq!(a.active, Duration::from_secs(300 + 300 + IDLE_GAP));
+        assert_eq!(digest.projects[1].active, Duration::from_secs(60));
+        assert_eq!(digest.projects[1].failed, 0);
+
+        assert_eq!(digest.failures.len(), 1);
+        assert_eq!((digest.failures[0].command.as_str(), digest.failures[0].count), ("cargo test", 2));
+
+        let markdown = digest.to_markdown();
+        assert!(markdown.contains("| /nowhere/a | 3 | 2 | 25m |"), "{}", markdown);
+        assert!(markdown.contains("- `cargo test` in /nowhere/a: failed 2× (exit 101)"));
+        assert!(markdown.contains("`API_TOKEN=<redacted> <env:DEPLOY>` (1)"));
+        assert!(!markdown.contains("abc123"));
+    }
+}
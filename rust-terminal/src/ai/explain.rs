@@ -0,0 +1,115 @@
+// Teaching mode: short explanations of executed commands with doc links.
+// Looking a program up runs tldr or whatis and, for programs known to answer
+// `--help` with just their help, `<program> --help`; that happens off the
+// UI thread, once per program.
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use crate::ai::completion_specs::{self, FlagSpec, SpecCache};
+use crate::utils::startup::Pending;
+
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub summary: String,
+    pub flags: Vec<(String, String)>,
+    pub links: Vec<String>,
+}
+
+impl Explanation {
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("📘 {}", self.summary)];
+        lines.extend(self.flags.iter().map(|(flag, desc)| format!("📘   {:<14} {}", flag, desc)));
+        lines.extend(self.links.iter().map(|link| format!("📘   ↗ {}", link)));
+        lines
+    }
+}
+
+// What teaching mode found out about a program
+#[derive(Clone)]
+struct Program {
+    summary: String,
+    flags: Vec<FlagSpec>,
+}
+
+#[derive(Clone, Default)]
+pub struct Explainer {
+    // Keyed by program name
+    programs: Arc<Mutex<HashMap<String, Program>>>,
+}
+
+impl Explainer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn explain(&self, command: &str) -> Pending<Option<Explanation>> {
+        let (explainer, command) = (self.clone(), command.to_string());
+        Pending::spawn("Teaching mode", move || explainer.explain_now(&command))
+    }
+
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
+    fn explain_now(&self, command: &str) -> Option<Explanation> {
+        let mut words = command.split_whitespace();
+        let program = words.next()?.to_string();
+        let known = self.program(&program);
+        let flags = words
+            .filter(|w| w.starts_with('-'))
+            .filter_map(|w| {
+                let flag = w.split('=').next().unwrap_or(w);
+                known.flags.iter()
+                    .find(|spec| spec.names.iter().any(|name| name == flag))
+                    .map(|spec| (flag.to_string(), spec.description.clone()))
+            })
+            .collect();
+
+        Some(Explanation {
+            summary: known.summary,
+            flags,
+            links: vec![
+                format!("https://tldr.inbrowser.app/pages/common/{}", program),
+                format!("https://man7.org/linux/man-pages/man1/{}.1.html", program),
+            ],
+        })
+    }
+
+    fn program(&self, name: &str) -> Program {
+        if let Some(known) = self.programs.lock().ok().and_then(|programs| programs.get(name).cloned()) {
+            return known;
+        }
+        // Anything else only gets the flags completion already parsed
+        let mut specs = SpecCache::new();
+        let flags = match completion_specs::answers_help(name) {
+            true => specs.get(name).map(|spec| spec.flags.clone()),
+            false => specs.cached(name).map(|spec| spec.flags),
+        };
+        let known = Program { summary: lookup_summary(name), flags: flags.unwrap_or_default() };
+        if let Ok(mut programs) = self.programs.lock() {
+            programs.insert(name.to_string(), known.clone());
+        }
+        known
+    }
+}
+
+// First description line from tldr, falling back to whatis
+fn lookup_summary(program: &str) -> String {
+    let tldr = run(&["tldr", "--raw", program]).and_then(|page| {
+        page.lines()
+            .find(|l| l.starts_with("> ") && !l.contains("More information"))
+            .map(|l| l.trim_start_matches("> ").trim().to_string())
+    });
+
+    tldr.or_else(|| {
+        run(&["whatis", program])
+            .and_then(|out| out.lines().next().map(|l| l.split(" - ").last().unwrap_or(l).trim().to_string()))
+    })
+    .map(|desc| format!("{}: {}", program, desc))
+    .unwrap_or_else(|| format!("{}: no description available", program))
+}
+
+fn run(args: &[&str]) -> Option<String> {
+    let output = Command::new(args[0]).args(&args[1..]).output().ok()?;
+    output.status.success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+        .filter(|s| !s.trim().is_empty())
+}
@@ -0,0 +1,205 @@
+// Why auto-wiring left a command as typed. Falling back to plain execution
+// is silent on purpose (a slow or missing worker must never get in the way),
+// which leaves "the AI isn't doing anything" without an answer. Every command
+// auto-wiring sees is recorded with its fallback, if any: `autowire
+// explain-last` tells the last one's story and the auto-wiring panel counts
+// the reasons.
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use crate::ai::autowire_bridge::AutoWireResult;
+use crate::utils::clock;
+use crate::utils::error::NexTermError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fallback {
+    // Auto-wiring isn't enabled, or hasn't finished loading
+    Off,
+    Restricted,
+    // Turned off after panicking too often
+    Disabled,
+    Panicked,
+    // No auto-wiring system; suggestions come from the native agents
+    Unavailable,
+    // Slower than the latency SLO; suggestions come from cheatsheets
+    SloBreach,
+    Timeout,
+    BridgeError(String),
+    BadReply,
+    // A rewrite below `min_confidence` under [autowire]
+    LowConfidence(f32),
+}
+
+impl Fallback {
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        let timed_out = error
+            .chain()
+            .any(|e| matches!(e.downcast_ref::<NexTermError>(), Some(NexTermError::BridgeTimeout { .. })));
+        if timed_out {
+            Fallback::Timeout
+        } else {
+            Fallback::BridgeError(format!("{:#}", error))
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Fallback::Off => "off",
+            Fallback::Restricted => "restricted",
+            Fallback::Disabled => "disabled",
+            Fallback::Panicked => "panicked",
+            Fallback::Unavailable => "unavailable",
+            Fallback::SloBreach => "slow",
+            Fallback::Timeout => "timeout",
+            Fallback::BridgeError(_) => "error",
+            Fallback::BadReply => "bad reply",
+            Fallback::LowConfidence(_) => "low confidence",
+        }
+    }
+
+    // What happened, and what to do about it
+    pub fn explain(&self) -> (String, &'static str) {
+        match self {
+            Fallback::Off => (
+                "auto-wiring isn't running".to_string(),
+                "Start NexTerm with NEXTERM_AUTOWIRE=true, or wait for it to finish loading",
+            ),
+            Fallback::Restricted => (
+                "restricted (kiosk) mode keeps commands away from the AI".to_string(),
+                "Set enabled = false under [restricted] to auto-wire commands",
+            ),
+            Fallback::Disabled => (
+                "auto-wiring was turned off after panicking repeatedly".to_string(),
+                "Check the notification center for the panics, then restart NexTerm",
+            ),
+            Fallback::Panicked => ("auto-wiring panicked on it".to_string(), "See the notification center for the panic"),
+            Fallback::Unavailable => (
+                "the auto-wiring system isn't reachable, only the native agents answered".to_string(),
+                "Run `doctor` to check python and package_path under [autowire]",
+            ),
+            Fallback::SloBreach => (
+                "the worker is slower than its latency limits, so suggestions are offline".to_string(),
+                "Raise slo_p50_ms / slo_p95_ms under [autowire], or see the panel's log (l)",
+            ),
+            Fallback::Timeout => (
+                "the worker didn't answer in time".to_string(),
+                "Raise timeout_ms under [autowire], or see the panel's log (l)",
+            ),
+            Fallback::BridgeError(message) => (
+                format!("the bridge failed: {}", message),
+                "See the panel's log (l) for the worker's side",
+            ),
+            Fallback::BadReply => (
+                "the worker's reply couldn't be read".to_string(),
+                "Check that the auto-wiring package matches this NexTerm (`doctor`)",
+            ),
+            Fallback::LowConfidence(confidence) => (
+                format!("the rewrite's confidence ({:.2}) was below min_confidence", confidence),
+                "Lower min_confidence under [autowire] to accept less certain rewrites",
+            ),
+        }
+    }
+}
+
+struct Record {
+    command: String,
+    result: Option<AutoWireResult>,
+    fallback: Option<Fallback>,
+    at: Instant,
+}
+
+#[derive(Default)]
+pub struct FallbackLog {
+    last: Option<Record>,
+    counts: BTreeMap<&'static str, usize>,
+    commands: usize,
+}
+
+impl FallbackLog {
+    pub fn record(&mut self, command: &str, result: Option<&AutoWireResult>, fallback: Option<Fallback>) {
+        self.commands += 1;
+        if let Some(fallback) = &fallback {
+            *self.counts.entry(fallback.label()).or_default() += 1;
+        }
+        self.last = Some(Record { command: command.to_string(), result: result.cloned(), fallback, at: clock::now() });
+    }
+
+    // "2 of 12 fell back: 1 timeout, 1 low confidence", once a command was seen
+    pub fn summary(&self) -> Option<String> {
+        if self.commands == 0 {
+            return None;
+        }
+        let fell_back: usize = self.counts.values().sum();
+        if fell_back == 0 {
+            return Some(format!("{} auto-wired, none fell back", self.commands));
+        }
+        let reasons: Vec<String> = self.counts.iter().map(|(label, n)| format!("{} {}", n, label)).collect();
+        Some(format!("{} of {} fell back: {}", fell_back, self.commands, reasons.join(", ")))
+    }
+
+    // `autowire explain-last`
+    pub fn explain_last(&self) -> Vec<String> {
+        let Some(record) = &self.last else {
+            return vec!["No command has gone through auto-wiring yet".to_string()];
+        };
+        let mut lines = vec![format!("`{}` ({}s ago)", record.command, clock::since(record.at).as_secs())];
+        match (&record.fallback, &record.result) {
+            (Some(fallback), _) => {
+                let (reason, hint) = fallback.explain();
+                lines.push(format!("Ran as typed: {}", reason));
+                lines.push(format!("  {}", hint));
+            }
+            (None, Some(result)) => {
+                let agent = result.agent_used.as_deref().unwrap_or("the auto-wiring system");
+                lines.push(if result.processed_command == record.command {
+                    format!("Kept as typed by {} (confidence {:.2})", agent, result.confidence)
+                } else {
+                    format!("Rewritten by {} (confidence {:.2}) to `{}`", agent, result.confidence, result.processed_command)
+                });
+            }
+            (None, None) => lines.push("Ran as typed".to_string()),
+        }
+        if let Some(result) = record.result.as_ref().filter(|r| !r.suggestions.is_empty()) {
+            lines.push(format!("Suggested: {}", result.suggestions.join(", ")));
+        }
+        lines.extend(self.summary());
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(processed: &str, confidence: f32) -> AutoWireResult {
+        AutoWireResult {
+            processed_command: processed.to_string(),
+            suggestions: vec!["git push -u origin main".to_string()],
+            agent_used: Some("git-agent".to_string()),
+            confidence,
+        }
+    }
+
+    #[test]
+    fn records_why_commands_ran_as_typed() {
+        let mut log = FallbackLog::default();
+        assert_eq!(log.explain_last(), ["No command has gone through auto-wiring yet"]);
+        assert_eq!(log.summary(), None);
+
+        log.record("gti status", Some(&result("git status", 0.9)), None);
+        assert_eq!(log.explain_last()[1], "Rewritten by git-agent (confidence 0.90) to `git status`");
+        assert_eq!(log.summary().unwrap(), "1 auto-wired, none fell back");
+
+        let timeout = NexTermError::BridgeTimeout { transport: "stdio".into(), method: "process_command".into() };
+        let timeout = Fallback::from_error(&anyhow::Error::from(timeout).context("Auto-wiring failed"));
+        log.record("git push", None, Some(timeout));
+        log.record("ls", None, Some(Fallback::Timeout));
+        log.record("git pul", Some(&result("git pul", 0.1)), Some(Fallback::LowConfidence(0.1)));
+        let explained = log.explain_last();
+        assert_eq!(explained[1], "Ran as typed: the rewrite's confidence (0.10) was below min_confidence");
+        assert_eq!(explained[3], "Suggested: git push -u origin main");
+        assert_eq!(explained[4], "3 of 4 fell back: 1 low confidence, 2 timeout");
+        assert_eq!(Fallback::from_error(&anyhow::anyhow!("broken pipe")).label(), "error");
+        assert_eq!(Fallback::from_error(&anyhow::anyhow!("the worker timed out")).label(), "error");
+    }
+}
@@ -1,4 +1,18 @@
 // AI module
 pub mod autowire_bridge;
+pub mod autowire_daemon;
 pub mod suggestions;
-pub mod nlp;
+pub mod explain;
+pub mod project_notes;
+pub mod cheatsheets;
+pub mod completion_specs;
+pub mod community_specs;
+pub mod slo;
+pub mod transport;
+pub mod protocol;
+pub mod registry;
+pub mod routing;
+pub mod digest;
+pub mod review;
+pub mod proposals;
+pub mod fallbacks;
@@ -1,14 +0,0 @@
-// NLP processing
-use anyhow::Result;
-
-pub struct NLPProcessor;
-
-impl NLPProcessor {
-    pub fn new() -> Self {
-        Self
-    }
-
-    pub fn process(&self, text: &str) -> Result<String> {
-        Ok(text.to_string())
-    }
-}
@@ -0,0 +1,55 @@
+// Per-project knowledge notes (.nexterm/notes.md) fed to the AI context
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const NOTES_DIR: &str = ".nexterm";
+const NOTES_FILE: &str = "notes.md";
+
+const TEMPLATE: &str = "# Project notes\n\n\
+Conventions, deploy steps and gotchas for this project.\n\
+NexTerm passes this file to the AI when suggesting commands.\n";
+
+pub struct ProjectNotes {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+impl ProjectNotes {
+    // Nearest notes file from the current directory upwards
+    pub fn discover() -> Option<Self> {
+        let cwd = std::env::current_dir().ok()?;
+        cwd.ancestors()
+            .map(|dir| dir.join(NOTES_DIR).join(NOTES_FILE))
+            .find(|path| path.is_file())
+            .and_then(|path| {
+                let content = fs::read_to_string(&path).ok()?;
+                Some(Self { path, content })
+            })
+    }
+
+    // Where `notes edit` should write: the existing notes file, or a new one at
+    // the project root (nearest directory with a .git), created from a template
+    pub fn path_for_edit() -> Result<PathBuf> {
+        if let Some(notes) = Self::discover() {
+            return Ok(notes.path);
+        }
+
+        let cwd = std::env::current_dir()?;
+        let root = cwd
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .unwrap_or(&cwd);
+        let path = root.join(NOTES_DIR).join(NOTES_FILE);
+        create_from_template(&path)?;
+        Ok(path)
+    }
+}
+
+fn create_from_template(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, TEMPLATE)?;
+    Ok(())
+}
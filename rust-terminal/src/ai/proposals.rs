@@ -0,0 +1,201 @@
+// `ai do <request>`: the auto-wiring system answers a request with actions
+// it proposes (read a file, patch a file, run a command, have the user edit a
+// file), and each one is
+// shown as a card that does nothing until approved. Files are confined to
+// the project (the nearest directory with a .git, else the current one);
+// patches go through utils::patch, with the file as it was kept under
+// backups/ in the data directory. What approved reads and patches produced
+// is sent back for another round, until the AI has nothing more to propose.
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::utils::editor::EditResult;
+use crate::utils::{patch, storage};
+
+// Most of a file an approved read hands back
+pub const MAX_READ: usize = 64 * 1024;
+// Follow-up rounds one request gets
+pub const MAX_ROUNDS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Proposal {
+    ReadFile {
+        path: String,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    // A unified diff of one file
+    WritePatch {
+        path: String,
+        diff: String,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    RunCommand {
+        command: String,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    // Opened in the user's editor, see utils::editor
+    EditFile {
+        path: String,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+}
+
+impl Proposal {
+    pub fn title(&self) -> String {
+        match self {
+            Proposal::ReadFile { path, .. } => format!("Read {}", path),
+            Proposal::WritePatch { path, .. } => format!("Patch {}", path),
+            Proposal::RunCommand { command, .. } => format!("Run {}", command),
+            Proposal::EditFile { path, .. } => format!("Edit {}", path),
+        }
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            Proposal::ReadFile { reason, .. }
+            | Proposal::WritePatch { reason, .. }
+            | Proposal::RunCommand { reason, .. }
+            | Proposal::EditFile { reason, .. } => reason.as_deref(),
+        }
+    }
+}
+
+// A reply to `propose_actions`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Proposals {
+    // What the AI has to say, shown above the cards
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub actions: Vec<Proposal>,
+}
+
+// Where approved file actions may touch
+pub struct Workspace {
+    root: PathBuf,
+}
+
+impl Workspace {
+    pub fn discover() -> Result<Self> {
+        let cwd = std::env::current_dir()?;
+        let root = cwd.ancestors().find(|dir| dir.join(".git").exists()).unwrap_or(&cwd).to_path_buf();
+        Ok(Self { root })
+    }
+
+    // `path` under the root; absolute paths and `..` that leave it are refused
+    pub fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let path = Path::new(path);
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative,
+            Err(_) if path.is_absolute() => bail!("{} is outside the project ({})", path.display(), self.root.display()),
+            Err(_) => path,
+        };
+        let mut resolved = self.root.clone();
+        for component in relative.components() {
+            match component {
+                Component::Normal(part) => resolved.push(part),
+                Component::CurDir => {}
+                Component::ParentDir if resolved != self.root => {
+                    resolved.pop();
+                }
+                _ => bail!("{} is outside the project ({})", path.display(), self.root.display()),
+            }
+        }
+        // Symlinks out of the project count as outside it too
+        let existing = resolved.ancestors().find(|dir| dir.exists()).unwrap_or(&self.root);
+        if !fs::canonicalize(existing)?.starts_with(fs::canonicalize(&self.root)?) {
+            bail!("{} leads outside the project ({})", path.display(), self.root.display());
+        }
+        Ok(resolved)
+    }
+
+    // Carries out an approved read or patch; what came of it is returned for
+    // the next round. Commands and edits are left to the caller.
+    pub fn apply(&self, proposal: &Proposal) -> Result<Value> {
+        match proposal {
+            Proposal::ReadFile { path, .. } => {
+                let bytes = fs::read(self.resolve(path)?).with_context(|| format!("Failed to read {}", path))?;
+                let truncated = bytes.len() > MAX_READ;
+                let content = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_READ)]).into_owned();
+                Ok(json!({ "kind": "read_file", "path": path, "content": content, "truncated": truncated }))
+            }
+            Proposal::WritePatch { path, diff, .. } => {
+                let backup = patch::apply_file(&self.resolve(path)?, diff, &storage::data_dir().join("backups"))?;
+                Ok(json!({ "kind": "write_patch", "path": path, "applied": true, "backup": backup }))
+            }
+            Proposal::RunCommand { .. } => bail!("Commands are run by the terminal"),
+            Proposal::EditFile { .. } => bail!("Edits are opened by the terminal"),
+        }
+    }
+}
+
+// An approved command, handed to the terminal
+pub fn submitted(command: &str) -> Value {
+    json!({ "kind": "run_command", "command": command, "submitted": true })
+}
+
+// An approved edit: whether the user saved it, and what the file says now
+pub fn edited(path: &str, result: &EditResult) -> Value {
+    let content = fs::read(&result.path)
+        .ok()
+        .map(|bytes| String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_READ)]).into_owned());
+    json!({
+        "kind": "edit_file",
+        "path": path,
+        "exit_code": result.exit_code,
+        "changed": result.changed,
+        "content": content,
+    })
+}
+
+// What a declined or failed action tells the AI
+pub fn declined(proposal: &Proposal, error: Option<&str>) -> Value {
+    json!({
+        "kind": serde_json::to_value(proposal).ok().and_then(|p| p.get("kind").cloned()),
+        "action": proposal.title(),
+        "declined": error.is_none(),
+        "error": error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confines_actions_to_the_project() {
+        let proposals: Proposals = serde_json::from_value(json!({
+            "message": "Fixing the typo",
+            "actions": [
+                { "kind": "read_file", "path": "README.md" },
+                { "kind": "write_patch", "path": "README.md", "diff": "@@ -1 +1 @@\n-helo\n+hello\n", "reason": "typo" },
+                { "kind": "run_command", "command": "git diff" },
+                { "kind": "edit_file", "path": "CHANGELOG.md" },
+            ],
+        }))
+        .unwrap();
+        assert_eq!(proposals.actions[1].title(), "Patch README.md");
+        assert_eq!(proposals.actions[1].reason(), Some("typo"));
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("README.md"), "helo\n").unwrap();
+        let workspace = Workspace { root: dir.path().to_path_buf() };
+        assert!(workspace.resolve("../etc/passwd").is_err());
+        assert!(workspace.resolve("/etc/passwd").is_err());
+        assert_eq!(workspace.resolve("src/../README.md").unwrap(), dir.path().join("README.md"));
+
+        let read = workspace.apply(&proposals.actions[0]).unwrap();
+        assert_eq!(read["content"], "helo\n");
+        assert!(workspace.apply(&proposals.actions[2]).is_err());
+        assert_eq!(proposals.actions[3].title(), "Edit CHANGELOG.md");
+        assert!(workspace.apply(&proposals.actions[3]).is_err());
+    }
+}
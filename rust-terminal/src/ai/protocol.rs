@@ -0,0 +1,92 @@
+// Versioned handshake with the auto-wiring system. Before anything else the
+// bridge sends `handshake` {"protocol_version", "client"} and the server
+// answers {"protocol_version", "min_protocol_version", "features"}. Each side
+// speaks one version and accepts peers down to its minimum; features let the
+// server leave parts out, and the bridge skips what isn't offered.
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+// What this NexTerm speaks, and the oldest server it still talks to
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+// list_services, service_details, invoke_service, restart_service
+pub const SERVICES: &str = "services";
+// `context` in process_command: project notes and cheatsheets
+pub const PROJECT_NOTES: &str = "project_notes";
+// `flags` in process_command: the flags the program documents
+pub const FLAG_HINTS: &str = "flag_hints";
+// review_diff: findings for a piece of a diff
+pub const REVIEW: &str = "review";
+// polish_script: a generated script, tidied
+pub const SCRIPT_POLISH: &str = "script_polish";
+// propose_actions: file reads, patches and commands for a request, see ai::proposals
+pub const ACTIONS: &str = "actions";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Capabilities {
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub min_protocol_version: u32,
+    #[serde(default)]
+    pub features: BTreeSet<String>,
+}
+
+impl Capabilities {
+    pub fn supports(&self, feature: &str) -> bool {
+        self.features.contains(feature)
+    }
+}
+
+pub fn handshake_params() -> Value {
+    json!({
+        "protocol_version": PROTOCOL_VERSION,
+        "client": format!("nexterm {}", env!("CARGO_PKG_VERSION")),
+    })
+}
+
+// The server's capabilities, or why the two sides can't talk
+pub fn negotiate(reply: &Value) -> Result<Capabilities, String> {
+    let theirs: Capabilities = serde_json::from_value(reply.clone())
+        .map_err(|e| format!("unreadable handshake reply ({})", e))?;
+    if theirs.protocol_version < MIN_PROTOCOL_VERSION {
+        return Err(format!(
+            "the auto-wiring system speaks protocol {}, NexTerm needs at least {}",
+            theirs.protocol_version, MIN_PROTOCOL_VERSION
+        ));
+    }
+    if theirs.min_protocol_version > PROTOCOL_VERSION {
+        return Err(format!(
+            "the auto-wiring system needs protocol {} or later, NexTerm speaks {}",
+            theirs.min_protocol_version, PROTOCOL_VERSION
+        ));
+    }
+    Ok(theirs)
+}
+
+// A server from before the handshake existed rejects it as an unknown method
+pub fn predates_handshake(error: &str) -> bool {
+    error.starts_with("KeyError") || error.contains("unknown method")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiates_versions_and_features() {
+        let caps = negotiate(&json!({ "protocol_version": 1, "features": ["services"] })).unwrap();
+        assert!(caps.supports(SERVICES));
+        assert!(!caps.supports(FLAG_HINTS));
+
+        let old = negotiate(&json!({ "protocol_version": 0 })).unwrap_err();
+        assert_eq!(old, "the auto-wiring system speaks protocol 0, NexTerm needs at least 1");
+        let new = negotiate(&json!({ "protocol_version": 3, "min_protocol_version": 2 })).unwrap_err();
+        assert_eq!(new, "the auto-wiring system needs protocol 2 or later, NexTerm speaks 1");
+        assert!(negotiate(&json!("pong")).is_err());
+
+        assert!(predates_handshake("KeyError: 'handshake'"));
+        assert!(!predates_handshake("timed out"));
+    }
+}
@@ -0,0 +1,451 @@
+// Native service registry: what core/autowire.py does, without Python.
+// Services are registered with a factory, the names of the services it needs
+// and a scope; resolving one builds its dependencies first and refuses
+// cycles. Services may declare capabilities (see ai::routing) that commands
+// are routed by. Providers (the Python auto-wiring system among them)
+// contribute services that are listed and acted on alongside the native ones.
+use anyhow::{anyhow, bail, Result};
+use serde::Serialize;
+use std::any::{type_name, Any};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info};
+
+use crate::ai::cheatsheets::Cheatsheet;
+use crate::ai::routing;
+
+pub type Service = Arc<dyn Any + Send + Sync>;
+type Factory = Arc<dyn Fn(&Dependencies) -> Result<Service> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    // One instance, kept until the service is restarted
+    #[default]
+    Singleton,
+    // A new instance every time it's resolved
+    Transient,
+    // One instance until clear_scoped
+    Scoped,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ServiceSpec {
+    pub name: String,
+    pub scope: Scope,
+    pub dependencies: Vec<String>,
+    pub tags: Vec<String>,
+    // What the service can handle: git, docker, fs, network
+    pub capabilities: Vec<String>,
+    // Singletons are built when registered unless lazy
+    pub lazy: bool,
+}
+
+impl ServiceSpec {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), ..Default::default() }
+    }
+
+    pub fn with_scope(mut self, scope: Scope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    pub fn depends_on(mut self, names: &[&str]) -> Self {
+        self.dependencies.extend(names.iter().map(|n| n.to_string()));
+        self
+    }
+
+    pub fn tagged(mut self, tags: &[&str]) -> Self {
+        self.tags.extend(tags.iter().map(|t| t.to_string()));
+        self
+    }
+
+    pub fn capable_of(mut self, capabilities: &[&str]) -> Self {
+        self.capabilities.extend(capabilities.iter().map(|c| c.to_string()));
+        self
+    }
+}
+
+// What service_details shows; the same fields as the Python registry info
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServiceInfo {
+    pub scope: Scope,
+    pub dependencies: Vec<String>,
+    pub tags: Vec<String>,
+    pub capabilities: Vec<String>,
+    pub lazy: bool,
+    pub initialized: bool,
+    #[serde(rename = "type")]
+    pub type_name: &'static str,
+}
+
+// A factory's resolved dependencies, by name
+pub struct Dependencies(HashMap<String, Service>);
+
+impl Dependencies {
+    pub fn get<T: Any + Send + Sync>(&self, name: &str) -> Result<Arc<T>> {
+        let service = self.0.get(name).ok_or_else(|| anyhow!("'{}' is not a declared dependency", name))?;
+        downcast(name, service.clone())
+    }
+}
+
+// Services that live elsewhere, listed and acted on through the registry
+pub trait ServiceProvider: Send + Sync {
+    // Never blocks; may be a cached listing
+    fn services(&self) -> Vec<String>;
+
+    fn details(&self, name: &str) -> Result<String>;
+
+    fn invoke(&self, name: &str) -> Result<String>;
+
+    fn restart(&self, name: &str) -> Result<String>;
+}
+
+// Takes on the commands routed to it by capability; the native counterpart
+// of the Python system's agents, registered with the "agent" tag
+pub struct Agent {
+    pub hint: &'static str,
+    cheatsheets: Arc<Cheatsheets>,
+}
+
+impl Agent {
+    // The hint, then examples from the program's cheatsheet
+    pub fn suggestions(&self, program: &str, limit: usize) -> Vec<String> {
+        let mut suggestions = vec![self.hint.to_string()];
+        suggestions.extend(self.cheatsheets.examples(program));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+// Cheatsheet examples by tool, read once per scope (see clear_scoped)
+#[derive(Default)]
+pub struct Cheatsheets(Mutex<HashMap<String, Vec<String>>>);
+
+impl Cheatsheets {
+    pub fn examples(&self, tool: &str) -> Vec<String> {
+        let mut loaded = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        loaded
+            .entry(tool.to_string())
+            .or_insert_with(|| Cheatsheet::load(tool).map(|c| c.examples()).unwrap_or_default())
+            .clone()
+    }
+}
+
+pub const AGENT_TAG: &str = "agent";
+
+struct Entry {
+    spec: ServiceSpec,
+    type_name: &'static str,
+    factory: Factory,
+    // Singletons, and scoped services until clear_scoped
+    instance: Option<Service>,
+}
+
+#[derive(Default)]
+pub struct ServiceRegistry {
+    entries: Mutex<BTreeMap<String, Entry>>,
+    providers: Mutex<Vec<Arc<dyn ServiceProvider>>>,
+}
+
+impl ServiceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // NexTerm's own services, which work without Python
+    pub fn builtin() -> Result<Self> {
+        let registry = Self::new();
+        registry.register(ServiceSpec::new("cheatsheets").with_scope(Scope::Scoped), |_| Ok(Cheatsheets::default()))?;
+        let agents: [(&str, &[&str], &'static str); 4] = [
+            ("git_agent", &[routing::GIT], "Git agent available"),
+            ("docker_agent", &[routing::DOCKER, routing::NETWORK], "Docker agent available"),
+            ("fs_agent", &[routing::FS], "Filesystem agent available"),
+            ("network_agent", &[routing::NETWORK], "Network agent available"),
+        ];
+        for (name, capabilities, hint) in agents {
+            let spec = ServiceSpec::new(name)
+                .with_scope(Scope::Scoped)
+                .depends_on(&["cheatsheets"])
+                .tagged(&[AGENT_TAG])
+                .capable_of(capabilities);
+            registry.register(spec, move |deps| Ok(Agent { hint, cheatsheets: deps.get("cheatsheets")? }))?;
+        }
+        Ok(registry)
+    }
+
+    // Replaces a service of the same name; a singleton that isn't lazy is built right away
+    pub fn register<T, F>(&self, spec: ServiceSpec, factory: F) -> Result<()>
+    where
+        T: Any + Send + Sync,
+        F: Fn(&Dependencies) -> Result<T> + Send + Sync + 'static,
+    {
+        let name = spec.name.clone();
+        let eager = spec.scope == Scope::Singleton && !spec.lazy;
+        info!("Registered service: {} (scope={:?}, lazy={})", name, spec.scope, spec.lazy);
+        let factory: Factory = Arc::new(move |deps: &Dependencies| Ok(Arc::new(factory(deps)?) as Service));
+        let entry = Entry { spec, type_name: type_name::<T>(), factory, instance: None };
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).insert(name.clone(), entry);
+        if eager {
+            self.resolve(&name)?;
+        }
+        Ok(())
+    }
+
+    pub fn add_provider(&self, provider: Arc<dyn ServiceProvider>) {
+        self.providers.lock().unwrap_or_else(|e| e.into_inner()).push(provider);
+    }
+
+    pub fn resolve(&self, name: &str) -> Result<Service> {
+        self.resolve_from(name, &mut Vec::new())
+    }
+
+    pub fn resolve_as<T: Any + Send + Sync>(&self, name: &str) -> Result<Arc<T>> {
+        downcast(name, self.resolve(name)?)
+    }
+
+    // Native services that declare capabilities, with them
+    pub fn capabilities(&self) -> Vec<(String, Vec<String>)> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|entry| !entry.spec.capabilities.is_empty())
+            .map(|entry| (entry.spec.name.clone(), entry.spec.capabilities.clone()))
+            .collect()
+    }
+
+    // Native services with `tag`, e.g. the agents
+    pub fn with_tag(&self, tag: &str) -> Vec<String> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter(|entry| entry.spec.tags.iter().any(|t| t == tag))
+            .map(|entry| entry.spec.name.clone())
+            .collect()
+    }
+
+    // `path` is the chain of services being built, to report cycles with
+    fn resolve_from(&self, name: &str, path: &mut Vec<String>) -> Result<Service> {
+        if path.iter().any(|n| n == name) {
+            bail!("Circular dependency detected: {} -> {}", path.join(" -> "), name);
+        }
+        // The lock isn't held while factories run, so they may be slow
+        let (spec, factory) = {
+            let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(entry) = entries.get(name) else {
+                bail!("Service '{}' not found", name);
+            };
+            if let Some(instance) = &entry.instance {
+                return Ok(instance.clone());
+            }
+            (entry.spec.clone(), entry.factory.clone())
+        };
+
+        path.push(name.to_string());
+        let dependencies = spec
+            .dependencies
+            .iter()
+            .map(|dep| Ok((dep.clone(), self.resolve_from(dep, path)?)))
+            .collect::<Result<HashMap<_, _>>>();
+        path.pop();
+        let instance = factory(&Dependencies(dependencies?))?;
+        debug!("Created instance of '{}'", name);
+
+        if spec.scope == Scope::Transient {
+            return Ok(instance);
+        }
+        // Whoever built it first wins, so everyone shares one instance
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get_mut(name) {
+            Some(entry) => Ok(entry.instance.get_or_insert(instance).clone()),
+            None => Ok(instance),
+        }
+    }
+
+    pub fn clear_scoped(&self) {
+        for entry in self.entries.lock().unwrap_or_else(|e| e.into_inner()).values_mut() {
+            if entry.spec.scope == Scope::Scoped {
+                entry.instance = None;
+            }
+        }
+    }
+
+    pub fn info(&self, name: &str) -> Option<ServiceInfo> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.get(name).map(|entry| ServiceInfo {
+            scope: entry.spec.scope,
+            dependencies: entry.spec.dependencies.clone(),
+            tags: entry.spec.tags.clone(),
+            capabilities: entry.spec.capabilities.clone(),
+            lazy: entry.spec.lazy,
+            initialized: entry.instance.is_some(),
+            type_name: entry.type_name,
+        })
+    }
+
+    fn is_native(&self, name: &str) -> bool {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).contains_key(name)
+    }
+
+    // Native services first, then each provider's; a name is only listed once
+    pub fn list(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.entries.lock().unwrap_or_else(|e| e.into_inner()).keys().cloned().collect();
+        for provider in self.providers() {
+            for name in provider.services() {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    pub fn details(&self, name: &str) -> Result<String> {
+        match self.info(name) {
+            Some(info) => Ok(serde_json::to_string_pretty(&info)?),
+            None => self.provider_for(name)?.details(name),
+        }
+    }
+
+    pub fn invoke(&self, name: &str) -> Result<String> {
+        if !self.is_native(name) {
+            return self.provider_for(name)?.invoke(name);
+        }
+        self.resolve(name)?;
+        let type_name = self.info(name).map(|info| info.type_name).unwrap_or_default();
+        Ok(format!("{} -> {}", name, type_name.rsplit("::").next().unwrap_or(type_name)))
+    }
+
+    // Drops the instance and builds a new one
+    pub fn restart(&self, name: &str) -> Result<String> {
+        if !self.is_native(name) {
+            return self.provider_for(name)?.restart(name);
+        }
+        if let Some(entry) = self.entries.lock().unwrap_or_else(|e| e.into_inner()).get_mut(name) {
+            entry.instance = None;
+        }
+        self.resolve(name)?;
+        Ok(format!("{} restarted", name))
+    }
+
+    fn providers(&self) -> Vec<Arc<dyn ServiceProvider>> {
+        self.providers.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn provider_for(&self, name: &str) -> Result<Arc<dyn ServiceProvider>> {
+        self.providers()
+            .into_iter()
+            .find(|provider| provider.services().iter().any(|s| s == name))
+            .ok_or_else(|| anyhow!("Service '{}' not found", name))
+    }
+}
+
+fn downcast<T: Any + Send + Sync>(name: &str, service: Service) -> Result<Arc<T>> {
+    service
+        .downcast::<T>()
+        .map_err(|_| anyhow!("Service '{}' is not a {}", name, type_name::<T>()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn lazy(name: &str) -> ServiceSpec {
+        ServiceSpec { lazy: true, ..ServiceSpec::new(name) }
+    }
+
+    struct Database {
+        url: String,
+    }
+
+    struct Users {
+        db: Arc<Database>,
+    }
+
+    #[test]
+    fn resolves_dependencies_by_scope() {
+        let registry = ServiceRegistry::new();
+        let built = Arc::new(AtomicUsize::new(0));
+        let counter = built.clone();
+        registry
+            .register(lazy("db"), move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(Database { url: "sqlite://".to_string() })
+            })
+            .unwrap();
+        registry
+            .register(ServiceSpec::new("users").with_scope(Scope::Transient).depends_on(&["db"]), |deps| {
+                Ok(Users { db: deps.get::<Database>("db")? })
+            })
+            .unwrap();
+        assert!(!registry.info("db").unwrap().initialized);
+
+        let first = registry.resolve_as::<Users>("users").unwrap();
+        let second = registry.resolve_as::<Users>("users").unwrap();
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert!(Arc::ptr_eq(&first.db, &second.db));
+        assert_eq!(first.db.url, "sqlite://");
+        assert_eq!(built.load(Ordering::SeqCst), 1);
+
+        registry.restart("db").unwrap();
+        assert_eq!(built.load(Ordering::SeqCst), 2);
+        assert!(registry.resolve_as::<Users>("db").is_err());
+        assert_eq!(registry.invoke("db").unwrap(), "db -> Database");
+    }
+
+    #[test]
+    fn reports_missing_and_circular_dependencies() {
+        let registry = ServiceRegistry::new();
+        registry.register(lazy("a").depends_on(&["b"]), |_| Ok(())).unwrap();
+        registry.register(lazy("b").depends_on(&["a"]), |_| Ok(())).unwrap();
+        registry.register(lazy("c").depends_on(&["missing"]), |_| Ok(())).unwrap();
+
+        assert_eq!(registry.resolve("a").unwrap_err().to_string(), "Circular dependency detected: a -> b -> a");
+        assert_eq!(registry.resolve("c").unwrap_err().to_string(), "Service 'missing' not found");
+        // An eager singleton fails to register when it can't be built
+        assert!(registry.register(ServiceSpec::new("d").depends_on(&["missing"]), |_| Ok(())).is_err());
+    }
+
+    struct Remote;
+
+    impl ServiceProvider for Remote {
+        fn services(&self) -> Vec<String> {
+            vec!["git_agent".to_string(), "llm".to_string()]
+        }
+
+        fn details(&self, name: &str) -> Result<String> {
+            Ok(format!("remote {}", name))
+        }
+
+        fn invoke(&self, name: &str) -> Result<String> {
+            Ok(format!("{} -> Remote", name))
+        }
+
+        fn restart(&self, name: &str) -> Result<String> {
+            Ok(format!("{} restarted remotely", name))
+        }
+    }
+
+    #[test]
+    fn lists_native_services_before_providers() {
+        let registry = ServiceRegistry::builtin().unwrap();
+        registry.add_provider(Arc::new(Remote));
+        assert_eq!(registry.list(), ["cheatsheets", "docker_agent", "fs_agent", "git_agent", "network_agent", "llm"]);
+        assert_eq!(registry.invoke("git_agent").unwrap(), "git_agent -> Agent");
+        assert_eq!(registry.invoke("llm").unwrap(), "llm -> Remote");
+        assert!(registry.details("nope").is_err());
+
+        let agent = registry.resolve_as::<Agent>("docker_agent").unwrap();
+        assert_eq!(agent.suggestions("docker", 1), ["Docker agent available"]);
+
+        // Scoped services are rebuilt, with fresh dependencies, after clear_scoped
+        registry.clear_scoped();
+        let rebuilt = registry.resolve_as::<Agent>("docker_agent").unwrap();
+        assert!(!Arc::ptr_eq(&agent, &rebuilt));
+        assert!(!Arc::ptr_eq(&agent.cheatsheets, &rebuilt.cheatsheets));
+    }
+}
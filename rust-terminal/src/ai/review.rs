@@ -0,0 +1,237 @@
+// `review`: the staged diff goes to the auto-wiring system in chunks that fit
+// its token budget, and the findings that come back are anchored to a file
+// and a line on the new side of the diff, for the problems panel.
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::process::Command;
+
+// Rough count for code and English alike
+pub const CHARS_PER_TOKEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn label(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+
+    // The problems panel's minimum: info, warning, error and round again
+    pub fn next(self) -> Self {
+        match self {
+            Level::Info => Level::Warning,
+            Level::Warning => Level::Error,
+            Level::Error => Level::Info,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Finding {
+    pub file: String,
+    // On the new side; none for the file as a whole
+    #[serde(default)]
+    pub line: Option<u32>,
+    #[serde(rename = "severity")]
+    pub level: Level,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn location(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{}", self.file, line),
+            None => self.file.clone(),
+        }
+    }
+}
+
+// What `git commit` would record
+pub fn staged_diff() -> Result<String> {
+    let output = Command::new("git").args(["diff", "--cached", "--no-color", "--unified=3"]).output()?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let diff = String::from_utf8_lossy(&output.stdout).into_owned();
+    if diff.trim().is_empty() {
+        bail!("Nothing staged to review; `git add` the changes first");
+    }
+    Ok(diff)
+}
+
+// Splits a diff into pieces of about `max_chars`: whole files while they fit,
+// else hunk by hunk, and a hunk too big on its own between lines. Every piece
+// carries its file's header, and a cut hunk gets a header of its own, so the
+// reviewer can still tell the file and line of what it sees.
+pub fn chunks(diff: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for (header, hunks) in files(diff) {
+        let whole: usize = header.len() + hunks.iter().map(String::len).sum::<usize>();
+        if !current.is_empty() && current.len() + whole > max_chars {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if current.len() + whole <= max_chars {
+            current.push_str(&header);
+            hunks.iter().for_each(|hunk| current.push_str(hunk));
+            continue;
+        }
+
+        let room = max_chars.saturating_sub(header.len());
+        for piece in hunks.iter().flat_map(|hunk| split_hunk(hunk, room)) {
+            if !current.is_empty() && current.len() + piece.len() > max_chars {
+                chunks.push(std::mem::take(&mut current));
+            }
+            if current.is_empty() {
+                current.push_str(&header);
+            }
+            current.push_str(&piece);
+        }
+        chunks.push(std::mem::take(&mut current));
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+// Most severe first, then by place
+pub fn sort(findings: &mut Vec<Finding>) {
+    findings.sort_by(|a, b| b.level.cmp(&a.level).then(a.file.cmp(&b.file)).then(a.line.cmp(&b.line)));
+    findings.dedup();
+}
+
+// Each file's header lines and its hunks, lines ending in newlines
+fn files(diff: &str) -> Vec<(String, Vec<String>)> {
+    let mut files: Vec<(String, Vec<String>)> = Vec::new();
+    for line in diff.split_inclusive('\n') {
+        if line.starts_with("diff --git ") || files.is_empty() {
+            files.push((String::new(), Vec::new()));
+        }
+        let (header, hunks) = files.last_mut().expect("pushed above");
+        if line.starts_with("@@") {
+            hunks.push(String::new());
+        }
+        match hunks.last_mut() {
+            Some(hunk) => hunk.push_str(line),
+            None => header.push_str(line),
+        }
+    }
+    files
+}
+
+// `hunk` in pieces of at most `room`, each after the first headed by where it
+// starts (`@@ -old +new @@`)
+fn split_hunk(hunk: &str, room: usize) -> Vec<String> {
+    if hunk.len() <= room {
+        return vec![hunk.to_string()];
+    }
+    let mut lines = hunk.split_inclusive('\n');
+    let first = lines.next().unwrap_or_default();
+    let (mut old, mut new) = hunk_start(first).unwrap_or((1, 1));
+    let mut pieces = vec![first.to_string()];
+    for line in lines {
+        let piece = pieces.last_mut().expect("starts with the header");
+        if piece.len() + line.len() > room && piece.lines().count() > 1 {
+            pieces.push(format!("@@ -{} +{} @@\n", old, new));
+        }
+        pieces.last_mut().expect("just pushed").push_str(line);
+        match line.as_bytes().first() {
+            Some(b'+') => new += 1,
+            Some(b'-') => old += 1,
+            Some(b'\\') => {}
+            _ => {
+                old += 1;
+                new += 1;
+            }
+        }
+    }
+    pieces
+}
+
+// `@@ -12,7 +12,9 @@` -> (12, 12)
+fn hunk_start(header: &str) -> Option<(u32, u32)> {
+    let mut ranges = header.split_whitespace().skip(1);
+    let start = |range: &str| range[1..].split(',').next()?.parse().ok();
+    Some((start(ranges.next()?)?, start(ranges.next()?)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const DIFF: &str = "\
+diff --git a/src/a.rs b/src/a.rs
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -1,2 +1,3 @@
+ fn a() {}
++fn b() {}
+ fn c() {}
+@@ -10,3 +11,3 @@
+ one
+-two
++deux
+ three
+diff --git a/README.md b/README.md
+--- a/README.md
++++ b/README.md
+@@ -1 +1 @@
+-old
++new
+";
+
+    #[test]
+    fn chunks_at_files_hunks_and_lines() {
+        // Everything fits
+        assert_eq!(chunks(DIFF, 10_000), [DIFF]);
+
+        // One file per chunk
+        let by_file = chunks(DIFF, 170);
+        assert_eq!(by_file.len(), 2);
+        assert!(by_file[0].starts_with("diff --git a/src/a.rs") && by_file[0].ends_with(" three\n"));
+        assert!(by_file[1].starts_with("diff --git a/README.md"));
+
+        // Hunks of a.rs apart, each with the file header
+        let by_hunk = chunks(DIFF, 120);
+        assert_eq!(by_hunk.len(), 3);
+        assert!(by_hunk[1].starts_with("diff --git a/src/a.rs b/src/a.rs\n--- a/src/a.rs\n+++ b/src/a.rs\n@@ -10,3 +11,3 @@"));
+
+        // A hunk cut between lines says where each piece starts
+        let by_line = split_hunk("@@ -10,3 +11,3 @@\n one\n-two\n+deux\n three\n", 24);
+        assert_eq!(
+            by_line,
+            ["@@ -10,3 +11,3 @@\n one\n", "@@ -11 +12 @@\n-two\n", "@@ -12 +12 @@\n+deux\n", "@@ -12 +13 @@\n three\n"]
+        );
+    }
+
+    #[test]
+    fn reads_and_orders_findings() {
+        let mut findings: Vec<Finding> = serde_json::from_value(json!([
+            { "file": "src/a.rs", "line": 2, "severity": "info", "message": "TODO left in" },
+            { "file": "src/a.rs", "line": 12, "severity": "error", "message": "Merge conflict marker" },
+            { "file": "README.md", "severity": "warning", "message": "Trailing whitespace" },
+        ]))
+        .unwrap();
+        sort(&mut findings);
+        let order: Vec<(Level, String)> = findings.iter().map(|f| (f.level, f.location())).collect();
+        assert_eq!(
+            order,
+            [
+                (Level::Error, "src/a.rs:12".to_string()),
+                (Level::Warning, "README.md".to_string()),
+                (Level::Info, "src/a.rs:2".to_string()),
+            ]
+        );
+    }
+}
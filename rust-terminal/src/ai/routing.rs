@@ -0,0 +1,188 @@
+// Capability routing: a parsed command is read for what it needs (the program
+// it runs, URLs and paths among its arguments), each native service that
+// declares capabilities is scored by the needs it covers, and the best one
+// takes the command. The whole reckoning is kept for `route <command>`.
+use std::path::Path;
+
+use crate::ai::cheatsheets;
+use crate::ai::registry::ServiceRegistry;
+use crate::core::command::ParsedCommand;
+
+pub const GIT: &str = "git";
+pub const DOCKER: &str = "docker";
+pub const FS: &str = "fs";
+pub const NETWORK: &str = "network";
+
+const PROGRAMS: &[(&str, &[&str])] = &[
+    (GIT, &["git", "gh", "tig"]),
+    (DOCKER, &["docker", "docker-compose", "podman"]),
+    (FS, &["ls", "cd", "cp", "mv", "rm", "mkdir", "rmdir", "touch", "cat", "find", "du", "df", "chmod", "chown", "ln", "tar", "zip", "unzip"]),
+    (NETWORK, &["curl", "wget", "ssh", "scp", "rsync", "ping", "nc", "dig", "nslookup", "http"]),
+];
+
+// The program counts for more than what its arguments look like
+const PROGRAM_WEIGHT: u32 = 2;
+const ARGUMENT_WEIGHT: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Need {
+    pub capability: &'static str,
+    pub weight: u32,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub service: String,
+    pub score: u32,
+    // The needs it covers, of its capabilities
+    pub matched: Vec<&'static str>,
+    pub capabilities: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Routing {
+    pub command: String,
+    pub program: Option<String>,
+    pub needs: Vec<Need>,
+    // Best first
+    pub candidates: Vec<Candidate>,
+}
+
+impl Routing {
+    // The service the command goes to; none when nothing scored
+    pub fn chosen(&self) -> Option<&Candidate> {
+        self.candidates.first().filter(|c| c.score > 0)
+    }
+
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines = vec![match self.chosen() {
+            Some(chosen) => format!("🧭 {} → {} (score {})", self.command, chosen.service, chosen.score),
+            None => format!("🧭 {} → no service (nothing it needs is covered)", self.command),
+        }];
+        lines.extend(self.needs.iter().map(|need| format!("🧭   needs {:<8} +{}  {}", need.capability, need.weight, need.reason)));
+        lines.extend(self.candidates.iter().map(|c| {
+            let marker = if self.chosen() == Some(c) { "▶" } else { " " };
+            let covers = if c.matched.is_empty() { "nothing".to_string() } else { c.matched.join(", ") };
+            format!("🧭 {} {:<14} {:>2}  covers {} of [{}]", marker, c.service, c.score, covers, c.capabilities.join(", "))
+        }));
+        lines
+    }
+}
+
+// What a command needs, strongest first
+pub fn needs(parsed: &ParsedCommand) -> Vec<Need> {
+    let words = words(parsed);
+    let Some(program) = cheatsheets::program_in(words.iter().copied()) else {
+        return Vec::new();
+    };
+    let name = file_name(program);
+    let mut needs: Vec<Need> = PROGRAMS
+        .iter()
+        .filter(|(_, programs)| programs.contains(&name))
+        .map(|(capability, _)| Need { capability, weight: PROGRAM_WEIGHT, reason: format!("runs {}", name) })
+        .collect();
+
+    for arg in words.iter().skip_while(|w| **w != program).skip(1) {
+        if arg.starts_with('-') {
+            continue;
+        }
+        let need = if arg.contains("://") {
+            Need { capability: NETWORK, weight: ARGUMENT_WEIGHT, reason: format!("`{}` is a URL", arg) }
+        } else if is_remote_path(arg) {
+            Need { capability: NETWORK, weight: ARGUMENT_WEIGHT, reason: format!("`{}` is on another host", arg) }
+        } else if is_path(arg) {
+            Need { capability: FS, weight: ARGUMENT_WEIGHT, reason: format!("`{}` is a path", arg) }
+        } else {
+            continue;
+        };
+        // Each capability counts once for the arguments
+        if !needs.iter().any(|n| n.capability == need.capability) {
+            needs.push(need);
+        }
+    }
+    needs
+}
+
+// Scores every native service with capabilities; ties go to the more
+// specialised service, then by name
+pub fn route(registry: &ServiceRegistry, parsed: &ParsedCommand) -> Routing {
+    let needs = needs(parsed);
+    let mut candidates: Vec<Candidate> = registry
+        .capabilities()
+        .into_iter()
+        .map(|(service, capabilities)| {
+            let matched: Vec<&Need> = needs.iter().filter(|n| capabilities.iter().any(|c| c == n.capability)).collect();
+            Candidate {
+                service,
+                score: matched.iter().map(|n| n.weight).sum(),
+                matched: matched.iter().map(|n| n.capability).collect(),
+                capabilities,
+            }
+        })
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(a.capabilities.len().cmp(&b.capabilities.len()))
+            .then_with(|| a.service.cmp(&b.service))
+    });
+    let words = words(parsed);
+    Routing {
+        command: words.join(" "),
+        program: cheatsheets::program_in(words.iter().copied()).map(|p| file_name(p).to_string()),
+        needs,
+        candidates,
+    }
+}
+
+fn words(parsed: &ParsedCommand) -> Vec<&str> {
+    std::iter::once(parsed.command.as_str()).chain(parsed.args.iter().map(String::as_str)).collect()
+}
+
+fn file_name(program: &str) -> &str {
+    Path::new(program).file_name().and_then(|n| n.to_str()).unwrap_or(program)
+}
+
+// user@host:path or host:path, as scp and rsync take them
+fn is_remote_path(arg: &str) -> bool {
+    match arg.split_once(':') {
+        Some((host, _)) => !host.is_empty() && !host.contains('/') && (host.contains('@') || host.contains('.')),
+        None => false,
+    }
+}
+
+fn is_path(arg: &str) -> bool {
+    ["/", "./", "../", "~/"].iter().any(|prefix| arg.starts_with(prefix)) || Path::new(arg).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::command::CommandParser;
+
+    fn route_line(line: &str) -> Routing {
+        let registry = ServiceRegistry::builtin().unwrap();
+        route(&registry, &CommandParser::new().parse(line).unwrap())
+    }
+
+    #[test]
+    fn routes_by_capability_score() {
+        let clone = route_line("git clone https://example.com/repo.git ./repo");
+        assert_eq!(clone.program.as_deref(), Some("git"));
+        let needs: Vec<(&str, u32)> = clone.needs.iter().map(|n| (n.capability, n.weight)).collect();
+        assert_eq!(needs, [(GIT, 2), (NETWORK, 1), (FS, 1)]);
+        let chosen = clone.chosen().unwrap();
+        assert_eq!((chosen.service.as_str(), chosen.score), ("git_agent", 2));
+
+        // docker_agent covers network too, but the narrower agent wins the tie
+        assert_eq!(route_line("curl -s https://example.com").chosen().unwrap().service, "network_agent");
+        assert_eq!(route_line("sudo /usr/bin/docker pull nginx").chosen().unwrap().service, "docker_agent");
+        assert_eq!(route_line("scp notes.txt me@host:backup/").chosen().unwrap().service, "network_agent");
+        assert!(route_line("echo hi").chosen().is_none());
+
+        let lines = clone.to_lines();
+        assert_eq!(lines[0], "🧭 git clone https://example.com/repo.git ./repo → git_agent (score 2)");
+        assert!(lines.iter().any(|l| l.contains("needs network") && l.contains("is a URL")));
+    }
+}
@@ -0,0 +1,101 @@
+// Latency objectives for auto-wiring round trips. When the median or p95 of
+// recent calls goes past its limit, callers stop waiting on the worker and use
+// offline suggestions for a while, rather than every Enter getting slower.
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Recent round trips the percentiles are taken over
+const WINDOW: usize = 50;
+// Too few samples to judge by
+const MIN_SAMPLES: usize = 8;
+// How long a breach keeps callers offline before the worker gets another chance
+const BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breach {
+    pub p50: Duration,
+    pub p95: Duration,
+}
+
+pub struct LatencySlo {
+    // None: no limit
+    p50_limit: Option<Duration>,
+    p95_limit: Option<Duration>,
+    samples: VecDeque<Duration>,
+    // The current breach and when callers go back to the worker
+    breach: Option<(Breach, Instant)>,
+}
+
+impl LatencySlo {
+    // Limits in milliseconds; 0 disables one
+    pub fn new(p50_ms: u64, p95_ms: u64) -> Self {
+        let limit = |ms| (ms > 0).then(|| Duration::from_millis(ms));
+        Self { p50_limit: limit(p50_ms), p95_limit: limit(p95_ms), samples: VecDeque::new(), breach: None }
+    }
+
+    pub fn record(&mut self, latency: Duration, now: Instant) {
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+        if self.breach.is_some() || self.samples.len() < MIN_SAMPLES {
+            return;
+        }
+        let (p50, p95) = (self.percentile(0.5), self.percentile(0.95));
+        let over = |limit: Option<Duration>, value| limit.is_some_and(|limit| value > limit);
+        if over(self.p50_limit, p50) || over(self.p95_limit, p95) {
+            self.breach = Some((Breach { p50, p95 }, now + BACKOFF));
+        }
+    }
+
+    // The breach callers are backing off from, if any; once it's over the
+    // worker starts from a clean slate
+    pub fn breach(&mut self, now: Instant) -> Option<Breach> {
+        match self.breach {
+            Some((_, until)) if now >= until => {
+                self.breach = None;
+                self.samples.clear();
+                None
+            }
+            current => current.map(|(breach, _)| breach),
+        }
+    }
+
+    fn percentile(&self, p: f64) -> Duration {
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort();
+        sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_while_calls_are_slow() {
+        let start = Instant::now();
+        let mut slo = LatencySlo::new(200, 1000);
+        for _ in 0..MIN_SAMPLES {
+            slo.record(Duration::from_millis(50), start);
+        }
+        assert_eq!(slo.breach(start), None);
+
+        // One slow call in ten is within the median but past p95
+        for _ in 0..2 {
+            slo.record(Duration::from_millis(1500), start);
+        }
+        let breach = slo.breach(start).expect("breach");
+        assert_eq!((breach.p50, breach.p95), (Duration::from_millis(50), Duration::from_millis(1500)));
+
+        assert!(slo.breach(start + BACKOFF - Duration::from_secs(1)).is_some());
+        assert_eq!(slo.breach(start + BACKOFF), None);
+
+        // Disabled limits never trip
+        let mut slo = LatencySlo::new(0, 0);
+        for _ in 0..WINDOW {
+            slo.record(Duration::from_secs(5), start);
+        }
+        assert_eq!(slo.breach(start), None);
+    }
+}
@@ -1,14 +1,396 @@
 // AI suggestions
-use anyhow::Result;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 
-pub struct SuggestionEngine;
+use crate::ai::autowire_bridge::AutoWireBridge;
+use crate::ai::community_specs::CommunitySpecs;
+use crate::ai::completion_specs::SpecCache;
+use crate::core::operations::{Cancel, Kind, Operation, Operations};
+use crate::core::variables::Variables;
+use crate::utils::clock;
+use crate::utils::shell_escape::{self, ShellKind};
+use crate::utils::supervisor::{self, Supervisor};
+
+const MAX_COMPLETIONS: usize = 50;
+
+// Suggest-as-you-type: wait for this much idle time before asking the bridge
+const DEBOUNCE: Duration = Duration::from_millis(300);
+// Bridge round-trips at once, counting ones whose request was aborted but
+// which had already started
+const MAX_IN_FLIGHT: usize = 2;
+// Minimum spacing between bridge calls, on top of the debounce
+const MIN_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    // Replaces the whole input line
+    History,
+    // Replace the word being typed
+    Flag,
+    Path,
+    // Subcommands and argument values from a community spec: replace the word
+    // being typed and move on to the next
+    Argument,
+    // `{{name}}` of a session variable: replaces the word being typed
+    Variable,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub text: String,
+    pub description: String,
+    pub kind: CompletionKind,
+}
+
+pub struct SuggestionEngine {
+    // Flags from each command's --help output or man page
+    specs: SpecCache,
+    // Installed specs, which know subcommands and argument values too
+    community: CommunitySpecs,
+    // Path completions are quoted for this shell
+    shell: ShellKind,
+}
 
 impl SuggestionEngine {
+    #[cfg(test)]
     pub fn new() -> Self {
-        Self
+        Self::for_shell(ShellKind::default())
+    }
+
+    pub fn for_shell(shell: ShellKind) -> Self {
+        Self {
+            shell,
+            specs: SpecCache::new(),
+            community: CommunitySpecs::new(),
+        }
+    }
+
+    // Completions for the last word of `input`: from the command's installed
+    // community spec if it has one, otherwise flags come from its --help output
+    // or man page, arguments that look like paths from the filesystem, and
+    // anything else is matched against previously run commands. An open `{{`
+    // completes session variables.
+    pub fn completions(&mut self, input: &str, history: &[String], variables: &Variables) -> Vec<Completion> {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        let current = &input[shell_escape::current_word_start(input)..];
+
+        if let Some(open) = current.rfind("{{").filter(|&open| !current[open..].contains("}}")) {
+            let (before, typed) = (&current[..open], &current[open + 2..]);
+            return variables
+                .values()
+                .iter()
+                .filter(|(name, _)| name.starts_with(typed))
+                .take(MAX_COMPLETIONS)
+                .map(|(name, value)| Completion {
+                    text: format!("{}{{{{{}}}}}", before, name),
+                    description: value.clone(),
+                    kind: CompletionKind::Variable,
+                })
+                .collect();
+        }
+
+        if words.len() > 1 || (!words.is_empty() && current.is_empty()) {
+            let before = &words[1..words.len() - usize::from(!current.is_empty())];
+            if let Some(spec) = self.community.get(words[0]) {
+                let completions: Vec<Completion> = spec.complete(before, current).into_iter().take(MAX_COMPLETIONS).collect();
+                if !completions.is_empty() {
+                    return completions;
+                }
+            }
+        }
+
+        if current.starts_with('-') && !words.is_empty() {
+            let command = words[0];
+            return self.flags_for(command)
+                .into_iter()
+                .filter(|c| c.text.starts_with(current))
+                .take(MAX_COMPLETIONS)
+                .collect();
+        }
+
+        if words.len() > 1 || input.ends_with(' ') {
+            let paths = self.path_completions(current);
+            if !paths.is_empty() {
+                return paths;
+            }
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        history
+            .iter()
+            .rev()
+            .filter(|cmd| cmd.starts_with(input) && cmd.as_str() != input)
+            .filter(|cmd| seen.insert(cmd.as_str()))
+            .take(MAX_COMPLETIONS)
+            .map(|cmd| Completion {
+                text: cmd.clone(),
+                description: "from history".to_string(),
+                kind: CompletionKind::History,
+            })
+            .collect()
+    }
+
+    // Directory entries matching the (possibly quoted) word being typed,
+    // quoted for the active shell
+    fn path_completions(&self, current: &str) -> Vec<Completion> {
+        let typed = self.shell.unquote_partial(current);
+        let (dir, prefix) = match typed.rfind('/') {
+            Some(slash) => (typed[..=slash].to_string(), &typed[slash + 1..]),
+            None => (String::new(), typed.as_str()),
+        };
+        let expanded = shellexpand::tilde(if dir.is_empty() { "." } else { &dir }).into_owned();
+        let Ok(entries) = std::fs::read_dir(&expanded) else {
+            return Vec::new();
+        };
+
+        let mut completions: Vec<Completion> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                    return None;
+                }
+                let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+                let path = format!("{}{}", dir, name);
+                // A leading ~ must stay outside the quotes to be expanded
+                let text = match path.strip_prefix("~/") {
+                    Some(rest) => format!("~/{}", self.shell.quote(rest)),
+                    None => self.shell.quote(&path),
+                };
+                Some(Completion {
+                    text: if is_dir { format!("{}/", text) } else { text },
+                    description: if is_dir { "directory" } else { "file" }.to_string(),
+                    kind: CompletionKind::Path,
+                })
+            })
+            .collect();
+        completions.sort_by(|a, b| a.text.cmp(&b.text));
+        completions.truncate(MAX_COMPLETIONS);
+        completions
+    }
+
+    fn flags_for(&mut self, command: &str) -> Vec<Completion> {
+        let Some(spec) = self.specs.get(command) else {
+            return Vec::new();
+        };
+        spec.flags
+            .iter()
+            .flat_map(|flag| {
+                flag.names.iter().map(|name| Completion {
+                    text: name.clone(),
+                    description: flag.description.clone(),
+                    kind: CompletionKind::Flag,
+                })
+            })
+            .collect()
+    }
+}
+
+// Debounced, rate-limited scheduler for auto-wire suggestions while typing.
+// Every input change bumps a generation; results from older generations are
+// stale and dropped, and their requests aborted where possible.
+pub struct SuggestionScheduler {
+    input: String,
+    changed_at: Instant,
+    generation: u64,
+    requested: Option<String>,
+    last_request: Option<Instant>,
+    in_flight: Vec<(u64, JoinHandle<()>, Operation)>,
+    // Blocking jobs still running, aborted or not
+    running: Arc<AtomicUsize>,
+    // A request's suggestions, or what it panicked with
+    tx: mpsc::UnboundedSender<(u64, Result<Vec<String>, String>)>,
+    rx: mpsc::UnboundedReceiver<(u64, Result<Vec<String>, String>)>,
+    current: Vec<String>,
+    project_notes: Option<String>,
+    supervisor: Supervisor,
+    operations: Operations,
+}
+
+impl SuggestionScheduler {
+    pub fn new(operations: Operations) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            input: String::new(),
+            changed_at: clock::now(),
+            generation: 0,
+            requested: None,
+            last_request: None,
+            in_flight: Vec::new(),
+            running: Arc::new(AtomicUsize::new(0)),
+            tx,
+            rx,
+            current: Vec::new(),
+            project_notes: None,
+            supervisor: Supervisor::new("Live suggestions"),
+            operations,
+        }
+    }
+
+    pub fn set_project_notes(&mut self, notes: Option<String>) {
+        self.project_notes = notes;
     }
 
-    pub fn get_suggestions(&self, input: &str) -> Vec<String> {
-        vec![]
+    pub fn input_changed(&mut self, input: &str) {
+        if input == self.input {
+            return;
+        }
+        self.input = input.to_string();
+        self.changed_at = clock::now();
+        self.generation += 1;
+        self.current.clear();
+
+        // Anything still running is for an older input
+        let generation = self.generation;
+        self.in_flight.retain(|(g, handle, _)| {
+            if *g < generation {
+                handle.abort();
+            }
+            *g >= generation
+        });
+    }
+
+    // Collects finished requests and starts a new one when the input has been idle
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
+    pub fn poll(&mut self) {
+        while let Ok((generation, reply)) = self.rx.try_recv() {
+            match reply {
+                Ok(suggestions) if generation == self.generation => self.current = suggestions,
+                Ok(_) => {}
+                Err(message) => self.supervisor.panicked(&message),
+            }
+        }
+        self.in_flight.retain(|(_, handle, _)| !handle.is_finished());
+        if self.supervisor.is_disabled() {
+            return;
+        }
+
+        let idle = clock::since(self.changed_at) >= DEBOUNCE;
+        let spaced = self.last_request.is_none_or(|t| clock::since(t) >= MIN_INTERVAL);
+        let fresh = self.requested.as_deref() != Some(self.input.as_str());
+        let busy = self.running.load(Ordering::SeqCst) >= MAX_IN_FLIGHT;
+        if !idle || !spaced || !fresh || busy || self.input.trim().is_empty() {
+            return;
+        }
+
+        let generation = self.generation;
+        let input = self.input.clone();
+        let tx = self.tx.clone();
+        let notes = self.project_notes.clone();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let running = Running::start(&self.running);
+        // Started here rather than in the task, so it runs (and stops counting) even if the task is
+        // aborted before its first poll
+        let job = tokio::task::spawn_blocking({
+            let cancelled = cancelled.clone();
+            move || {
+                let _running = running;
+                // Aborted while it waited for a blocking thread
+                if cancelled.load(Ordering::SeqCst) {
+                    return Ok(Ok(None));
+                }
+                supervisor::catch(|| AutoWireBridge::run_autowire_script(&input, notes.as_deref()))
+            }
+        });
+        let handle = tokio::spawn(async move {
+            let _cancel = CancelOnDrop(cancelled);
+            match job.await {
+                Ok(Ok(Ok(Some(result)))) => {
+                    let _ = tx.send((generation, Ok(result.suggestions)));
+                }
+                Ok(Err(message)) => {
+                    let _ = tx.send((generation, Err(message)));
+                }
+                _ => {}
+            }
+        });
+
+        self.requested = Some(self.input.clone());
+        self.last_request = Some(clock::now());
+        let operation = self.operations.start(Kind::Ai, format!("Suggestions for `{}`", self.input));
+        operation.cancel_with(Cancel::Abort(handle.abort_handle()));
+        self.in_flight.push((generation, handle, operation));
+    }
+
+    // Suggestions for the current input, once they have arrived
+    pub fn current(&self) -> &[String] {
+        &self.current
+    }
+}
+
+// Counts a blocking job as running until it's dropped
+struct Running(Arc<AtomicUsize>);
+
+impl Running {
+    fn start(running: &Arc<AtomicUsize>) -> Self {
+        running.fetch_add(1, Ordering::SeqCst);
+        Self(running.clone())
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Tells a request's blocking job its task is gone, aborted or done
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_braces_complete_variables() {
+        let mut variables = Variables::default();
+        variables.set("region=eu-west-1").unwrap();
+        variables.set("replicas=3").unwrap();
+        let mut engine = SuggestionEngine::new();
+
+        let texts = |completions: Vec<Completion>| completions.into_iter().map(|c| c.text).collect::<Vec<_>>();
+        assert_eq!(
+            texts(engine.completions("aws --region={{r", &[], &variables)),
+            ["--region={{region}}", "--region={{replicas}}"]
+        );
+        let region = engine.completions("echo {{reg", &[], &variables);
+        assert_eq!(region[0].description, "eu-west-1");
+        assert!(engine.completions("echo {{region}}", &[], &variables).is_empty());
+    }
+
+    #[tokio::test]
+    async fn waits_for_typing_to_settle() {
+        let (clock, _guard) = clock::mock();
+        let mut scheduler = SuggestionScheduler::new(Operations::new());
+        scheduler.input_changed("git st");
+        scheduler.poll();
+        assert_eq!(scheduler.requested, None);
+
+        clock.advance(DEBOUNCE);
+        scheduler.poll();
+        assert_eq!(scheduler.requested.as_deref(), Some("git st"));
+
+        // Settled again, but too soon after the last request
+        scheduler.input_changed("git status");
+        clock.advance(DEBOUNCE);
+        scheduler.poll();
+        assert_eq!(scheduler.requested.as_deref(), Some("git st"));
+        clock.advance(MIN_INTERVAL - DEBOUNCE);
+        scheduler.poll();
+        assert_eq!(scheduler.requested.as_deref(), Some("git status"));
+
+        // Never polled, so no worker gets asked
+        for (_, handle, _) in &scheduler.in_flight {
+            handle.abort();
+        }
     }
 }
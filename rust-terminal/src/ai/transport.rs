@@ -0,0 +1,666 @@
+// How AutoWireBridge reaches the Python auto-wiring system, chosen with
+// `transport` under [autowire]:
+//
+//   stdio  a worker process NexTerm starts and supervises itself (the
+//          default, see autowire_daemon)
+//   unix   a server listening on the socket at `endpoint`: one connection
+//          per call, one frame each way, framed like the worker's
+//   http   POST {"method", "params"} to the URL at `endpoint`, answered with
+//          {"result": ...} or {"error": "..."}
+//   grpc   unary /nexterm.AutoWire/Call at `endpoint`, with the same JSON
+//          documents as messages instead of protobuf; needs a build with
+//          `--features grpc`
+//
+// The remote ones send `auth_token` (environment variables expanded, e.g.
+// "$AUTOWIRE_TOKEN") as a bearer token: the Authorization header over http
+// and grpc, an "auth" field in the request frame over a unix socket. Each
+// transport starts with the handshake in ai::protocol.
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::BufReader;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+use crate::ai::autowire_daemon::{self, AutoWireConfig, AutoWireDaemon, Health};
+use crate::ai::protocol::{self, Capabilities};
+use crate::ai::slo::{Breach, LatencySlo};
+use crate::utils::clock;
+use crate::utils::error::NexTermError;
+use crate::utils::supervisor;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    #[default]
+    Stdio,
+    Unix,
+    Http,
+    Grpc,
+}
+
+impl fmt::Display for TransportKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TransportKind::Stdio => "stdio",
+            TransportKind::Unix => "unix",
+            TransportKind::Http => "http",
+            TransportKind::Grpc => "grpc",
+        };
+        f.write_str(name)
+    }
+}
+
+pub type Reply = Box<dyn FnOnce(Result<Value, String>) + Send>;
+
+pub trait BridgeTransport: Send + Sync {
+    // Doesn't wait: `reply` runs once the answer, or an error, comes in
+    fn call_with(&self, method: &str, params: Value, reply: Reply) -> Result<()>;
+
+    fn health(&self) -> Health;
+
+    // False when set up with an error, e.g. no interpreter for a local worker
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    // Recent output of a local worker and its restarts
+    fn logs(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    // Stops a local worker while the user is idle
+    fn suspend(&self) {}
+
+    fn is_suspended(&self) -> bool {
+        false
+    }
+}
+
+// The configured transport plus what every transport gets: the handshake,
+// timeouts, blocking and async calls, and the latency SLO
+pub struct Transport {
+    kind: TransportKind,
+    inner: Box<dyn BridgeTransport>,
+    timeout: Duration,
+    slo: Arc<Mutex<LatencySlo>>,
+    negotiation: Arc<Mutex<Negotiation>>,
+}
+
+enum Negotiation {
+    // Not asked yet, or the last handshake didn't get through
+    Unknown,
+    Asking,
+    Agreed(Capabilities),
+    // Every call fails with this
+    Mismatch(String),
+}
+
+static SHARED: OnceLock<Arc<Transport>> = OnceLock::new();
+
+// Sets up the process-wide transport from the config; the first call wins.
+// A transport that can't be set up stays down, with the error here.
+pub fn configure(config: &AutoWireConfig) -> Result<()> {
+    let mut result = Ok(());
+    SHARED.get_or_init(|| {
+        let (transport, connected) = build(config);
+        result = connected;
+        transport
+    });
+    result
+}
+
+// The configured transport, or the default one if nothing configured it
+pub fn shared() -> Arc<Transport> {
+    SHARED.get_or_init(|| build(&AutoWireConfig::default()).0).clone()
+}
+
+fn build(config: &AutoWireConfig) -> (Arc<Transport>, Result<()>) {
+    let (inner, result) = match connect(config) {
+        Ok(inner) => (inner, Ok(())),
+        Err(e) => (Box::new(Unavailable(format!("{:#}", e))) as Box<dyn BridgeTransport>, Err(e)),
+    };
+    let transport = Transport {
+        kind: config.transport,
+        inner,
+        timeout: Duration::from_millis(config.timeout_ms),
+        slo: Arc::new(Mutex::new(LatencySlo::new(config.slo_p50_ms, config.slo_p95_ms))),
+        negotiation: Arc::new(Mutex::new(Negotiation::Unknown)),
+    };
+    transport.negotiate();
+    (Arc::new(transport), result)
+}
+
+fn connect(config: &AutoWireConfig) -> Result<Box<dyn BridgeTransport>> {
+    let endpoint = || {
+        config
+            .endpoint
+            .clone()
+            .with_context(|| format!("the {} transport needs `endpoint` under [autowire]", config.transport))
+    };
+    let token = config.auth_token.as_deref().map(expand_token).transpose()?;
+    let timeout = Duration::from_millis(config.timeout_ms);
+    Ok(match config.transport {
+        TransportKind::Stdio => {
+            let python = autowire_daemon::interpreter(config);
+            let found = Command::new(&python).arg("--version").output().is_ok_and(|o| o.status.success());
+            info!("Python available: {} ({})", found, python.display());
+            match (found, &config.python) {
+                (true, _) => Box::new(Stdio(AutoWireDaemon::shared(config))),
+                // Only a configured interpreter that's missing is an error
+                (false, Some(_)) => bail!("`python` under [autowire]: {} doesn't run", python.display()),
+                (false, None) => Box::new(Unavailable(format!("{} not found", python.display()))),
+            }
+        }
+        TransportKind::Unix => Box::new(UnixTransport {
+            path: PathBuf::from(shellexpand::tilde(&endpoint()?).into_owned()),
+            token,
+            timeout,
+            link: Arc::new(Link::new(endpoint()?)),
+        }),
+        TransportKind::Http => Box::new(HttpTransport::new(endpoint()?, token, timeout)?),
+        #[cfg(feature = "grpc")]
+        TransportKind::Grpc => Box::new(grpc::GrpcTransport::new(endpoint()?, token, timeout)?),
+        #[cfg(not(feature = "grpc"))]
+        TransportKind::Grpc => bail!("the grpc transport needs a build with `--features grpc`"),
+    })
+}
+
+fn expand_token(token: &str) -> Result<String> {
+    shellexpand::env(token)
+        .map(|t| t.into_owned())
+        .map_err(|e| anyhow!("auth_token: {}", e))
+}
+
+impl Transport {
+    pub fn kind(&self) -> TransportKind {
+        self.kind
+    }
+
+    pub fn call_with(
+        &self,
+        method: &str,
+        params: Value,
+        reply: impl FnOnce(Result<Value, String>) + Send + 'static,
+    ) -> Result<()> {
+        if let Negotiation::Mismatch(reason) = &*self.negotiation.lock().map_err(|_| anyhow!("transport lock poisoned"))? {
+            return Err(NexTermError::ProtocolMismatch(reason.clone()).into());
+        }
+        // Calls don't wait for the handshake; a retry goes out with them
+        self.negotiate();
+        let sent = clock::now();
+        let timeout = self.timeout;
+        let slo = self.slo.clone();
+        self.inner.call_with(method, params, Box::new(move |result| {
+            // Late replies to timed-out calls count as the timeout
+            if let Ok(mut slo) = slo.lock() {
+                slo.record(clock::since(sent).min(timeout), clock::now());
+            }
+            // A panicking handler must not take a transport thread (or the main loop) with it
+            if let Err(message) = supervisor::catch(|| reply(result)) {
+                supervisor::fault("Auto-wiring reply handler", &message);
+            }
+        }))
+    }
+
+    // Waits on the calling thread, for synchronous callers and spawn_blocking
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all, fields(method = %method)))]
+    pub fn call_blocking(&self, method: &str, params: Value) -> Result<Value> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.call_with(method, params, move |reply| {
+            let _ = tx.send(reply);
+        })?;
+        match rx.recv_timeout(self.timeout) {
+            Ok(reply) => reply.map_err(|e| self.reply_error(method, e)),
+            Err(_) => Err(self.timed_out(method)),
+        }
+    }
+
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all, fields(method = %method)))]
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.call_with(method, params, move |reply| {
+            let _ = tx.send(reply);
+        })?;
+        match tokio::time::timeout(self.timeout, rx).await {
+            Ok(Ok(reply)) => reply.map_err(|e| self.reply_error(method, e)),
+            Ok(Err(_)) => bail!("auto-wiring ({}) dropped {}", self.kind, method),
+            Err(_) => Err(self.timed_out(method)),
+        }
+    }
+
+    fn timed_out(&self, method: &str) -> anyhow::Error {
+        NexTermError::BridgeTimeout { transport: self.kind.to_string(), method: method.to_string() }.into()
+    }
+
+    // The worker's error, or the daemon giving up on the reply
+    fn reply_error(&self, method: &str, error: String) -> anyhow::Error {
+        if error == autowire_daemon::TIMED_OUT {
+            self.timed_out(method)
+        } else {
+            anyhow!(error)
+        }
+    }
+
+    // Cheap enough to poll every frame
+    pub fn health(&self) -> Health {
+        if let Ok(negotiation) = self.negotiation.lock() {
+            if let Negotiation::Mismatch(reason) = &*negotiation {
+                return Health::Down(format!("protocol mismatch: {}", reason));
+            }
+        }
+        self.inner.health()
+    }
+
+    // Whether the server offers a protocol feature; false until the
+    // handshake is done
+    pub fn supports(&self, feature: &str) -> bool {
+        self.negotiate();
+        matches!(&*self.negotiation.lock().unwrap_or_else(|e| e.into_inner()), Negotiation::Agreed(caps) if caps.supports(feature))
+    }
+
+    // Sends the handshake unless it's done or on its way
+    fn negotiate(&self) {
+        match self.negotiation.lock() {
+            Ok(mut state) if matches!(*state, Negotiation::Unknown) => *state = Negotiation::Asking,
+            _ => return,
+        }
+        let negotiation = self.negotiation.clone();
+        let sent = self.inner.call_with("handshake", protocol::handshake_params(), Box::new(move |reply| {
+            let next = match reply {
+                Ok(reply) => match protocol::negotiate(&reply) {
+                    Ok(caps) => {
+                        info!("Auto-wiring protocol {} with {:?}", caps.protocol_version, caps.features);
+                        Negotiation::Agreed(caps)
+                    }
+                    Err(reason) => Negotiation::Mismatch(reason),
+                },
+                Err(e) if protocol::predates_handshake(&e) => {
+                    Negotiation::Mismatch("the auto-wiring system predates protocol versioning".to_string())
+                }
+                Err(_) => Negotiation::Unknown,
+            };
+            if let Negotiation::Mismatch(reason) = &next {
+                error!("Auto-wiring protocol mismatch: {}", reason);
+            }
+            if let Ok(mut state) = negotiation.lock() {
+                *state = next;
+            }
+        }));
+        if sent.is_err() {
+            if let Ok(mut state) = self.negotiation.lock() {
+                *state = Negotiation::Unknown;
+            }
+        }
+    }
+
+    // Set while recent round trips are over the configured limits; callers
+    // that would block on the transport use offline suggestions instead
+    pub fn slo_breach(&self) -> Option<Breach> {
+        self.slo.lock().ok().and_then(|mut slo| slo.breach(clock::now()))
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.inner.is_available()
+    }
+
+    pub fn logs(&self) -> Vec<String> {
+        self.inner.logs()
+    }
+
+    pub fn suspend(&self) {
+        self.inner.suspend();
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.inner.is_suspended()
+    }
+}
+
+// A reply document: {"result": ...} or {"error": "..."}
+pub fn into_reply(message: &Value) -> Result<Value, String> {
+    match message.get("error") {
+        Some(error) => Err(error.as_str().unwrap_or("unknown error").to_string()),
+        None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+    }
+}
+
+struct Stdio(&'static AutoWireDaemon);
+
+impl BridgeTransport for Stdio {
+    fn call_with(&self, method: &str, params: Value, reply: Reply) -> Result<()> {
+        self.0.call_with(method, params, reply)
+    }
+
+    fn health(&self) -> Health {
+        self.0.health()
+    }
+
+    fn logs(&self) -> Vec<String> {
+        self.0.logs()
+    }
+
+    fn suspend(&self) {
+        self.0.suspend();
+    }
+
+    fn is_suspended(&self) -> bool {
+        self.0.is_suspended()
+    }
+}
+
+// Set up with an error: every call fails with it
+struct Unavailable(String);
+
+impl BridgeTransport for Unavailable {
+    fn call_with(&self, _method: &str, _params: Value, _reply: Reply) -> Result<()> {
+        Err(NexTermError::BridgeUnavailable(self.0.clone()).into())
+    }
+
+    fn health(&self) -> Health {
+        Health::Down(self.0.clone())
+    }
+
+    fn is_available(&self) -> bool {
+        false
+    }
+}
+
+// Health of a remote server, from how the last call went
+struct Link {
+    endpoint: String,
+    health: Mutex<Health>,
+}
+
+impl Link {
+    fn new(endpoint: String) -> Self {
+        let health = Mutex::new(Health::Remote { endpoint: endpoint.clone(), latency: None });
+        Self { endpoint, health }
+    }
+
+    // Transport failures mark the server down; errors it answered with don't
+    fn finished(&self, sent: Instant, outcome: &Result<Result<Value, String>, String>) {
+        let health = match outcome {
+            Ok(_) => Health::Remote { endpoint: self.endpoint.clone(), latency: Some(clock::since(sent)) },
+            Err(reason) => Health::Down(reason.clone()),
+        };
+        if let Ok(mut current) = self.health.lock() {
+            *current = health;
+        }
+    }
+
+    fn health(&self) -> Health {
+        self.health.lock().map(|h| h.clone()).unwrap_or(Health::Starting)
+    }
+}
+
+struct UnixTransport {
+    path: PathBuf,
+    token: Option<String>,
+    timeout: Duration,
+    link: Arc<Link>,
+}
+
+impl BridgeTransport for UnixTransport {
+    fn call_with(&self, method: &str, params: Value, reply: Reply) -> Result<()> {
+        let request = json!({ "id": 1, "method": method, "params": params, "auth": self.token });
+        let (path, timeout, link) = (self.path.clone(), self.timeout, self.link.clone());
+        std::thread::spawn(move || {
+            let sent = clock::now();
+            let outcome = unix_call(&path, &request, timeout).map_err(|e| format!("{} unreachable: {}", path.display(), e));
+            link.finished(sent, &outcome);
+            reply(outcome.and_then(|reply| reply));
+        });
+        Ok(())
+    }
+
+    fn health(&self) -> Health {
+        self.link.health()
+    }
+}
+
+fn unix_call(path: &PathBuf, request: &Value, timeout: Duration) -> std::io::Result<Result<Value, String>> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    autowire_daemon::write_frame(&mut stream, request)?;
+    match autowire_daemon::read_frame(&mut BufReader::new(stream))? {
+        Some(message) => Ok(into_reply(&message)),
+        None => Err(std::io::ErrorKind::UnexpectedEof.into()),
+    }
+}
+
+struct HttpTransport {
+    url: String,
+    token: Option<String>,
+    client: reqwest::Client,
+    // Calls come from the UI thread and blocking threads alike, so the
+    // requests run on a runtime of their own
+    runtime: tokio::runtime::Runtime,
+    link: Arc<Link>,
+}
+
+impl HttpTransport {
+    fn new(url: String, token: Option<String>, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("autowire-http")
+            .enable_all()
+            .build()?;
+        Ok(Self { link: Arc::new(Link::new(url.clone())), url, token, client, runtime })
+    }
+}
+
+impl BridgeTransport for HttpTransport {
+    fn call_with(&self, method: &str, params: Value, reply: Reply) -> Result<()> {
+        let mut request = self.client.post(&self.url).json(&json!({ "method": method, "params": params }));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let link = self.link.clone();
+        self.runtime.spawn(async move {
+            let sent = clock::now();
+            let outcome = async {
+                let message: Value = request.send().await?.error_for_status()?.json().await?;
+                Ok::<_, reqwest::Error>(into_reply(&message))
+            }
+            .await
+            .map_err(|e| e.to_string());
+            link.finished(sent, &outcome);
+            reply(outcome.and_then(|reply| reply));
+        });
+        Ok(())
+    }
+
+    fn health(&self) -> Health {
+        self.link.health()
+    }
+}
+
+#[cfg(feature = "grpc")]
+mod grpc {
+    use super::*;
+    use bytes::{Buf, BufMut};
+    use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic::transport::{Channel, Endpoint};
+    use tonic::Status;
+
+    const CALL: &str = "/nexterm.AutoWire/Call";
+
+    pub struct GrpcTransport {
+        channel: Channel,
+        token: Option<String>,
+        runtime: tokio::runtime::Runtime,
+        link: Arc<Link>,
+    }
+
+    impl GrpcTransport {
+        pub fn new(endpoint: String, token: Option<String>, timeout: Duration) -> Result<Self> {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("autowire-grpc")
+                .enable_all()
+                .build()?;
+            // Connects on first use, and again after the server goes away
+            let channel = {
+                let _context = runtime.enter();
+                Endpoint::from_shared(endpoint.clone())?.timeout(timeout).connect_lazy()
+            };
+            Ok(Self { channel, token, runtime, link: Arc::new(Link::new(endpoint)) })
+        }
+    }
+
+    impl BridgeTransport for GrpcTransport {
+        fn call_with(&self, method: &str, params: Value, reply: Reply) -> Result<()> {
+            let mut request = tonic::Request::new(json!({ "method": method, "params": params }));
+            if let Some(token) = &self.token {
+                request.metadata_mut().insert("authorization", format!("Bearer {}", token).parse()?);
+            }
+            let (channel, link) = (self.channel.clone(), self.link.clone());
+            self.runtime.spawn(async move {
+                let sent = clock::now();
+                let mut client = tonic::client::Grpc::new(channel);
+                let outcome = async {
+                    client.ready().await.map_err(|e| Status::unavailable(e.to_string()))?;
+                    let response = client.unary(request, PathAndQuery::from_static(CALL), JsonCodec).await?;
+                    Ok::<_, Status>(into_reply(response.get_ref()))
+                }
+                .await
+                .map_err(|status| status.message().to_string());
+                link.finished(sent, &outcome);
+                reply(outcome.and_then(|reply| reply));
+            });
+            Ok(())
+        }
+
+        fn health(&self) -> Health {
+            self.link.health()
+        }
+    }
+
+    // Messages are JSON documents rather than protobuf
+    #[derive(Clone, Copy, Default)]
+    struct JsonCodec;
+
+    impl Codec for JsonCodec {
+        type Encode = Value;
+        type Decode = Value;
+        type Encoder = JsonCodec;
+        type Decoder = JsonCodec;
+
+        fn encoder(&mut self) -> JsonCodec {
+            JsonCodec
+        }
+
+        fn decoder(&mut self) -> JsonCodec {
+            JsonCodec
+        }
+    }
+
+    impl Encoder for JsonCodec {
+        type Item = Value;
+        type Error = Status;
+
+        fn encode(&mut self, item: Value, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+            serde_json::to_writer(dst.writer(), &item).map_err(|e| Status::internal(e.to_string()))
+        }
+    }
+
+    impl Decoder for JsonCodec {
+        type Item = Value;
+        type Error = Status;
+
+        fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Value>, Status> {
+            serde_json::from_reader(src.reader()).map(Some).map_err(|e| Status::internal(e.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    // Answers `count` connections, one frame each
+    fn serve(
+        listener: UnixListener,
+        count: usize,
+        answer: impl Fn(&Value) -> Value + Send + 'static,
+    ) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || {
+            for _ in 0..count {
+                let (mut stream, _) = listener.accept().unwrap();
+                let request = autowire_daemon::read_frame(&mut stream).unwrap().unwrap();
+                let reply = json!({ "id": request["id"], "result": answer(&request) });
+                autowire_daemon::write_frame(&mut stream, &reply).unwrap();
+            }
+        })
+    }
+
+    fn unix_config(path: &std::path::Path) -> AutoWireConfig {
+        AutoWireConfig {
+            transport: TransportKind::Unix,
+            endpoint: Some(path.display().to_string()),
+            ..AutoWireConfig::default()
+        }
+    }
+
+    fn wait_for(condition: impl Fn() -> bool) {
+        for _ in 0..200 {
+            if condition() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("condition not met in time");
+    }
+
+    #[test]
+    fn unix_transport_negotiates_and_sends_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("autowire.sock");
+        let server = serve(UnixListener::bind(&path).unwrap(), 2, |request| match request["method"].as_str() {
+            Some("handshake") => json!({ "protocol_version": 1, "features": ["services"] }),
+            _ => json!([request["method"], request["auth"]]),
+        });
+
+        std::env::set_var("NEXTERM_TEST_AUTOWIRE_TOKEN", "s3cret");
+        let config = AutoWireConfig {
+            auth_token: Some("$NEXTERM_TEST_AUTOWIRE_TOKEN".to_string()),
+            ..unix_config(&path)
+        };
+        let (transport, connected) = build(&config);
+        connected.unwrap();
+        assert_eq!(transport.call_blocking("ping", json!({})).unwrap(), json!(["ping", "s3cret"]));
+        server.join().unwrap();
+        wait_for(|| transport.supports(protocol::SERVICES));
+        assert!(!transport.supports(protocol::FLAG_HINTS));
+        assert!(matches!(transport.health(), Health::Remote { latency: Some(_), .. }));
+
+        // Nothing listening any more
+        drop(dir);
+        assert!(transport.call_blocking("ping", json!({})).is_err());
+        assert!(matches!(transport.health(), Health::Down(_)));
+    }
+
+    #[test]
+    fn refuses_servers_with_another_protocol() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("autowire.sock");
+        let server = serve(UnixListener::bind(&path).unwrap(), 1, |_| json!({ "protocol_version": 0 }));
+        let (transport, _) = build(&unix_config(&path));
+        server.join().unwrap();
+        wait_for(|| matches!(transport.health(), Health::Down(_)));
+
+        let error = transport.call_blocking("ping", json!({})).unwrap_err();
+        assert_eq!(error.downcast_ref::<NexTermError>().map(NexTermError::code), Some("NT202"));
+        assert!(!transport.supports(protocol::SERVICES));
+    }
+}
@@ -0,0 +1,549 @@
+// Named actions for the command palette (Ctrl+P) and key bindings, in one
+// registry whoever contributes them: NexTerm itself, workflows, plugins and
+// auto-wiring agents. An action runs a command line (builtins included) or
+// invokes a service. Contributors may suggest a key for it; keybindings.toml
+// in the config directory binds keys to action ids and wins over those:
+//
+//   "Alt+D" = "workflow.deploy"
+//   "Ctrl+K" = "nexterm.review"
+//   "Ctrl+X G" = "nexterm.review"
+//
+// The last is a sequence of chords, Ctrl+X then G; after the first the
+// registry lists what may follow for the which-key popup. Keys NexTerm
+// already uses stay with NexTerm, even in keybindings.toml, and keys without
+// Ctrl or Alt (other than F-keys) are left for typing, though not after a
+// prefix.
+//
+// An action can ask for inputs first, in a form; `{name}` in its command
+// line is replaced by the answer.
+use anyhow::{bail, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// Keys main.rs handles itself, before any binding, so no action can take them
+const RESERVED: &[(KeyCode, KeyModifiers)] = &[
+    (KeyCode::Char('c'), KeyModifiers::CONTROL),
+    (KeyCode::Char('d'), KeyModifiers::CONTROL),
+    (KeyCode::Char('t'), KeyModifiers::CONTROL),
+    (KeyCode::Char('w'), KeyModifiers::CONTROL),
+    (KeyCode::Tab, KeyModifiers::CONTROL),
+    // Split panes
+    (KeyCode::Char('d'), KeyModifiers::CONTROL.union(KeyModifiers::SHIFT)),
+    (KeyCode::Char('h'), KeyModifiers::CONTROL.union(KeyModifiers::SHIFT)),
+    (KeyCode::Char('f'), KeyModifiers::CONTROL),
+    (KeyCode::Char('r'), KeyModifiers::CONTROL),
+    (KeyCode::Char('g'), KeyModifiers::CONTROL),
+    (KeyCode::Char('y'), KeyModifiers::CONTROL),
+    (KeyCode::Char('p'), KeyModifiers::CONTROL),
+    (KeyCode::Char('a'), KeyModifiers::CONTROL),
+    (KeyCode::Char('s'), KeyModifiers::CONTROL),
+    (KeyCode::Char('n'), KeyModifiers::CONTROL),
+    (KeyCode::Char(' '), KeyModifiers::CONTROL),
+    (KeyCode::Char(']'), KeyModifiers::CONTROL),
+    // Suspends NexTerm
+    (KeyCode::Char('z'), KeyModifiers::CONTROL),
+    (KeyCode::Char('='), KeyModifiers::CONTROL),
+    (KeyCode::Char('-'), KeyModifiers::CONTROL),
+    (KeyCode::Char('0'), KeyModifiers::CONTROL),
+    (KeyCode::Up, KeyModifiers::CONTROL),
+    (KeyCode::Down, KeyModifiers::CONTROL),
+    (KeyCode::Char('o'), KeyModifiers::ALT),
+    (KeyCode::Char('l'), KeyModifiers::ALT),
+    (KeyCode::Char('i'), KeyModifiers::ALT),
+    (KeyCode::Left, KeyModifiers::ALT),
+    (KeyCode::Right, KeyModifiers::ALT),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    // As if typed and run: `workflow run deploy`, `jira open`
+    Command(String),
+    // An auto-wiring service, invoked as from the panel
+    Service(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Action {
+    // By contributor: nexterm.review, workflow.deploy, plugin.jira.open, agent.git_agent
+    pub id: String,
+    pub title: String,
+    pub icon: String,
+    // Also matched by the palette
+    pub keywords: Vec<String>,
+    pub target: Target,
+    // Suggested by the contributor
+    pub key: Option<KeyChord>,
+    // Asked for before a command runs
+    pub inputs: Vec<Input>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Input {
+    pub name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub kind: InputKind,
+    // For a select
+    #[serde(default)]
+    pub options: Vec<String>,
+    // The text or option to start with; "true" checks a checkbox
+    #[serde(default)]
+    pub default: Option<String>,
+    // What a checked checkbox puts in, `--<name>` unless given
+    #[serde(default)]
+    pub flag: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputKind {
+    #[default]
+    Text,
+    Select,
+    Checkbox,
+}
+
+// `command` with each `{name}` replaced by its answer, quoted by `quote`; an
+// unchecked checkbox leaves nothing
+pub fn fill(command: &str, inputs: &[Input], answers: &BTreeMap<String, String>, quote: impl Fn(&str) -> String) -> String {
+    inputs.iter().fold(command.to_string(), |command, input| {
+        let answer = answers.get(&input.name).map(String::as_str).unwrap_or_default();
+        let value = match input.kind {
+            InputKind::Checkbox if answer == "true" => {
+                input.flag.clone().unwrap_or_else(|| format!("--{}", input.name))
+            }
+            InputKind::Checkbox => String::new(),
+            _ => quote(answer),
+        };
+        command.replace(&format!("{{{}}}", input.name), &value)
+    })
+}
+
+impl Action {
+    pub fn new(id: &str, title: &str, target: Target) -> Self {
+        Self {
+            id: id.to_string(),
+            title: title.to_string(),
+            icon: "▸".to_string(),
+            keywords: Vec::new(),
+            target,
+            key: None,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn with_icon(mut self, icon: &str) -> Self {
+        self.icon = icon.to_string();
+        self
+    }
+
+    pub fn with_keywords<S: AsRef<str>>(mut self, keywords: &[S]) -> Self {
+        self.keywords.extend(keywords.iter().map(|k| k.as_ref().to_string()));
+        self
+    }
+
+    pub fn with_key(mut self, key: Option<KeyChord>) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn with_inputs(mut self, inputs: Vec<Input>) -> Self {
+        self.inputs = inputs;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    // Letters lowercase with Shift as a modifier, however the terminal sent them
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) if c.is_ascii_uppercase() => {
+                Self { code: KeyCode::Char(c.to_ascii_lowercase()), modifiers: modifiers | KeyModifiers::SHIFT }
+            }
+            KeyCode::BackTab => Self { code: KeyCode::Tab, modifiers: modifiers | KeyModifiers::SHIFT },
+            _ => Self { code, modifiers },
+        }
+    }
+
+    // "Ctrl+Shift+K", "Alt+Enter", "F5"
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        // "Ctrl++"
+        if text.ends_with("++") {
+            parts.truncate(parts.len() - 2);
+            parts.push("+");
+        }
+        let key = parts.pop().filter(|k| !k.is_empty()).with_context(|| format!("No key in {:?}", text))?;
+        let mut modifiers = KeyModifiers::NONE;
+        for part in parts {
+            modifiers |= match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "option" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("Unknown modifier {:?} in {:?}", part, text),
+            };
+        }
+        let code = match key.to_ascii_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "esc" | "escape" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            lower => match (lower.chars().next(), lower.chars().count()) {
+                (Some(c), 1) => KeyCode::Char(c),
+                (Some('f'), _) => KeyCode::F(lower[1..].parse().with_context(|| format!("Unknown key {:?}", key))?),
+                _ => bail!("Unknown key {:?} in {:?}", key, text),
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+
+    pub fn label(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        for (modifier, name) in [(KeyModifiers::CONTROL, "Ctrl"), (KeyModifiers::ALT, "Alt"), (KeyModifiers::SHIFT, "Shift")] {
+            if self.modifiers.contains(modifier) {
+                parts.push(name.to_string());
+            }
+        }
+        parts.push(match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_ascii_uppercase().to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            KeyCode::PageUp => "PageUp".to_string(),
+            KeyCode::PageDown => "PageDown".to_string(),
+            code => format!("{:?}", code),
+        });
+        parts.join("+")
+    }
+
+    // NexTerm's own, see RESERVED
+    pub fn is_reserved(&self) -> bool {
+        RESERVED.iter().any(|&(code, modifiers)| Self::new(code, modifiers) == *self)
+    }
+
+    // Would get in the way of typing
+    pub fn is_typing(&self) -> bool {
+        !self.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) && !matches!(self.code, KeyCode::F(_))
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+// One chord or several pressed in turn, "Ctrl+X G"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySequence(Vec<KeyChord>);
+
+impl KeySequence {
+    pub fn parse(text: &str) -> Result<Self> {
+        let chords = text.split_whitespace().map(KeyChord::parse).collect::<Result<Vec<_>>>()?;
+        if chords.is_empty() {
+            bail!("No key in {:?}", text);
+        }
+        Ok(Self(chords))
+    }
+
+    pub fn label(&self) -> String {
+        self.0.iter().map(KeyChord::label).collect::<Vec<_>>().join(" ")
+    }
+
+    fn first(&self) -> KeyChord {
+        self.0[0]
+    }
+
+    fn starts_with(&self, pressed: &[KeyChord]) -> bool {
+        self.0.starts_with(pressed)
+    }
+
+    // Either waits for the other, so they can't both be bound
+    fn overlaps(&self, other: &KeySequence) -> bool {
+        self.starts_with(&other.0) || other.starts_with(&self.0)
+    }
+}
+
+impl From<KeyChord> for KeySequence {
+    fn from(key: KeyChord) -> Self {
+        Self(vec![key])
+    }
+}
+
+#[derive(Default)]
+pub struct ActionRegistry {
+    actions: Vec<Action>,
+    bindings: Vec<(KeySequence, String)>,
+    // Keys that weren't bound, and why
+    warnings: Vec<String>,
+}
+
+impl ActionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replaces an action with the same id. Its key is bound unless it's taken
+    pub fn register(&mut self, action: Action) {
+        self.actions.retain(|a| a.id != action.id);
+        self.bindings.retain(|(_, id)| *id != action.id);
+        if let Some(key) = action.key {
+            let keys = KeySequence::from(key);
+            let taken = self.bindings.iter().find(|(bound, _)| bound.overlaps(&keys)).map(|(_, id)| id.clone());
+            match taken {
+                _ if key.is_typing() => self.warn(&keys, &action.id, "needs Ctrl or Alt"),
+                _ if key.is_reserved() => self.warn(&keys, &action.id, "is NexTerm's"),
+                Some(holder) => self.warn(&keys, &action.id, &format!("is taken by {}", holder)),
+                None => self.bindings.push((keys, action.id.clone())),
+            }
+        }
+        self.actions.push(action);
+    }
+
+    // Over whatever had the key or shares a prefix with it, but not NexTerm's own keys
+    pub fn bind(&mut self, keys: KeySequence, id: &str) {
+        if keys.first().is_typing() {
+            return self.warn(&keys, id, "needs Ctrl or Alt");
+        }
+        if keys.0.iter().any(KeyChord::is_reserved) {
+            return self.warn(&keys, id, "is NexTerm's");
+        }
+        if self.get(id).is_none() {
+            return self.warn(&keys, id, "is bound to an unknown action");
+        }
+        self.bindings.retain(|(bound, _)| !bound.overlaps(&keys));
+        self.bindings.push((keys, id.to_string()));
+    }
+
+    // keybindings.toml: "Key" = "action.id"; a missing file binds nothing
+    pub fn load_keybindings(&mut self, path: &Path) -> Result<()> {
+        let Ok(text) = std::fs::read_to_string(path) else {
+            return Ok(());
+        };
+        let table: BTreeMap<String, String> =
+            toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+        for (key, id) in table {
+            match KeySequence::parse(&key) {
+                Ok(keys) => self.bind(keys, &id),
+                Err(e) => self.warnings.push(format!("{:#}", e)),
+            }
+        }
+        Ok(())
+    }
+
+    fn warn(&mut self, keys: &KeySequence, id: &str, why: &str) {
+        self.warnings.push(format!("{} for {} {}", keys.label(), id, why));
+    }
+
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Action> {
+        self.actions.iter().find(|a| a.id == id)
+    }
+
+    // The action bound to exactly these chords
+    pub fn for_keys(&self, pressed: &[KeyChord]) -> Option<&Action> {
+        let (_, id) = self.bindings.iter().find(|(bound, _)| bound.0 == pressed)?;
+        self.get(id)
+    }
+
+    // What may follow a prefix, each next chord with its action's title, or
+    // how many bindings go on from there; empty when nothing starts so
+    pub fn continuations(&self, pressed: &[KeyChord]) -> Vec<(String, String)> {
+        let mut next: Vec<(KeyChord, Vec<&str>)> = Vec::new();
+        for (bound, id) in &self.bindings {
+            if bound.0.len() <= pressed.len() || !bound.starts_with(pressed) {
+                continue;
+            }
+            let chord = bound.0[pressed.len()];
+            let title = match (bound.0.len() == pressed.len() + 1, self.get(id)) {
+                (true, Some(action)) => action.title.as_str(),
+                _ => "",
+            };
+            match next.iter_mut().find(|(key, _)| *key == chord) {
+                Some((_, titles)) => titles.push(title),
+                None => next.push((chord, vec![title])),
+            }
+        }
+        let mut hints: Vec<(String, String)> = next
+            .into_iter()
+            .map(|(chord, titles)| {
+                let what = match titles.as_slice() {
+                    [title] if !title.is_empty() => title.to_string(),
+                    titles => format!("+{} more", titles.len()),
+                };
+                (chord.label(), what)
+            })
+            .collect();
+        hints.sort();
+        hints
+    }
+
+    pub fn key_for(&self, id: &str) -> Option<&KeySequence> {
+        self.bindings.iter().find(|(_, bound)| bound == id).map(|(keys, _)| keys)
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+}
+
+// NexTerm's builtins that make sense without arguments
+pub fn builtin() -> Vec<Action> {
+    let command = |id: &str, title: &str, icon: &str, keywords: &[&str], line: &str| {
+        Action::new(&format!("nexterm.{}", id), title, Target::Command(line.to_string()))
+            .with_icon(icon)
+            .with_keywords(keywords)
+    };
+    vec![
+        command("review", "Review staged changes", "🔍", &["git", "diff", "lint"], "review"),
+        command("problems", "Problems in the last output", "⚠", &["errors", "warnings", "diagnostics"], "problems"),
+        command("scriptify", "Turn recent commands into a script", "📜", &["history", "bash"], "scriptify"),
+        command("summarize", "Summarize recent history", "📝", &["ai", "digest", "week"], "ai summarize"),
+        command("logging", "Start or stop logging output", "⏺", &["tee", "record", "file"], "logging"),
+        command("history_top", "Most used commands", "📊", &["history", "stats"], "history top"),
+        command("calendar", "History calendar", "📅", &["history", "days"], "history calendar"),
+        command("batch", "Open the batch editor", "🧾", &["multiple", "commands"], "batch"),
+        command("daemons", "Background daemons", "👻", &["processes", "jobs"], "daemons"),
+        command("search", "Search scrollback, buffers and logs", "🔎", &["find", "grep", "workspace"], "search")
+            .with_key(KeyChord::parse("Ctrl+Shift+F").ok()),
+        command("activity", "Running operations", "⏳", &["cancel", "progress", "jobs", "downloads"], "activity"),
+        command("plugins", "Plugins", "🔌", &["extensions", "enable"], "plugins"),
+        command("ssh_keys", "SSH keys", "🔑", &["ssh", "agent"], "ssh-keys"),
+        command("notes", "Edit project notes", "🗒", &["context", "ai"], "notes edit"),
+        command("teach", "Toggle teaching mode", "🎓", &["explain", "learn"], "teach"),
+        command("lock", "Lock the session", "🔒", &["away", "passphrase"], "lock"),
+        command("compat", "Toggle the compatibility profile", "🖥", &["ascii", "colors", "ssh", "slow"], "compat"),
+        command("actions", "List actions and key bindings", "⌨", &["keys", "shortcuts"], "actions"),
+        command("notify test", "Send a test message to the notification sinks", "📣", &["webhook", "slack", "email"], "notify test"),
+        command("status", "Show runtime state for bug reports", "🩺", &["diagnostics", "debug", "jobs"], "status --verbose"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workflow(name: &str, key: &str) -> Action {
+        let target = Target::Command(format!("workflow run {}", name));
+        Action::new(&format!("workflow.{}", name), name, target).with_key(Some(KeyChord::parse(key).unwrap()))
+    }
+
+    #[test]
+    fn parses_and_labels_keys() {
+        let key = KeyChord::parse("ctrl+shift+k").unwrap();
+        assert_eq!(key, KeyChord::new(KeyCode::Char('K'), KeyModifiers::CONTROL));
+        assert_eq!(key.label(), "Ctrl+Shift+K");
+        assert_eq!(KeyChord::parse("Alt+Enter").unwrap().label(), "Alt+Enter");
+        assert_eq!(KeyChord::parse("F5").unwrap(), KeyChord::new(KeyCode::F(5), KeyModifiers::NONE));
+        assert_eq!(KeyChord::parse("Ctrl++").unwrap().label(), "Ctrl++");
+        assert!(KeyChord::parse("Hyper+K").is_err());
+        assert!(KeyChord::parse("Ctrl+").is_err());
+    }
+
+    #[test]
+    fn binds_suggested_keys_unless_taken() {
+        let mut registry = ActionRegistry::new();
+        registry.register(workflow("deploy", "Alt+D"));
+        registry.register(workflow("docs", "Alt+D"));
+        registry.register(workflow("tabs", "Ctrl+T"));
+        registry.register(workflow("typing", "x"));
+
+        let alt_d = KeyChord::parse("Alt+D").unwrap();
+        assert_eq!(registry.for_keys(&[alt_d]).unwrap().id, "workflow.deploy");
+        assert!(registry.for_keys(&[KeyChord::parse("Ctrl+T").unwrap()]).is_none());
+        assert_eq!(
+            registry.warnings(),
+            [
+                "Alt+D for workflow.docs is taken by workflow.deploy",
+                "Ctrl+T for workflow.tabs is NexTerm's",
+                "X for workflow.typing needs Ctrl or Alt",
+            ]
+        );
+
+        // The user's bindings win, but not over NexTerm's keys
+        registry.bind(alt_d.into(), "workflow.docs");
+        registry.bind(KeySequence::parse("Ctrl+T").unwrap(), "workflow.tabs");
+        registry.bind(KeySequence::parse("Ctrl+Shift+D").unwrap(), "workflow.tabs");
+        assert_eq!(registry.for_keys(&[alt_d]).unwrap().id, "workflow.docs");
+        assert!(registry.key_for("workflow.tabs").is_none());
+        assert!(registry.key_for("workflow.deploy").is_none());
+        assert_eq!(
+            registry.warnings()[3..],
+            ["Ctrl+T for workflow.tabs is NexTerm's", "Ctrl+Shift+D for workflow.tabs is NexTerm's"]
+        );
+    }
+
+    #[test]
+    fn lists_continuations_of_a_prefix() {
+        let mut registry = ActionRegistry::new();
+        for name in ["deploy", "docs", "tabs"] {
+            registry.register(Action::new(&format!("workflow.{}", name), name, Target::Command(name.into())));
+        }
+        let keys = |text: &str| KeySequence::parse(text).unwrap();
+        registry.bind(keys("Ctrl+X D"), "workflow.deploy");
+        registry.bind(keys("Ctrl+X Shift+D"), "workflow.docs");
+        registry.bind(keys("Ctrl+X T A"), "workflow.tabs");
+        assert_eq!(registry.key_for("workflow.docs").unwrap().label(), "Ctrl+X Shift+D");
+
+        let ctrl_x = KeyChord::parse("Ctrl+X").unwrap();
+        assert!(registry.for_keys(&[ctrl_x]).is_none());
+        assert_eq!(
+            registry.continuations(&[ctrl_x]),
+            [("D".to_string(), "deploy".to_string()), ("Shift+D".into(), "docs".into()), ("T".into(), "+1 more".into())]
+        );
+        let d = KeyChord::parse("D").unwrap();
+        assert_eq!(registry.for_keys(&[ctrl_x, d]).unwrap().id, "workflow.deploy");
+        assert!(registry.continuations(&[d]).is_empty());
+
+        // A plain chord over the prefix replaces the sequences under it
+        registry.bind(ctrl_x.into(), "workflow.docs");
+        assert!(registry.continuations(&[ctrl_x]).is_empty());
+        assert!(registry.key_for("workflow.deploy").is_none());
+    }
+    #[test]
+    fn fills_inputs_into_the_command() {
+        let inputs: Vec<Input> = toml::from_str::<toml::Table>(
+            r#"
+            [[inputs]]
+            name = "ticket"
+            [[inputs]]
+            name = "watch"
+            kind = "checkbox"
+            flag = "-w"
+            [[inputs]]
+            name = "all"
+            kind = "checkbox"
+            "#,
+        )
+        .unwrap()["inputs"]
+            .clone()
+            .try_into()
+            .unwrap();
+        let answers: BTreeMap<String, String> =
+            [("ticket", "PROJ 12"), ("watch", "true"), ("all", "false")].map(|(k, v)| (k.into(), v.into())).into();
+        let command = fill("jira open {ticket} {watch}{all}", &inputs, &answers, |w| format!("'{}'", w));
+        assert_eq!(command, "jira open 'PROJ 12' -w");
+    }
+}
@@ -0,0 +1,110 @@
+// Bells: the ASCII bell (BEL) from a command, and commands finishing, each
+// mapped to a flash of the output pane, a sound (the host terminal's bell),
+// a desktop notification or nothing. `tab mute` silences them while that tab
+// is shown. Configured as
+//
+//   [bell]
+//   bell = "flash"           # none, flash, sound or desktop
+//   success = "none"         # a command exited 0
+//   failure = "desktop"      # a command exited with anything else
+//   min_secs = 10            # commands quicker than this don't ring on finishing
+use serde::{Deserialize, Serialize};
+
+use crate::core::terminal::Severity;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BellEffect {
+    None,
+    Flash,
+    Sound,
+    Desktop,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BellConfig {
+    #[serde(default = "default_bell")]
+    pub bell: BellEffect,
+    #[serde(default = "default_success")]
+    pub success: BellEffect,
+    #[serde(default = "default_failure")]
+    pub failure: BellEffect,
+    #[serde(default = "default_min_secs")]
+    pub min_secs: u64,
+}
+
+fn default_bell() -> BellEffect {
+    BellEffect::Flash
+}
+
+fn default_success() -> BellEffect {
+    BellEffect::None
+}
+
+fn default_failure() -> BellEffect {
+    BellEffect::Flash
+}
+
+fn default_min_secs() -> u64 {
+    10
+}
+
+impl Default for BellConfig {
+    fn default() -> Self {
+        Self {
+            bell: default_bell(),
+            success: default_success(),
+            failure: default_failure(),
+            min_secs: default_min_secs(),
+        }
+    }
+}
+
+// One bell for the UI to ring
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ring {
+    pub effect: BellEffect,
+    pub severity: Severity,
+    // For desktop notifications
+    pub message: String,
+}
+
+impl BellConfig {
+    // For BELs from `command`, however many arrived since the last look
+    pub fn bell(&self, command: &str) -> Option<Ring> {
+        self.ring(self.bell, Severity::Info, format!("🔔 {}", command))
+    }
+
+    pub fn finished(&self, command: &str, exit_code: i32, duration_ms: u64) -> Option<Ring> {
+        if duration_ms < self.min_secs * 1000 {
+            return None;
+        }
+        let secs = duration_ms / 1000;
+        match exit_code {
+            0 => self.ring(self.success, Severity::Success, format!("✓ {} finished in {}s", command, secs)),
+            code => self.ring(self.failure, Severity::Error, format!("✗ {} exited with {} after {}s", command, code, secs)),
+        }
+    }
+
+    fn ring(&self, effect: BellEffect, severity: Severity, message: String) -> Option<Ring> {
+        (effect != BellEffect::None).then_some(Ring { effect, severity, message })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_events_to_effects() {
+        let config: BellConfig = toml::from_str("success = \"sound\"\nmin_secs = 5").unwrap();
+        assert_eq!(config.bell("make").map(|r| r.effect), Some(BellEffect::Flash));
+        assert_eq!(config.finished("make", 0, 4_000), None);
+        assert_eq!(config.finished("make", 0, 6_000).map(|r| r.effect), Some(BellEffect::Sound));
+        let failed = config.finished("make", 2, 12_500).unwrap();
+        assert_eq!((failed.effect, failed.message.as_str()), (BellEffect::Flash, "✗ make exited with 2 after 12s"));
+
+        let quiet = BellConfig { bell: BellEffect::None, ..BellConfig::default() };
+        assert_eq!(quiet.bell("make"), None);
+    }
+}
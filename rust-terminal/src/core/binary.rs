@@ -0,0 +1,192 @@
+// Binary output: a command that writes NUL bytes or mostly control characters
+// and undecodable text (`cat image.png`) gets a placeholder line instead of
+// garbage in the scrollback. What it wrote is kept, up to MAX_KEPT bytes, for
+// the hex viewer (`binary view N`) or to save (`binary save N FILE`).
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::core::encoding::Encoding;
+
+pub const MARKER: &str = "⚠ binary output #";
+// Only the start of a command's output is looked at
+const SNIFF_BYTES: usize = 8192;
+// Most read at once from a pipe when looking for the end of a line
+pub const PIECE: u64 = 4096;
+const MAX_KEPT: usize = 16 * 1024 * 1024;
+// Commands whose binary output is kept
+pub const KEPT: usize = 8;
+// Share of odd bytes (control characters, invalid UTF-8) that makes a chunk binary
+const MAX_ODD_PERCENT: usize = 10;
+
+// NUL anywhere, or too many odd bytes (a short line needs a few, not one).
+// Invalid UTF-8 only counts in a UTF-8 pane; latin-1 and Shift-JIS text is
+// full of it.
+pub fn looks_binary(bytes: &[u8], encoding: Encoding) -> bool {
+    if bytes.contains(&0) {
+        return true;
+    }
+    let control = bytes
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x1b | 0x07 | 0x08 | 0x0c))
+        .count();
+    let invalid = match encoding {
+        Encoding::Utf8 => invalid_utf8(bytes),
+        _ => 0,
+    };
+    (control + invalid) * 100 > bytes.len().max(64) * MAX_ODD_PERCENT
+}
+
+fn invalid_utf8(mut bytes: &[u8]) -> usize {
+    let mut invalid = 0;
+    loop {
+        match std::str::from_utf8(bytes) {
+            Ok(_) => return invalid,
+            Err(e) => match e.error_len() {
+                Some(len) => {
+                    invalid += len;
+                    bytes = &bytes[e.valid_up_to() + len..];
+                }
+                // Cut off at the end of the chunk
+                None => return invalid,
+            },
+        }
+    }
+}
+
+// Decides, for one stream of a command's output, whether it is binary. Once
+// it is, everything after is too.
+pub struct Sniffer {
+    encoding: Encoding,
+    seen: usize,
+    binary: bool,
+}
+
+impl Sniffer {
+    pub fn new(encoding: Encoding) -> Self {
+        Self { encoding, seen: 0, binary: false }
+    }
+
+    pub fn is_binary(&mut self, bytes: &[u8]) -> bool {
+        if !self.binary && self.seen < SNIFF_BYTES {
+            self.binary = looks_binary(bytes, self.encoding);
+        }
+        self.seen += bytes.len();
+        self.binary
+    }
+}
+
+pub struct BinaryOutput {
+    id: usize,
+    command: String,
+    bytes: Vec<u8>,
+    total: usize,
+}
+
+pub type SharedBinary = Arc<Mutex<BinaryOutput>>;
+
+impl BinaryOutput {
+    pub fn new(id: usize, command: &str) -> Self {
+        Self { id, command: command.to_string(), bytes: Vec::new(), total: 0 }
+    }
+
+    pub fn shared(self) -> SharedBinary {
+        Arc::new(Mutex::new(self))
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    // Everything written, kept or not
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn placeholder(&self) -> String {
+        let cut = if self.total > self.bytes.len() { ", first 16 MB kept" } else { "" };
+        format!(
+            "{}{} ({}{}): `binary view {}` or `binary save {} FILE`",
+            MARKER,
+            self.id,
+            size_label(self.total),
+            cut,
+            self.id,
+            self.id
+        )
+    }
+
+    // Keeps `bytes` and shows the placeholder at the end of `buffer`, updated
+    // in place as more arrives
+    pub fn write(&mut self, bytes: &[u8], buffer: &Mutex<Vec<String>>) {
+        let first = self.total == 0;
+        self.total += bytes.len();
+        let room = MAX_KEPT.saturating_sub(self.bytes.len());
+        self.bytes.extend_from_slice(&bytes[..bytes.len().min(room)]);
+        let Ok(mut buffer) = buffer.lock() else {
+            return;
+        };
+        let placeholder = self.placeholder();
+        if first {
+            buffer.push(placeholder);
+        } else if let Some(line) = buffer.iter_mut().rev().find(|l| placeholder_id(l) == Some(self.id)) {
+            *line = placeholder;
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, &self.bytes).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+// The output a placeholder line stands for
+pub fn placeholder_id(line: &str) -> Option<usize> {
+    let rest = line.strip_prefix(MARKER)?;
+    rest[..rest.find(' ')?].parse().ok()
+}
+
+pub fn size_label(bytes: usize) -> String {
+    match bytes {
+        0..=1023 => format!("{} bytes", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_binary_and_keeps_a_placeholder() {
+        assert!(looks_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", Encoding::Utf8));
+        assert!(!looks_binary("héllo \x1b[1mworld\x1b[0m\n".as_bytes(), Encoding::Utf8));
+        // Mostly invalid UTF-8: binary, unless the pane reads latin-1
+        let latin = b"\xe9t\xe9 \xe0 l'h\xf4tel, \xe7a co\xfbte cher \xe0 Gen\xe8ve, \xe9videmment";
+        assert!(looks_binary(latin, Encoding::Utf8));
+        assert!(!looks_binary(latin, Encoding::Latin1));
+
+        let mut sniffer = Sniffer::new(Encoding::Utf8);
+        assert!(!sniffer.is_binary(b"caf\xe9 \x01\n"));
+        assert!(sniffer.is_binary(b"\0\x01\x02"));
+        assert!(sniffer.is_binary(b"plain text after"));
+
+        let buffer = Mutex::new(vec!["$ cat image.png".to_string()]);
+        let mut output = BinaryOutput::new(3, "cat image.png");
+        output.write(&[0; 1000], &buffer);
+        output.write(&[0; 1000], &buffer);
+        let lines = buffer.lock().unwrap().clone();
+        assert_eq!(lines[1], "⚠ binary output #3 (2.0 KB): `binary view 3` or `binary save 3 FILE`");
+        assert_eq!(lines.len(), 2);
+        assert_eq!(placeholder_id(&lines[1]), Some(3));
+        assert_eq!(output.bytes().len(), 2000);
+    }
+}
@@ -8,10 +8,12 @@ impl CommandParser {
         Self
     }
 
+    // First word and the rest, split on whitespace; quoting is left to the shell
     pub fn parse(&self, input: &str) -> Result<ParsedCommand> {
+        let mut words = input.split_whitespace().map(str::to_string);
         Ok(ParsedCommand {
-            command: input.to_string(),
-            args: vec![],
+            command: words.next().unwrap_or_default(),
+            args: words.collect(),
         })
     }
 }
@@ -20,3 +22,26 @@ pub struct ParsedCommand {
     pub command: String,
     pub args: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn any_input_parses(input in any::<String>()) {
+            let parsed = CommandParser::new().parse(&input).unwrap();
+            prop_assert!(!parsed.command.contains(char::is_whitespace));
+            let words: Vec<&str> = input.split_whitespace().collect();
+            prop_assert_eq!(parsed.command.is_empty(), words.is_empty());
+            prop_assert_eq!(parsed.args.len(), words.len().saturating_sub(1));
+        }
+
+        // Lossily decoded output pasted back as a command
+        #[test]
+        fn broken_utf8_parses(bytes in prop::collection::vec(any::<u8>(), 0..256)) {
+            prop_assert!(CommandParser::new().parse(&String::from_utf8_lossy(&bytes)).is_ok());
+        }
+    }
+}
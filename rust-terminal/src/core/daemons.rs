@@ -0,0 +1,229 @@
+// Daemonized commands: started in their own session with output going to a
+// log file, so they outlive NexTerm. The registry (daemons.json) and logs live
+// under the data directory and are shared by every NexTerm instance.
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::utils::clock;
+use crate::utils::durable;
+use crate::utils::storage;
+
+// A stopped daemon that ignores SIGTERM gets SIGKILL after this
+const STOP_GRACE: Duration = Duration::from_secs(5);
+// How much of the end of a log `tail` reads
+const TAIL_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonInfo {
+    pub id: u32,
+    pub command: String,
+    // Also the process group, which `stop` signals as a whole
+    pub pid: u32,
+    pub started: u64,
+    pub cwd: PathBuf,
+    pub log: PathBuf,
+    #[serde(skip)]
+    pub running: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct RegistryFile {
+    daemons: Vec<DaemonInfo>,
+}
+
+pub struct DaemonRegistry {
+    dir: PathBuf,
+}
+
+impl DaemonRegistry {
+    pub fn new() -> Self {
+        Self { dir: storage::data_dir().join("daemons") }
+    }
+
+    // Runs `command` through `shell` detached from NexTerm: a new session (no
+    // controlling terminal, so no SIGHUP when NexTerm exits), stdin from
+    // /dev/null and stdout/stderr appended to its log
+    pub fn start(&self, shell: &str, command: &str, env: &BTreeMap<String, String>) -> Result<DaemonInfo> {
+        let mut registry = self.load();
+        let id = registry.daemons.iter().map(|d| d.id).max().unwrap_or(0) + 1;
+        let log = self.dir.join(format!("{}.log", id));
+        fs::create_dir_all(&self.dir)?;
+        let out = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log)
+            .with_context(|| format!("Failed to open {}", log.display()))?;
+        let cwd = std::env::current_dir().unwrap_or_default();
+
+        let mut child = Command::new(shell);
+        child
+            .arg("-c")
+            .arg(command)
+            .envs(env)
+            .current_dir(&cwd)
+            .stdin(Stdio::null())
+            .stdout(out.try_clone()?)
+            .stderr(out);
+        unsafe {
+            child.pre_exec(|| {
+                if libc::setsid() < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let mut child = child.spawn().with_context(|| format!("Failed to start {}", command))?;
+        let info = DaemonInfo {
+            id,
+            command: command.to_string(),
+            pid: child.id(),
+            started: now(),
+            cwd,
+            log,
+            running: true,
+        };
+        // Reaped while NexTerm runs, so an exited daemon isn't left a zombie that
+        // still looks alive; after that init takes it over
+        std::thread::spawn(move || child.wait());
+
+        registry.daemons.push(info.clone());
+        self.save(&registry)?;
+        Ok(info)
+    }
+
+    // Every registered daemon, oldest first, with whether it still runs
+    pub fn list(&self) -> Vec<DaemonInfo> {
+        let mut daemons = self.load().daemons;
+        for daemon in &mut daemons {
+            daemon.running = is_alive(daemon.pid);
+        }
+        daemons
+    }
+
+    pub fn get(&self, id: u32) -> Result<DaemonInfo> {
+        self.list()
+            .into_iter()
+            .find(|d| d.id == id)
+            .with_context(|| format!("No daemon {}", id))
+    }
+
+    // SIGTERM to the daemon's process group, SIGKILL if it's still there
+    // after the grace period
+    pub fn stop(&self, id: u32) -> Result<()> {
+        let daemon = self.get(id)?;
+        if !daemon.running {
+            bail!("Daemon {} has already exited", id);
+        }
+        let group = -(daemon.pid as i32);
+        if unsafe { libc::kill(group, libc::SIGTERM) } < 0 {
+            return Err(std::io::Error::last_os_error()).context("Failed to stop daemon");
+        }
+        std::thread::spawn(move || {
+            std::thread::sleep(STOP_GRACE);
+            if is_alive(daemon.pid) {
+                unsafe { libc::kill(group, libc::SIGKILL) };
+            }
+        });
+        Ok(())
+    }
+
+    // Drops an exited daemon from the registry, with its log
+    pub fn forget(&self, id: u32) -> Result<()> {
+        let daemon = self.get(id)?;
+        if daemon.running {
+            bail!("Daemon {} is still running; stop it first", id);
+        }
+        let mut registry = self.load();
+        registry.daemons.retain(|d| d.id != id);
+        self.save(&registry)?;
+        let _ = fs::remove_file(&daemon.log);
+        Ok(())
+    }
+
+    fn load(&self) -> RegistryFile {
+        durable::load_json(&self.path()).unwrap_or_default()
+    }
+
+    fn save(&self, registry: &RegistryFile) -> Result<()> {
+        durable::write_atomic(&self.path(), serde_json::to_string_pretty(registry)?.as_bytes())
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join("daemons.json")
+    }
+}
+
+impl Default for DaemonRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The last `lines` lines of a log
+pub fn tail(log: &Path, lines: usize) -> Vec<String> {
+    let Ok(mut file) = File::open(log) else {
+        return Vec::new();
+    };
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let start = len.saturating_sub(TAIL_BYTES);
+    let mut bytes = Vec::new();
+    if file.seek(SeekFrom::Start(start)).is_err() || file.read_to_end(&mut bytes).is_err() {
+        return Vec::new();
+    }
+    let text = String::from_utf8_lossy(&bytes);
+    let all: Vec<&str> = text.lines().collect();
+    // A cut-off first line only when the read didn't start at the beginning
+    let skip = usize::from(start > 0).max(all.len().saturating_sub(lines));
+    all[skip.min(all.len())..].iter().map(|l| l.to_string()).collect()
+}
+
+// `3  running  2h  npm run dev`
+pub fn describe(daemon: &DaemonInfo) -> String {
+    let age = now().saturating_sub(daemon.started);
+    let age = match age {
+        0..=59 => format!("{}s", age),
+        60..=3599 => format!("{}m", age / 60),
+        3600..=86399 => format!("{}h", age / 3600),
+        _ => format!("{}d", age / 86400),
+    };
+    let state = if daemon.running { "running" } else { "exited" };
+    format!("{:>3}  {:<7}  {:>4}  {}", daemon.id, state, age, daemon.command)
+}
+
+fn is_alive(pid: u32) -> bool {
+    unsafe { libc::kill(pid as i32, 0) == 0 }
+}
+
+fn now() -> u64 {
+    clock::unix_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_lists_and_stops() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = DaemonRegistry { dir: dir.path().to_path_buf() };
+        let daemon = registry.start("/bin/sh", "echo started; exec sleep 30", &BTreeMap::new()).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert!(registry.get(daemon.id).unwrap().running);
+        assert_eq!(tail(&daemon.log, 10), vec!["started"]);
+        assert!(registry.forget(daemon.id).is_err());
+
+        registry.stop(daemon.id).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!registry.get(daemon.id).unwrap().running);
+        registry.forget(daemon.id).unwrap();
+        assert!(registry.list().is_empty());
+    }
+}
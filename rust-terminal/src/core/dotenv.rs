@@ -0,0 +1,122 @@
+// Project .env files, loaded into the environment of commands on request
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+// Later files override earlier ones (the .env.local convention)
+const ENV_FILES: &[&str] = &[".env", ".env.local"];
+
+// Values shorter than this are too likely to collide with ordinary text to redact
+const MIN_REDACT_LEN: usize = 4;
+
+pub const MASK: &str = "••••••";
+
+#[derive(Debug, Clone, Default)]
+pub struct DotEnv {
+    pub files: Vec<PathBuf>,
+    pub vars: BTreeMap<String, String>,
+}
+
+impl DotEnv {
+    // The .env files of the nearest directory (from cwd upwards) that has any
+    pub fn discover() -> Option<Vec<PathBuf>> {
+        let cwd = std::env::current_dir().ok()?;
+        cwd.ancestors().find_map(|dir| {
+            let files: Vec<PathBuf> = ENV_FILES
+                .iter()
+                .map(|name| dir.join(name))
+                .filter(|path| path.is_file())
+                .collect();
+            (!files.is_empty()).then_some(files)
+        })
+    }
+
+    pub fn load(files: Vec<PathBuf>) -> Result<Self> {
+        let mut vars = BTreeMap::new();
+        for file in &files {
+            let content = std::fs::read_to_string(file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+            vars.extend(parse(&content));
+        }
+        Ok(Self { files, vars })
+    }
+
+    // Same keys, every value replaced by MASK; for display and history snapshots
+    pub fn masked(&self) -> BTreeMap<String, String> {
+        self.vars.keys().map(|k| (k.clone(), MASK.to_string())).collect()
+    }
+
+    // Replaces loaded values in `text` with `<env:KEY>` so they don't reach
+    // the AI or the logs
+    pub fn redact(&self, text: &str) -> String {
+        self.vars
+            .iter()
+            .filter(|(_, value)| value.len() >= MIN_REDACT_LEN)
+            .fold(text.to_string(), |text, (key, value)| {
+                text.replace(value.as_str(), &format!("<env:{}>", key))
+            })
+    }
+
+    // Inverse of `redact`, for commands that come back from the AI
+    pub fn restore(&self, text: &str) -> String {
+        self.vars.iter().fold(text.to_string(), |text, (key, value)| {
+            text.replace(&format!("<env:{}>", key), value)
+        })
+    }
+
+    pub fn describe_files(&self) -> String {
+        self.files
+            .iter()
+            .filter_map(|f| f.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+}
+
+// KEY=value lines; `export` prefixes, comments and single/double quotes are handled
+fn parse(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let key = key.trim();
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return None;
+            }
+            Some((key.to_string(), unquote(value.trim())))
+        })
+        .collect()
+}
+
+fn unquote(value: &str) -> String {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|v| v.strip_suffix(quote)) {
+            return if quote == '"' { inner.replace("\\n", "\n").replace("\\\"", "\"") } else { inner.to_string() };
+        }
+    }
+    // Unquoted values end at an inline comment
+    match value.find(" #") {
+        Some(end) => value[..end].trim_end().to_string(),
+        None => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dotenv_syntax() {
+        let vars = parse("# comment\nexport A=1\nB=\"two words\"\nC='x#y'\nD=plain # note\n bad key=1\n");
+        assert_eq!(vars["A"], "1");
+        assert_eq!(vars["B"], "two words");
+        assert_eq!(vars["C"], "x#y");
+        assert_eq!(vars["D"], "plain");
+        assert_eq!(vars.len(), 4);
+    }
+}
@@ -0,0 +1,110 @@
+// Character encoding of the pane's child output. Legacy systems reached over
+// serial or SSH often speak latin-1 or Shift-JIS, which would otherwise show
+// up as replacement characters; their output is transcoded to UTF-8 before
+// the screen, the output buffer or a tee file sees it. What's typed is sent
+// as is. Configured as
+//
+//   encoding = "shift-jis"             # utf-8 (default), latin-1, shift-jis
+//
+// and changed for the pane with `encoding <name>`, from the next command on.
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Encoding {
+    #[default]
+    #[serde(rename = "utf-8", alias = "utf8")]
+    Utf8,
+    #[serde(rename = "latin-1", alias = "latin1", alias = "iso-8859-1")]
+    Latin1,
+    #[serde(rename = "shift-jis", alias = "shift_jis", alias = "sjis")]
+    ShiftJis,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 3] = [Encoding::Utf8, Encoding::Latin1, Encoding::ShiftJis];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Latin1 => "latin-1",
+            Encoding::ShiftJis => "shift-jis",
+        }
+    }
+
+    // The names the config takes, in any case
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "latin-1" | "latin1" | "iso-8859-1" => Some(Encoding::Latin1),
+            "shift-jis" | "shift_jis" | "sjis" => Some(Encoding::ShiftJis),
+            _ => None,
+        }
+    }
+
+    pub fn decoder(self) -> Decoder {
+        match self {
+            Encoding::Utf8 => Decoder::Utf8,
+            Encoding::Latin1 => Decoder::Latin1,
+            Encoding::ShiftJis => Decoder::Other(encoding_rs::SHIFT_JIS.new_decoder_without_bom_handling()),
+        }
+    }
+
+    // A whole line, for commands on pipes. Newlines can't be part of a
+    // multi-byte character in any of these, so lines split cleanly.
+    pub fn decode_line(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+            Encoding::ShiftJis => encoding_rs::SHIFT_JIS.decode_without_bom_handling(bytes).0.into_owned(),
+        }
+    }
+}
+
+// Transcodes a stream read in chunks: a character split across reads is
+// finished by the next one
+pub enum Decoder {
+    // Passed through; the screen decodes UTF-8 itself
+    Utf8,
+    // Every byte is the code point of the same value. encoding_rs would read
+    // it as windows-1252, which differs in 0x80-0x9f.
+    Latin1,
+    Other(encoding_rs::Decoder),
+}
+
+impl Decoder {
+    // UTF-8 for `bytes`
+    pub fn decode<'a>(&mut self, bytes: &'a [u8]) -> Cow<'a, [u8]> {
+        match self {
+            Decoder::Utf8 => Cow::Borrowed(bytes),
+            Decoder::Latin1 => Cow::Owned(bytes.iter().map(|&b| b as char).collect::<String>().into_bytes()),
+            Decoder::Other(decoder) => {
+                let room = decoder.max_utf8_buffer_length(bytes.len()).unwrap_or(bytes.len() * 3);
+                let mut text = String::with_capacity(room);
+                // Room for all of it, so the input is always used up
+                let _ = decoder.decode_to_string(bytes, &mut text, false);
+                Cow::Owned(text.into_bytes())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcodes_split_characters() {
+        // "カナ OK" in Shift-JIS, split inside the first character
+        let bytes = [0x83, 0x4a, 0x83, 0x69, b' ', b'O', b'K'];
+        let mut decoder = Encoding::ShiftJis.decoder();
+        let mut text = decoder.decode(&bytes[..1]).into_owned();
+        text.extend_from_slice(&decoder.decode(&bytes[1..]));
+        assert_eq!(String::from_utf8(text).unwrap(), "カナ OK");
+        assert_eq!(Encoding::ShiftJis.decode_line(&bytes), "カナ OK");
+
+        assert_eq!(Encoding::Latin1.decode_line(b"caf\xe9 \x80"), "café \u{80}");
+        assert_eq!(Encoding::Utf8.decode_line(b"caf\xe9"), "caf\u{fffd}");
+        assert_eq!(Encoding::parse("SJIS"), Some(Encoding::ShiftJis));
+    }
+}
@@ -0,0 +1,148 @@
+// Environment labels for tabs (dev/staging/prod), and which commands count as
+// changing something for the typed confirmation prod-labeled tabs ask for.
+// Unknown programs are assumed to change something.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvLabel {
+    Dev,
+    Staging,
+    Prod,
+}
+
+impl EnvLabel {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "dev" | "development" => Some(EnvLabel::Dev),
+            "staging" | "stage" => Some(EnvLabel::Staging),
+            "prod" | "production" => Some(EnvLabel::Prod),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EnvLabel::Dev => "dev",
+            EnvLabel::Staging => "staging",
+            EnvLabel::Prod => "prod",
+        }
+    }
+
+    // The word to type back before a mutating command runs
+    pub fn confirm_word(self) -> Option<&'static str> {
+        (self == EnvLabel::Prod).then_some("PROD")
+    }
+}
+
+// Shell syntax that can write or run more than it shows: `>` also covers
+// `>(`, and `&` covers `&&` as well as running in the background
+const UNSAFE_SYNTAX: &[&str] = &[">", "<(", ";", "&", "||", "`", "$(", "\n"];
+
+// Programs that only read. Pagers (less, more, man) aren't: `!cmd` runs a shell
+const READ_ONLY: &[&str] = &[
+    "ls", "cat", "head", "tail", "grep", "rg", "pwd", "echo", "whoami", "df", "du", "ps", "top", "htop",
+    "which", "printenv", "wc", "stat", "file", "history", "uptime", "id", "jq", "diff", "uniq", "cut",
+    "ping", "dig", "nslookup",
+];
+
+// Read-only subcommands of tools that can also write
+const READ_ONLY_SUBCOMMANDS: &[(&str, &[&str])] = &[
+    ("git", &["status", "log", "diff", "show", "blame", "rev-parse", "ls-files"]),
+    ("kubectl", &["get", "describe", "logs", "top", "explain", "version", "api-resources"]),
+    ("docker", &["ps", "images", "logs", "inspect", "version", "info", "stats"]),
+    ("helm", &["list", "ls", "status", "get", "history", "template", "version"]),
+    ("terraform", &["plan", "show", "output", "validate", "version"]),
+    ("systemctl", &["status", "list-units", "is-active", "show"]),
+];
+
+// `git branch` and `git remote` only read when they list
+const BRANCH_LISTING: &[&str] =
+    &["-a", "-r", "-v", "-vv", "-l", "--list", "--all", "--remotes", "--verbose", "--show-current"];
+const REMOTE_READS: &[&str] = &["show", "get-url"];
+
+pub fn is_mutating(command: &str) -> bool {
+    if UNSAFE_SYNTAX.iter().any(|s| command.contains(s)) {
+        return true;
+    }
+    command.split('|').any(segment_mutates)
+}
+
+fn segment_mutates(segment: &str) -> bool {
+    // Leading VAR=value assignments don't count
+    let mut words = segment.split_whitespace().skip_while(|w| w.contains('=') && !w.starts_with('-'));
+    let Some(program) = words.next() else {
+        return false;
+    };
+    let program = program.rsplit('/').next().unwrap_or(program);
+    if READ_ONLY.contains(&program) {
+        return false;
+    }
+    let args: Vec<&str> = words.collect();
+    if let Some(writes) = writes(program, &args) {
+        return writes;
+    }
+    let Some((_, reads)) = READ_ONLY_SUBCOMMANDS.iter().find(|(tool, _)| *tool == program) else {
+        return true;
+    };
+    let Some(at) = args.iter().position(|w| !w.starts_with('-')) else {
+        return true;
+    };
+    let (sub, rest) = (args[at], &args[at + 1..]);
+    match (program, sub) {
+        ("git", "branch") => !rest.iter().all(|w| BRANCH_LISTING.contains(w)),
+        ("git", "remote") => rest.iter().find(|w| !w.starts_with('-')).is_some_and(|w| !REMOTE_READS.contains(w)),
+        _ => !reads.contains(&sub),
+    }
+}
+
+// Readers with flags or arguments that write a file (`sort -o out`) or change
+// the system (`date -s`, `hostname NAME`): whether these are given. None for
+// other programs.
+fn writes(program: &str, args: &[&str]) -> Option<bool> {
+    // Short flags may be bunched up (`-ro out`)
+    let flag = |short: char, long: &str| {
+        args.iter().any(|arg| match arg.strip_prefix("--") {
+            Some(name) => name.starts_with(long),
+            None => arg.strip_prefix('-').is_some_and(|flags| flags.contains(short)),
+        })
+    };
+    Some(match program {
+        "hostname" => args.iter().any(|arg| !arg.starts_with('-')) || flag('F', "file") || flag('b', "boot"),
+        // A bare MMDDhhmm[[CC]YY][.ss] sets the clock too
+        "date" => flag('s', "set") || args.iter().any(|arg| arg.chars().all(|c| c.is_ascii_digit() || c == '.')),
+        "sort" | "tree" => flag('o', "output"),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_reads_from_writes() {
+        assert!(!is_mutating("ls -la"));
+        assert!(!is_mutating("kubectl get pods | grep api"));
+        assert!(!is_mutating("git status"));
+        assert!(is_mutating("kubectl delete pod api-1"));
+        assert!(is_mutating("cat config > backup"));
+        assert!(is_mutating("psql -c 'drop table users'"));
+        assert!(is_mutating("ls; rm -rf build"));
+        assert!(is_mutating("ls & rm -rf /"));
+        assert!(is_mutating("diff <(rm -rf build) a"));
+        assert!(!is_mutating("git branch -a"));
+        assert!(is_mutating("git branch -D main"));
+        assert!(!is_mutating("git remote -v"));
+        assert!(!is_mutating("git remote show origin"));
+        assert!(is_mutating("git remote remove origin"));
+        assert!(is_mutating("less /var/log/syslog"));
+        assert!(!is_mutating("hostname -f"));
+        assert!(is_mutating("hostname db-2"));
+        assert!(!is_mutating("date +%s"));
+        assert!(is_mutating("date -s '2024-01-01 00:00'"));
+        assert!(is_mutating("date 010112002024"));
+        assert!(!is_mutating("sort -u names.txt"));
+        assert!(is_mutating("sort -ro names.txt names.txt"));
+        assert!(is_mutating("tree --output=tree.txt"));
+        assert_eq!(EnvLabel::parse("Production"), Some(EnvLabel::Prod));
+    }
+}
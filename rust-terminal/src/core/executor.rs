@@ -1,14 +1,209 @@
-// Command executor
-use anyhow::Result;
+// Command executors: how a submitted command actually runs. The terminal uses
+// `ShellExecutor`; `ScriptedExecutor` answers from a script instead, so tests
+// and demos never touch the real shell.
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWriteExt, DuplexStream};
 
-pub struct Executor;
+use crate::utils::error::NexTermError;
 
-impl Executor {
-    pub fn new() -> Self {
-        Self
+pub type OutputStream = Box<dyn AsyncRead + Send + Unpin>;
+
+// A started command: its output streams and its exit code once it's done
+pub struct Execution {
+    pub pid: Option<u32>,
+    pub stdout: Option<OutputStream>,
+    pub stderr: Option<OutputStream>,
+    pub exit: Pin<Box<dyn Future<Output = i32> + Send>>,
+}
+
+pub trait Executor: Send + Sync {
+    fn spawn(&self, command: &str, env: &BTreeMap<String, String>) -> Result<Execution>;
+
+    // Commands may run in a pseudo-terminal instead, which always uses the shell
+    fn allows_pty(&self) -> bool {
+        false
+    }
+}
+
+pub struct ShellExecutor {
+    shell: String,
+}
+
+impl ShellExecutor {
+    pub fn new(shell: &str) -> Self {
+        Self { shell: shell.to_string() }
+    }
+}
+
+impl Executor for ShellExecutor {
+    fn spawn(&self, command: &str, env: &BTreeMap<String, String>) -> Result<Execution> {
+        let mut child = tokio::process::Command::new(&self.shell)
+            .arg("-c")
+            .arg(command)
+            .envs(env)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|source| NexTermError::ExecutionFailed { command: command.to_string(), source })?;
+        let stdout = child.stdout.take().map(|s| Box::new(s) as OutputStream);
+        let stderr = child.stderr.take().map(|s| Box::new(s) as OutputStream);
+        Ok(Execution {
+            pid: child.id(),
+            stdout,
+            stderr,
+            exit: Box::pin(async move { child.wait().await.ok().and_then(|s| s.code()).unwrap_or(-1) }),
+        })
+    }
+
+    fn allows_pty(&self) -> bool {
+        true
+    }
+}
+
+// demo.toml:
+//   [[commands]]
+//   run = "cargo build"
+//   exit = 0
+//   lines = [
+//     { text = "   Compiling demo v0.1.0", after_ms = 400 },
+//     { text = "warning: unused variable `x`", stderr = true },
+//   ]
+#[derive(Debug, Clone, Deserialize)]
+struct ScriptFile {
+    #[serde(default)]
+    commands: Vec<Script>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Script {
+    run: String,
+    #[serde(default)]
+    exit: i32,
+    #[serde(default)]
+    lines: Vec<ScriptLine>,
+}
+
+// Printed `after_ms` after the line before it
+#[derive(Debug, Clone, Deserialize)]
+struct ScriptLine {
+    text: String,
+    #[serde(default)]
+    after_ms: u64,
+    #[serde(default)]
+    stderr: bool,
+}
+
+// Plays back scripted output for the commands it knows; anything else fails
+// like an unknown command (exit 127)
+pub struct ScriptedExecutor {
+    scripts: BTreeMap<String, Script>,
+    ran: Mutex<Vec<String>>,
+}
+
+impl ScriptedExecutor {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("No script {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid script {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let file: ScriptFile = toml::from_str(content)?;
+        Ok(Self {
+            scripts: file.commands.into_iter().map(|s| (s.run.clone(), s)).collect(),
+            ran: Mutex::new(Vec::new()),
+        })
     }
 
-    pub async fn execute(&self, command: &str) -> Result<String> {
-        Ok(format!("Executed: {}", command))
+    // Commands spawned so far, in order
+    #[cfg(test)]
+    pub fn ran(&self) -> Vec<String> {
+        self.ran.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+}
+
+impl Executor for ScriptedExecutor {
+    fn spawn(&self, command: &str, _env: &BTreeMap<String, String>) -> Result<Execution> {
+        if let Ok(mut ran) = self.ran.lock() {
+            ran.push(command.to_string());
+        }
+        let script = self.scripts.get(command).cloned().unwrap_or_else(|| Script {
+            run: command.to_string(),
+            exit: 127,
+            lines: vec![ScriptLine {
+                text: format!("{}: command not found", command),
+                after_ms: 0,
+                stderr: true,
+            }],
+        });
+
+        let (stdout, out) = tokio::io::duplex(64 * 1024);
+        let (stderr, err) = tokio::io::duplex(64 * 1024);
+        let playback = tokio::spawn(play(script, out, err));
+        Ok(Execution {
+            pid: None,
+            stdout: Some(Box::new(stdout)),
+            stderr: Some(Box::new(stderr)),
+            exit: Box::pin(async move { playback.await.unwrap_or(-1) }),
+        })
+    }
+}
+
+// Writes the script's lines in order, closing both streams before it exits
+async fn play(script: Script, mut out: DuplexStream, mut err: DuplexStream) -> i32 {
+    for line in script.lines {
+        tokio::time::sleep(Duration::from_millis(line.after_ms)).await;
+        let stream = if line.stderr { &mut err } else { &mut out };
+        if stream.write_all(format!("{}\n", line.text).as_bytes()).await.is_err() {
+            break;
+        }
+    }
+    script.exit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    async fn read(stream: Option<OutputStream>) -> String {
+        let mut text = String::new();
+        stream.unwrap().read_to_string(&mut text).await.unwrap();
+        text
+    }
+
+    #[tokio::test]
+    async fn plays_back_scripts() {
+        let executor = ScriptedExecutor::parse(
+            r#"
+            [[commands]]
+            run = "make"
+            exit = 2
+            lines = [
+              { text = "cc -c main.c" },
+              { text = "main.c:3: error", stderr = true, after_ms = 5 },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        let execution = executor.spawn("make", &BTreeMap::new()).unwrap();
+        let (stdout, stderr) = tokio::join!(read(execution.stdout), read(execution.stderr));
+        assert_eq!((stdout.as_str(), stderr.as_str()), ("cc -c main.c\n", "main.c:3: error\n"));
+        assert_eq!(execution.exit.await, 2);
+
+        let execution = executor.spawn("frobnicate", &BTreeMap::new()).unwrap();
+        assert_eq!(read(execution.stderr).await, "frobnicate: command not found\n");
+        assert_eq!(execution.exit.await, 127);
+        assert_eq!(executor.ran(), ["make", "frobnicate"]);
     }
 }
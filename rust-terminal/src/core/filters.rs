@@ -0,0 +1,109 @@
+// Scrollback filters: the output buffer piped through a shell pipeline (grep,
+// jq, awk, ...) with the result shown in a temporary view. Pipelines worth
+// keeping are saved by name in filters.json under the data directory.
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::utils::durable;
+use crate::utils::storage;
+
+// A filter that never finishes (one waiting on a tty, say) is killed after this
+const FILTER_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct FilterPresets {
+    path: PathBuf,
+}
+
+impl FilterPresets {
+    pub fn new() -> Self {
+        Self { path: storage::data_dir().join("filters.json") }
+    }
+
+    // Name -> pipeline
+    pub fn list(&self) -> BTreeMap<String, String> {
+        durable::load_json(&self.path).unwrap_or_default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.list().remove(name)
+    }
+
+    // Replaces a preset of the same name
+    pub fn save(&self, name: &str, pipeline: &str) -> Result<()> {
+        let mut presets = self.list();
+        presets.insert(name.to_string(), pipeline.to_string());
+        self.write(&presets)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let mut presets = self.list();
+        if presets.remove(name).is_none() {
+            bail!("No filter preset {}", name);
+        }
+        self.write(&presets)
+    }
+
+    fn write(&self, presets: &BTreeMap<String, String>) -> Result<()> {
+        durable::write_atomic(&self.path, serde_json::to_string_pretty(presets)?.as_bytes())
+    }
+}
+
+impl Default for FilterPresets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Runs `pipeline` through `shell` with `input` on stdin and returns its output
+// lines. A failing pipeline with no output is an error only if it said why:
+// grep finding nothing exits 1 silently, and that's just an empty result.
+pub async fn run(shell: &str, pipeline: &str, env: &BTreeMap<String, String>, input: String) -> Result<Vec<String>> {
+    let mut child = Command::new(shell)
+        .arg("-c")
+        .arg(pipeline)
+        .envs(env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start {}", pipeline))?;
+
+    // Fed from a task: a filter that stops reading early (`head`) closes its
+    // stdin, and the write error that follows doesn't matter
+    let mut stdin = child.stdin.take().context("Filter has no stdin")?;
+    let writer = tokio::spawn(async move {
+        let _ = stdin.write_all(input.as_bytes()).await;
+    });
+    let output = match tokio::time::timeout(FILTER_TIMEOUT, child.wait_with_output()).await {
+        Ok(output) => output?,
+        Err(_) => bail!("`{}` timed out after {:?}", pipeline, FILTER_TIMEOUT),
+    };
+    writer.abort();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() && output.stdout.is_empty() && !stderr.trim().is_empty() {
+        bail!("{}", stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(String::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn pipes_input_through_the_shell() {
+        let input = "GET /a 200\nGET /b 500\nPOST /c 500\n".to_string();
+        let lines = run("/bin/sh", "grep 500 | cut -d' ' -f2", &BTreeMap::new(), input.clone()).await.unwrap();
+        assert_eq!(lines, vec!["/b", "/c"]);
+
+        assert!(run("/bin/sh", "grep nothing", &BTreeMap::new(), input.clone()).await.unwrap().is_empty());
+        assert!(run("/bin/sh", "no-such-filter-command", &BTreeMap::new(), input).await.is_err());
+    }
+}
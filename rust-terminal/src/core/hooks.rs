@@ -0,0 +1,90 @@
+// User-configured shell hooks around every command
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::warn;
+
+use crate::core::terminal::CommandResult;
+
+// A slow pre-command hook delays the command itself; give up on it after this
+const PRE_HOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Hooks run through the configured shell. Both see NEXTERM_COMMAND and
+// NEXTERM_CWD; post_command also gets NEXTERM_EXIT_CODE and NEXTERM_DURATION_MS.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HooksConfig {
+    #[serde(default)]
+    pub pre_command: Option<String>,
+    #[serde(default)]
+    pub post_command: Option<String>,
+}
+
+pub struct Hooks {
+    config: HooksConfig,
+    shell: String,
+}
+
+impl Hooks {
+    pub fn new(config: &HooksConfig, shell: &str) -> Self {
+        Self {
+            config: config.clone(),
+            shell: shell.to_string(),
+        }
+    }
+
+    // Runs to completion (or timeout) before the command starts
+    pub async fn pre_command(&self, command: &str) {
+        let Some(hook) = &self.config.pre_command else {
+            return;
+        };
+        let mut child = match self.hook_command(hook, command).kill_on_drop(true).spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                warn!("pre_command hook failed to start: {}", e);
+                return;
+            }
+        };
+        match tokio::time::timeout(PRE_HOOK_TIMEOUT, child.wait()).await {
+            Ok(Ok(status)) if !status.success() => warn!("pre_command hook exited with {}", status),
+            Ok(Err(e)) => warn!("pre_command hook failed: {}", e),
+            Err(_) => warn!("pre_command hook timed out after {:?}", PRE_HOOK_TIMEOUT),
+            Ok(Ok(_)) => {}
+        }
+    }
+
+    // Runs in the background; the next command doesn't wait for it
+    pub fn post_command(&self, result: &CommandResult) {
+        let Some(hook) = &self.config.post_command else {
+            return;
+        };
+        let spawned = self
+            .hook_command(hook, &result.command)
+            .env("NEXTERM_EXIT_CODE", result.exit_code.to_string())
+            .env("NEXTERM_DURATION_MS", result.duration_ms.to_string())
+            .spawn();
+        match spawned {
+            Ok(mut child) => {
+                tokio::spawn(async move {
+                    if let Ok(status) = child.wait().await {
+                        if !status.success() {
+                            warn!("post_command hook exited with {}", status);
+                        }
+                    }
+                });
+            }
+            Err(e) => warn!("post_command hook failed to start: {}", e),
+        }
+    }
+
+    fn hook_command(&self, hook: &str, command: &str) -> Command {
+        let mut cmd = Command::new(&self.shell);
+        cmd.arg("-c")
+            .arg(hook)
+            .env("NEXTERM_COMMAND", command)
+            .env("NEXTERM_CWD", std::env::current_dir().unwrap_or_default())
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null());
+        cmd
+    }
+}
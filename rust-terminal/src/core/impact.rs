@@ -0,0 +1,388 @@
+// What a file-changing command (`rm`, `mv`, `cp -r`, `chmod -R`, ...) would
+// touch, worked out before it runs: globs are expanded the way the shell
+// would, directories walked when the command recurses into them, and the
+// files, directories and bytes counted up. With
+//
+//   [impact]
+//   preview = true
+//   min_files = 20
+//
+// such commands are held for a yes/no with the preview whenever they touch
+// at least `min_files` files and directories, so an overly broad glob is
+// caught while it can still be cancelled. `impact <command>` shows the
+// preview without running anything. Walking can take a while on a slow disk,
+// so the guard does it off the UI thread.
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::core::{binary, tee};
+
+// Entries walked per command before counting stops
+const MAX_WALK: usize = 100_000;
+// Matches listed per argument
+const LISTED: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpactConfig {
+    #[serde(default)]
+    pub preview: bool,
+    #[serde(default = "default_min_files")]
+    pub min_files: usize,
+}
+
+fn default_min_files() -> usize {
+    1
+}
+
+impl Default for ImpactConfig {
+    fn default() -> Self {
+        Self { preview: false, min_files: default_min_files() }
+    }
+}
+
+// Where the files are among a program's arguments
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    Files,
+    // After the mode or owner (chmod, chown)
+    AfterFirst,
+    // Before the destination (cp, mv)
+    BeforeLast,
+}
+
+const PROGRAMS: &[(&str, Shape)] = &[
+    ("rm", Shape::Files),
+    ("rmdir", Shape::Files),
+    ("unlink", Shape::Files),
+    ("shred", Shape::Files),
+    ("mv", Shape::BeforeLast),
+    ("cp", Shape::BeforeLast),
+    ("rsync", Shape::BeforeLast),
+    ("chmod", Shape::AfterFirst),
+    ("chown", Shape::AfterFirst),
+    ("chgrp", Shape::AfterFirst),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Impact {
+    // The part of the command line it is about
+    pub command: String,
+    // Each file argument as typed, and the paths it came to
+    pub arguments: Vec<(String, Vec<PathBuf>)>,
+    pub destination: Option<String>,
+    pub files: usize,
+    pub directories: usize,
+    pub bytes: u64,
+    // Counting stopped at MAX_WALK
+    pub truncated: bool,
+}
+
+impl Impact {
+    pub fn touched(&self) -> usize {
+        self.files + self.directories
+    }
+
+    // The arguments, what they matched and the counts, paths shown
+    // relative to `cwd`
+    pub fn lines(&self, cwd: &Path) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (argument, paths) in &self.arguments {
+            let shown: Vec<String> = paths
+                .iter()
+                .take(LISTED)
+                .map(|path| path.strip_prefix(cwd).unwrap_or(path).display().to_string())
+                .collect();
+            let line = match paths.len() {
+                0 => format!("  {}: matches nothing", argument),
+                n if n > LISTED => format!("  {} → {} … and {} more", argument, shown.join(", "), n - LISTED),
+                _ if shown.len() == 1 && shown[0] == *argument => format!("  {}", argument),
+                _ => format!("  {} → {}", argument, shown.join(", ")),
+            };
+            lines.push(line);
+        }
+        if let Some(destination) = &self.destination {
+            lines.push(format!("  into {}", destination));
+        }
+        let count = |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+        lines.push(format!(
+            "  {}{}, {}, {}",
+            if self.truncated { "over " } else { "" },
+            count(self.files, "file", "files"),
+            count(self.directories, "directory", "directories"),
+            binary::size_label(self.bytes as usize)
+        ));
+        lines
+    }
+}
+
+// Each preview's lines, under its part of the command line when there are several
+pub fn describe(impacts: &[Impact], cwd: &Path) -> Vec<String> {
+    let headed = impacts.len() > 1;
+    impacts
+        .iter()
+        .flat_map(|impact| headed.then(|| format!("$ {}", impact.command)).into_iter().chain(impact.lines(cwd)))
+        .collect()
+}
+
+// One preview per file-changing part of `command` (`a && b`, `a; b`, `a | b`).
+// Paths are relative to `cwd`, or to where a `cd` in the line went
+pub fn preview(command: &str, cwd: &Path) -> Vec<Impact> {
+    let mut cwd = cwd.to_path_buf();
+    let mut impacts = Vec::new();
+    for segment in segments(command) {
+        let segment = segment.trim();
+        match segment.strip_prefix("cd").filter(|dir| dir.is_empty() || dir.starts_with(' ')) {
+            Some(dir) => cwd = cwd.join(unquote(dir.trim()).or_else(|| unquote("~")).unwrap_or_default()),
+            None => impacts.extend(preview_segment(segment, &cwd)),
+        }
+    }
+    impacts
+}
+
+// Whether `command` has a part `preview` looks at, without touching the disk
+pub fn applies(command: &str) -> bool {
+    segments(command).iter().any(|segment| program(segment).is_some())
+}
+
+// Split at `&&`, `||`, `;` and `|` outside quotes
+fn segments(command: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for at in tee::unquoted(command) {
+        if at < start {
+            continue;
+        }
+        let Some(separator) = ["&&", "||", ";", "|"].into_iter().find(|s| command[at..].starts_with(s)) else {
+            continue;
+        };
+        segments.push(&command[start..at]);
+        start = at + separator.len();
+    }
+    segments.push(&command[start..]);
+    segments
+}
+
+// `word` without its quotes, `~` expanded unless quoted; None when empty
+fn unquote(word: &str) -> Option<String> {
+    let quoted = word.len() > 1 && (word.starts_with('\'') || word.starts_with('"'));
+    let word = if quoted {
+        word[1..].trim_end_matches(&word[..1]).to_string()
+    } else {
+        shellexpand::tilde(word).into_owned()
+    };
+    Some(word).filter(|word| !word.is_empty())
+}
+
+// The program and the words after it; leading VAR=value assignments, and
+// sudo, don't count
+fn program(segment: &str) -> Option<(&str, Shape, Vec<&str>)> {
+    let mut words = segment
+        .split_whitespace()
+        .skip_while(|w| (w.contains('=') && !w.starts_with('-')) || *w == "sudo");
+    let program = words.next()?;
+    let program = program.rsplit('/').next().unwrap_or(program);
+    let shape = PROGRAMS.iter().find(|(name, _)| *name == program)?.1;
+    Some((program, shape, words.collect()))
+}
+
+// A chmod mode: `644`, `u+x,go-w`, and `-w`, which isn't a flag
+fn is_mode(word: &str) -> bool {
+    static MODE: OnceLock<Regex> = OnceLock::new();
+    MODE.get_or_init(|| {
+        let clause = r"[ugoa]*([-+=][rwxXstugo]*)+";
+        Regex::new(&format!(r"^([0-7]{{1,4}}|{0}(,{0})*)$", clause)).expect("valid mode pattern")
+    })
+    .is_match(word)
+}
+
+fn preview_segment(segment: &str, cwd: &Path) -> Option<Impact> {
+    let (program, mut shape, mut words) = program(segment)?;
+    if program == "chmod" {
+        if let Some(at) = words.iter().position(|w| is_mode(w)) {
+            words.remove(at);
+            shape = Shape::Files;
+        }
+    }
+
+    let (flags, mut arguments): (Vec<&str>, Vec<&str>) = words.into_iter().partition(|w| w.starts_with('-'));
+    // -r, -R, -a (cp, rsync) or spelled out; mv takes directories whole
+    let recursive = program == "mv"
+        || flags.iter().any(|flag| match flag.strip_prefix("--") {
+            Some(long) => long == "recursive" || long == "archive",
+            None => flag.contains(['r', 'R', 'a']),
+        });
+    let mut destination = None;
+    match shape {
+        Shape::Files => {}
+        Shape::AfterFirst if !arguments.is_empty() => {
+            arguments.remove(0);
+        }
+        Shape::BeforeLast if arguments.len() > 1 => destination = arguments.pop().map(str::to_string),
+        _ => {}
+    }
+
+    let mut impact = Impact { command: segment.to_string(), destination, ..Impact::default() };
+    let mut walked = 0;
+    for argument in arguments {
+        let paths = expand(argument, cwd);
+        for path in &paths {
+            count(path, recursive, &mut impact, &mut walked);
+        }
+        impact.arguments.push((argument.to_string(), paths));
+    }
+    Some(impact)
+}
+
+fn count(path: &Path, recursive: bool, impact: &mut Impact, walked: &mut usize) {
+    let Ok(metadata) = path.symlink_metadata() else {
+        return;
+    };
+    if !metadata.is_dir() {
+        impact.files += 1;
+        impact.bytes += metadata.len();
+        return;
+    }
+    if !recursive {
+        impact.directories += 1;
+        return;
+    }
+    for entry in walkdir::WalkDir::new(path).into_iter().filter_map(Result::ok) {
+        *walked += 1;
+        if *walked > MAX_WALK {
+            impact.truncated = true;
+            return;
+        }
+        if entry.file_type().is_dir() {
+            impact.directories += 1;
+        } else {
+            impact.files += 1;
+            impact.bytes += entry.metadata().map_or(0, |m| m.len());
+        }
+    }
+}
+
+// What the shell makes of `word`: quoted words stay as they are, globs are
+// matched one path component at a time. Paths that don't exist are dropped,
+// so an empty list means nothing would be touched
+fn expand(word: &str, cwd: &Path) -> Vec<PathBuf> {
+    let quoted = word.len() > 1 && (word.starts_with('\'') || word.starts_with('"'));
+    let Some(word) = unquote(word) else {
+        return Vec::new();
+    };
+    let path = Path::new(&word);
+    let mut matches = vec![if path.is_absolute() { PathBuf::from("/") } else { cwd.to_path_buf() }];
+    for component in path.components() {
+        let part = match component {
+            Component::Normal(part) => part.to_string_lossy(),
+            Component::ParentDir => "..".into(),
+            _ => continue,
+        };
+        if quoted || !part.contains(['*', '?', '[']) {
+            matches.iter_mut().for_each(|path| path.push(part.as_ref()));
+            continue;
+        }
+        let Some(pattern) = glob_pattern(&part) else {
+            return Vec::new();
+        };
+        matches = matches
+            .iter()
+            .flat_map(|dir| {
+                let mut names: Vec<String> = std::fs::read_dir(dir)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                    // Like the shell: * doesn't match dotfiles
+                    .filter(|name| pattern.is_match(name) && (!name.starts_with('.') || part.starts_with('.')))
+                    .collect();
+                names.sort();
+                names.into_iter().map(move |name| dir.join(name))
+            })
+            .collect();
+    }
+    matches.retain(|path| path.symlink_metadata().is_ok());
+    matches
+}
+
+// `*`, `?` and `[...]` of one path component as an anchored regex
+fn glob_pattern(glob: &str) -> Option<Regex> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '[' => {
+                pattern.push('[');
+                if chars.next_if(|&c| c == '!' || c == '^').is_some() {
+                    pattern.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        pattern.push('\\');
+                    }
+                    pattern.push(c);
+                }
+                pattern.push(']');
+            }
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_globs_and_counts_what_they_touch() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("build/deep")).unwrap();
+        std::fs::write(root.join("build/a.o"), "12345").unwrap();
+        std::fs::write(root.join("build/b.o"), "123").unwrap();
+        std::fs::write(root.join("build/.keep"), "").unwrap();
+        std::fs::write(root.join("build/deep/c.o"), "1").unwrap();
+
+        let impacts = preview("make clean && rm -rf build/* 'build/*'", root);
+        assert_eq!(impacts.len(), 1);
+        let rm = &impacts[0];
+        assert_eq!(rm.arguments[0].1, [root.join("build/a.o"), root.join("build/b.o"), root.join("build/deep")]);
+        assert!(rm.arguments[1].1.is_empty());
+        assert_eq!((rm.files, rm.directories, rm.bytes), (3, 1, 9));
+        assert_eq!(rm.lines(root).last().unwrap(), "  3 files, 1 directory, 9 bytes");
+
+        // Without -R chmod stops at the directory; cp's last argument is where it goes
+        let chmod = &preview("chmod 644 build/[ab].o build", root)[0];
+        assert_eq!((chmod.files, chmod.directories), (2, 1));
+        let cp = &preview("cp -r build/deep dist", root)[0];
+        assert_eq!((cp.files, cp.directories, cp.destination.as_deref()), (1, 1, Some("dist")));
+        assert!(preview("ls build/*", root).is_empty());
+        // The mode may look like a flag; separators in quotes don't split
+        let chmod = &preview("chmod -w build/a.o", root)[0];
+        assert_eq!((chmod.files, chmod.arguments[0].0.as_str()), (1, "build/a.o"));
+        assert_eq!(preview("echo 'a; rm -rf build' | tee x", root).len(), 0);
+        let cd = &preview("cd build && rm a.o", root)[0];
+        assert_eq!(cd.arguments[0].1, [root.join("build/a.o")]);
+        assert!(applies("make && rm -r build") && !applies("echo 'rm -r build'"));
+
+        let both = preview("rm build/*.o; rm nothing*", root);
+        assert_eq!(
+            describe(&both, root),
+            [
+                "$ rm build/*.o",
+                "  build/*.o → build/a.o, build/b.o",
+                "  2 files, 0 directories, 8 bytes",
+                "$ rm nothing*",
+                "  nothing*: matches nothing",
+                "  0 files, 0 directories, 0 bytes",
+            ]
+        );
+    }
+}
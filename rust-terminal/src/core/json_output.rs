@@ -0,0 +1,69 @@
+// Machine-readable CommandResult stream (JSON lines) for downstream tooling
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+// Suffix that makes a single command report its result as JSON in the output pane
+pub const INLINE_SUFFIX: &str = ":json";
+
+#[derive(Debug, Clone)]
+pub enum JsonTarget {
+    File(PathBuf),
+    Socket(PathBuf),
+    Stdout,
+}
+
+impl JsonTarget {
+    // `unix:/path/to.sock` selects a control socket, `-` standard output (for
+    // subcommands), anything else is a file path
+    pub fn parse(spec: &str) -> Self {
+        match spec.strip_prefix("unix:") {
+            Some(path) => JsonTarget::Socket(PathBuf::from(path)),
+            None if spec == "-" => JsonTarget::Stdout,
+            None => JsonTarget::File(PathBuf::from(spec)),
+        }
+    }
+}
+
+pub struct JsonSink {
+    writer: Box<dyn Write + Send>,
+}
+
+impl JsonSink {
+    pub fn open(target: &JsonTarget) -> Result<Self> {
+        let writer: Box<dyn Write + Send> = match target {
+            JsonTarget::File(path) => Box::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open JSON output file {}", path.display()))?,
+            ),
+            JsonTarget::Socket(path) => Box::new(
+                UnixStream::connect(path)
+                    .with_context(|| format!("Failed to connect to control socket {}", path.display()))?,
+            ),
+            JsonTarget::Stdout => Box::new(std::io::stdout()),
+        };
+        Ok(Self { writer })
+    }
+
+    // A CommandResult, or a status report
+    pub fn emit(&mut self, value: &impl Serialize) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// Splits a trailing `:json` off `command`
+pub fn strip_inline(command: &str) -> (&str, bool) {
+    match command.trim_end().strip_suffix(INLINE_SUFFIX) {
+        Some(rest) => (rest.trim_end(), true),
+        None => (command, false),
+    }
+}
@@ -2,3 +2,37 @@
 pub mod terminal;
 pub mod command;
 pub mod executor;
+pub mod process_monitor;
+pub mod policy;
+pub mod json_output;
+pub mod preflight;
+pub mod dotenv;
+pub mod hooks;
+pub mod workflow;
+pub mod pty;
+pub mod shell;
+pub mod vt;
+pub mod triggers;
+pub mod daemons;
+pub mod filters;
+pub mod environments;
+pub mod roaming;
+pub mod retention;
+pub mod scriptify;
+pub mod tee;
+pub mod actions;
+pub mod encoding;
+pub mod binary;
+pub mod bell;
+pub mod status;
+pub mod sinks;
+pub mod rerun;
+pub mod postprocess;
+pub mod operations;
+pub mod workspace_search;
+pub mod workflow_deps;
+pub mod peer;
+pub mod modes;
+pub mod prompts;
+pub mod variables;
+pub mod impact;
@@ -0,0 +1,228 @@
+// User-defined modal key layers, hydra/which-key style. A mode is entered
+// with its key (or `mode <name>`); while it's active single keys run its
+// commands and the status bar shows it with its keys. Esc or the mode's key
+// leaves it. Configured as
+//
+//   [modes.kube]
+//   key = "Alt+K"
+//   title = "Kubernetes"
+//   [modes.kube.keys]
+//   p = "kubectl get pods"
+//   l = { run = "kubectl logs -f deploy/api", label = "api logs", exit = true }
+//
+// Other plain keys do nothing while in a mode, so a stray letter doesn't
+// reach the prompt; keys with Ctrl or Alt work as usual.
+use crossterm::event::{KeyCode, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::warn;
+
+use crate::core::actions::{Action, KeyChord, Target};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModeConfig {
+    // Enters the mode, and leaves it again
+    #[serde(default)]
+    pub key: Option<String>,
+    // For the status bar and palette; the mode's name otherwise
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub keys: BTreeMap<String, BindingConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BindingConfig {
+    Run(String),
+    Full {
+        run: String,
+        #[serde(default)]
+        label: Option<String>,
+        // Leave the mode after running it
+        #[serde(default)]
+        exit: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub key: KeyChord,
+    pub run: String,
+    pub label: String,
+    pub exit: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mode {
+    pub name: String,
+    pub title: String,
+    key: Option<KeyChord>,
+    pub bindings: Vec<Binding>,
+}
+
+impl Mode {
+    // Which-key style, for the status bar: "p get pods · Shift+P all pods"
+    pub fn hints(&self) -> String {
+        let hints: Vec<String> = self
+            .bindings
+            .iter()
+            .map(|binding| {
+                // Plain letters as typed
+                let key = match binding.key.label() {
+                    label if label.chars().count() == 1 => label.to_lowercase(),
+                    label => label,
+                };
+                format!("{} {}", key, binding.label)
+            })
+            .collect();
+        hints.join(" · ")
+    }
+}
+
+// What a key press came to while a mode is active
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModeKey {
+    Run(String),
+    Left,
+    // A plain key the mode doesn't bind
+    Ignored,
+    // Not the mode's business
+    Pass,
+}
+
+#[derive(Default)]
+pub struct Modes {
+    modes: Vec<Mode>,
+    active: Option<usize>,
+}
+
+impl Modes {
+    // Keys that don't parse are logged and left out
+    pub fn new(configs: &BTreeMap<String, ModeConfig>) -> Self {
+        let parse = |text: &str, mode: &str| {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                // "P" is Shift+P here, where plain letters are keys of their own
+                (Some(c), None) if c.is_ascii_uppercase() => {
+                    Some(KeyChord::new(KeyCode::Char(c), KeyModifiers::NONE))
+                }
+                _ => KeyChord::parse(text).map_err(|e| warn!("Mode {}: {:#}", mode, e)).ok(),
+            }
+        };
+        let modes = configs
+            .iter()
+            .map(|(name, config)| Mode {
+                name: name.clone(),
+                title: config.title.clone().unwrap_or_else(|| name.clone()),
+                key: config.key.as_deref().and_then(|key| parse(key, name)),
+                bindings: config
+                    .keys
+                    .iter()
+                    .filter_map(|(key, binding)| {
+                        let (run, label, exit) = match binding {
+                            BindingConfig::Run(run) => (run.clone(), None, false),
+                            BindingConfig::Full { run, label, exit } => (run.clone(), label.clone(), *exit),
+                        };
+                        let label = label.unwrap_or_else(|| run.clone());
+                        Some(Binding { key: parse(key, name)?, run, label, exit })
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self { modes, active: None }
+    }
+
+    // Palette entries that enter each mode, with its key
+    pub fn actions(&self) -> Vec<Action> {
+        self.modes
+            .iter()
+            .map(|mode| {
+                let target = Target::Command(format!("mode {}", mode.name));
+                Action::new(&format!("mode.{}", mode.name), &format!("{} mode", mode.title), target)
+                    .with_icon("⌨")
+                    .with_keywords(&["mode", "keys", mode.name.as_str()])
+                    .with_key(mode.key)
+            })
+            .collect()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.modes.iter().map(|mode| mode.name.as_str()).collect()
+    }
+
+    // Entering the active mode again leaves it
+    pub fn toggle(&mut self, name: &str) -> Option<bool> {
+        let index = self.modes.iter().position(|mode| mode.name == name)?;
+        self.active = if self.active == Some(index) { None } else { Some(index) };
+        Some(self.active.is_some())
+    }
+
+    pub fn leave(&mut self) {
+        self.active = None;
+    }
+
+    pub fn active(&self) -> Option<&Mode> {
+        self.modes.get(self.active?)
+    }
+
+    pub fn handle(&mut self, key: KeyChord) -> ModeKey {
+        let Some(mode) = self.active() else {
+            return ModeKey::Pass;
+        };
+        if key == KeyChord::new(KeyCode::Esc, KeyModifiers::NONE) || Some(key) == mode.key {
+            self.active = None;
+            return ModeKey::Left;
+        }
+        match mode.bindings.iter().find(|binding| binding.key == key) {
+            Some(binding) => {
+                let run = binding.run.clone();
+                if binding.exit {
+                    self.active = None;
+                }
+                ModeKey::Run(run)
+            }
+            None if key.is_typing() => ModeKey::Ignored,
+            None => ModeKey::Pass,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_bound_keys_until_left() {
+        let config: BTreeMap<String, ModeConfig> = toml::from_str(
+            r#"
+            [kube]
+            key = "Alt+K"
+            [kube.keys]
+            p = "kubectl get pods"
+            P = "kubectl get pods -A"
+            l = { run = "kubectl logs -f deploy/api", label = "api logs", exit = true }
+            "#,
+        )
+        .unwrap();
+        let mut modes = Modes::new(&config);
+        let key = |text: &str| KeyChord::parse(text).unwrap();
+        assert_eq!(modes.actions()[0].key, Some(key("Alt+K")));
+        assert_eq!(modes.handle(key("P")), ModeKey::Pass);
+
+        assert_eq!(modes.toggle("kube"), Some(true));
+        let hints = modes.active().unwrap().hints();
+        assert_eq!(hints, "Shift+P kubectl get pods -A · l api logs · p kubectl get pods");
+        assert_eq!(modes.handle(key("p")), ModeKey::Run("kubectl get pods".into()));
+        let shift_p = KeyChord::new(KeyCode::Char('P'), KeyModifiers::NONE);
+        assert_eq!(modes.handle(shift_p), ModeKey::Run("kubectl get pods -A".into()));
+        assert_eq!(modes.handle(key("X")), ModeKey::Ignored);
+        assert_eq!(modes.handle(key("Ctrl+C")), ModeKey::Pass);
+        assert_eq!(modes.handle(key("L")), ModeKey::Run("kubectl logs -f deploy/api".into()));
+        assert!(modes.active().is_none());
+
+        modes.toggle("kube");
+        assert_eq!(modes.handle(key("Alt+K")), ModeKey::Left);
+        assert_eq!(modes.toggle("nope"), None);
+    }
+}
@@ -0,0 +1,323 @@
+// Long-running activity in one place: the running command, AI requests,
+// plugin indexing at startup and completion spec downloads each register an
+// operation for as long as they run, with progress where it's known and a way
+// to cancel them where there is one. `activity` lists them in a panel with
+// per-item cancel; work started with `spawn` reports how it went as a
+// notification instead of disappearing into the background.
+//
+// Progress is reported the same way by everything: `start` an operation,
+// give it a total, `advance` it a step at a time with a label for what it's
+// on now, and `finish` it (or drop it). The status bar and the panel draw
+// the same bar from that.
+use anyhow::{bail, Result};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::task::AbortHandle;
+
+use crate::utils::clock;
+use crate::utils::startup::Pending;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Command,
+    Ai,
+    Indexing,
+    Download,
+    Workflow,
+    Plugin,
+}
+
+impl Kind {
+    pub fn icon(self) -> &'static str {
+        match self {
+            Kind::Command => "❯",
+            Kind::Ai => "✦",
+            Kind::Indexing => "≡",
+            Kind::Download => "↓",
+            Kind::Workflow => "▶",
+            Kind::Plugin => "◆",
+        }
+    }
+}
+
+// How an operation is stopped
+#[derive(Debug)]
+pub enum Cancel {
+    // SIGTERM to the process group, or the process if it has none of its own
+    Signal(u32),
+    Abort(AbortHandle),
+}
+
+// One row of the panel
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationInfo {
+    pub id: u64,
+    pub kind: Kind,
+    pub title: String,
+    pub secs: u64,
+    // 0.0..=1.0, for operations that count steps
+    pub progress: Option<f32>,
+    // Steps done of the total, for operations that count them
+    pub steps: Option<(u64, u64)>,
+    // What it's on now
+    pub label: Option<String>,
+    pub cancellable: bool,
+    pub cancelled: bool,
+}
+
+struct Entry {
+    id: u64,
+    kind: Kind,
+    title: String,
+    started: Instant,
+    done: u64,
+    total: Option<u64>,
+    label: Option<String>,
+    cancel: Option<Cancel>,
+    cancelled: bool,
+}
+
+#[derive(Default)]
+struct Inner {
+    next: u64,
+    entries: Vec<Entry>,
+    // From `spawn`ed work: Ok(message) or Err(error)
+    outcomes: Vec<std::result::Result<String, String>>,
+}
+
+#[derive(Clone, Default)]
+pub struct Operations {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Operations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Registered until the returned handle is dropped
+    pub fn start(&self, kind: Kind, title: impl Into<String>) -> Operation {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.next += 1;
+        let id = inner.next;
+        inner.entries.push(Entry {
+            id,
+            kind,
+            title: title.into(),
+            started: clock::now(),
+            done: 0,
+            total: None,
+            label: None,
+            cancel: None,
+            cancelled: false,
+        });
+        Operation { id, operations: self.clone() }
+    }
+
+    // Runs `work` as a task of its own; what it returns becomes a notification
+    pub fn spawn<F, Fut>(&self, kind: Kind, title: impl Into<String>, work: F) -> u64
+    where
+        F: FnOnce(Progress) -> Fut,
+        Fut: Future<Output = Result<String>> + Send + 'static,
+    {
+        let operation = self.start(kind, title);
+        let id = operation.id;
+        let inner = self.inner.clone();
+        let future = work(Progress { id, operations: self.clone() });
+        let task = tokio::spawn(async move {
+            let outcome = future.await.map_err(|e| format!("{:#}", e));
+            inner.lock().unwrap_or_else(|e| e.into_inner()).outcomes.push(outcome);
+            drop(operation);
+        });
+        self.with_entry(id, |entry| entry.cancel = Some(Cancel::Abort(task.abort_handle())));
+        id
+    }
+
+    // `spawn` for work whose result the caller wants back rather than as a
+    // notification; cancelling it polls as Ready(None)
+    pub fn run<T, F, Fut>(&self, kind: Kind, title: &'static str, work: F) -> Pending<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(Progress) -> Fut,
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        let operation = self.start(kind, title);
+        let id = operation.id;
+        let future = work(Progress { id, operations: self.clone() });
+        let (pending, task) = Pending::task(title, async move {
+            let result = future.await;
+            drop(operation);
+            result
+        });
+        self.with_entry(id, |entry| entry.cancel = Some(Cancel::Abort(task)));
+        pending
+    }
+
+    pub fn list(&self) -> Vec<OperationInfo> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner
+            .entries
+            .iter()
+            .map(|entry| OperationInfo {
+                id: entry.id,
+                kind: entry.kind,
+                title: entry.title.clone(),
+                secs: clock::since(entry.started).as_secs(),
+                progress: entry.total.map(|total| entry.done.min(total) as f32 / total.max(1) as f32),
+                steps: entry.total.map(|total| (entry.done.min(total), total)),
+                label: entry.label.clone(),
+                cancellable: entry.cancel.is_some(),
+                cancelled: entry.cancelled,
+            })
+            .collect()
+    }
+
+    pub fn cancel(&self, id: u64) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(entry) = inner.entries.iter_mut().find(|entry| entry.id == id) else {
+            bail!("Operation {} has already finished", id);
+        };
+        match &entry.cancel {
+            None => bail!("{} can't be cancelled", entry.title),
+            Some(Cancel::Signal(pid)) => {
+                let pid = *pid as i32;
+                // SAFETY: kill has no memory effects
+                if unsafe { libc::kill(-pid, libc::SIGTERM) } < 0 && unsafe { libc::kill(pid, libc::SIGTERM) } < 0 {
+                    return Err(std::io::Error::last_os_error().into());
+                }
+            }
+            Some(Cancel::Abort(handle)) => {
+                handle.abort();
+                let title = entry.title.clone();
+                inner.entries.retain(|entry| entry.id != id);
+                inner.outcomes.push(Err(format!("{} cancelled", title)));
+                return Ok(());
+            }
+        }
+        entry.cancelled = true;
+        Ok(())
+    }
+
+    // What `spawn`ed work came to since the last call
+    pub fn take_outcomes(&self) -> Vec<std::result::Result<String, String>> {
+        std::mem::take(&mut self.inner.lock().unwrap_or_else(|e| e.into_inner()).outcomes)
+    }
+
+    fn with_entry(&self, id: u64, f: impl FnOnce(&mut Entry)) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = inner.entries.iter_mut().find(|entry| entry.id == id) {
+            f(entry);
+        }
+    }
+}
+
+// A registered operation; finished when dropped
+pub struct Operation {
+    id: u64,
+    operations: Operations,
+}
+
+impl Operation {
+    pub fn cancel_with(&self, cancel: Cancel) {
+        self.operations.with_entry(self.id, |entry| entry.cancel = Some(cancel));
+    }
+
+    // For reporting from wherever the work happens
+    pub fn progress(&self) -> Progress {
+        Progress { id: self.id, operations: self.operations.clone() }
+    }
+
+    pub fn finish(self) {}
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        let mut inner = self.operations.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.entries.retain(|entry| entry.id != self.id);
+    }
+}
+
+// What `spawn`ed work reports how far along it is through
+#[derive(Clone)]
+pub struct Progress {
+    id: u64,
+    operations: Operations,
+}
+
+impl Progress {
+    // Steps it will take; `advance` counts them off
+    pub fn set_total(&self, total: u64) {
+        self.operations.with_entry(self.id, |entry| entry.total = Some(total));
+    }
+
+    // On to the next step, which `label` names. The first call starts step 1
+    pub fn advance(&self, label: impl Into<String>) {
+        let label = label.into();
+        self.operations.with_entry(self.id, |entry| {
+            if entry.label.is_some() {
+                entry.done += 1;
+            }
+            entry.label = Some(label);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tracks_and_cancels_operations() {
+        let operations = Operations::new();
+        let indexing = operations.start(Kind::Indexing, "Indexing plugins");
+        assert!(operations.cancel(operations.list()[0].id).is_err());
+        drop(indexing);
+        assert!(operations.list().is_empty());
+
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        operations.spawn(Kind::Download, "Fetching", |progress| async move {
+            progress.set_total(2);
+            progress.advance("first");
+            progress.advance("second");
+            let _ = rx.await;
+            Ok("Fetched".to_string())
+        });
+        tokio::task::yield_now().await;
+        let fetching = operations.list().remove(0);
+        assert_eq!((fetching.progress, fetching.cancellable), (Some(0.5), true));
+        tx.send(()).unwrap();
+        while !operations.list().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(operations.take_outcomes(), vec![Ok("Fetched".to_string())]);
+
+        let workflow = operations.start(Kind::Workflow, "deploy");
+        let progress = workflow.progress();
+        progress.set_total(4);
+        progress.advance("build");
+        progress.advance("test");
+        let info = operations.list().remove(0);
+        assert_eq!((info.steps, info.label.as_deref(), info.progress), (Some((1, 4)), Some("test"), Some(0.25)));
+        workflow.finish();
+        assert!(operations.list().is_empty());
+
+        let id = operations.spawn(Kind::Ai, "Waiting", |_| std::future::pending());
+        operations.cancel(id).unwrap();
+        assert!(operations.list().is_empty());
+        assert_eq!(operations.take_outcomes(), vec![Err("Waiting cancelled".to_string())]);
+
+        let mut reviewing = operations.run(Kind::Ai, "Reviewing", |_| std::future::pending::<u32>());
+        operations.cancel(operations.list()[0].id).unwrap();
+        while reviewing.poll().is_pending() {
+            tokio::task::yield_now().await;
+        }
+        let mut answering = operations.run(Kind::Ai, "Answering", |_| async { 42 });
+        loop {
+            match answering.poll() {
+                std::task::Poll::Ready(answer) => break assert_eq!(answer, Some(42)),
+                std::task::Poll::Pending => tokio::task::yield_now().await,
+            }
+        }
+    }
+}
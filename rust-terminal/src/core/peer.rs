@@ -0,0 +1,164 @@
+// Clipboard and file hand-off between two NexTerms, one of them running
+// inside the other over SSH. The SSH session's terminal stream is the
+// channel: the inner one (remote) writes OSC 7770 sequences, which the outer
+// one (local) picks out of the ssh command's output, and the outer one
+// answers with bracketed pastes the inner one recognizes by their prefix.
+// Nothing is sent until the other end has said hello, so a plain shell never
+// gets a paste it wouldn't understand, and nothing is taken in without the
+// user accepting it.
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Private OSC number the inner NexTerm writes
+pub const OSC: &str = crate::core::vt::PEER_OSC;
+const PASTE_PREFIX: &str = "nexterm-peer:";
+// Files bigger than this are refused; the terminal stream is no file transfer
+pub const MAX_FILE: usize = 256 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    Hello,
+    // The inner NexTerm is exiting
+    Bye,
+    Offer { id: u64, item: Item },
+    Reply { id: u64, accepted: bool },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Item {
+    Clipboard { text: String },
+    // `data` in base64
+    File { name: String, data: String },
+}
+
+impl Item {
+    pub fn file(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if data.len() > MAX_FILE {
+            bail!("{} is {} KB; files up to {} KB can be sent", path.display(), data.len() / 1024, MAX_FILE / 1024);
+        }
+        let name = path.file_name().context("Not a file")?.to_string_lossy().into_owned();
+        Ok(Item::File { name, data: base64::engine::general_purpose::STANDARD.encode(data) })
+    }
+
+    // For the accept prompt
+    pub fn describe(&self) -> String {
+        match self {
+            Item::Clipboard { text } => format!("clipboard text ({} characters)", text.chars().count()),
+            Item::File { name, data } => {
+                let bytes = (data.len() / 4 * 3).saturating_sub(data.matches('=').count());
+                format!("file {} ({} bytes)", name, bytes)
+            },
+        }
+    }
+}
+
+// Which way the other NexTerm is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    // Running in our ssh command's pty
+    Inner,
+    // Running us, over ssh
+    Outer,
+}
+
+fn encode(message: &Message) -> String {
+    let json = serde_json::to_string(message).unwrap_or_default();
+    base64::engine::general_purpose::STANDARD.encode(json)
+}
+
+fn decode(payload: &str) -> Option<Message> {
+    let json = base64::engine::general_purpose::STANDARD.decode(payload.trim()).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+// What the inner NexTerm writes to its terminal
+pub fn sequence(message: &Message) -> String {
+    format!("\x1b]{};{}\x07", OSC, encode(message))
+}
+
+// An OSC body the screen kept, if it's one of ours
+pub fn from_osc(body: &str) -> Option<Message> {
+    decode(body.strip_prefix(OSC)?.strip_prefix(';')?)
+}
+
+// What the outer NexTerm pastes into the inner one
+pub fn paste(message: &Message) -> String {
+    format!("{}{}", PASTE_PREFIX, encode(message))
+}
+
+pub fn from_paste(text: &str) -> Option<Message> {
+    decode(text.strip_prefix(PASTE_PREFIX)?)
+}
+
+// Whether `command` is an ssh session, the only place an inner NexTerm can
+// write from; any other program printing the sequence is ignored
+pub fn is_ssh(command: &str) -> bool {
+    let program = command
+        .split_whitespace()
+        .find(|w| (!w.contains('=') || w.starts_with('-')) && *w != "sudo")
+        .unwrap_or("");
+    program.rsplit('/').next() == Some("ssh")
+}
+
+// Whether we run over ssh, where an outer NexTerm may be listening
+pub fn over_ssh() -> bool {
+    std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some()
+}
+
+// Writes an accepted file into `dir` under its own name, or `name (2)` and
+// so on when that's taken; returns where it went
+pub fn save_file(dir: &Path, name: &str, data: &str) -> Result<PathBuf> {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data).context("Invalid file data")?;
+    // Only the name; no directories from the other end
+    let name = Path::new(name).file_name().context("Invalid file name")?.to_string_lossy().into_owned();
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem.to_string(), format!(".{}", extension)),
+        _ => (name.clone(), String::new()),
+    };
+    let candidates = std::iter::once(dir.join(&name))
+        .chain((2..).map(|n| dir.join(format!("{} ({}){}", stem, n, extension))));
+    for path in candidates {
+        // create_new, so a file that appears meanwhile is never overwritten
+        let mut file = match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to write {}", path.display())),
+        };
+        file.write_all(&bytes).with_context(|| format!("Failed to write {}", path.display()))?;
+        return Ok(path);
+    }
+    bail!("No free file name")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_messages_and_saves_files() {
+        let offer = Message::Offer { id: 3, item: Item::Clipboard { text: "ssh-ed25519 AAAA".into() } };
+        let osc = sequence(&offer);
+        let body = osc.strip_prefix("\x1b]").and_then(|s| s.strip_suffix('\x07')).unwrap();
+        assert_eq!(from_osc(body), Some(offer.clone()));
+        assert_eq!(from_paste(&paste(&offer)), Some(offer));
+        assert_eq!(from_osc("52;c;aGk="), None);
+        assert_eq!(from_paste("echo hello"), None);
+        assert!(is_ssh("TERM=xterm /usr/bin/ssh -t host") && !is_ssh("cat ssh.txt"));
+        assert_eq!(Item::File { name: "x".into(), data: "=".into() }.describe(), "file x (0 bytes)");
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "hi").unwrap();
+        let Item::File { name, data } = Item::file(&dir.path().join("notes.txt")).unwrap() else {
+            panic!("expected a file");
+        };
+        assert_eq!(save_file(dir.path(), &name, &data).unwrap(), dir.path().join("notes (2).txt"));
+        assert_eq!(save_file(dir.path(), "../../etc/x", &data).unwrap(), dir.path().join("x"));
+        assert_eq!(std::fs::read_to_string(dir.path().join("x")).unwrap(), "hi");
+    }
+}
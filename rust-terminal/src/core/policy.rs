@@ -0,0 +1,76 @@
+// Restricted (kiosk) mode: whitelist-based command policy
+use serde::{Deserialize, Serialize};
+
+// Characters that would let a command escape the whitelist through the shell
+const SHELL_ESCAPES: &[&str] = &[";", "|", "&", "`", "$(", ">", "<", "\n"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestrictedConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    // Program names (first word of a command) that may be run
+    #[serde(default)]
+    pub allowed_commands: Vec<String>,
+}
+
+pub struct CommandPolicy {
+    allowed: Vec<String>,
+}
+
+impl CommandPolicy {
+    pub fn from_config(config: &RestrictedConfig) -> Option<Self> {
+        config.enabled.then(|| Self {
+            allowed: config.allowed_commands.clone(),
+        })
+    }
+
+    // Returns the reason `command` is refused, if any
+    pub fn check(&self, command: &str) -> Option<String> {
+        let command = command.trim();
+        if let Some(escape) = SHELL_ESCAPES.iter().find(|e| command.contains(*e)) {
+            return Some(format!("'{}' is not allowed in restricted mode", escape.escape_debug()));
+        }
+
+        let program = command.split_whitespace().next().unwrap_or("");
+        if program == "ai" {
+            return Some("AI execution is disabled in restricted mode".to_string());
+        }
+        if !self.allowed.iter().any(|a| a == program) {
+            return Some(format!("'{}' is not in the restricted-mode whitelist", program));
+        }
+        None
+    }
+}
+
+// Why a NexTerm feature that writes files or starts programs past the
+// whitelist (`:tee`, `logging start`, `edit-wait`, ...) is refused
+pub fn refuse(feature: &str) -> String {
+    format!("{} is not allowed in restricted mode", feature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> CommandPolicy {
+        CommandPolicy::from_config(&RestrictedConfig {
+            enabled: true,
+            allowed_commands: vec!["ls".to_string(), "echo".to_string()],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_whitelisted_command_allowed() {
+        assert!(policy().check("ls -la").is_none());
+    }
+
+    #[test]
+    fn test_unlisted_command_and_shell_escapes_blocked() {
+        let policy = policy();
+        assert!(policy.check("rm -rf /").is_some());
+        assert!(policy.check("echo hi; rm -rf /").is_some());
+        assert!(policy.check("echo $(whoami)").is_some());
+        assert!(policy.check("ai deploy").is_some());
+    }
+}
@@ -0,0 +1,248 @@
+// Output post-processors: what happens to a command's output lines between
+// the program and the output pane. Every line is cleaned first (decoded, the
+// \r of a CRLF dropped, bells taken out to ring), then goes through a
+// pipeline of steps run in order. Configured per command as
+//
+//   [[postprocess]]
+//   pattern = '^(cargo|make|gcc|tsc)\b'
+//   steps = ["strip_ansi", "redact", "colorize", "problems"]
+//
+//   [[postprocess]]
+//   pattern = '^cat '
+//   steps = ["strip_ansi", "truncate"]
+//   max_line = 300
+//
+// Steps are strip_ansi (escape sequences the pane can't draw, from commands
+// on pipes), redact (loaded .env values become <env:KEY>), truncate (lines
+// past `max_line` characters), colorize (error and warning lines drawn in
+// color; the text is left alone, the pane styles them) and problems (`file:line` diagnostics collected for
+// `problems`, from the output as a whole). The first rule whose pattern
+// matches the command applies; other commands get strip_ansi and problems.
+// tee files and `log` get the output as cleaned, before the steps.
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing::warn;
+
+use crate::ai::review::{Finding, Level};
+use crate::core::dotenv::DotEnv;
+use crate::core::encoding::Encoding;
+
+const DEFAULT_STEPS: &[Step] = &[Step::StripAnsi, Step::Problems];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    StripAnsi,
+    Redact,
+    Truncate,
+    Colorize,
+    Problems,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostProcessConfig {
+    pub pattern: String,
+    pub steps: Vec<Step>,
+    #[serde(default = "default_max_line")]
+    pub max_line: usize,
+}
+
+fn default_max_line() -> usize {
+    2000
+}
+
+// The steps for one command, handed to whatever streams its output
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    steps: Vec<Step>,
+    max_line: usize,
+    // For redact
+    dotenv: Option<DotEnv>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self { steps: DEFAULT_STEPS.to_vec(), max_line: default_max_line(), dotenv: None }
+    }
+}
+
+impl Pipeline {
+    // One line on its way to the output buffer
+    pub fn line(&self, mut line: String) -> String {
+        for step in &self.steps {
+            line = match step {
+                Step::StripAnsi if line.contains('\x1b') => strip_ansi(&line),
+                Step::Redact => match &self.dotenv {
+                    Some(dotenv) => dotenv.redact(&line),
+                    None => line,
+                },
+                Step::Truncate => truncate(line, self.max_line),
+                _ => line,
+            };
+        }
+        line
+    }
+
+    // Whether the pane draws error and warning lines in color, see `level`
+    pub fn colorizes(&self) -> bool {
+        self.steps.contains(&Step::Colorize)
+    }
+
+    // Diagnostics in a finished command's output, if this pipeline collects them
+    pub fn problems(&self, output: &str) -> Vec<Finding> {
+        match self.steps.contains(&Step::Problems) {
+            true => problems(output),
+            false => Vec::new(),
+        }
+    }
+}
+
+pub struct PostProcessors {
+    rules: Vec<(Regex, Pipeline)>,
+}
+
+impl PostProcessors {
+    pub fn new(configs: &[PostProcessConfig]) -> Self {
+        let rules = configs
+            .iter()
+            .filter_map(|config| match Regex::new(&config.pattern) {
+                Ok(pattern) => {
+                    let pipeline = Pipeline { steps: config.steps.clone(), max_line: config.max_line, dotenv: None };
+                    Some((pattern, pipeline))
+                }
+                Err(e) => {
+                    warn!("Ignoring postprocess rule {:?}: {}", config.pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn for_command(&self, command: &str, dotenv: Option<&DotEnv>) -> Pipeline {
+        let pipeline = self.rules.iter().find(|(pattern, _)| pattern.is_match(command)).map(|(_, p)| p.clone());
+        Pipeline { dotenv: dotenv.cloned(), ..pipeline.unwrap_or_default() }
+    }
+}
+
+// One line as read from a pipe, without its newline: decoded, without the \r
+// of a CRLF and without bells, which are counted so they ring
+pub fn clean(raw: &[u8], encoding: Encoding) -> (String, usize) {
+    let mut line = encoding.decode_line(raw.strip_suffix(b"\r").unwrap_or(raw));
+    let bells = line.matches('\x07').count();
+    if bells > 0 {
+        line.retain(|c| c != '\x07');
+    }
+    (line, bells)
+}
+
+// CSI (colors, cursor movement), OSC (titles, links) and two-byte escapes
+fn strip_ansi(line: &str) -> String {
+    static ESCAPES: OnceLock<Regex> = OnceLock::new();
+    let escapes = ESCAPES.get_or_init(|| {
+        Regex::new(r"\x1b(\[[0-?]*[ -/]*[@-~]|\][^\x07\x1b]*(\x07|\x1b\\)?|[@-Z\\-_])").expect("valid escape pattern")
+    });
+    escapes.replace_all(line, "").into_owned()
+}
+
+fn truncate(line: String, max: usize) -> String {
+    let count = line.chars().count();
+    if count <= max {
+        return line;
+    }
+    let kept: String = line.chars().take(max).collect();
+    format!("{}… ({} more characters)", kept, count - max)
+}
+
+// For colorize: the color the pane draws a line in
+pub fn level(line: &str) -> Option<Level> {
+    static LEVELS: OnceLock<(Regex, Regex)> = OnceLock::new();
+    let (error, warning) = LEVELS.get_or_init(|| {
+        (
+            Regex::new(r"(?i)\b(error|failed|fatal|panicked)\b").expect("valid error pattern"),
+            Regex::new(r"(?i)\bwarn(ing)?\b").expect("valid warning pattern"),
+        )
+    });
+    if error.is_match(line) {
+        Some(Level::Error)
+    } else if warning.is_match(line) {
+        Some(Level::Warning)
+    } else {
+        None
+    }
+}
+
+// `file:line[:col]: error: message` as gcc, clang, tsc and most linters print
+// them, and rustc's `error[E0308]: message` followed by `--> file:line:col`
+fn problems(output: &str) -> Vec<Finding> {
+    static PATTERNS: OnceLock<(Regex, Regex, Regex)> = OnceLock::new();
+    let (inline, header, arrow) = PATTERNS.get_or_init(|| {
+        (
+            Regex::new(r"^([^\s:]+):(\d+)(?::\d+)?:\s*(?:(fatal error|error|warning|note)\b[^:]*:)\s*(.*)$")
+                .expect("valid inline pattern"),
+            Regex::new(r"^(error|warning)(?:\[\w+\])?: (.*)$").expect("valid header pattern"),
+            Regex::new(r"^\s*--> ([^\s:]+):(\d+)(?::\d+)?").expect("valid arrow pattern"),
+        )
+    });
+    let level = |word: &str| match word {
+        "warning" => Level::Warning,
+        "note" => Level::Info,
+        _ => Level::Error,
+    };
+    let mut found = Vec::new();
+    let mut pending: Option<(Level, String)> = None;
+    for line in output.lines() {
+        let line = strip_ansi(line);
+        let line = line.as_str();
+        if let Some(captures) = inline.captures(line) {
+            found.push(Finding {
+                file: captures[1].to_string(),
+                line: captures[2].parse().ok(),
+                level: level(&captures[3]),
+                message: captures[4].to_string(),
+            });
+        } else if let Some(captures) = header.captures(line) {
+            pending = Some((level(&captures[1]), captures[2].to_string()));
+        } else if let (Some(captures), Some((level, message))) = (arrow.captures(line), pending.take()) {
+            found.push(Finding { file: captures[1].to_string(), line: captures[2].parse().ok(), level, message });
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_the_steps_of_the_matching_rule() {
+        let configs: Vec<PostProcessConfig> = vec![PostProcessConfig {
+            pattern: "^cat ".into(),
+            steps: vec![Step::Redact, Step::Truncate, Step::Colorize],
+            max_line: 12,
+        }];
+        let dotenv = DotEnv { files: Vec::new(), vars: [("TOKEN".to_string(), "s3cr3t-value".to_string())].into() };
+        let processors = PostProcessors::new(&configs);
+
+        let cat = processors.for_command("cat .env", Some(&dotenv));
+        assert_eq!(cat.line("TOKEN=s3cr3t-value".into()), "TOKEN=<env:T… (5 more characters)");
+        assert_eq!(cat.line("warning: x".into()), "warning: x");
+        assert!(cat.colorizes() && !processors.for_command("ls", None).colorizes());
+        assert_eq!(
+            (level("warning: x"), level("build failed"), level("ok")),
+            (Some(Level::Warning), Some(Level::Error), None)
+        );
+        assert_eq!(clean(b"\x07done\r", Encoding::Utf8), ("done".to_string(), 1));
+        assert!(cat.problems("src/a.c:3:1: error: oops").is_empty());
+
+        let make = processors.for_command("make", Some(&dotenv));
+        assert_eq!(make.line("\x1b[1;31merror\x1b[0m: \x1b]8;;http://x\x07link\x1b]8;;\x07".into()), "error: link");
+        let output = "src/a.c:3:14: warning: unused variable 'x' [-Wunused-variable]\n\
+                      error[E0308]: mismatched types\n  --> src/main.rs:4:5\n";
+        let found = make.problems(output);
+        assert_eq!(found.len(), 2);
+        assert_eq!((found[0].location(), found[0].level), ("src/a.c:3".to_string(), Level::Warning));
+        assert_eq!((found[1].location(), found[1].message.as_str()), ("src/main.rs:4".to_string(), "mismatched types"));
+    }
+}
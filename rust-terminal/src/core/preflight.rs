@@ -0,0 +1,243 @@
+// Pre-flight checks run before a command is executed
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::warn;
+
+use crate::utils::ssh_keys;
+
+// Prefix that runs a command without pre-flight checks
+pub const SKIP_PREFIX: char = '!';
+
+// bash's builtins and keywords; anything else not on PATH is asked of the
+// user's shell before it's reported missing
+const SHELL_BUILTINS: &[&str] = &[
+    "cd", "export", "source", ".", ":", "alias", "unalias", "set", "unset", "shift", "exit", "return",
+    "logout", "echo", "printf", "pwd", "type", "read", "readarray", "mapfile", "eval", "exec", "ulimit",
+    "umask", "wait", "jobs", "fg", "bg", "kill", "disown", "suspend", "trap", "times", "hash", "help",
+    "history", "fc", "bind", "builtin", "command", "enable", "caller", "compgen", "complete", "compopt",
+    "declare", "typeset", "local", "readonly", "let", "getopts", "shopt", "dirs", "pushd", "popd", "[",
+    "[[", "test", "true", "false", "break", "continue", "if", "then", "else", "elif", "fi", "for", "while",
+    "until", "do", "done", "case", "esac", "select", "function", "time", "coproc", "!", "{", "}",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingLevel {
+    Warn,
+    Block,
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub check: String,
+    pub level: FindingLevel,
+    pub message: String,
+}
+
+pub trait PreflightCheck: Send {
+    fn name(&self) -> &str;
+    fn check(&self, command: &str, cwd: &Path) -> Option<(FindingLevel, String)>;
+}
+
+// User-defined check from config: when `pattern` matches the command, `script`
+// runs through the shell and a non-zero exit (with its output) is the finding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptCheckConfig {
+    pub name: String,
+    pub pattern: String,
+    pub script: String,
+    #[serde(default)]
+    pub block: bool,
+}
+
+pub struct CheckRegistry {
+    checks: Vec<Box<dyn PreflightCheck>>,
+}
+
+impl CheckRegistry {
+    pub fn new() -> Self {
+        Self { checks: Vec::new() }
+    }
+
+    pub fn with_builtins(user_checks: &[ScriptCheckConfig]) -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(RequiredBinary));
+        registry.register(Box::new(NpmInstallGitignore));
+        registry.register(Box::new(ProdKubeContext));
+        registry.register(Box::new(SshAgentKey));
+        for config in user_checks {
+            match ScriptCheck::new(config) {
+                Some(check) => registry.register(Box::new(check)),
+                None => warn!("Ignoring pre-flight check {:?}: invalid pattern", config.name),
+            }
+        }
+        registry
+    }
+
+    pub fn register(&mut self, check: Box<dyn PreflightCheck>) {
+        self.checks.push(check);
+    }
+
+    pub fn run(&self, command: &str, cwd: &Path) -> Vec<Finding> {
+        self.checks
+            .iter()
+            .filter_map(|check| {
+                check.check(command, cwd).map(|(level, message)| Finding {
+                    check: check.name().to_string(),
+                    level,
+                    message,
+                })
+            })
+            .collect()
+    }
+}
+
+struct RequiredBinary;
+
+impl PreflightCheck for RequiredBinary {
+    fn name(&self) -> &str {
+        "required-binary"
+    }
+
+    fn check(&self, command: &str, _cwd: &Path) -> Option<(FindingLevel, String)> {
+        let program = command.split_whitespace().find(|w| !w.contains('='))?;
+        if program.contains('/')
+            || SHELL_BUILTINS.contains(&program)
+            || find_in_path(program).is_some()
+            || shell_knows(program)
+        {
+            return None;
+        }
+        Some((FindingLevel::Block, format!("'{}' is not installed or not on PATH", program)))
+    }
+}
+
+struct NpmInstallGitignore;
+
+impl PreflightCheck for NpmInstallGitignore {
+    fn name(&self) -> &str {
+        "npm-gitignore"
+    }
+
+    fn check(&self, command: &str, cwd: &Path) -> Option<(FindingLevel, String)> {
+        let mut words = command.split_whitespace();
+        if words.next()? != "npm" || !matches!(words.next(), Some("install" | "i" | "ci")) {
+            return None;
+        }
+        let gitignore = std::fs::read_to_string(cwd.join(".gitignore")).unwrap_or_default();
+        let ignored = gitignore
+            .lines()
+            .any(|l| l.trim().trim_matches('/') == "node_modules");
+        (!ignored).then(|| {
+            (FindingLevel::Warn, "node_modules is not in .gitignore".to_string())
+        })
+    }
+}
+
+struct ProdKubeContext;
+
+impl PreflightCheck for ProdKubeContext {
+    fn name(&self) -> &str {
+        "prod-kubeconfig"
+    }
+
+    fn check(&self, command: &str, _cwd: &Path) -> Option<(FindingLevel, String)> {
+        let program = command.split_whitespace().next()?;
+        if !matches!(program, "kubectl" | "helm" | "k9s") {
+            return None;
+        }
+        let output = Command::new("kubectl").args(["config", "current-context"]).output().ok()?;
+        let context = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        context.to_lowercase().contains("prod").then(|| {
+            (FindingLevel::Warn, format!("kube context is '{}' (production)", context))
+        })
+    }
+}
+
+struct SshAgentKey;
+
+impl PreflightCheck for SshAgentKey {
+    fn name(&self) -> &str {
+        "ssh-agent-key"
+    }
+
+    fn check(&self, command: &str, _cwd: &Path) -> Option<(FindingLevel, String)> {
+        ssh_keys::check_connect(command).map(|message| (FindingLevel::Warn, message))
+    }
+}
+
+struct ScriptCheck {
+    name: String,
+    pattern: Regex,
+    script: String,
+    level: FindingLevel,
+}
+
+impl ScriptCheck {
+    fn new(config: &ScriptCheckConfig) -> Option<Self> {
+        Some(Self {
+            name: config.name.clone(),
+            pattern: Regex::new(&config.pattern).ok()?,
+            script: config.script.clone(),
+            level: if config.block { FindingLevel::Block } else { FindingLevel::Warn },
+        })
+    }
+}
+
+impl PreflightCheck for ScriptCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, command: &str, cwd: &Path) -> Option<(FindingLevel, String)> {
+        if !self.pattern.is_match(command) {
+            return None;
+        }
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&self.script)
+            .env("NEXTERM_COMMAND", command)
+            .current_dir(cwd)
+            .output()
+            .ok()?;
+        if output.status.success() {
+            return None;
+        }
+        let message = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let message = if message.is_empty() { format!("{} failed", self.name) } else { message };
+        Some((self.level, message))
+    }
+}
+
+// Splits a leading `!` (skip pre-flight checks) off `command`
+pub fn strip_skip(command: &str) -> (&str, bool) {
+    match command.trim_start().strip_prefix(SKIP_PREFIX) {
+        Some(rest) => (rest.trim_start(), true),
+        None => (command, false),
+    }
+}
+
+// Whether the user's shell has `program` as a builtin or function of its own
+// (zsh's `autoload`, fish's `funced`, ...)
+fn shell_knows(program: &str) -> bool {
+    if program.starts_with('-') || program.contains('\'') {
+        return false;
+    }
+    // Quoted into the script rather than passed as $1, which fish doesn't have
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+    Command::new(shell)
+        .arg("-c")
+        .arg(format!("command -v '{}'", program))
+        .stdin(std::process::Stdio::null())
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+pub(crate) fn find_in_path(program: &str) -> Option<PathBuf> {
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|path| path.is_file())
+    })
+}
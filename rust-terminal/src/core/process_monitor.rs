@@ -0,0 +1,96 @@
+// CPU usage sampling for running child processes (Linux /proc based)
+use std::collections::VecDeque;
+use std::fs;
+use std::time::Instant;
+
+use crate::utils::clock;
+
+const MAX_SAMPLES: usize = 16;
+const SPARK_CHARS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+pub struct RunningCommand {
+    pub command: String,
+    pub started: Instant,
+    pub pid: Option<u32>,
+    cpu: CpuSampler,
+}
+
+impl RunningCommand {
+    pub fn new(command: &str, pid: Option<u32>) -> Self {
+        Self {
+            command: command.to_string(),
+            started: clock::now(),
+            pid,
+            cpu: CpuSampler::new(),
+        }
+    }
+
+    pub fn sample_cpu(&mut self) {
+        if let Some(pid) = self.pid {
+            self.cpu.sample(pid);
+        }
+    }
+
+    pub fn sparkline(&self) -> String {
+        self.cpu.sparkline()
+    }
+}
+
+struct CpuSampler {
+    last: Option<(u64, Instant)>,
+    samples: VecDeque<f32>,
+    ticks_per_second: f32,
+}
+
+impl CpuSampler {
+    fn new() -> Self {
+        // SAFETY: sysconf has no preconditions
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        Self {
+            last: None,
+            samples: VecDeque::with_capacity(MAX_SAMPLES),
+            ticks_per_second: if ticks > 0 { ticks as f32 } else { 100.0 },
+        }
+    }
+
+    // Records CPU usage since the previous sample as a fraction of one core
+    fn sample(&mut self, pid: u32) {
+        let Some(ticks) = read_cpu_ticks(pid) else {
+            return;
+        };
+        let now = Instant::now();
+
+        if let Some((last_ticks, last_time)) = self.last {
+            let elapsed = now.duration_since(last_time).as_secs_f32();
+            if elapsed > 0.0 {
+                let used = ticks.saturating_sub(last_ticks) as f32 / self.ticks_per_second;
+                if self.samples.len() == MAX_SAMPLES {
+                    self.samples.pop_front();
+                }
+                self.samples.push_back((used / elapsed).min(1.0));
+            }
+        }
+        self.last = Some((ticks, now));
+    }
+
+    fn sparkline(&self) -> String {
+        self.samples
+            .iter()
+            .map(|&usage| {
+                let index = (usage * (SPARK_CHARS.len() - 1) as f32).round() as usize;
+                SPARK_CHARS[index.min(SPARK_CHARS.len() - 1)]
+            })
+            .collect()
+    }
+}
+
+// utime + stime from /proc/<pid>/stat; the comm field may contain spaces,
+// so fields are counted from the closing parenthesis
+fn read_cpu_ticks(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let rest = &stat[stat.rfind(')')? + 2..];
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
@@ -0,0 +1,198 @@
+// Prompts inside interactive shells that run as a command (ssh, a container's
+// shell, a nested zsh), so the commands typed there get prompt marks like
+// NexTerm's own and prompt jumps and command blocks work inside them. Shells
+// that mark their prompts with OSC 133 (shell integration as in VS Code or
+// iTerm2, starship, powerlevel10k) are understood as they are; for others a
+// profile, picked by the command that started the shell, says how:
+//
+//   [[prompts]]
+//   command = '^ssh\b'
+//   detect = "regex"
+//   pattern = '^\S+@\S+:\S*[$#] (?P<command>.*)$'
+//
+//   [[prompts]]
+//   command = '^docker exec'
+//   detect = "sentinel"
+//   shell = "bash"
+//
+// "regex" takes output lines matching `pattern` as prompts, the command being
+// its `command` group or what follows the match. "sentinel" types a line into
+// the shell at its first prompt that has it send OSC 133 from then on, which
+// holds up however the prompt is themed.
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Detect {
+    // OSC 133 only, which is always read
+    #[default]
+    Osc,
+    Regex,
+    Sentinel,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Shell {
+    #[default]
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    // Typed at the shell's prompt; the leading space keeps it out of history
+    // where the shell ignores space-prefixed lines
+    fn hook(self) -> &'static str {
+        match self {
+            Shell::Bash => r#" PS0=$'\e]133;C\a'; PS1='\[\e]133;A\a\]'"$PS1"'\[\e]133;B\a\]'"#,
+            // Appended from precmd, after themes that build PS1 there
+            Shell::Zsh => concat!(
+                r#" autoload -Uz add-zsh-hook; _nexterm_a() { print -n '\e]133;A\a';"#,
+                r#" [[ $PS1 == *133\;B* ]] || PS1+=$'%{\e]133;B\a%}' };"#,
+                r#" _nexterm_c() { print -n '\e]133;C\a' }; add-zsh-hook precmd _nexterm_a;"#,
+                r#" add-zsh-hook preexec _nexterm_c"#,
+            ),
+            Shell::Fish => concat!(
+                r#" functions -c fish_prompt _nexterm_prompt; function fish_prompt; printf '\e]133;A\a';"#,
+                r#" _nexterm_prompt; printf '\e]133;B\a'; end;"#,
+                r#" function _nexterm_c --on-event fish_preexec; printf '\e]133;C\a'; end"#,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptConfig {
+    // Regex for the command lines that start the shell
+    pub command: String,
+    #[serde(default)]
+    pub detect: Detect,
+    // For "regex"
+    #[serde(default)]
+    pub pattern: Option<String>,
+    // For "sentinel"
+    #[serde(default)]
+    pub shell: Shell,
+}
+
+struct Profile {
+    command: Regex,
+    detect: Detect,
+    pattern: Option<Regex>,
+    shell: Shell,
+}
+
+#[derive(Default)]
+pub struct PromptProfiles {
+    profiles: Vec<Profile>,
+}
+
+impl PromptProfiles {
+    // Profiles whose regexes don't compile are logged and left out
+    pub fn new(configs: &[PromptConfig]) -> Self {
+        let compile = |pattern: &str| Regex::new(pattern).map_err(|e| warn!("Prompt profile {}: {}", pattern, e)).ok();
+        let profiles = configs
+            .iter()
+            .filter_map(|config| {
+                let pattern = match (&config.pattern, config.detect) {
+                    (Some(pattern), _) => Some(compile(pattern)?),
+                    (None, Detect::Regex) => {
+                        warn!("Prompt profile {} detects by regex but has no pattern", config.command);
+                        return None;
+                    }
+                    (None, _) => None,
+                };
+                Some(Profile { command: compile(&config.command)?, detect: config.detect, pattern, shell: config.shell })
+            })
+            .collect();
+        Self { profiles }
+    }
+
+    // How to find prompts in what `command` prints, from the first profile
+    // for it; None when OSC 133 is all there is
+    pub fn detector(&self, command: &str) -> Option<Detector> {
+        let profile = self.profiles.iter().find(|profile| profile.command.is_match(command))?;
+        match profile.detect {
+            Detect::Osc => None,
+            Detect::Regex => Some(Detector { pattern: profile.pattern.clone(), hook: None }),
+            Detect::Sentinel => Some(Detector { pattern: None, hook: Some(profile.shell.hook()) }),
+        }
+    }
+}
+
+pub struct Detector {
+    pattern: Option<Regex>,
+    // Until it's typed
+    hook: Option<&'static str>,
+}
+
+impl Detector {
+    // The command typed at `line`, if it's a prompt
+    pub fn command(&self, line: &str) -> Option<String> {
+        let captures = self.pattern.as_ref()?.captures(line)?;
+        let command = match captures.name("command") {
+            Some(command) => command.as_str(),
+            None => &line[captures.get(0)?.end()..],
+        };
+        Some(command.trim().to_string())
+    }
+
+    // The hook to type, once, when `cursor_line` looks like a shell waiting
+    // for input
+    pub fn hook(&mut self, cursor_line: &str) -> Option<&'static str> {
+        static WAITING: OnceLock<Regex> = OnceLock::new();
+        let waiting = WAITING.get_or_init(|| Regex::new(r"[$#%>❯»]\s*$").expect("valid prompt pattern"));
+        if waiting.is_match(cursor_line) {
+            self.hook.take()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_pick_a_detector_by_command() {
+        let configs: Vec<PromptConfig> = toml::from_str::<toml::Table>(
+            r#"
+            [[prompts]]
+            command = '^ssh\b'
+            detect = "regex"
+            pattern = '^\S+@\S+:\S*[$#] (?P<command>.*)$'
+            [[prompts]]
+            command = '^docker exec'
+            detect = "sentinel"
+            shell = "zsh"
+            [[prompts]]
+            command = '^nix-shell'
+            detect = "regex"
+            "#,
+        )
+        .unwrap()["prompts"]
+            .clone()
+            .try_into()
+            .unwrap();
+        let profiles = PromptProfiles::new(&configs);
+
+        let ssh = profiles.detector("ssh prod").unwrap();
+        assert_eq!(ssh.command("me@prod:~$ tail -f app.log"), Some("tail -f app.log".into()));
+        assert_eq!(ssh.command("me@prod:~$ "), Some(String::new()));
+        assert_eq!(ssh.command("Last login: Mon"), None);
+
+        let mut docker = profiles.detector("docker exec -it api zsh").unwrap();
+        assert_eq!(docker.command("$ ls"), None);
+        assert_eq!(docker.hook("Welcome"), None);
+        assert!(docker.hook("api% ").unwrap().contains("add-zsh-hook"));
+        assert_eq!(docker.hook("api% "), None);
+
+        assert!(profiles.detector("nix-shell").is_none());
+        assert!(profiles.detector("vim").is_none());
+    }
+}
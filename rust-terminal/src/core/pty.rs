@@ -0,0 +1,516 @@
+// Pseudo-terminal sessions: a command runs with a tty as stdin, stdout and
+// stderr, so colors, progress bars, password prompts and full-screen programs
+// (vim, htop, less) behave as in any terminal, and it can be typed into and
+// resized while it runs
+use anyhow::{Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use nix::pty::{openpty, Winsize};
+use nix::sys::termios::Termios;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::core::binary::{SharedBinary, Sniffer};
+use crate::core::encoding::{Decoder, Encoding};
+use crate::core::postprocess::Pipeline;
+use crate::core::retention::Window;
+use crate::core::tee::Tee;
+use crate::core::vt::{Screen, ScreenView, SemanticPrompt};
+use crate::utils::error::NexTermError;
+
+// How long to keep reading after the command exits while something it
+// started in the background still holds the tty open
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(200);
+// Output held back while the pane is hidden, before it's parsed anyway
+const MAX_HELD: usize = 4 * 1024 * 1024;
+// How often held-back output checks whether the pane is shown again, while
+// nothing new arrives
+const HELD_POLL: Duration = Duration::from_millis(100);
+// Longest body of a core::shell mark
+const MAX_MARK: usize = 64;
+
+pub struct PtySession {
+    master: File,
+    screen: Arc<Mutex<Screen>>,
+    // Keystrokes go to the command instead of the input line
+    attached: bool,
+    was_alternate: bool,
+}
+
+// The command's output once it's done, see `PtyOutput::wait`
+pub struct PtyOutput {
+    target: Slot,
+    screen: Arc<Mutex<Screen>>,
+    // Lines that scrolled off the screen
+    transcript: Arc<Mutex<Vec<String>>>,
+    done: Receiver<Option<i32>>,
+    pipeline: Pipeline,
+    process: Arc<Mutex<Child>>,
+    // The command is done when a core::shell mark says so, not when the process exits
+    marked: bool,
+}
+
+// Where the output read from a pty goes: the command running on it, if any
+pub(crate) type Slot = Arc<Mutex<Option<Target>>>;
+
+// What a command's output is rendered into, see `PtySession::spawn`
+pub(crate) struct Target {
+    decoder: Decoder,
+    sniffer: Sniffer,
+    binary: SharedBinary,
+    screen: Arc<Mutex<Screen>>,
+    transcript: Arc<Mutex<Vec<String>>>,
+    buffer: Arc<Mutex<Vec<String>>>,
+    window: Option<Arc<Mutex<Window>>>,
+    tee: Tee,
+    pipeline: Pipeline,
+    visible: Arc<AtomicBool>,
+    // Where the rows still on screen start in `buffer`, and how many are shown
+    live_start: usize,
+    shown: usize,
+    held: Vec<u8>,
+    mark: Option<Mark>,
+    // The exit code from the mark; None when the pty closed first
+    done: Sender<Option<i32>>,
+}
+
+// The sinks of one command's output
+pub struct Sinks {
+    pub buffer: Arc<Mutex<Vec<String>>>,
+    pub window: Option<Arc<Mutex<Window>>>,
+    pub tee: Tee,
+    pub encoding: Encoding,
+    pub binary: SharedBinary,
+    pub visible: Arc<AtomicBool>,
+    pub pipeline: Pipeline,
+}
+
+impl PtySession {
+    // Starts `shell -c command` on a new pty of `size` (rows, cols). Output is
+    // rendered into the buffer from its current end, with progress bars and
+    // other redrawn lines updated in place, and trimmed to the window if there
+    // is one. What the command writes is read as the encoding and goes to the
+    // tee as UTF-8, unless it turns out binary: then it goes to the binary
+    // sink as is. Lines go through the pipeline on their way to the buffer.
+    // While `visible` is off, output is only held until it's on again.
+    pub fn spawn(
+        shell: &str,
+        command: &str,
+        envs: BTreeMap<String, String>,
+        size: (u16, u16),
+        sinks: Sinks,
+    ) -> Result<(Self, u32, PtyOutput)> {
+        let mut child = Command::new(shell);
+        child.arg("-c").arg(command).envs(envs);
+        let (master, process) = open(child, size)?;
+        let pid = process.id();
+        let target = Arc::new(Mutex::new(None));
+        let (session, output) = Self::start(&master, &target, size, sinks, None, Arc::new(Mutex::new(process)))?;
+        let reader = master.try_clone()?;
+        std::thread::spawn(move || pump(reader, target));
+        Ok((session, pid, output))
+    }
+
+    // Points the output of `master`, read into `target`, at a new command.
+    // With a `mark`, the command is done once it's seen.
+    pub(crate) fn start(
+        master: &File,
+        target: &Slot,
+        size: (u16, u16),
+        sinks: Sinks,
+        mark: Option<Vec<u8>>,
+        process: Arc<Mutex<Child>>,
+    ) -> Result<(Self, PtyOutput)> {
+        let (rows, cols) = size;
+        let screen = Arc::new(Mutex::new(Screen::new(rows as usize, cols as usize)));
+        let transcript = Arc::new(Mutex::new(Vec::new()));
+        let (done_tx, done) = mpsc::channel();
+        let live_start = sinks.buffer.lock().map(|b| b.len()).unwrap_or(0);
+        let marked = mark.is_some();
+        if let Ok(mut target) = target.lock() {
+            *target = Some(Target {
+                decoder: sinks.encoding.decoder(),
+                sniffer: Sniffer::new(sinks.encoding),
+                binary: sinks.binary,
+                screen: screen.clone(),
+                transcript: transcript.clone(),
+                buffer: sinks.buffer,
+                window: sinks.window,
+                tee: sinks.tee,
+                pipeline: sinks.pipeline.clone(),
+                visible: sinks.visible,
+                live_start,
+                shown: 0,
+                held: Vec::new(),
+                mark: mark.map(Mark::new),
+                done: done_tx,
+            });
+        }
+
+        let session = Self { master: master.try_clone()?, screen: screen.clone(), attached: false, was_alternate: false };
+        let output =
+            PtyOutput { target: target.clone(), screen, transcript, done, pipeline: sinks.pipeline, process, marked };
+        Ok((session, output))
+    }
+
+    // Full-screen programs take the keyboard when they switch to the alternate
+    // screen and hand it back when they leave it
+    pub fn update(&mut self) {
+        let alternate = self.screen.lock().map(|s| s.is_alternate()).unwrap_or(false);
+        if alternate != self.was_alternate {
+            self.attached = alternate;
+            self.was_alternate = alternate;
+        }
+    }
+
+    pub fn take_bells(&self) -> usize {
+        self.screen.lock().map(|mut s| s.take_bells()).unwrap_or(0)
+    }
+
+    pub fn take_peer_messages(&self) -> Vec<String> {
+        self.screen.lock().map(|mut s| s.take_peer_messages()).unwrap_or_default()
+    }
+
+    pub fn take_prompts(&self) -> Vec<SemanticPrompt> {
+        self.screen.lock().map(|mut s| s.take_prompts()).unwrap_or_default()
+    }
+
+    // Whether the program asked for bracketed paste
+    pub fn bracketed_paste(&self) -> bool {
+        self.screen.lock().map(|s| s.bracketed_paste()).unwrap_or(false)
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.attached
+    }
+
+    pub fn toggle_attached(&mut self) {
+        self.attached = !self.attached;
+    }
+
+    // The screen of a full-screen program; None while output is line based
+    pub fn alternate_view(&self) -> Option<ScreenView> {
+        let screen = self.screen.lock().ok()?;
+        screen.is_alternate().then(|| screen.view())
+    }
+
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        resize(&self.master, rows, cols);
+        if let Ok(mut screen) = self.screen.lock() {
+            screen.resize(rows as usize, cols as usize);
+        }
+    }
+
+    pub fn send_key(&mut self, key: KeyEvent) {
+        let app_cursor = self.screen.lock().map(|s| s.app_cursor_keys()).unwrap_or(false);
+        let _ = self.master.write_all(&encode_key(key, app_cursor));
+    }
+
+    pub fn send_paste(&mut self, text: &str) {
+        let bytes = if self.bracketed_paste() {
+            format!("\x1b[200~{}\x1b[201~", text)
+        } else {
+            text.to_string()
+        };
+        let _ = self.master.write_all(bytes.as_bytes());
+    }
+}
+
+impl PtyOutput {
+    // The exit code and everything the command printed; blocks until the
+    // command is done and the pty is drained
+    pub fn wait(self) -> (i32, String) {
+        let exited = || self.process.lock().ok()?.wait().ok()?.code();
+        let code = if self.marked {
+            // A shell that exited (`exit`) never marks the end
+            self.done.recv().ok().flatten().or_else(exited)
+        } else {
+            let code = exited();
+            let _ = self.done.recv_timeout(DRAIN_TIMEOUT);
+            code
+        };
+        // Background leftovers after the command returned stay off the output
+        if let Ok(mut target) = self.target.lock() {
+            target.take();
+        }
+        let mut lines = self.transcript.lock().map(|t| t.clone()).unwrap_or_default();
+        if let Ok(screen) = self.screen.lock() {
+            lines.extend(screen.live_lines().into_iter().map(|line| self.pipeline.line(line)));
+        }
+        (code.unwrap_or(-1), lines.iter().map(|line| format!("{}\n", line)).collect())
+    }
+}
+
+impl Target {
+    // Output of the command; `last` lets through what's held
+    fn take(&mut self, mut chunk: &[u8], last: bool) {
+        if !chunk.is_empty() && self.sniffer.is_binary(chunk) {
+            self.tee.write(chunk);
+            if let Ok(mut binary) = self.binary.lock() {
+                binary.write(chunk, &self.buffer);
+            }
+            chunk = &[];
+        }
+        let decoded = self.decoder.decode(chunk);
+        self.tee.write(&decoded);
+        if !last && !self.visible.load(Ordering::Relaxed) && self.held.len() < MAX_HELD {
+            self.held.extend_from_slice(&decoded);
+            return;
+        }
+        if self.held.is_empty() && decoded.is_empty() {
+            return;
+        }
+        let Ok(mut screen) = self.screen.lock() else {
+            return;
+        };
+        if !self.held.is_empty() {
+            screen.feed(&std::mem::take(&mut self.held));
+        }
+        screen.feed(&decoded);
+        let scrolled: Vec<String> = screen.take_scrolled().into_iter().map(|line| self.pipeline.line(line)).collect();
+        let live: Vec<String> = screen.live_lines().into_iter().map(|line| self.pipeline.line(line)).collect();
+        drop(screen);
+
+        let mut window = self.window.as_ref().and_then(|w| w.lock().ok());
+        if let Ok(mut transcript) = self.transcript.lock() {
+            transcript.extend(scrolled.iter().cloned());
+            if let Some(window) = &window {
+                let excess = transcript.len().saturating_sub(window.keep());
+                transcript.drain(..excess);
+            }
+        }
+        if let Ok(mut buffer) = self.buffer.lock() {
+            // The output was cleared while the command ran
+            if self.live_start > buffer.len() {
+                self.live_start = buffer.len();
+                self.shown = 0;
+            }
+            if let Some(window) = &mut window {
+                window.check_cleared(self.live_start);
+            }
+            let end = (self.live_start + self.shown).min(buffer.len());
+            let added = scrolled.len();
+            self.shown = live.len();
+            buffer.splice(self.live_start..end, scrolled.into_iter().chain(live));
+            self.live_start += added;
+            if let Some(window) = &mut window {
+                window.trim(&mut buffer, &mut self.live_start);
+            }
+        }
+    }
+}
+
+// The private OSC a core::shell ends each command with, `prefix` then the
+// exit code and `;`, found however it's split across reads
+pub(crate) struct Mark {
+    prefix: Vec<u8>,
+    seen: Vec<u8>,
+}
+
+impl Mark {
+    fn new(prefix: Vec<u8>) -> Self {
+        Self { prefix, seen: Vec::new() }
+    }
+
+    // Where in `chunk` the mark ends, and the exit code in it
+    fn find(&mut self, chunk: &[u8]) -> Option<(usize, Option<i32>)> {
+        for (i, &byte) in chunk.iter().enumerate() {
+            if self.seen.len() < self.prefix.len() {
+                if byte != self.prefix[self.seen.len()] {
+                    self.seen.clear();
+                }
+                if byte == self.prefix[self.seen.len()] {
+                    self.seen.push(byte);
+                }
+            } else if byte == b'\x07' {
+                let body = String::from_utf8_lossy(&self.seen[self.prefix.len()..]).into_owned();
+                self.seen.clear();
+                return Some((i + 1, body.split(';').next().and_then(|code| code.parse().ok())));
+            } else if self.seen.len() < self.prefix.len() + MAX_MARK {
+                self.seen.push(byte);
+            } else {
+                self.seen.clear();
+            }
+        }
+        None
+    }
+}
+
+// Opens a pty of `size` and starts `command` on it
+pub(crate) fn open(mut command: Command, size: (u16, u16)) -> Result<(File, Child)> {
+    let (rows, cols) = size;
+    let winsize = Winsize { ws_row: rows, ws_col: cols, ws_xpixel: 0, ws_ypixel: 0 };
+    let pty = openpty(Some(&winsize), None::<&Termios>).map_err(|e| NexTermError::PtyError(e.to_string()))?;
+    let master = File::from(pty.master);
+    // SAFETY: fcntl on a descriptor we own
+    unsafe { libc::fcntl(master.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+
+    command
+        .env("TERM", "xterm-256color")
+        .stdin(Stdio::from(pty.slave.try_clone()?))
+        .stdout(Stdio::from(pty.slave.try_clone()?))
+        .stderr(Stdio::from(pty.slave));
+    // A new session with the pty as its controlling terminal, so Ctrl+C and
+    // window size changes reach the command. Closing the master when NexTerm
+    // exits hangs it up.
+    // SAFETY: only async-signal-safe calls between fork and exec
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() < 0 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let process = command.spawn().context("Failed to execute command")?;
+    // Closes our copies of the slave, so reads fail once the command's are gone
+    drop(command);
+    Ok((master, process))
+}
+
+pub(crate) fn resize(master: &File, rows: u16, cols: u16) {
+    let winsize = Winsize { ws_row: rows, ws_col: cols, ws_xpixel: 0, ws_ypixel: 0 };
+    // SAFETY: TIOCSWINSZ reads a winsize; the kernel sends SIGWINCH
+    unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &winsize) };
+}
+
+// Feeds a pty's output to the command in `target`: scrolled-off lines are
+// appended to its buffer for good, the rows still on screen are replaced on
+// every update. While the pane is hidden the output is only decoded and held,
+// so a noisy command costs next to nothing until it's shown. Output with no
+// command to take it is dropped. Runs until the pty closes.
+pub(crate) fn pump(mut reader: File, target: Slot) {
+    let mut chunk = [0u8; 4096];
+    loop {
+        let holding = target.lock().is_ok_and(|t| t.as_ref().is_some_and(|t| !t.held.is_empty()));
+        // With output held, only wait so long for more before looking at
+        // `visible` again
+        let (n, eof) = match !holding || readable(&reader, HELD_POLL) {
+            // EIO once every process holding the slave has closed it
+            true => match reader.read(&mut chunk) {
+                Ok(n) => (n, n == 0),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => (0, true),
+            },
+            false => (0, false),
+        };
+        let Ok(mut slot) = target.lock() else {
+            break;
+        };
+        if let Some(command) = slot.as_mut() {
+            let (end, marked) = match command.mark.as_mut().and_then(|mark| mark.find(&chunk[..n])) {
+                Some((end, code)) => (end, Some(code)),
+                None => (n, None),
+            };
+            command.take(&chunk[..end], eof || marked.is_some());
+            if eof || marked.is_some() {
+                let _ = command.done.send(marked.flatten());
+                *slot = None;
+            }
+        }
+        if eof {
+            break;
+        }
+    }
+}
+
+// Whether `file` has something to read, or is hung up, within `timeout`
+fn readable(file: &File, timeout: Duration) -> bool {
+    let mut fd = libc::pollfd { fd: file.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+    // SAFETY: poll on one pollfd we own, for a descriptor we own
+    unsafe { libc::poll(&mut fd, 1, timeout.as_millis() as libc::c_int) != 0 }
+}
+
+// The bytes an xterm sends for `key`
+pub fn encode_key(key: KeyEvent, app_cursor: bool) -> Vec<u8> {
+    let cursor = |c: char| {
+        let prefix = if app_cursor { "\x1bO" } else { "\x1b[" };
+        format!("{}{}", prefix, c).into_bytes()
+    };
+    let mut bytes = match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => match c {
+            'a'..='z' | 'A'..='Z' => vec![c.to_ascii_lowercase() as u8 & 0x1f],
+            ' ' | '@' | '2' => vec![0],
+            '[' | '3' => vec![0x1b],
+            '\\' | '4' => vec![0x1c],
+            ']' | '5' => vec![0x1d],
+            '^' | '6' => vec![0x1e],
+            '_' | '7' | '/' => vec![0x1f],
+            c => c.to_string().into_bytes(),
+        },
+        KeyCode::Char(c) => c.to_string().into_bytes(),
+        KeyCode::Enter => vec![b'\r'],
+        KeyCode::Backspace => vec![0x7f],
+        KeyCode::Tab => vec![b'\t'],
+        KeyCode::BackTab => b"\x1b[Z".to_vec(),
+        KeyCode::Esc => vec![0x1b],
+        KeyCode::Up => cursor('A'),
+        KeyCode::Down => cursor('B'),
+        KeyCode::Right => cursor('C'),
+        KeyCode::Left => cursor('D'),
+        KeyCode::Home => cursor('H'),
+        KeyCode::End => cursor('F'),
+        KeyCode::Insert => b"\x1b[2~".to_vec(),
+        KeyCode::Delete => b"\x1b[3~".to_vec(),
+        KeyCode::PageUp => b"\x1b[5~".to_vec(),
+        KeyCode::PageDown => b"\x1b[6~".to_vec(),
+        KeyCode::F(n @ 1..=4) => format!("\x1bO{}", (b'P' + n - 1) as char).into_bytes(),
+        KeyCode::F(n @ 5..=12) => {
+            const CODES: [u8; 8] = [15, 17, 18, 19, 20, 21, 23, 24];
+            format!("\x1b[{}~", CODES[n as usize - 5]).into_bytes()
+        }
+        _ => Vec::new(),
+    };
+    if key.modifiers.contains(KeyModifiers::ALT) && !bytes.is_empty() {
+        bytes.insert(0, 0x1b);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_control_and_cursor_keys() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        assert_eq!(encode_key(key(KeyCode::Char('c'), KeyModifiers::CONTROL), false), vec![3]);
+        assert_eq!(encode_key(key(KeyCode::Up, KeyModifiers::NONE), false), b"\x1b[A");
+        assert_eq!(encode_key(key(KeyCode::Up, KeyModifiers::NONE), true), b"\x1bOA");
+        assert_eq!(encode_key(key(KeyCode::Char('x'), KeyModifiers::ALT), false), b"\x1bx");
+    }
+    #[test]
+    fn holds_output_while_hidden() {
+        use crate::core::binary::BinaryOutput;
+
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let visible = Arc::new(AtomicBool::new(false));
+        let binary = BinaryOutput::new(1, "printf").shared();
+        let command = "printf 'one\\ntwo\\n'; sleep 2";
+        let sinks = Sinks {
+            buffer: buffer.clone(),
+            window: None,
+            tee: Tee::new([]),
+            encoding: Encoding::Utf8,
+            binary,
+            visible: visible.clone(),
+            pipeline: Pipeline::default(),
+        };
+        let (_session, pid, _output) = PtySession::spawn("sh", command, BTreeMap::new(), (24, 80), sinks).unwrap();
+        let lines = || buffer.lock().unwrap().iter().filter(|l| !l.is_empty()).cloned().collect::<Vec<_>>();
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(lines().is_empty());
+        visible.store(true, Ordering::Relaxed);
+        std::thread::sleep(HELD_POLL * 4);
+        assert_eq!(lines(), ["one", "two"]);
+        // SAFETY: kill has no memory effects
+        unsafe { libc::kill(pid as i32, libc::SIGKILL) };
+    }
+}
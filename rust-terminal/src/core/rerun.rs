@@ -0,0 +1,156 @@
+// Re-running a command from history in another context: the directory it ran
+// in, the current one, an SSH host from ~/.ssh/config or a running container.
+// In the history overlay (Ctrl+R / Ctrl+F), Ctrl+O re-runs the selected
+// command where it ran and Ctrl+X picks where else. When that isn't where the
+// command was recorded, the differences are shown for confirmation first.
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::utils::shell_escape::ShellKind;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    // The recorded directory
+    Original,
+    Here,
+    Ssh(String),
+    Container(String),
+}
+
+// What Ctrl+X offers a list of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Place {
+    Host,
+    Container,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rerun {
+    pub command: String,
+    // Where it ran, when recorded
+    pub cwd: Option<PathBuf>,
+    pub target: Target,
+}
+
+impl Rerun {
+    // The command line that runs it at the target, for `shell`. Remote ends
+    // get it as a POSIX `sh` command.
+    pub fn command_line(&self, shell: ShellKind) -> Result<String> {
+        Ok(match &self.target {
+            Target::Original => {
+                let dir = self.cwd.as_ref().context("No directory was recorded for this command")?;
+                if !dir.is_dir() {
+                    bail!("{} no longer exists", dir.display());
+                }
+                format!("cd {} && {}", shell.quote(&dir.to_string_lossy()), self.command)
+            }
+            Target::Here => self.command.clone(),
+            Target::Ssh(host) => {
+                // The same directory under the remote home, where there is
+                // one; if it's missing there the command doesn't run at all
+                let remote = match self.cwd.as_deref().and_then(home_relative) {
+                    Some(dir) => format!("cd {} && {}", ShellKind::Posix.quote(&dir), self.command),
+                    None => self.command.clone(),
+                };
+                format!("ssh -t {} {}", shell.quote(host), shell.quote(&remote))
+            }
+            Target::Container(name) => {
+                format!("docker exec -it {} sh -c {}", shell.quote(name), shell.quote(&self.command))
+            }
+        })
+    }
+
+    // How the context differs from the recorded one; empty when it doesn't
+    pub fn changes(&self, here: &Path) -> Vec<String> {
+        let recorded = self.cwd.as_deref().map(display).unwrap_or_else(|| "an unrecorded directory".to_string());
+        match &self.target {
+            Target::Original => Vec::new(),
+            Target::Here if self.cwd.as_deref().is_none_or(|cwd| cwd == here) => Vec::new(),
+            Target::Here => vec![format!("Ran in {}, runs in {} instead.", recorded, display(here))],
+            Target::Ssh(host) => {
+                let mut changes = vec![format!("Ran on this machine in {}, runs on {} instead.", recorded, host)];
+                if let Some(dir) = self.cwd.as_deref().and_then(home_relative) {
+                    changes.push(format!("It runs in ~/{} there, and not at all if that doesn't exist.", dir));
+                }
+                changes
+            }
+            Target::Container(name) => {
+                vec![format!("Ran on this machine in {}, runs in container {} instead.", recorded, name)]
+            }
+        }
+    }
+}
+
+// Hosts or containers to pick from
+pub fn choices(place: Place) -> Result<Vec<String>> {
+    let choices = match place {
+        Place::Host => crate::utils::ssh_keys::config_hosts(),
+        Place::Container => containers()?,
+    };
+    if choices.is_empty() {
+        bail!(match place {
+            Place::Host => "No hosts in ~/.ssh/config",
+            Place::Container => "No running containers",
+        });
+    }
+    Ok(choices)
+}
+
+fn containers() -> Result<Vec<String>> {
+    let output = Command::new("docker")
+        .args(["ps", "--format", "{{.Names}}"])
+        .output()
+        .context("Failed to run docker")?;
+    if !output.status.success() {
+        bail!("docker ps failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+}
+
+fn home_relative(dir: &Path) -> Option<String> {
+    let home = dirs::home_dir()?;
+    let rest = dir.strip_prefix(&home).ok()?;
+    (!rest.as_os_str().is_empty()).then(|| rest.to_string_lossy().into_owned())
+}
+
+fn display(dir: &Path) -> String {
+    match home_relative(dir) {
+        Some(rest) => format!("~/{}", rest),
+        None => dir.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_commands_for_each_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let rerun = |target| Rerun { command: "make test".to_string(), cwd: Some(dir.path().to_path_buf()), target };
+        let posix = ShellKind::Posix;
+
+        let original = rerun(Target::Original);
+        assert_eq!(original.command_line(posix).unwrap(), format!("cd {} && make test", dir.path().display()));
+        assert!(original.changes(Path::new("/")).is_empty());
+
+        let here = rerun(Target::Here);
+        assert_eq!(here.command_line(posix).unwrap(), "make test");
+        assert!(here.changes(dir.path()).is_empty());
+        assert_eq!(here.changes(Path::new("/srv")).len(), 1);
+
+        let container = rerun(Target::Container("web 1".to_string()));
+        assert_eq!(container.command_line(posix).unwrap(), "docker exec -it 'web 1' sh -c 'make test'");
+        assert!(container.changes(dir.path())[0].ends_with("runs in container web 1 instead."));
+
+        let ssh = Rerun { cwd: None, ..rerun(Target::Ssh("build".to_string())) };
+        assert_eq!(ssh.command_line(posix).unwrap(), "ssh -t build 'make test'");
+        let ssh = Rerun { cwd: dirs::home_dir().map(|home| home.join("src/app")), ..ssh };
+        assert_eq!(ssh.command_line(posix).unwrap(), r"ssh -t build 'cd src/app && make test'");
+        assert!(ssh.changes(dir.path())[1].starts_with("It runs in ~/src/app there"));
+
+        let gone = Rerun { cwd: Some(dir.path().join("gone")), ..original };
+        assert!(gone.command_line(posix).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+// Output retention per command: how much of a command's output the output
+// buffer keeps, and whether the command is stored in history at all.
+// Configured as
+//
+//   [[retention]]
+//   pattern = '^tail -f'
+//   output = { last = 100 }
+//
+//   [[retention]]
+//   pattern = '(?i)password|token|secret'
+//   persist = false
+//
+// `output` is "full" (the default), "none" or { last = N }. The first rule
+// whose pattern matches the command applies.
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputRetention {
+    #[default]
+    Full,
+    Last(usize),
+    None,
+}
+
+impl OutputRetention {
+    // Lines kept; None for all of them
+    pub fn limit(self) -> Option<usize> {
+        match self {
+            OutputRetention::Full => None,
+            OutputRetention::Last(lines) => Some(lines),
+            OutputRetention::None => Some(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    pub pattern: String,
+    #[serde(default)]
+    pub output: OutputRetention,
+    // false keeps the command out of the stored history (and so out of sync)
+    #[serde(default = "default_true")]
+    pub persist: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+    pub output: OutputRetention,
+    pub persist: bool,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self { output: OutputRetention::Full, persist: true }
+    }
+}
+
+pub struct RetentionRules {
+    rules: Vec<(Regex, Retention)>,
+}
+
+impl RetentionRules {
+    pub fn new(configs: &[RetentionConfig]) -> Self {
+        let rules = configs
+            .iter()
+            .filter_map(|config| match Regex::new(&config.pattern) {
+                Ok(pattern) => Some((pattern, Retention { output: config.output, persist: config.persist })),
+                Err(e) => {
+                    warn!("Ignoring retention rule {:?}: {}", config.pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn for_command(&self, command: &str) -> Retention {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(command))
+            .map(|(_, retention)| *retention)
+            .unwrap_or_default()
+    }
+}
+
+// A running command's block of the output buffer, trimmed to its last `keep`
+// lines as output arrives. Dropped lines are counted on a marker line at the
+// start of the block.
+#[derive(Debug)]
+pub struct Window {
+    start: usize,
+    keep: usize,
+    dropped: usize,
+    // Lines removed minus markers inserted since the last `shift`
+    net_removed: usize,
+}
+
+impl Window {
+    pub fn new(start: usize, keep: usize) -> Self {
+        Self { start, keep, dropped: 0, net_removed: 0 }
+    }
+
+    pub fn keep(&self) -> usize {
+        self.keep
+    }
+
+    // The buffer was cleared while the command ran; its block starts over at `len`
+    pub fn check_cleared(&mut self, len: usize) {
+        if len < self.start {
+            self.start = len;
+            self.dropped = 0;
+            self.net_removed = 0;
+        }
+    }
+
+    // Drops the block's oldest lines past `keep`, but none from `live_start`
+    // on (rows a pty may still rewrite); `live_start` moves with the lines
+    pub fn trim(&mut self, buffer: &mut Vec<String>, live_start: &mut usize) {
+        let first = self.start + usize::from(self.dropped > 0);
+        let excess = buffer.len().saturating_sub(first + self.keep).min(live_start.saturating_sub(first));
+        if excess == 0 {
+            return;
+        }
+        buffer.drain(first..first + excess);
+        *live_start -= excess;
+        self.net_removed += excess;
+        if self.dropped == 0 {
+            buffer.insert(self.start, String::new());
+            *live_start += 1;
+            self.net_removed -= 1;
+        }
+        self.dropped += excess;
+        buffer[self.start] = format!(
+            "⋯ {} line{} not kept (retention)",
+            self.dropped,
+            if self.dropped == 1 { "" } else { "s" }
+        );
+    }
+
+    // Where output line `index`, counted before the trims since the last
+    // call, is now
+    pub fn shift(&mut self, index: usize) -> usize {
+        let removed = std::mem::take(&mut self.net_removed);
+        if index <= self.start {
+            return index;
+        }
+        index.saturating_sub(removed).max(self.start + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_last_lines_behind_a_marker() {
+        let rules = RetentionRules::new(&[
+            RetentionConfig { pattern: "^tail -f".into(), output: OutputRetention::Last(2), persist: true },
+            RetentionConfig { pattern: "secret".into(), output: OutputRetention::Full, persist: false },
+        ]);
+        assert_eq!(rules.for_command("tail -f app.log").output, OutputRetention::Last(2));
+        assert!(!rules.for_command("export SECRET=1; echo secret").persist);
+        assert_eq!(rules.for_command("ls"), Retention::default());
+
+        let mut buffer: Vec<String> = vec!["❯ tail -f app.log".into()];
+        let mut window = Window::new(1, 2);
+        for i in 1..=5 {
+            buffer.push(format!("line {}", i));
+            let mut end = buffer.len();
+            window.trim(&mut buffer, &mut end);
+        }
+        assert_eq!(buffer, vec!["❯ tail -f app.log", "⋯ 3 lines not kept (retention)", "line 4", "line 5"]);
+        // Read up to "line 3" before the trims: the next unread line is "line 4"
+        assert_eq!(window.shift(4), 2);
+    }
+}
@@ -0,0 +1,153 @@
+// Roaming for remote tabs: an interactive `ssh <host>` is run through mosh
+// (UDP, survives network changes and sleep), or wrapped in a loop that
+// reconnects after a dropped connection and resumes a remote tmux session,
+// so flaky Wi-Fi doesn't end the tab
+use serde::{Deserialize, Serialize};
+
+use crate::core::preflight;
+use crate::utils::ssh_keys;
+
+// ssh's own exit status for connection errors, as opposed to the remote shell's
+const CONNECTION_LOST: i32 = 255;
+
+// Flags that don't open an interactive session (no shell, background,
+// forwarding only, control commands)
+const NON_INTERACTIVE: &[&str] = &["-N", "-f", "-T", "-W", "-O", "-G", "-V", "-Q"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoamMode {
+    #[default]
+    Off,
+    // mosh when it is installed, reconnecting otherwise
+    Auto,
+    Mosh,
+    Reconnect,
+}
+
+impl RoamMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(RoamMode::Off),
+            "auto" => Some(RoamMode::Auto),
+            "mosh" => Some(RoamMode::Mosh),
+            "reconnect" => Some(RoamMode::Reconnect),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RoamMode::Off => "off",
+            RoamMode::Auto => "auto",
+            RoamMode::Mosh => "mosh",
+            RoamMode::Reconnect => "reconnect",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoamingConfig {
+    #[serde(default)]
+    pub mode: RoamMode,
+    // Reconnect attempts after a dropped connection, waiting 1s, 2s, ... between them
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    // Remote tmux session reconnects attach to; without tmux a plain login shell
+    #[serde(default = "default_session")]
+    pub session: String,
+}
+
+fn default_retries() -> u32 {
+    5
+}
+
+fn default_session() -> String {
+    "nexterm".to_string()
+}
+
+impl Default for RoamingConfig {
+    fn default() -> Self {
+        Self {
+            mode: RoamMode::default(),
+            retries: default_retries(),
+            session: default_session(),
+        }
+    }
+}
+
+// The shell command to run in place of `command`, when it is an interactive
+// `ssh <host>` and roaming is on
+pub fn wrap(command: &str, config: &RoamingConfig) -> Option<String> {
+    if config.mode == RoamMode::Off || command.contains(['|', ';', '&', '>', '<', '`', '$', '\n']) {
+        return None;
+    }
+    let ssh = ssh_keys::parse(command)?;
+    if !ssh.remote.is_empty() || ssh.options.iter().any(|o| NON_INTERACTIVE.contains(o)) {
+        return None;
+    }
+    let mosh = match config.mode {
+        RoamMode::Mosh => true,
+        RoamMode::Auto => preflight::find_in_path("mosh").is_some(),
+        _ => false,
+    };
+    // mosh takes the ssh options as one --ssh argument
+    if mosh && !ssh.options.iter().any(|o| o.contains('\'')) {
+        let mut wrapped = String::from("mosh");
+        if !ssh.options.is_empty() {
+            wrapped.push_str(&format!(" --ssh='ssh {}'", ssh.options.join(" ")));
+        }
+        wrapped.push_str(&format!(" {}", ssh.destination));
+        return Some(wrapped);
+    }
+    Some(reconnect_loop(&ssh.options, ssh.destination, config))
+}
+
+fn reconnect_loop(options: &[&str], destination: &str, config: &RoamingConfig) -> String {
+    let session: String = config
+        .session
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        .collect();
+    let remote = format!("tmux new-session -A -s {} 2>/dev/null || exec \"$SHELL\" -l", session);
+    let mut ssh = vec!["ssh", "-t"];
+    ssh.extend(options);
+    ssh.push(destination);
+    format!(
+        "n=0; while :; do {ssh} '{remote}'; s=$?; \
+         if [ $s -ne {lost} ] || [ $n -ge {retries} ]; then exit $s; fi; n=$((n+1)); \
+         printf '\\r\\n⟳ Connection to {destination} lost, reconnecting (%d/{retries})…\\r\\n' $n; sleep $n; done",
+        ssh = ssh.join(" "),
+        remote = remote,
+        lost = CONNECTION_LOST,
+        retries = config.retries,
+        destination = destination,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(mode: RoamMode) -> RoamingConfig {
+        RoamingConfig { mode, ..RoamingConfig::default() }
+    }
+
+    #[test]
+    fn wraps_interactive_ssh_only() {
+        let reconnect = config(RoamMode::Reconnect);
+        assert_eq!(wrap("ssh dev", &config(RoamMode::Off)), None);
+        assert_eq!(wrap("ssh dev uptime", &reconnect), None);
+        assert_eq!(wrap("ssh -N -L 8080:localhost:80 dev", &reconnect), None);
+        assert_eq!(wrap("ssh dev; ls", &reconnect), None);
+
+        let wrapped = wrap("ssh -p 2222 me@dev", &reconnect).unwrap();
+        assert!(wrapped.contains("ssh -t -p 2222 me@dev 'tmux new-session -A -s nexterm"));
+        assert!(wrapped.contains("-ne 255"));
+
+        assert_eq!(
+            wrap("ssh -p 2222 me@dev", &config(RoamMode::Mosh)).as_deref(),
+            Some("mosh --ssh='ssh -p 2222' me@dev")
+        );
+    }
+}
@@ -0,0 +1,221 @@
+// `scriptify`: recent commands as a shell script. Values that come up in more
+// than one command become variables at the top, and loaded .env values (which
+// history shows redacted as `<env:KEY>`) become references to KEY that the
+// script checks are set.
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::ai::cheatsheets;
+use crate::utils::history::HistoryItem;
+
+// Looking around rather than doing anything; these start out unchecked
+const INCIDENTAL: &[&str] = &[
+    "ls", "ll", "la", "pwd", "clear", "history", "man", "less", "more", "which", "whoami", "exit", "top", "htop", "tldr",
+];
+// Repeated words shorter than this stay where they are
+const MIN_VARIABLE_LEN: usize = 6;
+// Interrupted with Ctrl+C rather than failed
+const INTERRUPTED: i32 = 130;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScriptOptions {
+    // Written there (executable) instead of shown
+    pub out: Option<PathBuf>,
+    // Passed through the auto-wiring system before it's written
+    pub polish: bool,
+    // Replaces `out` if it exists
+    pub force: bool,
+}
+
+// The checklist `scriptify` opens with, oldest first: a command repeated
+// back to back once, and failed or incidental ones unchecked
+pub fn checklist(items: &[HistoryItem]) -> Vec<(String, bool)> {
+    let mut lines: Vec<(String, bool)> = Vec::new();
+    for item in items {
+        let failed = matches!(item.exit_code, Some(code) if code != 0 && code != INTERRUPTED);
+        let checked = !failed && !is_incidental(&item.command);
+        match lines.last_mut() {
+            Some((last, was_checked)) if *last == item.command => *was_checked = checked,
+            _ => lines.push((item.command.clone(), checked)),
+        }
+    }
+    lines
+}
+
+fn is_incidental(command: &str) -> bool {
+    let program = cheatsheets::program_in(command.split_whitespace()).unwrap_or_default();
+    let name = Path::new(program).file_name().and_then(|n| n.to_str()).unwrap_or(program);
+    INCIDENTAL.contains(&name)
+}
+
+pub fn script(commands: &[String]) -> String {
+    let variables = variables(commands);
+    let mut secrets = BTreeSet::new();
+    let body: Vec<String> = commands
+        .iter()
+        .map(|command| {
+            let command = command
+                .split(' ')
+                .map(|word| match variables.iter().find(|(_, value)| value == word) {
+                    Some((name, _)) => format!("\"${{{}}}\"", name),
+                    None => word.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            env_references(&command, &mut secrets)
+        })
+        .collect();
+
+    let mut lines = vec![
+        "#!/usr/bin/env bash".to_string(),
+        format!("# {} commands from history, by nexterm scriptify", commands.len()),
+        "set -euo pipefail".to_string(),
+        String::new(),
+    ];
+    if !secrets.is_empty() {
+        lines.extend(secrets.iter().map(|key| format!(": \"${{{key}:?set {key} (it was in .env)}}\"")));
+        lines.push(String::new());
+    }
+    if !variables.is_empty() {
+        lines.extend(variables.iter().map(|(name, value)| format!("{}={}", name, value)));
+        lines.push(String::new());
+    }
+    lines.extend(body);
+    lines.join("\n") + "\n"
+}
+
+// `<env:KEY>` -> `${KEY}`, noting each KEY
+fn env_references(command: &str, keys: &mut BTreeSet<String>) -> String {
+    let mut out = String::new();
+    let mut rest = command;
+    while let Some(start) = rest.find("<env:") {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let key = &rest[start + "<env:".len()..start + end];
+        out.push_str(&rest[..start]);
+        out.push_str(&format!("${{{}}}", key));
+        keys.insert(key.to_string());
+        rest = &rest[start + end + 1..];
+    }
+    out + rest
+}
+
+// (name, value) for every path, URL, host or image used by more than one
+// command, in order of first use
+fn variables(commands: &[String]) -> Vec<(String, String)> {
+    let mut seen: Vec<&str> = Vec::new();
+    let mut variables: Vec<(String, String)> = Vec::new();
+    for (i, command) in commands.iter().enumerate() {
+        for word in command.split(' ').skip(1).filter(|w| is_value(w)) {
+            if seen.contains(&word) {
+                continue;
+            }
+            seen.push(word);
+            if commands[i + 1..].iter().any(|later| later.split(' ').any(|w| w == word)) {
+                let base = variable_name(word);
+                let mut name = base.clone();
+                for n in 2.. {
+                    if !variables.iter().any(|(taken, _)| *taken == name) {
+                        break;
+                    }
+                    name = format!("{}_{}", base, n);
+                }
+                variables.push((name, word.to_string()));
+            }
+        }
+    }
+    variables
+}
+
+// Worth naming, and safe to use bare on the right of an assignment
+fn is_value(word: &str) -> bool {
+    word.len() >= MIN_VARIABLE_LEN
+        && !word.starts_with('-')
+        && word.contains(['/', '.', ':'])
+        && word.chars().all(|c| c.is_ascii_alphanumeric() || "@%+:,./-_~".contains(c))
+}
+
+// https://... -> URL, /srv/app -> APP_PATH, registry.local/api:1.4 -> API_1_4
+fn variable_name(value: &str) -> String {
+    if value.contains("://") {
+        return "URL".to_string();
+    }
+    let stem = value.rsplit('/').find(|s| !s.is_empty()).unwrap_or(value);
+    let is_path = ["/", "./", "../", "~/"].iter().any(|prefix| value.starts_with(prefix));
+    let suffix = if is_path { "_PATH" } else { "" };
+    let mut name = String::new();
+    for c in stem.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_uppercase());
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name = name.trim_matches('_');
+    match name.chars().next() {
+        None => format!("VALUE{}", suffix),
+        Some(c) if c.is_ascii_digit() => format!("V_{}{}", name, suffix),
+        Some(_) => format!("{}{}", name, suffix),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(command: &str, exit_code: i32) -> HistoryItem {
+        HistoryItem { command: command.to_string(), timestamp: 0, cwd: None, exit_code: Some(exit_code), runs: 1 }
+    }
+
+    #[test]
+    fn checks_steps_and_skips_the_rest() {
+        let items = [
+            item("ls -la", 0),
+            item("cd /srv/app", 0),
+            item("git pull", 1),
+            item("git pull", 0),
+            item("make deploy", 2),
+        ];
+        assert_eq!(
+            checklist(&items),
+            [
+                ("ls -la".to_string(), false),
+                ("cd /srv/app".to_string(), true),
+                ("git pull".to_string(), true),
+                ("make deploy".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn extracts_variables_and_env_references() {
+        let commands: Vec<String> = [
+            "docker build -t registry.local/api:1.4 /srv/app",
+            "docker push registry.local/api:1.4",
+            "TOKEN=<env:DEPLOY_TOKEN> ./deploy.sh /srv/app",
+        ]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+
+        let script = script(&commands);
+        assert_eq!(
+            script,
+            "#!/usr/bin/env bash\n\
+             # 3 commands from history, by nexterm scriptify\n\
+             set -euo pipefail\n\
+             \n\
+             : \"${DEPLOY_TOKEN:?set DEPLOY_TOKEN (it was in .env)}\"\n\
+             \n\
+             API_1_4=registry.local/api:1.4\n\
+             APP_PATH=/srv/app\n\
+             \n\
+             docker build -t \"${API_1_4}\" \"${APP_PATH}\"\n\
+             docker push \"${API_1_4}\"\n\
+             TOKEN=${DEPLOY_TOKEN} ./deploy.sh \"${APP_PATH}\"\n"
+        );
+        assert_eq!(variable_name("https://example.com/x"), "URL");
+        assert_eq!(variable_name("nginx:1.25"), "NGINX_1_25");
+    }
+}
@@ -0,0 +1,212 @@
+// One shell per tab that commands run in, so `cd`, exports and functions
+// carry over from one command to the next. The shell sits on a pty of its own
+// but reads nothing from it: each command is written to a script that the
+// shell sources when a line comes in on a fifo, and it ends every command with
+// a private OSC mark carrying the exit code (see core::pty). Only POSIX shells
+// can source the script; with fish or PowerShell each command runs on its own.
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+use crate::core::pty::{self, PtyOutput, PtySession, Sinks, Slot};
+use crate::utils::shell_escape::ShellKind;
+
+// Private like core::peer's
+const MARK_OSC: &str = "7771";
+
+// Sources the script for each line on the fifo. Ctrl+C and `cancel` end the
+// command, not the shell, and the positional parameters are cleared for the
+// commands. The directory is written before the mark goes out.
+const DRIVER: &str = r#"__nexterm_fifo=$1 __nexterm_script=$2 __nexterm_cwd=$3 __nexterm_mark=$4
+set --
+trap : INT QUIT TERM
+while read -r __nexterm_go < "$__nexterm_fifo"; do
+  . "$__nexterm_script"
+  __nexterm_status=$?
+  printf '%s' "$PWD" > "$__nexterm_cwd"
+  printf '\033]%s%s\007' "$__nexterm_mark" "$__nexterm_status" > /dev/tty
+done"#;
+
+pub struct Shell {
+    master: File,
+    process: Arc<Mutex<Child>>,
+    pid: u32,
+    target: Slot,
+    // Holds the fifo, the script and the directory the shell was last in
+    dir: TempDir,
+    fifo: File,
+    mark: String,
+    size: (u16, u16),
+    // The command environment as of the last command, see `script`
+    exported: BTreeMap<String, String>,
+}
+
+impl Shell {
+    pub fn supports(shell: &str) -> bool {
+        ShellKind::from_shell(shell) == ShellKind::Posix
+    }
+
+    // Starts `shell` in NexTerm's working directory on a pty of `size`
+    pub fn spawn(shell: &str, size: (u16, u16)) -> Result<Self> {
+        let dir = tempfile::Builder::new().prefix("nexterm-shell").tempdir()?;
+        let fifo = dir.path().join("go");
+        let path = CString::new(fifo.as_os_str().as_bytes())?;
+        // SAFETY: mkfifo reads a NUL-terminated path we own
+        if unsafe { libc::mkfifo(path.as_ptr(), 0o600) } < 0 {
+            return Err(std::io::Error::last_os_error()).context("Failed to create the shell's fifo");
+        }
+        // Read as well, so opening doesn't wait for the shell and lines wait
+        // in the fifo until it reads them
+        let writer = OpenOptions::new().read(true).write(true).open(&fifo)?;
+
+        let mut nonce = [0u8; 8];
+        openssl::rand::rand_bytes(&mut nonce)?;
+        let nonce: String = nonce.iter().map(|b| format!("{:02x}", b)).collect();
+        let mark = format!("{};{};", MARK_OSC, nonce);
+
+        let mut command = Command::new(shell);
+        command
+            .arg("-c")
+            .arg(DRIVER)
+            .arg(shell)
+            .arg(&fifo)
+            .arg(dir.path().join("command"))
+            .arg(dir.path().join("cwd"))
+            .arg(&mark);
+        let (master, process) = pty::open(command, size)?;
+        let pid = process.id();
+        let target = Arc::new(Mutex::new(None));
+        let reader = master.try_clone()?;
+        {
+            let target = target.clone();
+            std::thread::spawn(move || pty::pump(reader, target));
+        }
+        Ok(Self {
+            master,
+            process: Arc::new(Mutex::new(process)),
+            pid,
+            target,
+            dir,
+            fifo: writer,
+            mark,
+            size,
+            exported: BTreeMap::new(),
+        })
+    }
+
+    // Runs `command` with `envs` on top of NexTerm's environment; done once
+    // the shell marks its end
+    pub fn run(
+        &mut self,
+        command: &str,
+        envs: &BTreeMap<String, String>,
+        size: (u16, u16),
+        sinks: Sinks,
+    ) -> Result<(PtySession, PtyOutput)> {
+        if self.size != size {
+            pty::resize(&self.master, size.0, size.1);
+            self.size = size;
+        }
+        std::fs::write(self.dir.path().join("command"), self.script(command, envs))?;
+        let prefix = format!("\x1b]{}", self.mark).into_bytes();
+        let started = PtySession::start(&self.master, &self.target, size, sinks, Some(prefix), self.process.clone())?;
+        self.fifo.write_all(b"\n").context("The shell is gone")?;
+        Ok(started)
+    }
+
+    // `command` after bringing the shell's copy of the command environment up
+    // to date; variables the command set itself are left alone
+    fn script(&mut self, command: &str, envs: &BTreeMap<String, String>) -> String {
+        let valid = |name: &str| {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        };
+        let mut script = String::new();
+        for name in self.exported.keys().filter(|name| !envs.contains_key(*name) && valid(name)) {
+            script.push_str(&format!("unset {}\n", name));
+        }
+        for (name, value) in envs.iter().filter(|(name, value)| self.exported.get(*name) != Some(value)) {
+            if valid(name) {
+                script.push_str(&format!("export {}={}\n", name, ShellKind::Posix.quote(value)));
+            }
+        }
+        self.exported = envs.clone();
+        script.push_str(command);
+        script.push('\n');
+        script
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    // Where the shell was when its last command ended
+    pub fn cwd(&self) -> Option<PathBuf> {
+        let cwd = std::fs::read_to_string(self.dir.path().join("cwd")).ok()?;
+        Some(PathBuf::from(cwd)).filter(|cwd| cwd.is_dir())
+    }
+
+    // False once the shell exited, e.g. on `exit`
+    pub fn is_alive(&self) -> bool {
+        self.process.lock().is_ok_and(|mut process| matches!(process.try_wait(), Ok(None)))
+    }
+}
+
+// Hangs up on the shell and whatever it left running, as closing a tab does
+impl Drop for Shell {
+    fn drop(&mut self) {
+        // SAFETY: kill has no memory effects
+        unsafe { libc::kill(-(self.pid as i32), libc::SIGHUP) };
+        if let Ok(mut process) = self.process.lock() {
+            let _ = process.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::binary::BinaryOutput;
+    use crate::core::encoding::Encoding;
+    use crate::core::postprocess::Pipeline;
+    use crate::core::tee::Tee;
+    use std::sync::atomic::AtomicBool;
+
+    fn sinks() -> Sinks {
+        Sinks {
+            buffer: Arc::new(Mutex::new(Vec::new())),
+            window: None,
+            tee: Tee::new([]),
+            encoding: Encoding::Utf8,
+            binary: BinaryOutput::new(1, "sh").shared(),
+            visible: Arc::new(AtomicBool::new(true)),
+            pipeline: Pipeline::default(),
+        }
+    }
+
+    #[test]
+    fn keeps_state_between_commands() {
+        let mut shell = Shell::spawn("sh", (24, 80)).unwrap();
+        let mut run = |command: &str, envs: &BTreeMap<String, String>| {
+            let (_session, output) = shell.run(command, envs, (24, 80), sinks()).unwrap();
+            output.wait()
+        };
+        let envs = BTreeMap::from([("NEXTERM_TEST".to_string(), "it's".to_string())]);
+        assert_eq!(run("cd /tmp; greet() { echo hi $1; }; X=1", &envs).0, 0);
+        assert_eq!(run("greet there; echo $X $NEXTERM_TEST; false", &envs), (1, "hi there\n1 it's\n".to_string()));
+        assert_eq!(run("echo ${NEXTERM_TEST-unset}", &BTreeMap::new()).1, "unset\n");
+        assert_eq!(shell.cwd().unwrap(), PathBuf::from("/tmp"));
+
+        let (_session, output) = shell.run("exit 3", &BTreeMap::new(), (24, 80), sinks()).unwrap();
+        assert_eq!(output.wait().0, 3);
+        assert!(!shell.is_alive());
+    }
+}
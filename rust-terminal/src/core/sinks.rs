@@ -0,0 +1,334 @@
+// Notification sinks: finished commands, and BELs, delivered off this machine
+// (a webhook, Slack or email) so a long remote build can ping a phone. Each
+// sink picks its events and how the message reads:
+//
+//   [[notify]]
+//   sink = "slack"                       # webhook, slack or email
+//   token = "$SLACK_TOKEN"               # "$VAR" reads it from the environment
+//   channel = "#builds"
+//   events = ["failure"]                 # success, failure, bell; default success and failure
+//   template = "{status}: {command} after {duration} on {host}"
+//
+//   [[notify]]
+//   sink = "webhook"                     # POSTs {"text": ..., "event": ..., "command": ..., ...}
+//   url = "https://ntfy.example.com/builds"
+//
+//   [[notify]]
+//   sink = "email"                       # sent with curl
+//   smtp = "smtps://smtp.example.com:465"
+//   user = "me@example.com"
+//   password = "$SMTP_PASSWORD"
+//   from = "me@example.com"
+//   to = "me@example.com"
+//
+// Template fields are {status}, {command}, {code}, {duration}, {host} and
+// {cwd}. Commands quicker than `min_secs` under [bell] don't notify on
+// finishing. Delivery happens in the background; failures come back as toasts.
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::Write;
+use std::process::Stdio;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use crate::utils::rng::Rng;
+
+// Most a delivery may take before it counts as failed
+const SEND_TIMEOUT: Duration = Duration::from_secs(15);
+const DEFAULT_TEMPLATE: &str = "{status}: {command} after {duration} on {host}";
+const SLACK_POST: &str = "https://slack.com/api/chat.postMessage";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyEvent {
+    Success,
+    Failure,
+    Bell,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "sink", rename_all = "lowercase")]
+pub enum Sink {
+    Webhook { url: String },
+    Slack { token: String, channel: String },
+    Email { smtp: String, user: Option<String>, password: Option<String>, from: String, to: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinkConfig {
+    #[serde(flatten)]
+    pub sink: Sink,
+    #[serde(default = "default_events")]
+    pub events: Vec<NotifyEvent>,
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+fn default_events() -> Vec<NotifyEvent> {
+    vec![NotifyEvent::Success, NotifyEvent::Failure]
+}
+
+impl Sink {
+    fn name(&self) -> &'static str {
+        match self {
+            Sink::Webhook { .. } => "webhook",
+            Sink::Slack { .. } => "Slack",
+            Sink::Email { .. } => "email",
+        }
+    }
+}
+
+// What happened, for the template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub event: NotifyEvent,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub duration_ms: Option<u64>,
+    pub host: String,
+    pub cwd: String,
+}
+
+impl Notification {
+    fn new(event: NotifyEvent, command: &str) -> Self {
+        Self {
+            event,
+            command: command.to_string(),
+            exit_code: None,
+            duration_ms: None,
+            host: hostname(),
+            cwd: std::env::current_dir().map(|d| d.display().to_string()).unwrap_or_default(),
+        }
+    }
+
+    pub fn render(&self, template: &str) -> String {
+        let status = match (self.event, self.exit_code) {
+            (NotifyEvent::Success, _) => "✓ Finished".to_string(),
+            (NotifyEvent::Failure, Some(code)) => format!("✗ Failed with {}", code),
+            (NotifyEvent::Failure, None) => "✗ Failed".to_string(),
+            (NotifyEvent::Bell, _) => "🔔 Bell".to_string(),
+        };
+        let duration = self.duration_ms.map(|ms| format_duration(ms / 1000)).unwrap_or_else(|| "-".to_string());
+        template
+            .replace("{status}", &status)
+            .replace("{command}", &self.command)
+            .replace("{code}", &self.exit_code.map(|c| c.to_string()).unwrap_or_default())
+            .replace("{duration}", &duration)
+            .replace("{host}", &self.host)
+            .replace("{cwd}", &self.cwd)
+    }
+}
+
+fn format_duration(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs / 60 % 60),
+    }
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/etc/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|h| h.trim().to_string())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+pub struct Notifier {
+    sinks: Vec<SinkConfig>,
+    min_secs: u64,
+    http: reqwest::Client,
+    // Why a delivery failed, for the notification center
+    failures: Sender<String>,
+    failed: Receiver<String>,
+}
+
+impl Notifier {
+    pub fn new(sinks: &[SinkConfig], min_secs: u64) -> Self {
+        let (failures, failed) = mpsc::channel();
+        let http = reqwest::Client::builder().timeout(SEND_TIMEOUT).build().unwrap_or_default();
+        Self { sinks: sinks.to_vec(), min_secs, http, failures, failed }
+    }
+
+    pub fn finished(&self, command: &str, exit_code: i32, duration_ms: u64) {
+        if duration_ms < self.min_secs * 1000 {
+            return;
+        }
+        let event = if exit_code == 0 { NotifyEvent::Success } else { NotifyEvent::Failure };
+        let notification = Notification {
+            exit_code: Some(exit_code),
+            duration_ms: Some(duration_ms),
+            ..Notification::new(event, command)
+        };
+        self.send(notification);
+    }
+
+    pub fn bell(&self, command: &str) {
+        self.send(Notification::new(NotifyEvent::Bell, command));
+    }
+
+    // `notify test`: a made-up success to every sink, whatever its events.
+    // Returns how many sinks there are.
+    pub fn test(&self) -> usize {
+        let notification = Notification {
+            exit_code: Some(0),
+            duration_ms: Some(0),
+            ..Notification::new(NotifyEvent::Success, "notify test")
+        };
+        for config in &self.sinks {
+            self.deliver(config, &notification);
+        }
+        self.sinks.len()
+    }
+
+    fn send(&self, notification: Notification) {
+        for config in self.sinks.iter().filter(|c| c.events.contains(&notification.event)) {
+            self.deliver(config, &notification);
+        }
+    }
+
+    fn deliver(&self, config: &SinkConfig, notification: &Notification) {
+        let text = notification.render(config.template.as_deref().unwrap_or(DEFAULT_TEMPLATE));
+        let (sink, notification, http, failures) =
+            (config.sink.clone(), notification.clone(), self.http.clone(), self.failures.clone());
+        tokio::spawn(async move {
+            if let Err(e) = deliver(&sink, &notification, &text, &http).await {
+                let _ = failures.send(format!("Notification to {} failed: {:#}", sink.name(), e));
+            }
+        });
+    }
+
+    pub fn take_failures(&self) -> Vec<String> {
+        self.failed.try_iter().collect()
+    }
+}
+
+async fn deliver(sink: &Sink, notification: &Notification, text: &str, http: &reqwest::Client) -> Result<()> {
+    match sink {
+        Sink::Webhook { url } => {
+            let body = json!({
+                "text": text,
+                "event": notification.event,
+                "command": notification.command,
+                "exit_code": notification.exit_code,
+                "duration_ms": notification.duration_ms,
+                "host": notification.host,
+            });
+            http.post(url).json(&body).send().await?.error_for_status()?;
+        }
+        Sink::Slack { token, channel } => {
+            let reply: serde_json::Value = http
+                .post(SLACK_POST)
+                .bearer_auth(expand(token)?)
+                .json(&json!({ "channel": channel, "text": text }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            if reply["ok"] != true {
+                bail!("{}", reply["error"].as_str().unwrap_or("rejected"));
+            }
+        }
+        Sink::Email { smtp, user, password, from, to } => {
+            let credentials = match (user, password) {
+                (Some(user), Some(password)) => Some(format!("{}:{}", user, expand(password)?)),
+                (Some(user), None) => Some(user.clone()),
+                _ => None,
+            };
+            let subject = text.lines().next().unwrap_or_default();
+            let message = format!("From: {}\r\nTo: {}\r\nSubject: {}\r\n\r\n{}\r\n", from, to, subject, text);
+            let (smtp, from, to) = (smtp.clone(), from.clone(), to.clone());
+            tokio::task::spawn_blocking(move || send_mail(&smtp, credentials.as_deref(), &from, &to, &message)).await??;
+        }
+    }
+    Ok(())
+}
+
+// `$VAR` and `${VAR}` from the environment, for secrets kept out of the config
+fn expand(secret: &str) -> Result<String> {
+    shellexpand::env(secret).map(|s| s.into_owned()).map_err(|e| anyhow!("{}", e))
+}
+
+// Through curl, which speaks SMTP with STARTTLS and auth. The credentials go
+// in on stdin, as curl config, so they don't show up in `ps`; the message
+// goes through a private temporary file.
+fn send_mail(smtp: &str, credentials: Option<&str>, from: &str, to: &str, message: &str) -> Result<()> {
+    let path = std::env::temp_dir().join(format!("nexterm-mail-{}-{}", std::process::id(), Rng::new().next_u64()));
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    file.write_all(message.as_bytes())?;
+    drop(file);
+
+    let result = (|| {
+        let mut child = std::process::Command::new("curl")
+            .args(["--silent", "--show-error", "--ssl-reqd", "--max-time", &SEND_TIMEOUT.as_secs().to_string()])
+            .args(["--url", smtp, "--mail-from", from, "--mail-rcpt", to, "--upload-file"])
+            .arg(&path)
+            .args(["--config", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to run curl")?;
+        let config = credentials.map(|c| format!("user = \"{}\"\n", c.replace('\\', "\\\\").replace('"', "\\\"")));
+        child.stdin.take().context("curl has no stdin")?.write_all(config.unwrap_or_default().as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(())
+    })();
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_sinks_and_renders_templates() {
+        #[derive(Deserialize)]
+        struct File {
+            notify: Vec<SinkConfig>,
+        }
+        let config = toml::from_str::<File>(
+            r##"
+            [[notify]]
+            sink = "slack"
+            token = "$SLACK_TOKEN"
+            channel = "#builds"
+            events = ["failure"]
+            template = "{status} `{command}` ({duration}) on {host}"
+
+            [[notify]]
+            sink = "webhook"
+            url = "https://hooks.example.com/x"
+            "##,
+        )
+        .unwrap()
+        .notify;
+        assert!(matches!(&config[0].sink, Sink::Slack { channel, .. } if channel == "#builds"));
+        assert_eq!(config[1].events, [NotifyEvent::Success, NotifyEvent::Failure]);
+
+        let notification = Notification {
+            event: NotifyEvent::Failure,
+            command: "make release".to_string(),
+            exit_code: Some(2),
+            duration_ms: Some(754_000),
+            host: "build01".to_string(),
+            cwd: "/src".to_string(),
+        };
+        assert_eq!(
+            notification.render(config[0].template.as_deref().unwrap()),
+            "✗ Failed with 2 `make release` (12m34s) on build01"
+        );
+        assert_eq!(notification.render(DEFAULT_TEMPLATE), "✗ Failed with 2: make release after 12m34s on build01");
+    }
+}
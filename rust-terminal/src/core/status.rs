@@ -0,0 +1,291 @@
+// `status [--verbose]`: everything NexTerm is running with, in one place, for
+// bug reports: recent sessions, tabs, jobs, the auto-wiring bridge, plugins,
+// what's stored on disk and where the configuration came from. As text, or
+// as JSON (`--json`) for tooling and the control socket. `nexterm status`
+// reports the same from outside the TUI, without tabs.
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::ai::project_notes::ProjectNotes;
+use crate::core::binary::size_label;
+use crate::core::terminal::Terminal;
+use crate::plugins::manager::{PluginInfo, PluginManager};
+use crate::utils::clock;
+use crate::utils::config::Config;
+use crate::utils::sessions::{RecentSessions, SessionEntry};
+use crate::utils::storage;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub version: String,
+    pub pid: u32,
+    pub sources: Vec<Source>,
+    // NEXTERM_* variables that are set; values are left out, some are secrets
+    pub env: Vec<String>,
+    pub sessions: Vec<SessionEntry>,
+    pub tabs: Vec<TabStatus>,
+    pub jobs: Jobs,
+    pub bridge: BridgeStatus,
+    pub plugins: Vec<PluginInfo>,
+    pub data_dir: PathBuf,
+    // Top-level entries of the data directory, biggest first
+    pub storage: Vec<Usage>,
+}
+
+// A file configuration is read from
+#[derive(Debug, Clone, Serialize)]
+pub struct Source {
+    pub name: String,
+    pub path: PathBuf,
+    pub present: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TabStatus {
+    pub title: String,
+    pub env: Option<String>,
+    pub muted: bool,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Jobs {
+    pub running: Option<Job>,
+    pub queued: usize,
+    pub daemons: Vec<Job>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub command: String,
+    pub pid: Option<u32>,
+    pub running: bool,
+    // How long the running command has been at it
+    pub secs: Option<u64>,
+    // Daemons only
+    pub id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeStatus {
+    pub transport: String,
+    pub status: String,
+    pub services: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Usage {
+    pub name: String,
+    pub bytes: u64,
+}
+
+impl StatusReport {
+    pub fn collect(terminal: &Terminal, tabs: Vec<TabStatus>) -> Self {
+        let config = terminal.config();
+        let running = terminal.running_command().map(|running| Job {
+            command: running.command.clone(),
+            pid: running.pid,
+            running: true,
+            secs: Some(clock::since(running.started).as_secs()),
+            id: None,
+        });
+        let daemons = terminal
+            .daemons()
+            .list()
+            .into_iter()
+            .map(|daemon| Job {
+                command: daemon.command,
+                pid: Some(daemon.pid),
+                running: daemon.running,
+                secs: None,
+                id: Some(daemon.id),
+            })
+            .collect();
+        let data_dir = storage::data_dir();
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            pid: std::process::id(),
+            sources: sources(terminal),
+            env: std::env::vars().map(|(name, _)| name).filter(|name| name.starts_with("NEXTERM_")).collect(),
+            sessions: RecentSessions::load(&config.storage).entries,
+            tabs,
+            jobs: Jobs { running, queued: terminal.queued_commands(), daemons },
+            bridge: BridgeStatus {
+                transport: config.autowire.transport.to_string(),
+                status: terminal.get_autowire_status(),
+                services: terminal.get_autowire_services().len(),
+            },
+            plugins: terminal.plugins(),
+            storage: usage(&data_dir),
+            data_dir,
+        }
+    }
+
+    // A line per section; `verbose` lists what's in each
+    pub fn render(&self, verbose: bool) -> Vec<String> {
+        let mut lines = vec![format!("NexTerm {} (pid {})", self.version, self.pid)];
+        let mut section = |summary: String, items: Vec<String>| {
+            lines.push(summary);
+            if verbose {
+                lines.extend(items.into_iter().map(|item| format!("    {}", item)));
+            }
+        };
+
+        section(
+            format!("Config: {}", self.sources.iter().filter(|s| s.present).map(|s| s.name.as_str()).collect::<Vec<_>>().join(", ")),
+            self.sources
+                .iter()
+                .map(|s| format!("{} {}: {}", if s.present { "✓" } else { "·" }, s.name, s.path.display()))
+                .chain(self.env.iter().map(|name| format!("$ {}", name)))
+                .collect(),
+        );
+        section(
+            format!("Sessions: {} recent", self.sessions.len()),
+            self.sessions.iter().map(|s| s.directory.clone()).collect(),
+        );
+        let active = self.tabs.iter().find(|t| t.active).map(|t| format!(" (active: {})", t.title)).unwrap_or_default();
+        section(
+            match self.tabs.len() {
+                0 => "Tabs: none outside the TUI".to_string(),
+                n => format!("Tabs: {}{}", n, active),
+            },
+            self.tabs
+                .iter()
+                .map(|t| {
+                    let env = t.env.as_ref().map(|e| format!(" [{}]", e)).unwrap_or_default();
+                    let muted = if t.muted { " (muted)" } else { "" };
+                    format!("{} {}{}{}", if t.active { "▸" } else { " " }, t.title, env, muted)
+                })
+                .collect(),
+        );
+        let alive = self.jobs.daemons.iter().filter(|d| d.running).count();
+        let running = match &self.jobs.running {
+            Some(job) => format!("running `{}`", job.command),
+            None => "idle".to_string(),
+        };
+        section(
+            format!("Jobs: {}, {} queued, {} daemons ({} running)", running, self.jobs.queued, self.jobs.daemons.len(), alive),
+            self.jobs.running.iter().chain(&self.jobs.daemons).map(describe_job).collect(),
+        );
+        section(
+            format!("Auto-Wire: {} over {}", self.bridge.status, self.bridge.transport),
+            vec![format!("{} services", self.bridge.services)],
+        );
+        let enabled = self.plugins.iter().filter(|p| p.enabled).count();
+        let failed = self.plugins.iter().filter(|p| p.error.is_some()).count();
+        section(
+            format!("Plugins: {} ({} enabled, {} failed)", self.plugins.len(), enabled, failed),
+            self.plugins
+                .iter()
+                .map(|p| {
+                    let state = match (&p.error, p.enabled) {
+                        (Some(error), _) => format!("failed: {}", error),
+                        (None, true) => "enabled".to_string(),
+                        (None, false) => "disabled".to_string(),
+                    };
+                    format!("{} ({}) {}", p.name, p.source, state)
+                })
+                .collect(),
+        );
+        let total: u64 = self.storage.iter().map(|u| u.bytes).sum();
+        section(
+            format!("Storage: {} in {}", size_label(total as usize), self.data_dir.display()),
+            self.storage.iter().map(|u| format!("{:>10}  {}", size_label(u.bytes as usize), u.name)).collect(),
+        );
+        lines
+    }
+}
+
+fn describe_job(job: &Job) -> String {
+    let pid = job.pid.map(|pid| format!("pid {}", pid)).unwrap_or_else(|| "no pid".to_string());
+    match (job.id, job.secs) {
+        (Some(id), _) => format!("daemon {} `{}` ({}, {})", id, job.command, pid, if job.running { "running" } else { "exited" }),
+        (None, secs) => format!("`{}` ({}, {}s)", job.command, pid, secs.unwrap_or(0)),
+    }
+}
+
+fn sources(terminal: &Terminal) -> Vec<Source> {
+    let source = |name: &str, path: PathBuf| Source { name: name.to_string(), present: path.exists(), path };
+    let mut sources = vec![
+        source("config.toml", Config::dir().join("config.toml")),
+        source("keybindings.toml", Config::dir().join("keybindings.toml")),
+        source("plugins", PluginManager::dir()),
+    ];
+    if let Some(dotenv) = terminal.dotenv() {
+        sources.extend(dotenv.files.iter().map(|file| source(".env", file.clone())));
+    }
+    if let Some(notes) = ProjectNotes::discover() {
+        sources.push(source("project notes", notes.path));
+    }
+    sources
+}
+
+fn usage(dir: &Path) -> Vec<Usage> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut usage: Vec<Usage> = entries
+        .flatten()
+        .map(|entry| Usage { name: entry.file_name().to_string_lossy().into_owned(), bytes: size(&entry.path()) })
+        .collect();
+    usage.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    usage
+}
+
+fn size(path: &Path) -> u64 {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    std::fs::read_dir(path).map(|entries| entries.flatten().map(|e| size(&e.path())).sum()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_a_summary_and_the_details() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("daemons")).unwrap();
+        std::fs::write(dir.path().join("daemons/1.log"), vec![b'x'; 2048]).unwrap();
+        std::fs::write(dir.path().join("history.json"), b"[]").unwrap();
+        let report = StatusReport {
+            version: "0.1.0".to_string(),
+            pid: 42,
+            sources: vec![Source { name: "config.toml".to_string(), path: PathBuf::from("/c/config.toml"), present: true }],
+            env: vec!["NEXTERM_AI".to_string()],
+            sessions: Vec::new(),
+            tabs: vec![TabStatus { title: "Terminal".to_string(), env: Some("prod".to_string()), muted: true, active: true }],
+            jobs: Jobs {
+                running: None,
+                queued: 1,
+                daemons: vec![Job { command: "serve".to_string(), pid: Some(7), running: true, secs: None, id: Some(1) }],
+            },
+            bridge: BridgeStatus { transport: "stdio".to_string(), status: "⏳ Loading".to_string(), services: 0 },
+            plugins: Vec::new(),
+            storage: usage(dir.path()),
+            data_dir: PathBuf::from("/d"),
+        };
+        assert_eq!(
+            report.render(false),
+            [
+                "NexTerm 0.1.0 (pid 42)",
+                "Config: config.toml",
+                "Sessions: 0 recent",
+                "Tabs: 1 (active: Terminal)",
+                "Jobs: idle, 1 queued, 1 daemons (1 running)",
+                "Auto-Wire: ⏳ Loading over stdio",
+                "Plugins: 0 (0 enabled, 0 failed)",
+                "Storage: 2.0 KB in /d",
+            ]
+        );
+        let verbose = report.render(true);
+        assert!(verbose.contains(&"    ▸ Terminal [prod] (muted)".to_string()));
+        assert!(verbose.contains(&"    daemon 1 `serve` (pid 7, running)".to_string()));
+        assert!(verbose.contains(&"        2.0 KB  daemons".to_string()));
+        assert!(serde_json::to_value(&report).unwrap()["jobs"]["daemons"][0]["pid"] == 7);
+    }
+}
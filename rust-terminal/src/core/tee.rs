@@ -0,0 +1,207 @@
+// Output teed to a file as it streams: a single command's with a `:tee path`
+// suffix, or every command's while the pane is logging (`log start`). What
+// goes to disk is raw, escape sequences and all for commands on a pty, but
+// transcoded to UTF-8 if the pane has another encoding (core::encoding). Files
+// rotate once they reach `max_bytes`, keeping `keep` old ones as path.1,
+// path.2 and so on. Configured as
+//
+//   [tee]
+//   max_bytes = 10485760
+//   keep = 3
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::utils::storage;
+
+// Suffix that tees a single command's output, followed by the file
+pub const SUFFIX: &str = ":tee";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeeConfig {
+    // 0 never rotates
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+}
+
+fn default_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_keep() -> usize {
+    3
+}
+
+impl Default for TeeConfig {
+    fn default() -> Self {
+        Self { max_bytes: default_max_bytes(), keep: default_keep() }
+    }
+}
+
+pub struct TeeFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+pub type SharedTee = Arc<Mutex<TeeFile>>;
+
+impl TeeFile {
+    // Appends to `path`, creating it and its directory if need be
+    pub fn open(path: &Path, config: &TeeConfig) -> Result<Self> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {} for output", path.display()))?;
+        let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self { path: path.to_path_buf(), file, written, max_bytes: config.max_bytes, keep: config.keep })
+    }
+
+    pub fn shared(self) -> SharedTee {
+        Arc::new(Mutex::new(self))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if self.max_bytes > 0 && self.written > 0 && self.written + bytes.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+
+    // path -> path.1 -> path.2 ..., the oldest past `keep` dropped
+    fn rotate(&mut self) -> io::Result<()> {
+        let numbered = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+        for n in (1..self.keep).rev() {
+            ignore_missing(fs::rename(numbered(n), numbered(n + 1)))?;
+        }
+        match self.keep {
+            0 => ignore_missing(fs::remove_file(&self.path))?,
+            _ => ignore_missing(fs::rename(&self.path, numbered(1)))?,
+        }
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+fn ignore_missing(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+// Where a running command's output goes besides the output buffer: its own
+// file, the pane's log, both or neither
+#[derive(Clone, Default)]
+pub struct Tee(Vec<SharedTee>);
+
+impl Tee {
+    pub fn new(files: impl IntoIterator<Item = SharedTee>) -> Self {
+        Self(files.into_iter().collect())
+    }
+
+    // A file that fails is left out from then on
+    pub fn write(&mut self, bytes: &[u8]) {
+        self.0.retain(|file| {
+            let Ok(mut file) = file.lock() else {
+                return false;
+            };
+            match file.write(bytes) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("Stopped writing output to {}: {}", file.path().display(), e);
+                    false
+                }
+            }
+        });
+    }
+}
+
+// Splits a trailing `:tee path` off `command`; a `:tee` inside quotes is
+// part of the command
+pub fn strip_suffix(command: &str) -> (&str, Option<PathBuf>) {
+    let marker = format!(" {} ", SUFFIX);
+    let Some(at) = unquoted(command).filter(|&i| command[i..].starts_with(&marker)).last() else {
+        return (command, None);
+    };
+    match command[at + marker.len()..].trim() {
+        "" => (command, None),
+        path => (command[..at].trim_end(), Some(PathBuf::from(shellexpand::tilde(path).into_owned()))),
+    }
+}
+
+// Byte offsets in `command` outside single and double quotes
+pub fn unquoted(command: &str) -> impl Iterator<Item = usize> + '_ {
+    let mut quote = None;
+    let mut escaped = false;
+    command.char_indices().filter_map(move |(i, c)| {
+        let outside = quote.is_none() && !escaped;
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (None, '\'' | '"') => quote = Some(c),
+            _ => {}
+        }
+        outside.then_some(i)
+    })
+}
+
+// Where `log start` without a path writes
+pub fn default_log_path(timestamp: u64) -> PathBuf {
+    logs_dir().join(format!("output-{}.log", timestamp))
+}
+
+pub fn logs_dir() -> PathBuf {
+    storage::data_dir().join("logs")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_off_the_tee_suffix() {
+        assert_eq!(strip_suffix("make build :tee build.log"), ("make build", Some(PathBuf::from("build.log"))));
+        assert_eq!(strip_suffix("echo ':tee' :tee out.txt "), ("echo ':tee'", Some(PathBuf::from("out.txt"))));
+        assert_eq!(strip_suffix("make build :tee "), ("make build :tee ", None));
+        assert_eq!(strip_suffix("make build"), ("make build", None));
+        assert_eq!(strip_suffix("echo 'a :tee x'"), ("echo 'a :tee x'", None));
+        assert_eq!(strip_suffix("echo \"a \\\" :tee x\""), ("echo \"a \\\" :tee x\"", None));
+    }
+
+    #[test]
+    fn rotates_and_keeps_the_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.log");
+        let mut tee = TeeFile::open(&path, &TeeConfig { max_bytes: 8, keep: 2 }).unwrap();
+        for chunk in ["one\n", "two\n", "three\n", "four\n", "five\n"] {
+            tee.write(chunk.as_bytes()).unwrap();
+        }
+        let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(read("out.log"), "five\n");
+        assert_eq!(read("out.log.1"), "four\n");
+        assert_eq!(read("out.log.2"), "three\n");
+        assert!(!dir.path().join("out.log.3").exists());
+    }
+}
@@ -1,13 +1,72 @@
 // Core Terminal Engine with Auto-Wiring Integration
 use anyhow::{Result, Context};
-use std::process::{Command, Child, Stdio};
+use std::process::Child;
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
 use tokio::sync::mpsc;
-use tracing::{info, error, debug};
+use crossterm::event::{KeyCode, KeyEvent};
+use tracing::{info, error, debug, warn};
 use serde::{Deserialize, Serialize};
 
+use crate::core::actions::{self, ActionRegistry};
+use crate::core::bell::Ring;
+use crate::core::binary::{self, BinaryOutput, SharedBinary, Sniffer};
+use crate::core::command::CommandParser;
+use crate::core::daemons::{DaemonInfo, DaemonRegistry};
+use crate::core::dotenv::DotEnv;
+use crate::core::encoding::Encoding;
+use crate::core::environments;
+use crate::core::executor::{Executor, ShellExecutor};
+use crate::core::filters::{self, FilterPresets};
+use crate::core::hooks::Hooks;
+use crate::core::peer::{self, Item, Message, Side};
+use crate::core::workflow::{Next, RollbackPlan, Transaction, Workflow};
+use crate::core::json_output::{self, JsonSink};
+use crate::core::modes::Modes;
+use crate::core::policy::{self, CommandPolicy};
+use crate::core::preflight::{self, CheckRegistry, FindingLevel};
+use crate::core::operations::{Cancel, Kind, Operation, Operations};
+use crate::core::postprocess::{self, Pipeline, PostProcessors};
+use crate::core::process_monitor::RunningCommand;
+use crate::core::prompts::{Detector, PromptProfiles};
+use crate::core::pty::{PtyOutput, PtySession, Sinks};
+use crate::core::retention::{RetentionRules, Window};
+use crate::core::roaming::{self, RoamMode};
+use crate::core::scriptify::{self, ScriptOptions};
+use crate::core::shell::Shell;
+use crate::core::sinks::Notifier;
+use crate::core::tee::{self, SharedTee, Tee, TeeFile};
+use crate::core::triggers::{TriggerAction, Triggers};
+use crate::core::variables::Variables;
+use crate::core::vt::ScreenView;
+use crate::plugins::manager::{PluginInfo, PluginManager};
+use crate::utils::browser;
+use crate::utils::buffers::CaptureBuffers;
+use crate::utils::clock;
 use crate::utils::config::Config;
-use crate::ai::autowire_bridge::AutoWireBridge;
+use crate::utils::editor;
+use crate::utils::error::{self, NexTermError};
+use crate::utils::failures::FailureTracker;
+use crate::utils::history::{History, HistoryItem};
+use crate::utils::report;
+use crate::utils::sessions::RecentSessions;
+use crate::utils::startup::Pending;
+use crate::utils::shell_escape::ShellKind;
+use crate::utils::supervisor::{self, Supervisor};
+use crate::ai::autowire_bridge::{AutoWireBridge, AutoWireResult};
+use crate::ai::autowire_daemon::Health;
+use crate::ai::digest::Digest;
+use crate::ai::explain::{Explainer, Explanation};
+use crate::ai::fallbacks::{Fallback, FallbackLog};
+use crate::ai::project_notes::ProjectNotes;
+use crate::ai::proposals::Proposals;
+use crate::ai::review::{self, Finding};
+use crate::ai::suggestions::SuggestionScheduler;
+use crate::ai::transport;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandResult {
@@ -17,115 +76,1188 @@ pub struct CommandResult {
     pub exit_code: i32,
     pub duration_ms: u64,
     pub autowire_processed: bool,
+    // Auto-wiring metadata (suggestions, agent, confidence) when it processed the command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autowire: Option<AutoWireResult>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+// Transient message for the UI, shown as a toast rather than written to the output buffer
+#[derive(Debug, Clone)]
+pub struct Notice {
+    pub severity: Severity,
+    pub message: String,
+}
+
+// Semantic prompt marker: the output line where a command's echoed prompt starts
+#[derive(Debug, Clone)]
+pub struct PromptMark {
+    pub line: usize,
+    pub command: String,
+}
+
+// A clipboard text or file from the other NexTerm, see core::peer
+#[derive(Debug, Clone)]
+pub struct PeerOffer {
+    pub id: u64,
+    pub item: Item,
+}
+
+// AI output (auto-wiring suggestions, teaching-mode explanations, fix hints),
+// kept out of the output buffer so captures, filters and copies only ever see
+// what commands printed. Drawn after output line `line - 1`.
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    pub line: usize,
+    pub text: Vec<String>,
+}
+
+// Commands NexTerm starts on its own, held for the frontend's guard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Held {
+    Step,
+    Trigger,
+    // Auto-wiring rewrote one into a command that changes something, which
+    // the guard hasn't seen yet; it then runs as rewritten
+    Rewrite { step: bool, triggered: bool },
 }
 
 pub struct Terminal {
     config: Config,
+    // Runs commands that don't get a pseudo-terminal
+    executor: Arc<dyn Executor>,
     shell_process: Option<Child>,
     output_buffer: Arc<Mutex<Vec<String>>>,
-    command_history: Vec<String>,
+    prompt_marks: Vec<PromptMark>,
+    annotations: Vec<Annotation>,
+    command_history: History,
     autowire_bridge: Option<AutoWireBridge>,
+    // Set up in the background at startup: the transport (whether it could
+    // be configured) and the bridge over it, then the plugins
+    loading_autowire: Option<Pending<(Result<()>, Result<AutoWireBridge>)>>,
+    loading_plugins: Option<Pending<PluginManager>>,
+    // Until both are in; unapplied key bindings are reported then
+    starting: bool,
+    // Catch panics in auto-wiring
+    autowire_supervisor: Supervisor,
+    // Auto-wiring is over its latency limits; warned about once per breach
+    autowire_slow: bool,
+    // Why commands ran as typed, for `autowire explain-last` and the panel
+    autowire_fallbacks: FallbackLog,
+    notices: Vec<Notice>,
+    // Bells for the UI to ring, and BELs from commands on pipes not yet looked at
+    rings: Vec<Ring>,
+    bells: Arc<AtomicUsize>,
+    // The same events, sent to webhooks, Slack and email
+    notifier: Notifier,
+    // Whether the running command's output is on screen. While it isn't, pty
+    // output is held back unparsed and triggers and plugins don't see new lines.
+    visible: Arc<AtomicBool>,
+    // The tab shown, and the one the running command started in
+    tab: usize,
+    command_tab: usize,
+    // Tabs switched to another encoding than the config's, by tab id
+    encodings: HashMap<usize, Encoding>,
+    // Behind the lock screen: other NexTerms aren't listened to
+    locked: bool,
+    running: Option<RunningCommand>,
+    // The running command's pseudo-terminal, and the window size new ones get
+    pty: Option<PtySession>,
+    pty_size: (u16, u16),
+    // Each tab's shell, by tab id, started with its first command
+    shells: HashMap<usize, Shell>,
+    policy: Option<CommandPolicy>,
+    preflight: CheckRegistry,
+    hooks: Hooks,
+    plugins: PluginManager,
+    daemons: DaemonRegistry,
+    filters: FilterPresets,
+    // Pipeline of the last `filter`, for `filter save`
+    last_filter: Option<String>,
+    // A `filter` still running, with its pipeline
+    filtering: Option<(String, Pending<Result<Vec<String>>>)>,
+    // Output lines already handed to plugins and triggers
+    output_seen: usize,
+    triggers: Triggers,
+    // Custom key layers, see core::modes
+    modes: Modes,
+    prompt_profiles: PromptProfiles,
+    // Finds the prompts of the shell running on the pty, see core::prompts
+    detector: Option<Detector>,
+    retention: RetentionRules,
+    postprocessors: PostProcessors,
+    operations: Operations,
+    // The running command's, cancelled by signal
+    command_operation: Option<Operation>,
+    // The running command's, for its problems once it finishes
+    pipeline: Pipeline,
+    // `file:line` diagnostics in the last command's output, for `problems`
+    problems: Vec<Finding>,
+    // A `review` under way, cancellable from `activity`
+    reviewing: Option<Pending<Result<Vec<Finding>>>>,
+    // `ai do` proposals being worked out, with the request when they're the
+    // first round (and open the cards)
+    proposing: Option<(Option<String>, Pending<Result<Proposals>>)>,
+    // A `scriptify --polish` script, as written, while it's being polished
+    polishing: Option<(String, ScriptOptions, Pending<Result<String>>)>,
+    // Output lines (from, to) of commands whose pipeline colorizes; the
+    // running one's goes to usize::MAX
+    colorized: Vec<(usize, usize)>,
+    // Trims the running command's output when a retention rule limits it
+    window: Option<Arc<Mutex<Window>>>,
+    explainer: Option<Explainer>,
+    // Teaching-mode explanations being looked up, each with the output line it goes after
+    explaining: Vec<(usize, Pending<Option<Explanation>>)>,
+    // Commands waiting for the running one, and whether a trigger ran each
+    queue: VecDeque<(String, bool)>,
+    // Project .env variables passed to every command once loaded
+    dotenv: Option<DotEnv>,
+    // The running command was started by a trigger
+    triggered: bool,
+    // Variables set by `set_env` triggers, on top of the .env ones
+    trigger_env: std::collections::BTreeMap<String, String>,
+    // `{{name}}` in commands, see core::variables
+    variables: Variables,
+    // `capture save` and the other NexTerm's clipboard, for this session
+    buffers: CaptureBuffers,
+    // Workflow whose steps are running, and the rollback offered after a failed one
+    transaction: Option<Transaction>,
+    // File an `edit-wait` step is waiting on, for the frontend to open
+    edit_request: Option<PathBuf>,
+    rollback_offer: Option<RollbackPlan>,
+    pending_rollback: Option<RollbackPlan>,
+    // Workflow steps and trigger `run`s wait here for the guard, like typed
+    // commands; then where the ones it has now came from (None: typed)
+    held: VecDeque<(String, Held)>,
+    guarding: Option<Held>,
+    // The NexTerm on the other end of an ssh session, if one said hello; an
+    // offer from it waiting to be shown, then one waiting for the answer
+    peer: Option<Side>,
+    next_offer: u64,
+    // What we offered, by number, for the answer
+    offered: std::collections::BTreeMap<u64, String>,
+    peer_offer: Option<PeerOffer>,
+    pending_peer_offer: Option<PeerOffer>,
+    // Escape sequences for the terminal we run in, written by the frontend
+    host_output: Vec<String>,
+    json_sink: Option<JsonSink>,
+    // The running command was submitted with a `:json` suffix
+    inline_json: bool,
+    // The running command's `:tee` file, and the log every command goes to
+    // while the pane is logging
+    command_tee: Option<SharedTee>,
+    pane_log: Option<SharedTee>,
+    // Where the running command's output goes once it turns out binary, and
+    // the last few commands' that did
+    command_binary: Option<SharedBinary>,
+    binaries: VecDeque<SharedBinary>,
+    next_binary: usize,
+    failures: FailureTracker,
+    // Palette actions and key bindings, see refresh_actions
+    actions: ActionRegistry,
+    live_suggestions: Option<SuggestionScheduler>,
     tx: mpsc::Sender<CommandResult>,
     rx: mpsc::Receiver<CommandResult>,
 }
 
 impl Terminal {
     pub fn new(config: Config) -> Result<Self> {
+        let executor = Arc::new(ShellExecutor::new(&config.shell));
+        Self::with_executor(config, executor)
+    }
+
+    // A terminal whose commands run through `executor`, e.g. a scripted one
+    // for tests and demos
+    pub fn with_executor(config: Config, executor: Arc<dyn Executor>) -> Result<Self> {
         let (tx, rx) = mpsc::channel(100);
-        
-        Ok(Self {
+        let policy = CommandPolicy::from_config(&config.restricted);
+        let explainer = config.teaching_mode.then(Explainer::new);
+        let preflight = CheckRegistry::with_builtins(&config.preflight);
+        let command_history = History::open(&config.storage, &config.history);
+        let hooks = Hooks::new(&config.hooks, &config.shell);
+        let triggers = Triggers::new(&config.triggers, ShellKind::from_shell(&config.shell));
+        let modes = Modes::new(&config.modes);
+        let prompt_profiles = PromptProfiles::new(&config.prompts);
+        let retention = RetentionRules::new(&config.retention);
+        let postprocessors = PostProcessors::new(&config.postprocess);
+        let notifier = Notifier::new(&config.notify, config.bell.min_secs);
+        let operations = Operations::new();
+        let variables = Variables::new(RecentSessions::load(&config.storage).current_variables());
+        // Plugins run arbitrary programs, so restricted mode goes without
+        let loading_plugins = policy.is_none().then(|| {
+            let (config, operations) = (config.plugins.clone(), operations.clone());
+            Pending::spawn("Plugins", move || PluginManager::discover(&config, &operations))
+        });
+
+        let mut terminal = Self {
             config,
+            executor,
             shell_process: None,
             output_buffer: Arc::new(Mutex::new(Vec::new())),
-            command_history: Vec::new(),
+            prompt_marks: Vec::new(),
+            annotations: Vec::new(),
+            command_history,
             autowire_bridge: None,
+            loading_autowire: None,
+            loading_plugins,
+            starting: true,
+            autowire_supervisor: Supervisor::new("Auto-wiring"),
+            autowire_slow: false,
+            autowire_fallbacks: FallbackLog::default(),
+            notices: Vec::new(),
+            rings: Vec::new(),
+            bells: Arc::new(AtomicUsize::new(0)),
+            notifier,
+            visible: Arc::new(AtomicBool::new(true)),
+            tab: 0,
+            command_tab: 0,
+            encodings: HashMap::new(),
+            locked: false,
+            running: None,
+            pty: None,
+            pty_size: (24, 80),
+            shells: HashMap::new(),
+            policy,
+            preflight,
+            hooks,
+            plugins: PluginManager::new(),
+            daemons: DaemonRegistry::new(),
+            filters: FilterPresets::new(),
+            last_filter: None,
+            filtering: None,
+            output_seen: 0,
+            triggers,
+            modes,
+            prompt_profiles,
+            detector: None,
+            retention,
+            postprocessors,
+            operations,
+            command_operation: None,
+            pipeline: Pipeline::default(),
+            problems: Vec::new(),
+            reviewing: None,
+            proposing: None,
+            polishing: None,
+            colorized: Vec::new(),
+            window: None,
+            explainer,
+            explaining: Vec::new(),
+            queue: VecDeque::new(),
+            dotenv: None,
+            triggered: false,
+            trigger_env: std::collections::BTreeMap::new(),
+            variables,
+            buffers: CaptureBuffers::new(),
+            transaction: None,
+            edit_request: None,
+            rollback_offer: None,
+            pending_rollback: None,
+            held: VecDeque::new(),
+            guarding: None,
+            peer: None,
+            next_offer: 1,
+            offered: std::collections::BTreeMap::new(),
+            peer_offer: None,
+            pending_peer_offer: None,
+            // An outer NexTerm answers; any other terminal ignores it
+            host_output: if peer::over_ssh() { vec![peer::sequence(&Message::Hello)] } else { Vec::new() },
+            json_sink: None,
+            inline_json: false,
+            command_tee: None,
+            pane_log: None,
+            command_binary: None,
+            binaries: VecDeque::new(),
+            next_binary: 1,
+            failures: FailureTracker::load(),
+            actions: ActionRegistry::new(),
+            live_suggestions: None,
             tx,
             rx,
-        })
+        };
+        terminal.offer_dotenv();
+        Ok(terminal)
     }
 
     pub fn enable_autowire_integration(&mut self) -> Result<()> {
+        if self.policy.is_some() {
+            info!("Restricted mode: auto-wiring integration disabled");
+            return Ok(());
+        }
+
         info!("Enabling auto-wiring integration...");
-        
-        match AutoWireBridge::new() {
+        // Probing for Python and the handshake take a while; the status bar
+        // says it's loading meanwhile
+        let config = self.config.autowire.clone();
+        let connecting = self.operations.start(Kind::Ai, "Connecting auto-wiring");
+        self.loading_autowire = Some(Pending::spawn("Auto-wiring", move || {
+            let _connecting = connecting;
+            (transport::configure(&config), AutoWireBridge::new())
+        }));
+        Ok(())
+    }
+
+    fn autowire_loaded(&mut self, configured: Result<()>, bridge: Result<AutoWireBridge>) {
+        // A transport that can't be set up stays down, with the reason in the status bar
+        if let Err(e) = configured {
+            self.notify_error(&e.context("Auto-wiring transport not set up"));
+        }
+
+        match bridge {
             Ok(bridge) => {
+                if bridge.is_available() && self.config.ai_enabled {
+                    self.live_suggestions = Some(SuggestionScheduler::new(self.operations.clone()));
+                }
                 self.autowire_bridge = Some(bridge);
+                self.reload_project_notes();
                 info!("✅ Auto-wiring bridge initialized");
-                Ok(())
             }
             Err(e) => {
                 error!("Failed to initialize auto-wiring bridge: {}", e);
-                info!("⚠️  Terminal will run without auto-wiring features");
-                Ok(()) // Don't fail, just warn
+                self.notify(Severity::Warning, "Terminal will run without auto-wiring features");
+            }
+        }
+    }
+
+    // Takes in what startup left to the background as it arrives; plugin and
+    // agent actions join the palette then
+    fn check_startup(&mut self) {
+        if !self.starting {
+            return;
+        }
+        if let Some(Poll::Ready(plugins)) = self.loading_plugins.as_mut().map(Pending::poll) {
+            self.loading_plugins = None;
+            self.plugins = plugins.unwrap_or_default();
+            self.refresh_actions();
+        }
+        if let Some(Poll::Ready(loaded)) = self.loading_autowire.as_mut().map(Pending::poll) {
+            self.loading_autowire = None;
+            if let Some((configured, bridge)) = loaded {
+                self.autowire_loaded(configured, bridge);
+            }
+            self.refresh_actions();
+        }
+        if self.loading_plugins.is_none() && self.loading_autowire.is_none() {
+            self.starting = false;
+            let unbound = self.actions.warnings().len();
+            if unbound > 0 {
+                self.notify(Severity::Warning, format!("{} key bindings not applied; `actions` lists why", unbound));
             }
         }
     }
 
-    pub async fn execute_command(&mut self, command: &str) -> Result<CommandResult> {
-        info!("Executing command: {}", command);
-        self.command_history.push(command.to_string());
+    pub fn is_loading_plugins(&self) -> bool {
+        self.loading_plugins.is_some()
+    }
 
-        let start = std::time::Instant::now();
+    // Waits, up to `timeout`, for what startup left to the background; for
+    // `nexterm status` and tests, which have no main loop to pick it up
+    pub fn wait_for_startup(&mut self, timeout: std::time::Duration) {
+        let deadline = clock::now() + timeout;
+        while self.starting && clock::now() < deadline {
+            self.check_startup();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
 
-        // Execute through shell
-        let output = Command::new(&self.config.shell)
-            .arg("-c")
-            .arg(command)
-            .output()
-            .context("Failed to execute command")?;
+    // Errs in restricted mode: for features that write files or start programs
+    // past the whitelist
+    pub fn check_restricted(&self, feature: &str) -> Result<()> {
+        if self.policy.is_some() {
+            anyhow::bail!(policy::refuse(feature));
+        }
+        Ok(())
+    }
 
-        let duration = start.elapsed();
+    // Refuses `command` under restricted mode, returning the result to report;
+    // `teed` when it had a `:tee` suffix, which would write anywhere
+    fn check_policy(&mut self, command: &str, teed: bool) -> Option<CommandResult> {
+        let policy = self.policy.as_ref()?;
+        let reason = policy.check(command).or_else(|| teed.then(|| policy::refuse(":tee")))?;
+        warn!("Blocked command '{}': {}", command, reason);
+        self.command_history.finish(126);
+        self.add_output(&format!("⛔ {}", reason));
+        self.notify(Severity::Error, reason.clone());
+        Some(CommandResult {
+            command: command.to_string(),
+            output: String::new(),
+            error: reason,
+            exit_code: 126,
+            duration_ms: 0,
+            autowire_processed: false,
+            autowire: None,
+        })
+    }
 
-        let result = CommandResult {
+    // Runs the pre-flight checks; warnings are shown, a blocking finding refuses
+    // the command until it is re-run with the `!` prefix
+    fn run_preflight(&mut self, command: &str) -> Option<CommandResult> {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let findings = self.preflight.run(command, &cwd);
+        let mut blocked = None;
+        for finding in findings {
+            let message = format!("{}: {}", finding.check, finding.message);
+            match finding.level {
+                FindingLevel::Warn => {
+                    self.add_output(&format!("⚠ {}", message));
+                    self.notify(Severity::Warning, message);
+                }
+                FindingLevel::Block => {
+                    self.add_output(&format!("⛔ {} (run `!{}` to skip checks)", message, command));
+                    self.notify(Severity::Error, message.clone());
+                    blocked.get_or_insert(message);
+                }
+            }
+        }
+        let reason = blocked?;
+        self.command_history.finish(126);
+        Some(CommandResult {
             command: command.to_string(),
-            output: String::from_utf8_lossy(&output.stdout).to_string(),
-            error: String::from_utf8_lossy(&output.stderr).to_string(),
-            exit_code: output.status.code().unwrap_or(-1),
-            duration_ms: duration.as_millis() as u64,
+            output: String::new(),
+            error: reason,
+            exit_code: 126,
+            duration_ms: 0,
             autowire_processed: false,
-        };
+            autowire: None,
+        })
+    }
 
-        // Add to output buffer
-        self.add_output(&result.output);
-        if !result.error.is_empty() {
-            self.add_output(&format!("Error: {}", result.error));
+    // Surfaces the fix that worked last time this command failed the same way
+    fn learn_from(&mut self, result: &CommandResult) {
+        // Under a pty stderr is part of the output
+        let error = if result.error.is_empty() { &result.output } else { &result.error };
+        if let Some(hint) = self.failures.record(&result.command, result.exit_code, error) {
+            self.annotate(vec![format!("💡 {}", hint)]);
+            self.notify(Severity::Info, hint);
         }
+    }
 
-        Ok(result)
+    fn explain(&mut self, command: &str) {
+        let Some(explainer) = &self.explainer else {
+            return;
+        };
+        let pending = explainer.explain(command);
+        self.explaining.push((self.output_len(), pending));
     }
 
-    pub async fn execute_command_with_autowire(&mut self, command: &str) -> Result<CommandResult> {
-        info!("Executing command with auto-wiring: {}", command);
+    // Explanations that came in go where their command's output ended
+    fn check_explanations(&mut self) {
+        let mut explaining = std::mem::take(&mut self.explaining);
+        explaining.retain_mut(|(line, pending)| match pending.poll() {
+            Poll::Ready(explanation) => {
+                if let Some(explanation) = explanation.flatten() {
+                    let at = self.annotations.partition_point(|a| a.line <= *line);
+                    self.annotations.insert(at, Annotation { line: *line, text: explanation.to_lines() });
+                }
+                false
+            }
+            Poll::Pending => true,
+        });
+        self.explaining = explaining;
+    }
+
+    // Queues `command` behind the one currently running, or starts it right away.
+    // Execution happens on a background task so input stays responsive.
+    pub async fn submit_command(&mut self, command: &str) {
+        self.submit(command, false).await;
+    }
 
-        // Try to process through auto-wiring first
+    // `triggered` when a trigger's `run` asked for it
+    async fn submit(&mut self, command: &str, triggered: bool) {
+        if self.running.is_some() {
+            self.queue.push_back((command.to_string(), triggered));
+            self.notify(Severity::Info, format!("Queued: {}", command));
+            return;
+        }
+        self.start_command(command, triggered.then_some(Held::Trigger)).await;
+    }
+
+    // Re-reads .nexterm/notes.md and hands it to the AI as project context
+    pub fn reload_project_notes(&mut self) {
+        let notes = ProjectNotes::discover().map(|n| n.content);
         if let Some(bridge) = &mut self.autowire_bridge {
-            match bridge.process_command(command).await {
-                Ok(Some(autowire_result)) => {
-                    info!("Command processed by auto-wiring system");
-                    
-                    // Execute the auto-wiring suggested command
-                    let mut result = self.execute_command(&autowire_result.processed_command).await?;
-                    result.autowire_processed = true;
-                    
-                    // Add auto-wiring metadata to output
-                    if !autowire_result.suggestions.is_empty() {
-                        self.add_output(&format!("\n💡 Auto-Wiring Suggestions: {:?}", 
-                                                 autowire_result.suggestions));
+            bridge.set_project_notes(notes.clone());
+        }
+        if let Some(scheduler) = &mut self.live_suggestions {
+            scheduler.set_project_notes(notes);
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    // Suggest-as-you-type: tells the scheduler what is in the input line
+    pub fn input_changed(&mut self, input: &str) {
+        let input = self.redact(input);
+        if let Some(scheduler) = &mut self.live_suggestions {
+            scheduler.input_changed(&input);
+        }
+    }
+
+    // Suggests `dotenv load` when the project has .env files that aren't loaded
+    fn offer_dotenv(&mut self) {
+        if let Some(files) = DotEnv::discover() {
+            let names = DotEnv { files, ..DotEnv::default() }.describe_files();
+            self.notify(Severity::Info, format!("Found {} — run `dotenv load` to use it", names));
+        }
+    }
+
+    // Loads the project's .env files (.env.local overriding .env) for all
+    // following commands
+    pub fn load_dotenv(&mut self) -> Result<&DotEnv> {
+        let files = DotEnv::discover().context("No .env file in this project")?;
+        let dotenv = self.dotenv.insert(DotEnv::load(files)?);
+        Ok(dotenv)
+    }
+
+    pub fn unload_dotenv(&mut self) -> bool {
+        self.dotenv.take().is_some()
+    }
+
+    pub fn dotenv(&self) -> Option<&DotEnv> {
+        self.dotenv.as_ref()
+    }
+
+    pub fn variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    pub fn buffers(&self) -> &CaptureBuffers {
+        &self.buffers
+    }
+
+    pub fn buffers_mut(&mut self) -> &mut CaptureBuffers {
+        &mut self.buffers
+    }
+
+    // `set name=value`, kept with the session
+    pub fn set_variable(&mut self, assignment: &str) -> Result<(String, String)> {
+        let assigned = self.variables.set(assignment)?;
+        self.save_variables()?;
+        Ok(assigned)
+    }
+
+    pub fn unset_variable(&mut self, name: &str) -> Result<bool> {
+        if !self.variables.unset(name) {
+            return Ok(false);
+        }
+        self.save_variables()?;
+        Ok(true)
+    }
+
+    fn save_variables(&self) -> Result<()> {
+        RecentSessions::load(&self.config.storage).save_variables(self.variables.values())
+    }
+
+    // Environment added to every command: .env values, then trigger-set ones
+    fn command_env(&self) -> std::collections::BTreeMap<String, String> {
+        let mut env = self.dotenv.as_ref().map(|d| d.vars.clone()).unwrap_or_default();
+        env.extend(self.trigger_env.clone());
+        env
+    }
+
+    fn masked_env(&self) -> std::collections::BTreeMap<String, String> {
+        self.dotenv.as_ref().map(|d| d.masked()).unwrap_or_default()
+    }
+
+    fn redact(&self, text: &str) -> String {
+        match &self.dotenv {
+            Some(dotenv) => dotenv.redact(text),
+            None => text.to_string(),
+        }
+    }
+
+    // A command as it may leave the machine (notification sinks): .env values
+    // and inline secrets masked, and nothing of one kept out of history
+    fn shareable(&self, command: &str) -> String {
+        if !self.retention.for_command(command).persist {
+            return "(command not kept)".to_string();
+        }
+        report::redact_line(&self.redact(command))
+    }
+
+    // `ai summarize`: recent history as a digest; commands a retention rule
+    // keeps out of history are left out, the rest are redacted
+    pub fn summarize_history(&self, since: std::time::Duration) -> Digest {
+        let to = clock::unix_secs();
+        let from = to.saturating_sub(since.as_secs());
+        let items: Vec<HistoryItem> = self
+            .command_history
+            .since(from)
+            .into_iter()
+            .filter(|item| self.retention.for_command(&item.command).persist)
+            .collect();
+        Digest::build(&items, from, to, |command| self.redact(command))
+    }
+
+    // `scriptify`: the latest `count` commands as its checklist, redacted and
+    // without those a retention rule keeps out of history
+    pub fn scriptify_checklist(&self, count: usize) -> Vec<(String, bool)> {
+        let items: Vec<HistoryItem> = self
+            .command_history
+            .last(count)
+            .into_iter()
+            .filter(|item| self.retention.for_command(&item.command).persist)
+            .map(|item| HistoryItem { command: self.redact(&item.command), ..item })
+            .collect();
+        scriptify::checklist(&items)
+    }
+
+    // Sends `script` off to be polished; see take_polished
+    pub fn polish_script(&mut self, script: &str, options: &ScriptOptions) -> Result<()> {
+        if self.polishing.is_some() {
+            anyhow::bail!("A script is already being polished (see `activity`)");
+        }
+        let polish = self.connected_bridge()?.polish_script(script);
+        let pending = self.operations.run(Kind::Ai, "Polishing the script", |_| polish);
+        self.polishing = Some((script.to_string(), options.clone(), pending));
+        Ok(())
+    }
+
+    // The script to write once polishing is done, as written if it failed;
+    // nothing if it was cancelled
+    pub fn take_polished(&mut self) -> Option<(String, ScriptOptions)> {
+        let Poll::Ready(polished) = self.polishing.as_mut()?.2.poll() else {
+            return None;
+        };
+        let (script, options, _) = self.polishing.take()?;
+        match polished? {
+            Ok(polished) => Some((polished, options)),
+            Err(e) => {
+                self.notify(Severity::Warning, format!("Not polished: {:#}", e));
+                Some((script, options))
+            }
+        }
+    }
+
+    pub fn live_suggestions(&self) -> &[String] {
+        self.live_suggestions.as_ref().map(|s| s.current()).unwrap_or(&[])
+    }
+
+    pub fn queued_commands(&self) -> usize {
+        self.queue.len()
+    }
+
+    // `from` is where the guard got it (None: typed). Returns false when the
+    // command was refused or could not be spawned
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
+    async fn start_command(&mut self, line: &str, from: Option<Held>) -> bool {
+        let triggered = matches!(from, Some(Held::Trigger | Held::Rewrite { triggered: true, .. }));
+        self.triggered = triggered;
+        self.command_tab = self.tab;
+        self.visible.store(!self.locked, Ordering::Relaxed);
+        let (command, inline_json) = json_output::strip_inline(line);
+        let (command, tee_path) = tee::strip_suffix(command);
+        let (typed, skip_preflight) = preflight::strip_skip(command);
+        let (command, autowire) = match from {
+            Some(Held::Rewrite { .. }) => (typed.to_string(), None),
+            _ => self.autowire_preprocess(typed).await,
+        };
+        // The guard saw the command as typed; a rewrite that changes something
+        // goes back to it, the rest of the line kept
+        if command != typed && environments::is_mutating(&command) {
+            let step = matches!(from, Some(Held::Step));
+            self.notify(Severity::Info, format!("Auto-wiring rewrote `{}` to `{}`", typed, command));
+            self.held.push_back((line.replacen(typed, &command, 1), Held::Rewrite { step, triggered }));
+            return true;
+        }
+        info!("Starting command: {}", self.redact(&command));
+        self.command_history.add(command.clone(), &self.masked_env());
+        let retention = self.retention.for_command(&command);
+        if !retention.persist {
+            self.command_history.skip_persist();
+        }
+        self.mark_prompt(&command);
+        if let Some(suggestions) = autowire.as_ref().map(|a| a.suggestions.clone()) {
+            self.suggest(&suggestions);
+        }
+        self.inline_json = inline_json;
+
+        if let Some(blocked) = self.check_policy(&command, tee_path.is_some()) {
+            self.report_json(&blocked);
+            return false;
+        }
+        if !skip_preflight {
+            if let Some(blocked) = self.run_preflight(&command) {
+                self.report_json(&blocked);
+                return false;
+            }
+        }
+        self.command_tee = match tee_path.map(|path| TeeFile::open(&path, &self.config.tee)) {
+            Some(Ok(file)) => Some(file.shared()),
+            Some(Err(e)) => {
+                self.add_output(&format!("Error: {}", error::describe(&e)));
+                return false;
+            }
+            None => None,
+        };
+        if let Some(log) = &self.pane_log {
+            Tee::new([log.clone()]).write(format!("❯ {}\n", command).as_bytes());
+        }
+        let mut tee = Tee::new(self.command_tee.iter().chain(&self.pane_log).cloned());
+        let binary = BinaryOutput::new(self.next_binary, &command).shared();
+        self.command_binary = Some(binary.clone());
+        let pipeline = self.postprocessors.for_command(&command, self.dotenv.as_ref());
+        self.pipeline = pipeline.clone();
+        if pipeline.colorizes() {
+            self.colorized.push((self.output_len(), usize::MAX));
+        }
+
+        self.hooks.pre_command(&command).await;
+        self.plugins.command_pre(&command);
+        self.triggers.reset();
+
+        self.window = retention.output.limit().map(|keep| {
+            let start = self.output_buffer.lock().map(|b| b.len()).unwrap_or(0);
+            Arc::new(Mutex::new(Window::new(start, keep)))
+        });
+
+        // Plugin commands answer in place of the shell
+        let parsed = CommandParser::new().parse(&command).ok();
+        if let Some(reply) = parsed.and_then(|parsed| self.plugins.run_command(&parsed)) {
+            self.running = Some(RunningCommand::new(&command, None));
+            let (buffer, tx) = (self.output_buffer.clone(), self.tx.clone());
+            let start = clock::now();
+
+            tokio::spawn(async move {
+                let reply = tokio::task::spawn_blocking(reply).await;
+                let (output, exit_code) = match reply.unwrap_or_else(|_| Err(anyhow::anyhow!("panicked"))) {
+                    Ok(output) => (output, 0),
+                    Err(e) => (format!("Error: {:#}", e), 1),
+                };
+                if let Ok(mut buffer) = buffer.lock() {
+                    buffer.extend(output.lines().map(str::to_string));
+                }
+                tee.write(format!("{}\n", output).as_bytes());
+                let result = CommandResult {
+                    command,
+                    output,
+                    error: String::new(),
+                    exit_code,
+                    duration_ms: clock::since(start).as_millis() as u64,
+                    autowire_processed: autowire.is_some(),
+                    autowire,
+                };
+                let _ = tx.send(result).await;
+            });
+            return true;
+        }
+
+        if self.config.pty && self.executor.allows_pty() {
+            // Remote tabs roam; history and results keep the command as typed
+            let spawned = roaming::wrap(&command, &self.config.roaming);
+            let sinks = Sinks {
+                buffer: self.output_buffer.clone(),
+                window: self.window.clone(),
+                tee: tee.clone(),
+                encoding: self.encoding(),
+                binary: binary.clone(),
+                visible: self.visible.clone(),
+                pipeline: pipeline.clone(),
+            };
+            match self.start_on_pty(spawned.as_deref().unwrap_or(&command), sinks) {
+                Ok((session, pid, output)) => {
+                    self.running = Some(RunningCommand::new(&command, Some(pid)));
+                    self.track_command(&command, Some(pid));
+                    self.pty = Some(session);
+                    self.detector = self.prompt_profiles.detector(&command);
+                    let tx = self.tx.clone();
+                    let start = clock::now();
+
+                    tokio::spawn(async move {
+                        let (exit_code, output) =
+                            tokio::task::spawn_blocking(move || output.wait()).await.unwrap_or((-1, String::new()));
+                        let result = CommandResult {
+                            command,
+                            output,
+                            error: String::new(),
+                            exit_code,
+                            duration_ms: clock::since(start).as_millis() as u64,
+                            autowire_processed: autowire.is_some(),
+                            autowire,
+                        };
+                        let _ = tx.send(result).await;
+                    });
+                    return true;
+                }
+                // e.g. no /dev/ptmx in a container
+                Err(e) => warn!("Falling back to pipes: {}", error::describe(&e)),
+            }
+        }
+
+        let execution = match self.executor.spawn(&command, &self.command_env()) {
+            Ok(execution) => execution,
+            Err(e) => {
+                let message = error::describe(&e);
+                error!("{}", message);
+                self.add_output(&format!("Error: {}", message));
+                return false;
+            }
+        };
+
+        self.running = Some(RunningCommand::new(&command, execution.pid));
+        self.track_command(&command, execution.pid);
+        let encoding = self.encoding();
+        let stdout = tokio::spawn(stream_lines(
+            execution.stdout,
+            self.output_buffer.clone(),
+            self.window.clone(),
+            tee.clone(),
+            encoding,
+            binary.clone(),
+            self.bells.clone(),
+            pipeline.clone(),
+        ));
+        let stderr = tokio::spawn(stream_lines(
+            execution.stderr,
+            self.output_buffer.clone(),
+            self.window.clone(),
+            tee,
+            encoding,
+            binary,
+            self.bells.clone(),
+            pipeline,
+        ));
+        let tx = self.tx.clone();
+        let start = clock::now();
+
+        tokio::spawn(async move {
+            let exit_code = execution.exit.await;
+            let output = stdout.await.unwrap_or_default();
+            let error = stderr.await.unwrap_or_default();
+            let result = CommandResult {
+                command,
+                output,
+                error,
+                exit_code,
+                duration_ms: clock::since(start).as_millis() as u64,
+                autowire_processed: autowire.is_some(),
+                autowire,
+            };
+            let _ = tx.send(result).await;
+        });
+        true
+    }
+
+    // Runs `name` from the workflows directory step by step; if a step fails,
+    // the rollbacks of the steps before it are offered for confirmation
+    pub async fn run_workflow(&mut self, name: &str) -> Result<()> {
+        if self.running.is_some() || self.transaction.is_some() {
+            anyhow::bail!("Wait for the running command to finish before starting a workflow");
+        }
+        let workflow = Workflow::load(name)?;
+        let operation = self.operations.start(Kind::Workflow, format!("Workflow {}", name));
+        self.transaction = Some(Transaction::new(workflow, operation));
+        self.advance_transaction().await;
+        Ok(())
+    }
+
+    async fn advance_transaction(&mut self) {
+        let command = loop {
+            let Some(transaction) = &mut self.transaction else {
+                return;
+            };
+            let next = transaction.advance();
+            let progress = format!(
+                "{} [{}/{}]",
+                transaction.workflow.name,
+                transaction.current_step(),
+                transaction.workflow.steps.len()
+            );
+            match next {
+                Some(Next::Run(command)) => {
+                    self.add_output(&format!("▶ {}", progress));
+                    break command;
+                }
+                Some(Next::UpToDate(label)) => self.add_output(&format!("⏭ {} {}: up to date", progress, label)),
+                None => {
+                    let name = transaction.workflow.name.clone();
+                    self.transaction = None;
+                    self.notify(Severity::Success, format!("Workflow '{}' completed", name));
+                    return;
+                }
+            }
+        };
+        let command = self.variables.expand(&command);
+        // The step goes on in finish_edit, once the editor exits
+        if let Some(file) = editor::requested(&command) {
+            self.edit_request = Some(file);
+            return;
+        }
+        self.held.push_back((command, Held::Step));
+    }
+
+    // The next held command for the frontend's guard; handed out once, and
+    // not while the guard still has the previous one
+    pub fn take_held(&mut self) -> Option<String> {
+        if self.guarding.is_some() {
+            return None;
+        }
+        let (command, held) = self.held.pop_front()?;
+        self.guarding = Some(held);
+        Some(command)
+    }
+
+    // The guard's answer for what it was given: the commands to run, or None
+    // when they were cancelled, which fails a workflow step
+    pub async fn release(&mut self, commands: Option<Vec<String>>) {
+        let held = self.guarding.take();
+        let step = matches!(held, Some(Held::Step | Held::Rewrite { step: true, .. }));
+        let Some(commands) = commands else {
+            self.notify(Severity::Info, "Cancelled");
+            if step {
+                self.fail_transaction();
+            }
+            if step || matches!(held, Some(Held::Rewrite { .. })) {
+                self.start_queued().await;
+            }
+            return;
+        };
+        for command in &commands {
+            match held {
+                Some(Held::Step | Held::Rewrite { .. }) => {
+                    if !self.start_command(command, held).await && step {
+                        self.fail_transaction();
                     }
-                    
-                    return Ok(result);
                 }
-                Ok(None) => {
-                    debug!("No auto-wiring processing for command");
+                Some(Held::Trigger) => self.submit(command, true).await,
+                None => self.submit_command(command).await,
+            }
+        }
+    }
+
+    // A workflow step or a rewritten command is waiting for the guard; queued
+    // commands wait for it
+    fn waiting_on_guard(&self) -> bool {
+        let waits = |held: &Held| matches!(held, Held::Step | Held::Rewrite { .. });
+        self.guarding.as_ref().is_some_and(waits) || self.held.iter().any(|(_, held)| waits(held))
+    }
+
+    // A file a workflow step asked to edit; handed out once
+    pub fn take_edit_request(&mut self) -> Option<PathBuf> {
+        self.edit_request.take()
+    }
+
+    // The workflow waiting on an `edit-wait` step goes on if the editor
+    // exited cleanly
+    pub async fn finish_edit(&mut self, succeeded: bool) {
+        if self.transaction.is_none() {
+            return;
+        }
+        if succeeded {
+            if let Some(transaction) = &mut self.transaction {
+                transaction.step_succeeded();
+            }
+            self.advance_transaction().await;
+        } else {
+            self.fail_transaction();
+        }
+    }
+
+    fn fail_transaction(&mut self) {
+        let Some(transaction) = self.transaction.take() else {
+            return;
+        };
+        let plan = transaction.into_rollback_plan();
+        let message = format!("Workflow '{}' failed at step {}", plan.workflow, plan.failed_step);
+        if plan.commands.is_empty() {
+            self.notify(Severity::Error, format!("{}; nothing to roll back", message));
+        } else {
+            self.notify(Severity::Error, message);
+            self.rollback_offer = Some(plan);
+        }
+    }
+
+    // A rollback waiting for the user's confirmation; handed out once
+    pub fn take_rollback_offer(&mut self) -> Option<RollbackPlan> {
+        let plan = self.rollback_offer.take()?;
+        self.pending_rollback = Some(plan.clone());
+        Some(plan)
+    }
+
+    // The confirmed rollback commands, to run in order through the guard
+    pub fn resolve_rollback(&mut self, confirmed: bool) -> Vec<String> {
+        let Some(plan) = self.pending_rollback.take() else {
+            return Vec::new();
+        };
+        if !confirmed {
+            self.notify(Severity::Info, format!("Rollback of '{}' skipped", plan.workflow));
+            return Vec::new();
+        }
+        plan.commands
+    }
+
+    // Rewrites `command` through the auto-wiring bridge when it has something to say
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
+    async fn autowire_preprocess(&mut self, command: &str) -> (String, Option<AutoWireResult>) {
+        let (result, fallback) = self.autowire_result(command).await;
+        self.autowire_fallbacks.record(command, result.as_ref(), fallback);
+        let Some(autowire_result) = result else {
+            return (command.to_string(), None);
+        };
+        if !autowire_result.suggestions.is_empty() {
+            self.notify(
+                Severity::Info,
+                format!("💡 {}", autowire_result.suggestions.join(", ")),
+            );
+        }
+        (autowire_result.processed_command.clone(), Some(autowire_result))
+    }
+
+    // The bridge's result for `command`, and why it fell back if it did
+    async fn autowire_result(&mut self, command: &str) -> (Option<AutoWireResult>, Option<Fallback>) {
+        if self.policy.is_some() {
+            return (None, Some(Fallback::Restricted));
+        }
+        let Some(bridge) = self.autowire_bridge.as_mut() else {
+            return (None, Some(Fallback::Off));
+        };
+        if self.autowire_supervisor.is_disabled() {
+            return (None, Some(Fallback::Disabled));
+        }
+
+        // .env values never leave for the AI; put them back in what it returns
+        let redacted = match &self.dotenv {
+            Some(dotenv) => dotenv.redact(command),
+            None => command.to_string(),
+        };
+        let processed = self.autowire_supervisor.run_async(bridge.process_command(&redacted)).await;
+        let fallback = bridge.take_fallback();
+        let mut autowire_result = match processed {
+            None => return (None, Some(Fallback::Panicked)),
+            Some(Ok(Some(result))) => result,
+            Some(Ok(None)) => return (None, fallback),
+            Some(Err(e)) => {
+                let fallback = Fallback::from_error(&e);
+                self.notify_error(&e.context("Auto-wiring error"));
+                return (None, Some(fallback));
+            }
+        };
+        if let Some(dotenv) = &self.dotenv {
+            autowire_result.processed_command = dotenv.restore(&autowire_result.processed_command);
+        }
+        let confidence = autowire_result.confidence;
+        if autowire_result.processed_command != command && confidence < self.config.autowire.min_confidence {
+            autowire_result.processed_command = command.to_string();
+            return (Some(autowire_result), Some(Fallback::LowConfidence(confidence)));
+        }
+        (Some(autowire_result), fallback)
+    }
+
+    // Sends `result` to the global JSON sink and, for `:json` commands, the output pane
+    fn report_json(&mut self, result: &CommandResult) {
+        if let Some(sink) = &mut self.json_sink {
+            if let Err(e) = sink.emit(result) {
+                warn!("Failed to write JSON result: {}", e);
+            }
+        }
+        if std::mem::take(&mut self.inline_json) {
+            match serde_json::to_string(result) {
+                Ok(json) => self.add_output(&json),
+                Err(e) => warn!("Failed to serialize result: {}", e),
+            }
+        }
+    }
+
+    // Sends `value` where command results go; false if they go nowhere
+    pub fn emit_json(&mut self, value: &impl Serialize) -> Result<bool> {
+        match &mut self.json_sink {
+            Some(sink) => sink.emit(value).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    pub fn set_json_sink(&mut self, sink: JsonSink) {
+        self.json_sink = Some(sink);
+    }
+
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
+    async fn finish_command(&mut self, result: CommandResult) {
+        debug!("Command finished: {} (exit {})", result.command, result.exit_code);
+        self.running = None;
+        self.command_operation = None;
+        self.pty = None;
+        self.detector = None;
+        self.follow_shell();
+        if self.peer == Some(Side::Inner) {
+            self.peer = None;
+        }
+        self.command_tee = None;
+        if let Some(binary) = self.command_binary.take() {
+            if binary.lock().map(|b| b.total() > 0).unwrap_or(false) {
+                self.binaries.push_back(binary);
+                self.next_binary += 1;
+                if self.binaries.len() > binary::KEPT {
+                    self.binaries.pop_front();
+                }
+            }
+        }
+        // Once more for the rows that were still live
+        if let Some(window) = self.window.take() {
+            if let Ok(mut window) = window.lock() {
+                if let Ok(mut buffer) = self.output_buffer.lock() {
+                    let mut end = buffer.len();
+                    window.trim(&mut buffer, &mut end);
                 }
-                Err(e) => {
-                    error!("Auto-wiring processing error: {}", e);
+                self.output_seen = window.shift(self.output_seen);
+            }
+        }
+        self.command_history.finish(result.exit_code);
+        self.rings.extend(self.config.bell.finished(&result.command, result.exit_code, result.duration_ms));
+        self.notifier.finished(&self.shareable(&result.command), result.exit_code, result.duration_ms);
+        let end = self.output_len();
+        if let Some((_, to)) = self.colorized.last_mut().filter(|(_, to)| *to == usize::MAX) {
+            *to = end;
+        }
+        self.problems = self.pipeline.problems(&format!("{}{}", result.output, result.error));
+        if !self.problems.is_empty() {
+            let count = self.problems.len();
+            let noun = if count == 1 { "problem" } else { "problems" };
+            self.notify(Severity::Info, format!("{} {} in the output; `problems` lists them", count, noun));
+        }
+        if result.exit_code != 0 {
+            self.notify(
+                Severity::Warning,
+                format!("{} exited with {}", result.command, result.exit_code),
+            );
+        }
+        self.explain(&result.command);
+        self.learn_from(&result);
+        self.report_json(&result);
+        self.hooks.post_command(&result);
+        self.plugins.command_post(&result);
+
+        if self.transaction.is_some() {
+            match result.exit_code {
+                0 => {
+                    if let Some(transaction) = &mut self.transaction {
+                        transaction.step_succeeded();
+                    }
+                    self.advance_transaction().await;
                 }
+                _ => self.fail_transaction(),
             }
         }
 
-        // Fallback to normal execution
-        self.execute_command(command).await
+        self.start_queued().await;
+    }
+
+    // Queued commands wait until the workflow's steps are done
+    async fn start_queued(&mut self) {
+        while self.running.is_none() && !self.waiting_on_guard() {
+            let Some((next, triggered)) = self.queue.pop_front() else {
+                break;
+            };
+            self.start_command(&next, triggered.then_some(Held::Trigger)).await;
+        }
     }
 
     pub fn add_output(&self, text: &str) {
@@ -136,34 +1268,644 @@ impl Terminal {
         }
     }
 
+    fn mark_prompt(&mut self, command: &str) {
+        let line = self.output_buffer.lock().map(|b| b.len()).unwrap_or(0);
+        self.prompt_marks.push(PromptMark {
+            line,
+            command: command.to_string(),
+        });
+        self.add_output(&format!("❯ {}", command));
+    }
+
+    pub fn prompt_marks(&self) -> &[PromptMark] {
+        &self.prompt_marks
+    }
+
+    // Anchored after the output so far
+    fn annotate(&mut self, text: Vec<String>) {
+        let line = self.output_buffer.lock().map(|b| b.len()).unwrap_or(0);
+        self.annotations.push(Annotation { line, text });
+    }
+
+    // AI suggestions, shown below the output so far
+    pub fn suggest(&mut self, suggestions: &[String]) {
+        if !suggestions.is_empty() {
+            self.annotate(vec![format!("💡 {}", suggestions.join(", "))]);
+        }
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    // Output of the command whose block contains `line`, without its prompt line
+    pub fn command_output_at(&self, line: usize) -> Option<String> {
+        let index = self.prompt_marks.iter().rposition(|m| m.line <= line)?;
+        let start = self.prompt_marks[index].line + 1;
+        let output = self.get_output();
+        let end = self.prompt_marks
+            .get(index + 1)
+            .map(|m| m.line)
+            .unwrap_or(output.len());
+        Some(output.get(start..end)?.join("\n"))
+    }
+
+    pub fn output_len(&self) -> usize {
+        self.output_buffer.lock().map(|b| b.len()).unwrap_or_default()
+    }
+
+    // The output as it is, without the copy `get_output` makes; the buffer
+    // stays locked while `f` runs
+    pub fn with_output<R>(&self, f: impl FnOnce(&[String]) -> R) -> R {
+        match self.output_buffer.lock() {
+            Ok(buffer) => f(&buffer),
+            Err(_) => f(&[]),
+        }
+    }
+
     pub fn get_output(&self) -> Vec<String> {
         self.output_buffer.lock()
             .map(|b| b.clone())
             .unwrap_or_default()
     }
 
-    pub fn clear_output(&self) {
-        if let Ok(mut buffer) = self.output_buffer.lock() {
-            buffer.clear();
+    // Whether the pane draws output line `line` in its level's color
+    pub fn is_colorized(&self, line: usize) -> bool {
+        self.colorized.iter().any(|(from, to)| (*from..*to).contains(&line))
+    }
+
+    pub fn notify(&mut self, severity: Severity, message: impl Into<String>) {
+        self.notices.push(Notice {
+            severity,
+            message: message.into(),
+        });
+    }
+
+    // Logs `error` and shows it as a toast, with its code and hint if it has one
+    pub fn notify_error(&mut self, error: &anyhow::Error) {
+        let message = error::describe(error);
+        error!("{}", message);
+        self.notify(Severity::Error, message);
+    }
+
+    fn check_bells(&mut self) {
+        let rang = self.bells.swap(0, Ordering::SeqCst) + self.pty.as_ref().map_or(0, |pty| pty.take_bells());
+        if let (true, Some(running)) = (rang > 0, &self.running) {
+            self.rings.extend(self.config.bell.bell(&running.command));
+            self.notifier.bell(&self.shareable(&running.command));
+        }
+    }
+
+    // Messages only count from an ssh session's pty, and not while the
+    // session is locked
+    fn check_peer(&mut self) {
+        let bodies = self.pty.as_ref().map(|pty| pty.take_peer_messages()).unwrap_or_default();
+        let from_ssh = self.running.as_ref().is_some_and(|running| peer::is_ssh(&running.command));
+        if self.locked || !from_ssh {
+            return;
+        }
+        for message in bodies.iter().filter_map(|body| peer::from_osc(body)) {
+            self.receive_peer(Side::Inner, message);
+        }
+    }
+
+    // Prompts of a shell running on the pty: those it marked with OSC 133 and,
+    // with a regex profile, unseen lines that match. The prompt line already
+    // is in the output, so only the mark is added.
+    fn check_prompts(&mut self, start: usize, lines: &[String]) {
+        let Some(pty) = &mut self.pty else {
+            return;
+        };
+        let marked = pty.take_prompts();
+        let mut found: Vec<(usize, String)> = Vec::new();
+        if let Ok(buffer) = self.output_buffer.lock() {
+            // Below the last mark, which is at least the command's own prompt
+            let floor = self.prompt_marks.last().map_or(0, |m| m.line + 1);
+            for prompt in marked {
+                let line = self.pipeline.line(prompt.line);
+                let below = found.last().map_or(floor, |(i, _)| i + 1);
+                if let Some(i) = (below..buffer.len()).find(|&i| buffer[i] == line) {
+                    found.push((i, prompt.command));
+                }
+            }
+            if let Some(detector) = &mut self.detector {
+                let commands = lines.iter().enumerate().filter(|(i, _)| start + i >= floor);
+                found.extend(commands.filter_map(|(i, line)| Some((start + i, detector.command(line)?))));
+                // Only the command's own lines: earlier output can end like a prompt too
+                let own = buffer.get(floor..).unwrap_or_default();
+                let cursor_line = own.iter().rev().find(|line| !line.trim().is_empty());
+                if let Some(hook) = cursor_line.and_then(|line| detector.hook(line)) {
+                    debug!("Typing the prompt hook into the shell");
+                    pty.send_paste(hook);
+                    pty.send_key(KeyEvent::from(KeyCode::Enter));
+                }
+            }
+        }
+        found.sort_by_key(|(line, _)| *line);
+        found.dedup_by_key(|(line, _)| *line);
+        for (line, command) in found.into_iter().filter(|(_, command)| !command.is_empty()) {
+            self.prompt_marks.push(PromptMark { line, command });
+        }
+    }
+
+    // A paste that came from an outer NexTerm, not the user; true when handled
+    pub fn receive_peer_paste(&mut self, text: &str) -> bool {
+        let Some(message) = peer::from_paste(text) else {
+            return false;
+        };
+        self.receive_peer(Side::Outer, message);
+        true
+    }
+
+    fn receive_peer(&mut self, side: Side, message: Message) {
+        match message {
+            Message::Hello => {
+                self.peer = Some(side);
+                let message = if side == Side::Inner {
+                    let _ = self.send_peer(&Message::Hello);
+                    "NexTerm is running in this ssh session"
+                } else {
+                    "Connected to the NexTerm this session runs in"
+                };
+                self.notify(Severity::Info, format!("{}; `peer` sends it clipboard text and files", message));
+            }
+            Message::Bye if self.peer == Some(side) => self.peer = None,
+            Message::Bye => {}
+            Message::Offer { id, item } => {
+                if self.peer_offer.is_some() || self.pending_peer_offer.is_some() {
+                    let _ = self.send_peer(&Message::Reply { id, accepted: false });
+                } else {
+                    self.peer_offer = Some(PeerOffer { id, item });
+                }
+            }
+            Message::Reply { id, accepted } => {
+                let what = self.offered.remove(&id).unwrap_or_else(|| format!("transfer {}", id));
+                let (severity, answer) =
+                    if accepted { (Severity::Success, "accepted") } else { (Severity::Warning, "declined") };
+                self.notify(severity, format!("The other NexTerm {} the {}", answer, what));
+            }
+        }
+    }
+
+    fn send_peer(&mut self, message: &Message) -> Result<()> {
+        match (self.peer, &mut self.pty) {
+            (Some(Side::Inner), Some(pty)) if pty.bracketed_paste() => pty.send_paste(&peer::paste(message)),
+            (Some(Side::Inner), _) => anyhow::bail!("The NexTerm in this session isn't taking input right now"),
+            (Some(Side::Outer), _) => self.host_output.push(peer::sequence(message)),
+            (None, _) => anyhow::bail!("No NexTerm on the other end; start one over ssh from this one"),
+        }
+        Ok(())
+    }
+
+    // Sends `item` for the other end to accept
+    pub fn offer_to_peer(&mut self, item: Item) -> Result<()> {
+        let id = self.next_offer;
+        let what = item.describe();
+        self.send_peer(&Message::Offer { id, item })?;
+        self.offered.insert(id, what);
+        self.next_offer += 1;
+        Ok(())
+    }
+
+    pub fn peer(&self) -> Option<Side> {
+        self.peer
+    }
+
+    // An offer for the user to accept; handed out once
+    pub fn take_peer_offer(&mut self) -> Option<PeerOffer> {
+        let offer = self.peer_offer.take()?;
+        self.pending_peer_offer = Some(offer.clone());
+        Some(offer)
+    }
+
+    // The offer the user just answered
+    pub fn take_answered_peer_offer(&mut self) -> Option<PeerOffer> {
+        self.pending_peer_offer.take()
+    }
+
+    // Tells the sender how its offer went
+    pub fn reply_peer(&mut self, id: u64, accepted: bool) {
+        if let Err(e) = self.send_peer(&Message::Reply { id, accepted }) {
+            self.notify_error(&e);
+        }
+    }
+
+    // On the way out, so the outer NexTerm stops pasting into whatever's next
+    pub fn leave_peer(&mut self) {
+        if self.peer == Some(Side::Outer) {
+            self.host_output.push(peer::sequence(&Message::Bye));
+        }
+    }
+
+    pub fn take_host_output(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.host_output)
+    }
+
+    // `notify test`: how many sinks a test message went to
+    pub fn test_notifications(&self) -> usize {
+        self.notifier.test()
+    }
+
+    pub fn take_rings(&mut self) -> Vec<Ring> {
+        std::mem::take(&mut self.rings)
+    }
+
+    pub fn take_notices(&mut self) -> Vec<Notice> {
+        std::mem::take(&mut self.notices)
+    }
+
+    pub fn is_teaching_mode(&self) -> bool {
+        self.explainer.is_some()
+    }
+
+    pub fn set_teaching_mode(&mut self, enabled: bool) {
+        if enabled != self.is_teaching_mode() {
+            self.explainer = enabled.then(Explainer::new);
+        }
+    }
+
+    pub fn roam_mode(&self) -> RoamMode {
+        self.config.roaming.mode
+    }
+
+    // For this session; `[roaming]` in the config sets the default
+    pub fn set_roam_mode(&mut self, mode: RoamMode) {
+        self.config.roaming.mode = mode;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible.load(Ordering::Relaxed)
+    }
+
+    // The UI says which tab is shown and whether it's behind the lock screen;
+    // the running command is on screen if neither hides it
+    pub fn set_view(&mut self, tab: usize, locked: bool) {
+        let switched = tab != self.tab;
+        self.tab = tab;
+        self.visible.store(!locked && tab == self.command_tab, Ordering::Relaxed);
+        if switched {
+            self.follow_shell();
+        }
+    }
+
+    pub fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+    }
+
+    // The shown tab's
+    pub fn encoding(&self) -> Encoding {
+        self.encodings.get(&self.tab).copied().unwrap_or(self.config.encoding)
+    }
+
+    // For the shown tab, from its next command on; `encoding` in the config
+    // sets the default
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encodings.insert(self.tab, encoding);
+    }
+
+    // `log start`: the output of every command from the next one on also goes
+    // to `path`
+    pub fn start_log(&mut self, path: &Path) -> Result<()> {
+        self.check_restricted("Logging output to a file")?;
+        self.pane_log = Some(TeeFile::open(path, &self.config.tee)?.shared());
+        Ok(())
+    }
+
+    pub fn is_logging(&self) -> bool {
+        self.pane_log.is_some()
+    }
+
+    // The file that was being written, if any
+    pub fn stop_log(&mut self) -> Option<PathBuf> {
+        let log = self.pane_log.take()?;
+        let path = log.lock().ok()?.path().to_path_buf();
+        Some(path)
+    }
+
+    // Where output is going right now, for the pane title: the running
+    // command's `:tee` file, else the pane's log
+    pub fn recording(&self) -> Option<PathBuf> {
+        let file = self.command_tee.as_ref().or(self.pane_log.as_ref())?;
+        let path = file.lock().ok()?.path().to_path_buf();
+        Some(path)
+    }
+
+    // `binary view N`: a recent command's binary output, the latest without an id
+    pub fn binary_output(&self, id: Option<usize>) -> Option<SharedBinary> {
+        let mut kept = self.binaries.iter().rev();
+        match id {
+            Some(id) => kept.find(|b| b.lock().map(|b| b.id() == id).unwrap_or(false)),
+            None => kept.next(),
+        }
+        .cloned()
+    }
+
+    pub fn running_command(&self) -> Option<&RunningCommand> {
+        self.running.as_ref()
+    }
+
+    // Output lines added since the last call, and the index of the first. While
+    // a command runs its last line may still be rewritten (progress bars), so it
+    // waits until the next one
+    fn unseen_output(&mut self) -> (usize, Vec<String>) {
+        // Held throughout so no trim lands between the shift and the read
+        let window = self.window.clone();
+        let mut window = window.as_ref().and_then(|w| w.lock().ok());
+        let Ok(buffer) = self.output_buffer.lock() else {
+            return (0, Vec::new());
+        };
+        if let Some(window) = &mut window {
+            self.output_seen = window.shift(self.output_seen);
+        }
+        // Cleared in the meantime
+        self.output_seen = self.output_seen.min(buffer.len());
+        let start = self.output_seen;
+        let end = if self.running.is_some() { buffer.len().saturating_sub(1) } else { buffer.len() };
+        if end <= start {
+            return (start, Vec::new());
+        }
+        self.output_seen = end;
+        (start, buffer[start..end].to_vec())
+    }
+
+    // Matches new output against the configured triggers; the echoed prompt
+    // lines are skipped so a command never triggers on its own text, and the
+    // output of commands a trigger ran so triggers can't feed themselves
+    async fn run_triggers(&mut self, start: usize, lines: &[String]) {
+        if self.triggered {
+            return;
+        }
+        let output: Vec<String> = lines
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.prompt_marks.iter().any(|m| m.line == start + i))
+            .map(|(_, line)| line.clone())
+            .collect();
+        for action in self.triggers.scan(&output) {
+            debug!("Trigger fired: {:?}", action);
+            match action {
+                TriggerAction::Notify(message) => self.notify(Severity::Info, message),
+                TriggerAction::OpenUrl(url) => match browser::open(&url) {
+                    Ok(()) => self.notify(Severity::Info, format!("Opened {}", url)),
+                    Err(e) => self.notify(Severity::Error, format!("Could not open {}: {:#}", url, e)),
+                },
+                TriggerAction::SetEnv { name, value } => {
+                    self.notify(Severity::Info, format!("{}={} set for the next commands", name, value));
+                    self.trigger_env.insert(name, value);
+                }
+                TriggerAction::Run(command) => {
+                    let command = self.variables.expand(&command);
+                    self.held.push_back((command, Held::Trigger));
+                }
+            }
+        }
+    }
+
+    // `daemonize`: runs `command` detached, under the same policy and
+    // environment as other commands
+    pub fn daemonize(&mut self, command: &str) -> Result<DaemonInfo> {
+        if let Some(reason) = self.policy.as_ref().and_then(|p| p.check(command)) {
+            anyhow::bail!(reason);
+        }
+        info!("Daemonizing: {}", self.redact(command));
+        self.daemons.start(&self.config.shell, command, &self.command_env())
+    }
+
+    pub fn daemons(&self) -> &DaemonRegistry {
+        &self.daemons
+    }
+
+    // `filter`: the whole output buffer piped through `pipeline`, under the
+    // same policy and environment as other commands, in the background; see
+    // take_filtered
+    pub fn filter(&mut self, pipeline: &str) -> Result<()> {
+        if let Some(reason) = self.policy.as_ref().and_then(|p| p.check(pipeline)) {
+            anyhow::bail!(reason);
+        }
+        if self.filtering.is_some() {
+            anyhow::bail!("A filter is already running (see `activity`)");
+        }
+        self.last_filter = Some(pipeline.to_string());
+        let mut input = self.get_output().join("\n");
+        input.push('\n');
+        let (shell, env, command) = (self.config.shell.clone(), self.command_env(), pipeline.to_string());
+        let pending = self.operations.run(Kind::Command, "Filtering the output", |_| async move {
+            filters::run(&shell, &command, &env, input).await
+        });
+        self.filtering = Some((pipeline.to_string(), pending));
+        Ok(())
+    }
+
+    // The filter's pipeline and output once it's done; nothing if it was cancelled
+    pub fn take_filtered(&mut self) -> Option<(String, Result<Vec<String>>)> {
+        let Poll::Ready(lines) = self.filtering.as_mut()?.1.poll() else {
+            return None;
+        };
+        let (pipeline, _) = self.filtering.take()?;
+        Some((pipeline, lines?))
+    }
+
+    pub fn modes(&self) -> &Modes {
+        &self.modes
+    }
+
+    pub fn modes_mut(&mut self) -> &mut Modes {
+        &mut self.modes
+    }
+
+    pub fn filters(&self) -> &FilterPresets {
+        &self.filters
+    }
+
+    pub fn last_filter(&self) -> Option<&str> {
+        self.last_filter.as_deref()
+    }
+
+    pub fn plugins(&self) -> Vec<PluginInfo> {
+        self.plugins.list()
+    }
+
+    // Loads or unloads a plugin and remembers the choice in the config file
+    pub fn set_plugin_enabled(&mut self, name: &str, enabled: bool) -> Result<()> {
+        if self.is_loading_plugins() {
+            anyhow::bail!("Plugins are still loading");
+        }
+        if !self.plugins.set_enabled(name, enabled) {
+            anyhow::bail!("No plugin named {}", name);
+        }
+        let mut config = Config::load()?;
+        config.plugins.disabled.retain(|d| d != name);
+        if !enabled {
+            config.plugins.disabled.push(name.to_string());
+        }
+        config.save()?;
+        self.config.plugins = config.plugins;
+        self.refresh_actions();
+        Ok(())
+    }
+
+    // Collects the actions NexTerm, workflows, plugins and agents contribute,
+    // then applies keybindings.toml
+    pub fn refresh_actions(&mut self) {
+        let mut registry = ActionRegistry::new();
+        let contributed = actions::builtin()
+            .into_iter()
+            .chain(Workflow::actions())
+            .chain(self.plugins.actions())
+            .chain(self.modes.actions())
+            .chain(self.autowire_bridge.iter().flat_map(AutoWireBridge::agent_actions));
+        for action in contributed {
+            registry.register(action);
+        }
+        if let Err(e) = registry.load_keybindings(&Config::dir().join("keybindings.toml")) {
+            self.notify_error(&e);
+        }
+        for warning in registry.warnings() {
+            warn!("Key binding not applied: {}", warning);
+        }
+        self.actions = registry;
+    }
+
+    pub fn actions(&self) -> &ActionRegistry {
+        &self.actions
+    }
+
+    // Size of the output pane in cells, the window size commands see
+    pub fn set_pty_size(&mut self, rows: u16, cols: u16) {
+        if self.pty_size == (rows, cols) || rows == 0 || cols == 0 {
+            return;
+        }
+        self.pty_size = (rows, cols);
+        if let Some(pty) = &mut self.pty {
+            pty.resize(rows, cols);
+        }
+    }
+
+    // Screen of the running full-screen program, drawn instead of the output
+    pub fn live_screen(&self) -> Option<ScreenView> {
+        self.pty.as_ref()?.alternate_view()
+    }
+
+    // Keys and pastes go to the running command rather than the input line
+    pub fn is_pty_attached(&self) -> bool {
+        self.pty.as_ref().is_some_and(|pty| pty.is_attached())
+    }
+
+    // Ctrl+]: attach to or detach from the running command; false when
+    // nothing runs on a pty
+    pub fn toggle_pty_attached(&mut self) -> bool {
+        match &mut self.pty {
+            Some(pty) => {
+                pty.toggle_attached();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn send_key(&mut self, key: KeyEvent) {
+        if let Some(pty) = &mut self.pty {
+            pty.send_key(key);
+        }
+    }
+
+    pub fn send_paste(&mut self, text: &str) {
+        if let Some(pty) = &mut self.pty {
+            pty.send_paste(text);
         }
     }
 
     pub fn get_history(&self) -> &[String] {
+        self.command_history.get_all()
+    }
+
+    pub fn history(&self) -> &History {
         &self.command_history
     }
 
-    pub fn update_output(&mut self) -> Result<()> {
-        // Check for new output from background processes
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
+    pub async fn update_output(&mut self) -> Result<()> {
+        self.check_startup();
+        self.check_explanations();
+        // Before a finishing command's pty goes
+        self.check_bells();
+        self.check_peer();
+        // Output streams straight into the buffer; this only collects completions
         while let Ok(result) = self.rx.try_recv() {
-            self.add_output(&result.output);
+            self.finish_command(result).await;
+        }
+        for failure in self.notifier.take_failures() {
+            self.notify(Severity::Warning, failure);
+        }
+        for outcome in self.operations.take_outcomes() {
+            match outcome {
+                Ok(message) => self.notify(Severity::Success, message),
+                Err(error) => self.notify(Severity::Error, error),
+            }
+        }
+        for fault in supervisor::take_faults() {
+            self.notify(Severity::Error, fault);
+        }
+        let breach = self.autowire_bridge.as_ref().and_then(|bridge| bridge.slo_breach());
+        if breach.is_some() != self.autowire_slow {
+            self.autowire_slow = breach.is_some();
+            if let Some(breach) = breach {
+                self.notify(
+                    Severity::Warning,
+                    format!("Auto-wiring is slow (p95 {}ms), using offline suggestions for now", breach.p95.as_millis()),
+                );
+            }
+        }
+        if let Some(running) = &mut self.running {
+            running.sample_cpu();
+        }
+        if let Some(pty) = &mut self.pty {
+            pty.update();
+        }
+        // Caught up on once the pane is shown again
+        let (start, lines) = if self.is_visible() { self.unseen_output() } else { (0, Vec::new()) };
+        self.check_prompts(start, &lines);
+        if !lines.is_empty() {
+            self.plugins.output(&lines);
+            self.run_triggers(start, &lines).await;
+        }
+        if let Some(scheduler) = &mut self.live_suggestions {
+            scheduler.poll();
         }
         Ok(())
     }
 
     pub fn get_autowire_status(&self) -> String {
-        match &self.autowire_bridge {
-            Some(bridge) => format!("✅ Connected - {} services", bridge.service_count()),
-            None => "⚠️  Not connected".to_string(),
+        if self.loading_autowire.is_some() {
+            return "⏳ Loading".to_string();
+        }
+        let Some(bridge) = &self.autowire_bridge else {
+            return "⚠️  Not connected".to_string();
+        };
+        if let Some(breach) = bridge.slo_breach() {
+            return format!("🐢 Slow (p50 {}ms, p95 {}ms) - offline suggestions", breach.p50.as_millis(), breach.p95.as_millis());
+        }
+        match bridge.health() {
+            Health::Ready { latency: Some(latency), .. } => {
+                format!("✅ Connected - {} services ({}ms)", bridge.service_count(), latency.as_millis())
+            }
+            Health::Ready { .. } => format!("✅ Connected - {} services", bridge.service_count()),
+            Health::Remote { endpoint, latency } => {
+                let latency = latency.map(|l| format!(" ({}ms)", l.as_millis())).unwrap_or_default();
+                format!("✅ Connected to {} - {} services{}", endpoint, bridge.service_count(), latency)
+            }
+            Health::Starting => "⏳ Starting worker".to_string(),
+            Health::Restarting { attempt } => format!("♻️  Restarting worker ({})", attempt),
+            Health::Suspended => "💤 Worker suspended while idle".to_string(),
+            // Native services keep working without the worker
+            Health::Down(reason) => format!("⚠️  Worker down: {} - {} native services", reason, bridge.service_count()),
+        }
+    }
+
+    // Stops the worker while the user is idle; its next request restarts it
+    pub fn suspend_autowire(&self) {
+        if let Some(bridge) = &self.autowire_bridge {
+            bridge.suspend();
         }
     }
 
@@ -173,6 +1915,217 @@ impl Terminal {
             None => vec!["Auto-wiring not available".to_string()],
         }
     }
+
+    // `route <command>`: which native service the command would go to, and why
+    pub fn explain_route(&self, command: &str) -> Result<Vec<String>> {
+        Ok(self.connected_bridge()?.route(command)?.to_lines())
+    }
+
+    pub fn operations(&self) -> &Operations {
+        &self.operations
+    }
+
+    // On the tab's shell (see core::shell), or on a pty of its own with a shell
+    // that can't keep one or with persistent_shell off; the pid is the one
+    // `cancel` signals
+    fn start_on_pty(&mut self, command: &str, sinks: Sinks) -> Result<(PtySession, u32, PtyOutput)> {
+        let env = self.command_env();
+        if !self.config.persistent_shell || !Shell::supports(&self.config.shell) {
+            return PtySession::spawn(&self.config.shell, command, env, self.pty_size, sinks);
+        }
+        let shell = match self.shells.entry(self.tab) {
+            Entry::Occupied(entry) if entry.get().is_alive() => entry.into_mut(),
+            // Gone after `exit`; a new one starts where NexTerm is
+            Entry::Occupied(mut entry) => {
+                entry.insert(Shell::spawn(&self.config.shell, self.pty_size)?);
+                entry.into_mut()
+            }
+            Entry::Vacant(entry) => entry.insert(Shell::spawn(&self.config.shell, self.pty_size)?),
+        };
+        let (session, output) = shell.run(command, &env, self.pty_size, sinks)?;
+        Ok((session, shell.pid(), output))
+    }
+
+    // NexTerm's working directory follows the shown tab's shell, for history,
+    // the git branch, completion and the impact preview
+    fn follow_shell(&self) {
+        let Some(cwd) = self.shells.get(&self.tab).and_then(Shell::cwd) else {
+            return;
+        };
+        if std::env::current_dir().ok().as_ref() != Some(&cwd) {
+            if let Err(e) = std::env::set_current_dir(&cwd) {
+                warn!("Could not follow the shell to {}: {}", cwd.display(), e);
+            }
+        }
+    }
+
+    // The closed tab's shell is hung up on
+    pub fn close_shell(&mut self, tab: usize) {
+        self.shells.remove(&tab);
+    }
+
+    fn track_command(&mut self, command: &str, pid: Option<u32>) {
+        let operation = self.operations.start(Kind::Command, command);
+        if let Some(pid) = pid {
+            operation.cancel_with(Cancel::Signal(pid));
+        }
+        self.command_operation = Some(operation);
+    }
+
+    pub fn problems(&self) -> &[Finding] {
+        &self.problems
+    }
+
+    // `review`: the staged diff, redacted, sent off for findings; see take_review
+    pub fn review_staged(&mut self) -> Result<()> {
+        if self.reviewing.is_some() {
+            anyhow::bail!("A review is already running (see `activity`)");
+        }
+        let bridge = self.connected_bridge()?;
+        let diff = self.redact(&review::staged_diff()?);
+        let tokens = self.config.autowire.review_tokens;
+        let reviewing = self.operations.run(Kind::Ai, "Reviewing the staged changes", |progress| {
+            bridge.review(&diff, tokens, progress)
+        });
+        self.reviewing = Some(reviewing);
+        Ok(())
+    }
+
+    // The review's findings once they're in; nothing if it was cancelled
+    pub fn take_review(&mut self) -> Option<Result<Vec<Finding>>> {
+        let Poll::Ready(review) = self.reviewing.as_mut()?.poll() else {
+            return None;
+        };
+        self.reviewing = None;
+        review
+    }
+
+    // `ai do`: asks for the next actions for `request`, with the request and
+    // what approved actions read redacted; see take_proposals. No `results`
+    // is the first round
+    pub fn propose_actions(&mut self, request: &str, results: &[serde_json::Value]) -> Result<()> {
+        if self.proposing.is_some() {
+            anyhow::bail!("Still waiting for the last proposals (see `activity`)");
+        }
+        let bridge = self.connected_bridge()?;
+        let redacted: Vec<serde_json::Value> = results
+            .iter()
+            .map(|result| serde_json::from_str(&self.redact(&result.to_string())).unwrap_or_else(|_| result.clone()))
+            .collect();
+        let proposing = bridge.propose_actions(&self.redact(request), &std::env::current_dir()?, &redacted);
+        let pending = self.operations.run(Kind::Ai, "Proposing actions", |_| proposing);
+        self.proposing = Some((results.is_empty().then(|| request.to_string()), pending));
+        Ok(())
+    }
+
+    // The proposals once they're in, with the request if they're the first
+    // round; nothing if they were cancelled
+    pub fn take_proposals(&mut self) -> Option<(Option<String>, Result<Proposals>)> {
+        let Poll::Ready(proposals) = self.proposing.as_mut()?.1.poll() else {
+            return None;
+        };
+        let (request, _) = self.proposing.take()?;
+        Some((request, proposals?))
+    }
+
+    pub fn autowire_fallbacks(&self) -> &FallbackLog {
+        &self.autowire_fallbacks
+    }
+
+    pub fn get_autowire_logs(&self) -> Vec<String> {
+        self.autowire_bridge.as_ref().map(AutoWireBridge::worker_logs).unwrap_or_default()
+    }
+
+    pub fn describe_autowire_service(&self, name: &str) -> Result<String> {
+        self.connected_bridge()?.service_details(name)
+    }
+
+    pub fn invoke_autowire_service(&self, name: &str) -> Result<String> {
+        info!("Invoking auto-wire service: {}", name);
+        self.connected_bridge()?.invoke_service(name)
+    }
+
+    pub fn restart_autowire_service(&self, name: &str) -> Result<String> {
+        info!("Restarting auto-wire service: {}", name);
+        self.connected_bridge()?.restart_service(name)
+    }
+
+    fn connected_bridge(&self) -> Result<&AutoWireBridge> {
+        let bridge = self.autowire_bridge.as_ref();
+        Ok(bridge.ok_or_else(|| NexTermError::BridgeUnavailable("not enabled in this terminal".to_string()))?)
+    }
+}
+
+// Appends each line from `reader` to `tee` as it arrives, and through
+// `pipeline` to the output buffer, trimmed to `window` if there is one, and
+// returns everything read (that it keeps) once the stream closes
+#[allow(clippy::too_many_arguments)]
+async fn stream_lines<R: AsyncRead + Unpin>(
+    reader: Option<R>,
+    buffer: Arc<Mutex<Vec<String>>>,
+    window: Option<Arc<Mutex<Window>>>,
+    mut tee: Tee,
+    encoding: Encoding,
+    binary: SharedBinary,
+    bells: Arc<AtomicUsize>,
+    pipeline: Pipeline,
+) -> String {
+    let Some(reader) = reader else {
+        return String::new();
+    };
+
+    let mut collected = VecDeque::new();
+    let mut reader = BufReader::new(reader);
+    let mut bytes = Vec::new();
+    let mut sniffer = Sniffer::new(encoding);
+    loop {
+        let start = bytes.len();
+        // In pieces, so binary output without newlines isn't read in whole
+        let more = matches!((&mut reader).take(binary::PIECE).read_until(b'\n', &mut bytes).await, Ok(n) if n > 0);
+        if more && sniffer.is_binary(&bytes[start..]) {
+            tee.write(&bytes);
+            if let Ok(mut binary) = binary.lock() {
+                binary.write(&bytes, &buffer);
+            }
+            bytes.clear();
+            continue;
+        }
+        // The rest of a long line is still to come
+        if more && !bytes.ends_with(b"\n") {
+            continue;
+        }
+        if bytes.is_empty() {
+            break;
+        }
+        let (line, rang) = postprocess::clean(bytes.strip_suffix(b"\n").unwrap_or(&bytes), encoding);
+        bytes.clear();
+        if rang > 0 {
+            bells.fetch_add(rang, Ordering::SeqCst);
+        }
+        tee.write(format!("{}\n", line).as_bytes());
+        let line = pipeline.line(line);
+        let mut window = window.as_ref().and_then(|w| w.lock().ok());
+        if let Ok(mut buffer) = buffer.lock() {
+            if let Some(window) = &mut window {
+                window.check_cleared(buffer.len());
+            }
+            buffer.push(line.clone());
+            if let Some(window) = &mut window {
+                let mut end = buffer.len();
+                window.trim(&mut buffer, &mut end);
+            }
+        }
+        collected.push_back(line);
+        if let Some(window) = &window {
+            while collected.len() > window.keep() {
+                collected.pop_front();
+            }
+        }
+        if !more {
+            break;
+        }
+    }
+    collected.into_iter().map(|line| format!("{}\n", line)).collect()
 }
 
 impl Drop for Terminal {
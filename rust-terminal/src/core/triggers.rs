@@ -0,0 +1,196 @@
+// Output triggers: regexes matched against output lines as they stream in,
+// each firing an action. Configured as
+//
+//   [[triggers]]
+//   pattern = 'Listening on port (\d+)'
+//   action = { open_url = "http://localhost:$1" }
+//
+// Actions are notify, open_url, set_env { name, value } and run; `$1` or
+// `${name}` in them expand to the pattern's capture groups. Captures come from
+// untrusted output, so in `run` each one is quoted for the shell.
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::core::variables;
+use crate::utils::shell_escape::ShellKind;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerConfig {
+    pub pattern: String,
+    pub action: TriggerAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerAction {
+    Notify(String),
+    OpenUrl(String),
+    // For the commands that follow
+    SetEnv { name: String, value: String },
+    Run(String),
+}
+
+impl TriggerAction {
+    fn expand(&self, captures: &Captures, shell: ShellKind) -> Self {
+        let plain = |template: &str| substitute(template, captures, str::to_string);
+        match self {
+            Self::Notify(message) => Self::Notify(plain(message)),
+            Self::OpenUrl(url) => Self::OpenUrl(plain(url)),
+            Self::SetEnv { name, value } => Self::SetEnv {
+                name: name.clone(),
+                value: plain(value),
+            },
+            Self::Run(command) => Self::Run(substitute(command, captures, |text| shell.quote(text))),
+        }
+    }
+}
+
+// `template` with `$1`, `$name` and `${name}` replaced by what `escape` makes
+// of the capture (empty when it didn't match) and `$$` by `$`, as in
+// `Captures::expand`
+fn substitute(template: &str, captures: &Captures, escape: impl Fn(&str) -> String) -> String {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(at) = rest.find('$') {
+        expanded.push_str(&rest[..at]);
+        rest = &rest[at + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        let (name, after) = match rest.strip_prefix('{').and_then(|inner| inner.split_once('}')) {
+            Some((name, after)) => (name, after),
+            None => {
+                let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        if name.is_empty() {
+            expanded.push('$');
+            continue;
+        }
+        let group = match name.parse::<usize>() {
+            Ok(index) => captures.get(index),
+            Err(_) => captures.name(name),
+        };
+        expanded.push_str(&escape(group.map_or("", |m| m.as_str())));
+        rest = after;
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+struct Trigger {
+    pattern: Regex,
+    action: TriggerAction,
+    // Fires once per command, so a chatty log line doesn't repeat the action
+    fired: bool,
+}
+
+pub struct Triggers {
+    triggers: Vec<Trigger>,
+    // How `run` captures are quoted
+    shell: ShellKind,
+}
+
+impl Triggers {
+    pub fn new(configs: &[TriggerConfig], shell: ShellKind) -> Self {
+        let triggers = configs
+            .iter()
+            .filter_map(|config| {
+                if let TriggerAction::SetEnv { name, .. } = &config.action {
+                    if !variables::is_name(name) {
+                        warn!("Ignoring trigger {:?}: {:?} is not a variable name", config.pattern, name);
+                        return None;
+                    }
+                }
+                match Regex::new(&config.pattern) {
+                    Ok(pattern) => Some(Trigger {
+                        pattern,
+                        action: config.action.clone(),
+                        fired: false,
+                    }),
+                    Err(e) => {
+                        warn!("Ignoring trigger {:?}: {}", config.pattern, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self { triggers, shell }
+    }
+
+    // A new command started; every trigger may fire again
+    pub fn reset(&mut self) {
+        for trigger in &mut self.triggers {
+            trigger.fired = false;
+        }
+    }
+
+    // Actions fired by `lines`, with their captures filled in
+    pub fn scan(&mut self, lines: &[String]) -> Vec<TriggerAction> {
+        let mut fired = Vec::new();
+        for line in lines {
+            for trigger in self.triggers.iter_mut().filter(|t| !t.fired) {
+                if let Some(captures) = trigger.pattern.captures(line) {
+                    trigger.fired = true;
+                    fired.push(trigger.action.expand(&captures, self.shell));
+                }
+            }
+        }
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fires_once_with_captures() {
+        let config: Vec<TriggerConfig> = vec![
+            TriggerConfig {
+                pattern: r"Listening on port (?P<port>\d+)".into(),
+                action: TriggerAction::OpenUrl("http://localhost:${port}/".into()),
+            },
+            TriggerConfig {
+                pattern: r"Listening on port (\d+)".into(),
+                action: TriggerAction::SetEnv { name: "PORT".into(), value: "$1".into() },
+            },
+            TriggerConfig { pattern: "(".into(), action: TriggerAction::Run("true".into()) },
+            TriggerConfig {
+                pattern: "port".into(),
+                action: TriggerAction::SetEnv { name: "A;B".into(), value: "x".into() },
+            },
+        ];
+        let mut triggers = Triggers::new(&config, ShellKind::Posix);
+        let lines = vec!["compiling".to_string(), "Listening on port 3000".to_string()];
+
+        assert_eq!(
+            triggers.scan(&lines),
+            vec![
+                TriggerAction::OpenUrl("http://localhost:3000/".into()),
+                TriggerAction::SetEnv { name: "PORT".into(), value: "3000".into() },
+            ]
+        );
+        assert!(triggers.scan(&lines).is_empty());
+        triggers.reset();
+        assert_eq!(triggers.scan(&lines).len(), 2);
+    }
+
+    #[test]
+    fn quotes_captures_in_commands() {
+        let config = vec![TriggerConfig {
+            pattern: r"Listening on port (?P<port>.+)".into(),
+            action: TriggerAction::Run("curl localhost:$1/${port} $$HOME $9".into()),
+        }];
+        let mut triggers = Triggers::new(&config, ShellKind::Posix);
+        let fired = triggers.scan(&["Listening on port 80; rm -rf ~".to_string()]);
+        assert_eq!(
+            fired,
+            vec![TriggerAction::Run("curl localhost:'80; rm -rf ~'/'80; rm -rf ~' $HOME ''".into())]
+        );
+    }
+}
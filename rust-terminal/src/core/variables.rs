@@ -0,0 +1,91 @@
+// Session variables: `set region=eu-west-1`, then `{{region}}` anywhere in a
+// command is replaced before it runs. Lighter than exporting env vars: they
+// don't reach the environment of every command, and they are kept with the
+// session (the directory NexTerm was started in) for the next time.
+// Unknown names are left alone, so Go templates (`{{.State}}`) and Helm's
+// `{{ .Values.x }}` pass through untouched.
+use anyhow::Result;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Default)]
+pub struct Variables {
+    values: BTreeMap<String, String>,
+}
+
+impl Variables {
+    pub fn new(values: BTreeMap<String, String>) -> Self {
+        Self { values }
+    }
+
+    // `name=value`; the value may hold spaces and `=`
+    pub fn set(&mut self, assignment: &str) -> Result<(String, String)> {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Usage: set <name>=<value>"))?;
+        let name = name.trim();
+        if !is_name(name) {
+            anyhow::bail!("Not a variable name: {} (letters, digits, _ and -)", name);
+        }
+        let value = value.trim().to_string();
+        self.values.insert(name.to_string(), value.clone());
+        Ok((name.to_string(), value))
+    }
+
+    pub fn unset(&mut self, name: &str) -> bool {
+        self.values.remove(name).is_some()
+    }
+
+    pub fn values(&self) -> &BTreeMap<String, String> {
+        &self.values
+    }
+
+    // `command` with every `{{name}}` (or `{{ name }}`) of a set variable replaced
+    pub fn expand(&self, command: &str) -> String {
+        if self.values.is_empty() || !command.contains("{{") {
+            return command.to_string();
+        }
+        static REFERENCE: OnceLock<Regex> = OnceLock::new();
+        let reference = REFERENCE
+            .get_or_init(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_-]*)\s*\}\}").expect("valid variable pattern"));
+        reference
+            .replace_all(command, |c: &regex::Captures| match self.values.get(&c[1]) {
+                Some(value) => value.clone(),
+                None => c[0].to_string(),
+            })
+            .into_owned()
+    }
+}
+
+// Letters, digits, `_` and `-`, not starting with a digit or `-`
+pub fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_set_variables_only() {
+        let mut variables = Variables::default();
+        assert_eq!(variables.set("region=eu-west-1").unwrap(), ("region".into(), "eu-west-1".into()));
+        variables.set("query = a=b c").unwrap();
+        assert!(variables.set("2fast=x").is_err());
+        assert!(variables.set("region").is_err());
+
+        assert_eq!(
+            variables.expand("aws --region {{region}} logs '{{ query }}'"),
+            "aws --region eu-west-1 logs 'a=b c'"
+        );
+        let untouched = "docker inspect -f '{{.State}}' {{other}}";
+        assert_eq!(variables.expand(untouched), untouched);
+
+        assert!(variables.unset("region"));
+        assert!(!variables.unset("region"));
+        assert_eq!(variables.expand("echo {{region}}"), "echo {{region}}");
+    }
+}
@@ -0,0 +1,890 @@
+// Minimal VT100/xterm screen emulator for output read from a PTY: cursor
+// movement, erasing, scroll regions, SGR colors and the alternate screen.
+// Lines scrolling off the top of the main screen become plain scrollback.
+use unicode_width::UnicodeWidthChar;
+
+// Longest OSC body kept, enough for a core::peer file
+const MAX_OSC: usize = 512 * 1024;
+// The private OSC core::peer messages come in; defined here so the fuzz
+// target can build this file on its own
+pub const PEER_OSC: &str = "7770";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VtColor {
+    #[default]
+    Default,
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CellStyle {
+    pub fg: VtColor,
+    pub bg: VtColor,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub style: CellStyle,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self { ch: ' ', style: CellStyle::default() }
+    }
+}
+
+// Copy of the visible grid for drawing
+#[derive(Debug, Clone)]
+pub struct ScreenView {
+    pub rows: Vec<Vec<Cell>>,
+    pub cursor: Option<(usize, usize)>,
+}
+
+enum State {
+    Ground,
+    Escape,
+    // ESC ( and friends: the next byte picks a charset, which is ignored
+    Charset,
+    Csi { params: String, private: bool },
+    // OSC, DCS and the like; the body is kept for OSC only
+    Osc(Option<String>),
+    OscEscape(Option<String>),
+}
+
+pub struct Screen {
+    rows: usize,
+    cols: usize,
+    grid: Vec<Vec<Cell>>,
+    // The main screen's grid while the alternate one is shown
+    saved_main: Option<Vec<Vec<Cell>>>,
+    // Per row of `grid`: the row was filled and its line continues on the next
+    wrapped: Vec<bool>,
+    // The main screen's flags while the alternate one is shown
+    saved_wrapped: Vec<bool>,
+    // Start of a line whose first rows have scrolled off but whose rest hasn't
+    partial: String,
+    cursor: (usize, usize),
+    saved_cursor: (usize, usize),
+    // The last column was written; the next printable character wraps first
+    wrap_pending: bool,
+    cursor_visible: bool,
+    app_cursor_keys: bool,
+    bracketed_paste: bool,
+    style: CellStyle,
+    scroll_top: usize,
+    scroll_bottom: usize,
+    scrolled: Vec<String>,
+    state: State,
+    utf8: Vec<u8>,
+    // BELs since the last call to take_bells
+    bells: usize,
+    // Bodies of OSC 7770 (core::peer) since the last call to take_peer_messages
+    peer_messages: Vec<String>,
+    // Row of the prompt the shell is showing, and where its input starts once
+    // known, from OSC 133 A and B (see core::prompts)
+    prompt_at: Option<(usize, Option<usize>)>,
+    // Prompts whose command ran (OSC 133 C) since the last call to take_prompts
+    prompts: Vec<SemanticPrompt>,
+}
+
+// A shell prompt line in the output with the command typed at it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticPrompt {
+    pub line: String,
+    pub command: String,
+}
+
+impl Screen {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        Self {
+            rows,
+            cols,
+            grid: vec![vec![Cell::default(); cols]; rows],
+            saved_main: None,
+            wrapped: vec![false; rows],
+            saved_wrapped: Vec::new(),
+            partial: String::new(),
+            cursor: (0, 0),
+            saved_cursor: (0, 0),
+            wrap_pending: false,
+            cursor_visible: true,
+            app_cursor_keys: false,
+            bracketed_paste: false,
+            style: CellStyle::default(),
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            scrolled: Vec::new(),
+            state: State::Ground,
+            utf8: Vec::new(),
+            bells: 0,
+            peer_messages: Vec::new(),
+            prompt_at: None,
+            prompts: Vec::new(),
+        }
+    }
+
+    // The main screen's lines are re-wrapped to the new width instead of being
+    // cut off; the alternate screen is redrawn by its program anyway
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        self.prompt_at = None;
+        let alternate = self.saved_main.is_some();
+        let (mut main, mut wrapped, mut cursor) = match self.saved_main.take() {
+            Some(main) => (main, std::mem::take(&mut self.saved_wrapped), self.saved_cursor),
+            None => (std::mem::take(&mut self.grid), std::mem::take(&mut self.wrapped), self.cursor),
+        };
+        if cols != self.cols {
+            (main, wrapped, cursor) = rewrap(main, &wrapped, cursor, cols);
+        }
+        for (row, continues) in fit(&mut main, &mut wrapped, &mut cursor, rows, cols) {
+            self.push_scrolled(&row, continues);
+        }
+        if alternate {
+            self.saved_main = Some(main);
+            self.saved_wrapped = wrapped;
+            self.saved_cursor = cursor;
+            let mut alternate_cursor = self.cursor;
+            let mut alternate_wrapped = std::mem::take(&mut self.wrapped);
+            fit(&mut self.grid, &mut alternate_wrapped, &mut alternate_cursor, rows, cols);
+            self.wrapped = alternate_wrapped;
+            self.cursor = alternate_cursor;
+        } else {
+            self.grid = main;
+            self.wrapped = wrapped;
+            self.cursor = cursor;
+        }
+        self.rows = rows;
+        self.cols = cols;
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.cursor = (self.cursor.0.min(rows - 1), self.cursor.1.min(cols - 1));
+        self.wrap_pending = false;
+    }
+
+    pub fn is_alternate(&self) -> bool {
+        self.saved_main.is_some()
+    }
+
+    pub fn app_cursor_keys(&self) -> bool {
+        self.app_cursor_keys
+    }
+
+    pub fn bracketed_paste(&self) -> bool {
+        self.bracketed_paste
+    }
+
+    pub fn take_bells(&mut self) -> usize {
+        std::mem::take(&mut self.bells)
+    }
+
+    pub fn take_peer_messages(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.peer_messages)
+    }
+
+    pub fn take_prompts(&mut self) -> Vec<SemanticPrompt> {
+        std::mem::take(&mut self.prompts)
+    }
+
+    // Lines that scrolled off the main screen since the last call
+    pub fn take_scrolled(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.scrolled)
+    }
+
+    // Lines of the main screen in use, up to the cursor or the last non-blank
+    // row; rows joined by wrapping come back as one line
+    pub fn live_lines(&self) -> Vec<String> {
+        let (grid, wrapped) = match &self.saved_main {
+            Some(main) => (main, &self.saved_wrapped),
+            None => (&self.grid, &self.wrapped),
+        };
+        let last_text = grid.iter().rposition(|row| row.iter().any(|c| c.ch != ' '));
+        let cursor_row = if self.saved_main.is_none() && self.cursor.1 > 0 { Some(self.cursor.0) } else { None };
+        let end = match (last_text, cursor_row) {
+            (Some(a), Some(b)) => a.max(b) + 1,
+            (Some(a), None) => a + 1,
+            (None, Some(b)) => b + 1,
+            (None, None) => 0,
+        };
+        let mut lines = Vec::new();
+        let mut line = self.partial.clone();
+        for (row, &continues) in grid[..end].iter().zip(wrapped) {
+            if continues {
+                line.push_str(&cells_text(row));
+            } else {
+                line.push_str(&row_text(row));
+                lines.push(std::mem::take(&mut line));
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    pub fn view(&self) -> ScreenView {
+        ScreenView {
+            rows: self.grid.clone(),
+            cursor: self.cursor_visible.then_some(self.cursor),
+        }
+    }
+
+    // Invalid UTF-8 shows as U+FFFD, the same however the bytes are split
+    // across reads
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.utf8.extend_from_slice(bytes);
+        let pending = std::mem::take(&mut self.utf8);
+        let mut rest = &pending[..];
+        loop {
+            let (valid, error) = match std::str::from_utf8(rest) {
+                Ok(text) => (text, None),
+                Err(e) => (std::str::from_utf8(&rest[..e.valid_up_to()]).unwrap_or_default(), Some(e)),
+            };
+            for c in valid.chars() {
+                self.advance(c);
+            }
+            let Some(e) = error else {
+                break;
+            };
+            match e.error_len() {
+                Some(len) => {
+                    self.advance(char::REPLACEMENT_CHARACTER);
+                    rest = &rest[e.valid_up_to() + len..];
+                }
+                // A character split across reads; finish it next time
+                None => {
+                    self.utf8 = rest[e.valid_up_to()..].to_vec();
+                    break;
+                }
+            }
+        }
+    }
+
+    fn advance(&mut self, c: char) {
+        match std::mem::replace(&mut self.state, State::Ground) {
+            State::Ground => self.ground(c),
+            State::Escape => self.escape(c),
+            State::Charset => {}
+            State::Csi { mut params, private } => {
+                if c.is_ascii_digit() || c == ';' || c == ':' {
+                    params.push(c);
+                    self.state = State::Csi { params, private };
+                } else if matches!(c, '?' | '>' | '=' | '<') {
+                    self.state = State::Csi { params, private: true };
+                } else if (' '..='/').contains(&c) {
+                    // Intermediate bytes (e.g. `CSI ! p`) are accepted and ignored
+                    self.state = State::Csi { params, private };
+                } else {
+                    self.csi(&params, private, c);
+                }
+            }
+            State::Osc(body) => match c {
+                '\x07' => self.osc(body),
+                '\x1b' => self.state = State::OscEscape(body),
+                // Bodies past MAX_OSC are dropped, not buffered without end
+                c => {
+                    let body = body.filter(|b| b.len() < MAX_OSC).map(|mut b| {
+                        b.push(c);
+                        b
+                    });
+                    self.state = State::Osc(body);
+                }
+            },
+            State::OscEscape(body) => {
+                if c == '\\' {
+                    self.osc(body);
+                } else {
+                    self.state = State::Osc(body);
+                }
+            }
+        }
+    }
+
+    fn osc(&mut self, body: Option<String>) {
+        let Some(body) = body else {
+            return;
+        };
+        if body.starts_with(PEER_OSC) {
+            self.peer_messages.push(body);
+        } else if let Some(mark) = body.strip_prefix("133;").filter(|_| self.saved_main.is_none()) {
+            self.semantic_prompt(mark);
+        }
+    }
+
+    // OSC 133: A prompt starts, B input starts, C the command runs. A shell
+    // that never sends C has its prompt taken at the next A
+    fn semantic_prompt(&mut self, mark: &str) {
+        match mark.chars().next() {
+            Some('A') => {
+                self.finish_prompt();
+                self.prompt_at = Some((self.cursor.0, None));
+            }
+            Some('B') => self.prompt_at = Some((self.cursor.0, Some(self.cursor.1))),
+            Some('C') => self.finish_prompt(),
+            _ => {}
+        }
+    }
+
+    fn finish_prompt(&mut self) {
+        let Some((row, input)) = self.prompt_at.take() else {
+            return;
+        };
+        let mut line = String::new();
+        let mut command = String::new();
+        for r in row..self.rows {
+            let start = if r == row { input.unwrap_or(0).min(self.cols) } else { 0 };
+            command.push_str(&cells_text(&self.grid[r][start..]));
+            line.push_str(&cells_text(&self.grid[r]));
+            if !self.wrapped[r] {
+                break;
+            }
+        }
+        let command = command.trim();
+        // Without B the whole line stands for the command
+        let command = if input.is_some() { command.to_string() } else { line.trim().to_string() };
+        if !command.is_empty() {
+            self.prompts.push(SemanticPrompt { line: line.trim_end().to_string(), command });
+        }
+    }
+
+    fn ground(&mut self, c: char) {
+        match c {
+            '\x1b' => self.state = State::Escape,
+            '\r' => {
+                self.cursor.1 = 0;
+                self.wrap_pending = false;
+            }
+            '\n' | '\x0b' | '\x0c' => self.line_feed(),
+            '\x08' => {
+                self.cursor.1 = self.cursor.1.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            '\t' => {
+                self.cursor.1 = ((self.cursor.1 / 8 + 1) * 8).min(self.cols - 1);
+            }
+            '\x07' => self.bells += 1,
+            c if c.is_control() => {}
+            c => self.print(c),
+        }
+    }
+
+    fn escape(&mut self, c: char) {
+        match c {
+            '[' => self.state = State::Csi { params: String::new(), private: false },
+            ']' => self.state = State::Osc(Some(String::new())),
+            'P' | '_' | '^' => self.state = State::Osc(None),
+            '(' | ')' | '*' | '+' => self.state = State::Charset,
+            '7' => self.saved_cursor = self.cursor,
+            '8' => self.restore_cursor(),
+            'D' => self.line_feed(),
+            'E' => {
+                self.cursor.1 = 0;
+                self.line_feed();
+            }
+            'M' => {
+                if self.cursor.0 == self.scroll_top {
+                    self.scroll_down(1);
+                } else {
+                    self.cursor.0 = self.cursor.0.saturating_sub(1);
+                }
+            }
+            'c' => *self = Screen::new(self.rows, self.cols),
+            _ => {}
+        }
+    }
+
+    fn print(&mut self, c: char) {
+        let width = c.width().unwrap_or(0);
+        if width == 0 {
+            return;
+        }
+        if self.wrap_pending || self.cursor.1 + width > self.cols {
+            self.wrapped[self.cursor.0] = true;
+            self.cursor.1 = 0;
+            self.line_feed();
+        }
+        let (row, col) = self.cursor;
+        self.grid[row][col] = Cell { ch: c, style: self.style };
+        // The second column of a wide character stays empty
+        if width == 2 && col + 1 < self.cols {
+            self.grid[row][col + 1] = Cell { ch: '\0', style: self.style };
+        }
+        if col + width >= self.cols {
+            self.cursor.1 = self.cols - 1;
+            self.wrap_pending = true;
+        } else {
+            self.cursor.1 = col + width;
+        }
+    }
+
+    fn line_feed(&mut self) {
+        self.wrap_pending = false;
+        if self.cursor.0 == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.cursor.0 + 1 < self.rows {
+            self.cursor.0 += 1;
+        }
+    }
+
+    fn blank_row(&self) -> Vec<Cell> {
+        vec![Cell { ch: ' ', style: CellStyle { bg: self.style.bg, ..CellStyle::default() } }; self.cols]
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        for _ in 0..n {
+            let line = self.grid.remove(self.scroll_top);
+            let continues = self.wrapped.remove(self.scroll_top);
+            if self.scroll_top == 0 && self.saved_main.is_none() {
+                self.push_scrolled(&line, continues);
+                self.prompt_at = self.prompt_at.and_then(|(row, input)| Some((row.checked_sub(1)?, input)));
+            }
+            self.grid.insert(self.scroll_bottom, self.blank_row());
+            self.wrapped.insert(self.scroll_bottom, false);
+        }
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        for _ in 0..n {
+            self.grid.remove(self.scroll_bottom);
+            self.grid.insert(self.scroll_top, self.blank_row());
+            self.wrapped.remove(self.scroll_bottom);
+            self.wrapped.insert(self.scroll_top, false);
+        }
+    }
+
+    // A row leaving the top of the main screen; a wrapped one is held until
+    // the rest of its line follows
+    fn push_scrolled(&mut self, row: &[Cell], continues: bool) {
+        if continues {
+            self.partial.push_str(&cells_text(row));
+        } else {
+            let mut line = std::mem::take(&mut self.partial);
+            line.push_str(&row_text(row));
+            self.scrolled.push(line);
+        }
+    }
+
+    fn csi(&mut self, params: &str, private: bool, action: char) {
+        let args: Vec<usize> = params
+            .split(';')
+            .map(|p| p.split(':').next().unwrap_or("").parse().unwrap_or(0))
+            .collect();
+        let arg = |i: usize, default: usize| match args.get(i) {
+            Some(0) | None => default,
+            Some(n) => *n,
+        };
+        let (row, col) = self.cursor;
+        let last_row = self.rows - 1;
+        let last_col = self.cols - 1;
+        self.wrap_pending = false;
+
+        match (private, action) {
+            (false, 'A') => self.cursor.0 = row.saturating_sub(arg(0, 1)).max(if row >= self.scroll_top { self.scroll_top } else { 0 }),
+            (false, 'B') | (false, 'e') => self.cursor.0 = row.saturating_add(arg(0, 1)).min(if row <= self.scroll_bottom { self.scroll_bottom } else { last_row }),
+            (false, 'C') | (false, 'a') => self.cursor.1 = col.saturating_add(arg(0, 1)).min(last_col),
+            (false, 'D') => self.cursor.1 = col.saturating_sub(arg(0, 1)),
+            (false, 'E') => self.cursor = (row.saturating_add(arg(0, 1)).min(last_row), 0),
+            (false, 'F') => self.cursor = (row.saturating_sub(arg(0, 1)), 0),
+            (false, 'G') | (false, '`') => self.cursor.1 = (arg(0, 1) - 1).min(last_col),
+            (false, 'd') => self.cursor.0 = (arg(0, 1) - 1).min(last_row),
+            (false, 'H') | (false, 'f') => self.cursor = ((arg(0, 1) - 1).min(last_row), (arg(1, 1) - 1).min(last_col)),
+            (false, 'J') => {
+                let blank = self.blank_row();
+                let (start, end) = match args.first().copied().unwrap_or(0) {
+                    0 => {
+                        self.grid[row][col..].fill(blank[0]);
+                        (row + 1, self.rows)
+                    }
+                    1 => {
+                        self.grid[row][..=col].fill(blank[0]);
+                        (0, row)
+                    }
+                    _ => (0, self.rows),
+                };
+                for r in start..end {
+                    self.grid[r] = blank.clone();
+                    self.wrapped[r] = false;
+                }
+            }
+            (false, 'K') => {
+                let blank = self.blank_row()[0];
+                match args.first().copied().unwrap_or(0) {
+                    0 => self.grid[row][col..].fill(blank),
+                    1 => self.grid[row][..=col].fill(blank),
+                    _ => self.grid[row].fill(blank),
+                }
+            }
+            (false, 'X') => {
+                let end = col.saturating_add(arg(0, 1)).min(self.cols);
+                let blank = self.blank_row()[0];
+                self.grid[row][col..end].fill(blank);
+            }
+            (false, 'P') => {
+                let n = arg(0, 1).min(self.cols - col);
+                let blank = self.blank_row()[0];
+                self.grid[row].drain(col..col + n);
+                self.grid[row].resize(self.cols, blank);
+            }
+            (false, '@') => {
+                let n = arg(0, 1).min(self.cols - col);
+                let blank = self.blank_row()[0];
+                for _ in 0..n {
+                    self.grid[row].insert(col, blank);
+                }
+                self.grid[row].truncate(self.cols);
+            }
+            (false, 'L') | (false, 'M') if (self.scroll_top..=self.scroll_bottom).contains(&row) => {
+                let saved_top = self.scroll_top;
+                self.scroll_top = row;
+                if action == 'L' {
+                    self.scroll_down(arg(0, 1).min(self.scroll_bottom - row + 1));
+                } else {
+                    self.scroll_up(arg(0, 1).min(self.scroll_bottom - row + 1));
+                }
+                self.scroll_top = saved_top;
+                self.cursor.1 = 0;
+            }
+            // More than the region's height clears it all the same
+            (false, 'S') => self.scroll_up(arg(0, 1).min(self.scroll_bottom - self.scroll_top + 1)),
+            (false, 'T') => self.scroll_down(arg(0, 1).min(self.scroll_bottom - self.scroll_top + 1)),
+            (false, 'r') => {
+                let top = arg(0, 1) - 1;
+                let bottom = arg(1, self.rows) - 1;
+                if top < bottom && bottom < self.rows {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.cursor = (0, 0);
+                }
+            }
+            (false, 's') => self.saved_cursor = self.cursor,
+            (false, 'u') => self.restore_cursor(),
+            (false, 'm') => self.sgr(params),
+            (true, 'h') | (true, 'l') => {
+                let on = action == 'h';
+                for mode in &args {
+                    match mode {
+                        1 => self.app_cursor_keys = on,
+                        25 => self.cursor_visible = on,
+                        2004 => self.bracketed_paste = on,
+                        47 | 1047 | 1049 => self.set_alternate(on),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Saved before a resize, the position may be off the screen now
+    fn restore_cursor(&mut self) {
+        self.cursor = (self.saved_cursor.0.min(self.rows - 1), self.saved_cursor.1.min(self.cols - 1));
+        self.wrap_pending = false;
+    }
+
+    fn set_alternate(&mut self, on: bool) {
+        if on && self.saved_main.is_none() {
+            self.saved_cursor = self.cursor;
+            let alternate = vec![vec![Cell::default(); self.cols]; self.rows];
+            self.saved_main = Some(std::mem::replace(&mut self.grid, alternate));
+            self.saved_wrapped = std::mem::replace(&mut self.wrapped, vec![false; self.rows]);
+            self.cursor = (0, 0);
+        } else if !on {
+            if let Some(main) = self.saved_main.take() {
+                self.grid = main;
+                self.wrapped = std::mem::take(&mut self.saved_wrapped);
+                self.cursor = self.saved_cursor;
+            }
+        }
+        self.scroll_top = 0;
+        self.scroll_bottom = self.rows - 1;
+    }
+
+    fn sgr(&mut self, params: &str) {
+        let codes: Vec<u16> = if params.is_empty() {
+            vec![0]
+        } else {
+            params.split([';', ':']).map(|p| p.parse().unwrap_or(0)).collect()
+        };
+        let mut i = 0;
+        while i < codes.len() {
+            match codes[i] {
+                0 => self.style = CellStyle::default(),
+                1 => self.style.bold = true,
+                2 => self.style.dim = true,
+                3 => self.style.italic = true,
+                4 => self.style.underline = true,
+                7 => self.style.reverse = true,
+                22 => {
+                    self.style.bold = false;
+                    self.style.dim = false;
+                }
+                23 => self.style.italic = false,
+                24 => self.style.underline = false,
+                27 => self.style.reverse = false,
+                n @ 30..=37 => self.style.fg = VtColor::Indexed((n - 30) as u8),
+                n @ 90..=97 => self.style.fg = VtColor::Indexed((n - 90 + 8) as u8),
+                n @ 40..=47 => self.style.bg = VtColor::Indexed((n - 40) as u8),
+                n @ 100..=107 => self.style.bg = VtColor::Indexed((n - 100 + 8) as u8),
+                39 => self.style.fg = VtColor::Default,
+                49 => self.style.bg = VtColor::Default,
+                n @ (38 | 48) => {
+                    let color = match codes.get(i + 1) {
+                        Some(5) => {
+                            i += 2;
+                            codes.get(i).map(|c| VtColor::Indexed(*c as u8))
+                        }
+                        Some(2) => {
+                            i += 4;
+                            match (codes.get(i - 2), codes.get(i - 1), codes.get(i)) {
+                                (Some(r), Some(g), Some(b)) => Some(VtColor::Rgb(*r as u8, *g as u8, *b as u8)),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                    if let Some(color) = color {
+                        if n == 38 {
+                            self.style.fg = color;
+                        } else {
+                            self.style.bg = color;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+fn row_text(row: &[Cell]) -> String {
+    cells_text(row).trim_end().to_string()
+}
+
+// Row text with trailing blanks kept, for rows whose line continues
+fn cells_text(row: &[Cell]) -> String {
+    row.iter().map(|c| c.ch).filter(|c| *c != '\0').collect()
+}
+
+// Joins wrapped rows back into lines and wraps them again at `cols`, carrying
+// the cursor along
+fn rewrap(
+    grid: Vec<Vec<Cell>>,
+    wrapped: &[bool],
+    cursor: (usize, usize),
+    cols: usize,
+) -> (Vec<Vec<Cell>>, Vec<bool>, (usize, usize)) {
+    let mut rows = Vec::new();
+    let mut flags = Vec::new();
+    let mut new_cursor = (0, 0);
+    let mut line: Vec<Cell> = Vec::new();
+    let mut cursor_at = None;
+    let last = grid.len().saturating_sub(1);
+    for (r, row) in grid.into_iter().enumerate() {
+        if r == cursor.0 {
+            cursor_at = Some(line.len() + cursor.1);
+        }
+        line.extend(row);
+        if wrapped.get(r).copied().unwrap_or(false) && r < last {
+            continue;
+        }
+        // Trailing blanks aren't part of the line, short of the cursor
+        let text = line.iter().rposition(|c| c.ch != ' ').map_or(0, |i| i + 1);
+        line.truncate(text.max(cursor_at.unwrap_or(0)).min(line.len()));
+
+        let mut row: Vec<Cell> = Vec::with_capacity(cols);
+        for (i, cell) in line.iter().enumerate() {
+            // A wide character doesn't straddle two rows
+            let wide = cell.ch.width() == Some(2);
+            if row.len() == cols || (wide && row.len() + 2 > cols && !row.is_empty()) {
+                row.resize(cols, Cell::default());
+                rows.push(std::mem::replace(&mut row, Vec::with_capacity(cols)));
+                flags.push(true);
+            }
+            if cursor_at == Some(i) {
+                new_cursor = (rows.len(), row.len());
+            }
+            row.push(*cell);
+        }
+        if cursor_at == Some(line.len()) {
+            if row.len() == cols {
+                rows.push(std::mem::replace(&mut row, Vec::with_capacity(cols)));
+                flags.push(true);
+            }
+            new_cursor = (rows.len(), row.len());
+        }
+        row.resize(cols, Cell::default());
+        rows.push(row);
+        flags.push(false);
+        line.clear();
+        cursor_at = None;
+    }
+    (rows, flags, new_cursor)
+}
+
+// Brings a grid to `rows` rows of `cols` cells. Blank rows below the cursor go
+// first, then rows off the top, which are returned for the scrollback
+fn fit(
+    grid: &mut Vec<Vec<Cell>>,
+    wrapped: &mut Vec<bool>,
+    cursor: &mut (usize, usize),
+    rows: usize,
+    cols: usize,
+) -> Vec<(Vec<Cell>, bool)> {
+    let mut dropped = Vec::new();
+    while grid.len() > rows {
+        let last = grid.len() - 1;
+        let blank = grid[last].iter().all(|c| c.ch == ' ');
+        if cursor.0 > 0 && !(last > cursor.0 && blank) {
+            dropped.push((grid.remove(0), wrapped.remove(0)));
+            cursor.0 -= 1;
+        } else {
+            grid.pop();
+            wrapped.pop();
+        }
+    }
+    grid.resize(rows, vec![Cell::default(); cols]);
+    wrapped.resize(rows, false);
+    for row in grid.iter_mut() {
+        row.resize(cols, Cell::default());
+    }
+    *cursor = (cursor.0.min(rows - 1), cursor.1.min(cols - 1));
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn carriage_return_overwrites_progress() {
+        let mut screen = Screen::new(5, 20);
+        screen.feed(b"progress 10%\rprogress 100%\r\n\x1b[31mdone\x1b[0m\r\n");
+        assert_eq!(screen.live_lines(), vec!["progress 100%", "done"]);
+        assert_eq!(screen.view().rows[1][0].style.fg, VtColor::Indexed(1));
+    }
+
+    #[test]
+    fn semantic_prompts_give_the_command() {
+        let mut screen = Screen::new(3, 20);
+        screen.feed(b"\x1b]133;A\x07~/src\r\n\xe2\x9d\xaf \x1b]133;B\x07ls -l\r\n\x1b]133;C\x07a.txt\r\n");
+        screen.feed(b"\x1b]133;A\x07$ \x1b]133;B\x07\r\n\x1b]133;A\x07$ ");
+        assert_eq!(screen.take_prompts(), vec![SemanticPrompt { line: "\u{276f} ls -l".into(), command: "ls -l".into() }]);
+        assert_eq!(screen.take_scrolled(), vec!["~/src", "\u{276f} ls -l"]);
+    }
+
+    #[test]
+    fn scrolled_lines_become_scrollback() {
+        let mut screen = Screen::new(2, 10);
+        screen.feed(b"one\r\ntwo\r\nthree\r\n");
+        assert_eq!(screen.take_scrolled(), vec!["one", "two"]);
+        assert_eq!(screen.live_lines(), vec!["three"]);
+    }
+
+    #[test]
+    fn alternate_screen_leaves_main_untouched() {
+        let mut screen = Screen::new(3, 10);
+        screen.feed(b"prompt\r\n\x1b[?1049h\x1b[2J\x1b[1;1Hvim\x1b[?1049l");
+        assert!(!screen.is_alternate());
+        assert_eq!(screen.live_lines(), vec!["prompt"]);
+    }
+
+    #[test]
+    fn wrapped_rows_stay_one_line() {
+        let mut screen = Screen::new(2, 5);
+        screen.feed(b"abcdefghijkl\r\n");
+        assert_eq!(screen.take_scrolled(), Vec::<String>::new());
+        assert_eq!(screen.live_lines(), vec!["abcdefghijkl"]);
+        screen.feed(b"x\r\n");
+        assert_eq!(screen.take_scrolled(), vec!["abcdefghijkl"]);
+    }
+
+    #[test]
+    fn resize_rewraps_instead_of_truncating() {
+        let mut screen = Screen::new(3, 10);
+        screen.feed(b"abcdefghij\r\n$ ");
+        screen.resize(3, 4);
+        assert_eq!(screen.take_scrolled(), Vec::<String>::new());
+        assert_eq!(screen.live_lines(), vec!["abcdefghij", "$"]);
+        assert_eq!(screen.cursor, (2, 2));
+
+        screen.resize(3, 20);
+        assert_eq!(screen.live_lines(), vec!["abcdefghij", "$"]);
+        assert_eq!(screen.cursor, (1, 2));
+    }
+
+    // Program output as it may come off a pty: text, escape sequences with
+    // odd or huge parameters, and bytes that aren't UTF-8
+    fn output() -> impl Strategy<Value = Vec<u8>> {
+        let piece = prop_oneof![
+            "[ -~]{0,12}".prop_map(String::into_bytes),
+            "[^\\x00-\\x7f]{0,3}".prop_map(String::into_bytes),
+            "\\x1b\\[[?>]?[0-9;:]{0,12}[ -/]?[@-~]".prop_map(String::into_bytes),
+            "\\x1b\\[[0-9]{10,25}(;[0-9]{10,25})?[@-~]".prop_map(String::into_bytes),
+            "\\x1b[\\]P_^][ -~]{0,10}(\\x07|\\x1b\\\\)?".prop_map(String::into_bytes),
+            "\\x1b[ -~]".prop_map(String::into_bytes),
+            "[\\r\\n\\t\\x08\\x0b\\x0c]".prop_map(String::into_bytes),
+            prop::collection::vec(any::<u8>(), 0..8),
+        ];
+        prop::collection::vec(piece, 0..48).prop_map(|pieces| pieces.concat())
+    }
+
+    fn assert_consistent(screen: &Screen) {
+        let view = screen.view();
+        assert_eq!(view.rows.len(), screen.rows);
+        assert!(view.rows.iter().all(|row| row.len() == screen.cols));
+        assert!(screen.cursor.0 < screen.rows && screen.cursor.1 < screen.cols);
+        assert!(screen.scroll_top <= screen.scroll_bottom && screen.scroll_bottom < screen.rows);
+    }
+
+    proptest! {
+        #[test]
+        fn any_output_keeps_the_screen_consistent(
+            bytes in output(),
+            size in (1..40usize, 1..100usize),
+            resized in (1..40usize, 1..100usize),
+            split in any::<prop::sample::Index>(),
+        ) {
+            let mut screen = Screen::new(size.0, size.1);
+            let (first, second) = bytes.split_at(split.index(bytes.len() + 1));
+            screen.feed(first);
+            assert_consistent(&screen);
+            screen.resize(resized.0, resized.1);
+            assert_consistent(&screen);
+            screen.feed(second);
+            assert_consistent(&screen);
+            screen.live_lines();
+            screen.take_scrolled();
+        }
+
+        #[test]
+        fn reads_split_anywhere_give_the_same_screen(
+            bytes in output(),
+            splits in prop::collection::vec(any::<prop::sample::Index>(), 0..6),
+        ) {
+            let mut whole = Screen::new(6, 20);
+            whole.feed(&bytes);
+
+            let mut cuts: Vec<usize> = splits.iter().map(|i| i.index(bytes.len() + 1)).collect();
+            cuts.sort_unstable();
+            let mut pieces = Screen::new(6, 20);
+            let mut start = 0;
+            for cut in cuts.into_iter().chain([bytes.len()]) {
+                pieces.feed(&bytes[start..cut]);
+                start = cut;
+            }
+
+            prop_assert_eq!(whole.view().rows, pieces.view().rows);
+            prop_assert_eq!(whole.live_lines(), pieces.live_lines());
+            prop_assert_eq!(whole.take_scrolled(), pieces.take_scrolled());
+        }
+    }
+}
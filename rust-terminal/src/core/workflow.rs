@@ -0,0 +1,191 @@
+// Workflows: named command sequences whose steps can declare a rollback, and
+// inputs and outputs to be skipped by while up to date (see workflow_deps)
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::core::actions::{Action, KeyChord, Target};
+use crate::core::operations::Operation;
+use crate::core::workflow_deps::{Freshness, Stamps};
+use crate::utils::config::Config;
+
+// workflows/<name>.toml:
+//   description = "Deploy the API"
+//   icon = "🚀"                        # for the command palette, like
+//   keywords = ["release", "ship"]      # these and a key (optional)
+//   key = "Alt+D"
+//   [[steps]]
+//   name = "build"                     # for the graph (optional)
+//   run = "cargo build --release"
+//   inputs = ["src", "Cargo.toml", "$RUSTFLAGS"]
+//   outputs = ["target/release/api"]
+//   [[steps]]
+//   run = "kubectl apply -f api.yaml"
+//   rollback = "kubectl delete -f api.yaml"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workflow {
+    #[serde(skip)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub key: Option<String>,
+    pub steps: Vec<WorkflowStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowStep {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub run: String,
+    #[serde(default)]
+    pub rollback: Option<String>,
+    // Files, directories and `$VARS`, relative to where the workflow runs
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    #[serde(default)]
+    pub outputs: Vec<String>,
+}
+
+impl WorkflowStep {
+    // Its name, or `step N` for the 0-based `index`
+    pub fn label(&self, index: usize) -> String {
+        self.name.clone().unwrap_or_else(|| format!("step {}", index + 1))
+    }
+}
+
+impl Workflow {
+    pub fn load(name: &str) -> Result<Self> {
+        if name.contains('/') || name.starts_with('.') {
+            bail!("Invalid workflow name: {}", name);
+        }
+        let path = Self::dir().join(format!("{}.toml", name));
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("No workflow '{}' ({})", name, path.display()))?;
+        let mut workflow: Workflow = toml::from_str(&content)
+            .with_context(|| format!("Invalid workflow {}", path.display()))?;
+        if workflow.steps.is_empty() {
+            bail!("Workflow '{}' has no steps", name);
+        }
+        workflow.name = name.to_string();
+        Ok(workflow)
+    }
+
+    pub fn list() -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(Self::dir())
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter_map(|e| {
+                        let path = e.path();
+                        if path.extension()? != "toml" {
+                            return None;
+                        }
+                        Some(path.file_stem()?.to_string_lossy().into_owned())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    // A palette action for every workflow that loads; one with a key that
+    // doesn't parse is offered without it
+    pub fn actions() -> Vec<Action> {
+        Self::list()
+            .iter()
+            .filter_map(|name| Self::load(name).ok())
+            .map(|workflow| {
+                let title = match workflow.description.as_str() {
+                    "" => format!("Run workflow {}", workflow.name),
+                    description => description.to_string(),
+                };
+                let target = Target::Command(format!("workflow run {}", workflow.name));
+                Action::new(&format!("workflow.{}", workflow.name), &title, target)
+                    .with_icon(workflow.icon.as_deref().unwrap_or("⚙"))
+                    .with_keywords(&workflow.keywords)
+                    .with_keywords(&["workflow", workflow.name.as_str()])
+                    .with_key(workflow.key.as_deref().and_then(|key| KeyChord::parse(key).ok()))
+            })
+            .collect()
+    }
+
+    fn dir() -> PathBuf {
+        Config::dir().join("workflows")
+    }
+}
+
+// What `advance` came to
+pub enum Next {
+    Run(String),
+    // Skipped: the step's outputs are up to date
+    UpToDate(String),
+}
+
+// A workflow being run as a transaction: steps go one at a time, and the
+// rollbacks of the steps that succeeded are collected for undoing a failure.
+// `operation` shows how far along it is until the transaction is dropped.
+pub struct Transaction {
+    pub workflow: Workflow,
+    next: usize,
+    rollbacks: Vec<String>,
+    stamps: Stamps,
+    operation: Operation,
+}
+
+impl Transaction {
+    pub fn new(workflow: Workflow, operation: Operation) -> Self {
+        operation.progress().set_total(workflow.steps.len() as u64);
+        Self { workflow, next: 0, rollbacks: Vec::new(), stamps: Stamps::new(), operation }
+    }
+
+    // The next step, or None when all steps ran. Freshness is checked as
+    // each step comes up, after the steps it depends on have run
+    pub fn advance(&mut self) -> Option<Next> {
+        let step = self.workflow.steps.get(self.next)?;
+        let label = step.label(self.next);
+        self.next += 1;
+        self.operation.progress().advance(label.as_str());
+        match self.stamps.freshness(&self.workflow.name, step, &label) {
+            Freshness::UpToDate => Some(Next::UpToDate(label)),
+            _ => Some(Next::Run(step.run.clone())),
+        }
+    }
+
+    // 1-based number of the step last returned by `advance`
+    pub fn current_step(&self) -> usize {
+        self.next
+    }
+
+    pub fn step_succeeded(&mut self) {
+        let Some(index) = self.next.checked_sub(1) else {
+            return;
+        };
+        let step = &self.workflow.steps[index];
+        self.stamps.record(&self.workflow.name, step, &step.label(index));
+        if let Some(rollback) = step.rollback.clone() {
+            self.rollbacks.push(rollback);
+        }
+    }
+
+    // Rollbacks of the completed steps, most recent first
+    pub fn into_rollback_plan(self) -> RollbackPlan {
+        RollbackPlan {
+            workflow: self.workflow.name,
+            failed_step: self.next,
+            commands: self.rollbacks.into_iter().rev().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RollbackPlan {
+    pub workflow: String,
+    pub failed_step: usize,
+    pub commands: Vec<String>,
+}
@@ -0,0 +1,225 @@
+// Make-like incrementality for workflows: a step that declares its inputs
+// and outputs is skipped while its outputs are up to date, which means they
+// all exist, none is older than the newest input, and the `$VARS` among the
+// inputs have the values they had when the step last succeeded. Directories
+// count by the newest file under them. Those values are kept as hashes in
+// workflow-stamps.json under the data directory. A step without outputs
+// always runs. Steps depend on earlier steps whose outputs they take as
+// inputs, which is what `workflow graph` draws.
+use openssl::sha::sha256;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+use crate::core::workflow::{Workflow, WorkflowStep};
+use crate::utils::{durable, storage};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Freshness {
+    // Declares no outputs
+    Always,
+    UpToDate,
+    // Why it has to run
+    Stale(String),
+}
+
+// A step as the graph panel shows it
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub label: String,
+    pub run: String,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    // Earlier steps whose outputs this one reads
+    pub depends_on: Vec<usize>,
+    pub freshness: Freshness,
+}
+
+// Workflow -> step label -> variable -> hash of its value
+type StampMap = BTreeMap<String, BTreeMap<String, BTreeMap<String, String>>>;
+
+pub struct Stamps {
+    path: PathBuf,
+}
+
+impl Stamps {
+    pub fn new() -> Self {
+        Self { path: storage::data_dir().join("workflow-stamps.json") }
+    }
+
+    fn load(&self) -> StampMap {
+        durable::load_json(&self.path).unwrap_or_default()
+    }
+
+    fn recorded(&self, workflow: &str, label: &str) -> BTreeMap<String, String> {
+        self.load().remove(workflow).and_then(|mut steps| steps.remove(label)).unwrap_or_default()
+    }
+
+    // After a step succeeded: its variables as they are now
+    pub fn record(&self, workflow: &str, step: &WorkflowStep, label: &str) {
+        let vars = env_inputs(step);
+        if vars.is_empty() {
+            return;
+        }
+        let mut stamps = self.load();
+        let current = vars.iter().map(|var| (var.to_string(), env_hash(var))).collect();
+        stamps.entry(workflow.to_string()).or_default().insert(label.to_string(), current);
+        let written = serde_json::to_string_pretty(&stamps)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| durable::write_atomic(&self.path, json.as_bytes()));
+        if let Err(e) = written {
+            tracing::warn!("Failed to save workflow stamps: {}", e);
+        }
+    }
+
+    pub fn freshness(&self, workflow: &str, step: &WorkflowStep, label: &str) -> Freshness {
+        freshness(step, &self.recorded(workflow, label))
+    }
+}
+
+impl Default for Stamps {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn graph(workflow: &Workflow, stamps: &Stamps) -> Vec<Node> {
+    workflow
+        .steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            let depends_on = (0..i)
+                .filter(|&earlier| {
+                    let outputs = &workflow.steps[earlier].outputs;
+                    file_inputs(step).any(|input| outputs.iter().any(|output| Path::new(output).starts_with(input)))
+                })
+                .collect();
+            let label = step.label(i);
+            Node {
+                freshness: stamps.freshness(&workflow.name, step, &label),
+                label,
+                run: step.run.clone(),
+                inputs: step.inputs.clone(),
+                outputs: step.outputs.clone(),
+                depends_on,
+            }
+        })
+        .collect()
+}
+
+fn freshness(step: &WorkflowStep, recorded: &BTreeMap<String, String>) -> Freshness {
+    if step.outputs.is_empty() {
+        return Freshness::Always;
+    }
+    let mut oldest: Option<(SystemTime, &str)> = None;
+    for output in &step.outputs {
+        match newest(Path::new(output)) {
+            None => return Freshness::Stale(format!("{} is missing", output)),
+            Some(time) if oldest.is_none_or(|(t, _)| time < t) => oldest = Some((time, output)),
+            Some(_) => {}
+        }
+    }
+    for var in env_inputs(step) {
+        if recorded.get(var) != Some(&env_hash(var)) {
+            return Freshness::Stale(format!("${} changed", var));
+        }
+    }
+    let Some((oldest, output)) = oldest else {
+        return Freshness::UpToDate;
+    };
+    for input in file_inputs(step) {
+        match newest(Path::new(input)) {
+            None => return Freshness::Stale(format!("{} is missing", input)),
+            Some(time) if time > oldest => return Freshness::Stale(format!("{} is newer than {}", input, output)),
+            Some(_) => {}
+        }
+    }
+    Freshness::UpToDate
+}
+
+fn env_inputs(step: &WorkflowStep) -> Vec<&str> {
+    step.inputs.iter().filter_map(|input| input.strip_prefix('$')).collect()
+}
+
+fn file_inputs(step: &WorkflowStep) -> impl Iterator<Item = &str> {
+    step.inputs.iter().map(String::as_str).filter(|input| !input.starts_with('$'))
+}
+
+// Unset and empty hash differently
+fn env_hash(var: &str) -> String {
+    let value = std::env::var(var).map_or_else(|_| "unset".to_string(), |value| format!("={}", value));
+    sha256(value.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Modification time of a file, or of the newest file under a directory
+fn newest(path: &Path) -> Option<SystemTime> {
+    let metadata = path.metadata().ok()?;
+    if !metadata.is_dir() {
+        return metadata.modified().ok();
+    }
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok()?.modified().ok())
+        .max()
+        .or_else(|| metadata.modified().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::time::Duration;
+
+    fn step(inputs: &[&str], outputs: &[&str]) -> WorkflowStep {
+        WorkflowStep {
+            name: None,
+            run: "true".into(),
+            rollback: None,
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            outputs: outputs.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn touch(path: &Path, age: u64) {
+        File::create(path).unwrap().set_modified(SystemTime::now() - Duration::from_secs(age)).unwrap();
+    }
+
+    #[test]
+    fn skips_steps_with_fresh_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src, out) = (dir.path().join("src"), dir.path().join("app"));
+        fs::create_dir(&src).unwrap();
+        touch(&src.join("main.c"), 100);
+        touch(&out, 50);
+        let (src, out) = (src.to_string_lossy().into_owned(), out.to_string_lossy().into_owned());
+
+        assert_eq!(freshness(&step(&[&src], &[]), &BTreeMap::new()), Freshness::Always);
+        assert_eq!(freshness(&step(&[&src], &[&out]), &BTreeMap::new()), Freshness::UpToDate);
+        touch(&Path::new(&src).join("util.c"), 0);
+        let stale = freshness(&step(&[&src], &[&out]), &BTreeMap::new());
+        assert!(matches!(stale, Freshness::Stale(why) if why.contains("newer")));
+        assert!(matches!(freshness(&step(&[], &["/nonexistent/app"]), &BTreeMap::new()), Freshness::Stale(_)));
+
+        let with_var = step(&["$NEXTERM_TEST_STAMP"], &[&out]);
+        let stale = freshness(&with_var, &BTreeMap::new());
+        assert!(matches!(stale, Freshness::Stale(why) if why == "$NEXTERM_TEST_STAMP changed"));
+        let recorded = [("NEXTERM_TEST_STAMP".to_string(), env_hash("NEXTERM_TEST_STAMP"))].into();
+        assert_eq!(freshness(&with_var, &recorded), Freshness::UpToDate);
+
+        let workflow = Workflow {
+            name: "build".into(),
+            description: String::new(),
+            icon: None,
+            keywords: Vec::new(),
+            key: None,
+            steps: vec![step(&[&src], &[&out]), step(&[&out], &[]), step(&[], &[])],
+        };
+        let nodes = graph(&workflow, &Stamps { path: dir.path().join("stamps.json") });
+        assert_eq!(nodes.iter().map(|n| n.depends_on.clone()).collect::<Vec<_>>(), vec![vec![], vec![0], vec![]]);
+        assert_eq!(nodes[2].label, "step 3");
+    }
+}
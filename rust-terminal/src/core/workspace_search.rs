@@ -0,0 +1,227 @@
+// Workspace search (Ctrl+Shift+F): one query over everything NexTerm has
+// kept, which is the scrollback every tab shows, capture buffers and the
+// newest session logs under logs/ in the data directory. Matches come back
+// grouped by where they were found: scrollback by the command that printed
+// them, buffers and logs by name. Matching is substring, case-insensitive
+// unless the query has capitals.
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::core::terminal::PromptMark;
+use crate::utils::buffers::CaptureBuffers;
+
+// Session logs searched, newest first
+const RECENT_LOGS: usize = 10;
+// Only the end of a bigger log is read
+const MAX_LOG_BYTES: u64 = 2 * 1024 * 1024;
+// Past this many the search stops
+const MAX_HITS: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    // Scrollback, under the command that printed it (None: before the first)
+    Output { command: Option<String> },
+    Buffer(String),
+    Log(PathBuf),
+}
+
+impl Source {
+    pub fn title(&self) -> String {
+        match self {
+            Source::Output { command: Some(command) } => format!("❯ {}", command),
+            Source::Output { command: None } => "Scrollback".to_string(),
+            Source::Buffer(name) => format!("buffer {}", name),
+            Source::Log(path) => format!(
+                "log {}",
+                path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    // In the source's own lines; scrollback hits count from the top of the output
+    pub line: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub source: Source,
+    pub hits: Vec<Hit>,
+}
+
+// Where a hit takes you
+pub enum Target {
+    // A line of the output pane
+    Output(usize),
+    // A buffer or log, opened at `line`
+    Document { title: String, lines: Vec<String>, line: usize },
+}
+
+struct Document {
+    source: Source,
+    // Where its first line sits in the scrollback; 0 otherwise
+    start: usize,
+    lines: Vec<String>,
+}
+
+// What a search runs over, gathered once when the search opens
+pub struct Corpus {
+    documents: Vec<Document>,
+}
+
+impl Corpus {
+    // Logs come from `logs`, tee::logs_dir() outside tests
+    pub fn collect(output: &[String], marks: &[PromptMark], buffers: &CaptureBuffers, logs: &Path) -> Self {
+        let mut documents = Vec::new();
+        // Scrollback, cut at each prompt mark
+        let mut cuts: Vec<(usize, Option<String>)> = vec![(0, None)];
+        cuts.extend(marks.iter().map(|mark| (mark.line, Some(mark.command.clone()))));
+        for (i, (start, command)) in cuts.iter().enumerate() {
+            let end = cuts.get(i + 1).map_or(output.len(), |(next, _)| *next).min(output.len());
+            if *start < end {
+                documents.push(Document {
+                    source: Source::Output { command: command.clone() },
+                    start: *start,
+                    lines: output[*start..end].to_vec(),
+                });
+            }
+        }
+        for buffer in buffers.iter() {
+            documents.push(Document {
+                source: Source::Buffer(buffer.name.clone()),
+                start: 0,
+                lines: buffer.content.lines().map(str::to_string).collect(),
+            });
+        }
+        for path in recent_logs(logs) {
+            if let Some(content) = read_tail(&path) {
+                let lines = content.lines().map(str::to_string).collect();
+                documents.push(Document { source: Source::Log(path), start: 0, lines });
+            }
+        }
+        Self { documents }
+    }
+
+    // Groups in corpus order: scrollback oldest first, then buffers, then logs
+    pub fn search(&self, query: &str) -> Vec<Group> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let fold = !query.chars().any(char::is_uppercase);
+        let needle = if fold { query.to_lowercase() } else { query.to_string() };
+        let mut groups = Vec::new();
+        let mut total = 0;
+        for document in &self.documents {
+            let mut hits = Vec::new();
+            for (i, line) in document.lines.iter().enumerate() {
+                if total + hits.len() >= MAX_HITS {
+                    break;
+                }
+                let found = if fold { line.to_lowercase().contains(&needle) } else { line.contains(&needle) };
+                if found {
+                    hits.push(Hit { line: document.start + i, text: line.clone() });
+                }
+            }
+            total += hits.len();
+            if !hits.is_empty() {
+                groups.push(Group { source: document.source.clone(), hits });
+            }
+        }
+        groups
+    }
+
+    // Up to `radius` lines either side of `line`, with their line numbers
+    pub fn around(&self, source: &Source, line: usize, radius: usize) -> Vec<(usize, &str)> {
+        let Some(document) = self.documents.iter().find(|d| d.source == *source) else {
+            return Vec::new();
+        };
+        let index = line.saturating_sub(document.start);
+        let first = index.saturating_sub(radius);
+        let last = (index + radius + 1).min(document.lines.len());
+        (first..last).map(|i| (document.start + i, document.lines[i].as_str())).collect()
+    }
+
+    pub fn target(&self, source: &Source, line: usize) -> Option<Target> {
+        let document = self.documents.iter().find(|d| d.source == *source)?;
+        Some(match source {
+            Source::Output { .. } => Target::Output(line),
+            _ => Target::Document { title: source.title(), lines: document.lines.clone(), line },
+        })
+    }
+}
+
+fn recent_logs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut logs: Vec<_> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let modified = entry.metadata().ok().filter(|m| m.is_file())?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    logs.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    logs.into_iter().take(RECENT_LOGS).map(|(_, path)| path).collect()
+}
+
+fn read_tail(path: &Path) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    if len > MAX_LOG_BYTES {
+        file.seek(SeekFrom::Start(len - MAX_LOG_BYTES)).ok()?;
+    }
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).ok()?;
+    let mut content = String::from_utf8_lossy(&bytes).into_owned();
+    // The first line of a tail is likely cut short
+    if len > MAX_LOG_BYTES {
+        content = content.split_once('\n').map(|(_, rest)| rest.to_string()).unwrap_or_default();
+    }
+    Some(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_matches_by_command_and_buffer() {
+        let output: Vec<String> = ["welcome", "❯ make", "Error: linker failed", "❯ ls", "error.log", "notes"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let marks = vec![
+            PromptMark { line: 1, command: "make".into() },
+            PromptMark { line: 3, command: "ls".into() },
+        ];
+        let mut buffers = CaptureBuffers::new();
+        buffers.save(Some("build"), "ok\nerror: again".into());
+        let logs = tempfile::tempdir().unwrap();
+        std::fs::write(logs.path().join("output-1.log"), "cargo build\nerror[E0308]: mismatched types\n").unwrap();
+        let corpus = Corpus::collect(&output, &marks, &buffers, logs.path());
+
+        let groups = corpus.search("error");
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[0].source.title(), "❯ make");
+        assert_eq!(groups[0].hits, vec![Hit { line: 2, text: "Error: linker failed".into() }]);
+        assert_eq!(groups[1].hits[0].line, 4);
+        assert_eq!((groups[2].source.title(), groups[2].hits[0].line), ("buffer build".to_string(), 1));
+        assert_eq!(groups[3].source.title(), "log output-1.log");
+        // Capitals make it case-sensitive
+        assert_eq!(corpus.search("Error").len(), 1);
+        assert_eq!(corpus.search("E0308").len(), 1);
+        assert!(corpus.search("").is_empty());
+
+        assert_eq!(corpus.around(&groups[1].source, 4, 1), vec![(3, "❯ ls"), (4, "error.log"), (5, "notes")]);
+        assert!(matches!(corpus.target(&groups[0].source, 2), Some(Target::Output(2))));
+        let Some(Target::Document { lines, line, .. }) = corpus.target(&groups[2].source, 1) else {
+            panic!("expected a document");
+        };
+        assert_eq!((lines.len(), line), (2, 1));
+    }
+}
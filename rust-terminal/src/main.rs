@@ -1,11 +1,13 @@
 // NexTerm - Revolutionary Terminal Experience with AI Auto-Wiring
 // Main entry point integrating Python AI Auto-Wiring System
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::io;
-use std::process::{Command, Stdio};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::process::Command;
 use tracing::{info, error, warn};
-use tracing_subscriber;
 
 mod core;
 mod ui;
@@ -13,45 +15,121 @@ mod ai;
 mod plugins;
 mod utils;
 
-use crate::core::terminal::Terminal;
+use crate::ai::autowire_daemon::{self, AutoWireConfig};
+use crate::ai::cheatsheets::Cheatsheet;
+use crate::ai::community_specs::SpecRegistry;
+use crate::ai::digest;
+use crate::ai::project_notes::ProjectNotes;
+use crate::ai::proposals::{self, Proposal, Workspace};
+use crate::core::actions::{Action, Target};
+use crate::core::binary;
+use crate::core::daemons;
+use crate::core::encoding::Encoding;
+use crate::core::environments::EnvLabel;
+use crate::core::executor::ScriptedExecutor;
+use crate::core::impact;
+use crate::core::json_output::{JsonSink, JsonTarget};
+use crate::core::operations::Kind;
+use crate::core::peer::{self, Item, Side};
+use crate::core::roaming::RoamMode;
+use crate::core::scriptify::{self, ScriptOptions};
+use crate::core::status::StatusReport;
+use crate::core::tee;
+use crate::core::workflow::Workflow;
+use crate::core::workflow_deps::{self, Stamps};
+use crate::core::workspace_search::Corpus;
+use crate::plugins::manager::{PluginInfo, PluginManager};
+use crate::core::terminal::{Severity, Terminal};
+use crate::ui::demo::DemoPlayer;
+use crate::ui::frontend::{Frontend, Panel, Question, Request};
 use crate::ui::tui::TerminalUI;
 use crate::utils::config::Config;
+use crate::utils::calendar::{self, Calendar};
+use crate::utils::editor::{self, Edit, EditResult};
+use crate::utils::startup::Pending;
+use crate::utils::{clock, doctor, error, profile, profiling, report, ssh_keys, storage};
+use crate::utils::sync::{self, SyncClient};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .init();
+    // Errors that end the program keep their code and hint
+    run().await.map_err(|e| anyhow::anyhow!(error::describe(&e)))
+}
 
-    info!("🚀 NexTerm with AI Auto-Wiring starting...");
+async fn run() -> Result<()> {
+    // Parse command line arguments
+    let args = parse_args();
+
+    // Initialize logging (and tracing to --trace-out in profiling builds)
+    let _trace = profiling::init(args.trace_out.as_deref())?;
+    // `nexterm demo <script.toml>` plays a scripted session in the TUI
+    let demo = match &args.command[..] {
+        [] => None,
+        [word, script] if word == "demo" => Some(PathBuf::from(script)),
+        _ => return run_subcommand(&args).await,
+    };
 
-    // Initialize AI Auto-Wiring System
-    info!("Initializing AI Auto-Wiring Engine...");
-    let autowire_status = initialize_autowire_system().await?;
-    info!("✅ Auto-Wiring Engine: {}", autowire_status);
+    info!("🚀 NexTerm with AI Auto-Wiring starting...");
+    let started = clock::now();
 
     // Load configuration
     let config = Config::load()?;
     info!("Configuration loaded");
 
-    // Parse command line arguments
-    let args = parse_args();
+    // Demos answer from a script and leave the shell and Python alone, and
+    // start from a fresh data directory so no history shows up or is kept.
+    // Otherwise Python, plugins and auto-wiring come up in the background,
+    // after the prompt.
+    let (mut terminal, probe, player) = match &demo {
+        Some(script) => {
+            storage::set_data_dir(std::env::temp_dir().join(format!("nexterm-demo-{}", std::process::id())));
+            let executor = Arc::new(ScriptedExecutor::load(script)?);
+            let player = DemoPlayer::load(script)?;
+            info!("Demo mode: playing {}", script.display());
+            (Terminal::with_executor(config.clone(), executor)?, None, Some(player))
+        }
+        None => {
+            let autowire = config.autowire.clone();
+            let probe = Pending::spawn("Auto-Wiring Engine", move || probe_autowire_system(&autowire));
 
-    // Initialize terminal with auto-wiring integration
-    let mut terminal = Terminal::new(config.clone())?;
-    terminal.enable_autowire_integration()?;
-    info!("Terminal initialized with auto-wiring");
+            // Initialize terminal with auto-wiring integration
+            let mut terminal = Terminal::new(config.clone())?;
+            terminal.enable_autowire_integration()?;
+            info!("Terminal initialized with auto-wiring");
+            (terminal, Some(probe), None)
+        }
+    };
+
+    if let Some(target) = &args.json {
+        if matches!(target, JsonTarget::Stdout) {
+            anyhow::bail!("--json - is for subcommands; the TUI needs a file or unix:<socket>");
+        }
+        terminal.set_json_sink(JsonSink::open(target)?);
+        info!("Writing command results as JSON lines to {:?}", target);
+    }
+    // Built-in actions now, plugin and agent ones once they're loaded
+    terminal.refresh_actions();
 
     // Initialize UI
     let mut ui = TerminalUI::new(&config)?;
+    if let Some(player) = player {
+        ui.play(player);
+    }
     info!("UI initialized");
 
     // Display welcome message with auto-wiring status
-    ui.show_welcome_with_autowire(&autowire_status)?;
+    if let Some(probe) = probe {
+        ui.show_welcome_with_autowire("⏳ Checking for the auto-wiring system...")?;
+        ui.probe_autowire(probe);
+    }
+    info!("Ready for input after {}ms", clock::since(started).as_millis());
 
     // Main event loop
-    match run_terminal(&mut terminal, &mut ui).await {
+    let result = run_terminal(&mut terminal, &mut ui).await;
+    if demo.is_some() {
+        let _ = std::fs::remove_dir_all(storage::data_dir());
+    }
+    match result {
         Ok(_) => {
             info!("NexTerm shutting down gracefully");
             shutdown_autowire_system().await?;
@@ -65,44 +143,46 @@ async fn main() -> Result<()> {
     }
 }
 
-async fn initialize_autowire_system() -> Result<String> {
-    // Check if Python auto-wiring system is available
-    let python_check = Command::new("python3")
-        .args(&["-c", "import sys; print(sys.version)"])
-        .output();
-
-    match python_check {
-        Ok(output) if output.status.success() => {
-            info!("Python detected: {}", String::from_utf8_lossy(&output.stdout).trim());
-            
-            // Try to import and initialize the auto-wiring system
-            let autowire_init = Command::new("python3")
-                .args(&[
-                    "-c",
-                    r#"
+// Python's version, then, given the package, whether the auto-wiring system
+// imports: one interpreter start for both
+const AUTOWIRE_PROBE: &str = r#"
 import sys
-import os
-# Add parent directory to path to allow importing src as a package
-sys.path.insert(0, os.path.abspath('..'))
-from src.core.autowire import get_autowire
-autowire = get_autowire()
-print(f"Auto-Wire initialized with {len(autowire.get_registry_info())} components")
-                    "#
-                ])
-                .output()?;
-
-            if autowire_init.status.success() {
-                let status = String::from_utf8_lossy(&autowire_init.stdout).trim().to_string();
-                Ok(status)
-            } else {
-                warn!("Auto-wiring system not initialized: {}", 
-                      String::from_utf8_lossy(&autowire_init.stderr));
-                Ok("Auto-wiring available in fallback mode".to_string())
-            }
-        }
+print(sys.version.split()[0])
+if len(sys.argv) > 1:
+    sys.path.insert(0, sys.argv[1])
+    from core.autowire import get_autowire
+    autowire = get_autowire()
+    print(f"Auto-Wire initialized with {len(autowire.get_registry_info())} components")
+"#;
+
+// Runs in the background at startup; the welcome view shows the answer
+fn probe_autowire_system(config: &AutoWireConfig) -> String {
+    let python = autowire_daemon::interpreter(config);
+    let package = autowire_daemon::package_dir(config);
+    let output = Command::new(&python).args(["-c", AUTOWIRE_PROBE]).args(&package).output();
+    let output = match output {
+        Ok(output) if !output.stdout.is_empty() => output,
         _ => {
             warn!("Python not available, auto-wiring disabled");
-            Ok("Auto-wiring disabled (Python not found)".to_string())
+            return "Auto-wiring disabled (Python not found)".to_string();
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    info!("Python detected: {}", lines.next().unwrap_or_default());
+
+    if package.is_none() {
+        warn!("Auto-wiring package not found; set `package_path` under [autowire]");
+        return "Auto-wiring available in fallback mode".to_string();
+    }
+    match (output.status.success(), lines.next()) {
+        (true, Some(status)) => {
+            info!("✅ Auto-Wiring Engine: {}", status.trim());
+            status.trim().to_string()
+        }
+        _ => {
+            warn!("Auto-wiring system not initialized: {}", String::from_utf8_lossy(&output.stderr));
+            "Auto-wiring available in fallback mode".to_string()
         }
     }
 }
@@ -113,130 +193,1215 @@ async fn shutdown_autowire_system() -> Result<()> {
     Ok(())
 }
 
-async fn run_terminal(terminal: &mut Terminal, ui: &mut TerminalUI) -> Result<()> {
-    use crossterm::event::{self, Event, KeyCode, KeyModifiers};
-    use std::time::Duration;
-
+async fn run_terminal(terminal: &mut Terminal, ui: &mut impl Frontend) -> Result<()> {
     loop {
-        // Render UI
-        ui.render(terminal)?;
-
-        // Handle events
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                match (key.code, key.modifiers) {
-                    // Exit
-                    (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
-                        if ui.confirm_exit()? {
-                            break;
-                        }
-                    }
-                    (KeyCode::Char('d'), KeyModifiers::CONTROL) => {
-                        // EOF - exit if input is empty
-                        if ui.is_input_empty() {
-                            break;
-                        }
-                    }
+        match ui.handle_input(terminal)? {
+            Some(Request::Exit) => break,
+            Some(request) => handle_request(terminal, ui, request).await,
+            None => {}
+        }
 
-                    // Tab management
-                    (KeyCode::Char('t'), KeyModifiers::CONTROL) => {
-                        ui.new_tab()?;
-                    }
-                    (KeyCode::Char('w'), KeyModifiers::CONTROL) => {
-                        ui.close_tab()?;
-                    }
-                    (KeyCode::Tab, KeyModifiers::CONTROL) => {
-                        ui.next_tab()?;
-                    }
+        // Update terminal output
+        refresh(terminal, ui).await?;
+    }
 
-                    // Pane management
-                    (KeyCode::Char('d'), KeyModifiers::CONTROL | KeyModifiers::SHIFT) => {
-                        ui.split_pane_vertical()?;
-                    }
-                    (KeyCode::Char('h'), KeyModifiers::CONTROL | KeyModifiers::SHIFT) => {
-                        ui.split_pane_horizontal()?;
-                    }
+    terminal.leave_peer();
+    for sequence in terminal.take_host_output() {
+        ui.write_to_host(&sequence)?;
+    }
+    Ok(())
+}
 
-                    // Features
-                    (KeyCode::Char('f'), KeyModifiers::CONTROL) => {
-                        ui.open_fuzzy_finder()?;
-                    }
-                    (KeyCode::Char('r'), KeyModifiers::CONTROL) => {
-                        ui.open_history()?;
-                    }
-                    (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
-                        ui.open_git_status()?;
-                    }
-                    (KeyCode::Char(' '), KeyModifiers::CONTROL) => {
-                        // AI suggestions using auto-wiring system
-                        ui.show_ai_suggestions_with_autowire()?;
-                    }
+// What the frontend's input needs NexTerm for, see ui::frontend::Request
+async fn handle_request(terminal: &mut Terminal, ui: &mut impl Frontend, request: Request) {
+    match request {
+        // The event loop's to handle
+        Request::Exit => {}
+        Request::Run(command) => {
+            run_command_line(terminal, ui, &command).await;
+            terminal.input_changed(&ui.get_input());
+        }
+        Request::Queue(commands) => submit_guarded(terminal, ui, commands).await,
+        Request::Submit(commands) => {
+            for command in &commands {
+                terminal.submit_command(command).await;
+            }
+        }
+        Request::Release(commands) => terminal.release(commands).await,
+        // A rollback's or the other NexTerm's question
+        Request::Answer(confirmed) => {
+            let rollback = terminal.resolve_rollback(confirmed);
+            submit_guarded(terminal, ui, rollback).await;
+            answer_peer_offer(terminal, ui, confirmed);
+        }
+        Request::Action(action) => run_action(terminal, ui, action).await,
+        Request::Approve(index) => approve_action(terminal, ui, index).await,
+        Request::RunWorkflow(name) => {
+            if let Err(e) = terminal.run_workflow(&name).await {
+                terminal.notify_error(&e);
+            }
+        }
+        Request::Script(commands, options) => {
+            if let Err(e) = write_script(terminal, &commands, &options) {
+                terminal.notify_error(&e);
+            }
+        }
+    }
+}
 
-                    // Auto-wiring features
-                    (KeyCode::Char('a'), KeyModifiers::CONTROL) => {
-                        ui.show_autowire_status()?;
-                    }
-                    (KeyCode::Char('s'), KeyModifiers::CONTROL) => {
-                        ui.show_autowire_services()?;
-                    }
+// Once a frame, after input: the terminal's news for the frontend
+async fn refresh(terminal: &mut Terminal, ui: &mut impl Frontend) -> Result<()> {
+    terminal.set_view(ui.active_tab_id(), ui.is_locked());
+    terminal.set_locked(ui.is_locked());
+    terminal.update_output().await?;
+    ui.output_changed(terminal.output_len());
+    for notice in terminal.take_notices() {
+        ui.notify(notice);
+    }
+    for ring in terminal.take_rings() {
+        ui.ring(ring);
+    }
+    if let Some(plan) = terminal.take_rollback_offer() {
+        let lines = std::iter::once(format!("Step {} failed. Run these rollbacks, in order?", plan.failed_step))
+            .chain(plan.commands.iter().map(|c| format!("  ↶ {}", c)))
+            .collect();
+        ui.ask(Question { title: format!("Roll back '{}'", plan.workflow), lines, yes: "Roll back", no: "Leave it" });
+    }
+    if let Some(offer) = terminal.take_peer_offer() {
+        let lines = vec![
+            format!("The other NexTerm sends {}.", offer.item.describe()),
+            match &offer.item {
+                Item::Clipboard { .. } => "Copy it to the clipboard?".to_string(),
+                Item::File { .. } => format!("Save it in {}?", std::env::current_dir()?.display()),
+            },
+        ];
+        ui.ask(Question { title: "Accept from the other NexTerm".to_string(), lines, yes: "Accept", no: "Decline" });
+    }
+    if let Some(commands) = ui.take_released() {
+        terminal.release(Some(commands)).await;
+    }
+    // Workflow steps and trigger `run`s are guarded like typed commands
+    if !ui.is_guarding() {
+        if let Some(command) = terminal.take_held() {
+            if let Some(commands) = ui.guard(vec![command]) {
+                terminal.release(Some(commands)).await;
+            }
+        }
+    }
+    if let Some(file) = terminal.take_edit_request() {
+        let succeeded = match edit_wait(terminal, ui, &file) {
+            Ok(result) => {
+                report_edit(terminal, &result)?;
+                result.succeeded()
+            }
+            Err(e) => {
+                terminal.notify_error(&e);
+                false
+            }
+        };
+        terminal.finish_edit(succeeded).await;
+    }
+    if let Some((script, options)) = terminal.take_polished() {
+        if let Err(e) = save_script(terminal, &script, &options) {
+            terminal.notify_error(&e);
+        }
+    }
+    match terminal.take_filtered() {
+        Some((pipeline, Ok(lines))) => ui.show(Panel::Filter { pipeline: &pipeline, lines })?,
+        Some((_, Err(e))) => terminal.notify_error(&e),
+        None => {}
+    }
+    match terminal.take_proposals() {
+        Some((Some(request), Ok(proposals))) => ui.show(Panel::ActionCards { request: &request, proposals })?,
+        Some((None, Ok(proposals))) => ui.add_action_cards(proposals),
+        Some((_, Err(e))) => terminal.notify_error(&e),
+        None => {}
+    }
+    match terminal.take_review() {
+        Some(Ok(findings)) if findings.is_empty() => {
+            terminal.notify(Severity::Success, "Review found nothing in the staged changes")
+        }
+        Some(Ok(findings)) => ui.show(Panel::Problems(findings))?,
+        Some(Err(e)) => terminal.notify_error(&e),
+        None => {}
+    }
+    for sequence in terminal.take_host_output() {
+        ui.write_to_host(&sequence)?;
+    }
+    // Daemons exit on their own; keep open panels current
+    ui.refresh_panels(terminal);
+    ui.tick(terminal);
+    Ok(())
+}
 
-                    // Command input
-                    (KeyCode::Enter, _) => {
-                        let command = ui.get_input();
-                        if !command.is_empty() {
-                            // Execute through auto-wiring system if available
-                            terminal.execute_command_with_autowire(&command).await?;
-                            ui.clear_input();
-                        }
-                    }
-                    (KeyCode::Char(c), _) => {
-                        ui.input_char(c);
-                    }
-                    (KeyCode::Backspace, _) => {
-                        ui.input_backspace();
-                    }
+// The editor gets the screen until it exits. Not in restricted mode: an
+// editor writes any file and most can start a shell
+fn edit_wait(terminal: &Terminal, ui: &mut impl Frontend, path: &Path) -> Result<EditResult> {
+    terminal.check_restricted("Opening an editor (edit-wait)")?;
+    let edit = Edit::start(path);
+    let status = ui.edit(&terminal.config().editor, path)?;
+    Ok(edit.finish(status))
+}
 
-                    // Navigation
-                    (KeyCode::Up, _) => {
-                        ui.history_previous();
-                    }
-                    (KeyCode::Down, _) => {
-                        ui.history_next();
-                    }
-                    (KeyCode::Left, _) => {
-                        ui.cursor_left();
-                    }
-                    (KeyCode::Right, _) => {
-                        ui.cursor_right();
+// To the user, and as JSON to the control socket when there is one
+fn report_edit(terminal: &mut Terminal, result: &EditResult) -> Result<()> {
+    let severity = if result.succeeded() { Severity::Success } else { Severity::Warning };
+    terminal.notify(severity, result.describe());
+    terminal.emit_json(result)?;
+    Ok(())
+}
+
+// After the accept prompt for something the other NexTerm sent
+fn answer_peer_offer(terminal: &mut Terminal, ui: &mut impl Frontend, confirmed: bool) {
+    let Some(offer) = terminal.take_answered_peer_offer() else {
+        return;
+    };
+    let accepted = confirmed
+        && match accept_peer_offer(terminal, ui, offer.item) {
+            Ok(()) => true,
+            Err(e) => {
+                terminal.notify_error(&e);
+                false
+            }
+        };
+    terminal.reply_peer(offer.id, accepted);
+}
+
+fn accept_peer_offer(terminal: &mut Terminal, ui: &mut impl Frontend, item: Item) -> Result<()> {
+    match item {
+        Item::Clipboard { text } => {
+            // Kept as a buffer too, for terminals that ignore clipboard writes
+            let name = terminal.buffers_mut().save(Some("peer"), text.clone());
+            ui.copy_text(&text, &format!("the other NexTerm's clipboard (buffer {})", name))?;
+        }
+        Item::File { name, data } => {
+            let path = peer::save_file(&std::env::current_dir()?, &name, &data)?;
+            terminal.notify(Severity::Success, format!("Saved {}", path.display()));
+        }
+    }
+    Ok(())
+}
+
+// Entries shown by a bare `history`
+const HISTORY_LISTED: usize = 20;
+// Commands shown by `history top` without a count
+const HISTORY_TOP: usize = 10;
+// Period `ai summarize` covers without --since
+const SUMMARY_PERIOD: std::time::Duration = std::time::Duration::from_secs(7 * 86_400);
+// Log lines shown by `daemons logs <id>` without a count
+const DAEMON_LOG_LINES: usize = 50;
+// History entries `scriptify` offers without --last
+const SCRIPTIFY_COMMANDS: usize = 10;
+
+// Commands handled by NexTerm itself instead of the shell
+
+async fn run_builtin(terminal: &mut Terminal, ui: &mut impl Frontend, command: &str) -> Result<bool> {
+    let mut words = command.split_whitespace();
+    let (name, arg) = (words.next(), words.next());
+
+    match name {
+        Some("lock") => ui.lock(),
+        Some("teach") => {
+            let enabled = toggle(arg, terminal.is_teaching_mode());
+            terminal.set_teaching_mode(enabled);
+            terminal.notify(Severity::Info, format!("Teaching mode {}", on_off(enabled)));
+        }
+        Some("status") => {
+            let flags: Vec<&str> = arg.into_iter().chain(words).collect();
+            let report = StatusReport::collect(terminal, ui.tab_status());
+            if flags.contains(&"--json") {
+                // To the control socket when there is one
+                if !terminal.emit_json(&report)? {
+                    terminal.add_output(&serde_json::to_string_pretty(&report)?);
+                }
+            } else {
+                terminal.add_output(&report.render(flags.contains(&"--verbose")).join("\n"));
+            }
+        }
+        Some("notify") => match arg {
+            Some("test") => match terminal.test_notifications() {
+                0 => terminal.notify(Severity::Warning, "No [[notify]] sinks configured"),
+                n => terminal.notify(Severity::Info, format!("Test notification sent to {} sink(s)", n)),
+            },
+            _ => anyhow::bail!("Usage: notify test"),
+        },
+        Some("compat") => {
+            let enabled = toggle(arg, ui.is_compat());
+            ui.set_compat(enabled);
+            terminal.notify(Severity::Info, format!("Compatibility profile {}", on_off(enabled)));
+        }
+        Some("demo") => {
+            let enabled = toggle(arg, ui.is_demo_mode());
+            ui.set_demo_mode(enabled);
+            terminal.notify(Severity::Info, format!("Demo mode {}", on_off(enabled)));
+        }
+        Some("history") => match (arg, words.next(), words.next()) {
+            (Some("env"), Some(id), other) => show_history_env(terminal, id, other)?,
+            (Some("top"), first, second) => {
+                let options: Vec<&str> = [first, second].into_iter().flatten().chain(words).collect();
+                show_history_top(terminal, &options)?;
+            }
+            (Some("calendar"), None, _) => {
+                let lines = Calendar::new(terminal.history().timed()).render(calendar::today());
+                terminal.add_output(&lines.join("\n"));
+            }
+            (Some("calendar"), Some(date), _) => match calendar::parse_date(date) {
+                Some(day) => {
+                    let lines = calendar::day_listing(terminal.history().timed(), day);
+                    terminal.add_output(&lines.join("\n"));
+                }
+                None => terminal.notify(Severity::Warning, format!("Not a date: {} (use YYYY-MM-DD)", date)),
+            },
+            _ => {
+                let entries = terminal.get_history();
+                let start = entries.len().saturating_sub(HISTORY_LISTED);
+                let listing: Vec<String> = entries[start..]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, command)| format!("{:>5}  {}", start + i + 1, command))
+                    .collect();
+                terminal.add_output(&listing.join("\n"));
+            }
+        },
+        Some(editor::BUILTIN) => {
+            let file = editor::requested(command).context("Usage: edit-wait <file>")?;
+            let result = edit_wait(terminal, ui, &file)?;
+            report_edit(terminal, &result)?;
+        }
+        Some("impact") => {
+            let line = command.trim_start()["impact".len()..].trim();
+            if line.is_empty() {
+                anyhow::bail!("Usage: impact <command>");
+            }
+            let cwd = std::env::current_dir()?;
+            let impacts = impact::preview(line, &cwd);
+            if impacts.is_empty() {
+                terminal.notify(Severity::Info, "No rm, mv, cp, chmod or the like in that command");
+            } else {
+                terminal.add_output(&impact::describe(&impacts, &cwd).join("\n"));
+            }
+        }
+        // Session variables; `set -e` and the like still go to the shell
+        Some("set") if arg.is_none_or(|arg| !arg.starts_with('-')) => match arg {
+            Some(_) => {
+                let (name, value) = terminal.set_variable(command.trim_start()["set".len()..].trim())?;
+                terminal.notify(Severity::Success, format!("{{{{{}}}}} = {}", name, value));
+            }
+            None => ui.show(Panel::Variables(terminal.variables().values()))?,
+        },
+        Some("unset") => match arg {
+            Some(name) if terminal.unset_variable(name)? => terminal.notify(Severity::Info, format!("Unset {}", name)),
+            Some(name) => terminal.notify(Severity::Warning, format!("No session variable {}", name)),
+            None => anyhow::bail!("Usage: unset <name>"),
+        },
+        Some("dotenv") => match arg {
+            Some("load") => {
+                let dotenv = terminal.load_dotenv()?;
+                let message = format!("Loaded {} variables from {}", dotenv.vars.len(), dotenv.describe_files());
+                terminal.notify(Severity::Success, message);
+            }
+            Some("unload") => {
+                if terminal.unload_dotenv() {
+                    terminal.notify(Severity::Info, ".env variables unloaded");
+                }
+            }
+            _ => match terminal.dotenv() {
+                // Values stay masked; `echo $NAME` shows one deliberately
+                Some(dotenv) => {
+                    let lines: Vec<String> = std::iter::once(format!("From {}:", dotenv.describe_files()))
+                        .chain(dotenv.masked().into_iter().map(|(k, v)| format!("  {}={}", k, v)))
+                        .collect();
+                    terminal.add_output(&lines.join("\n"));
+                }
+                None => terminal.notify(Severity::Info, "No .env loaded — run `dotenv load`"),
+            },
+        },
+        Some("workflow") => match (arg, words.next()) {
+            (Some("run"), Some(name)) => terminal.run_workflow(name).await?,
+            (Some("graph"), Some(name)) => {
+                let workflow = Workflow::load(name)?;
+                let nodes = workflow_deps::graph(&workflow, &Stamps::new());
+                ui.show(Panel::WorkflowGraph { workflow: name, nodes })?;
+            }
+            _ => {
+                let names = Workflow::list();
+                let listing = if names.is_empty() {
+                    format!("No workflows in {}", Config::dir().join("workflows").display())
+                } else {
+                    names.join("\n")
+                };
+                terminal.add_output(&listing);
+            }
+        },
+        Some("cheat") => match (arg, words.next()) {
+            (Some("edit"), Some(tool)) => {
+                terminal.check_restricted("Opening an editor (cheat edit)")?;
+                let path = Cheatsheet::path_for_edit(tool)?;
+                let editor = terminal.config().editor.clone();
+                ui.edit(&editor, &path)?;
+                terminal.notify(Severity::Success, format!("Cheatsheet saved: {}", path.display()));
+            }
+            (Some(tool), None) if tool != "edit" => match Cheatsheet::load(tool) {
+                Some(cheat) => terminal.add_output(&cheat.content),
+                None => terminal.notify(Severity::Info, format!("No cheatsheet for {} — run `cheat edit {}`", tool, tool)),
+            },
+            _ => {
+                let tools = Cheatsheet::list();
+                let listing = if tools.is_empty() {
+                    format!("No cheatsheets in {} — run `cheat edit <tool>`", Cheatsheet::dir().display())
+                } else {
+                    tools.join("\n")
+                };
+                terminal.add_output(&listing);
+            }
+        },
+        Some("completions") => {
+            let registry = SpecRegistry::new(&terminal.config().completions);
+            match (arg, words.next()) {
+                // Downloads run in the background, under `activity`
+                (Some("install"), Some(tool)) => {
+                    let (tool, source) = (tool.to_string(), words.next().map(str::to_string));
+                    let title = format!("Installing the completion spec for {}", tool);
+                    terminal.operations().spawn(Kind::Download, title, |_| async move {
+                        registry.install(&tool, source.as_deref()).await?;
+                        Ok(format!("Completion spec for {} installed", tool))
+                    });
+                }
+                (Some("update"), tool) => {
+                    let tool = tool.map(str::to_string);
+                    terminal.operations().spawn(Kind::Download, "Updating completion specs", |progress| async move {
+                        let changed = registry.update(tool.as_deref(), &progress).await?;
+                        Ok(if changed.is_empty() {
+                            "Completion specs are up to date".to_string()
+                        } else {
+                            format!("Updated completion specs: {}", changed.join(", "))
+                        })
+                    });
+                }
+                (Some("remove"), Some(tool)) => {
+                    registry.remove(tool)?;
+                    terminal.notify(Severity::Info, format!("Completion spec for {} removed", tool));
+                }
+                (None | Some("list"), _) => {
+                    let installed = registry.list();
+                    let listing: Vec<String> = if installed.is_empty() {
+                        vec!["No completion specs installed — run `completions install <tool> [url|file]`".to_string()]
+                    } else {
+                        installed.iter().map(|(tool, spec)| format!("{:<16} {}", tool, spec.source)).collect()
+                    };
+                    terminal.add_output(&listing.join("\n"));
+                }
+                _ => anyhow::bail!("Usage: completions [list | install <tool> [url|file] | update [tool] | remove <tool>]"),
+            }
+        }
+        Some("plugins") => match (arg, words.next()) {
+            (Some("list"), _) => {
+                let plugins = terminal.plugins();
+                let listing: Vec<String> = if plugins.is_empty() {
+                    vec![format!("No plugins in {}", PluginManager::dir().display())]
+                } else {
+                    plugins.iter().map(describe_plugin).collect()
+                };
+                terminal.add_output(&listing.join("\n"));
+            }
+            (Some(action @ ("enable" | "disable")), Some(name)) => {
+                let enabled = action == "enable";
+                terminal.set_plugin_enabled(name, enabled)?;
+                terminal.notify(Severity::Success, format!("Plugin {} {}d", name, action));
+            }
+            _ => ui.show(Panel::Plugins(terminal.plugins()))?,
+        },
+        // Other `ai ...` commands go to the auto-wiring system
+        Some("ai") if arg == Some("do") => {
+            let request = words.collect::<Vec<_>>().join(" ");
+            if request.is_empty() {
+                anyhow::bail!("Usage: ai do <request>");
+            }
+            terminal.propose_actions(&request, &[])?;
+        }
+        Some("ai") if arg == Some("summarize") => {
+            let options: Vec<&str> = words.collect();
+            summarize_history(terminal, &options)?;
+        }
+        // Why the last command ran as typed, if it did
+        Some("autowire") if arg == Some("explain-last") => {
+            terminal.add_output(&terminal.autowire_fallbacks().explain_last().join("\n"))
+        }
+        Some("route") => {
+            let command = command.trim_start()["route".len()..].trim();
+            if command.is_empty() {
+                anyhow::bail!("Usage: route <command>");
+            }
+            terminal.add_output(&terminal.explain_route(command)?.join("\n"));
+        }
+        Some("daemonize") => {
+            let command = command.trim_start()["daemonize".len()..].trim();
+            if command.is_empty() {
+                anyhow::bail!("Usage: daemonize <command>");
+            }
+            let daemon = terminal.daemonize(command)?;
+            terminal.add_output(&format!(
+                "Daemon {} started (pid {}), logging to {}",
+                daemon.id,
+                daemon.pid,
+                daemon.log.display()
+            ));
+        }
+        Some("daemons") => {
+            let id = || -> Result<u32> {
+                let id = words.clone().next().context("Usage: daemons logs|stop|forget <id>")?;
+                id.parse().map_err(|_| anyhow::anyhow!("Not a daemon id: {}", id))
+            };
+            match arg {
+                Some("list") => {
+                    let listing: Vec<String> = terminal.daemons().list().iter().map(daemons::describe).collect();
+                    terminal.add_output(&if listing.is_empty() { "No daemons".to_string() } else { listing.join("\n") });
+                }
+                Some("logs") => {
+                    let daemon = terminal.daemons().get(id()?)?;
+                    let lines = words.nth(1).and_then(|n| n.parse().ok()).unwrap_or(DAEMON_LOG_LINES);
+                    terminal.add_output(&daemons::tail(&daemon.log, lines).join("\n"));
+                }
+                Some("stop") => {
+                    let id = id()?;
+                    terminal.daemons().stop(id)?;
+                    terminal.notify(Severity::Success, format!("Stopping daemon {}", id));
+                }
+                Some("forget") => {
+                    let id = id()?;
+                    terminal.daemons().forget(id)?;
+                    terminal.notify(Severity::Info, format!("Daemon {} forgotten", id));
+                }
+                _ => ui.show(Panel::Daemons(terminal.daemons().list()))?,
+            }
+        }
+        Some("ssh-keys") => ui.show(Panel::SshKeys(ssh_keys::list()?))?,
+        Some("activity") => ui.show(Panel::Activity(terminal.operations().list()))?,
+        // `mode [name|off]`: enter or leave a custom key layer, see core::modes
+        Some("mode") => match arg {
+            Some("off") => terminal.modes_mut().leave(),
+            Some(name) => {
+                let names = terminal.modes().names().join(", ");
+                terminal.modes_mut().toggle(name).with_context(|| format!("Unknown mode {} ({})", name, names))?;
+            }
+            None => {
+                let status = match terminal.modes().active() {
+                    Some(mode) => format!("In {} mode: {}", mode.title, mode.hints()),
+                    None if terminal.modes().names().is_empty() => "No modes; define them in the config".into(),
+                    None => format!("Modes: {}", terminal.modes().names().join(", ")),
+                };
+                terminal.notify(Severity::Info, status);
+            }
+        },
+        // `search [query]`: scrollback, capture buffers and recent session logs
+        Some("search") => {
+            let query = arg.into_iter().chain(words).collect::<Vec<_>>().join(" ");
+            let output = terminal.get_output();
+            let corpus = Corpus::collect(&output, terminal.prompt_marks(), terminal.buffers(), &tee::logs_dir());
+            ui.show(Panel::WorkspaceSearch { corpus, query: &query })?;
+        }
+        // `peer [clip [buffer] | send <file>]`: hand things to the NexTerm on the other end of ssh
+        Some("peer") => {
+            let item = match (arg, words.next()) {
+                (None, _) => {
+                    let status = match terminal.peer() {
+                        Some(Side::Inner) => "NexTerm is running in this tab's ssh session",
+                        Some(Side::Outer) => "This session runs in another NexTerm",
+                        None => "No other NexTerm on the line",
+                    };
+                    terminal.notify(Severity::Info, status);
+                    return Ok(true);
+                }
+                (Some("clip"), name) => {
+                    let buffer = terminal.buffers().get(name).context("No such capture buffer")?;
+                    Item::Clipboard { text: buffer.content.clone() }
+                }
+                (Some("send"), Some(path)) => Item::file(&PathBuf::from(shellexpand::tilde(path).into_owned()))?,
+                _ => anyhow::bail!("Usage: peer [clip [buffer] | send <file>]"),
+            };
+            let what = item.describe();
+            terminal.offer_to_peer(item)?;
+            terminal.notify(Severity::Info, format!("Offered {} to the other NexTerm", what));
+        }
+        // `problems`: diagnostics the post-processors found in the last command's output
+        Some("problems") => {
+            if terminal.problems().is_empty() {
+                terminal.notify(Severity::Info, "No problems in the last command's output");
+            } else {
+                ui.show(Panel::Problems(terminal.problems().to_vec()))?;
+            }
+        }
+        Some("review") => terminal.review_staged()?,
+        // `roam [off|auto|mosh|reconnect]`: how following `ssh <host>` tabs survive dropped connections
+        Some("roam") => {
+            if let Some(name) = arg {
+                let mode = RoamMode::parse(name)
+                    .with_context(|| format!("Unknown roaming mode {} (off, auto, mosh or reconnect)", name))?;
+                terminal.set_roam_mode(mode);
+            }
+            terminal.notify(Severity::Info, format!("Roaming {}", terminal.roam_mode().name()));
+        }
+        // `encoding [name]`: how the tab reads command output
+        Some("encoding") => {
+            if let Some(name) = arg {
+                let names: Vec<&str> = Encoding::ALL.iter().map(|e| e.name()).collect();
+                let encoding = Encoding::parse(name)
+                    .with_context(|| format!("Unknown encoding {} ({})", name, names.join(", ")))?;
+                terminal.set_encoding(encoding);
+            }
+            terminal.notify(Severity::Info, format!("Output read as {}", terminal.encoding().name()));
+        }
+        Some("tab") => match (arg, words.next()) {
+            (Some("env"), Some(label)) => {
+                let env = match label {
+                    "none" | "off" => None,
+                    label => Some(EnvLabel::parse(label).with_context(|| {
+                        format!("Unknown environment {} (dev, staging, prod or none)", label)
+                    })?),
+                };
+                ui.set_tab_env(env);
+                match env {
+                    Some(env) => terminal.notify(Severity::Info, format!("Tab labeled {}", env.name())),
+                    None => terminal.notify(Severity::Info, "Tab label cleared"),
+                }
+            }
+            (Some("mute"), state) => {
+                let muted = toggle(state, ui.is_tab_muted());
+                ui.set_tab_muted(muted);
+                terminal.notify(Severity::Info, format!("Bells {} in this tab", if muted { "muted" } else { "on" }));
+            }
+            _ => anyhow::bail!("Usage: tab env dev|staging|prod|none or tab mute [on|off]"),
+        },
+        // `batch [buffer]`: edit a capture buffer (or an empty page) as commands to run
+        Some("batch") => {
+            let text = match arg {
+                Some(name) => terminal
+                    .buffers()
+                    .get(Some(name))
+                    .map(|b| b.content.clone())
+                    .with_context(|| format!("No buffer {}", name))?,
+                None => String::new(),
+            };
+            ui.show(Panel::BatchEditor { title: arg.unwrap_or("Batch"), text: &text })?;
+        }
+        // `scriptify [--last N] [--out FILE [--force]] [--polish]`
+        Some("scriptify") => {
+            let options: Vec<&str> = command.split_whitespace().skip(1).collect();
+            let value = |flag: &str| options.iter().position(|o| *o == flag).and_then(|i| options.get(i + 1)).copied();
+            let count = match value("--last") {
+                Some(n) => n.parse().map_err(|_| anyhow::anyhow!("Not a count: {}", n))?,
+                None => SCRIPTIFY_COMMANDS,
+            };
+            let lines = terminal.scriptify_checklist(count);
+            if lines.is_empty() {
+                anyhow::bail!("No commands in history to make a script from");
+            }
+            let out = value("--out").map(|path| PathBuf::from(shellexpand::tilde(path).into_owned()));
+            let (polish, force) = (options.contains(&"--polish"), options.contains(&"--force"));
+            let options = ScriptOptions { out, polish, force };
+            check_overwrite(&options)?;
+            ui.show(Panel::Scriptify { lines, options })?;
+        }
+        // `logging [start [FILE] | stop]`: every command's output to a file too;
+        // bare, switches between the two
+        Some("logging") => match arg {
+            Some("start") => start_logging(terminal, words.next())?,
+            Some("stop") => stop_logging(terminal),
+            None => toggle_logging(terminal)?,
+            Some(other) => anyhow::bail!("Unknown logging command: {} (start [FILE] or stop)", other),
+        },
+        // `binary [view] [N]`, `binary save [N] FILE`: a recent command's binary output
+        Some("binary") => {
+            let args: Vec<&str> = arg.into_iter().chain(words).collect();
+            match args.as_slice() {
+                ["save", rest @ ..] => {
+                    let (id, file) = match rest {
+                        [file] => (None, *file),
+                        [id, file] => (Some(binary_id(id)?), *file),
+                        _ => anyhow::bail!("Usage: binary save [N] FILE"),
+                    };
+                    terminal.check_restricted("Saving binary output to a file")?;
+                    let path = PathBuf::from(shellexpand::tilde(file).into_owned());
+                    let output = terminal.binary_output(id).context("No binary output to save")?;
+                    let output = output.lock().map_err(|_| anyhow::anyhow!("Binary output unavailable"))?;
+                    output.save(&path)?;
+                    terminal.notify(
+                        Severity::Info,
+                        format!("Saved {} to {}", binary::size_label(output.bytes().len()), path.display()),
+                    );
+                }
+                ["view"] | [] => show_binary(terminal, ui, None)?,
+                ["view", id] | [id] => show_binary(terminal, ui, Some(binary_id(id)?))?,
+                _ => anyhow::bail!("Usage: binary [view] [N] or binary save [N] FILE"),
+            }
+        }
+        // What the palette offers and the keys bound to it
+        Some("actions") => {
+            terminal.refresh_actions();
+            let registry = terminal.actions();
+            let mut lines: Vec<String> = registry
+                .actions()
+                .iter()
+                .map(|action| {
+                    let key = registry.key_for(&action.id).map(|k| k.label()).unwrap_or_default();
+                    format!("{} {:<28} {:<14} {}", action.icon, action.id, key, action.title)
+                })
+                .collect();
+            lines.extend(registry.warnings().iter().map(|warning| format!("⚠ not bound: {}", warning)));
+            terminal.add_output(&lines.join("\n"));
+        }
+        Some("filter") => {
+            let rest = command.trim_start()["filter".len()..].trim();
+            filter(terminal, rest)?;
+        }
+        Some("capture") => {
+            let args: Vec<&str> = words.collect();
+            capture(terminal, ui, arg, &args)?;
+        }
+        Some("notes") => match arg {
+            Some("edit") => {
+                terminal.check_restricted("Opening an editor (notes edit)")?;
+                let path = ProjectNotes::path_for_edit()?;
+                let editor = terminal.config().editor.clone();
+                ui.edit(&editor, &path)?;
+                terminal.reload_project_notes();
+                terminal.notify(Severity::Success, format!("Project notes saved: {}", path.display()));
+            }
+            _ => match ProjectNotes::discover() {
+                Some(notes) => terminal.add_output(&notes.content),
+                None => terminal.notify(Severity::Info, "No project notes yet — run `notes edit`"),
+            },
+        },
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+// `history env <id>` shows what a command ran with; `history env <id> <other>` diffs two
+fn show_history_env(terminal: &mut Terminal, id: &str, other: Option<&str>) -> Result<()> {
+    let snapshot_of = |terminal: &Terminal, id: &str| {
+        let id: usize = id.parse().map_err(|_| anyhow::anyhow!("Not a history id: {}", id))?;
+        terminal
+            .history()
+            .snapshot(id)
+            .map(|(command, snapshot)| (command.to_string(), snapshot.clone()))
+            .ok_or_else(|| anyhow::anyhow!("No environment recorded for history entry {}", id))
+    };
+
+    let (command, snapshot) = snapshot_of(terminal, id)?;
+    let lines = match other {
+        Some(other) => {
+            let (other_command, other_snapshot) = snapshot_of(terminal, other)?;
+            let mut lines = vec![format!("{}: {}", id, command), format!("{}: {}", other, other_command)];
+            match snapshot.diff(&other_snapshot) {
+                diff if diff.is_empty() => lines.push("Environments are identical".to_string()),
+                diff => lines.extend(diff),
+            }
+            lines
+        }
+        None => std::iter::once(format!("{}: {}", id, command)).chain(snapshot.to_lines()).collect(),
+    };
+    terminal.add_output(&lines.join("\n"));
+    Ok(())
+}
+
+// `history top [--here] [--markdown] [N]`: the most frequent commands, with
+// --here only those run in this repository (or directory); --markdown prints a
+// "common commands" section for project docs
+fn show_history_top(terminal: &mut Terminal, options: &[&str]) -> Result<()> {
+    let limit = match options.iter().find(|o| !o.starts_with("--")) {
+        Some(n) => n.parse().map_err(|_| anyhow::anyhow!("Not a count: {}", n))?,
+        None => HISTORY_TOP,
+    };
+    let root = options.contains(&"--here").then(|| {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        cwd.ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .map(Path::to_path_buf)
+            .unwrap_or(cwd)
+    });
+
+    let top = terminal.history().top(root.as_deref(), limit);
+    let scope = match &root {
+        Some(root) => format!("in {}", root.display()),
+        None => "overall".to_string(),
+    };
+    let lines: Vec<String> = if top.is_empty() {
+        vec![format!("No commands recorded {}", scope)]
+    } else if options.contains(&"--markdown") {
+        std::iter::once("## Common commands\n".to_string())
+            .chain(top.iter().map(|(command, _)| format!("- `{}`", command)))
+            .collect()
+    } else {
+        std::iter::once(format!("Most used commands {}:", scope))
+            .chain(top.iter().map(|(command, count)| format!("{:>6}  {}", count, command)))
+            .collect()
+    };
+    terminal.add_output(&lines.join("\n"));
+    Ok(())
+}
+
+// The checked commands of a `scriptify` checklist, shown or written out
+fn write_script(terminal: &mut Terminal, commands: &[String], options: &ScriptOptions) -> Result<()> {
+    if commands.is_empty() {
+        anyhow::bail!("No commands checked; nothing to script");
+    }
+    let script = scriptify::script(commands);
+    if options.polish {
+        // Written once it's back, see refresh
+        match terminal.polish_script(&script, options) {
+            Ok(()) => return Ok(()),
+            Err(e) => terminal.notify(Severity::Warning, format!("Not polished: {:#}", e)),
+        }
+    }
+    save_script(terminal, &script, options)
+}
+
+// An existing `--out` is only replaced with --force
+fn check_overwrite(options: &ScriptOptions) -> Result<()> {
+    match &options.out {
+        Some(path) if path.exists() && !options.force => {
+            anyhow::bail!("{} already exists; add --force to replace it", path.display())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn save_script(terminal: &mut Terminal, script: &str, options: &ScriptOptions) -> Result<()> {
+    check_overwrite(options)?;
+    match &options.out {
+        Some(path) => {
+            terminal.check_restricted("Writing a script to a file")?;
+            std::fs::write(path, script)?;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+            terminal.notify(Severity::Success, format!("Script written to {}", path.display()));
+        }
+        None => terminal.add_output(script.trim_end()),
+    }
+    Ok(())
+}
+
+// `ai summarize [--since 7d] [--out digest.md]`: a markdown digest of recent
+// history, shown or written to a file
+fn summarize_history(terminal: &mut Terminal, options: &[&str]) -> Result<()> {
+    let value = |flag: &str| options.iter().position(|o| *o == flag).and_then(|i| options.get(i + 1)).copied();
+    let since = match value("--since") {
+        Some(text) => digest::parse_since(text).ok_or_else(|| anyhow::anyhow!("Not a period: {} (e.g. 7d, 12h, 2w)", text))?,
+        None => SUMMARY_PERIOD,
+    };
+    let markdown = terminal.summarize_history(since).to_markdown();
+    match value("--out") {
+        Some(path) => {
+            terminal.check_restricted("Writing a digest to a file")?;
+            let path = PathBuf::from(shellexpand::tilde(path).into_owned());
+            std::fs::write(&path, format!("{}\n", markdown))?;
+            terminal.notify(Severity::Success, format!("Digest written to {}", path.display()));
+        }
+        None => terminal.add_output(&markdown),
+    }
+    Ok(())
+}
+
+// `name [on|off] description (commands)`, plus why it failed to load
+fn describe_plugin(plugin: &PluginInfo) -> String {
+    let mut line = format!("{:<16} {:<4} {}", plugin.name, on_off(plugin.enabled), plugin.description);
+    if !plugin.commands.is_empty() {
+        line.push_str(&format!(" ({})", plugin.commands.join(", ")));
+    }
+    if let Some(error) = &plugin.error {
+        line.push_str(&format!("\n{:<16} error: {}", "", error));
+    }
+    line
+}
+
+// `capture save [name] [--pane]` keeps the last command's output (or the
+// visible pane) in a named buffer; `capture paste|show|delete [name]` and
+// `capture write <name> <file>` use it; bare `capture` lists the buffers
+fn capture(terminal: &mut Terminal, ui: &mut impl Frontend, action: Option<&str>, args: &[&str]) -> Result<()> {
+    let name = args.iter().copied().find(|a| !a.starts_with("--"));
+    match action {
+        Some("save") => {
+            let content = if args.contains(&"--pane") {
+                ui.visible_output(terminal).join("\n")
+            } else {
+                let last = terminal.prompt_marks().last().map(|m| m.line);
+                match last.and_then(|line| terminal.command_output_at(line)) {
+                    Some(output) => output,
+                    None => {
+                        terminal.notify(Severity::Info, "No command output to capture yet");
+                        return Ok(());
                     }
+                }
+            };
+            let lines = content.lines().count();
+            let name = terminal.buffers_mut().save(name, content);
+            terminal.notify(Severity::Success, format!("Captured {} lines into {}", lines, name));
+        }
+        Some(action @ ("paste" | "show")) => match terminal.buffers().get(name).map(|b| b.content.clone()) {
+            Some(content) if action == "paste" => ui.insert_text(&content),
+            Some(content) => terminal.add_output(&content),
+            None => terminal.notify(Severity::Warning, format!("No buffer {}", name.unwrap_or("saved yet"))),
+        },
+        Some("write") => match args {
+            [name, file] => {
+                terminal.check_restricted("Writing a capture to a file")?;
+                let path = shellexpand::tilde(file).into_owned();
+                let bytes = terminal.buffers().write(name, Path::new(&path))?;
+                terminal.notify(Severity::Success, format!("Wrote {} bytes to {}", bytes, path));
+            }
+            _ => terminal.notify(Severity::Info, "Usage: capture write <name> <file>"),
+        },
+        Some("delete") => match name {
+            Some(name) if terminal.buffers_mut().delete(name) => {
+                terminal.notify(Severity::Info, format!("Deleted buffer {}", name))
+            }
+            _ => terminal.notify(Severity::Warning, format!("No buffer {}", name.unwrap_or(""))),
+        },
+        _ => {
+            let listing = terminal.buffers().list();
+            if listing.is_empty() {
+                terminal.notify(Severity::Info, "No buffers — run `capture save <name>`");
+            } else {
+                terminal.add_output(&listing.join("\n"));
+            }
+        }
+    }
+    Ok(())
+}
+
+// `filter <pipeline>` pipes the scrollback through a shell pipeline and shows
+// the result in a temporary view; `filter <preset>` runs a saved pipeline,
+// `filter save <name> [pipeline]` saves one (by default the last run),
+// `filter delete <name>` drops one and a bare `filter` lists them
+fn filter(terminal: &mut Terminal, rest: &str) -> Result<()> {
+    let mut words = rest.split_whitespace();
+    match (words.next(), words.next()) {
+        (None, _) | (Some("list"), None) => {
+            let presets = terminal.filters().list();
+            if presets.is_empty() {
+                terminal.notify(Severity::Info, "No filter presets — run `filter save <name> <pipeline>`");
+            } else {
+                let lines: Vec<String> = presets
+                    .iter()
+                    .map(|(name, pipeline)| format!("{:<16} {}", name, pipeline))
+                    .collect();
+                terminal.add_output(&lines.join("\n"));
+            }
+        }
+        (Some("save"), Some(name)) => {
+            let given = rest["save".len()..].trim_start()[name.len()..].trim();
+            let pipeline = match given {
+                "" => terminal
+                    .last_filter()
+                    .context("No filter run yet — use `filter save <name> <pipeline>`")?
+                    .to_string(),
+                given => given.to_string(),
+            };
+            terminal.filters().save(name, &pipeline)?;
+            terminal.notify(Severity::Success, format!("Saved filter {}: {}", name, pipeline));
+        }
+        (Some("delete"), Some(name)) => {
+            terminal.filters().delete(name)?;
+            terminal.notify(Severity::Info, format!("Deleted filter {}", name));
+        }
+        _ => {
+            let pipeline = terminal.filters().get(rest).unwrap_or_else(|| rest.to_string());
+            terminal.filter(&pipeline)?;
+        }
+    }
+    Ok(())
+}
 
-                    _ => {}
+// `on` / `off` set a mode explicitly, no argument flips it
+fn toggle(arg: Option<&str>, current: bool) -> bool {
+    match arg {
+        Some("on") => true,
+        Some("off") => false,
+        _ => !current,
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled { "on" } else { "off" }
+}
+
+fn binary_id(text: &str) -> Result<usize> {
+    text.trim_start_matches('#').parse().map_err(|_| anyhow::anyhow!("Not a binary output number: {}", text))
+}
+
+fn show_binary(terminal: &Terminal, ui: &mut impl Frontend, id: Option<usize>) -> Result<()> {
+    let output = terminal.binary_output(id).context("No binary output to show")?;
+    let output = output.lock().map_err(|_| anyhow::anyhow!("Binary output unavailable"))?;
+    ui.show(Panel::Hex(&output))?;
+    Ok(())
+}
+
+fn start_logging(terminal: &mut Terminal, path: Option<&str>) -> Result<()> {
+    let path = match path {
+        Some(path) => PathBuf::from(shellexpand::tilde(path).into_owned()),
+        None => tee::default_log_path(clock::unix_secs()),
+    };
+    terminal.start_log(&path)?;
+    terminal.notify(Severity::Info, format!("Logging output to {}", path.display()));
+    Ok(())
+}
+
+fn stop_logging(terminal: &mut Terminal) {
+    match terminal.stop_log() {
+        Some(path) => terminal.notify(Severity::Success, format!("Output logged to {}", path.display())),
+        None => terminal.notify(Severity::Info, "Output isn't being logged"),
+    }
+}
+
+fn toggle_logging(terminal: &mut Terminal) -> Result<()> {
+    if terminal.is_logging() {
+        stop_logging(terminal);
+        return Ok(());
+    }
+    start_logging(terminal, None)
+}
+
+// A builtin, or else to the shell: in the background (through auto-wiring if
+// available), unless a prod-labeled tab holds it for confirmation
+async fn run_command_line(terminal: &mut Terminal, ui: &mut impl Frontend, command: &str) {
+    match run_builtin(terminal, ui, command).await {
+        Ok(false) if !command.is_empty() => submit_guarded(terminal, ui, vec![command.to_string()]).await,
+        Ok(_) => {}
+        // A failed builtin is reported, not fatal
+        Err(e) => terminal.notify_error(&e),
+    }
+}
+
+// Runs `commands` like typed ones: session variables expanded first, so the
+// guard (and its dialog) sees what will actually run
+async fn submit_guarded(terminal: &mut Terminal, ui: &mut impl Frontend, commands: Vec<String>) {
+    if commands.is_empty() {
+        return;
+    }
+    let commands = commands.iter().map(|command| terminal.variables().expand(command)).collect();
+    if let Some(commands) = ui.guard(commands) {
+        for command in &commands {
+            terminal.submit_command(command).await;
+        }
+    }
+}
+
+// From the palette or a bound key; services answer in the autowire panel
+async fn run_action(terminal: &mut Terminal, ui: &mut impl Frontend, action: Action) {
+    match action.target {
+        Target::Command(_) if !action.inputs.is_empty() => ui.open_action_form(action),
+        Target::Command(command) => run_command_line(terminal, ui, &command).await,
+        Target::Service(name) => {
+            ui.show(Panel::AutowireServices).ok();
+            match terminal.invoke_autowire_service(&name) {
+                Ok(text) => ui.set_autowire_detail(text),
+                Err(e) => ui.set_autowire_detail(format!("Invoke failed: {}", e)),
+            }
+        }
+    }
+}
+
+// An approved `ai do` card: files are read or patched in the project, commands
+// run like typed ones (so a prod-labeled tab still asks)
+async fn approve_action(terminal: &mut Terminal, ui: &mut impl Frontend, index: usize) {
+    let Some(proposal) = ui.proposed_action(index) else {
+        return;
+    };
+    let result = match &proposal {
+        Proposal::RunCommand { command, .. } => {
+            submit_guarded(terminal, ui, vec![command.clone()]).await;
+            Ok(proposals::submitted(command))
+        }
+        Proposal::EditFile { path, .. } => Workspace::discover()
+            .and_then(|workspace| workspace.resolve(path))
+            .and_then(|resolved| edit_wait(terminal, ui, &resolved))
+            .map(|result| proposals::edited(path, &result)),
+        _ => Workspace::discover().and_then(|workspace| workspace.apply(&proposal)),
+    };
+    ui.resolve_action(index, result);
+}
+
+// `nexterm <subcommand> ...`: one-shot commands that run without the TUI
+async fn run_subcommand(args: &Args) -> Result<()> {
+    let words: Vec<&str> = args.command.iter().map(String::as_str).collect();
+    match words[..] {
+        // Runs before Config::load so it can diagnose a broken config
+        ["doctor"] => run_doctor(args.fix),
+        ["status"] => run_status(args),
+        ["sync", ref rest @ ..] => run_sync(rest, &Config::load()?).await,
+        ["export-profile", bundle] => {
+            let files = profile::export(Path::new(bundle))?;
+            println!("Exported {} to {}", files.join(", "), bundle);
+            Ok(())
+        }
+        ["import-profile", bundle] => {
+            let files = profile::import(Path::new(bundle))?;
+            println!("Imported {} (replaced files kept as .bak)", files.join(", "));
+            Ok(())
+        }
+        ["report"] => run_report(None),
+        ["report", bundle] => run_report(Some(Path::new(bundle))),
+        _ => anyhow::bail!("Unknown command: {}", args.command.join(" ")),
+    }
+}
+
+fn run_doctor(fix: bool) -> Result<()> {
+    let mut diagnoses = doctor::run_checks();
+    if fix && diagnoses.iter().any(|d| d.fix.is_some()) {
+        for diagnosis in diagnoses.iter().filter(|d| d.status != doctor::Status::Ok) {
+            if let Some(apply) = diagnosis.fix {
+                match apply() {
+                    Ok(()) => println!("🔧 fixed {}", diagnosis.check),
+                    Err(e) => println!("🔧 could not fix {}: {}", diagnosis.check, e),
                 }
             }
         }
+        diagnoses = doctor::run_checks();
+    }
 
-        // Update terminal output
-        terminal.update_output()?;
+    for diagnosis in &diagnoses {
+        println!("{} {}: {}", diagnosis.symbol(), diagnosis.check, diagnosis.detail);
+        if let Some(advice) = &diagnosis.advice {
+            let auto = if diagnosis.fix.is_some() && !fix { " (--fix can do this)" } else { "" };
+            println!("    → {}{}", advice, auto);
+        }
+    }
+
+    let failed = diagnoses.iter().filter(|d| d.status == doctor::Status::Fail).count();
+    if failed > 0 {
+        anyhow::bail!("{} check(s) failed", failed);
+    }
+    Ok(())
+}
+
+// Shows everything that would go into the bundle and writes it only once confirmed
+fn run_report(bundle: Option<&Path>) -> Result<()> {
+    let sections = report::collect();
+    for section in &sections {
+        println!("──── {} ────", section.name);
+        println!("{}", section.content.trim_end());
+        println!();
+    }
+    let bundle = bundle
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from(format!("nexterm-report-{}.tar.gz", clock::unix_secs())));
+    print!("Review the above; write it to {}? [y/N] ", bundle.display());
+    io::Write::flush(&mut io::stdout())?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        println!("Nothing written");
+        return Ok(());
+    }
+    report::write(&sections, &bundle)?;
+    println!("Wrote {}; attach it to the GitHub issue", bundle.display());
+    Ok(())
+}
+
+// Auto-wiring and plugins get this long to come up before `nexterm status` reports
+const STATUS_STARTUP_WAIT: std::time::Duration = std::time::Duration::from_secs(5);
+
+fn run_status(args: &Args) -> Result<()> {
+    let mut terminal = Terminal::new(Config::load()?)?;
+    terminal.enable_autowire_integration()?;
+    terminal.wait_for_startup(STATUS_STARTUP_WAIT);
+    let report = StatusReport::collect(&terminal, Vec::new());
+    match &args.json {
+        Some(target) => JsonSink::open(target)?.emit(&report)?,
+        None => {
+            for line in report.render(args.verbose) {
+                println!("{}", line);
+            }
+        }
     }
+    Ok(())
+}
 
+async fn run_sync(words: &[&str], config: &Config) -> Result<()> {
+    match words {
+        ["key"] => println!("{}", sync::export_key()?),
+        ["key", "import", key] => {
+            sync::import_key(key)?;
+            println!("Sync key imported");
+        }
+        _ => {
+            let mut client = SyncClient::new(&config.sync)?;
+            match words {
+                [] => {
+                    let report = client.sync(&config.storage).await?;
+                    println!("Pushed {} and pulled {} history entries", report.pushed, report.pulled);
+                }
+                ["status"] => match client.device_name() {
+                    Some(name) => println!("Device '{}', {} entries pushed", name, client.pushed()),
+                    None => println!("Not registered; run `nexterm sync register <name>`"),
+                },
+                ["register", name] => {
+                    let id = client.register(name).await?;
+                    println!("Registered '{}' as device {}", name, id);
+                }
+                ["devices"] => {
+                    for device in client.devices().await? {
+                        println!("{}  {}", device.id, device.name);
+                    }
+                }
+                ["remove", id] => {
+                    client.remove_device(id).await?;
+                    println!("Removed device {}", id);
+                }
+                _ => anyhow::bail!(
+                    "Usage: nexterm sync [status | register <name> | devices | remove <id> | key [import <key>]]"
+                ),
+            }
+        }
+    }
     Ok(())
 }
 
 fn parse_args() -> Args {
     // Simple argument parsing
     // In production, use clap or structopt
+    let argv: Vec<String> = std::env::args().collect();
+    let flag_value = |flag: &str| {
+        argv.iter()
+            .position(|a| a == flag)
+            .and_then(|i| argv.get(i + 1))
+            .cloned()
+    };
+
     Args {
-        shell: std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string()),
-        config: None,
-        ai_enabled: std::env::var("NEXTERM_AI").is_ok(),
-        autowire_enabled: std::env::var("NEXTERM_AUTOWIRE").unwrap_or_else(|_| "true".to_string()) == "true",
+        // --json <file> or --json unix:<socket>
+        json: flag_value("--json").map(|spec| JsonTarget::parse(&spec)),
+        // --trace-out <file.json>, with the profiling feature
+        trace_out: flag_value("--trace-out").map(PathBuf::from),
+        command: positional(&argv[1..]),
+        fix: argv.iter().any(|a| a == "--fix"),
+        verbose: argv.iter().any(|a| a == "--verbose"),
     }
 }
 
 #[derive(Debug)]
 struct Args {
-    shell: String,
-    config: Option<String>,
-    ai_enabled: bool,
-    autowire_enabled: bool,
+    json: Option<JsonTarget>,
+    trace_out: Option<PathBuf>,
+    // Subcommand and its arguments, e.g. `sync register laptop`
+    command: Vec<String>,
+    // `doctor --fix`: apply the automatable fixes
+    fix: bool,
+    // `status --verbose`: list what's in each section
+    verbose: bool,
+}
+
+// Non-flag arguments, skipping the values of flags that take one
+fn positional(argv: &[String]) -> Vec<String> {
+    const VALUE_FLAGS: &[&str] = &["--json", "--trace-out"];
+    let mut words = Vec::new();
+    let mut args = argv.iter();
+    while let Some(arg) = args.next() {
+        if VALUE_FLAGS.contains(&arg.as_str()) {
+            args.next();
+        } else if !arg.starts_with('-') {
+            words.push(arg.clone());
+        }
+    }
+    words
 }
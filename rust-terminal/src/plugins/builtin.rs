@@ -1,3 +0,0 @@
-// Built-in plugins
-pub mod git;
-pub mod docker;
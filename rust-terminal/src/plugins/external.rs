@@ -0,0 +1,326 @@
+// Executable plugins declared by a manifest, plugins/<name>/plugin.toml:
+//
+//   name = "jira"
+//   description = "Look up tickets"
+//   executable = "jira-plugin"        # relative to the plugin's directory
+//   commands = ["jira"]
+//   events = ["command_post"]         # command_pre, command_post, output
+//
+//   [[actions]]                       # command palette entries (optional)
+//   id = "open"                       # plugin.jira.open
+//   title = "Open ticket in browser"
+//   command = "jira open"
+//   icon = "🎫"
+//   keywords = ["issue"]
+//   key = "Alt+J"
+//
+//   [[actions]]                       # asks in a form first, see core::actions
+//   id = "assign"
+//   title = "Assign ticket"
+//   command = "jira assign {ticket} {to} {watch}"
+//   inputs = [
+//     { name = "ticket", label = "Ticket" },
+//     { name = "to", kind = "select", options = ["me", "triage"] },
+//     { name = "watch", kind = "checkbox", flag = "--watch" },
+//   ]
+//
+// The executable starts when the plugin loads and reads one JSON object per
+// line on stdin: the events it asked for ({"event": "command_pre", "command"},
+// {"event": "command_post", "command", "exit_code", "duration_ms"},
+// {"event": "output", "lines"}) and its commands ({"event": "command",
+// "args": [...]}), which it answers with one line, {"output": "..."} or
+// {"error": "..."}.
+//
+// Work it does in the background shows in the status bar and `activity`
+// from progress lines it writes at any time:
+//   {"progress": "start", "task": "sync", "title": "Syncing tickets", "total": 40}
+//   {"progress": "advance", "task": "sync", "label": "PROJ-12"}
+//   {"progress": "finish", "task": "sync"}
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::core::actions::{Action, Input, KeyChord, Target};
+use crate::core::operations::{Kind, Operation, Operations};
+use crate::core::terminal::CommandResult;
+use crate::plugins::manager::{Plugin, Reply};
+
+// A plugin command that takes longer than this fails
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+const EVENTS: &[&str] = &["command_pre", "command_post", "output"];
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    name: String,
+    #[serde(default)]
+    description: String,
+    executable: PathBuf,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    commands: Vec<String>,
+    #[serde(default)]
+    events: Vec<String>,
+    #[serde(default)]
+    actions: Vec<ManifestAction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestAction {
+    id: String,
+    title: String,
+    command: String,
+    #[serde(default)]
+    icon: Option<String>,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default)]
+    key: Option<String>,
+    #[serde(default)]
+    inputs: Vec<Input>,
+}
+
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    // Shared with the command waiting for its answer
+    replies: Arc<Mutex<Receiver<String>>>,
+}
+
+pub struct ExecutablePlugin {
+    manifest: Manifest,
+    dir: PathBuf,
+    // Behind a lock because commands run through `&self`
+    process: Mutex<Option<Process>>,
+    // Where its progress lines go
+    operations: Operations,
+}
+
+// Each directory in `dir` with a plugin.toml, by directory name
+pub fn discover(dir: &Path, operations: &Operations) -> Vec<(String, Result<ExecutablePlugin>)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut found: Vec<(String, Result<ExecutablePlugin>)> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.join("plugin.toml").is_file())
+        .map(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            (name, ExecutablePlugin::open(&path, operations.clone()))
+        })
+        .collect();
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    found
+}
+
+impl ExecutablePlugin {
+    fn open(dir: &Path, operations: Operations) -> Result<Self> {
+        let path = dir.join("plugin.toml");
+        let text = fs::read_to_string(&path)?;
+        let manifest: Manifest = toml::from_str(&text).with_context(|| format!("invalid {}", path.display()))?;
+        if let Some(event) = manifest.events.iter().find(|e| !EVENTS.contains(&e.as_str())) {
+            bail!("unknown event {} in {}", event, path.display());
+        }
+        for action in &manifest.actions {
+            if let Some(key) = &action.key {
+                KeyChord::parse(key).with_context(|| format!("action {} in {}", action.id, path.display()))?;
+            }
+        }
+        Ok(Self {
+            manifest,
+            dir: dir.to_path_buf(),
+            process: Mutex::new(None),
+            operations,
+        })
+    }
+
+    pub fn source(&self) -> String {
+        self.dir.display().to_string()
+    }
+
+    fn wants(&self, event: &str) -> bool {
+        self.manifest.events.iter().any(|e| e == event)
+    }
+
+    // Events are fire-and-forget; a plugin that went away just stops getting them
+    fn send(&self, message: &Value) -> Result<()> {
+        let mut process = self.process.lock().map_err(|_| anyhow::anyhow!("plugin lock poisoned"))?;
+        let Some(process) = process.as_mut() else {
+            bail!("plugin is not running");
+        };
+        writeln!(process.stdin, "{}", message).context("plugin exited")?;
+        process.stdin.flush().context("plugin exited")
+    }
+
+    // Sends a command; its answer is the next line on the returned channel
+    fn start(&self, args: &[String]) -> Result<Arc<Mutex<Receiver<String>>>> {
+        let replies = {
+            let process = self.process.lock().map_err(|_| anyhow::anyhow!("plugin lock poisoned"))?;
+            process.as_ref().context("plugin is not running")?.replies.clone()
+        };
+        // Stray lines from before this command aren't its reply
+        if let Ok(replies) = replies.lock() {
+            replies.try_iter().for_each(drop);
+        }
+        self.send(&json!({ "event": "command", "args": args }))?;
+        Ok(replies)
+    }
+
+    fn stop(&mut self) {
+        if let Ok(Some(mut process)) = self.process.get_mut().map(Option::take) {
+            let _ = process.child.kill();
+            let _ = process.child.wait();
+        }
+    }
+}
+
+fn report_progress(plugin: &str, operations: &Operations, tasks: &mut HashMap<String, Operation>, message: &Value) {
+    let field = |key: &str| message.get(key).and_then(Value::as_str).unwrap_or_default();
+    let task = field("task").to_string();
+    match field("progress") {
+        "start" => {
+            let title = match field("title") {
+                "" => format!("{}: {}", plugin, task),
+                title => format!("{}: {}", plugin, title),
+            };
+            let operation = operations.start(Kind::Plugin, title);
+            if let Some(total) = message.get("total").and_then(Value::as_u64) {
+                operation.progress().set_total(total);
+            }
+            tasks.insert(task, operation);
+        }
+        "advance" => {
+            if let Some(operation) = tasks.get(&task) {
+                operation.progress().advance(field("label"));
+            }
+        }
+        "finish" => {
+            if let Some(operation) = tasks.remove(&task) {
+                operation.finish();
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Plugin for ExecutablePlugin {
+    fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    fn description(&self) -> &str {
+        &self.manifest.description
+    }
+
+    fn commands(&self) -> Vec<String> {
+        self.manifest.commands.clone()
+    }
+
+    fn actions(&self) -> Vec<Action> {
+        self.manifest
+            .actions
+            .iter()
+            .map(|action| {
+                let id = format!("plugin.{}.{}", self.manifest.name, action.id);
+                Action::new(&id, &action.title, Target::Command(action.command.clone()))
+                    .with_icon(action.icon.as_deref().unwrap_or("🔌"))
+                    .with_keywords(&action.keywords)
+                    .with_keywords(&[self.manifest.name.as_str()])
+                    .with_key(action.key.as_deref().and_then(|key| KeyChord::parse(key).ok()))
+                    .with_inputs(action.inputs.clone())
+            })
+            .collect()
+    }
+
+    fn on_load(&mut self) -> Result<()> {
+        let executable = self.dir.join(&self.manifest.executable);
+        let mut child = Command::new(&executable)
+            .args(&self.manifest.args)
+            .env("NEXTERM_PLUGIN_DIR", &self.dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("failed to start {}", executable.display()))?;
+        let stdin = child.stdin.take().context("plugin has no stdin")?;
+        let stdout = child.stdout.take().context("plugin has no stdout")?;
+
+        let (tx, replies) = mpsc::channel();
+        let (name, operations) = (self.manifest.name.clone(), self.operations.clone());
+        std::thread::spawn(move || {
+            // Finished when the plugin exits, if it didn't say so
+            let mut tasks: HashMap<String, Operation> = HashMap::new();
+            for line in BufReader::new(stdout).lines().map_while(|l| l.ok()) {
+                match serde_json::from_str::<Value>(&line) {
+                    Ok(message) if message.get("progress").is_some() => {
+                        report_progress(&name, &operations, &mut tasks, &message);
+                    }
+                    _ => {
+                        if tx.send(line).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+        *self.process.get_mut().map_err(|_| anyhow::anyhow!("plugin lock poisoned"))? =
+            Some(Process { child, stdin, replies: Arc::new(Mutex::new(replies)) });
+        Ok(())
+    }
+
+    fn on_unload(&mut self) {
+        self.stop();
+    }
+
+    fn execute(&self, args: &[String]) -> Reply {
+        let replies = self.start(args);
+        Box::new(move || {
+            let replies = replies?;
+            let replies = replies.lock().map_err(|_| anyhow::anyhow!("plugin lock poisoned"))?;
+            let line = replies.recv_timeout(COMMAND_TIMEOUT).context("plugin did not answer")?;
+            let reply: Value = serde_json::from_str(&line).context("plugin sent an invalid reply")?;
+            if let Some(error) = reply.get("error").and_then(Value::as_str) {
+                bail!("{}", error);
+            }
+            Ok(reply.get("output").and_then(Value::as_str).unwrap_or_default().to_string())
+        })
+    }
+
+    fn on_command_pre(&mut self, command: &str) {
+        if self.wants("command_pre") {
+            let _ = self.send(&json!({ "event": "command_pre", "command": command }));
+        }
+    }
+
+    fn on_command_post(&mut self, result: &CommandResult) {
+        if self.wants("command_post") {
+            let _ = self.send(&json!({
+                "event": "command_post",
+                "command": result.command,
+                "exit_code": result.exit_code,
+                "duration_ms": result.duration_ms,
+            }));
+        }
+    }
+
+    fn on_output(&mut self, lines: &[String]) {
+        if self.wants("output") {
+            let _ = self.send(&json!({ "event": "output", "lines": lines }));
+        }
+    }
+}
+
+impl Drop for ExecutablePlugin {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
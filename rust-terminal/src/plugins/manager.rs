@@ -1,23 +1,306 @@
 // Plugin manager
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::warn;
 
-pub struct PluginManager {
-    plugins: Vec<Box<dyn Plugin>>,
+use crate::core::actions::Action;
+use crate::core::command::ParsedCommand;
+use crate::core::operations::{Kind, Operations};
+use crate::core::terminal::CommandResult;
+use crate::plugins::external;
+use crate::utils::config::Config;
+use crate::utils::supervisor::Supervisor;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginsConfig {
+    // Discovered plugins that are not loaded
+    #[serde(default)]
+    pub disabled: Vec<String>,
 }
 
-pub trait Plugin {
+// Waits for a plugin command's answer; it runs off the UI thread
+pub type Reply = Box<dyn FnOnce() -> Result<String> + Send>;
+
+pub trait Plugin: Send {
     fn name(&self) -> &str;
-    fn execute(&self, args: &[String]) -> Result<String>;
+    // Starts one of `commands()`; `args[0]` is the command name
+    fn execute(&self, args: &[String]) -> Reply;
+
+    fn description(&self) -> &str {
+        ""
+    }
+
+    // Command names dispatched to this plugin instead of the shell
+    fn commands(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    // Command palette entries, see core::actions
+    fn actions(&self) -> Vec<Action> {
+        Vec::new()
+    }
+
+    // Called once when the plugin is enabled; an error leaves it unloaded
+    fn on_load(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    // Called when the plugin is disabled, to let go of what `on_load` started
+    fn on_unload(&mut self) {}
+
+    fn on_command_pre(&mut self, _command: &str) {}
+
+    fn on_command_post(&mut self, _result: &CommandResult) {}
+
+    // New output lines, as they appear in the output pane
+    fn on_output(&mut self, _lines: &[String]) {}
+}
+
+// What `plugins list` and the plugins panel show
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+    pub name: String,
+    pub description: String,
+    pub commands: Vec<String>,
+    pub source: String,
+    pub enabled: bool,
+    // Why it failed to load, or why its manifest was rejected
+    pub error: Option<String>,
+}
+
+struct Entry {
+    plugin: Box<dyn Plugin>,
+    source: String,
+    enabled: bool,
+    loaded: bool,
+    error: Option<String>,
+    // Catches the plugin's panics; it's disabled after repeated ones
+    supervisor: Supervisor,
+}
+
+impl Entry {
+    fn supervise<T>(&mut self, f: impl FnOnce(&mut dyn Plugin) -> T) -> Option<T> {
+        let plugin = self.plugin.as_mut();
+        let result = self.supervisor.run(|| f(plugin));
+        if self.supervisor.is_disabled() {
+            self.enabled = false;
+            self.error = Some("disabled after repeated panics".to_string());
+        }
+        result
+    }
+}
+
+#[derive(Default)]
+pub struct PluginManager {
+    plugins: Vec<Entry>,
+    // Plugin directories whose manifest could not be read: (name, error)
+    broken: Vec<(String, String)>,
 }
 
 impl PluginManager {
     pub fn new() -> Self {
-        Self {
-            plugins: Vec::new(),
+        Self::default()
+    }
+
+    // Every plugin under the plugins directory, loaded unless disabled in the config
+    // Plugins report background work to `operations`; loading them is one
+    pub fn discover(config: &PluginsConfig, operations: &Operations) -> Self {
+        let mut manager = Self::new();
+        let indexing = operations.start(Kind::Indexing, "Indexing plugins");
+        let found = external::discover(&Self::dir(), operations);
+        indexing.progress().set_total(found.len() as u64);
+        for (name, found) in found {
+            indexing.progress().advance(&name);
+            match found {
+                Ok(plugin) => {
+                    let source = plugin.source();
+                    let enabled = !config.disabled.iter().any(|d| d == plugin.name());
+                    manager.add(Box::new(plugin), source, enabled);
+                }
+                Err(e) => {
+                    warn!("Plugin {} not loaded: {:#}", name, e);
+                    manager.broken.push((name, format!("{:#}", e)));
+                }
+            }
         }
+        indexing.finish();
+        manager
+    }
+
+    pub fn dir() -> PathBuf {
+        Config::dir().join("plugins")
     }
 
-    pub fn register(&mut self, plugin: Box<dyn Plugin>) {
-        self.plugins.push(plugin);
+    fn add(&mut self, plugin: Box<dyn Plugin>, source: String, enabled: bool) {
+        let supervisor = Supervisor::new(format!("Plugin {}", plugin.name()));
+        let mut entry = Entry { plugin, source, enabled, loaded: false, error: None, supervisor };
+        if enabled {
+            load(&mut entry);
+        }
+        self.plugins.push(entry);
+    }
+
+    // Returns false for unknown plugins
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let Some(entry) = self.plugins.iter_mut().find(|e| e.plugin.name() == name) else {
+            return false;
+        };
+        entry.enabled = enabled;
+        if enabled {
+            entry.supervisor.reset();
+            entry.error = None;
+        }
+        if enabled && !entry.loaded {
+            load(entry);
+        } else if !enabled && entry.loaded {
+            entry.supervise(|plugin| plugin.on_unload());
+            entry.loaded = false;
+        }
+        true
+    }
+
+    pub fn list(&self) -> Vec<PluginInfo> {
+        let loaded = self.plugins.iter().map(|e| PluginInfo {
+            name: e.plugin.name().to_string(),
+            description: e.plugin.description().to_string(),
+            commands: e.plugin.commands(),
+            source: e.source.clone(),
+            enabled: e.enabled,
+            error: e.error.clone(),
+        });
+        let broken = self.broken.iter().map(|(name, error)| PluginInfo {
+            name: name.clone(),
+            description: String::new(),
+            commands: Vec::new(),
+            source: Self::dir().join(name).display().to_string(),
+            enabled: false,
+            error: Some(error.clone()),
+        });
+        loaded.chain(broken).collect()
+    }
+
+    // Starts `command` if an active plugin provides it; None falls through to the shell
+    pub fn run_command(&mut self, command: &ParsedCommand) -> Option<Reply> {
+        let entry = self.active().find(|e| e.plugin.commands().contains(&command.command))?;
+        let args: Vec<String> = std::iter::once(command.command.clone())
+            .chain(command.args.iter().cloned())
+            .collect();
+        let name = entry.plugin.name().to_string();
+        let reply = entry.supervise(|plugin| plugin.execute(&args));
+        Some(Box::new(move || {
+            let reply = reply.ok_or_else(|| anyhow!("panicked")).and_then(|reply| reply());
+            reply.with_context(|| format!("plugin {}", name))
+        }))
+    }
+
+    // What the active plugins add to the command palette
+    pub fn actions(&mut self) -> Vec<Action> {
+        self.active().flat_map(|entry| entry.plugin.actions()).collect()
+    }
+
+    pub fn command_pre(&mut self, command: &str) {
+        for entry in self.active() {
+            entry.supervise(|plugin| plugin.on_command_pre(command));
+        }
+    }
+
+    pub fn command_post(&mut self, result: &CommandResult) {
+        for entry in self.active() {
+            entry.supervise(|plugin| plugin.on_command_post(result));
+        }
+    }
+
+    pub fn output(&mut self, lines: &[String]) {
+        for entry in self.active() {
+            entry.supervise(|plugin| plugin.on_output(lines));
+        }
+    }
+
+    fn active(&mut self) -> impl Iterator<Item = &mut Entry> {
+        self.plugins.iter_mut().filter(|e| e.enabled && e.loaded)
+    }
+}
+
+fn load(entry: &mut Entry) {
+    match entry.supervise(|plugin| plugin.on_load()).unwrap_or_else(|| Err(anyhow!("panicked"))) {
+        Ok(()) => {
+            entry.loaded = true;
+            entry.error = None;
+        }
+        Err(e) => {
+            warn!("Plugin {} failed to load: {:#}", entry.plugin.name(), e);
+            entry.error = Some(format!("{:#}", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::command::CommandParser;
+
+    struct Shout;
+
+    impl Plugin for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        fn commands(&self) -> Vec<String> {
+            vec!["shout".to_string()]
+        }
+
+        fn execute(&self, args: &[String]) -> Reply {
+            let shouted = args[1..].join(" ").to_uppercase();
+            Box::new(move || Ok(shouted))
+        }
+    }
+
+    #[test]
+    fn dispatches_to_enabled_plugins() {
+        let parser = CommandParser::new();
+        let mut manager = PluginManager::new();
+        manager.add(Box::new(Shout), "test".to_string(), true);
+
+        let reply = manager.run_command(&parser.parse("shout hi there").unwrap());
+        assert_eq!(reply.unwrap()().unwrap(), "HI THERE");
+        assert!(manager.run_command(&parser.parse("ls -l").unwrap()).is_none());
+
+        assert!(manager.set_enabled("shout", false));
+        assert!(manager.run_command(&parser.parse("shout hi").unwrap()).is_none());
+        assert!(!manager.set_enabled("missing", true));
+    }
+
+    struct Fragile;
+
+    impl Plugin for Fragile {
+        fn name(&self) -> &str {
+            "fragile"
+        }
+
+        fn execute(&self, _args: &[String]) -> Reply {
+            Box::new(|| Ok(String::new()))
+        }
+
+        fn on_output(&mut self, _lines: &[String]) {
+            panic!("index out of bounds");
+        }
+    }
+
+    #[test]
+    fn disables_plugins_that_keep_panicking() {
+        let mut manager = PluginManager::new();
+        manager.add(Box::new(Fragile), "test".to_string(), true);
+        for _ in 0..5 {
+            manager.output(&["line".to_string()]);
+        }
+        let info = &manager.list()[0];
+        assert!(!info.enabled);
+        assert_eq!(info.error.as_deref(), Some("disabled after repeated panics"));
+
+        // Turning it back on gives it another chance
+        manager.set_enabled("fragile", true);
+        assert!(manager.list()[0].enabled);
     }
 }
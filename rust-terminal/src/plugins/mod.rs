@@ -1,3 +1,3 @@
 // Plugins module
 pub mod manager;
-pub mod builtin;
+pub mod external;
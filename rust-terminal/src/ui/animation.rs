@@ -0,0 +1,68 @@
+// Time-based animations: frames are derived from elapsed time, not from how
+// often the UI happens to redraw, so speed is independent of the frame rate
+use std::time::{Duration, Instant};
+
+use crate::utils::clock;
+
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_INTERVAL: Duration = Duration::from_millis(80);
+const TRANSITION_DURATION: Duration = Duration::from_millis(150);
+
+// How often the event loop should wake up while something is animating
+pub const TICK_RATE: Duration = Duration::from_millis(50);
+
+pub struct Animator {
+    enabled: bool,
+    transition_start: Option<Instant>,
+}
+
+impl Animator {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            transition_start: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // Spinner frame for something that started at `since`; static when motion is off
+    pub fn spinner(&self, since: Instant) -> &'static str {
+        if !self.enabled {
+            return "…";
+        }
+        let elapsed = clock::since(since).as_millis() / SPINNER_INTERVAL.as_millis();
+        SPINNER_FRAMES[elapsed as usize % SPINNER_FRAMES.len()]
+    }
+
+    pub fn start_transition(&mut self) {
+        if self.enabled {
+            self.transition_start = Some(clock::now());
+        }
+    }
+
+    // Progress of the current transition in 0.0..=1.0, or None once it has finished
+    pub fn transition(&self) -> Option<f32> {
+        let start = self.transition_start?;
+        let t = clock::since(start).as_secs_f32() / TRANSITION_DURATION.as_secs_f32();
+        (t < 1.0).then_some(t)
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.transition().is_some()
+    }
+}
+
+// Fixed-width text progress bar, e.g. `[█████░░░░░] 50%`
+pub fn progress_bar(ratio: f64, width: usize) -> String {
+    let ratio = ratio.clamp(0.0, 1.0);
+    let filled = (ratio * width as f64).round() as usize;
+    format!(
+        "[{}{}] {:>3}%",
+        "█".repeat(filled),
+        "░".repeat(width - filled),
+        (ratio * 100.0).round() as u32
+    )
+}
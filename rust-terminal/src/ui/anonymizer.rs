@@ -0,0 +1,68 @@
+// Demo mode: masks identifying details in rendered output
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DemoConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    // Extra regexes (API keys, internal domains, ...) replaced with <secret>
+    #[serde(default)]
+    pub secret_patterns: Vec<String>,
+}
+
+pub struct Anonymizer {
+    rules: Vec<(Regex, &'static str)>,
+}
+
+impl Anonymizer {
+    pub fn new(config: &DemoConfig) -> Self {
+        let mut rules = Vec::new();
+
+        // Configured secrets go first so a partial IP/user match can't split them
+        for pattern in &config.secret_patterns {
+            match Regex::new(pattern) {
+                Ok(re) => rules.push((re, "<secret>")),
+                Err(e) => warn!("Ignoring invalid demo secret pattern {:?}: {}", pattern, e),
+            }
+        }
+
+        rules.push((
+            Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b").expect("valid IPv4 pattern"),
+            "<ip>",
+        ));
+        rules.push((
+            Regex::new(r"\b(?:[0-9a-fA-F]{1,4}:){2,7}[0-9a-fA-F]{1,4}\b").expect("valid IPv6 pattern"),
+            "<ip>",
+        ));
+
+        if let Some(host) = hostname().filter(|h| h.len() > 1) {
+            rules.push((literal(&host), "<host>"));
+        }
+        if let Some(user) = std::env::var("USER").ok().filter(|u| u.len() > 1) {
+            rules.push((literal(&user), "<user>"));
+        }
+
+        Self { rules }
+    }
+
+    pub fn apply(&self, line: &str) -> String {
+        self.rules
+            .iter()
+            .fold(line.to_string(), |text, (re, placeholder)| {
+                re.replace_all(&text, *placeholder).into_owned()
+            })
+    }
+}
+
+fn literal(word: &str) -> Regex {
+    Regex::new(&format!(r"\b{}\b", regex::escape(word))).expect("escaped literal is valid")
+}
+
+fn hostname() -> Option<String> {
+    std::fs::read_to_string("/etc/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|h| h.trim().to_string())
+}
@@ -0,0 +1,176 @@
+// Compatibility profile for dumb terminals and slow links: ASCII borders and
+// symbols, the 16 ANSI colors, no emoji and no animations. Frames are drawn
+// as usual and then degraded cell by cell, so no widget has to know about it.
+// Configured as
+//
+//   compat = "auto"          # auto (default), on or off
+//
+// `auto` turns it on for terminals without Unicode or colors to speak of
+// (TERM unset, dumb, vt100, linux, ...) and over SSH once frames keep taking
+// long to get through; `compat on|off` switches at runtime.
+use ratatui::buffer::Buffer;
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+use unicode_width::UnicodeWidthStr;
+
+// TERM values of terminals that get the profile under `auto`
+const DUMB_TERMS: &[&str] = &["dumb", "vt52", "vt100", "vt102", "vt220", "ansi", "linux", "cons25"];
+// A frame this slow to draw, for most of the last few, means a slow link
+const SLOW_FRAME: Duration = Duration::from_millis(120);
+const FRAMES_SAMPLED: usize = 10;
+const SLOW_FRAMES: usize = 6;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompatMode {
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+// Whether `auto` starts with the profile on, for this TERM
+pub fn dumb_terminal(term: Option<&str>) -> bool {
+    match term {
+        None | Some("") => true,
+        Some(term) => DUMB_TERMS.contains(&term),
+    }
+}
+
+pub fn over_ssh() -> bool {
+    std::env::var_os("SSH_CONNECTION").is_some() || std::env::var_os("SSH_TTY").is_some()
+}
+
+// Watches how long frames take to draw, for `auto` over SSH
+#[derive(Default)]
+pub struct SlowLink {
+    slow: VecDeque<bool>,
+}
+
+impl SlowLink {
+    // True once enough recent frames were slow
+    pub fn frame_drawn(&mut self, took: Duration) -> bool {
+        if self.slow.len() == FRAMES_SAMPLED {
+            self.slow.pop_front();
+        }
+        self.slow.push_back(took >= SLOW_FRAME);
+        self.slow.iter().filter(|&&slow| slow).count() >= SLOW_FRAMES
+    }
+}
+
+// Rewrites a drawn frame for the profile
+pub fn degrade(buffer: &mut Buffer) {
+    for cell in &mut buffer.content {
+        if !cell.symbol().is_ascii() {
+            let symbol = ascii(cell.symbol());
+            cell.set_symbol(symbol);
+        }
+        cell.fg = ansi16(cell.fg);
+        cell.bg = ansi16(cell.bg);
+    }
+}
+
+// Closest ASCII for a symbol. Wide ones (emoji, CJK) become a space; the
+// cell after them is blank already.
+fn ascii(symbol: &str) -> &'static str {
+    let Some(c) = symbol.chars().next() else {
+        return " ";
+    };
+    match c {
+        '─' | '━' | '═' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍' | '╴' | '╶' | '╸' | '╺' | '—' | '–' => "-",
+        '│' | '┃' | '║' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' | '╵' | '╷' | '╹' | '╻' | '▏' | '▕' => "|",
+        '\u{2500}'..='\u{257f}' => "+",
+        '▁' | '▂' => "_",
+        '▃' | '▄' | '▅' => "-",
+        '▆' | '▇' => "=",
+        '█' | '▉' | '▊' | '▋' | '▌' | '▍' | '▎' | '▐' | '▓' => "#",
+        '░' | '▒' => ":",
+        '\u{2800}'..='\u{28ff}' => "*",
+        '✓' | '✔' => "+",
+        '✗' | '✖' | '✘' | '×' => "x",
+        '⚠' | '❗' => "!",
+        'ℹ' => "i",
+        '●' | '•' | '◆' | '◉' | '★' => "*",
+        '○' | '◯' | '◦' => "o",
+        '❯' | '›' | '»' | '▸' | '▶' | '►' | '→' | '⇒' => ">",
+        '‹' | '«' | '◂' | '◀' | '←' | '↶' => "<",
+        '↑' | '▲' | '▴' => "^",
+        '↓' | '▼' | '▾' => "v",
+        '…' | '·' | '⋯' => ".",
+        '⊘' => "-",
+        '“' | '”' => "\"",
+        '‘' | '’' => "'",
+        _ if symbol.width() > 1 => " ",
+        _ => "?",
+    }
+}
+
+// The 16 ANSI colors with the RGB values xterm gives them
+const ANSI: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn ansi16(color: Color) -> Color {
+    let (r, g, b) = match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(n) if n < 16 => return ANSI[n as usize].0,
+        // 6x6x6 cube, then 24 grays
+        Color::Indexed(n) if n < 232 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let n = n - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        Color::Indexed(n) => {
+            let gray = 8 + (n - 232) * 10;
+            (gray, gray, gray)
+        }
+        other => return other,
+    };
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+    ANSI.iter().min_by_key(|(_, rgb)| distance(*rgb)).map(|(color, _)| *color).unwrap_or(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+    use ratatui::style::Style;
+
+    #[test]
+    fn degrades_symbols_and_colors() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 12, 1));
+        buffer.set_string(0, 0, "╭─🚀 ✓ é…│", Style::default().fg(Color::Rgb(0xbd, 0x93, 0xf9)).bg(Color::Indexed(236)));
+        degrade(&mut buffer);
+        let text: String = buffer.content.iter().map(|cell| cell.symbol()).collect();
+        assert_eq!(text, "+-   + ?.|  ");
+        assert_eq!((buffer.content[0].fg, buffer.content[0].bg), (Color::Gray, Color::Black));
+
+        assert!(dumb_terminal(Some("vt100")) && dumb_terminal(None));
+        assert!(!dumb_terminal(Some("xterm-256color")));
+
+        let mut link = SlowLink::default();
+        let slow = (0..FRAMES_SAMPLED).map(|i| link.frame_drawn(if i % 3 == 0 { SLOW_FRAME / 2 } else { SLOW_FRAME }));
+        // Slow from the sixth slow frame on
+        assert_eq!(slow.filter(|&s| s).count(), 2);
+    }
+}
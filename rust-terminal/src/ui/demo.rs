@@ -0,0 +1,167 @@
+// Scripted demo sessions for screencasts: `nexterm demo <script.toml>` types
+// each step into the input line at a fixed pace, runs it (output comes from
+// the same file's [[commands]], see core::executor) and shows its suggestions.
+// Nothing depends on timing or the machine, so every run looks the same.
+//
+//   typing_ms = 60
+//   [[steps]]
+//   type = "cargo test"
+//   suggest = ["cargo test -- --nocapture"]
+//   pause_ms = 1500
+use anyhow::{Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::utils::clock;
+
+#[derive(Debug, Deserialize)]
+struct DemoFile {
+    #[serde(default = "default_typing_ms")]
+    typing_ms: u64,
+    #[serde(default)]
+    steps: Vec<DemoStep>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DemoStep {
+    #[serde(rename = "type")]
+    input: String,
+    // Shown as AI suggestions once the command finishes
+    #[serde(default)]
+    suggest: Vec<String>,
+    // Before the next step starts typing
+    #[serde(default = "default_pause_ms")]
+    pause_ms: u64,
+}
+
+fn default_typing_ms() -> u64 {
+    60
+}
+
+fn default_pause_ms() -> u64 {
+    1500
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DemoAction {
+    Key(KeyEvent),
+    Suggest(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Cue {
+    Key(KeyEvent),
+    // Until the submitted command (and anything queued) has finished
+    WaitIdle,
+    Suggest(Vec<String>),
+    Pause(Duration),
+}
+
+pub struct DemoPlayer {
+    cues: VecDeque<Cue>,
+    typing: Duration,
+    // The next cue plays no earlier than this
+    ready_at: Instant,
+}
+
+impl DemoPlayer {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("No demo script {}", path.display()))?;
+        Self::parse(&content, clock::now()).with_context(|| format!("Invalid demo script {}", path.display()))
+    }
+
+    fn parse(content: &str, start: Instant) -> Result<Self> {
+        let file: DemoFile = toml::from_str(content)?;
+        let mut cues = VecDeque::new();
+        for step in file.steps {
+            let key = |code| Cue::Key(KeyEvent::new(code, KeyModifiers::NONE));
+            cues.extend(step.input.chars().map(|c| key(KeyCode::Char(c))));
+            cues.push_back(key(KeyCode::Enter));
+            cues.push_back(Cue::WaitIdle);
+            if !step.suggest.is_empty() {
+                cues.push_back(Cue::Suggest(step.suggest));
+            }
+            cues.push_back(Cue::Pause(Duration::from_millis(step.pause_ms)));
+        }
+        Ok(Self {
+            cues,
+            typing: Duration::from_millis(file.typing_ms),
+            ready_at: start,
+        })
+    }
+
+    // The next action due at `now`, if any; `busy` holds the script while a
+    // command is still running
+    pub fn next(&mut self, now: Instant, busy: bool) -> Option<DemoAction> {
+        while now >= self.ready_at {
+            match self.cues.front()? {
+                Cue::WaitIdle if busy => return None,
+                Cue::WaitIdle => {}
+                Cue::Pause(pause) => self.ready_at = now + *pause,
+                Cue::Key(key) => {
+                    let key = *key;
+                    self.cues.pop_front();
+                    self.ready_at = now + self.typing;
+                    return Some(DemoAction::Key(key));
+                }
+                Cue::Suggest(suggestions) => {
+                    let suggestions = suggestions.clone();
+                    self.cues.pop_front();
+                    return Some(DemoAction::Suggest(suggestions));
+                }
+            }
+            self.cues.pop_front();
+        }
+        None
+    }
+
+    // How long the event loop may wait for input before the next cue is due
+    pub fn wait(&self, now: Instant) -> Duration {
+        self.ready_at.saturating_duration_since(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> Option<DemoAction> {
+        Some(DemoAction::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)))
+    }
+
+    #[test]
+    fn plays_steps_at_a_fixed_pace() {
+        let start = Instant::now();
+        let mut player = DemoPlayer::parse(
+            r#"
+            typing_ms = 100
+            [[steps]]
+            type = "ls"
+            suggest = ["ls -la"]
+            pause_ms = 1000
+            [[steps]]
+            type = "pwd"
+            "#,
+            start,
+        )
+        .unwrap();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        assert_eq!(player.next(at(0), false), key('l'));
+        assert_eq!(player.next(at(50), false), None);
+        assert_eq!(player.wait(at(50)), Duration::from_millis(50));
+        assert_eq!(player.next(at(100), false), key('s'));
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(player.next(at(200), false), Some(DemoAction::Key(enter)));
+
+        // Suggestions wait for the command, then the pause before the next step
+        assert_eq!(player.next(at(300), true), None);
+        assert_eq!(player.next(at(900), false), Some(DemoAction::Suggest(vec!["ls -la".into()])));
+        assert_eq!(player.next(at(900), false), None);
+        assert_eq!(player.next(at(1900), false), key('p'));
+    }
+}
@@ -0,0 +1,310 @@
+// What the rest of NexTerm needs from a user interface. The event loop,
+// builtins, palette actions and the per-frame refresh in main.rs only go
+// through `Frontend`, so another frontend (a native window, a web page)
+// implements this trait without touching core or main.rs. Input stays the
+// frontend's own: keys, the mouse and resizing are handled where they arrive,
+// and what needs NexTerm (running commands, builtins) comes back as a
+// `Request`. TerminalUI, the ratatui one, is the only implementation so far.
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::ExitStatus;
+
+use crate::ai::proposals::{Proposal, Proposals};
+use crate::ai::review::Finding;
+use crate::core::actions::Action;
+use crate::core::bell::Ring;
+use crate::core::binary::BinaryOutput;
+use crate::core::daemons::DaemonInfo;
+use crate::core::environments::EnvLabel;
+use crate::core::operations::OperationInfo;
+use crate::core::scriptify::ScriptOptions;
+use crate::core::status::TabStatus;
+use crate::core::terminal::{Notice, Terminal};
+use crate::core::workflow_deps::Node;
+use crate::core::workspace_search::Corpus;
+use crate::plugins::manager::PluginInfo;
+use crate::ui::tui::TerminalUI;
+use crate::ui::widgets::confirm_dialog::ConfirmDialog;
+use crate::ui::widgets::form::Button;
+use crate::utils::editor;
+use crate::utils::ssh_keys::SshKey;
+
+// Lists, viewers and editors a frontend opens over the output
+pub enum Panel<'a> {
+    Problems(Vec<Finding>),
+    Daemons(Vec<DaemonInfo>),
+    Activity(Vec<OperationInfo>),
+    WorkflowGraph { workflow: &'a str, nodes: Vec<Node> },
+    SshKeys(Vec<SshKey>),
+    Plugins(Vec<PluginInfo>),
+    AutowireServices,
+    Hex(&'a BinaryOutput),
+    Filter { pipeline: &'a str, lines: Vec<String> },
+    ActionCards { request: &'a str, proposals: Proposals },
+    BatchEditor { title: &'a str, text: &'a str },
+    Scriptify { lines: Vec<(String, bool)>, options: ScriptOptions },
+    WorkspaceSearch { corpus: Corpus, query: &'a str },
+    Variables(&'a BTreeMap<String, String>),
+}
+
+// A yes/no question; the answer comes back as `Request::Answer`
+pub struct Question {
+    pub title: String,
+    pub lines: Vec<String>,
+    // What the two answers say
+    pub yes: &'static str,
+    pub no: &'static str,
+}
+
+// What input asks of NexTerm, see `Frontend::handle_input`
+pub enum Request {
+    // Quit; the frontend asked first if it had to
+    Exit,
+    // A line as typed: a builtin, or else a command
+    Run(String),
+    // Commands run like typed ones, queued behind one another
+    Queue(Vec<String>),
+    // Commands run as they are, e.g. picked from history
+    Submit(Vec<String>),
+    // Guarded commands once their dialog is answered: Some if confirmed
+    Release(Option<Vec<String>>),
+    // To the last `ask`
+    Answer(bool),
+    Action(Action),
+    // An `ai do` card was approved
+    Approve(usize),
+    RunWorkflow(String),
+    // A batch to save as a script instead of running it
+    Script(Vec<String>, ScriptOptions),
+}
+
+pub trait Frontend {
+    // One turn of the event loop: draws a frame if one is due, then waits up
+    // to the poll interval for input and handles it
+    fn handle_input(&mut self, terminal: &mut Terminal) -> Result<Option<Request>>;
+    // What changed since the last frame
+    fn output_changed(&mut self, len: usize);
+    fn notify(&mut self, notice: Notice);
+    fn ring(&mut self, ring: Ring);
+    // Open panels showing live state catch up with it
+    fn refresh_panels(&mut self, terminal: &Terminal);
+    // Once a frame: theme schedules, idle locking and the like
+    fn tick(&mut self, terminal: &Terminal);
+    fn is_locked(&self) -> bool;
+    // Hides the session until the passphrase is given, choosing one if there's none
+    fn lock(&mut self);
+
+    // What the output pane shows
+    fn visible_output(&self, terminal: &Terminal) -> Vec<String>;
+
+    // The input line
+    fn get_input(&self) -> String;
+    fn insert_text(&mut self, text: &str);
+
+    // Panels and dialogs
+    fn show(&mut self, panel: Panel) -> Result<()>;
+    fn ask(&mut self, question: Question);
+    fn open_action_form(&mut self, action: Action);
+    // The commands that may run now, after any confirmation the tab asks for
+    fn guard(&mut self, commands: Vec<String>) -> Option<Vec<String>>;
+    // Guarded commands that may run after all, once their impact preview is ready
+    fn take_released(&mut self) -> Option<Vec<String>>;
+    // Guarded commands wait on a dialog or a preview
+    fn is_guarding(&self) -> bool;
+    fn proposed_action(&self, index: usize) -> Option<Proposal>;
+    // A later round of `ai do` proposals, if the cards are still open
+    fn add_action_cards(&mut self, proposals: Proposals);
+    fn resolve_action(&mut self, index: usize, result: Result<Value>);
+    fn set_autowire_detail(&mut self, detail: String);
+
+    // Tabs
+    fn tab_status(&self) -> Vec<TabStatus>;
+    fn set_tab_muted(&mut self, muted: bool);
+    fn is_tab_muted(&self) -> bool;
+    // Which tab is shown, by an id that outlives closing the tabs before it
+    fn active_tab_id(&self) -> usize;
+    fn set_tab_env(&mut self, env: Option<EnvLabel>);
+
+    // Modes
+    fn set_demo_mode(&mut self, enabled: bool);
+    fn is_demo_mode(&self) -> bool;
+    fn set_compat(&mut self, on: bool);
+    fn is_compat(&self) -> bool;
+
+    // The desktop around it
+    fn copy_text(&mut self, text: &str, what: &str) -> Result<()>;
+    // Raw escape sequences for the terminal NexTerm itself runs in
+    fn write_to_host(&mut self, sequence: &str) -> Result<()>;
+    // Opens `path` in `editor` (the command line from the config) and waits
+    // until it's closed
+    fn edit(&mut self, editor: &str, path: &Path) -> Result<ExitStatus>;
+}
+
+impl Frontend for TerminalUI {
+    fn handle_input(&mut self, terminal: &mut Terminal) -> Result<Option<Request>> {
+        TerminalUI::handle_input(self, terminal)
+    }
+
+    fn output_changed(&mut self, len: usize) {
+        TerminalUI::output_changed(self, len)
+    }
+
+    fn notify(&mut self, notice: Notice) {
+        TerminalUI::notify(self, notice)
+    }
+
+    fn ring(&mut self, ring: Ring) {
+        TerminalUI::ring(self, ring)
+    }
+
+    fn refresh_panels(&mut self, terminal: &Terminal) {
+        if self.is_daemons_panel_visible() {
+            self.update_daemons_panel(terminal.daemons().list());
+        }
+        if self.is_activity_panel_visible() {
+            self.update_activity_panel(terminal.operations().list());
+        }
+        if self.is_variables_panel_visible() {
+            self.update_variables_panel(terminal.variables().values());
+        }
+    }
+
+    fn tick(&mut self, terminal: &Terminal) {
+        self.check_theme();
+        self.check_startup();
+        self.check_rerun_choices();
+        self.check_impact();
+        self.check_idle_lock();
+        self.check_idle_suspend(terminal);
+    }
+
+    fn is_locked(&self) -> bool {
+        TerminalUI::is_locked(self)
+    }
+
+    fn lock(&mut self) {
+        TerminalUI::lock_session(self)
+    }
+
+    fn visible_output(&self, terminal: &Terminal) -> Vec<String> {
+        TerminalUI::visible_output(self, terminal)
+    }
+
+    fn get_input(&self) -> String {
+        TerminalUI::get_input(self)
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        TerminalUI::insert_text(self, text)
+    }
+
+    fn show(&mut self, panel: Panel) -> Result<()> {
+        match panel {
+            Panel::Problems(findings) => self.show_problems_panel(findings),
+            Panel::Daemons(daemons) => self.show_daemons_panel(daemons),
+            Panel::Activity(operations) => self.show_activity_panel(operations),
+            Panel::WorkflowGraph { workflow, nodes } => self.show_workflow_graph(workflow, nodes),
+            Panel::SshKeys(keys) => self.show_ssh_keys_panel(keys),
+            Panel::Plugins(plugins) => self.show_plugins_panel(plugins),
+            Panel::AutowireServices => self.show_autowire_services()?,
+            Panel::Hex(output) => self.show_hex_viewer(output),
+            Panel::Filter { pipeline, lines } => self.show_filter_view(pipeline, lines),
+            Panel::ActionCards { request, proposals } => self.show_action_cards(request, proposals),
+            Panel::BatchEditor { title, text } => self.open_batch_editor(title, text),
+            Panel::Scriptify { lines, options } => self.open_scriptify(lines, options),
+            Panel::WorkspaceSearch { corpus, query } => self.show_workspace_search(corpus, query),
+            Panel::Variables(variables) => self.show_variables_panel(variables),
+        }
+        Ok(())
+    }
+
+    fn ask(&mut self, question: Question) {
+        let buttons = vec![Button::new(question.yes).key('y'), Button::cancel(question.no).key('n')];
+        let dialog = ConfirmDialog::new(format!(" {} ", question.title), question.lines).with_buttons(buttons);
+        TerminalUI::show_confirm(self, dialog)
+    }
+
+    fn open_action_form(&mut self, action: Action) {
+        TerminalUI::open_action_form(self, action)
+    }
+
+    fn guard(&mut self, commands: Vec<String>) -> Option<Vec<String>> {
+        TerminalUI::guard(self, commands)
+    }
+
+    fn take_released(&mut self) -> Option<Vec<String>> {
+        TerminalUI::take_released(self)
+    }
+
+    fn is_guarding(&self) -> bool {
+        TerminalUI::is_guarding(self)
+    }
+
+    fn proposed_action(&self, index: usize) -> Option<Proposal> {
+        TerminalUI::proposed_action(self, index)
+    }
+
+    fn add_action_cards(&mut self, proposals: Proposals) {
+        TerminalUI::add_action_cards(self, proposals)
+    }
+
+    fn resolve_action(&mut self, index: usize, result: Result<Value>) {
+        TerminalUI::resolve_action(self, index, result)
+    }
+
+    fn set_autowire_detail(&mut self, detail: String) {
+        TerminalUI::set_autowire_detail(self, detail)
+    }
+
+    fn tab_status(&self) -> Vec<TabStatus> {
+        TerminalUI::tab_status(self)
+    }
+
+    fn set_tab_muted(&mut self, muted: bool) {
+        TerminalUI::set_tab_muted(self, muted)
+    }
+
+    fn is_tab_muted(&self) -> bool {
+        TerminalUI::is_tab_muted(self)
+    }
+
+    fn active_tab_id(&self) -> usize {
+        TerminalUI::active_tab_id(self)
+    }
+
+    fn set_tab_env(&mut self, env: Option<EnvLabel>) {
+        TerminalUI::set_tab_env(self, env)
+    }
+
+    fn set_demo_mode(&mut self, enabled: bool) {
+        TerminalUI::set_demo_mode(self, enabled)
+    }
+
+    fn is_demo_mode(&self) -> bool {
+        TerminalUI::is_demo_mode(self)
+    }
+
+    fn set_compat(&mut self, on: bool) {
+        TerminalUI::set_compat(self, on)
+    }
+
+    fn is_compat(&self) -> bool {
+        TerminalUI::is_compat(self)
+    }
+
+    fn copy_text(&mut self, text: &str, what: &str) -> Result<()> {
+        TerminalUI::copy_text(self, text, what)
+    }
+
+    fn write_to_host(&mut self, sequence: &str) -> Result<()> {
+        TerminalUI::write_to_host(self, sequence)
+    }
+
+    fn edit(&mut self, editor: &str, path: &Path) -> Result<ExitStatus> {
+        let (program, args) = editor::command(editor, path);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        TerminalUI::run_external(self, &program, &args)
+    }
+}
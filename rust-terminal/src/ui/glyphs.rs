@@ -0,0 +1,186 @@
+// Status bar segments (OS, git branch, project language) drawn with Nerd Font
+// icons when the font has them, plain ASCII labels otherwise. Asking
+// fontconfig and reading the repository happen in the background, so drawing
+// never waits on them; the segments fill in when they answer.
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use crate::utils::clock;
+use crate::utils::startup::Pending;
+
+// The branch changes under `git checkout`; re-read the repository at most this often
+const SEGMENTS_TTL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GlyphMode {
+    // Nerd Font icons if one is installed (fontconfig), ASCII otherwise
+    #[default]
+    Auto,
+    Nerd,
+    Ascii,
+}
+
+// Marker file in the project root -> language
+const LANGUAGES: &[(&str, &str)] = &[
+    ("Cargo.toml", "rust"),
+    ("go.mod", "go"),
+    ("package.json", "node"),
+    ("pyproject.toml", "python"),
+    ("requirements.txt", "python"),
+    ("setup.py", "python"),
+    ("Gemfile", "ruby"),
+    ("pom.xml", "java"),
+    ("build.gradle", "java"),
+];
+
+// The git branch and project language of a directory
+type Project = (Option<String>, Option<&'static str>);
+
+pub struct Glyphs {
+    nerd: Cell<bool>,
+    // Under `auto`, until fontconfig answers; ASCII meanwhile
+    detecting: RefCell<Option<Pending<bool>>>,
+    cache: RefCell<Option<(Instant, PathBuf, Project)>>,
+    looking_up: RefCell<Option<(PathBuf, Pending<Project>)>>,
+    // Set in snapshot tests, which mustn't depend on where they run
+    fixed: Option<String>,
+}
+
+impl Glyphs {
+    pub fn new(mode: GlyphMode) -> Self {
+        let detecting = (mode == GlyphMode::Auto).then(|| Pending::spawn("Font check", nerd_font_installed));
+        Self {
+            nerd: Cell::new(mode == GlyphMode::Nerd),
+            detecting: RefCell::new(detecting),
+            cache: RefCell::new(None),
+            looking_up: RefCell::new(None),
+            fixed: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn fixed(segments: &str) -> Self {
+        Self {
+            nerd: Cell::new(false),
+            detecting: RefCell::new(None),
+            cache: RefCell::new(None),
+            looking_up: RefCell::new(None),
+            fixed: Some(segments.to_string()),
+        }
+    }
+
+    // e.g. `<tux> linux | <branch> main | <crab> rust`, or `linux | git:main | rust` in ASCII
+    pub fn segments(&self) -> String {
+        if let Some(text) = &self.fixed {
+            return text.clone();
+        }
+        let mut detecting = self.detecting.borrow_mut();
+        if let Some(Poll::Ready(nerd)) = detecting.as_mut().map(Pending::poll) {
+            self.nerd.set(nerd.unwrap_or(false));
+            *detecting = None;
+        }
+
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let mut looking_up = self.looking_up.borrow_mut();
+        if let Some((dir, Poll::Ready(project))) = looking_up.as_mut().map(|(dir, p)| (dir.clone(), p.poll())) {
+            *self.cache.borrow_mut() = project.map(|project| (clock::now(), dir, project));
+            *looking_up = None;
+        }
+        let cache = self.cache.borrow();
+        let project = cache.as_ref().filter(|(_, dir, _)| *dir == cwd);
+        let stale = project.is_none_or(|(at, ..)| clock::since(*at) >= SEGMENTS_TTL);
+        if stale && looking_up.is_none() {
+            let dir = cwd.clone();
+            *looking_up = Some((cwd, Pending::spawn("Git lookup", move || look_up(&dir))));
+        }
+
+        // Just the OS until the first lookup in this directory is in
+        let os = std::env::consts::OS;
+        let mut segments = vec![self.icon(os_icon(os), os, os)];
+        let (branch, language) = project.map(|(_, _, project)| project.clone()).unwrap_or_default();
+        if let Some(branch) = branch {
+            segments.push(self.icon("\u{e0a0}", &branch, &format!("git:{}", branch)));
+        }
+        if let Some(language) = language {
+            segments.push(self.icon(language_icon(language), language, language));
+        }
+        segments.join(" | ")
+    }
+
+    fn icon(&self, glyph: &str, label: &str, ascii: &str) -> String {
+        if self.nerd.get() {
+            format!("{} {}", glyph, label)
+        } else {
+            ascii.to_string()
+        }
+    }
+}
+
+fn look_up(cwd: &Path) -> Project {
+    let root = cwd.ancestors().find(|dir| dir.join(".git").exists());
+    (root.and_then(git_branch), project_language(root.unwrap_or(cwd)))
+}
+
+// The branch checked out in a repository; a short commit id when detached
+fn git_branch(root: &Path) -> Option<String> {
+    let mut git_dir = root.join(".git");
+    // Worktrees and submodules have a `.git` file pointing at the real directory
+    if git_dir.is_file() {
+        let pointer = std::fs::read_to_string(&git_dir).ok()?;
+        git_dir = root.join(pointer.trim().strip_prefix("gitdir:")?.trim());
+    }
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref: refs/heads/") {
+        Some(branch) => Some(branch.to_string()),
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+fn project_language(root: &Path) -> Option<&'static str> {
+    LANGUAGES
+        .iter()
+        .find(|(marker, _)| root.join(marker).exists())
+        .map(|(_, language)| *language)
+}
+
+fn os_icon(os: &str) -> &'static str {
+    match os {
+        "linux" => "\u{f17c}",
+        "macos" => "\u{f179}",
+        "windows" => "\u{f17a}",
+        "freebsd" => "\u{f30c}",
+        _ => "\u{f108}",
+    }
+}
+
+fn language_icon(language: &str) -> &'static str {
+    match language {
+        "rust" => "\u{e7a8}",
+        "go" => "\u{e626}",
+        "node" => "\u{e718}",
+        "python" => "\u{e73c}",
+        "ruby" => "\u{e739}",
+        "java" => "\u{e738}",
+        _ => "\u{f121}",
+    }
+}
+
+// The terminal's font can't be queried from inside it, so this asks
+// fontconfig whether a Nerd Font is installed at all; set `glyphs` in the
+// config when that guess is wrong
+fn nerd_font_installed() -> bool {
+    Command::new("fc-list")
+        .args([":", "family"])
+        .output()
+        .map(|output| {
+            let families = String::from_utf8_lossy(&output.stdout);
+            families.contains("Nerd Font") || families.contains("NerdFont")
+        })
+        .unwrap_or(false)
+}
@@ -0,0 +1,56 @@
+// Keypress-to-render latency instrumentation
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const MAX_SAMPLES: usize = 128;
+
+pub struct LatencyTracker {
+    // Oldest keypress not yet reflected on screen
+    pending: Option<Instant>,
+    samples: VecDeque<Duration>,
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self {
+            pending: None,
+            samples: VecDeque::with_capacity(MAX_SAMPLES),
+        }
+    }
+
+    pub fn key_received(&mut self) {
+        self.pending.get_or_insert_with(Instant::now);
+    }
+
+    pub fn frame_rendered(&mut self) {
+        if let Some(start) = self.pending.take() {
+            if self.samples.len() == MAX_SAMPLES {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(start.elapsed());
+        }
+    }
+
+    pub fn last(&self) -> Option<Duration> {
+        self.samples.back().copied()
+    }
+
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort();
+        let index = ((sorted.len() - 1) as f64 * p).round() as usize;
+        Some(sorted[index])
+    }
+
+    // e.g. `⌨ 3ms p95 8ms`
+    pub fn summary(&self) -> Option<String> {
+        Some(format!(
+            "⌨ {}ms p95 {}ms",
+            self.last()?.as_millis(),
+            self.percentile(0.95)?.as_millis()
+        ))
+    }
+}
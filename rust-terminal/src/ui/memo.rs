@@ -0,0 +1,46 @@
+// The last thing a widget built and what it was built from, so bars that
+// look the same frame after frame (the tab bar, the status line) aren't laid
+// out again each time. Drawing borrows the UI immutably, hence the RefCell.
+use std::cell::RefCell;
+
+pub struct Memo<K, V> {
+    last: RefCell<Option<(K, V)>>,
+}
+
+impl<K: PartialEq, V: Clone> Memo<K, V> {
+    pub fn new() -> Self {
+        Self { last: RefCell::new(None) }
+    }
+
+    // The value for `key`, built only when the key changed since the last call
+    pub fn get(&self, key: K, build: impl FnOnce(&K) -> V) -> V {
+        let mut last = self.last.borrow_mut();
+        match &*last {
+            Some((built_from, value)) if *built_from == key => value.clone(),
+            _ => {
+                let value = build(&key);
+                *last = Some((key, value.clone()));
+                value
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_again_only_when_the_key_changes() {
+        let memo = Memo::new();
+        let builds = std::cell::Cell::new(0);
+        let build = |key: &u32| {
+            builds.set(builds.get() + 1);
+            key * 2
+        };
+        assert_eq!(memo.get(1, build), 2);
+        assert_eq!(memo.get(1, build), 2);
+        assert_eq!(memo.get(3, build), 6);
+        assert_eq!(builds.get(), 2);
+    }
+}
@@ -2,3 +2,16 @@
 pub mod tui;
 pub mod widgets;
 pub mod themes;
+pub mod scrollback;
+pub mod animation;
+pub mod latency;
+pub mod memo;
+pub mod anonymizer;
+pub mod zoom;
+pub mod glyphs;
+pub mod compat;
+pub mod state;
+pub mod demo;
+#[cfg(test)]
+pub mod snapshot;
+pub mod frontend;
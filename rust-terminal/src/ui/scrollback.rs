@@ -0,0 +1,105 @@
+// Scrollback layout: wraps logical output lines to the visible width
+use unicode_width::UnicodeWidthChar;
+
+use crate::core::terminal::{Annotation, PromptMark};
+
+// Splits each logical line into display rows no wider than `width` columns
+pub fn reflow(lines: &[String], width: usize) -> Vec<String> {
+    if width == 0 {
+        return Vec::new();
+    }
+
+    let mut rows = Vec::with_capacity(lines.len());
+    for line in lines {
+        if line.is_empty() {
+            rows.push(String::new());
+            continue;
+        }
+
+        let mut row = String::new();
+        let mut row_width = 0;
+        for c in line.chars() {
+            let w = c.width().unwrap_or(0);
+            if row_width + w > width && !row.is_empty() {
+                rows.push(std::mem::take(&mut row));
+                row_width = 0;
+            }
+            row.push(c);
+            row_width += w;
+        }
+        rows.push(row);
+    }
+    rows
+}
+
+// Marks a line with hidden annotations after it
+const ANNOTATION_MARK: &str = "  ·💡";
+
+// Output with the annotation layer merged in: each annotation's lines after
+// the output line it follows, or with `shown` off just a mark at the end of
+// that line. Also returns where each output line landed, to map scroll positions.
+pub fn layer(output: &[String], annotations: &[Annotation], shown: bool) -> (Vec<String>, Vec<usize>) {
+    let mut lines: Vec<String> = Vec::with_capacity(output.len());
+    let mut index = Vec::with_capacity(output.len());
+    let mut pending = annotations.iter().peekable();
+    let place = |lines: &mut Vec<String>, annotation: &Annotation| {
+        if shown {
+            lines.extend(annotation.text.iter().cloned());
+        } else if let Some(last) = lines.last_mut().filter(|l| !l.ends_with(ANNOTATION_MARK)) {
+            last.push_str(ANNOTATION_MARK);
+        }
+    };
+    for (i, line) in output.iter().enumerate() {
+        while let Some(annotation) = pending.next_if(|a| a.line <= i) {
+            place(&mut lines, annotation);
+        }
+        index.push(lines.len());
+        lines.push(line.clone());
+    }
+    for annotation in pending {
+        place(&mut lines, annotation);
+    }
+    (lines, index)
+}
+
+// Returns the last `height` rows, i.e. what is visible when following output
+pub fn tail(rows: &[String], height: usize) -> &[String] {
+    &rows[rows.len().saturating_sub(height)..]
+}
+
+// The first logical line needed for the last `height` rows at `width`, so
+// following the output reflows what fits in the pane, not all of it
+pub fn tail_start(lines: &[String], width: usize, height: usize) -> usize {
+    if width == 0 {
+        return lines.len();
+    }
+    let mut start = lines.len();
+    let mut rows = 0;
+    while start > 0 && rows < height {
+        start -= 1;
+        rows += reflow(&lines[start..start + 1], width).len();
+    }
+    start
+}
+
+// Rows shown when the view is pinned with logical line `top` at the top
+pub fn window(lines: &[String], top: usize, width: usize, height: usize) -> Vec<String> {
+    let start = top.min(lines.len());
+    let mut rows = reflow(&lines[start..], width);
+    rows.truncate(height);
+    rows
+}
+
+// Prompt line before `top`, or the last prompt when following the tail
+pub fn previous_prompt(marks: &[PromptMark], top: Option<usize>) -> Option<usize> {
+    match top {
+        Some(top) => marks.iter().rev().find(|m| m.line < top).map(|m| m.line),
+        None => marks.last().map(|m| m.line),
+    }
+}
+
+// Prompt line after `top`; None means there is none and the view should follow again
+pub fn next_prompt(marks: &[PromptMark], top: Option<usize>) -> Option<usize> {
+    let top = top?;
+    marks.iter().find(|m| m.line > top).map(|m| m.line)
+}
@@ -0,0 +1,171 @@
+// Snapshot tests for UI rendering: a Terminal and a headless TerminalUI in a
+// throwaway data/config directory, drawn onto ratatui's TestBackend and
+// compared as text with insta.
+//
+//   let (terminal, mut ui) = snapshot::setup(snapshot::config());
+//   terminal.add_output("hello");
+//   insta::assert_snapshot!(snapshot::render(&mut ui, &terminal, 80, 24));
+//
+// New or changed snapshots land next to the test as .snap.new files; review
+// them with `cargo insta review`. Colors aren't part of the text.
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Buffer;
+use ratatui::Terminal as RatatuiTerminal;
+use unicode_width::UnicodeWidthStr;
+
+use crate::core::executor::{Executor, ShellExecutor};
+use crate::core::terminal::Terminal;
+use crate::ui::tui::TerminalUI;
+use crate::utils::config::Config;
+
+// Defaults that render the same everywhere
+pub fn config() -> Config {
+    Config {
+        shell: "/bin/bash".to_string(),
+        theme: "dracula".to_string(),
+        animations: false,
+        ..Config::default()
+    }
+}
+
+pub fn setup(config: Config) -> (Terminal, TerminalUI) {
+    let executor = Arc::new(ShellExecutor::new(&config.shell));
+    setup_with(config, executor)
+}
+
+// Like `setup`, with commands run by `executor` (e.g. a ScriptedExecutor)
+pub fn setup_with(config: Config, executor: Arc<dyn Executor>) -> (Terminal, TerminalUI) {
+    isolate();
+    let mut terminal = Terminal::with_executor(config.clone(), executor).expect("terminal");
+    terminal.wait_for_startup(Duration::from_secs(5));
+    let ui = TerminalUI::headless(&config).expect("headless UI");
+    (terminal, ui)
+}
+
+// Submits `command` and waits for it, and anything queued behind it, to finish
+pub async fn run(terminal: &mut Terminal, command: &str) {
+    terminal.submit_command(command).await;
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            terminal.update_output().await.expect("update output");
+            if terminal.running_command().is_none() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("command finished");
+}
+
+// One frame of `ui` at the given size, a line of text per row
+pub fn render(ui: &mut TerminalUI, terminal: &Terminal, width: u16, height: u16) -> String {
+    let mut screen = RatatuiTerminal::new(TestBackend::new(width, height)).expect("test backend");
+    ui.render_on(&mut screen, terminal).expect("render");
+    text(screen.backend().buffer())
+}
+
+fn text(buffer: &Buffer) -> String {
+    let width = buffer.area.width as usize;
+    let mut lines = Vec::new();
+    for row in buffer.content.chunks(width) {
+        let mut line = String::new();
+        // Cells covered by a wide character hold a blank of their own
+        let mut skip = 0;
+        for cell in row {
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            line.push_str(cell.symbol());
+            skip = cell.symbol().width().saturating_sub(1);
+        }
+        lines.push(line.trim_end().to_string());
+    }
+    lines.join("\n")
+}
+
+// History, plugins and the rest of the user's data stay out of the tests
+fn isolate() {
+    static DIR: OnceLock<tempfile::TempDir> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let dir = tempfile::tempdir().expect("temp dir");
+        std::env::set_var("XDG_DATA_HOME", dir.path().join("data"));
+        std::env::set_var("XDG_CONFIG_HOME", dir.path().join("config"));
+        dir
+    });
+}
+
+mod tests {
+    use super::*;
+    use crate::core::environments::EnvLabel;
+    use crate::core::executor::ScriptedExecutor;
+
+    #[test]
+    fn main_layout() {
+        let (terminal, mut ui) = setup(config());
+        terminal.add_output("❯ echo hello\nhello");
+        for c in "ls -la".chars() {
+            ui.input_char(c);
+        }
+        insta::assert_snapshot!(render(&mut ui, &terminal, 80, 20));
+    }
+
+    #[test]
+    fn labeled_tabs() {
+        let (terminal, mut ui) = setup(config());
+        ui.new_tab().unwrap();
+        ui.set_tab_env(Some(EnvLabel::Prod));
+        insta::assert_snapshot!(render(&mut ui, &terminal, 80, 20));
+    }
+
+    #[test]
+    fn too_small() {
+        let (terminal, mut ui) = setup(config());
+        insta::assert_snapshot!(render(&mut ui, &terminal, 30, 10));
+    }
+
+    #[test]
+    fn long_scrollback() {
+        let (mut terminal, mut ui) = setup(config());
+        let lines: Vec<String> = (1..=20_000).map(|n| format!("line {}", n)).collect();
+        terminal.add_output(&lines[..19_995].join("\n"));
+        terminal.suggest(&["cargo fix".to_string()]);
+        terminal.add_output(&format!("{}{}", "wrapped ".repeat(12), lines[19_995..].join("\n")));
+        let following = render(&mut ui, &terminal, 80, 20);
+
+        ui.output_changed(terminal.output_len());
+        for _ in 0..3 {
+            ui.scroll_output(true);
+        }
+        insta::assert_snapshot!(format!("{}\n{}", following, render(&mut ui, &terminal, 80, 20)));
+    }
+
+    #[tokio::test]
+    async fn scripted_command() {
+        let executor = Arc::new(
+            ScriptedExecutor::parse(
+                r#"
+                [[commands]]
+                run = "cargo test"
+                exit = 101
+                lines = [
+                  { text = "running 2 tests" },
+                  { text = "test parses ... ok", after_ms = 20 },
+                  { text = "test renders ... FAILED", after_ms = 20 },
+                ]
+                "#,
+            )
+            .unwrap(),
+        );
+        let (mut terminal, mut ui) = setup_with(config(), executor.clone());
+        run(&mut terminal, "cargo test").await;
+
+        assert_eq!(executor.ran(), ["cargo test"]);
+        assert_eq!(terminal.history().unique()[0].exit_code, Some(101));
+        insta::assert_snapshot!(render(&mut ui, &terminal, 80, 20));
+    }
+}
@@ -0,0 +1,284 @@
+// UI state as a reducer over typed events: the input line, history recall,
+// tabs and the scroll position change only through `UiState::apply`. Events
+// carry everything they depend on (the recalled entry, the page height), so
+// the same events always replay to the same state.
+use crate::core::environments::EnvLabel;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UiEvent {
+    Key(KeyAction),
+    Output(OutputChunk),
+    Tab(TabEvent),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyAction {
+    Insert(char),
+    Backspace,
+    CursorLeft,
+    CursorRight,
+    Clear,
+    // Replaces the line, cursor at the end (completions, history search)
+    SetInput(String),
+    // Up/Down landed on history entry `index`; the typed line is kept aside
+    Recall(usize, String),
+    // Down past the newest entry: back to the line as it was typed
+    RecallDraft,
+    PageUp { page: usize },
+    PageDown { page: usize },
+    // Pins the view at an output line; None follows the tail again
+    ScrollTo(Option<usize>),
+}
+
+// Output arrived or was trimmed; `len` is the output's length after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputChunk {
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TabEvent {
+    New,
+    Close,
+    Next,
+    Select(usize),
+    // Labels the active tab; None clears the label
+    Label(Option<EnvLabel>),
+    // Silences bells while the active tab is shown
+    Mute(bool),
+}
+
+// A tab's title, the environment it's labeled with and whether bells ring in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tab {
+    // Stays the same as tabs before it close
+    pub id: usize,
+    pub title: String,
+    pub env: Option<EnvLabel>,
+    pub muted: bool,
+}
+
+impl Tab {
+    fn new(id: usize, title: String) -> Self {
+        Self { id, title, env: None, muted: false }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UiState {
+    input: String,
+    cursor: usize,
+    history_index: Option<usize>,
+    // What was typed before Up started walking history, restored past the newest entry
+    history_draft: String,
+    tabs: Vec<Tab>,
+    active_tab: usize,
+    next_tab_id: usize,
+    // Logical output line pinned at the top of the view; None follows the tail
+    scroll_top: Option<usize>,
+    // Output length when the view left follow mode, to count unseen lines
+    lines_when_scrolled: usize,
+    output_len: usize,
+}
+
+impl Default for UiState {
+    fn default() -> Self {
+        Self {
+            input: String::new(),
+            cursor: 0,
+            history_index: None,
+            history_draft: String::new(),
+            tabs: vec![Tab::new(0, "Terminal".to_string())],
+            active_tab: 0,
+            next_tab_id: 1,
+            scroll_top: None,
+            lines_when_scrolled: 0,
+            output_len: 0,
+        }
+    }
+}
+
+impl UiState {
+    pub fn apply(&mut self, event: UiEvent) {
+        match event {
+            UiEvent::Key(action) => self.key(action),
+            UiEvent::Output(chunk) => self.output_len = chunk.len,
+            UiEvent::Tab(event) => self.tab(event),
+        }
+    }
+
+    fn key(&mut self, action: KeyAction) {
+        match action {
+            KeyAction::Insert(c) => {
+                self.input.insert(self.cursor, c);
+                self.cursor += 1;
+                self.history_index = None;
+            }
+            KeyAction::Backspace => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.input.remove(self.cursor);
+                }
+                self.history_index = None;
+            }
+            KeyAction::CursorLeft => self.cursor = self.cursor.saturating_sub(1),
+            KeyAction::CursorRight => {
+                if self.cursor < self.input.len() {
+                    self.cursor += 1;
+                }
+            }
+            KeyAction::Clear => {
+                self.input.clear();
+                self.cursor = 0;
+                self.history_index = None;
+            }
+            KeyAction::SetInput(text) => self.set_input(text),
+            KeyAction::Recall(index, text) => {
+                if self.history_index.is_none() {
+                    self.history_draft = self.input.clone();
+                }
+                self.history_index = Some(index);
+                self.set_input(text);
+            }
+            KeyAction::RecallDraft => {
+                self.history_index = None;
+                let draft = std::mem::take(&mut self.history_draft);
+                self.set_input(draft);
+            }
+            KeyAction::PageUp { page } => {
+                let top = self.scroll_top.unwrap_or(self.output_len.saturating_sub(page));
+                self.scroll_to(Some(top.saturating_sub(page)));
+            }
+            KeyAction::PageDown { page } => {
+                if let Some(top) = self.scroll_top {
+                    let top = top + page;
+                    self.scroll_to((top + page < self.output_len).then_some(top));
+                }
+            }
+            KeyAction::ScrollTo(top) => self.scroll_to(top),
+        }
+    }
+
+    fn tab(&mut self, event: TabEvent) {
+        match event {
+            TabEvent::New => {
+                self.tabs.push(Tab::new(self.next_tab_id, format!("Tab {}", self.tabs.len() + 1)));
+                self.next_tab_id += 1;
+                self.active_tab = self.tabs.len() - 1;
+            }
+            TabEvent::Close => {
+                if self.tabs.len() > 1 {
+                    self.tabs.remove(self.active_tab);
+                    self.active_tab = self.active_tab.min(self.tabs.len() - 1);
+                }
+            }
+            TabEvent::Next => self.active_tab = (self.active_tab + 1) % self.tabs.len(),
+            TabEvent::Select(index) => self.active_tab = index.min(self.tabs.len() - 1),
+            TabEvent::Label(env) => self.tabs[self.active_tab].env = env,
+            TabEvent::Mute(muted) => self.tabs[self.active_tab].muted = muted,
+        }
+    }
+
+    fn set_input(&mut self, text: String) {
+        self.input = text;
+        self.cursor = self.input.len();
+    }
+
+    fn scroll_to(&mut self, top: Option<usize>) {
+        if self.scroll_top.is_none() && top.is_some() {
+            self.lines_when_scrolled = self.output_len;
+        }
+        self.scroll_top = top;
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn history_index(&self) -> Option<usize> {
+        self.history_index
+    }
+
+    pub fn tabs(&self) -> &[Tab] {
+        &self.tabs
+    }
+
+    pub fn active_tab(&self) -> usize {
+        self.active_tab
+    }
+
+    pub fn active_tab_id(&self) -> usize {
+        self.tabs[self.active_tab].id
+    }
+
+    pub fn tab_env(&self) -> Option<EnvLabel> {
+        self.tabs[self.active_tab].env
+    }
+
+    pub fn tab_muted(&self) -> bool {
+        self.tabs[self.active_tab].muted
+    }
+
+    pub fn scroll_top(&self) -> Option<usize> {
+        self.scroll_top
+    }
+
+    pub fn output_len(&self) -> usize {
+        self.output_len
+    }
+
+    // Output that arrived while scrolled back
+    pub fn unseen_lines(&self) -> usize {
+        match self.scroll_top {
+            Some(_) => self.output_len.saturating_sub(self.lines_when_scrolled),
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay(events: &[UiEvent]) -> UiState {
+        let mut state = UiState::default();
+        for event in events {
+            state.apply(event.clone());
+        }
+        state
+    }
+
+    #[test]
+    fn replays_to_the_same_state() {
+        let events = vec![
+            UiEvent::Key(KeyAction::Insert('l')),
+            UiEvent::Key(KeyAction::Insert('s')),
+            UiEvent::Key(KeyAction::Recall(3, "git status".into())),
+            UiEvent::Key(KeyAction::RecallDraft),
+            UiEvent::Tab(TabEvent::New),
+            UiEvent::Tab(TabEvent::Label(Some(EnvLabel::Prod))),
+            UiEvent::Tab(TabEvent::Mute(true)),
+            UiEvent::Output(OutputChunk { len: 100 }),
+            UiEvent::Key(KeyAction::PageUp { page: 20 }),
+            UiEvent::Output(OutputChunk { len: 105 }),
+        ];
+        let state = replay(&events);
+        assert_eq!(state, replay(&events));
+
+        // The draft came back, and the history walk is over
+        assert_eq!((state.input(), state.cursor(), state.history_index()), ("ls", 2, None));
+        assert_eq!((state.active_tab(), state.tab_env(), state.tab_muted()), (1, Some(EnvLabel::Prod), true));
+        assert_eq!((state.scroll_top(), state.unseen_lines()), (Some(60), 5));
+
+        let mut state = state;
+        state.apply(UiEvent::Tab(TabEvent::Close));
+        state.apply(UiEvent::Key(KeyAction::ScrollTo(None)));
+        assert_eq!((state.active_tab(), state.tab_env(), state.unseen_lines()), (0, None, 0));
+        state.apply(UiEvent::Tab(TabEvent::New));
+        assert_eq!((state.active_tab(), state.active_tab_id()), (1, 2));
+    }
+}
@@ -1,12 +1,470 @@
-// Theme system
+// Theme system: named palettes the UI draws with, and light/dark switching
+use anyhow::{anyhow, Context, Result};
+use ratatui::style::Color;
+use ratatui::widgets::{Block, BorderType, Borders};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::utils::calendar;
+use crate::utils::clock;
+use crate::utils::idle;
+use crate::utils::config::Config;
+
+// How far inactive panes fade towards the background
+const INACTIVE_DIM: f32 = 0.45;
+
+// How often the OS appearance is re-read; the schedule needs far less
+const OS_POLL: Duration = Duration::from_secs(5);
+const SCHEDULE_POLL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub background: Color,
+    // Fill behind each pane's contents; None shows `background`
+    pub pane: Option<Color>,
+    pub text: Color,
+    pub muted: Color,
+    pub border: Color,
+    pub highlight: Color,
+    pub success: Color,
+    pub info: Color,
+}
+
+impl Palette {
+    // Step `t` (0.0..=1.0) of a fade from `self` to `to`
+    pub fn blend(&self, to: &Palette, t: f32) -> Palette {
+        let step = |a, b| mix(a, b, t);
+        let pane = match (self.pane, to.pane) {
+            (Some(a), Some(b)) => Some(step(a, b)),
+            (a, _) if t < 0.5 => a,
+            (_, b) => b,
+        };
+        Palette {
+            background: step(self.background, to.background),
+            pane,
+            text: step(self.text, to.text),
+            muted: step(self.muted, to.muted),
+            border: step(self.border, to.border),
+            highlight: step(self.highlight, to.highlight),
+            success: step(self.success, to.success),
+            info: step(self.info, to.info),
+        }
+    }
+
+    // Foreground colors faded towards the pane background, for panes without
+    // focus; without RGB to fade against they all become dark gray
+    pub fn dimmed(&self) -> Palette {
+        let base = self.pane.unwrap_or(self.background);
+        let dim = |c: Color| match (c, base) {
+            (Color::Rgb(..), Color::Rgb(..)) => mix(c, base, INACTIVE_DIM),
+            _ => Color::DarkGray,
+        };
+        Palette {
+            text: dim(self.text),
+            muted: dim(self.muted),
+            border: dim(self.border),
+            highlight: dim(self.highlight),
+            success: dim(self.success),
+            info: dim(self.info),
+            ..*self
+        }
+    }
+}
+
+// Colors that aren't RGB can't be mixed and switch halfway
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    match (a, b) {
+        (Color::Rgb(r1, g1, b1), Color::Rgb(r2, g2, b2)) => {
+            let lerp = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t).round() as u8;
+            Color::Rgb(lerp(r1, r2), lerp(g1, g2), lerp(b1, b2))
+        }
+        _ if t < 0.5 => a,
+        _ => b,
+    }
+}
+
+// The colors of the original hard-coded UI, drawn on the host terminal's background
+const TERMINAL: Palette = Palette {
+    background: Color::Reset,
+    pane: None,
+    text: Color::Gray,
+    muted: Color::DarkGray,
+    border: Color::White,
+    highlight: Color::Yellow,
+    success: Color::Green,
+    info: Color::Cyan,
+};
+
+const DRACULA: Palette = Palette {
+    background: Color::Rgb(0x28, 0x2a, 0x36),
+    pane: Some(Color::Rgb(0x21, 0x22, 0x2c)),
+    text: Color::Rgb(0xf8, 0xf8, 0xf2),
+    muted: Color::Rgb(0x62, 0x72, 0xa4),
+    border: Color::Rgb(0xbd, 0x93, 0xf9),
+    highlight: Color::Rgb(0xf1, 0xfa, 0x8c),
+    success: Color::Rgb(0x50, 0xfa, 0x7b),
+    info: Color::Rgb(0x8b, 0xe9, 0xfd),
+};
+
+const SOLARIZED_DARK: Palette = Palette {
+    background: Color::Rgb(0x00, 0x2b, 0x36),
+    pane: None,
+    text: Color::Rgb(0x83, 0x94, 0x96),
+    muted: Color::Rgb(0x58, 0x6e, 0x75),
+    border: Color::Rgb(0x93, 0xa1, 0xa1),
+    highlight: Color::Rgb(0xb5, 0x89, 0x00),
+    success: Color::Rgb(0x85, 0x99, 0x00),
+    info: Color::Rgb(0x2a, 0xa1, 0x98),
+};
+
+const SOLARIZED_LIGHT: Palette = Palette {
+    background: Color::Rgb(0xfd, 0xf6, 0xe3),
+    pane: Some(Color::Rgb(0xee, 0xe8, 0xd5)),
+    text: Color::Rgb(0x58, 0x6e, 0x75),
+    muted: Color::Rgb(0x93, 0xa1, 0xa1),
+    border: Color::Rgb(0x65, 0x7b, 0x83),
+    highlight: Color::Rgb(0xb5, 0x89, 0x00),
+    success: Color::Rgb(0x85, 0x99, 0x00),
+    info: Color::Rgb(0x26, 0x8b, 0xd2),
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BorderSet {
+    #[default]
+    Plain,
+    Rounded,
+    Double,
+    Heavy,
+    None,
+}
+
+impl BorderSet {
+    // An untitled block with this border, for widgets to title and style
+    pub fn block(self) -> Block<'static> {
+        let border_type = match self {
+            BorderSet::Plain | BorderSet::None => BorderType::Plain,
+            BorderSet::Rounded => BorderType::Rounded,
+            BorderSet::Double => BorderType::Double,
+            BorderSet::Heavy => BorderType::Thick,
+        };
+        let borders = if self == BorderSet::None { Borders::NONE } else { Borders::ALL };
+        Block::default().borders(borders).border_type(border_type)
+    }
+
+    // Cells the border takes on each side
+    pub fn width(self) -> u16 {
+        if self == BorderSet::None { 0 } else { 1 }
+    }
+}
+
+// Borders per widget class
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct BorderStyles {
+    // Output and auto-wire panel
+    #[serde(default)]
+    pub pane: BorderSet,
+    // Tabs, command input and status bar
+    #[serde(default)]
+    pub bar: BorderSet,
+    // Dialogs, completions, toasts, welcome and lock screens
+    #[serde(default)]
+    pub popup: BorderSet,
+}
+
+impl BorderStyles {
+    const fn all(set: BorderSet) -> Self {
+        Self { pane: set, bar: set, popup: set }
+    }
+}
+
+// Name, palette, interior padding and borders
+const BUILTIN: &[(&str, Palette, u16, BorderStyles)] = &[
+    ("terminal", TERMINAL, 0, BorderStyles::all(BorderSet::Plain)),
+    ("dracula", DRACULA, 1, BorderStyles::all(BorderSet::Rounded)),
+    ("solarized-dark", SOLARIZED_DARK, 1, BorderStyles::all(BorderSet::Rounded)),
+    ("solarized-light", SOLARIZED_LIGHT, 1, BorderStyles::all(BorderSet::Rounded)),
+];
+
 pub struct Theme {
     pub name: String,
+    pub palette: Palette,
+    // Columns between a pane's border and its contents
+    pub padding: u16,
+    // Fade panes that don't have focus
+    pub dim_inactive: bool,
+    pub borders: BorderStyles,
 }
 
 impl Theme {
+    // A built-in theme, or themes/<name>.toml from the config directory;
+    // falls back to dracula so a typo never leaves the UI unstyled
     pub fn load(name: &str) -> Self {
+        if let Some((_, palette, padding, borders)) = BUILTIN.iter().find(|(n, ..)| *n == name) {
+            return Self {
+                name: name.to_string(),
+                palette: *palette,
+                padding: *padding,
+                dim_inactive: true,
+                borders: *borders,
+            };
+        }
+        load_file(name).unwrap_or_else(|e| {
+            warn!("Theme {}: {:#}; using dracula", name, e);
+            Self { name: name.to_string(), ..Self::load("dracula") }
+        })
+    }
+}
+
+// themes/<name>.toml: every palette color as a name or #rrggbb, e.g.
+//   background = "#1e1e2e"
+//   pane_background = "#181825"   # optional
+//   text = "#cdd6f4"
+//   padding = 1                   # optional, default 0
+//   dim_inactive = false          # optional, default true
+//   [borders]                     # optional: plain, rounded, double, heavy or none
+//   pane = "rounded"
+//   bar = "none"
+//   popup = "double"
+#[derive(Deserialize)]
+struct ThemeFile {
+    background: String,
+    #[serde(default)]
+    pane_background: Option<String>,
+    text: String,
+    muted: String,
+    border: String,
+    highlight: String,
+    success: String,
+    info: String,
+    #[serde(default)]
+    padding: u16,
+    #[serde(default = "default_true")]
+    dim_inactive: bool,
+    #[serde(default)]
+    borders: BorderStyles,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn load_file(name: &str) -> Result<Theme> {
+    if name.contains('/') || name.starts_with('.') {
+        return Err(anyhow!("invalid theme name"));
+    }
+    let path = Config::dir().join("themes").join(format!("{}.toml", name));
+    let content = std::fs::read_to_string(&path).with_context(|| format!("no theme file {}", path.display()))?;
+    let file: ThemeFile = toml::from_str(&content).with_context(|| format!("invalid {}", path.display()))?;
+    let color = |value: &str| Color::from_str(value).map_err(|_| anyhow!("invalid color {:?}", value));
+    let palette = Palette {
+        background: color(&file.background)?,
+        pane: file.pane_background.as_deref().map(color).transpose()?,
+        text: color(&file.text)?,
+        muted: color(&file.muted)?,
+        border: color(&file.border)?,
+        highlight: color(&file.highlight)?,
+        success: color(&file.success)?,
+        info: color(&file.info)?,
+    };
+    Ok(Theme {
+        name: name.to_string(),
+        palette,
+        padding: file.padding,
+        dim_inactive: file.dim_inactive,
+        borders: file.borders,
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppearanceMode {
+    // Always `theme` from the config
+    #[default]
+    Fixed,
+    // Follow the OS dark-mode setting (XDG desktop portal, macOS)
+    Os,
+    // Light between `light_from` and `dark_from`, local time
+    Schedule,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppearanceConfig {
+    #[serde(default)]
+    pub mode: AppearanceMode,
+    #[serde(default = "default_light_theme")]
+    pub light_theme: String,
+    #[serde(default = "default_dark_theme")]
+    pub dark_theme: String,
+    // HH:MM
+    #[serde(default = "default_light_from")]
+    pub light_from: String,
+    #[serde(default = "default_dark_from")]
+    pub dark_from: String,
+}
+
+fn default_light_theme() -> String {
+    "solarized-light".to_string()
+}
+
+fn default_dark_theme() -> String {
+    "dracula".to_string()
+}
+
+fn default_light_from() -> String {
+    "07:00".to_string()
+}
+
+fn default_dark_from() -> String {
+    "19:00".to_string()
+}
+
+impl Default for AppearanceConfig {
+    fn default() -> Self {
         Self {
-            name: name.to_string(),
+            mode: AppearanceMode::default(),
+            light_theme: default_light_theme(),
+            dark_theme: default_dark_theme(),
+            light_from: default_light_from(),
+            dark_from: default_dark_from(),
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    Light,
+    Dark,
+}
+
+impl AppearanceConfig {
+    // The theme to start with: `fixed_theme` unless switching is enabled
+    pub fn initial_theme(&self, fixed_theme: &str) -> String {
+        match self.current_scheme() {
+            Some(scheme) => self.theme_for(scheme).to_string(),
+            None => fixed_theme.to_string(),
+        }
+    }
+
+    pub fn theme_for(&self, scheme: Scheme) -> &str {
+        match scheme {
+            Scheme::Light => &self.light_theme,
+            Scheme::Dark => &self.dark_theme,
+        }
+    }
+
+    // None in fixed mode, or when the OS doesn't say
+    pub fn current_scheme(&self) -> Option<Scheme> {
+        match self.mode {
+            AppearanceMode::Fixed => None,
+            AppearanceMode::Os => os_scheme(),
+            AppearanceMode::Schedule => self.scheduled_scheme(),
+        }
+    }
+
+    fn scheduled_scheme(&self) -> Option<Scheme> {
+        let light = parse_time(&self.light_from)?;
+        let dark = parse_time(&self.dark_from)?;
+        let now = local_minutes();
+        let is_light = if light <= dark {
+            now >= light && now < dark
+        } else {
+            now >= light || now < dark
+        };
+        Some(if is_light { Scheme::Light } else { Scheme::Dark })
+    }
+
+    // Background thread reporting scheme changes; None in fixed mode
+    pub fn watch(&self) -> Option<Receiver<Scheme>> {
+        let interval = match self.mode {
+            AppearanceMode::Fixed => return None,
+            AppearanceMode::Os => OS_POLL,
+            AppearanceMode::Schedule => SCHEDULE_POLL,
+        };
+        let config = self.clone();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut last = config.current_scheme();
+            loop {
+                std::thread::sleep(interval);
+                idle::wait_active();
+                let scheme = config.current_scheme();
+                if scheme != last {
+                    if let Some(scheme) = scheme {
+                        if tx.send(scheme).is_err() {
+                            return;
+                        }
+                    }
+                    last = scheme;
+                }
+            }
+        });
+        Some(rx)
+    }
+}
+
+fn parse_time(text: &str) -> Option<u32> {
+    let (hours, minutes) = text.trim().split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+// Minutes since local midnight
+fn local_minutes() -> u32 {
+    let now = clock::unix_secs();
+    let tm = calendar::local_tm(now);
+    (tm.tm_hour * 60 + tm.tm_min) as u32
+}
+
+fn os_scheme() -> Option<Scheme> {
+    if cfg!(target_os = "macos") {
+        // The key only exists while dark mode is on
+        let output = Command::new("defaults").args(["read", "-g", "AppleInterfaceStyle"]).output().ok()?;
+        let dark = output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "Dark";
+        return Some(if dark { Scheme::Dark } else { Scheme::Light });
+    }
+
+    // org.freedesktop.appearance color-scheme: 1 prefers dark, 2 prefers light, 0 no preference
+    let output = Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.portal.Desktop",
+            "--object-path",
+            "/org/freedesktop/portal/desktop",
+            "--method",
+            "org.freedesktop.portal.Settings.Read",
+            "org.freedesktop.appearance",
+            "color-scheme",
+        ])
+        .output()
+        .ok()?;
+    let reply = String::from_utf8_lossy(&output.stdout);
+    if reply.contains("uint32 1") {
+        Some(Scheme::Dark)
+    } else if reply.contains("uint32 2") {
+        Some(Scheme::Light)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blends_rgb_and_switches_named_colors() {
+        let faded = DRACULA.blend(&SOLARIZED_LIGHT, 0.5);
+        assert_eq!(faded.background, Color::Rgb(0x93, 0x90, 0x8d));
+        assert_eq!(TERMINAL.blend(&DRACULA, 0.4).text, Color::Gray);
+        assert_eq!(TERMINAL.blend(&DRACULA, 0.6).text, DRACULA.text);
+        assert_eq!(parse_time("19:30"), Some(19 * 60 + 30));
+        assert_eq!(parse_time("24:00"), None);
+    }
+}
@@ -1,321 +1,2110 @@
 // Terminal UI with Auto-Wiring Integration
 use anyhow::Result;
 use crossterm::{
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, KeyCode, KeyEvent},
+    execute, queue,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, BeginSynchronizedUpdate, EndSynchronizedUpdate, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span, Text},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, Padding, Paragraph, Tabs, Wrap},
     Frame, Terminal as RatatuiTerminal,
 };
-use std::io;
+use base64::Engine;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc::Receiver;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+use tracing::warn;
 
-use crate::core::terminal::Terminal;
+use crate::ai::proposals::{Proposal, Proposals};
+use crate::ai::review::{Finding, Level};
+use crate::ai::suggestions::{Completion, CompletionKind, SuggestionEngine};
+use crate::core::terminal::{Annotation, Notice, Severity, Terminal};
+use crate::plugins::manager::PluginInfo;
+use crate::core::actions::{self, Action, ActionRegistry, KeyChord};
+use crate::core::bell::{BellEffect, Ring};
+use crate::core::binary::BinaryOutput;
+use crate::core::daemons::DaemonInfo;
+use crate::core::operations::{Kind, OperationInfo};
+use crate::core::encoding::Encoding;
+use crate::core::environments::{self, EnvLabel};
+use crate::core::impact::{self, ImpactConfig};
+use crate::core::postprocess;
+use crate::core::rerun;
+use crate::core::scriptify::ScriptOptions;
+use crate::core::status::TabStatus;
+use crate::core::vt::{Cell, CellStyle, VtColor};
+use crate::core::workflow_deps::Node;
+use crate::core::workspace_search::{Corpus, Target};
+use crate::ui::animation::{self, Animator};
+use crate::ui::compat::{self, CompatMode, SlowLink};
+use crate::ui::demo::DemoPlayer;
+use crate::ui::glyphs::{GlyphMode, Glyphs};
+use crate::ui::anonymizer::{Anonymizer, DemoConfig};
+use crate::ui::latency::LatencyTracker;
+use crate::ui::memo::Memo;
+use crate::ui::scrollback;
+use crate::ui::state::{KeyAction, OutputChunk, Tab, TabEvent, UiEvent, UiState};
+use crate::ui::themes::{AppearanceConfig, Palette, Scheme, Theme};
+use crate::ui::zoom::{ZoomHost, ZoomStep};
+use crate::ui::widgets::autowire_panel::{AutoWirePanel, PanelAction};
+use crate::ui::widgets::completion_popup::CompletionPopup;
+use crate::ui::widgets::confirm_dialog::ConfirmDialog;
+use crate::ui::widgets::form::{self, Button, Form, FormOutcome};
+use crate::ui::widgets::batch_editor::{BatchAction, BatchEditor};
+use crate::ui::widgets::activity_panel::{progress_line, ActivityAction, ActivityPanel};
+use crate::ui::widgets::workflow_graph::{WorkflowGraph, WorkflowGraphAction};
+use crate::ui::widgets::key_hints::KeyHints;
+use crate::ui::widgets::daemons_panel::{DaemonsAction, DaemonsPanel};
+use crate::ui::widgets::variables_panel::{VariablesAction, VariablesPanel};
+use crate::ui::widgets::filter_view::FilterView;
+use crate::ui::widgets::workspace_search::{WorkspaceSearch, WorkspaceSearchAction};
+use crate::ui::widgets::history_search::{HistorySearch, SearchAction, SearchMode};
+use crate::ui::widgets::plugins_panel::{PluginsAction, PluginsPanel};
+use crate::ui::widgets::ssh_keys_panel::{SshKeysAction, SshKeysPanel};
+use crate::ui::widgets::problems_panel::{ProblemsAction, ProblemsPanel};
+use crate::ui::widgets::action_cards::{ActionCards, CardsAction};
+use crate::ui::widgets::hex_viewer::{HexAction, HexViewer};
+use crate::ui::widgets::context_menu::{ContextMenu, MenuAction, MenuCommand, MenuTarget};
+use crate::ui::widgets::command_palette::{CommandPalette, PaletteAction};
+use crate::ui::widgets::lock_screen::{LockInput, LockMode, LockScreen};
+use crate::ui::widgets::toast::ToastManager;
+use crate::ui::widgets::url_chips::UrlChips;
+use crate::ui::widgets::welcome::WelcomeView;
+use crate::utils::clock;
+use crate::utils::desktop;
 use crate::utils::config::Config;
+use crate::utils::history::HistoryItem;
+use crate::utils::idle;
+use crate::utils::job_control;
+use crate::utils::lock;
+use crate::utils::shell_escape::{self, ShellKind};
+use crate::utils::ssh_keys::SshKey;
+use crate::utils::sessions::RecentSessions;
+use crate::utils::startup::Pending;
+use crate::utils::storage::StorageConfig;
+
+mod input;
+
+// Below this size the layout cannot fit, so a placeholder is drawn instead
+pub const MIN_WIDTH: u16 = 40;
+pub const MIN_HEIGHT: u16 = 15;
+// Output lines a mouse wheel notch scrolls
+const WHEEL_LINES: usize = 3;
+// How long a visual bell inverts the output pane's border
+const FLASH_DURATION: Duration = Duration::from_millis(150);
+// Longest a frame is skipped for while nothing seems to change, for what
+// changes without telling (panels of live state, the theme schedule)
+const IDLE_FRAME: Duration = Duration::from_secs(1);
+
+// What the status line shows; it's laid out again only when one changes
+#[derive(PartialEq)]
+struct StatusInputs {
+    segments: String,
+    autowire: String,
+    // Active tab (from 1) and how many there are
+    tab: (usize, usize),
+    background: Option<String>,
+    queued: usize,
+    latency: Option<String>,
+    // Spinner frame, command and seconds it has run
+    running: Option<(&'static str, String, u64)>,
+    // A custom key layer's badge and keys
+    mode: Option<(String, String)>,
+    colors: (Color, Color),
+}
+
+fn status_line(inputs: &StatusInputs) -> Line<'static> {
+    let mut status_text = format!(
+        " {} | Auto-Wire: {} | Tab: {}/{} | Ctrl+H: Help ",
+        inputs.segments, inputs.autowire, inputs.tab.0, inputs.tab.1
+    );
+    if let Some(background) = &inputs.background {
+        status_text.push_str(&format!("| {} ", background));
+    }
+    if inputs.queued > 0 {
+        status_text.push_str(&format!("| Queued: {} ", inputs.queued));
+    }
+    if let Some(latency) = &inputs.latency {
+        status_text.push_str(&format!("| {} ", latency));
+    }
+    if let Some((spinner, command, secs)) = &inputs.running {
+        status_text.push_str(&format!("| {} {} ({}s) ", spinner, command, secs));
+    }
+
+    // A custom key layer shows first, with its keys
+    let mut line = Vec::new();
+    if let Some((title, hints)) = &inputs.mode {
+        let (background, highlight) = inputs.colors;
+        let badge = Style::default().fg(background).bg(highlight).add_modifier(Modifier::BOLD);
+        line.push(Span::styled(format!(" {} ", title), badge));
+        line.push(Span::styled(format!(" {} · esc leave |", hints), Style::default().fg(highlight)));
+    }
+    line.push(Span::raw(status_text));
+    Line::from(line)
+}
+
+// A guarded batch whose impact preview is being worked out off the UI thread
+struct ImpactCheck {
+    commands: Vec<String>,
+    changes: Vec<String>,
+    word: Option<&'static str>,
+    lines: Pending<Vec<String>>,
+}
+
+// What the commands' file-changing parts would touch, when that's at least
+// `min_files`
+fn impact_preview(commands: &[String], cwd: &Path, min_files: usize) -> Vec<String> {
+    let impacts: Vec<_> = commands.iter().flat_map(|command| impact::preview(command, cwd)).collect();
+    if impacts.iter().map(|impact| impact.touched()).sum::<usize>() < min_files {
+        return Vec::new();
+    }
+    impact::describe(&impacts, cwd)
+}
 
 pub struct TerminalUI {
-    terminal: RatatuiTerminal<CrosstermBackend<io::Stdout>>,
-    input_buffer: String,
-    cursor_pos: usize,
-    history_index: Option<usize>,
-    active_tab: usize,
-    tabs: Vec<String>,
-    show_help: bool,
+    // Borrowed mutably while draw_ui reads the rest of the struct
+    terminal: RefCell<RatatuiTerminal<CrosstermBackend<io::Stdout>>>,
+    // Raw mode and the alternate screen are on, and restored on drop; not for
+    // the headless UI of snapshot tests
+    attached: bool,
+    // Input line, tabs and scroll position, changed only through `dispatch`
+    state: UiState,
+    history_search: Option<HistorySearch>,
     show_autowire_panel: bool,
+    // AI annotations drawn inline; off, they're only marked
+    show_annotations: bool,
+    autowire_panel: AutoWirePanel,
+    completion_popup: CompletionPopup,
+    confirm: Option<ConfirmDialog>,
+    // The confirm dialog asks whether to quit
+    exiting: bool,
+    // Inputs an action asks for before it runs
+    action_form: Option<(Form, Action)>,
+    // Commands waiting on the confirmation of a prod-labeled tab
+    guarded: Option<Vec<String>>,
+    // Commands held while their impact preview is worked out
+    impact_check: Option<ImpactCheck>,
+    // Commands the guard let through after all, for main.rs to run
+    released: Option<Vec<String>>,
+    plugins_panel: Option<PluginsPanel>,
+    daemons_panel: Option<DaemonsPanel>,
+    variables_panel: Option<VariablesPanel>,
+    activity_panel: Option<ActivityPanel>,
+    workflow_graph: Option<WorkflowGraph>,
+    // After the prefix of a multi-chord binding
+    key_hints: Option<KeyHints>,
+    ssh_keys_panel: Option<SshKeysPanel>,
+    problems_panel: Option<ProblemsPanel>,
+    action_cards: Option<ActionCards>,
+    hex_viewer: Option<HexViewer>,
+    command_palette: Option<CommandPalette>,
+    context_menu: Option<ContextMenu>,
+    // Mouse events are captured (right-click menus, wheel scrolling)
+    mouse: bool,
+    filter_view: Option<FilterView>,
+    workspace_search: Option<WorkspaceSearch>,
+    batch_editor: Option<BatchEditor>,
+    suggestions: SuggestionEngine,
+    toasts: ToastManager,
+    // Visual bell: the output pane's border is inverted until then
+    flash_until: Option<Instant>,
+    welcome: Option<WelcomeView>,
+    // The startup check for the auto-wiring system, whose answer the welcome view shows
+    autowire_probe: Option<Pending<String>>,
+    // Hosts or containers being listed for the history overlay's Ctrl+X
+    rerun_choices: Option<(rerun::Place, Pending<Result<Vec<String>>>)>,
+    animator: Animator,
+    lock: Option<LockScreen>,
+    lock_hash: Option<String>,
+    lock_timeout: Option<Duration>,
+    // Background work stops after this long without input, see utils::idle
+    idle_suspend: Option<Duration>,
+    last_activity: Instant,
+    latency: LatencyTracker,
+    // The tab titles and the status line, as last laid out
+    tab_titles: Memo<(Vec<Tab>, Color), Vec<Line<'static>>>,
+    status_line: Memo<StatusInputs, Line<'static>>,
+    demo_config: DemoConfig,
+    storage_config: StorageConfig,
+    // Whether file-changing commands are held with a preview, see core::impact
+    impact: ImpactConfig,
+    // Quoting rules for paths inserted into the input line
+    shell_kind: ShellKind,
+    anonymizer: Option<Anonymizer>,
+    theme: Theme,
+    // Palette being faded out after a light/dark switch
+    previous_palette: Option<Palette>,
+    appearance: AppearanceConfig,
+    scheme_changes: Option<Receiver<Scheme>>,
+    // Borderless tab and status rows, for hosts whose font size can't be changed
+    compact: bool,
+    compact_default: bool,
+    zoom_host: ZoomHost,
+    glyphs: Glyphs,
+    glyph_mode: GlyphMode,
+    // The compatibility profile is on; `slow_link` watches for it under `auto`
+    compat: bool,
+    slow_link: Option<SlowLink>,
+    animations: bool,
+    url_chips: UrlChips,
+    // Input, output or a notice came since the last frame, see `needs_frame`
+    stale: bool,
+    last_frame: Instant,
+    // Frames skipped in a row while input was queued, see `handle_input`
+    skipped_frames: u32,
+    // Plays `nexterm demo`'s keystrokes
+    demo: Option<DemoPlayer>,
 }
 
 impl TerminalUI {
     pub fn new(config: &Config) -> Result<Self> {
+        job_control::install();
         enable_raw_mode()?;
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen)?;
+        execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
+        if config.mouse {
+            execute!(stdout, EnableMouseCapture)?;
+        }
         let backend = CrosstermBackend::new(stdout);
         let terminal = RatatuiTerminal::new(backend)?;
+        Ok(Self::with_terminal(config, terminal, true))
+    }
 
-        Ok(Self {
-            terminal,
-            input_buffer: String::new(),
-            cursor_pos: 0,
-            history_index: None,
-            active_tab: 0,
-            tabs: vec!["Terminal".to_string()],
-            show_help: false,
-            show_autowire_panel: false,
-        })
+    // Never touches the real terminal; snapshot tests draw it onto a
+    // TestBackend with `render_on`, see ui::snapshot
+    #[cfg(test)]
+    pub fn headless(config: &Config) -> Result<Self> {
+        let backend = CrosstermBackend::new(io::stdout());
+        let viewport = ratatui::Viewport::Fixed(Rect::default());
+        let terminal = RatatuiTerminal::with_options(backend, ratatui::TerminalOptions { viewport })?;
+        let mut ui = Self::with_terminal(config, terminal, false);
+        ui.set_compat(false);
+        ui.glyphs = Glyphs::fixed("linux | git:main | rust");
+        Ok(ui)
     }
 
-    pub fn show_welcome(&mut self) -> Result<()> {
-        self.show_welcome_with_autowire("Auto-wiring initializing...")
+    fn with_terminal(config: &Config, terminal: RatatuiTerminal<CrosstermBackend<io::Stdout>>, attached: bool) -> Self {
+        let compat = match config.compat {
+            CompatMode::On => true,
+            CompatMode::Off => false,
+            CompatMode::Auto => compat::dumb_terminal(std::env::var("TERM").ok().as_deref()),
+        };
+        Self {
+            terminal: RefCell::new(terminal),
+            attached,
+            state: UiState::default(),
+            history_search: None,
+            show_autowire_panel: false,
+            show_annotations: true,
+            autowire_panel: AutoWirePanel::new(),
+            completion_popup: CompletionPopup::new(),
+            confirm: None,
+            exiting: false,
+            action_form: None,
+            guarded: None,
+            impact_check: None,
+            released: None,
+            plugins_panel: None,
+            daemons_panel: None,
+            variables_panel: None,
+            activity_panel: None,
+            workflow_graph: None,
+            key_hints: None,
+            ssh_keys_panel: None,
+            problems_panel: None,
+            action_cards: None,
+            hex_viewer: None,
+            command_palette: None,
+            context_menu: None,
+            mouse: config.mouse,
+            filter_view: None,
+            workspace_search: None,
+            batch_editor: None,
+            suggestions: SuggestionEngine::for_shell(ShellKind::from_shell(&config.shell)),
+            shell_kind: ShellKind::from_shell(&config.shell),
+            toasts: ToastManager::new(),
+            welcome: None,
+            autowire_probe: None,
+            rerun_choices: None,
+            animator: Animator::new(config.animations && !compat),
+            lock: None,
+            lock_hash: config.lock_passphrase_hash.clone(),
+            lock_timeout: config.lock_timeout_secs.map(Duration::from_secs),
+            idle_suspend: (config.idle_suspend_mins > 0).then(|| Duration::from_secs(config.idle_suspend_mins * 60)),
+            last_activity: clock::now(),
+            flash_until: None,
+            latency: LatencyTracker::new(),
+            tab_titles: Memo::new(),
+            status_line: Memo::new(),
+            demo_config: config.demo.clone(),
+            storage_config: config.storage.clone(),
+            impact: config.impact.clone(),
+            anonymizer: config.demo.enabled.then(|| Anonymizer::new(&config.demo)),
+            theme: Theme::load(&config.appearance.initial_theme(&config.theme)),
+            previous_palette: None,
+            appearance: config.appearance.clone(),
+            scheme_changes: config.appearance.watch(),
+            compact: config.compact,
+            compact_default: config.compact,
+            zoom_host: if config.forward_zoom { ZoomHost::detect() } else { ZoomHost::Unsupported },
+            glyphs: Glyphs::new(if compat { GlyphMode::Ascii } else { config.glyphs }),
+            glyph_mode: config.glyphs,
+            compat,
+            slow_link: (config.compat == CompatMode::Auto && !compat && compat::over_ssh()).then(SlowLink::default),
+            animations: config.animations,
+            url_chips: UrlChips::new(),
+            stale: true,
+            last_frame: clock::now(),
+            skipped_frames: 0,
+            demo: None,
+        }
     }
 
     pub fn show_welcome_with_autowire(&mut self, autowire_status: &str) -> Result<()> {
-        let welcome_text = vec![
-            "╔══════════════════════════════════════════════════════════╗",
-            "║     🚀 NEXTERM - Revolutionary Terminal Experience      ║",
-            "║          with AI Auto-Wiring Integration                ║",
-            "╚══════════════════════════════════════════════════════════╝",
-            "",
-            &format!("Auto-Wiring Status: {}", autowire_status),
-            "",
-            "Quick Start:",
-            "  • Ctrl+T        - New tab",
-            "  • Ctrl+W        - Close tab",
-            "  • Ctrl+F        - Fuzzy finder",
-            "  • Ctrl+Space    - AI suggestions",
-            "  • Ctrl+A        - Auto-wire status",
-            "  • Ctrl+S        - Auto-wire services",
-            "  • Ctrl+C        - Exit",
-            "",
-            "AI Commands:",
-            "  ai <command>    - Process command through AI",
-            "  autowire list   - List auto-wire services",
-            "  autowire status - Show auto-wire status",
-            "",
-            "Type 'help' for more commands",
-            "",
-        ];
-
-        for line in welcome_text {
-            println!("{}", line);
-        }
-
-        std::thread::sleep(std::time::Duration::from_secs(2));
+        let mut sessions = RecentSessions::load(&self.storage_config);
+        let recent = sessions.entries.clone();
+        if let Err(e) = sessions.record_current() {
+            self.notify(Notice {
+                severity: Severity::Warning,
+                message: format!("Could not record session: {}", e),
+            });
+        }
+
+        self.welcome = Some(WelcomeView::new(autowire_status, recent));
         Ok(())
     }
 
+    // The welcome view's auto-wiring status, once `probe` answers
+    pub fn probe_autowire(&mut self, probe: Pending<String>) {
+        self.autowire_probe = Some(probe);
+    }
+
+    pub fn check_startup(&mut self) {
+        let Some(Poll::Ready(status)) = self.autowire_probe.as_mut().map(Pending::poll) else {
+            return;
+        };
+        self.autowire_probe = None;
+        if let (Some(welcome), Some(status)) = (&mut self.welcome, status) {
+            welcome.set_autowire_status(&status);
+        }
+    }
+
+    pub fn check_rerun_choices(&mut self) {
+        let Some((place, Poll::Ready(choices))) = self.rerun_choices.as_mut().map(|(place, p)| (*place, p.poll()))
+        else {
+            return;
+        };
+        self.rerun_choices = None;
+        match (choices, self.history_search.as_mut()) {
+            (Some(Ok(choices)), Some(search)) => {
+                search.choose(place, choices);
+                self.stale = true;
+            }
+            (Some(Err(e)), _) => self.notify(Notice { severity: Severity::Error, message: format!("{:#}", e) }),
+            _ => {}
+        }
+    }
+
+    pub fn is_welcome_visible(&self) -> bool {
+        self.welcome.is_some()
+    }
+
+    pub fn dismiss_welcome(&mut self) {
+        self.welcome = None;
+    }
+
+    // Confirmation dialog; it takes all keys until answered
+    pub fn show_confirm(&mut self, dialog: ConfirmDialog) {
+        self.stale = true;
+        self.confirm = Some(dialog);
+        // A guarded batch whose dialog is replaced doesn't run
+        self.guarded = None;
+        self.impact_check = None;
+        self.exiting = false;
+    }
+
+    // The commands that may run now. In a prod-labeled tab, a batch with any
+    // mutating command is held until the confirm word is typed back; with
+    // impact previews on, one that changes enough files is held for a yes/no
+    pub fn guard(&mut self, commands: Vec<String>) -> Option<Vec<String>> {
+        self.guard_rerun(commands, Vec::new())
+    }
+
+    // For re-runs from history: also held for a yes/no while `changes` lists
+    // how the context differs from the one the command was recorded in
+    fn guard_rerun(&mut self, commands: Vec<String>, changes: Vec<String>) -> Option<Vec<String>> {
+        let word = self
+            .tab_env()
+            .and_then(EnvLabel::confirm_word)
+            .filter(|_| commands.iter().any(|c| environments::is_mutating(c)));
+        if self.impact.preview && commands.iter().any(|c| impact::applies(c)) {
+            let (listed, min_files) = (commands.clone(), self.impact.min_files);
+            let cwd = std::env::current_dir().unwrap_or_default();
+            let lines = Pending::spawn("Impact preview", move || impact_preview(&listed, &cwd, min_files));
+            self.impact_check = Some(ImpactCheck { commands, changes, word, lines });
+            return None;
+        }
+        self.confirm_guarded(commands, changes, word, Vec::new())
+    }
+
+    // Shows the guard's dialog, if there's anything to confirm
+    fn confirm_guarded(
+        &mut self,
+        commands: Vec<String>,
+        changes: Vec<String>,
+        word: Option<&'static str>,
+        impact: Vec<String>,
+    ) -> Option<Vec<String>> {
+        if word.is_none() && changes.is_empty() && impact.is_empty() {
+            return Some(commands);
+        }
+        let about = match word {
+            Some(word) => format!("This tab is labeled {}. About to run:", word),
+            None => "About to run:".to_string(),
+        };
+        let impact_shown = !impact.is_empty();
+        let lines = changes
+            .iter()
+            .cloned()
+            .chain(std::iter::once(about))
+            .chain(commands.iter().map(|c| format!("  $ {}", c)))
+            .chain(impact_shown.then(|| "Files it would change:".to_string()))
+            .chain(impact)
+            .collect();
+        let dialog = match word {
+            Some(word) => ConfirmDialog::new(format!(" {} ", word), lines).with_phrase(word),
+            None if changes.is_empty() => ConfirmDialog::new(" Check the files ", lines),
+            None => ConfirmDialog::new(" Re-run ", lines),
+        };
+        self.show_confirm(dialog);
+        self.guarded = Some(commands);
+        None
+    }
+
+    // Once the preview is ready: the dialog, or the commands let through
+    pub fn check_impact(&mut self) {
+        let Some(Poll::Ready(lines)) = self.impact_check.as_mut().map(|check| check.lines.poll()) else {
+            return;
+        };
+        let Some(ImpactCheck { commands, changes, word, .. }) = self.impact_check.take() else {
+            return;
+        };
+        self.released = self.confirm_guarded(commands, changes, word, lines.unwrap_or_default());
+    }
+
+    // Set once the guard's dialog is answered
+    pub fn take_guarded(&mut self) -> Option<Vec<String>> {
+        self.guarded.take()
+    }
+
+    // Set when an impact preview turned out not to need a dialog
+    pub fn take_released(&mut self) -> Option<Vec<String>> {
+        self.released.take()
+    }
+
+    // A guarded batch is waiting, on its dialog or its preview
+    pub fn is_guarding(&self) -> bool {
+        self.confirm.is_some() || self.impact_check.is_some()
+    }
+
+    pub fn is_confirm_visible(&self) -> bool {
+        self.confirm.is_some()
+    }
+
+    // Set once the exit dialog is answered
+    pub fn take_exiting(&mut self) -> bool {
+        std::mem::take(&mut self.exiting)
+    }
+
+    // The form for an action's inputs; it takes all keys until submitted
+    pub fn open_action_form(&mut self, action: Action) {
+        self.action_form = Some((form::for_action(&action), action));
+    }
+
+    pub fn is_action_form_visible(&self) -> bool {
+        self.action_form.is_some()
+    }
+
+    // The action's command line with the answers filled in, once submitted
+    pub fn action_form_input(&mut self, code: KeyCode) -> Option<String> {
+        let outcome = self.action_form.as_mut()?.0.handle_key(code)?;
+        let (_, action) = self.action_form.take()?;
+        let (FormOutcome::Submit { values, .. }, actions::Target::Command(command)) = (outcome, &action.target) else {
+            return None;
+        };
+        let shell = self.shell_kind;
+        Some(actions::fill(command, &action.inputs, &values, |word| shell.quote(word)))
+    }
+
+    pub fn show_plugins_panel(&mut self, plugins: Vec<PluginInfo>) {
+        self.plugins_panel = Some(PluginsPanel::new(plugins));
+    }
+
+    pub fn is_plugins_panel_visible(&self) -> bool {
+        self.plugins_panel.is_some()
+    }
+
+    pub fn update_plugins_panel(&mut self, plugins: Vec<PluginInfo>) {
+        if let Some(panel) = &mut self.plugins_panel {
+            panel.update(plugins);
+        }
+    }
+
+    // Closing is handled here; toggles are left to the caller
+    pub fn plugins_panel_input(&mut self, code: KeyCode) -> Option<PluginsAction> {
+        match self.plugins_panel.as_mut()?.handle_key(code)? {
+            PluginsAction::Close => {
+                self.plugins_panel = None;
+                None
+            }
+            action => Some(action),
+        }
+    }
+
+    pub fn show_daemons_panel(&mut self, daemons: Vec<DaemonInfo>) {
+        self.daemons_panel = Some(DaemonsPanel::new(daemons));
+    }
+
+    pub fn is_daemons_panel_visible(&self) -> bool {
+        self.daemons_panel.is_some()
+    }
+
+    pub fn update_daemons_panel(&mut self, daemons: Vec<DaemonInfo>) {
+        if let Some(panel) = &mut self.daemons_panel {
+            panel.update(daemons);
+        }
+    }
+
+    pub fn daemons_panel_input(&mut self, code: KeyCode) -> Option<DaemonsAction> {
+        match self.daemons_panel.as_mut()?.handle_key(code)? {
+            DaemonsAction::Close => {
+                self.daemons_panel = None;
+                None
+            }
+            action => Some(action),
+        }
+    }
+
+    pub fn show_variables_panel(&mut self, variables: &BTreeMap<String, String>) {
+        self.variables_panel = Some(VariablesPanel::new(variables));
+    }
+
+    pub fn is_variables_panel_visible(&self) -> bool {
+        self.variables_panel.is_some()
+    }
+
+    pub fn update_variables_panel(&mut self, variables: &BTreeMap<String, String>) {
+        if let Some(panel) = &mut self.variables_panel {
+            panel.update(variables);
+        }
+    }
+
+    // Inserting a variable closes the panel
+    pub fn variables_panel_input(&mut self, code: KeyCode) -> Option<VariablesAction> {
+        match self.variables_panel.as_mut()?.handle_key(code)? {
+            VariablesAction::Close => {
+                self.variables_panel = None;
+                None
+            }
+            VariablesAction::Insert(name) => {
+                self.variables_panel = None;
+                self.insert_text(&format!("{{{{{}}}}}", name));
+                None
+            }
+            action => Some(action),
+        }
+    }
+
+    pub fn show_activity_panel(&mut self, operations: Vec<OperationInfo>) {
+        self.activity_panel = Some(ActivityPanel::new(operations));
+    }
+
+    pub fn is_activity_panel_visible(&self) -> bool {
+        self.activity_panel.is_some()
+    }
+
+    pub fn update_activity_panel(&mut self, operations: Vec<OperationInfo>) {
+        if let Some(panel) = &mut self.activity_panel {
+            panel.update(operations);
+        }
+    }
+
+    pub fn activity_panel_input(&mut self, code: KeyCode) -> Option<ActivityAction> {
+        match self.activity_panel.as_mut()?.handle_key(code)? {
+            ActivityAction::Close => {
+                self.activity_panel = None;
+                None
+            }
+            action => Some(action),
+        }
+    }
+
+    // Waits for the rest of a multi-chord binding, see core::actions
+    pub fn show_key_hints(&mut self, pressed: Vec<KeyChord>, continuations: Vec<(String, String)>) {
+        self.key_hints = Some(KeyHints::new(pressed, continuations));
+    }
+
+    // The chords of a binding pressed so far, if any
+    pub fn take_pending_keys(&mut self) -> Vec<KeyChord> {
+        self.key_hints.take().map(KeyHints::into_pressed).unwrap_or_default()
+    }
+
+    pub fn show_workflow_graph(&mut self, workflow: &str, nodes: Vec<Node>) {
+        self.workflow_graph = Some(WorkflowGraph::new(workflow, nodes));
+    }
+
+    pub fn is_workflow_graph_visible(&self) -> bool {
+        self.workflow_graph.is_some()
+    }
+
+    pub fn workflow_graph_input(&mut self, code: KeyCode) -> Option<WorkflowGraphAction> {
+        let action = self.workflow_graph.as_mut()?.handle_key(code)?;
+        // Running it closes the graph, so the output shows
+        self.workflow_graph = None;
+        match action {
+            WorkflowGraphAction::Close => None,
+            action => Some(action),
+        }
+    }
+
+    pub fn show_ssh_keys_panel(&mut self, keys: Vec<SshKey>) {
+        self.ssh_keys_panel = Some(SshKeysPanel::new(keys));
+    }
+
+    pub fn is_ssh_keys_panel_visible(&self) -> bool {
+        self.ssh_keys_panel.is_some()
+    }
+
+    pub fn update_ssh_keys_panel(&mut self, keys: Vec<SshKey>) {
+        if let Some(panel) = &mut self.ssh_keys_panel {
+            panel.update(keys);
+        }
+    }
+
+    pub fn ssh_keys_panel_input(&mut self, code: KeyCode) -> Option<SshKeysAction> {
+        match self.ssh_keys_panel.as_mut()?.handle_key(code)? {
+            SshKeysAction::Close => {
+                self.ssh_keys_panel = None;
+                None
+            }
+            action => Some(action),
+        }
+    }
+
+    pub fn show_problems_panel(&mut self, findings: Vec<Finding>) {
+        self.problems_panel = Some(ProblemsPanel::new(findings));
+    }
+
+    pub fn is_problems_panel_visible(&self) -> bool {
+        self.problems_panel.is_some()
+    }
+
+    pub fn problems_panel_input(&mut self, code: KeyCode) -> Option<ProblemsAction> {
+        match self.problems_panel.as_mut()?.handle_key(code)? {
+            ProblemsAction::Close => {
+                self.problems_panel = None;
+                None
+            }
+            action => Some(action),
+        }
+    }
+
+    pub fn show_action_cards(&mut self, request: &str, proposals: Proposals) {
+        self.action_cards = Some(ActionCards::new(request, proposals));
+    }
+
+    pub fn is_action_cards_visible(&self) -> bool {
+        self.action_cards.is_some()
+    }
+
+    pub fn action_cards_input(&mut self, code: KeyCode) -> Option<CardsAction> {
+        match self.action_cards.as_mut()?.handle_key(code)? {
+            CardsAction::Close => {
+                self.action_cards = None;
+                None
+            }
+            action => Some(action),
+        }
+    }
+
+    pub fn proposed_action(&self, index: usize) -> Option<Proposal> {
+        self.action_cards.as_ref()?.proposal(index).cloned()
+    }
+
+    pub fn resolve_action(&mut self, index: usize, result: Result<serde_json::Value>) {
+        if let Some(cards) = &mut self.action_cards {
+            cards.resolve(index, result);
+        }
+    }
+
+    // The request and the results to send for another round, when it's time
+    pub fn next_action_round(&mut self) -> Option<(String, Vec<serde_json::Value>)> {
+        let cards = self.action_cards.as_mut()?;
+        let results = cards.next_round()?;
+        Some((cards.request().to_string(), results))
+    }
+
+    pub fn add_action_cards(&mut self, proposals: Proposals) {
+        if let Some(cards) = &mut self.action_cards {
+            cards.add(proposals);
+        }
+    }
+
+    pub fn show_hex_viewer(&mut self, output: &BinaryOutput) {
+        self.hex_viewer = Some(HexViewer::new(output));
+    }
+
+    pub fn is_hex_viewer_visible(&self) -> bool {
+        self.hex_viewer.is_some()
+    }
+
+    // Saving closes the viewer, for the path to be typed
+    pub fn hex_viewer_input(&mut self, code: KeyCode) -> Option<HexAction> {
+        let action = self.hex_viewer.as_mut()?.handle_key(code)?;
+        self.hex_viewer = None;
+        match action {
+            HexAction::Close => None,
+            action => Some(action),
+        }
+    }
+
+    // Ctrl+P: the registry's actions, each with its key if it has one
+    pub fn open_command_palette(&mut self, actions: &ActionRegistry) {
+        let entries = actions
+            .actions()
+            .iter()
+            .map(|action| (action.clone(), actions.key_for(&action.id).map(|key| key.label())))
+            .collect();
+        self.command_palette = Some(CommandPalette::new(entries));
+    }
+
+    pub fn is_command_palette_visible(&self) -> bool {
+        self.command_palette.is_some()
+    }
+
+    // The action to run once one is picked; the palette closes either way
+    pub fn command_palette_input(&mut self, key: KeyEvent) -> Option<Action> {
+        let action = self.command_palette.as_mut()?.handle_key(key)?;
+        self.command_palette = None;
+        match action {
+            PaletteAction::Run(action) => Some(action),
+            PaletteAction::Close => None,
+        }
+    }
+
+    // Right-click: a menu for the output row, tab or input under the pointer
+    pub fn open_context_menu(&mut self, terminal: &Terminal, column: u16, row: u16) {
+        if let Some(target) = self.target_at(terminal, column, row) {
+            self.context_menu = ContextMenu::new(target, (column, row));
+        }
+    }
+
+    // Menu key: for the line at the top of the view while scrolled back,
+    // otherwise for the input, opening at the cursor
+    pub fn open_keyboard_menu(&mut self, terminal: &Terminal) {
+        let size = self.terminal.borrow().size().unwrap_or_default();
+        let regions = self.regions(size);
+        let inner = self.pane_block(&self.theme.palette).inner(self.output_area(regions[1]));
+        let target = match self.state.scroll_top() {
+            Some(_) => self.shown_rows(terminal, inner.width, inner.height).into_iter().next().map(|(text, line)| {
+                MenuTarget::Output(text, prompt_at(terminal, line))
+            }),
+            None => Some(MenuTarget::Input(self.state.input().to_string())),
+        };
+        let at = match target {
+            Some(MenuTarget::Output(..)) => (inner.x, inner.y),
+            _ => {
+                let inset = self.theme.borders.bar.width();
+                (regions[2].x + inset + 2 + self.state.cursor() as u16, regions[2].y + inset)
+            }
+        };
+        self.context_menu = target.and_then(|target| ContextMenu::new(target, at));
+    }
+
+    pub fn is_context_menu_visible(&self) -> bool {
+        self.context_menu.is_some()
+    }
+
+    pub fn context_menu_input(&mut self, code: KeyCode) -> Option<MenuCommand> {
+        let action = self.context_menu.as_mut()?.handle_key(code)?;
+        self.context_menu = None;
+        match action {
+            MenuAction::Run(command) => Some(command),
+            MenuAction::Close => None,
+        }
+    }
+
+    // Any click while the menu is open goes to it
+    pub fn context_menu_click(&mut self, column: u16, row: u16) -> Option<MenuCommand> {
+        let area = self.terminal.borrow().size().unwrap_or_default();
+        let action = self.context_menu.take()?.handle_click(column, row, area);
+        match action {
+            MenuAction::Run(command) => Some(command),
+            MenuAction::Close => None,
+        }
+    }
+
+    // What the pointer is over, if it offers a menu
+    fn target_at(&self, terminal: &Terminal, column: u16, row: u16) -> Option<MenuTarget> {
+        let size = self.terminal.borrow().size().ok()?;
+        if self.lock.is_some() || size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+            return None;
+        }
+        let regions = self.regions(size);
+        if contains(regions[0], column, row) {
+            return self.tab_at(regions[0], column).map(MenuTarget::Tab);
+        }
+        if contains(regions[2], column, row) {
+            return Some(MenuTarget::Input(self.state.input().to_string()));
+        }
+        // Full-screen programs take their own clicks
+        if terminal.live_screen().is_some() {
+            return None;
+        }
+        let inner = self.pane_block(&self.theme.palette).inner(self.output_area(regions[1]));
+        if !contains(inner, column, row) {
+            return None;
+        }
+        let rows = self.shown_rows(terminal, inner.width, inner.height);
+        let (text, line) = rows.into_iter().nth((row - inner.y) as usize)?;
+        Some(MenuTarget::Output(text, prompt_at(terminal, line)))
+    }
+
+    // The output pane within the main content
+    fn output_area(&self, content: Rect) -> Rect {
+        if self.show_autowire_panel {
+            self.autowire_panel.split(content).0
+        } else {
+            content
+        }
+    }
+
+    // Laid out as ratatui's Tabs does: a space either side of each title and
+    // a one-column divider between them
+    fn tab_at(&self, area: Rect, column: u16) -> Option<usize> {
+        let inner = if self.compact { area } else { self.theme.borders.bar.block().inner(area) };
+        let mut x = inner.x;
+        for (i, tab) in self.state.tabs().iter().enumerate() {
+            let label = tab.title.chars().count()
+                + tab.env.map_or(0, |env| env.name().chars().count() + 3)
+                + if tab.muted { 2 } else { 0 };
+            let width = label as u16 + 2;
+            if (x..x + width).contains(&column) {
+                return Some(i);
+            }
+            x += width + 1;
+        }
+        None
+    }
+
+    // Mouse wheel over the output
+    pub fn scroll_output(&mut self, up: bool) {
+        let page = WHEEL_LINES;
+        self.dispatch(UiEvent::Key(if up { KeyAction::PageUp { page } } else { KeyAction::PageDown { page } }));
+    }
+
+    pub fn show_filter_view(&mut self, pipeline: &str, lines: Vec<String>) {
+        self.filter_view = Some(FilterView::new(pipeline, lines));
+    }
+
+    pub fn is_filter_view_visible(&self) -> bool {
+        self.filter_view.is_some()
+    }
+
+    pub fn filter_view_input(&mut self, code: KeyCode) {
+        if self.filter_view.as_mut().is_some_and(|view| view.handle_key(code)) {
+            self.filter_view = None;
+        }
+    }
+
+    pub fn show_workspace_search(&mut self, corpus: Corpus, query: &str) {
+        self.workspace_search = Some(WorkspaceSearch::new(corpus, query));
+    }
+
+    pub fn is_workspace_search_visible(&self) -> bool {
+        self.workspace_search.is_some()
+    }
+
+    // Output matches scroll the pane to them; buffers and logs open in a view
+    pub fn workspace_search_input(&mut self, code: KeyCode) {
+        let Some(action) = self.workspace_search.as_mut().and_then(|search| search.handle_key(code)) else {
+            return;
+        };
+        self.workspace_search = None;
+        match action {
+            WorkspaceSearchAction::Jump(Target::Output(line)) => {
+                self.dispatch(UiEvent::Key(KeyAction::ScrollTo(Some(line))));
+            }
+            WorkspaceSearchAction::Jump(Target::Document { title, lines, line }) => {
+                self.filter_view = Some(FilterView::new(&title, lines).at(line));
+            }
+            WorkspaceSearchAction::Close => {}
+        }
+    }
+
+    pub fn open_batch_editor(&mut self, title: &str, text: &str) {
+        self.batch_editor = Some(BatchEditor::new(title, text));
+    }
+
+    pub fn is_batch_editor_visible(&self) -> bool {
+        self.batch_editor.is_some()
+    }
+
+    // `scriptify`: recent commands to pick and order for a script
+    pub fn open_scriptify(&mut self, lines: Vec<(String, bool)>, options: ScriptOptions) {
+        self.batch_editor = Some(BatchEditor::checklist("Scriptify", lines, options));
+    }
+
+    // The commands to run (or script) once the batch is confirmed
+    pub fn batch_editor_input(&mut self, key: KeyEvent) -> Option<BatchAction> {
+        let action = self.batch_editor.as_mut()?.handle_key(key)?;
+        self.batch_editor = None;
+        match action {
+            BatchAction::Close => None,
+            action => Some(action),
+        }
+    }
+
+    pub fn is_history_search_visible(&self) -> bool {
+        self.history_search.is_some()
+    }
+
+    // Keys for the Ctrl+R / Ctrl+F overlay; a chosen command replaces the
+    // input. Returns a re-run to submit now, unless it waits on confirmation.
+    pub fn history_search_input(&mut self, key: KeyEvent) -> Result<Option<Vec<String>>> {
+        let Some(search) = self.history_search.as_mut() else {
+            return Ok(None);
+        };
+        let Some(action) = search.handle_key(key) else {
+            return Ok(None);
+        };
+        let rerun = match action {
+            // `docker ps` can take a while; offered once it answers
+            SearchAction::Choose(place) => {
+                self.rerun_choices = Some((place, Pending::spawn("Re-run targets", move || rerun::choices(place))));
+                return Ok(None);
+            }
+            SearchAction::Insert(command) => {
+                self.dispatch(UiEvent::Key(KeyAction::SetInput(command)));
+                None
+            }
+            SearchAction::Rerun(rerun) => Some(rerun),
+            SearchAction::Close => None,
+        };
+        self.history_search = None;
+        let Some(rerun) = rerun else {
+            return Ok(None);
+        };
+        let command = rerun.command_line(self.shell_kind)?;
+        Ok(self.guard_rerun(vec![command], rerun.changes(&std::env::current_dir()?)))
+    }
+
+    pub fn is_url_chips_focused(&self) -> bool {
+        self.url_chips.is_focused()
+    }
+
+    pub fn toggle_url_chips(&mut self) {
+        self.url_chips.toggle_focus();
+    }
+
+    // Returns whether annotations are now shown
+    pub fn toggle_annotations(&mut self) -> bool {
+        self.show_annotations = !self.show_annotations;
+        self.show_annotations
+    }
+
+    // The local URL chosen from the chips, to open in the browser
+    pub fn url_chips_input(&mut self, code: KeyCode) -> Option<String> {
+        self.url_chips.handle_key(code)
+    }
+
+    pub fn confirm_input(&mut self, code: KeyCode) -> Option<bool> {
+        let answer = self.confirm.as_mut()?.handle_key(code)?;
+        self.confirm = None;
+        Some(answer)
+    }
+
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
     pub fn render(&mut self, terminal: &Terminal) -> Result<()> {
-        self.terminal.draw(|f| {
-            self.draw_ui(f, terminal);
-        })?;
+        self.toasts.expire();
+        terminal.with_output(|output| self.url_chips.scan(output));
+        let start = clock::now();
+        // DEC synchronized update: the host shows the frame once it's all there
+        if self.attached {
+            queue!(self.terminal.get_mut().backend_mut(), BeginSynchronizedUpdate)?;
+        }
+        self.terminal.borrow_mut().draw(|f| self.draw_ui(f, terminal))?;
+        if self.attached {
+            execute!(self.terminal.get_mut().backend_mut(), EndSynchronizedUpdate)?;
+        }
+        self.stale = false;
+        self.last_frame = clock::now();
+        self.latency.frame_rendered();
+        self.check_slow_link(clock::since(start));
+        Ok(())
+    }
+
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    // Whether the next frame would differ from the last. Frames are only
+    // skipped while nothing moves: no command running, no animation, toast or
+    // key hints, and no input, output or notice since the last frame.
+    pub fn needs_frame(&self, terminal: &Terminal) -> bool {
+        self.stale
+            || terminal.running_command().is_some()
+            || self.animator.is_animating()
+            || self.is_flashing()
+            || self.toasts.is_showing()
+            || self.key_hints.is_some()
+            || clock::since(self.last_frame) >= IDLE_FRAME
+    }
+
+    // `auto` over SSH: frames that keep taking long to get out mean a slow link
+    fn check_slow_link(&mut self, took: Duration) {
+        if self.slow_link.as_mut().is_some_and(|link| link.frame_drawn(took)) {
+            self.set_compat(true);
+            self.notify(Notice {
+                severity: Severity::Info,
+                message: "Slow link: compatibility profile on (`compat off` to undo)".to_string(),
+            });
+        }
+    }
+
+    // Once switched by hand, `auto` stops watching
+    pub fn set_compat(&mut self, on: bool) {
+        self.compat = on;
+        self.slow_link = None;
+        self.animator = Animator::new(self.animations && !on);
+        self.glyphs = Glyphs::new(if on { GlyphMode::Ascii } else { self.glyph_mode });
+        // Cells that held emoji are redrawn in full
+        if self.attached {
+            let _ = self.terminal.get_mut().clear();
+        }
+    }
+
+    pub fn is_compat(&self) -> bool {
+        self.compat
+    }
+
+    #[cfg(test)]
+    pub fn render_on<B: ratatui::backend::Backend>(&mut self, screen: &mut RatatuiTerminal<B>, terminal: &Terminal) -> Result<()> {
+        self.toasts.expire();
+        terminal.with_output(|output| self.url_chips.scan(output));
+        screen.draw(|f| self.draw_ui(f, terminal))?;
         Ok(())
     }
 
+    #[cfg_attr(feature = "profiling", tracing::instrument(skip_all))]
     fn draw_ui(&self, f: &mut Frame, terminal: &Terminal) {
+        self.draw_frame(f, terminal);
+        if self.compat {
+            compat::degrade(f.buffer_mut());
+        }
+    }
+
+    fn draw_frame(&self, f: &mut Frame, terminal: &Terminal) {
         let size = f.size();
+        let mut palette = self.palette();
+        if let Some(env) = self.tab_env() {
+            palette.border = env_color(env);
+        }
+        f.render_widget(Block::default().style(Style::default().bg(palette.background)), size);
+
+        if let Some(lock) = &self.lock {
+            lock.render(f, size, self.theme.borders.popup);
+            return;
+        }
+
+        if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+            self.draw_too_small(f, size, &palette);
+            return;
+        }
 
         // Main layout
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(3),  // Tabs
-                Constraint::Min(10),    // Main content
-                Constraint::Length(3),  // Input
-                Constraint::Length(3),  // Status bar
-            ])
-            .split(size);
+        let chunks = self.regions(size);
 
         // Draw tabs
-        self.draw_tabs(f, chunks[0]);
+        self.draw_tabs(f, chunks[0], &palette);
 
         // Draw main content (split if autowire panel is shown)
         if self.show_autowire_panel {
-            let content_chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([
-                    Constraint::Percentage(70),
-                    Constraint::Percentage(30),
-                ])
-                .split(chunks[1]);
-
-            self.draw_output(f, terminal, content_chunks[0]);
-            self.draw_autowire_panel(f, terminal, content_chunks[1]);
+            // Whichever side doesn't have focus fades when the theme asks for it
+            let dimmed = if self.theme.dim_inactive { palette.dimmed() } else { palette };
+            let (output_palette, panel_palette) = if self.autowire_panel.is_focused() {
+                (dimmed, palette)
+            } else {
+                (palette, dimmed)
+            };
+            let (content, panel) = self.autowire_panel.split(chunks[1]);
+            self.draw_output(f, terminal, content, &output_palette);
+            self.draw_autowire_panel(f, terminal, panel, &panel_palette);
         } else {
-            self.draw_output(f, terminal, chunks[1]);
+            self.draw_output(f, terminal, chunks[1], &palette);
+        }
+
+        // Draw input
+        self.draw_input(f, terminal, chunks[2], &palette);
+
+        // Draw status bar
+        self.draw_status_bar(f, terminal, chunks[3], &palette);
+
+        if let Some(hints) = &self.key_hints {
+            hints.render(f, chunks[1], self.theme.borders.popup);
+        }
+
+        // Completion dropdown floats over everything, anchored at the input cursor
+        let inset = self.theme.borders.bar.width();
+        let cursor = (chunks[2].x + inset + 2 + self.state.cursor() as u16, chunks[2].y + inset);
+        let popup = self.theme.borders.popup;
+        self.completion_popup.render(f, cursor, size, popup);
+
+        if let Some(welcome) = &self.welcome {
+            welcome.render(f, size, popup);
+        }
+
+        if let Some(panel) = &self.plugins_panel {
+            panel.render(f, size, popup);
+        }
+
+        if let Some(panel) = &self.daemons_panel {
+            panel.render(f, size, popup);
+        }
+
+        if let Some(panel) = &self.variables_panel {
+            panel.render(f, size, popup);
+        }
+
+        if let Some(panel) = &self.activity_panel {
+            panel.render(f, size, popup);
+        }
+
+        if let Some(graph) = &self.workflow_graph {
+            graph.render(f, size, popup);
+        }
+
+        if let Some(panel) = &self.ssh_keys_panel {
+            panel.render(f, size, popup);
+        }
+
+        if let Some(panel) = &self.problems_panel {
+            panel.render(f, size, popup);
+        }
+
+        if let Some(cards) = &self.action_cards {
+            cards.render(f, size, popup);
+        }
+
+        if let Some(viewer) = &self.hex_viewer {
+            viewer.render(f, size, popup);
+        }
+
+        if let Some(view) = &self.filter_view {
+            view.render(f, size, popup);
+        }
+
+        if let Some(search) = &self.workspace_search {
+            search.render(f, size, popup);
+        }
+
+        if let Some(editor) = &self.batch_editor {
+            editor.render(f, size, popup);
+        }
+
+        if let Some(search) = &self.history_search {
+            search.render(f, size, popup);
+        }
+
+        if let Some(palette) = &self.command_palette {
+            palette.render(f, size, popup);
+        }
+
+        if let Some((form, _)) = &self.action_form {
+            form.render(f, size, popup);
+        }
+
+        if let Some(confirm) = &self.confirm {
+            confirm.render(f, size, popup);
+        }
+
+        if let Some(menu) = &self.context_menu {
+            menu.render(f, size, popup);
         }
 
-        // Draw input
-        self.draw_input(f, chunks[2]);
+        self.toasts.render(f, chunks[1], popup);
+    }
 
-        // Draw status bar
-        self.draw_status_bar(f, terminal, chunks[3]);
+    fn draw_too_small(&self, f: &mut Frame, area: Rect, palette: &Palette) {
+        let message = Paragraph::new(vec![
+            Line::from("Window too small"),
+            Line::from(format!("{}x{} (need {}x{})", area.width, area.height, MIN_WIDTH, MIN_HEIGHT)),
+        ])
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(palette.highlight));
+
+        let y = area.y + area.height.saturating_sub(2) / 2;
+        f.render_widget(message, Rect::new(area.x, y, area.width, area.height.min(2)));
     }
 
-    fn draw_tabs(&self, f: &mut Frame, area: Rect) {
-        let tab_titles: Vec<Line> = self.tabs
-            .iter()
-            .map(|t| Line::from(t.as_str()))
-            .collect();
+    fn draw_tabs(&self, f: &mut Frame, area: Rect, palette: &Palette) {
+        let tab_titles = self.tab_titles.get((self.state.tabs().to_vec(), palette.muted), |(tabs, muted)| {
+            tabs.iter()
+                .map(|tab| {
+                    let mut spans = vec![Span::raw(tab.title.clone())];
+                    if let Some(env) = tab.env {
+                        spans.push(Span::styled(format!(" [{}]", env.name()), Style::default().fg(env_color(env))));
+                    }
+                    if tab.muted {
+                        spans.push(Span::styled(" ⊘", Style::default().fg(*muted)));
+                    }
+                    Line::from(spans)
+                })
+                .collect()
+        });
 
-        let tabs = Tabs::new(tab_titles)
-            .block(Block::default().borders(Borders::ALL).title("Tabs"))
-            .select(self.active_tab)
-            .style(Style::default().fg(Color::White))
+        // Freshly selected tab briefly starts out dimmer before settling
+        let highlight = match self.animator.transition() {
+            Some(t) if t < 0.5 => palette.muted,
+            _ => palette.highlight,
+        };
+
+        let mut tabs = Tabs::new(tab_titles)
+            .select(self.state.active_tab())
+            .style(Style::default().fg(palette.border))
             .highlight_style(
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(highlight)
                     .add_modifier(Modifier::BOLD),
             );
+        if !self.compact {
+            tabs = tabs.block(self.theme.borders.bar.block().title("Tabs"));
+        }
 
         f.render_widget(tabs, area);
     }
 
-    fn draw_output(&self, f: &mut Frame, terminal: &Terminal, area: Rect) {
-        let output = terminal.get_output();
-        let items: Vec<ListItem> = output
+    // Tabs, main content, input and status bar
+    fn regions(&self, size: Rect) -> Rc<[Rect]> {
+        let (bar_height, input_height) = self.bar_heights();
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(bar_height),    // Tabs
+                Constraint::Min(10),               // Main content
+                Constraint::Length(input_height),  // Input
+                Constraint::Length(bar_height),    // Status bar
+            ])
+            .split(size)
+    }
+
+    // The output rows drawn in a `width` x `height` pane: annotations laid in,
+    // anonymized in demo mode, at the scroll position. Only the lines that can
+    // reach the pane are copied and laid out, however long the scrollback.
+    // Each with the output line it's from; annotations are from none
+    fn shown_rows(&self, terminal: &Terminal, width: u16, height: u16) -> Vec<(String, Option<usize>)> {
+        self.styled_rows(terminal, width, height).into_iter().map(|(row, _, line)| (row, line)).collect()
+    }
+
+    // The same, each row also with the level colorize draws it in, if its
+    // command's pipeline colorizes and the line has one
+    fn styled_rows(
+        &self,
+        terminal: &Terminal,
+        width: u16,
+        height: u16,
+    ) -> Vec<(String, Option<Level>, Option<usize>)> {
+        let (width, height) = (width as usize, height as usize);
+        let top = self.state.scroll_top();
+        let (start, mut layered, levels, lines) = terminal.with_output(|output| {
+            // Each line is a row at least, so `height` lines from the top fill the pane
+            let (start, end) = match top {
+                Some(top) => (top.min(output.len()), top.saturating_add(height).min(output.len())),
+                None => (scrollback::tail_start(output, width, height), output.len()),
+            };
+            // Those at the top line come before it; scrolled, that's above the pane
+            let first = if top.is_some() { start + 1 } else { start };
+            let annotations: Vec<Annotation> = terminal
+                .annotations()
+                .iter()
+                .filter(|a| a.line >= first && a.line <= end)
+                .map(|a| Annotation { line: a.line - start, text: a.text.clone() })
+                .collect();
+            let (layered, index) = scrollback::layer(&output[start..end], &annotations, self.show_annotations);
+            let mut levels = vec![None; layered.len()];
+            let mut lines = vec![None; layered.len()];
+            for (i, &at) in index.iter().enumerate() {
+                lines[at] = Some(start + i);
+                if terminal.is_colorized(start + i) {
+                    levels[at] = postprocess::level(&layered[at]);
+                }
+            }
+            (start, layered, levels, lines)
+        });
+        if let Some(anonymizer) = &self.anonymizer {
+            layered = layered.iter().map(|line| anonymizer.apply(line)).collect();
+        }
+        // As visible_rows lays them out
+        let first = top.map_or(0, |top| (top - start).min(layered.len()));
+        let mut rows: Vec<(String, Option<Level>, Option<usize>)> = layered[first..]
+            .iter()
+            .zip(levels[first..].iter().zip(&lines[first..]))
+            .flat_map(|(text, (&level, &line))| {
+                scrollback::reflow(std::slice::from_ref(text), width).into_iter().map(move |row| (row, level, line))
+            })
+            .collect();
+        match top {
+            Some(_) => rows.truncate(height),
+            None => {
+                rows.drain(..rows.len().saturating_sub(height));
+            }
+        }
+        rows
+    }
+
+    fn draw_output(&self, f: &mut Frame, terminal: &Terminal, area: Rect, palette: &Palette) {
+        let mut title = vec![Span::raw(if self.state.scroll_top().is_some() { "Output [scrolled]" } else { "Output" })];
+        let mut border = Style::default().fg(palette.border);
+        if let Some(running) = terminal.running_command() {
+            border = border.fg(palette.highlight);
+            title.push(Span::styled(
+                format!(
+                    " {} {} {}s ",
+                    self.animator.spinner(running.started),
+                    running.command,
+                    clock::since(running.started).as_secs()
+                ),
+                Style::default().fg(palette.highlight),
+            ));
+            title.push(Span::styled(running.sparkline(), Style::default().fg(palette.success)));
+        }
+        if terminal.is_pty_attached() {
+            title.push(Span::styled(" attached (Ctrl+] detaches) ", Style::default().fg(palette.highlight)));
+        }
+        if terminal.encoding() != Encoding::Utf8 {
+            title.push(Span::styled(format!(" {} ", terminal.encoding().name()), Style::default().fg(palette.info)));
+        }
+        if let Some(path) = terminal.recording() {
+            let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            title.push(Span::styled(format!(" ● REC {} ", name), Style::default().fg(Color::Red)));
+        }
+        if self.is_flashing() {
+            border = border.fg(palette.highlight).add_modifier(Modifier::REVERSED);
+        }
+        let mut block = self.pane_block(palette)
+            .title(Line::from(title))
+            .border_style(border);
+        if let Some(chips) = self.url_chips.title(palette) {
+            block = block.title(chips);
+        }
+        let inner = block.inner(area);
+
+        // Full-screen programs draw their own screen
+        if let Some(screen) = terminal.live_screen() {
+            let lines: Vec<Line> = screen.rows.iter().map(|row| screen_line(row)).collect();
+            f.render_widget(
+                Paragraph::new(lines).block(block).style(Style::default().fg(palette.text)),
+                area,
+            );
+            if let (true, Some((row, col))) = (terminal.is_pty_attached(), screen.cursor) {
+                f.set_cursor(inner.x + col as u16, inner.y + row as u16);
+            }
+            return;
+        }
+
+        let rows = self.styled_rows(terminal, inner.width, inner.height);
+        let items: Vec<ListItem> = rows
             .iter()
-            .map(|line| ListItem::new(line.as_str()))
+            .map(|(line, level, _)| {
+                let style = if line.starts_with('❯') {
+                    Style::default().fg(palette.success).add_modifier(Modifier::BOLD)
+                } else if line.starts_with('📘') || line.starts_with('💡') {
+                    Style::default().fg(palette.info).add_modifier(Modifier::ITALIC)
+                } else if *level == Some(Level::Error) {
+                    Style::default().fg(Color::Red)
+                } else if *level == Some(Level::Warning) {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(line.as_str()).style(style)
+            })
             .collect();
 
         let list = List::new(items)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Output")
-                    .style(Style::default().fg(Color::White)),
-            )
-            .style(Style::default().fg(Color::Gray));
+            .block(block)
+            .style(Style::default().fg(palette.text));
 
         f.render_widget(list, area);
+
+        // Pinned indicator for output that arrived while scrolled back
+        let new_lines = self.state.unseen_lines();
+        if new_lines > 0 && area.height > 2 {
+            let text = format!(" {} new lines ↓  (End to follow) ", new_lines);
+            let width = (text.chars().count() as u16).min(area.width.saturating_sub(2));
+            let indicator = Rect::new(
+                area.right().saturating_sub(width + 1),
+                area.bottom() - 2,
+                width,
+                1,
+            );
+            f.render_widget(
+                Paragraph::new(text).style(Style::default().fg(Color::Black).bg(palette.highlight)),
+                indicator,
+            );
+        }
     }
 
-    fn draw_autowire_panel(&self, f: &mut Frame, terminal: &Terminal, area: Rect) {
+    fn draw_autowire_panel(&self, f: &mut Frame, terminal: &Terminal, area: Rect, palette: &Palette) {
         let services = terminal.get_autowire_services();
         let status = terminal.get_autowire_status();
-
-        let text = vec![
-            Line::from(vec![
-                Span::styled("🔌 Auto-Wiring", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
-            ]),
-            Line::from(""),
-            Line::from(vec![
-                Span::raw("Status: "),
-                Span::styled(status, Style::default().fg(Color::Green)),
-            ]),
-            Line::from(""),
-            Line::from(vec![
-                Span::styled("Services:", Style::default().add_modifier(Modifier::BOLD)),
-            ]),
-        ];
-
-        let mut all_lines = text;
-        for service in services.iter().take(10) {
-            all_lines.push(Line::from(vec![
-                Span::raw("  • "),
-                Span::styled(service, Style::default().fg(Color::Yellow)),
-            ]));
-        }
-
-        if services.len() > 10 {
-            all_lines.push(Line::from(format!("  ... and {} more", services.len() - 10)));
-        }
-
-        let panel = Paragraph::new(all_lines)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Auto-Wire Panel")
-                    .style(Style::default().fg(Color::Cyan)),
-            );
-
-        f.render_widget(panel, area);
+        let fallbacks = terminal.autowire_fallbacks().summary();
+        let logs = if self.autowire_panel.is_showing_logs() { terminal.get_autowire_logs() } else { Vec::new() };
+        let block = self.pane_block(palette);
+        self.autowire_panel.render(f, area, block, palette, &status, fallbacks.as_deref(), &services, &logs);
     }
 
-    fn draw_input(&self, f: &mut Frame, area: Rect) {
-        let input_text = format!("> {}", self.input_buffer);
-        let input = Paragraph::new(input_text)
+    fn draw_input(&self, f: &mut Frame, terminal: &Terminal, area: Rect, palette: &Palette) {
+        let mut input_text = vec![Span::raw(format!("> {}", self.state.input()))];
+        let suggestions = terminal.live_suggestions();
+        if !suggestions.is_empty() {
+            input_text.push(Span::styled(
+                format!("   💡 {}", suggestions.join(" · ")),
+                Style::default().fg(palette.muted),
+            ));
+        }
+        let input = Paragraph::new(Line::from(input_text))
             .block(
-                Block::default()
-                    .borders(Borders::ALL)
+                self.theme.borders.bar.block()
                     .title("Command Input")
-                    .style(Style::default().fg(Color::Green)),
+                    .style(Style::default().fg(palette.success)),
             );
 
         f.render_widget(input, area);
     }
 
-    fn draw_status_bar(&self, f: &mut Frame, terminal: &Terminal, area: Rect) {
-        let autowire_status = terminal.get_autowire_status();
-        let status_text = format!(
-            " Auto-Wire: {} | Tab: {}/{} | Ctrl+H: Help ",
-            autowire_status,
-            self.active_tab + 1,
-            self.tabs.len()
-        );
-
-        let status = Paragraph::new(status_text)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .style(Style::default().fg(Color::White)),
-            )
-            .style(Style::default().fg(Color::Cyan));
+    fn draw_status_bar(&self, f: &mut Frame, terminal: &Terminal, area: Rect, palette: &Palette) {
+        // The newest background operation with progress to show
+        let background = terminal.operations().list().into_iter().rev()
+            .filter(|op| op.kind != Kind::Command)
+            .find_map(|op| Some(format!("{} {} {}", op.kind.icon(), op.title, progress_line(&op, 10)?)));
+        let inputs = StatusInputs {
+            segments: self.glyphs.segments(),
+            autowire: terminal.get_autowire_status(),
+            tab: (self.state.active_tab() + 1, self.state.tabs().len()),
+            background,
+            queued: terminal.queued_commands(),
+            latency: self.latency.summary(),
+            running: terminal.running_command().map(|running| {
+                let spinner = self.animator.spinner(running.started);
+                (spinner, running.command.clone(), clock::since(running.started).as_secs())
+            }),
+            mode: terminal.modes().active().map(|mode| (mode.title.to_uppercase(), mode.hints())),
+            colors: (palette.background, palette.highlight),
+        };
+        let line = self.status_line.get(inputs, status_line);
+        let mut status = Paragraph::new(line).style(Style::default().fg(palette.info));
+        if !self.compact {
+            status = status.block(
+                self.theme.borders.bar.block()
+                    .style(Style::default().fg(palette.border)),
+            );
+        }
 
         f.render_widget(status, area);
     }
 
+    // Every change to `state` goes through here
+    fn dispatch(&mut self, event: UiEvent) {
+        let tab = (self.state.active_tab(), self.state.tabs().len());
+        self.state.apply(event);
+        if (self.state.active_tab(), self.state.tabs().len()) != tab {
+            self.animator.start_transition();
+        }
+    }
+
     // Input methods
     pub fn input_char(&mut self, c: char) {
-        self.input_buffer.insert(self.cursor_pos, c);
-        self.cursor_pos += 1;
+        self.dispatch(UiEvent::Key(KeyAction::Insert(c)));
     }
 
-    pub fn input_backspace(&mut self) {
-        if self.cursor_pos > 0 {
-            self.cursor_pos -= 1;
-            self.input_buffer.remove(self.cursor_pos);
+    // Bracketed paste. Dropped files arrive as file:// URIs or bare paths and are
+    // inserted as shell-quoted paths; several lines of commands open the batch
+    // editor; other text goes in as if typed
+    pub fn paste(&mut self, text: &str) {
+        if let Some(editor) = &mut self.batch_editor {
+            editor.insert_text(text);
+            return;
+        }
+        let commands = text.lines().filter(|l| !l.trim().is_empty()).count();
+        if commands > 1 && shell_escape::dropped_paths(text).is_none() {
+            self.open_batch_editor("Pasted commands", text);
+            return;
+        }
+        let text = match shell_escape::dropped_paths(text) {
+            Some(paths) => {
+                let quoted: Vec<String> = paths.iter().map(|p| self.shell_kind.quote(p)).collect();
+                format!("{} ", quoted.join(" "))
+            }
+            None => text.to_string(),
+        };
+        self.insert_text(&text);
+    }
+
+    // Types `text` at the cursor, with line breaks as spaces
+    pub fn insert_text(&mut self, text: &str) {
+        let text = text.replace(['\r', '\n'], " ");
+        for c in text.chars().filter(|c| !c.is_control()) {
+            self.input_char(c);
         }
     }
 
+    pub fn input_backspace(&mut self) {
+        self.dispatch(UiEvent::Key(KeyAction::Backspace));
+    }
+
     pub fn get_input(&self) -> String {
-        self.input_buffer.clone()
+        self.state.input().to_string()
     }
 
     pub fn clear_input(&mut self) {
-        self.input_buffer.clear();
-        self.cursor_pos = 0;
+        self.dispatch(UiEvent::Key(KeyAction::Clear));
+    }
+
+    pub fn set_input(&mut self, text: &str) {
+        self.dispatch(UiEvent::Key(KeyAction::SetInput(text.to_string())));
     }
 
     pub fn is_input_empty(&self) -> bool {
-        self.input_buffer.is_empty()
+        self.state.input().is_empty()
+    }
+
+    // Event poll timeout: short while something is moving, relaxed otherwise
+    // and long while suspended for idleness
+    pub fn poll_interval(&self, terminal: &Terminal) -> Duration {
+        let busy = terminal.running_command().is_some() || self.animator.is_animating();
+        if (busy && self.animator.is_enabled()) || self.is_flashing() {
+            animation::TICK_RATE
+        } else if idle::is_suspended() {
+            Duration::from_secs(1)
+        } else {
+            Duration::from_millis(100)
+        }
+    }
+
+    // Hands the real terminal to an interactive program (e.g. an editor) and
+    // restores the UI once it exits, redrawn from scratch
+    pub fn run_external(&mut self, program: &str, args: &[&str]) -> Result<std::process::ExitStatus> {
+        self.leave_screen()?;
+        // What's left on screen while an editor in its own window (code --wait) is open
+        println!("NexTerm is waiting for {} to exit…", program);
+
+        let status = std::process::Command::new(program).args(args).status();
+
+        self.enter_screen()?;
+        Ok(status?)
+    }
+
+    // Ctrl+Z: back to the parent shell with the terminal as it was before
+    // NexTerm started, until `fg`
+    pub fn suspend(&mut self) -> Result<()> {
+        if !self.attached {
+            return Ok(());
+        }
+        self.leave_screen()?;
+        job_control::stop();
+        self.enter_screen()?;
+        // The SIGCONT that woke us is handled already
+        job_control::take_resumed();
+        Ok(())
+    }
+
+    // After a SIGCONT from a stop NexTerm didn't ask for: the shell may
+    // have reset the terminal meanwhile
+    pub fn resume(&mut self) -> Result<()> {
+        if self.attached {
+            self.enter_screen()?;
+        }
+        Ok(())
+    }
+
+    fn leave_screen(&mut self) -> Result<()> {
+        disable_raw_mode()?;
+        if self.mouse {
+            execute!(self.terminal.get_mut().backend_mut(), DisableMouseCapture)?;
+        }
+        execute!(self.terminal.get_mut().backend_mut(), DisableBracketedPaste, LeaveAlternateScreen)?;
+        self.terminal.get_mut().show_cursor()?;
+        Ok(())
+    }
+
+    // Raw mode, the alternate screen and a full redraw on the next frame
+    fn enter_screen(&mut self) -> Result<()> {
+        enable_raw_mode()?;
+        execute!(self.terminal.get_mut().backend_mut(), EnterAlternateScreen, EnableBracketedPaste)?;
+        if self.mouse {
+            execute!(self.terminal.get_mut().backend_mut(), EnableMouseCapture)?;
+        }
+        self.terminal.get_mut().clear()?;
+        self.stale = true;
+        Ok(())
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) -> Result<()> {
+        self.terminal.get_mut().resize(Rect::new(0, 0, width, height))?;
+        Ok(())
+    }
+
+    // Session lock
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    // Locks immediately, or asks for a passphrase first if none is configured
+    pub fn lock_session(&mut self) {
+        let mode = if self.lock_hash.is_some() { LockMode::Unlock } else { LockMode::Choose };
+        self.lock = Some(LockScreen::new(mode));
+    }
+
+    pub fn record_activity(&mut self) {
+        self.last_activity = clock::now();
+        idle::resume();
+        self.latency.key_received();
+    }
+
+    // Ctrl+= / Ctrl+- / Ctrl+0: the host's font size when it can be asked,
+    // otherwise compact mode stands in for zooming out
+    pub fn zoom(&mut self, step: ZoomStep) -> Result<()> {
+        if let Some(sequence) = self.zoom_host.sequence(step) {
+            let backend = self.terminal.get_mut().backend_mut();
+            write!(backend, "{}", sequence)?;
+            backend.flush()?;
+            return Ok(());
+        }
+        self.compact = match step {
+            ZoomStep::In => false,
+            ZoomStep::Out => true,
+            ZoomStep::Reset => self.compact_default,
+        };
+        Ok(())
+    }
+
+    // Pane frame with the theme's border, padding and pane background;
+    // compact mode gives the padding back to the contents
+    fn pane_block(&self, palette: &Palette) -> Block<'static> {
+        let padding = if self.compact { 0 } else { self.theme.padding };
+        let block = self.theme.borders.pane.block().padding(Padding::horizontal(padding));
+        match palette.pane {
+            Some(pane) => block.style(Style::default().bg(pane)),
+            None => block,
+        }
+    }
+
+    // The output rows on screen at `width` x `height`, wrapped
+    fn visible_rows(&self, output: &[String], top: Option<usize>, width: usize, height: usize) -> Vec<String> {
+        match top {
+            Some(top) => scrollback::window(output, top, width, height),
+            None => {
+                let rows = scrollback::reflow(output, width);
+                scrollback::tail(&rows, height).to_vec()
+            }
+        }
+    }
+
+    // What the output pane shows right now, for `capture save --pane`
+    pub fn visible_output(&self, terminal: &Terminal) -> Vec<String> {
+        let (height, width) = self.output_size();
+        self.visible_rows(&terminal.get_output(), self.state.scroll_top(), width as usize, height as usize)
+    }
+
+    // Rows and columns inside the output pane: the window size of commands
+    // running on a pty
+    pub fn output_size(&self) -> (u16, u16) {
+        let size = self.terminal.borrow().size().unwrap_or_default();
+        let (bar, input) = self.bar_heights();
+        let mut area = Rect::new(0, 0, size.width, size.height.saturating_sub(2 * bar + input));
+        if self.show_autowire_panel {
+            area = self.autowire_panel.split(area).0;
+        }
+        let inner = self.pane_block(&self.theme.palette).inner(area);
+        (inner.height, inner.width)
+    }
+
+    // Heights of the tab/status rows and of the command input
+    fn bar_heights(&self) -> (u16, u16) {
+        let input = 1 + 2 * self.theme.borders.bar.width();
+        (if self.compact { 1 } else { input }, input)
+    }
+
+    // Swaps to the light or dark theme when the OS setting or schedule flips
+    pub fn check_theme(&mut self) {
+        let Some(changes) = &self.scheme_changes else {
+            return;
+        };
+        let Some(scheme) = changes.try_iter().last() else {
+            return;
+        };
+        let name = self.appearance.theme_for(scheme).to_string();
+        if name != self.theme.name {
+            self.previous_palette = Some(self.palette());
+            self.theme = Theme::load(&name);
+            self.animator.start_transition();
+        }
+    }
+
+    // The theme's palette, mid-fade right after a switch
+    fn palette(&self) -> Palette {
+        match (self.previous_palette, self.animator.transition()) {
+            (Some(previous), Some(t)) => previous.blend(&self.theme.palette, t),
+            _ => self.theme.palette,
+        }
+    }
+
+    pub fn check_idle_lock(&mut self) {
+        let idle = self.lock_timeout.is_some_and(|t| clock::since(self.last_activity) >= t);
+        if idle && self.lock.is_none() && self.lock_hash.is_some() {
+            self.lock_session();
+        }
+    }
+
+    // Suspends background work once input has been idle long enough, unless a
+    // command is still running; the next key press resumes it
+    pub fn check_idle_suspend(&mut self, terminal: &Terminal) {
+        let idle = self.idle_suspend.is_some_and(|t| clock::since(self.last_activity) >= t);
+        if idle && !idle::is_suspended() && terminal.running_command().is_none() {
+            idle::suspend();
+            terminal.suspend_autowire();
+        }
+    }
+
+    pub fn lock_input(&mut self, code: KeyCode) -> Result<()> {
+        let Some(screen) = &mut self.lock else {
+            return Ok(());
+        };
+        let passphrase = match screen.handle_key(code) {
+            LockInput::Pending => return Ok(()),
+            LockInput::Cancel => {
+                self.lock = None;
+                return Ok(());
+            }
+            LockInput::Submit(passphrase) => passphrase,
+        };
+
+        match screen.mode().clone() {
+            LockMode::Unlock => {
+                let hash = self.lock_hash.as_deref().unwrap_or_default();
+                if lock::verify_passphrase(&passphrase, hash)? {
+                    self.lock = None;
+                    self.record_activity();
+                } else {
+                    screen.set_message("Incorrect passphrase");
+                }
+            }
+            LockMode::Choose if passphrase.is_empty() => {
+                screen.set_message("Passphrase must not be empty");
+            }
+            LockMode::Choose => screen.set_mode(LockMode::Confirm(passphrase)),
+            LockMode::Confirm(first) if first != passphrase => {
+                screen.set_mode(LockMode::Choose);
+                screen.set_message("Passphrases did not match");
+            }
+            LockMode::Confirm(_) => {
+                let hash = lock::hash_passphrase(&passphrase)?;
+                let mut config = Config::load()?;
+                config.lock_passphrase_hash = Some(hash.clone());
+                config.save()?;
+                self.lock_hash = Some(hash);
+                screen.set_mode(LockMode::Unlock);
+            }
+        }
+        Ok(())
+    }
+
+    // Demo mode
+    pub fn is_demo_mode(&self) -> bool {
+        self.anonymizer.is_some()
+    }
+
+    pub fn set_demo_mode(&mut self, enabled: bool) {
+        self.anonymizer = enabled.then(|| Anonymizer::new(&self.demo_config));
+    }
+
+    // Notifications
+    pub fn notify(&mut self, notice: Notice) {
+        self.stale = true;
+        self.toasts.push(notice);
+    }
+
+    // A bell from the terminal, unless the tab is muted. A desktop notification
+    // that can't be sent flashes instead.
+    pub fn ring(&mut self, ring: Ring) {
+        if self.state.tab_muted() {
+            return;
+        }
+        match ring.effect {
+            BellEffect::None => {}
+            BellEffect::Flash => self.flash_until = Some(clock::now() + FLASH_DURATION),
+            BellEffect::Sound => {
+                let backend = self.terminal.get_mut().backend_mut();
+                let _ = backend.write_all(b"\x07").and_then(|_| backend.flush());
+            }
+            BellEffect::Desktop => {
+                if let Err(e) = desktop::notify("NexTerm", &ring.message, ring.severity) {
+                    warn!("Desktop notification failed: {:#}", e);
+                    self.flash_until = Some(clock::now() + FLASH_DURATION);
+                }
+            }
+        }
+    }
+
+    fn is_flashing(&self) -> bool {
+        self.flash_until.is_some_and(|until| clock::now() < until)
+    }
+
+    pub fn set_tab_muted(&mut self, muted: bool) {
+        self.dispatch(UiEvent::Tab(TabEvent::Mute(muted)));
+    }
+
+    // The tabs, for `status`
+    pub fn tab_status(&self) -> Vec<TabStatus> {
+        self.state
+            .tabs()
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| TabStatus {
+                title: tab.title.clone(),
+                env: tab.env.map(|env| env.name().to_string()),
+                muted: tab.muted,
+                active: i == self.state.active_tab(),
+            })
+            .collect()
+    }
+
+    pub fn active_tab_id(&self) -> usize {
+        self.state.active_tab_id()
+    }
+
+    pub fn is_tab_muted(&self) -> bool {
+        self.state.tab_muted()
+    }
+
+    pub fn toggle_notification_center(&mut self) {
+        self.toasts.toggle_center();
+    }
+
+    pub fn dismiss_notifications(&mut self) {
+        if self.toasts.is_center_open() {
+            self.toasts.toggle_center();
+        }
+        self.toasts.dismiss_all();
+    }
+
+    // Completion
+    pub fn is_completion_visible(&self) -> bool {
+        self.completion_popup.is_visible()
+    }
+
+    // Tab: cycle an open dropdown, otherwise look up completions for the input
+    pub fn complete(&mut self, terminal: &Terminal) {
+        if self.completion_popup.is_visible() {
+            self.completion_popup.select_next();
+            return;
+        }
+
+        let items = self.suggestions.completions(self.state.input(), terminal.get_history(), terminal.variables());
+        if items.len() == 1 {
+            self.apply_completion(&items[0]);
+        } else {
+            self.completion_popup.show(items);
+        }
+    }
+
+    // Re-filters an open dropdown after the input changed
+    pub fn update_completions(&mut self, terminal: &Terminal) {
+        if self.completion_popup.is_visible() {
+            let items = self.suggestions.completions(self.state.input(), terminal.get_history(), terminal.variables());
+            self.completion_popup.show(items);
+        }
+    }
+
+    pub fn completion_next(&mut self) {
+        self.completion_popup.select_next();
+    }
+
+    pub fn completion_previous(&mut self) {
+        self.completion_popup.select_previous();
+    }
+
+    pub fn accept_completion(&mut self) {
+        if let Some(completion) = self.completion_popup.selected().cloned() {
+            self.apply_completion(&completion);
+        }
+        self.completion_popup.hide();
+    }
+
+    pub fn dismiss_completion(&mut self) {
+        self.completion_popup.hide();
+    }
+
+    // Flags and paths replace the word being typed; history entries replace the whole line
+    fn apply_completion(&mut self, completion: &Completion) {
+        let input = self.state.input();
+        let text = match completion.kind {
+            CompletionKind::Flag | CompletionKind::Path | CompletionKind::Variable => {
+                format!("{}{}", &input[..shell_escape::current_word_start(input)], completion.text)
+            }
+            CompletionKind::Argument => {
+                format!("{}{} ", &input[..shell_escape::current_word_start(input)], completion.text)
+            }
+            CompletionKind::History => completion.text.clone(),
+        };
+        self.dispatch(UiEvent::Key(KeyAction::SetInput(text)));
     }
 
     // Navigation
     pub fn cursor_left(&mut self) {
-        if self.cursor_pos > 0 {
-            self.cursor_pos -= 1;
-        }
+        self.dispatch(UiEvent::Key(KeyAction::CursorLeft));
     }
 
     pub fn cursor_right(&mut self) {
-        if self.cursor_pos < self.input_buffer.len() {
-            self.cursor_pos += 1;
+        self.dispatch(UiEvent::Key(KeyAction::CursorRight));
+    }
+
+    // Scrollback navigation. The output's length feeds the scroll position and
+    // the count of lines that arrived while scrolled back
+    pub fn output_changed(&mut self, len: usize) {
+        self.stale |= len != self.state.output_len();
+        self.dispatch(UiEvent::Output(OutputChunk { len }));
+    }
+
+    fn page_height(&self) -> usize {
+        // Everything but the tab, input and status rows plus the output borders
+        let (bar, input) = self.bar_heights();
+        let chrome = 2 * bar + input + 2 * self.theme.borders.pane.width();
+        self.terminal.borrow().size().map(|r| r.height.saturating_sub(chrome)).unwrap_or(10).max(1) as usize
+    }
+
+    pub fn jump_to_previous_prompt(&mut self, terminal: &Terminal) {
+        if let Some(line) = scrollback::previous_prompt(terminal.prompt_marks(), self.state.scroll_top()) {
+            self.dispatch(UiEvent::Key(KeyAction::ScrollTo(Some(line))));
         }
     }
 
-    pub fn history_previous(&mut self) {
-        // Implement history navigation
+    pub fn jump_to_next_prompt(&mut self, terminal: &Terminal) {
+        let top = scrollback::next_prompt(terminal.prompt_marks(), self.state.scroll_top());
+        self.dispatch(UiEvent::Key(KeyAction::ScrollTo(top)));
+    }
+
+    pub fn page_up(&mut self) {
+        let page = self.page_height();
+        self.dispatch(UiEvent::Key(KeyAction::PageUp { page }));
+    }
+
+    pub fn page_down(&mut self) {
+        let page = self.page_height();
+        self.dispatch(UiEvent::Key(KeyAction::PageDown { page }));
+    }
+
+    // End: resume following new output
+    pub fn follow_output(&mut self) {
+        self.dispatch(UiEvent::Key(KeyAction::ScrollTo(None)));
+    }
+
+    // Copies the output of the command at the top of the view (or the latest one)
+    // to the system clipboard via OSC 52
+    pub fn copy_command_output(&mut self, terminal: &Terminal) -> Result<()> {
+        let line = self.state.scroll_top()
+            .or_else(|| terminal.prompt_marks().last().map(|m| m.line));
+        let Some(text) = line.and_then(|l| terminal.command_output_at(l)) else {
+            return Ok(());
+        };
+        self.copy_text(&text, &format!("{} lines of output", text.lines().count()))
+    }
+
+    // To the system clipboard via OSC 52; `what` is for the notice
+    // Passes `sequence` through to the outer terminal, as copy_text does
+    pub fn write_to_host(&mut self, sequence: &str) -> Result<()> {
+        let backend = self.terminal.get_mut().backend_mut();
+        write!(backend, "{}", sequence)?;
+        backend.flush()?;
+        Ok(())
+    }
+
+    pub fn copy_text(&mut self, text: &str, what: &str) -> Result<()> {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+        let backend = self.terminal.get_mut().backend_mut();
+        write!(backend, "\x1b]52;c;{}\x07", encoded)?;
+        backend.flush()?;
+
+        self.notify(Notice {
+            severity: Severity::Success,
+            message: format!("Copied {}", what),
+        });
+        Ok(())
+    }
+
+    // Up: the next older entry that differs from what the input shows
+    pub fn history_previous(&mut self, history: &[String]) {
+        let start = self.state.history_index().unwrap_or(history.len());
+        if let Some(index) = (0..start).rev().find(|&i| history[i] != self.state.input()) {
+            self.dispatch(UiEvent::Key(KeyAction::Recall(index, history[index].clone())));
+        }
     }
 
-    pub fn history_next(&mut self) {
-        // Implement history navigation
+    // Down: the next newer entry, and past the newest the line as it was typed
+    pub fn history_next(&mut self, history: &[String]) {
+        let Some(start) = self.state.history_index() else {
+            return;
+        };
+        let action = match (start + 1..history.len()).find(|&i| history[i] != self.state.input()) {
+            Some(index) => KeyAction::Recall(index, history[index].clone()),
+            None => KeyAction::RecallDraft,
+        };
+        self.dispatch(UiEvent::Key(action));
     }
 
     // Tab management
     pub fn new_tab(&mut self) -> Result<()> {
-        self.tabs.push(format!("Tab {}", self.tabs.len() + 1));
-        self.active_tab = self.tabs.len() - 1;
+        self.dispatch(UiEvent::Tab(TabEvent::New));
         Ok(())
     }
 
     pub fn close_tab(&mut self) -> Result<()> {
-        if self.tabs.len() > 1 {
-            self.tabs.remove(self.active_tab);
-            if self.active_tab >= self.tabs.len() {
-                self.active_tab = self.tabs.len() - 1;
-            }
-        }
+        self.dispatch(UiEvent::Tab(TabEvent::Close));
         Ok(())
     }
 
     pub fn next_tab(&mut self) -> Result<()> {
-        self.active_tab = (self.active_tab + 1) % self.tabs.len();
+        self.dispatch(UiEvent::Tab(TabEvent::Next));
         Ok(())
     }
 
+    pub fn select_tab(&mut self, index: usize) {
+        self.dispatch(UiEvent::Tab(TabEvent::Select(index)));
+    }
+
+    // Labels the active tab; None clears the label
+    pub fn set_tab_env(&mut self, env: Option<EnvLabel>) {
+        self.dispatch(UiEvent::Tab(TabEvent::Label(env)));
+    }
+
+    pub fn tab_env(&self) -> Option<EnvLabel> {
+        self.state.tab_env()
+    }
+
     // Feature methods
     pub fn split_pane_vertical(&mut self) -> Result<()> {
         Ok(())
@@ -325,11 +2114,15 @@ impl TerminalUI {
         Ok(())
     }
 
-    pub fn open_fuzzy_finder(&mut self) -> Result<()> {
+    // Ctrl+F: fuzzy search over history, seeded with what's typed
+    pub fn open_fuzzy_finder(&mut self, items: Vec<HistoryItem>) -> Result<()> {
+        self.history_search = Some(HistorySearch::new(SearchMode::Fuzzy, items, self.state.input()));
         Ok(())
     }
 
-    pub fn open_history(&mut self) -> Result<()> {
+    // Ctrl+R: reverse incremental search over history
+    pub fn open_history(&mut self, items: Vec<HistoryItem>) -> Result<()> {
+        self.history_search = Some(HistorySearch::new(SearchMode::Reverse, items, self.state.input()));
         Ok(())
     }
 
@@ -337,10 +2130,6 @@ impl TerminalUI {
         Ok(())
     }
 
-    pub fn show_ai_suggestions(&mut self) -> Result<()> {
-        Ok(())
-    }
-
     pub fn show_ai_suggestions_with_autowire(&mut self) -> Result<()> {
         self.show_autowire_panel = !self.show_autowire_panel;
         Ok(())
@@ -348,6 +2137,7 @@ impl TerminalUI {
 
     pub fn show_autowire_status(&mut self) -> Result<()> {
         self.show_autowire_panel = true;
+        self.autowire_panel.set_focused(true);
         Ok(())
     }
 
@@ -356,16 +2146,122 @@ impl TerminalUI {
         Ok(())
     }
 
-    pub fn confirm_exit(&self) -> Result<bool> {
-        // Simple confirmation - in production, show a dialog
-        Ok(true)
+    // Auto-wire panel interaction
+    pub fn is_autowire_panel_focused(&self) -> bool {
+        self.show_autowire_panel && self.autowire_panel.is_focused()
+    }
+
+    pub fn handle_autowire_panel_key(
+        &mut self,
+        code: KeyCode,
+        services: &[String],
+    ) -> Option<(PanelAction, String)> {
+        self.autowire_panel.handle_key(code, services)
+    }
+
+    pub fn set_autowire_detail(&mut self, detail: String) {
+        self.autowire_panel.set_detail(detail);
+    }
+
+    pub fn grow_autowire_panel(&mut self) {
+        self.autowire_panel.grow();
+    }
+
+    pub fn shrink_autowire_panel(&mut self) {
+        self.autowire_panel.shrink();
+    }
+
+    // Ctrl+C: true to quit now. With commands running or queued it asks
+    // first, and `take_exiting` tells the answer apart
+    pub fn confirm_exit(&mut self, terminal: &Terminal) -> bool {
+        let mut lines: Vec<String> = terminal.running_command().map(|c| format!("  ⏳ {}", c.command)).into_iter().collect();
+        if terminal.queued_commands() > 0 {
+            lines.push(format!("  {} more queued", terminal.queued_commands()));
+        }
+        if lines.is_empty() {
+            return true;
+        }
+        lines.insert(0, "Quitting stops:".to_string());
+        let buttons = vec![Button::new("Quit").key('y'), Button::cancel("Stay").key('n')];
+        self.show_confirm(ConfirmDialog::new(" Quit NexTerm? ", lines).with_buttons(buttons));
+        self.exiting = true;
+        false
     }
 }
 
 impl Drop for TerminalUI {
     fn drop(&mut self) {
+        if !self.attached {
+            return;
+        }
         let _ = disable_raw_mode();
-        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
-        let _ = self.terminal.show_cursor();
+        if self.mouse {
+            let _ = execute!(self.terminal.get_mut().backend_mut(), DisableMouseCapture);
+        }
+        let _ = execute!(self.terminal.get_mut().backend_mut(), DisableBracketedPaste, LeaveAlternateScreen);
+        let _ = self.terminal.get_mut().show_cursor();
+    }
+}
+
+// The command run at output `line`, if that's one's prompt mark
+fn prompt_at(terminal: &Terminal, line: Option<usize>) -> Option<String> {
+    let line = line?;
+    terminal.prompt_marks().iter().find(|mark| mark.line == line).map(|mark| mark.command.clone())
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    (area.x..area.right()).contains(&column) && (area.y..area.bottom()).contains(&row)
+}
+
+// Borders and tab label of a labeled tab
+fn env_color(env: EnvLabel) -> Color {
+    match env {
+        EnvLabel::Dev => Color::Green,
+        EnvLabel::Staging => Color::Yellow,
+        EnvLabel::Prod => Color::Red,
+    }
+}
+
+// One row of a pty screen in the program's own colors
+fn screen_line(row: &[Cell]) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut text = String::new();
+    let mut current = CellStyle::default();
+    // The second half of a wide character has no glyph of its own
+    for cell in row.iter().filter(|cell| cell.ch != '\0') {
+        if cell.style != current && !text.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut text), cell_style(&current)));
+        }
+        current = cell.style;
+        text.push(cell.ch);
+    }
+    spans.push(Span::styled(text, cell_style(&current)));
+    Line::from(spans)
+}
+
+fn cell_style(cell: &CellStyle) -> Style {
+    let color = |color: VtColor| match color {
+        VtColor::Default => None,
+        VtColor::Indexed(n) => Some(Color::Indexed(n)),
+        VtColor::Rgb(r, g, b) => Some(Color::Rgb(r, g, b)),
+    };
+    let mut style = Style::default();
+    if let Some(fg) = color(cell.fg) {
+        style = style.fg(fg);
+    }
+    if let Some(bg) = color(cell.bg) {
+        style = style.bg(bg);
+    }
+    for (on, modifier) in [
+        (cell.bold, Modifier::BOLD),
+        (cell.dim, Modifier::DIM),
+        (cell.italic, Modifier::ITALIC),
+        (cell.underline, Modifier::UNDERLINED),
+        (cell.reverse, Modifier::REVERSED),
+    ] {
+        if on {
+            style = style.add_modifier(modifier);
+        }
     }
+    style
 }
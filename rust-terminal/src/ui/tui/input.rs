@@ -0,0 +1,609 @@
+// Keys, the mouse and pastes, and the demo player's keystrokes. Panels,
+// dialogs and the input line are handled here; running commands, builtins and
+// actions goes back to main.rs as a `Request`.
+use anyhow::{Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use std::time::Duration;
+use tracing::warn;
+
+use super::TerminalUI;
+use crate::core::actions::KeyChord;
+use crate::core::modes::ModeKey;
+use crate::core::terminal::{Severity, Terminal};
+use crate::ui::demo::{DemoAction, DemoPlayer};
+use crate::ui::frontend::Request;
+use crate::ui::widgets::action_cards::CardsAction;
+use crate::ui::widgets::activity_panel::ActivityAction;
+use crate::ui::widgets::autowire_panel::PanelAction;
+use crate::ui::widgets::batch_editor::BatchAction;
+use crate::ui::widgets::context_menu::MenuCommand;
+use crate::ui::widgets::daemons_panel::DaemonsAction;
+use crate::ui::widgets::hex_viewer::HexAction;
+use crate::ui::widgets::plugins_panel::PluginsAction;
+use crate::ui::widgets::problems_panel::ProblemsAction;
+use crate::ui::widgets::ssh_keys_panel::SshKeysAction;
+use crate::ui::widgets::variables_panel::VariablesAction;
+use crate::ui::widgets::workflow_graph::WorkflowGraphAction;
+use crate::ui::zoom::ZoomStep;
+use crate::utils::{browser, clock, job_control, ssh_keys};
+
+// Frames skipped in a row by the typing fast path
+const MAX_SKIPPED_FRAMES: u32 = 8;
+
+impl TerminalUI {
+    // `nexterm demo`: the script's keystrokes come in as if typed
+    pub fn play(&mut self, player: DemoPlayer) {
+        self.demo = Some(player);
+    }
+
+    pub fn handle_input(&mut self, terminal: &mut Terminal) -> Result<Option<Request>> {
+        // Commands on a pty see the output pane as their window
+        let (rows, cols) = self.output_size();
+        terminal.set_pty_size(rows, cols);
+
+        // Render UI, unless more input is already queued: handling it first keeps
+        // typed characters from waiting behind frames that are stale on arrival.
+        // A frame that would look like the last one isn't drawn at all.
+        if self.skipped_frames < MAX_SKIPPED_FRAMES && event::poll(Duration::ZERO)? {
+            self.skipped_frames += 1;
+        } else {
+            if self.needs_frame(terminal) {
+                self.render(terminal)?;
+            }
+            self.skipped_frames = 0;
+        }
+
+        // A SIGTSTP from `kill -TSTP`, and `fg` after a stop NexTerm didn't
+        // ask for, see utils::job_control
+        if job_control::take_suspend() {
+            self.suspend()?;
+        }
+        if job_control::take_resumed() {
+            self.resume()?;
+        }
+
+        // Another round of `ai do` proposals once every card is decided
+        if let Some((request, results)) = self.next_action_round() {
+            if let Err(e) = terminal.propose_actions(&request, &results) {
+                terminal.notify_error(&e);
+            }
+        }
+
+        let Some(event) = self.next_event(terminal)? else {
+            return Ok(None);
+        };
+        self.invalidate();
+        match event {
+            Event::Resize(width, height) => self.resize(width, height)?,
+            Event::Paste(text) if !self.is_locked() => self.handle_paste(terminal, &text),
+            Event::Mouse(mouse) if !self.is_locked() => {
+                self.record_activity();
+                return Ok(self.handle_mouse(terminal, mouse));
+            }
+            Event::Key(key) => {
+                self.record_activity();
+                return self.handle_key(terminal, key);
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    // The next input event: a demo keystroke once it's due, or else whatever the
+    // user does within the poll interval. Demo suggestions are shown on the way.
+    fn next_event(&mut self, terminal: &mut Terminal) -> Result<Option<Event>> {
+        let mut timeout = self.poll_interval(terminal);
+        if let Some(player) = &mut self.demo {
+            let now = clock::now();
+            match player.next(now, terminal.running_command().is_some()) {
+                Some(DemoAction::Key(key)) => return Ok(Some(Event::Key(key))),
+                Some(DemoAction::Suggest(suggestions)) => {
+                    terminal.suggest(&suggestions);
+                    return Ok(None);
+                }
+                None => {
+                    let wait = player.wait(now);
+                    if !wait.is_zero() {
+                        timeout = timeout.min(wait);
+                    }
+                }
+            }
+        }
+        Ok(if event::poll(timeout)? { Some(event::read()?) } else { None })
+    }
+
+    fn handle_key(&mut self, terminal: &mut Terminal, key: KeyEvent) -> Result<Option<Request>> {
+        if self.is_locked() {
+            self.lock_input(key.code)?;
+            return Ok(None);
+        }
+
+        if self.is_welcome_visible() {
+            self.dismiss_welcome();
+            return Ok(None);
+        }
+
+        if self.is_context_menu_visible() {
+            let command = self.context_menu_input(key.code);
+            return Ok(self.run_menu_command(terminal, command));
+        }
+
+        if self.is_confirm_visible() {
+            let Some(confirmed) = self.confirm_input(key.code) else {
+                return Ok(None);
+            };
+            if self.take_exiting() {
+                return Ok(confirmed.then_some(Request::Exit));
+            }
+            return Ok(Some(match self.take_guarded() {
+                Some(commands) => Request::Release(confirmed.then_some(commands)),
+                None => Request::Answer(confirmed),
+            }));
+        }
+
+        if self.is_action_form_visible() {
+            return Ok(self.action_form_input(key.code).map(Request::Run));
+        }
+
+        if self.is_daemons_panel_visible() {
+            let result = match self.daemons_panel_input(key.code) {
+                Some(DaemonsAction::Stop(id)) => terminal.daemons().stop(id),
+                Some(DaemonsAction::Forget(id)) => terminal.daemons().forget(id),
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                terminal.notify_error(&e);
+            }
+            self.update_daemons_panel(terminal.daemons().list());
+            return Ok(None);
+        }
+
+        if self.is_variables_panel_visible() {
+            if let Some(VariablesAction::Unset(name)) = self.variables_panel_input(key.code) {
+                if let Err(e) = terminal.unset_variable(&name) {
+                    terminal.notify_error(&e);
+                }
+                self.update_variables_panel(terminal.variables().values());
+            }
+            return Ok(None);
+        }
+
+        if self.is_workflow_graph_visible() {
+            return Ok(match self.workflow_graph_input(key.code) {
+                Some(WorkflowGraphAction::Run(name)) => Some(Request::RunWorkflow(name)),
+                _ => None,
+            });
+        }
+
+        if self.is_activity_panel_visible() {
+            if let Some(ActivityAction::Cancel(id)) = self.activity_panel_input(key.code) {
+                if let Err(e) = terminal.operations().cancel(id) {
+                    terminal.notify_error(&e);
+                }
+            }
+            self.update_activity_panel(terminal.operations().list());
+            return Ok(None);
+        }
+
+        if self.is_batch_editor_visible() {
+            return Ok(match self.batch_editor_input(key) {
+                // Queued behind one another, in order
+                Some(BatchAction::Run(commands)) => Some(Request::Queue(commands)),
+                Some(BatchAction::Script(commands, options)) => Some(Request::Script(commands, options)),
+                _ => None,
+            });
+        }
+
+        if self.is_filter_view_visible() {
+            self.filter_view_input(key.code);
+            return Ok(None);
+        }
+
+        if self.is_workspace_search_visible() {
+            self.workspace_search_input(key.code);
+            return Ok(None);
+        }
+
+        if self.is_ssh_keys_panel_visible() {
+            let action = self.ssh_keys_panel_input(key.code);
+            let result = match &action {
+                Some(SshKeysAction::Add(path, passphrase)) => ssh_keys::add(path, passphrase.as_deref()),
+                Some(SshKeysAction::Remove(path)) => ssh_keys::remove(path),
+                _ => return Ok(None),
+            };
+            match result.and_then(|()| ssh_keys::list()) {
+                Ok(keys) => self.update_ssh_keys_panel(keys),
+                Err(e) => terminal.notify_error(&e),
+            }
+            return Ok(None);
+        }
+
+        if self.is_problems_panel_visible() {
+            if let Some(ProblemsAction::Open(file, line)) = self.problems_panel_input(key.code) {
+                let editor = terminal.config().editor.clone();
+                // `+N` puts vi, emacs, nano and most others on the line
+                let line = line.map(|line| format!("+{}", line));
+                let args: Vec<&str> = line.iter().map(String::as_str).chain([file.as_str()]).collect();
+                if let Err(e) = self.run_external(&editor, &args) {
+                    terminal.notify_error(&e);
+                }
+            }
+            return Ok(None);
+        }
+
+        if self.is_action_cards_visible() {
+            return Ok(match self.action_cards_input(key.code) {
+                Some(CardsAction::Approve(index)) => Some(Request::Approve(index)),
+                _ => None,
+            });
+        }
+
+        if self.is_hex_viewer_visible() {
+            if let Some(HexAction::Save(id)) = self.hex_viewer_input(key.code) {
+                self.set_input(&format!("binary save {} ", id));
+                terminal.input_changed(&self.get_input());
+            }
+            return Ok(None);
+        }
+
+        if self.is_plugins_panel_visible() {
+            if let Some(PluginsAction::Toggle(name, enabled)) = self.plugins_panel_input(key.code) {
+                if let Err(e) = terminal.set_plugin_enabled(&name, enabled) {
+                    terminal.notify_error(&e);
+                }
+                self.update_plugins_panel(terminal.plugins());
+            }
+            return Ok(None);
+        }
+
+        if self.is_history_search_visible() {
+            let commands = self.history_search_input(key).unwrap_or_else(|e| {
+                terminal.notify_error(&e);
+                None
+            });
+            self.update_completions(terminal);
+            terminal.input_changed(&self.get_input());
+            return Ok(commands.map(Request::Submit));
+        }
+
+        if self.is_command_palette_visible() {
+            return Ok(self.command_palette_input(key).map(Request::Action));
+        }
+
+        if self.is_autowire_panel_focused() && !key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.autowire_panel_key(terminal, key.code);
+            return Ok(None);
+        }
+
+        if self.is_url_chips_focused() {
+            if let Some(url) = self.url_chips_input(key.code) {
+                match browser::open(&url) {
+                    Ok(()) => terminal.notify(Severity::Info, format!("Opened {}", url)),
+                    Err(e) => terminal.notify(Severity::Error, format!("Could not open {}: {:#}", url, e)),
+                }
+            }
+            return Ok(None);
+        }
+
+        // Ctrl+] attaches the keyboard to the running command (full-screen
+        // programs get it automatically) and detaches it again
+        if key.code == KeyCode::Char(']') && key.modifiers == KeyModifiers::CONTROL && terminal.toggle_pty_attached() {
+            return Ok(None);
+        }
+        if terminal.is_pty_attached() {
+            terminal.send_key(key);
+            return Ok(None);
+        }
+
+        // Custom key layers, see core::modes
+        match terminal.modes_mut().handle(KeyChord::from(key)) {
+            ModeKey::Run(command) => return Ok(Some(Request::Run(command))),
+            ModeKey::Left | ModeKey::Ignored => return Ok(None),
+            ModeKey::Pass => {}
+        }
+
+        // Keys bound to palette actions, by keybindings.toml or their contributor.
+        // A prefix of longer bindings waits for the next chord, listing them
+        // in a popup if that takes a moment. NexTerm's own keys come first,
+        // and cancel a prefix
+        let mut pressed = self.take_pending_keys();
+        pressed.push(KeyChord::from(key));
+        if !KeyChord::from(key).is_reserved() {
+            if let Some(action) = terminal.actions().for_keys(&pressed).cloned() {
+                return Ok(Some(Request::Action(action)));
+            }
+            let continuations = terminal.actions().continuations(&pressed);
+            if !continuations.is_empty() {
+                self.show_key_hints(pressed, continuations);
+                return Ok(None);
+            }
+            // Anything else after a prefix, Esc included, just cancels it
+            if pressed.len() > 1 {
+                return Ok(None);
+            }
+        }
+
+        match (key.code, key.modifiers) {
+            // Exit
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                return Ok(self.confirm_exit(terminal).then_some(Request::Exit));
+            }
+            // EOF - exit if input is empty
+            (KeyCode::Char('d'), KeyModifiers::CONTROL) => {
+                return Ok(self.is_input_empty().then_some(Request::Exit));
+            }
+            // Back to the parent shell until `fg`
+            (KeyCode::Char('z'), KeyModifiers::CONTROL) => {
+                self.suspend()?;
+            }
+
+            // Tab management
+            (KeyCode::Char('t'), KeyModifiers::CONTROL) => {
+                self.new_tab()?;
+            }
+            (KeyCode::Char('w'), KeyModifiers::CONTROL) => {
+                self.close_tab_and_shell(terminal)?;
+            }
+            (KeyCode::Tab, KeyModifiers::CONTROL) => {
+                self.next_tab()?;
+            }
+
+            // Pane management: Ctrl+Shift, both held (a `|` pattern would take either alone)
+            (KeyCode::Char('d' | 'D'), modifiers) if modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT => {
+                self.split_pane_vertical()?;
+            }
+            (KeyCode::Char('h' | 'H'), modifiers) if modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT => {
+                self.split_pane_horizontal()?;
+            }
+
+            // Features
+            (KeyCode::Char('f'), KeyModifiers::CONTROL) => {
+                self.open_fuzzy_finder(terminal.history().unique())?;
+            }
+            (KeyCode::Char('r'), KeyModifiers::CONTROL) => {
+                self.open_history(terminal.history().unique())?;
+            }
+            (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
+                self.open_git_status()?;
+            }
+            (KeyCode::Char('y'), KeyModifiers::CONTROL) => {
+                self.copy_command_output(terminal)?;
+            }
+            (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
+                // Workflows and plugins may have changed since
+                terminal.refresh_actions();
+                self.open_command_palette(terminal.actions());
+            }
+            (KeyCode::Char('o'), KeyModifiers::ALT) => {
+                self.toggle_url_chips();
+            }
+            // Context menu; Shift+F10 where the Menu key isn't reported
+            (KeyCode::Menu, _) | (KeyCode::F(10), KeyModifiers::SHIFT) => {
+                self.open_keyboard_menu(terminal);
+            }
+            // Same as the bare `logging` builtin
+            (KeyCode::Char('l'), KeyModifiers::ALT) => {
+                return Ok(Some(Request::Run("logging".to_string())));
+            }
+            (KeyCode::Char('i'), KeyModifiers::ALT) => {
+                let shown = self.toggle_annotations();
+                terminal.notify(Severity::Info, format!("AI annotations {}", if shown { "shown" } else { "hidden" }));
+            }
+            (KeyCode::Char(' '), KeyModifiers::CONTROL) => {
+                // AI suggestions using auto-wiring system
+                self.show_ai_suggestions_with_autowire()?;
+            }
+
+            // Auto-wiring features
+            (KeyCode::Char('a'), KeyModifiers::CONTROL) => {
+                self.show_autowire_status()?;
+            }
+            (KeyCode::Char('s'), KeyModifiers::CONTROL) => {
+                self.show_autowire_services()?;
+            }
+            (KeyCode::Left, KeyModifiers::ALT) => {
+                self.grow_autowire_panel();
+            }
+            (KeyCode::Right, KeyModifiers::ALT) => {
+                self.shrink_autowire_panel();
+            }
+
+            // Zoom
+            (KeyCode::Char('=') | KeyCode::Char('+'), KeyModifiers::CONTROL) => {
+                self.zoom(ZoomStep::In)?;
+            }
+            (KeyCode::Char('-'), KeyModifiers::CONTROL) => {
+                self.zoom(ZoomStep::Out)?;
+            }
+            (KeyCode::Char('0'), KeyModifiers::CONTROL) => {
+                self.zoom(ZoomStep::Reset)?;
+            }
+
+            // Completion dropdown
+            (KeyCode::Tab, KeyModifiers::NONE) => {
+                self.complete(terminal);
+            }
+            (KeyCode::BackTab, _) if self.is_completion_visible() => {
+                self.completion_previous();
+            }
+            (KeyCode::Up, _) if self.is_completion_visible() => {
+                self.completion_previous();
+            }
+            (KeyCode::Down, _) if self.is_completion_visible() => {
+                self.completion_next();
+            }
+            (KeyCode::Enter, _) if self.is_completion_visible() => {
+                self.accept_completion();
+            }
+            (KeyCode::Esc, _) => {
+                self.dismiss_completion();
+                self.dismiss_notifications();
+            }
+            (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
+                self.toggle_notification_center();
+            }
+
+            // Command input
+            (KeyCode::Enter, _) => {
+                let command = self.get_input();
+                // Cleared first, so builtins can put text back (`capture paste`)
+                self.clear_input();
+                return Ok(Some(Request::Run(command)));
+            }
+            (KeyCode::Char(c), _) => {
+                self.input_char(c);
+                self.update_completions(terminal);
+                terminal.input_changed(&self.get_input());
+            }
+            (KeyCode::Backspace, _) => {
+                self.input_backspace();
+                self.update_completions(terminal);
+                terminal.input_changed(&self.get_input());
+            }
+
+            // Navigation
+            (KeyCode::Up, KeyModifiers::CONTROL) => {
+                self.jump_to_previous_prompt(terminal);
+            }
+            (KeyCode::Down, KeyModifiers::CONTROL) => {
+                self.jump_to_next_prompt(terminal);
+            }
+            (KeyCode::PageUp, _) => {
+                self.page_up();
+            }
+            (KeyCode::PageDown, _) => {
+                self.page_down();
+            }
+            (KeyCode::End, _) => {
+                self.follow_output();
+            }
+            (KeyCode::Up, _) => {
+                self.history_previous(terminal.get_history());
+            }
+            (KeyCode::Down, _) => {
+                self.history_next(terminal.get_history());
+            }
+            (KeyCode::Left, _) => {
+                self.cursor_left();
+            }
+            (KeyCode::Right, _) => {
+                self.cursor_right();
+            }
+
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    // To the program on the pty when there is one, else into the input line; a
+    // paste from the NexTerm this one runs in (over ssh) is its message instead
+    fn handle_paste(&mut self, terminal: &mut Terminal, text: &str) {
+        if terminal.receive_peer_paste(text) {
+            return;
+        }
+        self.record_activity();
+        if terminal.is_pty_attached() {
+            terminal.send_paste(text);
+            return;
+        }
+        self.dismiss_welcome();
+        self.paste(text);
+        self.update_completions(terminal);
+        terminal.input_changed(&self.get_input());
+    }
+
+    // Right-click menus and wheel scrolling; while a menu is open, clicks go to it
+    fn handle_mouse(&mut self, terminal: &mut Terminal, mouse: MouseEvent) -> Option<Request> {
+        let command = match mouse.kind {
+            MouseEventKind::Down(_) if self.is_context_menu_visible() => self.context_menu_click(mouse.column, mouse.row),
+            MouseEventKind::Down(MouseButton::Right) => {
+                self.open_context_menu(terminal, mouse.column, mouse.row);
+                None
+            }
+            // Attached programs scroll themselves
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown if !terminal.is_pty_attached() => {
+                self.scroll_output(mouse.kind == MouseEventKind::ScrollUp);
+                None
+            }
+            _ => None,
+        };
+        self.run_menu_command(terminal, command)
+    }
+
+    // A context menu's pick; what fails is reported
+    fn run_menu_command(&mut self, terminal: &mut Terminal, command: Option<MenuCommand>) -> Option<Request> {
+        self.menu_command(terminal, command?).unwrap_or_else(|e| {
+            terminal.notify_error(&e);
+            None
+        })
+    }
+
+    fn menu_command(&mut self, terminal: &mut Terminal, command: MenuCommand) -> Result<Option<Request>> {
+        match command {
+            MenuCommand::Copy(text) => self.copy_text(&text, &format!("{} characters", text.chars().count()))?,
+            MenuCommand::CopyCommandOutput => self.copy_command_output(terminal)?,
+            MenuCommand::Rerun(command) => return Ok(Some(Request::Queue(vec![command]))),
+            MenuCommand::Edit(command) => {
+                self.set_input(&command);
+                terminal.input_changed(&self.get_input());
+            }
+            MenuCommand::Route(command) => terminal.add_output(&terminal.explain_route(&command)?.join("\n")),
+            MenuCommand::OpenUrl(url) => {
+                browser::open(&url).with_context(|| format!("Could not open {}", url))?;
+                terminal.notify(Severity::Info, format!("Opened {}", url));
+            }
+            MenuCommand::SelectTab(index) => self.select_tab(index),
+            MenuCommand::NewTab => self.new_tab()?,
+            MenuCommand::CloseTab(index) => {
+                self.select_tab(index);
+                self.close_tab_and_shell(terminal)?;
+            }
+            MenuCommand::SearchHistory => self.open_history(terminal.history().unique())?,
+            MenuCommand::EditAsBatch(text) => {
+                self.clear_input();
+                self.open_batch_editor("Batch", &text);
+            }
+            MenuCommand::ClearInput => {
+                self.clear_input();
+                terminal.input_changed("");
+            }
+            MenuCommand::ViewBinary(id) => {
+                let output = terminal.binary_output(Some(id)).context("No binary output to show")?;
+                let output = output.lock().map_err(|_| anyhow::anyhow!("Binary output unavailable"))?;
+                self.show_hex_viewer(&output);
+            }
+        }
+        Ok(None)
+    }
+
+    // The shell of a tab that closed is hung up on; the last tab stays open
+    fn close_tab_and_shell(&mut self, terminal: &mut Terminal) -> Result<()> {
+        let closing = self.active_tab_id();
+        self.close_tab()?;
+        if self.active_tab_id() != closing {
+            terminal.close_shell(closing);
+        }
+        Ok(())
+    }
+
+    fn autowire_panel_key(&mut self, terminal: &Terminal, code: KeyCode) {
+        let services = terminal.get_autowire_services();
+        let Some((action, service)) = self.handle_autowire_panel_key(code, &services) else {
+            return;
+        };
+
+        let outcome = match action {
+            PanelAction::Invoke => terminal.invoke_autowire_service(&service),
+            PanelAction::Details => terminal.describe_autowire_service(&service),
+            PanelAction::Restart => terminal.restart_autowire_service(&service),
+        };
+
+        match outcome {
+            Ok(text) => self.set_autowire_detail(text),
+            Err(e) => {
+                warn!("Auto-wire {} failed for {}: {}", action.label(), service, e);
+                self.set_autowire_detail(format!("{} failed: {}", action.label(), e));
+            }
+        }
+    }
+}
@@ -1,3 +1,25 @@
 // Custom widgets
-pub mod status_bar;
-pub mod file_explorer;
+pub mod autowire_panel;
+pub mod completion_popup;
+pub mod toast;
+pub mod welcome;
+pub mod lock_screen;
+pub mod confirm_dialog;
+pub mod plugins_panel;
+pub mod url_chips;
+pub mod history_search;
+pub mod daemons_panel;
+pub mod filter_view;
+pub mod batch_editor;
+pub mod ssh_keys_panel;
+pub mod problems_panel;
+pub mod context_menu;
+pub mod command_palette;
+pub mod hex_viewer;
+pub mod action_cards;
+pub mod activity_panel;
+pub mod workspace_search;
+pub mod workflow_graph;
+pub mod key_hints;
+pub mod form;
+pub mod variables_panel;
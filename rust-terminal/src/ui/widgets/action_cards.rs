@@ -0,0 +1,271 @@
+// Modal cards for `ai do`: one per action the AI proposes, each waiting on
+// approval. The selected card shows what it would do (a patch's diff in
+// full); declined and failed ones are reported back with the next round.
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+    Frame,
+};
+use serde_json::Value;
+
+use crate::ai::proposals::{self, Proposal, Proposals, MAX_ROUNDS};
+use crate::ui::themes::BorderSet;
+
+// Diff lines shown under the selected card
+const PREVIEW_LINES: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CardState {
+    Pending,
+    Done(String),
+    Declined,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CardsAction {
+    // Carry out the card's action and `resolve` it
+    Approve(usize),
+    Close,
+}
+
+struct Card {
+    proposal: Proposal,
+    state: CardState,
+}
+
+pub struct ActionCards {
+    request: String,
+    message: Option<String>,
+    cards: Vec<Card>,
+    selected: usize,
+    // For the next round
+    results: Vec<Value>,
+    // Whether an approved read or patch gave the AI something new
+    progressed: bool,
+    round: usize,
+}
+
+impl ActionCards {
+    pub fn new(request: &str, proposals: Proposals) -> Self {
+        let mut cards = Self {
+            request: request.to_string(),
+            message: None,
+            cards: Vec::new(),
+            selected: 0,
+            results: Vec::new(),
+            progressed: false,
+            round: 1,
+        };
+        cards.add(proposals);
+        cards
+    }
+
+    // The next round's proposals, below the ones decided
+    pub fn add(&mut self, proposals: Proposals) {
+        self.message = proposals.message;
+        let first_new = self.cards.len();
+        self.cards.extend(proposals.actions.into_iter().map(|proposal| Card { proposal, state: CardState::Pending }));
+        self.selected = first_new.min(self.cards.len().saturating_sub(1));
+    }
+
+    pub fn request(&self) -> &str {
+        &self.request
+    }
+
+    pub fn proposal(&self, index: usize) -> Option<&Proposal> {
+        self.cards.get(index).map(|card| &card.proposal)
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<CardsAction> {
+        match code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < self.cards.len() => self.selected += 1,
+            KeyCode::Char('y') | KeyCode::Enter if self.is_pending(self.selected) => {
+                return Some(CardsAction::Approve(self.selected));
+            }
+            KeyCode::Char('n') if self.is_pending(self.selected) => {
+                let card = &mut self.cards[self.selected];
+                card.state = CardState::Declined;
+                self.results.push(proposals::declined(&card.proposal, None));
+                self.next_pending();
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return Some(CardsAction::Close),
+            _ => {}
+        }
+        None
+    }
+
+    fn is_pending(&self, index: usize) -> bool {
+        self.cards.get(index).is_some_and(|card| card.state == CardState::Pending)
+    }
+
+    fn next_pending(&mut self) {
+        if let Some(index) = (0..self.cards.len()).find(|&i| self.is_pending(i)) {
+            self.selected = index;
+        }
+    }
+
+    // What came of an approved card
+    pub fn resolve(&mut self, index: usize, result: anyhow::Result<Value>) {
+        let Some(card) = self.cards.get_mut(index) else {
+            return;
+        };
+        match result {
+            Ok(value) => {
+                card.state = CardState::Done(match &card.proposal {
+                    Proposal::ReadFile { .. } => format!("Sent {} bytes", value["content"].as_str().map_or(0, str::len)),
+                    Proposal::WritePatch { .. } => match value["backup"].as_str() {
+                        Some(backup) => format!("Applied; the old version is in {}", backup),
+                        None => "Created".to_string(),
+                    },
+                    Proposal::RunCommand { .. } => "Submitted".to_string(),
+                    Proposal::EditFile { .. } if value["changed"] == true => "Saved".to_string(),
+                    Proposal::EditFile { .. } => "Closed unchanged".to_string(),
+                });
+                self.progressed |= !matches!(card.proposal, Proposal::RunCommand { .. });
+                self.results.push(value);
+            }
+            Err(e) => {
+                let error = format!("{:#}", e);
+                self.results.push(proposals::declined(&card.proposal, Some(&error)));
+                card.state = CardState::Failed(error);
+            }
+        }
+        self.next_pending();
+    }
+
+    // Once every card is decided, what to send for another round, if there
+    // is anything new to go on and rounds left
+    pub fn next_round(&mut self) -> Option<Vec<Value>> {
+        let decided = (0..self.cards.len()).all(|i| !self.is_pending(i));
+        if !decided || !self.progressed || self.round >= MAX_ROUNDS {
+            return None;
+        }
+        self.round += 1;
+        self.progressed = false;
+        Some(std::mem::take(&mut self.results))
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let dim = Style::default().fg(Color::DarkGray);
+        let mut lines = vec![Line::styled(format!("ai do: {}", self.request), dim)];
+        if let Some(message) = &self.message {
+            lines.push(Line::styled(message.clone(), Style::default().fg(Color::White)));
+        }
+        lines.push(Line::from(""));
+        if self.cards.is_empty() {
+            lines.push(Line::from("Nothing to do."));
+        }
+
+        for (i, card) in self.cards.iter().enumerate() {
+            let (mark, color) = match &card.state {
+                CardState::Pending => ("◦ ", Color::Yellow),
+                CardState::Done(_) => ("✓ ", Color::Green),
+                CardState::Declined => ("⊘ ", Color::DarkGray),
+                CardState::Failed(_) => ("✗ ", Color::Red),
+            };
+            let mut title = Style::default().fg(Color::White);
+            if i == self.selected {
+                title = title.bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+            }
+            lines.push(Line::from(vec![
+                Span::styled(mark, Style::default().fg(color)),
+                Span::styled(card.proposal.title(), title),
+            ]));
+            if let Some(reason) = card.proposal.reason() {
+                lines.push(Line::styled(format!("  {}", reason), dim));
+            }
+            match &card.state {
+                CardState::Done(note) => lines.push(Line::styled(format!("  {}", note), Style::default().fg(Color::Green))),
+                CardState::Failed(error) => lines.push(Line::styled(format!("  {}", error), Style::default().fg(Color::Red))),
+                _ => {}
+            }
+            if i == self.selected && card.state == CardState::Pending {
+                lines.extend(preview(&card.proposal));
+            }
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "[↑↓] select   [y] approve   [n] decline   [esc] close",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let width = 100.min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let panel = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            borders
+                .block()
+                .title(" Proposed actions ")
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
+
+fn preview(proposal: &Proposal) -> Vec<Line<'static>> {
+    let dim = Style::default().fg(Color::DarkGray);
+    match proposal {
+        Proposal::ReadFile { .. } => vec![Line::styled("  Sends the file's contents to the AI", dim)],
+        Proposal::RunCommand { command, .. } => vec![Line::styled(format!("  $ {}", command), dim)],
+        Proposal::EditFile { .. } => vec![Line::styled("  Opens it in your editor; what you save goes to the AI", dim)],
+        Proposal::WritePatch { diff, .. } => {
+            let lines: Vec<&str> = diff.lines().filter(|l| !l.starts_with("---") && !l.starts_with("+++")).collect();
+            let mut preview: Vec<Line> = lines
+                .iter()
+                .take(PREVIEW_LINES)
+                .map(|line| {
+                    let color = match line.chars().next() {
+                        Some('+') => Color::Green,
+                        Some('-') => Color::Red,
+                        Some('@') => Color::Cyan,
+                        _ => Color::Gray,
+                    };
+                    Line::styled(format!("  {}", line), Style::default().fg(color))
+                })
+                .collect();
+            if lines.len() > PREVIEW_LINES {
+                preview.push(Line::styled(format!("  … {} more lines", lines.len() - PREVIEW_LINES), dim));
+            }
+            preview
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn read(path: &str) -> Proposal {
+        Proposal::ReadFile { path: path.to_string(), reason: None }
+    }
+
+    #[test]
+    fn rounds_follow_approved_reads() {
+        let proposals = Proposals { message: None, actions: vec![read("a.rs"), read("b.rs")] };
+        let mut cards = ActionCards::new("fix the build", proposals);
+        assert_eq!(cards.handle_key(KeyCode::Char('y')), Some(CardsAction::Approve(0)));
+        cards.resolve(0, Ok(json!({ "kind": "read_file", "content": "fn main() {}" })));
+        assert_eq!(cards.cards[0].state, CardState::Done("Sent 12 bytes".to_string()));
+        // Still waiting on b.rs, which is selected now
+        assert_eq!(cards.next_round(), None);
+        cards.handle_key(KeyCode::Char('n'));
+
+        let results = cards.next_round().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1]["declined"], true);
+        cards.add(Proposals { message: Some("Done".to_string()), actions: Vec::new() });
+        assert_eq!(cards.next_round(), None);
+    }
+}
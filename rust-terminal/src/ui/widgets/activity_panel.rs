@@ -0,0 +1,116 @@
+// Modal list of what's running: commands, AI requests, indexing and
+// downloads from core::operations, with progress and per-item cancel
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::core::operations::OperationInfo;
+use crate::ui::animation::progress_bar;
+use crate::ui::themes::BorderSet;
+
+// Cells in a progress bar
+const BAR_WIDTH: usize = 16;
+
+// `[████░░░░] 50% 2/4 test`: the bar the panel and the status bar draw for
+// an operation, None while it has nothing to show
+pub fn progress_line(operation: &OperationInfo, width: usize) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(progress) = operation.progress {
+        parts.push(progress_bar(progress as f64, width));
+    }
+    if let Some((done, total)) = operation.steps {
+        parts.push(format!("{}/{}", done, total));
+    }
+    parts.extend(operation.label.clone());
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActivityAction {
+    Cancel(u64),
+    Close,
+}
+
+pub struct ActivityPanel {
+    operations: Vec<OperationInfo>,
+    selected: usize,
+}
+
+impl ActivityPanel {
+    pub fn new(operations: Vec<OperationInfo>) -> Self {
+        Self { operations, selected: 0 }
+    }
+
+    // Refreshed while open, as operations come and go
+    pub fn update(&mut self, operations: Vec<OperationInfo>) {
+        self.selected = self.selected.min(operations.len().saturating_sub(1));
+        self.operations = operations;
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<ActivityAction> {
+        match code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < self.operations.len() => self.selected += 1,
+            KeyCode::Char('c') | KeyCode::Delete => {
+                return Some(ActivityAction::Cancel(self.operations.get(self.selected)?.id));
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return Some(ActivityAction::Close),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let dim = Style::default().fg(Color::DarkGray);
+        let mut lines = Vec::new();
+        if self.operations.is_empty() {
+            lines.push(Line::from("Nothing running."));
+        }
+        for (i, operation) in self.operations.iter().enumerate() {
+            let mut text = Style::default().fg(Color::White);
+            if i == self.selected {
+                text = text.bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+            }
+            let state = match (operation.cancelled, operation.cancellable) {
+                (true, _) => " cancelling…".to_string(),
+                (false, false) => " (can't be cancelled)".to_string(),
+                (false, true) => String::new(),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{} ", operation.kind.icon()), Style::default().fg(Color::Cyan)),
+                Span::styled(operation.title.clone(), text),
+                Span::styled(format!("  {}s{}", operation.secs, state), dim),
+            ]));
+            if let Some(progress) = progress_line(operation, BAR_WIDTH) {
+                lines.push(Line::styled(format!("  {}", progress), Style::default().fg(Color::Green)));
+            }
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "[↑↓] select   [c] cancel   [esc] close",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let width = 80.min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let panel = Paragraph::new(lines).block(
+            borders
+                .block()
+                .title(" Activity ")
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
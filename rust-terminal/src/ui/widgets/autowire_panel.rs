@@ -0,0 +1,252 @@
+// Auto-wire side panel: resizable, focusable service list with actions, or
+// the local worker's log, under how many commands fell back and why
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+use crate::ui::themes::Palette;
+
+const MIN_WIDTH_PERCENT: u16 = 20;
+const MAX_WIDTH_PERCENT: u16 = 70;
+const WIDTH_STEP: u16 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelAction {
+    Invoke,
+    Details,
+    Restart,
+}
+
+impl PanelAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PanelAction::Invoke => "invoke",
+            PanelAction::Details => "details",
+            PanelAction::Restart => "restart",
+        }
+    }
+}
+
+pub struct AutoWirePanel {
+    width_percent: u16,
+    focused: bool,
+    selected: Option<usize>,
+    detail: Option<String>,
+    // Shows the worker's log instead of the service list
+    show_logs: bool,
+}
+
+impl AutoWirePanel {
+    pub fn new() -> Self {
+        Self {
+            width_percent: 30,
+            focused: false,
+            selected: None,
+            detail: None,
+            show_logs: false,
+        }
+    }
+
+    pub fn grow(&mut self) {
+        self.width_percent = (self.width_percent + WIDTH_STEP).min(MAX_WIDTH_PERCENT);
+    }
+
+    pub fn shrink(&mut self) {
+        self.width_percent = self.width_percent.saturating_sub(WIDTH_STEP).max(MIN_WIDTH_PERCENT);
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    pub fn select_next(&mut self, service_count: usize) {
+        if service_count == 0 {
+            self.selected = None;
+            return;
+        }
+        self.selected = match self.selected {
+            Some(i) if i + 1 < service_count => Some(i + 1),
+            _ => Some(0),
+        };
+    }
+
+    pub fn select_previous(&mut self, service_count: usize) {
+        if service_count == 0 {
+            self.selected = None;
+            return;
+        }
+        self.selected = match self.selected {
+            Some(0) | None => Some(service_count - 1),
+            Some(i) => Some(i - 1),
+        };
+    }
+
+    pub fn selected_service<'a>(&self, services: &'a [String]) -> Option<&'a str> {
+        self.selected
+            .and_then(|i| services.get(i))
+            .map(|s| s.as_str())
+    }
+
+    pub fn set_detail(&mut self, detail: String) {
+        self.detail = Some(detail);
+    }
+
+    pub fn clear_detail(&mut self) {
+        self.detail = None;
+    }
+
+    pub fn is_showing_logs(&self) -> bool {
+        self.show_logs
+    }
+
+    // Handles a key while focused; returns the action to run on the selected service
+    pub fn handle_key(&mut self, code: KeyCode, services: &[String]) -> Option<(PanelAction, String)> {
+        let action = match code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.select_previous(services.len());
+                None
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.select_next(services.len());
+                None
+            }
+            KeyCode::Char('<') => {
+                self.shrink();
+                None
+            }
+            KeyCode::Char('>') => {
+                self.grow();
+                None
+            }
+            KeyCode::Char('l') => {
+                self.show_logs = !self.show_logs;
+                None
+            }
+            KeyCode::Esc => {
+                self.focused = false;
+                self.clear_detail();
+                None
+            }
+            // Actions need the service list in view
+            _ if self.show_logs => None,
+            KeyCode::Enter => Some(PanelAction::Invoke),
+            KeyCode::Char('d') => Some(PanelAction::Details),
+            KeyCode::Char('r') => Some(PanelAction::Restart),
+            _ => None,
+        }?;
+
+        self.selected_service(services)
+            .map(|service| (action, service.to_string()))
+    }
+
+    // Splits `area` into (content, panel) according to the current width
+    pub fn split(&self, area: Rect) -> (Rect, Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(100 - self.width_percent),
+                Constraint::Percentage(self.width_percent),
+            ])
+            .split(area);
+        (chunks[0], chunks[1])
+    }
+
+    // `block` is the themed pane frame; focus picks its border color
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        block: Block,
+        palette: &Palette,
+        status: &str,
+        fallbacks: Option<&str>,
+        services: &[String],
+        logs: &[String],
+    ) {
+        let border_color = if self.focused { palette.highlight } else { palette.info };
+        let block = block
+            .title("Auto-Wire Panel")
+            .border_style(Style::default().fg(border_color));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let detail_height = if self.detail.is_some() { 6 } else { 0 };
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(2 + fallbacks.is_some() as u16),
+                Constraint::Min(1),
+                Constraint::Length(detail_height),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+
+        let mut header = vec![
+            Line::from(Span::styled(
+                "🔌 Auto-Wiring",
+                Style::default().fg(palette.info).add_modifier(Modifier::BOLD),
+            )),
+            Line::from(vec![
+                Span::styled("Status: ", Style::default().fg(palette.text)),
+                Span::styled(status.to_string(), Style::default().fg(palette.success)),
+            ]),
+        ];
+        if let Some(fallbacks) = fallbacks {
+            header.push(Line::styled(fallbacks.to_string(), Style::default().fg(palette.muted)));
+        }
+        f.render_widget(Paragraph::new(header), chunks[0]);
+
+        if self.show_logs {
+            // The newest lines that fit
+            let height = chunks[1].height as usize;
+            let lines: Vec<Line> = match logs {
+                [] => vec![Line::from("No worker output yet")],
+                _ => logs[logs.len().saturating_sub(height)..].iter().map(|l| Line::from(l.as_str())).collect(),
+            };
+            let log = Paragraph::new(lines).style(Style::default().fg(palette.text));
+            f.render_widget(log, chunks[1]);
+        } else {
+            let items: Vec<ListItem> = services
+                .iter()
+                .map(|s| ListItem::new(Line::from(vec![
+                    Span::styled("• ", Style::default().fg(palette.text)),
+                    Span::styled(s.as_str(), Style::default().fg(palette.highlight)),
+                ])))
+                .collect();
+            let list = List::new(items)
+                .highlight_style(Style::default().bg(palette.muted).add_modifier(Modifier::BOLD))
+                .highlight_symbol("▶ ");
+            let mut state = ListState::default().with_selected(self.selected);
+            f.render_stateful_widget(list, chunks[1], &mut state);
+        }
+
+        if let Some(detail) = &self.detail {
+            let detail = Paragraph::new(detail.as_str())
+                .block(Block::default().borders(Borders::TOP).title("Details"))
+                .style(Style::default().fg(palette.text));
+            f.render_widget(detail, chunks[2]);
+        }
+
+        let hint = if self.focused && self.show_logs {
+            "l: services  </>: resize  Esc: back"
+        } else if self.focused {
+            "Enter: invoke  d: details  r: restart  l: log  </>: resize  Esc: back"
+        } else {
+            "Ctrl+A: focus panel  Alt+←/→: resize"
+        };
+        f.render_widget(
+            Paragraph::new(hint).style(Style::default().fg(palette.muted)),
+            chunks[3],
+        );
+    }
+}
@@ -0,0 +1,331 @@
+// Mini-editor for a batch of commands (a pasted script, a generated plan):
+// every line can be edited or switched off before the enabled ones run, in
+// order. Shift+Up/Down adds cursors on the neighbouring lines at the same
+// column, to type a prefix or flag into several commands at once; Alt+Up/Down
+// moves the line. `scriptify` uses it as a checklist to write a script from.
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+use std::ops::RangeInclusive;
+
+use crate::core::scriptify::ScriptOptions;
+use crate::ui::themes::BorderSet;
+
+// Lines shown at once; the view follows the cursor
+const MAX_ROWS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchAction {
+    // The enabled, non-empty lines
+    Run(Vec<String>),
+    // The same, for `scriptify`
+    Script(Vec<String>, ScriptOptions),
+    Close,
+}
+
+struct BatchLine {
+    text: String,
+    enabled: bool,
+}
+
+pub struct BatchEditor {
+    title: String,
+    // Set when the lines become a script rather than run
+    script: Option<ScriptOptions>,
+    lines: Vec<BatchLine>,
+    row: usize,
+    // In chars; lines shorter than this put their cursor at the end
+    col: usize,
+    // Other end of the multi-cursor range
+    anchor: Option<usize>,
+}
+
+impl BatchEditor {
+    // Blank lines and comments start out disabled
+    pub fn new(title: &str, text: &str) -> Self {
+        let mut editor = Self {
+            title: title.to_string(),
+            script: None,
+            lines: Vec::new(),
+            row: 0,
+            col: 0,
+            anchor: None,
+        };
+        editor.insert_text(text);
+        editor.row = 0;
+        if editor.lines.is_empty() {
+            editor.lines.push(BatchLine { text: String::new(), enabled: true });
+        }
+        editor
+    }
+
+    // (command, checked) lines whose checked ones become a script
+    pub fn checklist(title: &str, lines: Vec<(String, bool)>, options: ScriptOptions) -> Self {
+        let mut editor = Self::new(title, "");
+        editor.script = Some(options);
+        if !lines.is_empty() {
+            editor.lines = lines.into_iter().map(|(text, enabled)| BatchLine { text, enabled }).collect();
+        }
+        editor
+    }
+
+    // Pasted while open: the lines go in after the current one
+    pub fn insert_text(&mut self, text: &str) {
+        let at = if self.lines.is_empty() { 0 } else { self.row + 1 };
+        let new: Vec<BatchLine> = text
+            .lines()
+            .map(|line| {
+                let line = line.trim_end();
+                let enabled = !line.trim().is_empty() && !line.trim_start().starts_with('#');
+                BatchLine { text: line.to_string(), enabled }
+            })
+            .collect();
+        let count = new.len();
+        self.lines.splice(at..at, new);
+        self.row = (at + count).saturating_sub(1);
+        self.col = 0;
+        self.anchor = None;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<BatchAction> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let last = self.lines.len() - 1;
+        match key.code {
+            KeyCode::Esc => return Some(BatchAction::Close),
+            KeyCode::Char('r') if ctrl => {
+                return Some(match &self.script {
+                    Some(options) => BatchAction::Script(self.commands(), options.clone()),
+                    None => BatchAction::Run(self.commands()),
+                });
+            }
+            KeyCode::Char('k') if ctrl => self.delete_lines(),
+            KeyCode::Up if alt && self.row > 0 => {
+                self.anchor = None;
+                self.lines.swap(self.row, self.row - 1);
+                self.row -= 1;
+            }
+            KeyCode::Down if alt && self.row < last => {
+                self.anchor = None;
+                self.lines.swap(self.row, self.row + 1);
+                self.row += 1;
+            }
+            KeyCode::Tab => {
+                let enabled = !self.lines[self.row].enabled;
+                for row in self.cursors() {
+                    self.lines[row].enabled = enabled;
+                }
+            }
+            KeyCode::Up | KeyCode::Down => {
+                if shift {
+                    self.anchor.get_or_insert(self.row);
+                } else {
+                    self.anchor = None;
+                }
+                self.row = match key.code {
+                    KeyCode::Up => self.row.saturating_sub(1),
+                    _ => (self.row + 1).min(last),
+                };
+                if !shift {
+                    self.col = self.col.min(self.len(self.row));
+                }
+            }
+            KeyCode::Left => self.col = self.col.min(self.len(self.row)).saturating_sub(1),
+            KeyCode::Right => self.col = (self.col + 1).min(self.len(self.row)),
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = self.len(self.row),
+            KeyCode::Enter => self.split_line(),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => {
+                for row in self.cursors() {
+                    let at = self.col.min(self.len(row));
+                    if at < self.len(row) {
+                        let byte = byte_at(&self.lines[row].text, at);
+                        self.lines[row].text.remove(byte);
+                    }
+                }
+            }
+            KeyCode::Char(c) if !ctrl => {
+                for row in self.cursors() {
+                    let at = self.col.min(self.len(row));
+                    let byte = byte_at(&self.lines[row].text, at);
+                    self.lines[row].text.insert(byte, c);
+                }
+                self.col = self.col.min(self.len(self.row).saturating_sub(1)) + 1;
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn commands(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .filter(|line| line.enabled && !line.text.trim().is_empty())
+            .map(|line| line.text.trim().to_string())
+            .collect()
+    }
+
+    fn cursors(&self) -> RangeInclusive<usize> {
+        let anchor = self.anchor.unwrap_or(self.row);
+        anchor.min(self.row)..=anchor.max(self.row)
+    }
+
+    fn len(&self, row: usize) -> usize {
+        self.lines[row].text.chars().count()
+    }
+
+    fn delete_lines(&mut self) {
+        let cursors = self.cursors();
+        let first = *cursors.start();
+        self.lines.drain(cursors);
+        if self.lines.is_empty() {
+            self.lines.push(BatchLine { text: String::new(), enabled: true });
+        }
+        self.row = first.min(self.lines.len() - 1);
+        self.col = self.col.min(self.len(self.row));
+        self.anchor = None;
+    }
+
+    fn split_line(&mut self) {
+        self.anchor = None;
+        let at = byte_at(&self.lines[self.row].text, self.col.min(self.len(self.row)));
+        let rest = self.lines[self.row].text.split_off(at);
+        self.lines.insert(self.row + 1, BatchLine { text: rest, enabled: true });
+        self.row += 1;
+        self.col = 0;
+    }
+
+    // With one cursor at the start of a line, joins it onto the one above
+    fn backspace(&mut self) {
+        let col = self.col.min(self.len(self.row));
+        if col == 0 {
+            if self.anchor.is_none() && self.row > 0 {
+                let line = self.lines.remove(self.row);
+                self.row -= 1;
+                self.col = self.len(self.row);
+                self.lines[self.row].text.push_str(&line.text);
+            }
+            return;
+        }
+        for row in self.cursors() {
+            let at = self.col.min(self.len(row));
+            if at > 0 {
+                let byte = byte_at(&self.lines[row].text, at - 1);
+                self.lines[row].text.remove(byte);
+            }
+        }
+        self.col = col - 1;
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let cursors = self.cursors();
+        let first = self.row.saturating_sub(MAX_ROWS - 1);
+        let mut lines = Vec::new();
+        for (i, line) in self.lines.iter().enumerate().skip(first).take(MAX_ROWS) {
+            let text = if line.enabled {
+                Style::default().fg(Color::White)
+            } else {
+                Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT)
+            };
+            let mut spans = vec![
+                Span::styled(if line.enabled { "[x] " } else { "[ ] " }, Style::default().fg(Color::Cyan)),
+                Span::styled(format!("{:>3}  ", i + 1), Style::default().fg(Color::DarkGray)),
+            ];
+            if cursors.contains(&i) {
+                let at = self.col.min(line.text.chars().count());
+                let (before, after) = line.text.split_at(byte_at(&line.text, at));
+                let mut rest = after.chars();
+                let under = rest.next().map(String::from).unwrap_or_else(|| " ".to_string());
+                spans.push(Span::styled(before.to_string(), text));
+                spans.push(Span::styled(under, text.add_modifier(Modifier::REVERSED)));
+                spans.push(Span::styled(rest.collect::<String>(), text));
+            } else {
+                spans.push(Span::styled(line.text.clone(), text));
+            }
+            lines.push(Line::from(spans));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            format!(
+                "[tab] on/off   [shift+↑↓] more cursors   [alt+↑↓] move   [ctrl+k] delete line   [ctrl+r] {} {}   [esc] cancel",
+                if self.script.is_some() { "script" } else { "run" },
+                self.commands().len()
+            ),
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let width = 100.min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let panel = Paragraph::new(lines).block(
+            borders
+                .block()
+                .title(format!(" {} ", self.title))
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
+
+// Byte offset of char `index` in `text`, or its length past the end
+fn byte_at(text: &str, index: usize) -> usize {
+    text.char_indices().nth(index).map_or(text.len(), |(byte, _)| byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn edits_toggles_and_runs_enabled_lines() {
+        let mut editor = BatchEditor::new("Script", "# setup\nnpm install\nnpm test\n\nnpm run build");
+        assert_eq!(editor.commands(), vec!["npm install", "npm test", "npm run build"]);
+
+        // Two cursors at the start of lines 2 and 3
+        editor.handle_key(key(KeyCode::Down, KeyModifiers::NONE));
+        editor.handle_key(key(KeyCode::Down, KeyModifiers::SHIFT));
+        for c in "CI=1 ".chars() {
+            editor.handle_key(key(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        // The last line off
+        editor.handle_key(key(KeyCode::Down, KeyModifiers::NONE));
+        editor.handle_key(key(KeyCode::Down, KeyModifiers::NONE));
+        editor.handle_key(key(KeyCode::Tab, KeyModifiers::NONE));
+
+        assert_eq!(
+            editor.handle_key(key(KeyCode::Char('r'), KeyModifiers::CONTROL)),
+            Some(BatchAction::Run(vec!["CI=1 npm install".into(), "CI=1 npm test".into()]))
+        );
+    }
+
+    #[test]
+    fn reorders_a_checklist_into_a_script() {
+        let lines = vec![("make".to_string(), true), ("ls".to_string(), false), ("cd build".to_string(), true)];
+        let mut editor = BatchEditor::checklist("Scriptify", lines, ScriptOptions::default());
+        editor.handle_key(key(KeyCode::Down, KeyModifiers::NONE));
+        editor.handle_key(key(KeyCode::Down, KeyModifiers::NONE));
+        editor.handle_key(key(KeyCode::Up, KeyModifiers::ALT));
+        editor.handle_key(key(KeyCode::Up, KeyModifiers::ALT));
+        assert_eq!(
+            editor.handle_key(key(KeyCode::Char('r'), KeyModifiers::CONTROL)),
+            Some(BatchAction::Script(vec!["cd build".into(), "make".into()], ScriptOptions::default()))
+        );
+    }
+}
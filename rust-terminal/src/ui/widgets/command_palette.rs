@@ -0,0 +1,207 @@
+// Ctrl+P: every action in one fuzzy-searchable list (see core::actions), by
+// title, keywords or id, with its icon and key; Enter runs the selected one
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::core::actions::Action;
+use crate::ui::themes::BorderSet;
+use crate::ui::widgets::history_search::fuzzy_match;
+
+// Actions listed at once
+const MAX_ROWS: usize = 12;
+// A keyword or id match ranks below a title match as good
+const KEYWORD_PENALTY: i64 = 300;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteAction {
+    Run(Action),
+    Close,
+}
+
+struct Match {
+    entry: usize,
+    // Char positions of the query in the title, for highlighting
+    positions: Vec<usize>,
+}
+
+pub struct CommandPalette {
+    // Each action with the label of its key, if bound
+    entries: Vec<(Action, Option<String>)>,
+    query: String,
+    matches: Vec<Match>,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn new(entries: Vec<(Action, Option<String>)>) -> Self {
+        let mut palette = Self { entries, query: String::new(), matches: Vec::new(), selected: 0 };
+        palette.refresh();
+        palette
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<PaletteAction> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Some(PaletteAction::Close),
+            KeyCode::Char('p' | 'c') if ctrl => return Some(PaletteAction::Close),
+            KeyCode::Enter => {
+                let found = self.matches.get(self.selected)?;
+                return Some(PaletteAction::Run(self.entries[found.entry].0.clone()));
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < self.matches.len() => self.selected += 1,
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.refresh();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.refresh();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn refresh(&mut self) {
+        let query = self.query.to_lowercase();
+        let mut scored: Vec<(i64, Match)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(entry, (action, _))| {
+                if query.is_empty() {
+                    return Some((0, Match { entry, positions: Vec::new() }));
+                }
+                let by_title = fuzzy_match(&query, &action.title);
+                let by_keyword = action
+                    .keywords
+                    .iter()
+                    .chain(std::iter::once(&action.id))
+                    .filter_map(|keyword| fuzzy_match(&query, keyword))
+                    .map(|(score, _)| score - KEYWORD_PENALTY)
+                    .max();
+                match (by_title, by_keyword) {
+                    (Some((title, _)), Some(keyword)) if keyword > title => {
+                        Some((keyword, Match { entry, positions: Vec::new() }))
+                    }
+                    (Some((score, positions)), _) => Some((score, Match { entry, positions })),
+                    (None, Some(score)) => Some((score, Match { entry, positions: Vec::new() })),
+                    (None, None) => None,
+                }
+            })
+            .collect();
+        // Stable, so equal scores stay in registration order
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        self.matches = scored.into_iter().map(|(_, found)| found).collect();
+        self.selected = 0;
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let width = 80.min(area.width);
+        let room = width.saturating_sub(4) as usize;
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled("> ", Style::default().fg(Color::Cyan)),
+                Span::styled(self.query.clone(), Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+                Span::styled("▏", Style::default().fg(Color::Cyan)),
+                Span::styled(
+                    format!("  {}/{}", self.matches.len(), self.entries.len()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]),
+            Line::from(""),
+        ];
+
+        let first = self.selected.saturating_sub(MAX_ROWS - 1);
+        for (i, found) in self.matches.iter().enumerate().skip(first).take(MAX_ROWS) {
+            let (action, key) = &self.entries[found.entry];
+            let mut base = Style::default().fg(Color::Gray);
+            if i == self.selected {
+                base = base.fg(Color::White).bg(Color::DarkGray);
+            }
+            let key = key.as_deref().unwrap_or_default();
+            let icon = action.icon.width() + 1;
+            let title_room = room.saturating_sub(icon + key.chars().count() + 1);
+            let mut spans = vec![Span::styled(format!("{} ", action.icon), base)];
+            spans.extend(action.title.chars().take(title_room).enumerate().map(|(n, c)| {
+                let style = if found.positions.contains(&n) {
+                    base.fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                } else {
+                    base
+                };
+                Span::styled(c.to_string(), style)
+            }));
+            let used = icon + action.title.chars().count().min(title_room);
+            spans.push(Span::styled(" ".repeat(room.saturating_sub(used + key.chars().count())), base));
+            spans.push(Span::styled(key.to_string(), base.fg(Color::Cyan)));
+            lines.push(Line::from(spans));
+        }
+        if self.matches.is_empty() {
+            lines.push(Line::styled("  No matching actions", Style::default().fg(Color::DarkGray)));
+        }
+        if let Some(found) = self.matches.get(self.selected) {
+            lines.push(Line::from(""));
+            lines.push(Line::styled(self.entries[found.entry].0.id.clone(), Style::default().fg(Color::DarkGray)));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "[type] search   [↑↓] select   [enter] run   [esc] close",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 3, width, height);
+        let panel = Paragraph::new(lines).block(
+            borders
+                .block()
+                .title(" Command palette ")
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::actions::Target;
+
+    fn action(id: &str, title: &str, keywords: &[&str]) -> (Action, Option<String>) {
+        let action = Action::new(id, title, Target::Command(id.to_string())).with_keywords(keywords);
+        (action, None)
+    }
+
+    fn typed(palette: &mut CommandPalette, text: &str) {
+        for c in text.chars() {
+            palette.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn finds_actions_by_title_then_keyword() {
+        let mut palette = CommandPalette::new(vec![
+            action("nexterm.review", "Review staged changes", &["git", "diff"]),
+            action("workflow.deploy", "Deploy the API", &["release"]),
+            action("agent.git_agent", "Invoke git_agent", &["git", "agent"]),
+        ]);
+        typed(&mut palette, "git");
+        let found: Vec<&str> = palette.matches.iter().map(|m| palette.entries[m.entry].0.id.as_str()).collect();
+        assert_eq!(found, ["agent.git_agent", "nexterm.review"]);
+
+        palette.handle_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        palette.handle_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        palette.handle_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        typed(&mut palette, "rel");
+        let run = palette.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(matches!(run, Some(PaletteAction::Run(action)) if action.id == "workflow.deploy"));
+    }
+}
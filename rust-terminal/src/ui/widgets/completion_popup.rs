@@ -0,0 +1,105 @@
+// Autocomplete dropdown anchored to the input cursor
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::ai::suggestions::Completion;
+use crate::ui::themes::BorderSet;
+
+const MAX_VISIBLE: u16 = 8;
+const MAX_WIDTH: u16 = 72;
+
+pub struct CompletionPopup {
+    items: Vec<Completion>,
+    selected: usize,
+}
+
+impl CompletionPopup {
+    pub fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        !self.items.is_empty()
+    }
+
+    pub fn show(&mut self, items: Vec<Completion>) {
+        self.items = items;
+        self.selected = 0;
+    }
+
+    pub fn hide(&mut self) {
+        self.items.clear();
+        self.selected = 0;
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + 1) % self.items.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Completion> {
+        self.items.get(self.selected)
+    }
+
+    // Places the dropdown below `cursor` when it fits in `bounds`, otherwise above it
+    fn area(&self, cursor: (u16, u16), bounds: Rect) -> Rect {
+        let label_width = self.items.iter().map(|c| c.text.len()).max().unwrap_or(0) as u16;
+        let desc_width = self.items.iter().map(|c| c.description.len()).max().unwrap_or(0) as u16;
+        let width = (label_width + desc_width + 5).min(MAX_WIDTH).min(bounds.width);
+        let height = (self.items.len() as u16).min(MAX_VISIBLE) + 2;
+
+        let (x, y) = cursor;
+        let x = x.min(bounds.right().saturating_sub(width));
+        let y = if y + 1 + height <= bounds.bottom() {
+            y + 1
+        } else {
+            y.saturating_sub(height).max(bounds.y)
+        };
+
+        Rect::new(x, y, width, height.min(bounds.height))
+    }
+
+    pub fn render(&self, f: &mut Frame, cursor: (u16, u16), bounds: Rect, borders: BorderSet) {
+        if !self.is_visible() {
+            return;
+        }
+
+        let area = self.area(cursor, bounds);
+        let label_width = self.items.iter().map(|c| c.text.len()).max().unwrap_or(0);
+        let items: Vec<ListItem> = self.items
+            .iter()
+            .map(|c| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:<width$}  ", c.text, width = label_width),
+                        Style::default().fg(Color::White),
+                    ),
+                    Span::styled(c.description.as_str(), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(borders.block().style(Style::default().fg(Color::Cyan)))
+            .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut state);
+    }
+}
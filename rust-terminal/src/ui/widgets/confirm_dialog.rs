@@ -0,0 +1,52 @@
+// Modal yes/no confirmation listing what is about to happen; with a phrase,
+// it has to be typed back instead. A form (see form.rs) underneath, so it
+// answers to the same keys as every other dialog.
+use crossterm::event::KeyCode;
+use ratatui::{layout::Rect, Frame};
+
+use crate::ui::themes::BorderSet;
+use crate::ui::widgets::form::{Button, Field, Form, FormOutcome};
+
+pub struct ConfirmDialog {
+    form: Form,
+    phrase: Option<String>,
+}
+
+impl ConfirmDialog {
+    pub fn new(title: impl Into<String>, lines: Vec<String>) -> Self {
+        let form = Form::new(title)
+            .with_lines(lines)
+            .with_buttons(vec![Button::new("Run").key('y'), Button::cancel("Cancel").key('n')]);
+        Self { form, phrase: None }
+    }
+
+    // Other buttons than Run (y) and Cancel (n)
+    pub fn with_buttons(mut self, buttons: Vec<Button>) -> Self {
+        self.form = self.form.with_buttons(buttons);
+        self
+    }
+
+    pub fn with_phrase(mut self, phrase: &str) -> Self {
+        self.form = self
+            .form
+            .with_field(Field::text("phrase", &format!("Type {} to continue", phrase), ""))
+            .with_buttons(vec![Button::new("Confirm"), Button::cancel("Cancel")]);
+        self.phrase = Some(phrase.to_string());
+        self
+    }
+
+    // Some(true) to go ahead, Some(false) to cancel, None while undecided.
+    // A mistyped phrase cancels.
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<bool> {
+        match self.form.handle_key(code)? {
+            FormOutcome::Submit { values, .. } => {
+                Some(self.phrase.as_ref().is_none_or(|phrase| values.get("phrase") == Some(phrase)))
+            }
+            FormOutcome::Cancel => Some(false),
+        }
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        self.form.render(f, area, borders);
+    }
+}
@@ -0,0 +1,204 @@
+// Quick actions for what was right-clicked (an output line, a tab or the
+// command input), or with the Menu key for the input. Most have shortcuts
+// too; the menu is there to find them.
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::core::binary;
+use crate::ui::themes::BorderSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuTarget {
+    // The row's text as shown, and the command when the row is where one
+    // was run (its prompt mark)
+    Output(String, Option<String>),
+    Tab(usize),
+    Input(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuCommand {
+    Copy(String),
+    CopyCommandOutput,
+    Rerun(String),
+    // Into the input line, to change before running
+    Edit(String),
+    // `route <command>`
+    Route(String),
+    OpenUrl(String),
+    SelectTab(usize),
+    NewTab,
+    CloseTab(usize),
+    SearchHistory,
+    EditAsBatch(String),
+    ClearInput,
+    // In the hex viewer, see core::binary
+    ViewBinary(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuAction {
+    Run(MenuCommand),
+    Close,
+}
+
+pub struct ContextMenu {
+    // The cell it was opened on
+    at: (u16, u16),
+    items: Vec<(String, MenuCommand)>,
+    selected: usize,
+}
+
+impl ContextMenu {
+    // None when there's nothing to offer
+    pub fn new(target: MenuTarget, at: (u16, u16)) -> Option<Self> {
+        let items = items(target);
+        (!items.is_empty()).then_some(Self { at, items, selected: 0 })
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<MenuAction> {
+        match code {
+            KeyCode::Up => self.selected = self.selected.checked_sub(1).unwrap_or(self.items.len() - 1),
+            KeyCode::Down => self.selected = (self.selected + 1) % self.items.len(),
+            KeyCode::Enter => return Some(MenuAction::Run(self.items[self.selected].1.clone())),
+            KeyCode::Esc | KeyCode::Menu => return Some(MenuAction::Close),
+            _ => {}
+        }
+        None
+    }
+
+    // A click on an item runs it; anywhere else closes the menu
+    pub fn handle_click(&self, column: u16, row: u16, area: Rect) -> MenuAction {
+        let rect = self.rect(area);
+        let inside = column > rect.x && column < rect.right() - 1 && row > rect.y && row < rect.bottom() - 1;
+        match self.items.get((row.saturating_sub(rect.y + 1)) as usize) {
+            Some((_, command)) if inside => MenuAction::Run(command.clone()),
+            _ => MenuAction::Close,
+        }
+    }
+
+    fn rect(&self, area: Rect) -> Rect {
+        let longest = self.items.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+        let width = (longest as u16 + 4).min(area.width);
+        let height = (self.items.len() as u16 + 2).min(area.height);
+        let x = self.at.0.min(area.right().saturating_sub(width)).max(area.x);
+        // Under that cell, or over it when there's no room below
+        let y = if self.at.1 + 1 + height <= area.bottom() {
+            self.at.1 + 1
+        } else {
+            self.at.1.saturating_sub(height).max(area.y)
+        };
+        Rect::new(x, y, width, height)
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let lines: Vec<Line> = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, (label, _))| {
+                let mut style = Style::default().fg(Color::White);
+                if i == self.selected {
+                    style = style.bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+                }
+                Line::styled(format!(" {} ", label), style)
+            })
+            .collect();
+        let rect = self.rect(area);
+        let menu = Paragraph::new(lines).block(borders.block().style(Style::default().fg(Color::Yellow)));
+        f.render_widget(Clear, rect);
+        f.render_widget(menu, rect);
+    }
+}
+
+fn items(target: MenuTarget) -> Vec<(String, MenuCommand)> {
+    let mut items = Vec::new();
+    match target {
+        MenuTarget::Output(text, command) => {
+            let text = text.trim_end().to_string();
+            if let Some(command) = command.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+                items.push(("Run again".to_string(), MenuCommand::Rerun(command.to_string())));
+                items.push(("Edit command".to_string(), MenuCommand::Edit(command.to_string())));
+                items.push(("Show route".to_string(), MenuCommand::Route(command.to_string())));
+            }
+            // Binary output's placeholder
+            if let Some(id) = binary::placeholder_id(&text) {
+                items.push(("View as hex".to_string(), MenuCommand::ViewBinary(id)));
+                items.push(("Save to file".to_string(), MenuCommand::Edit(format!("binary save {} ", id))));
+            }
+            static URL: OnceLock<Regex> = OnceLock::new();
+            let url = URL.get_or_init(|| Regex::new(r#"https?://[^\s"'<>)\]]+"#).expect("valid URL pattern"));
+            if let Some(found) = url.find(&text) {
+                let url = found.as_str().trim_end_matches(['.', ',', ';', ':']).to_string();
+                items.push((format!("Open {}", url), MenuCommand::OpenUrl(url)));
+            }
+            if !text.trim().is_empty() {
+                items.push(("Copy line".to_string(), MenuCommand::Copy(text)));
+            }
+            items.push(("Copy command output".to_string(), MenuCommand::CopyCommandOutput));
+        }
+        MenuTarget::Tab(index) => {
+            items.push(("Switch to tab".to_string(), MenuCommand::SelectTab(index)));
+            items.push(("New tab".to_string(), MenuCommand::NewTab));
+            items.push(("Close tab".to_string(), MenuCommand::CloseTab(index)));
+        }
+        MenuTarget::Input(input) => {
+            if !input.trim().is_empty() {
+                items.push(("Edit as batch".to_string(), MenuCommand::EditAsBatch(input.clone())));
+                items.push(("Show route".to_string(), MenuCommand::Route(input.trim().to_string())));
+                items.push(("Copy".to_string(), MenuCommand::Copy(input)));
+                items.push(("Clear".to_string(), MenuCommand::ClearInput));
+            }
+            items.push(("Search history".to_string(), MenuCommand::SearchHistory));
+        }
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(target: MenuTarget) -> Vec<String> {
+        items(target).into_iter().map(|(label, _)| label).collect()
+    }
+
+    #[test]
+    fn offers_actions_for_what_was_clicked() {
+        let output = |text: &str| MenuTarget::Output(text.to_string(), None);
+        assert_eq!(
+            labels(MenuTarget::Output("❯ npm run dev".to_string(), Some("npm run dev".to_string()))),
+            ["Run again", "Edit command", "Show route", "Copy line", "Copy command output"]
+        );
+        // Output that only looks like a prompt isn't one
+        assert_eq!(labels(output("❯ rm -rf ~")), ["Copy line", "Copy command output"]);
+        assert_eq!(
+            labels(output("  Local: http://localhost:5173/")),
+            ["Open http://localhost:5173/", "Copy line", "Copy command output"]
+        );
+        assert_eq!(
+            labels(output("⚠ binary output #2 (12 bytes): `binary view 2`")),
+            ["View as hex", "Save to file", "Copy line", "Copy command output"]
+        );
+        assert_eq!(labels(MenuTarget::Input(String::new())), ["Search history"]);
+
+        // Clicks inside run the item under them; outside close
+        let area = Rect::new(0, 0, 80, 24);
+        let mut menu = ContextMenu::new(MenuTarget::Tab(2), (78, 1)).unwrap();
+        assert_eq!(menu.rect(area), Rect::new(63, 2, 17, 5));
+        assert_eq!(menu.handle_click(65, 5, area), MenuAction::Run(MenuCommand::CloseTab(2)));
+        assert_eq!(menu.handle_click(10, 4, area), MenuAction::Close);
+        // No room under the cell: over it
+        assert_eq!(ContextMenu::new(MenuTarget::Tab(0), (5, 22)).unwrap().rect(area), Rect::new(5, 17, 17, 5));
+        menu.handle_key(KeyCode::Up);
+        assert_eq!(menu.handle_key(KeyCode::Enter), Some(MenuAction::Run(MenuCommand::CloseTab(2))));
+    }
+}
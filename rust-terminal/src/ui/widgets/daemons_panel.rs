@@ -0,0 +1,116 @@
+// Modal list of daemonized commands with the end of the selected one's log
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::core::daemons::{self, DaemonInfo};
+use crate::ui::themes::BorderSet;
+
+// Log lines shown under the list
+const LOG_LINES: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DaemonsAction {
+    Stop(u32),
+    // Drop an exited daemon and its log
+    Forget(u32),
+    Close,
+}
+
+pub struct DaemonsPanel {
+    daemons: Vec<DaemonInfo>,
+    selected: usize,
+}
+
+impl DaemonsPanel {
+    pub fn new(daemons: Vec<DaemonInfo>) -> Self {
+        // Starts on the newest
+        let selected = daemons.len().saturating_sub(1);
+        Self { daemons, selected }
+    }
+
+    // Refreshed while open, as daemons exit
+    pub fn update(&mut self, daemons: Vec<DaemonInfo>) {
+        self.selected = self.selected.min(daemons.len().saturating_sub(1));
+        self.daemons = daemons;
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<DaemonsAction> {
+        match code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < self.daemons.len() => self.selected += 1,
+            KeyCode::Char('s') => return Some(DaemonsAction::Stop(self.daemons.get(self.selected)?.id)),
+            KeyCode::Char('d') | KeyCode::Delete => {
+                return Some(DaemonsAction::Forget(self.daemons.get(self.selected)?.id));
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return Some(DaemonsAction::Close),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let mut lines = Vec::new();
+        if self.daemons.is_empty() {
+            lines.push(Line::from("No daemons."));
+            lines.push(Line::styled(
+                "Start one with `daemonize <command>`",
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        for (i, daemon) in self.daemons.iter().enumerate() {
+            let color = if daemon.running { Color::Green } else { Color::DarkGray };
+            let mut text = Style::default().fg(Color::White);
+            if i == self.selected {
+                text = text.bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+            }
+            lines.push(Line::from(vec![
+                Span::styled(if daemon.running { "● " } else { "○ " }, Style::default().fg(color)),
+                Span::styled(daemons::describe(daemon), text),
+            ]));
+        }
+
+        let width = 96.min(area.width);
+        if let Some(daemon) = self.daemons.get(self.selected) {
+            lines.push(Line::from(""));
+            lines.push(Line::styled(
+                format!("── {} (pid {}) ", daemon.log.display(), daemon.pid),
+                Style::default().fg(Color::Cyan),
+            ));
+            let log = daemons::tail(&daemon.log, LOG_LINES);
+            if log.is_empty() {
+                lines.push(Line::styled("(no output yet)", Style::default().fg(Color::DarkGray)));
+            }
+            let room = width.saturating_sub(2) as usize;
+            lines.extend(log.into_iter().map(|line| {
+                Line::styled(line.chars().take(room).collect::<String>(), Style::default().fg(Color::Gray))
+            }));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "[↑↓] select   [s] stop   [d] forget exited   [esc] close",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let panel = Paragraph::new(lines).block(
+            borders
+                .block()
+                .title(" Daemons ")
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
@@ -0,0 +1,100 @@
+// Temporary view over the output of a scrollback filter; closing it leaves
+// the output pane as it was
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::ui::themes::BorderSet;
+
+// Rows PageUp/PageDown move by
+const PAGE: usize = 20;
+
+pub struct FilterView {
+    pipeline: String,
+    lines: Vec<String>,
+    // First line shown
+    offset: usize,
+}
+
+impl FilterView {
+    pub fn new(pipeline: &str, lines: Vec<String>) -> Self {
+        Self {
+            pipeline: pipeline.to_string(),
+            lines,
+            offset: 0,
+        }
+    }
+
+    // Opened with `line` at the top
+    pub fn at(mut self, line: usize) -> Self {
+        self.offset = line.min(self.lines.len().saturating_sub(1));
+        self
+    }
+
+    // True when the view should close
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        let last = self.lines.len().saturating_sub(1);
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => self.offset = self.offset.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.offset = (self.offset + 1).min(last),
+            KeyCode::PageUp => self.offset = self.offset.saturating_sub(PAGE),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.offset = (self.offset + PAGE).min(last),
+            KeyCode::Home | KeyCode::Char('g') => self.offset = 0,
+            KeyCode::End | KeyCode::Char('G') => self.offset = last,
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => return true,
+            _ => {}
+        }
+        false
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let width = area.width.saturating_sub(4).max(20).min(area.width);
+        let height = area.height.saturating_sub(2).max(5).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        // Borders and the key hint line
+        let rows = height.saturating_sub(3) as usize;
+        let room = width.saturating_sub(2) as usize;
+
+        let mut lines: Vec<Line> = if self.lines.is_empty() {
+            vec![Line::styled("(no output)", Style::default().fg(Color::DarkGray))]
+        } else {
+            self.lines
+                .iter()
+                .skip(self.offset)
+                .take(rows)
+                .map(|line| Line::styled(line.chars().take(room).collect::<String>(), Style::default().fg(Color::White)))
+                .collect()
+        };
+        lines.resize(rows, Line::from(""));
+        let shown = (self.offset + rows).min(self.lines.len());
+        lines.push(Line::styled(
+            format!(
+                "{}-{} of {}   [↑↓/PgUp/PgDn] scroll   [esc] close",
+                (self.offset + 1).min(shown),
+                shown,
+                self.lines.len()
+            ),
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let title: String = self.pipeline.chars().take(room.saturating_sub(12)).collect();
+        let panel = Paragraph::new(lines).block(
+            borders
+                .block()
+                .title(format!(" filter: {} ", title))
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
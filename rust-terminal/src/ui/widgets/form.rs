@@ -0,0 +1,316 @@
+// Modal forms: a message, fields (text inputs, selects, checkboxes) and a row
+// of buttons, all worked from the keyboard the same way. Tab and ↓ move to
+// the next field or button, Shift+Tab and ↑ back; Enter in a field moves on
+// (the last one submits), Enter on a button presses it; Esc cancels from
+// anywhere. Space toggles a checkbox, ← → pick a select's option. A button's
+// key (y for "Run (y)") presses it unless a text input has the focus. The
+// focused line is marked with ▸, not only by color.
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+    Frame,
+};
+use std::collections::BTreeMap;
+
+use crate::core::actions::{Action, InputKind};
+use crate::ui::themes::BorderSet;
+
+#[derive(Debug, Clone)]
+enum FieldKind {
+    Text(String),
+    Select { options: Vec<String>, selected: usize },
+    Checkbox(bool),
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    // Key of its value in the submitted form
+    name: String,
+    label: String,
+    kind: FieldKind,
+}
+
+impl Field {
+    pub fn text(name: &str, label: &str, value: &str) -> Self {
+        Self::new(name, label, FieldKind::Text(value.to_string()))
+    }
+
+    // Starts at `selected`, or the first option
+    pub fn select(name: &str, label: &str, options: Vec<String>, selected: Option<&str>) -> Self {
+        let selected = selected.and_then(|s| options.iter().position(|o| o == s)).unwrap_or(0);
+        Self::new(name, label, FieldKind::Select { options, selected })
+    }
+
+    pub fn checkbox(name: &str, label: &str, checked: bool) -> Self {
+        Self::new(name, label, FieldKind::Checkbox(checked))
+    }
+
+    fn new(name: &str, label: &str, kind: FieldKind) -> Self {
+        Self { name: name.to_string(), label: label.to_string(), kind }
+    }
+
+    // Checkboxes as "true" or "false"
+    fn value(&self) -> String {
+        match &self.kind {
+            FieldKind::Text(value) => value.clone(),
+            FieldKind::Select { options, selected } => options.get(*selected).cloned().unwrap_or_default(),
+            FieldKind::Checkbox(checked) => checked.to_string(),
+        }
+    }
+
+    fn control(&self, focused: bool) -> String {
+        match &self.kind {
+            FieldKind::Text(value) => format!("[{}{}]", value, if focused { "▏" } else { "" }),
+            FieldKind::Select { options, selected } => {
+                format!("‹ {} ›", options.get(*selected).map(String::as_str).unwrap_or(""))
+            }
+            FieldKind::Checkbox(checked) => (if *checked { "[x]" } else { "[ ]" }).to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Button {
+    label: String,
+    key: Option<char>,
+    // Pressing it cancels the form
+    cancels: bool,
+}
+
+impl Button {
+    pub fn new(label: &str) -> Self {
+        Self { label: label.to_string(), key: None, cancels: false }
+    }
+
+    pub fn cancel(label: &str) -> Self {
+        Self { cancels: true, ..Self::new(label) }
+    }
+
+    pub fn key(mut self, key: char) -> Self {
+        self.key = Some(key.to_ascii_lowercase());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormOutcome {
+    // Which button, and each field's value by name
+    Submit { button: usize, values: BTreeMap<String, String> },
+    Cancel,
+}
+
+#[derive(Debug, Clone)]
+pub struct Form {
+    title: String,
+    lines: Vec<String>,
+    fields: Vec<Field>,
+    buttons: Vec<Button>,
+    // Fields first, then buttons
+    focus: usize,
+}
+
+impl Form {
+    // With OK and Cancel until other buttons are given
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            lines: Vec::new(),
+            fields: Vec::new(),
+            buttons: vec![Button::new("OK"), Button::cancel("Cancel")],
+            focus: 0,
+        }
+    }
+
+    pub fn with_lines(mut self, lines: Vec<String>) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    pub fn with_field(mut self, field: Field) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    pub fn with_buttons(mut self, buttons: Vec<Button>) -> Self {
+        self.buttons = buttons;
+        self
+    }
+
+    fn focusables(&self) -> usize {
+        self.fields.len() + self.buttons.len()
+    }
+
+    fn focused_field(&mut self) -> Option<&mut Field> {
+        self.fields.get_mut(self.focus)
+    }
+
+    // None while the form stays open
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<FormOutcome> {
+        let count = self.focusables().max(1);
+        let typing = matches!(self.fields.get(self.focus), Some(Field { kind: FieldKind::Text(_), .. }));
+        match code {
+            KeyCode::Esc => return Some(FormOutcome::Cancel),
+            KeyCode::Tab | KeyCode::Down => self.focus = (self.focus + 1) % count,
+            KeyCode::BackTab | KeyCode::Up => self.focus = (self.focus + count - 1) % count,
+            KeyCode::Enter => match self.focus.checked_sub(self.fields.len()) {
+                Some(button) => return self.press(button),
+                None if self.focus + 1 == self.fields.len() => return self.press(0),
+                None => self.focus += 1,
+            },
+            KeyCode::Char(c) if !typing => {
+                let c = c.to_ascii_lowercase();
+                if let Some(button) = self.buttons.iter().position(|b| b.key == Some(c)) {
+                    return self.press(button);
+                }
+                if c == ' ' {
+                    if let Some(Field { kind: FieldKind::Checkbox(checked), .. }) = self.focused_field() {
+                        *checked = !*checked;
+                    }
+                }
+            }
+            // Along the row of buttons
+            KeyCode::Right if self.focus >= self.fields.len() => self.focus = (self.focus + 1).min(count - 1),
+            KeyCode::Left if self.focus > self.fields.len() => self.focus -= 1,
+            code => match (self.focused_field().map(|field| &mut field.kind), code) {
+                (Some(FieldKind::Text(value)), KeyCode::Char(c)) => value.push(c),
+                (Some(FieldKind::Text(value)), KeyCode::Backspace) => {
+                    value.pop();
+                }
+                (Some(FieldKind::Select { options, selected }), KeyCode::Right) if !options.is_empty() => {
+                    *selected = (*selected + 1) % options.len();
+                }
+                (Some(FieldKind::Select { options, selected }), KeyCode::Left) if !options.is_empty() => {
+                    *selected = (*selected + options.len() - 1) % options.len();
+                }
+                _ => {}
+            },
+        }
+        None
+    }
+
+    fn press(&self, button: usize) -> Option<FormOutcome> {
+        match self.buttons.get(button) {
+            Some(b) if b.cancels => Some(FormOutcome::Cancel),
+            _ => Some(FormOutcome::Submit {
+                button,
+                values: self.fields.iter().map(|field| (field.name.clone(), field.value())).collect(),
+            }),
+        }
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let focus_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+        let mut lines: Vec<Line> = self
+            .lines
+            .iter()
+            .map(|l| Line::styled(l.as_str(), Style::default().fg(Color::White)))
+            .collect();
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+
+        let label_width = self.fields.iter().map(|field| field.label.chars().count()).max().unwrap_or(0);
+        for (i, field) in self.fields.iter().enumerate() {
+            let focused = i == self.focus;
+            let (marker, style) = if focused { ("▸", focus_style) } else { (" ", Style::default().fg(Color::White)) };
+            lines.push(Line::styled(
+                format!("{} {:<width$}  {}", marker, field.label, field.control(focused), width = label_width),
+                style,
+            ));
+        }
+        if !self.fields.is_empty() {
+            lines.push(Line::from(""));
+        }
+
+        let mut buttons = Vec::new();
+        for (i, button) in self.buttons.iter().enumerate() {
+            let focused = self.focus == self.fields.len() + i;
+            let label = match button.key {
+                Some(key) => format!("{} ({})", button.label, key),
+                None => button.label.clone(),
+            };
+            let (text, style) = if focused {
+                (format!("▸[ {} ]", label), focus_style.add_modifier(Modifier::REVERSED))
+            } else {
+                (format!(" [ {} ]", label), Style::default().fg(Color::Cyan))
+            };
+            buttons.push(Span::styled(text, style));
+            buttons.push(Span::raw("  "));
+        }
+        lines.push(Line::from(buttons));
+
+        let hint = match self.fields.get(self.focus).map(|field| &field.kind) {
+            Some(FieldKind::Select { .. }) => "←→ choose · tab next · esc cancel",
+            Some(FieldKind::Checkbox(_)) => "space toggles · tab next · esc cancel",
+            _ => "tab next · enter ok · esc cancel",
+        };
+        lines.push(Line::styled(hint, Style::default().fg(Color::DarkGray)));
+
+        let width = 70.min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+        let form = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(borders.block().title(self.title.as_str()).style(Style::default().fg(Color::Yellow)));
+        f.render_widget(Clear, rect);
+        f.render_widget(form, rect);
+    }
+}
+
+// What an action asks for before it runs, see core::actions::Input
+pub fn for_action(action: &Action) -> Form {
+    let mut form = Form::new(format!(" {} {} ", action.icon, action.title))
+        .with_buttons(vec![Button::new("Run"), Button::cancel("Cancel")]);
+    for input in &action.inputs {
+        let label = input.label.as_deref().unwrap_or(&input.name);
+        let default = input.default.as_deref();
+        form = form.with_field(match input.kind {
+            InputKind::Text => Field::text(&input.name, label, default.unwrap_or("")),
+            InputKind::Select => Field::select(&input.name, label, input.options.clone(), default),
+            InputKind::Checkbox => Field::checkbox(&input.name, label, default == Some("true")),
+        });
+    }
+    form
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyboard_fills_and_submits() {
+        let mut form = Form::new("Deploy")
+            .with_field(Field::text("tag", "Tag", "v1"))
+            .with_field(Field::select("env", "Environment", vec!["dev".into(), "prod".into()], Some("dev")))
+            .with_field(Field::checkbox("dry_run", "Dry run", false))
+            .with_buttons(vec![Button::new("Deploy").key('d'), Button::cancel("Cancel").key('n')]);
+
+        // Letters go into the text input, not to the buttons
+        for code in [KeyCode::Char('d'), KeyCode::Backspace, KeyCode::Char('.'), KeyCode::Char('1')] {
+            assert_eq!(form.handle_key(code), None);
+        }
+        form.handle_key(KeyCode::Enter);
+        form.handle_key(KeyCode::Right);
+        form.handle_key(KeyCode::Tab);
+        form.handle_key(KeyCode::Char(' '));
+        form.handle_key(KeyCode::BackTab);
+        form.handle_key(KeyCode::Right);
+        form.handle_key(KeyCode::Down);
+        let Some(FormOutcome::Submit { button, values }) = form.handle_key(KeyCode::Char('D')) else {
+            panic!("expected a submit");
+        };
+        assert_eq!(button, 0);
+        assert_eq!(values["tag"], "v1.1");
+        assert_eq!(values["env"], "dev");
+        assert_eq!(values["dry_run"], "true");
+
+        // On the buttons: Enter presses the focused one, Esc cancels anywhere
+        form.handle_key(KeyCode::Tab);
+        form.handle_key(KeyCode::Right);
+        assert_eq!(form.handle_key(KeyCode::Enter), Some(FormOutcome::Cancel));
+        assert_eq!(Form::new("Quit?").handle_key(KeyCode::Esc), Some(FormOutcome::Cancel));
+    }
+}
@@ -0,0 +1,143 @@
+// Modal hex dump of a command's binary output (see core::binary): offset, 16
+// bytes in hex and the same as ASCII per row; `s` saves it to a file
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::core::binary::{self, BinaryOutput};
+use crate::ui::themes::BorderSet;
+
+const BYTES_PER_ROW: usize = 16;
+// Rows shown at once
+const VISIBLE: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HexAction {
+    // Which output to save, see `binary save`
+    Save(usize),
+    Close,
+}
+
+pub struct HexViewer {
+    id: usize,
+    command: String,
+    bytes: Vec<u8>,
+    total: usize,
+    // First row shown
+    top: usize,
+}
+
+impl HexViewer {
+    pub fn new(output: &BinaryOutput) -> Self {
+        Self {
+            id: output.id(),
+            command: output.command().to_string(),
+            bytes: output.bytes().to_vec(),
+            total: output.total(),
+            top: 0,
+        }
+    }
+
+    fn rows(&self) -> usize {
+        self.bytes.len().div_ceil(BYTES_PER_ROW)
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<HexAction> {
+        let last = self.rows().saturating_sub(VISIBLE);
+        match code {
+            KeyCode::Up => self.top = self.top.saturating_sub(1),
+            KeyCode::Down => self.top = (self.top + 1).min(last),
+            KeyCode::PageUp => self.top = self.top.saturating_sub(VISIBLE),
+            KeyCode::PageDown => self.top = (self.top + VISIBLE).min(last),
+            KeyCode::Home => self.top = 0,
+            KeyCode::End => self.top = last,
+            KeyCode::Char('s') => return Some(HexAction::Save(self.id)),
+            KeyCode::Esc | KeyCode::Char('q') => return Some(HexAction::Close),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let width = 80.min(area.width);
+        let kept = match self.total > self.bytes.len() {
+            true => format!("first {} of {}", binary::size_label(self.bytes.len()), binary::size_label(self.total)),
+            false => binary::size_label(self.total),
+        };
+        let mut lines = vec![Line::styled(
+            format!("{} · {}", self.command, kept),
+            Style::default().fg(Color::DarkGray),
+        )];
+        for row in self.top..(self.top + VISIBLE).min(self.rows()) {
+            let start = row * BYTES_PER_ROW;
+            let end = (start + BYTES_PER_ROW).min(self.bytes.len());
+            lines.push(Line::styled(dump_row(start, &self.bytes[start..end]), Style::default().fg(Color::White)));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "[↑↓ pgup pgdn] scroll   [s] save to file   [esc] close",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let panel = Paragraph::new(lines).block(
+            borders
+                .block()
+                .title(format!(" Binary output #{} ", self.id))
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
+
+// `00000010  89 50 4e 47 0d 0a 1a 0a  00 00 00 0d 49 48 44 52  .PNG........IHDR`
+fn dump_row(offset: usize, bytes: &[u8]) -> String {
+    let mut hex = String::new();
+    for i in 0..BYTES_PER_ROW {
+        if i == BYTES_PER_ROW / 2 {
+            hex.push(' ');
+        }
+        match bytes.get(i) {
+            Some(b) => hex.push_str(&format!("{:02x} ", b)),
+            None => hex.push_str("   "),
+        }
+    }
+    let ascii: String = bytes.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+    format!("{:08x}  {} {}", offset, hex, ascii)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dumps_rows_and_scrolls() {
+        assert_eq!(
+            dump_row(16, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            "00000010  89 50 4e 47 0d 0a 1a 0a  00 00 00 0d 49 48 44 52  .PNG........IHDR"
+        );
+        assert_eq!(dump_row(0, b"hi"), format!("00000000  68 69 {} hi", " ".repeat(43)));
+
+        let buffer = std::sync::Mutex::new(Vec::new());
+        let mut output = BinaryOutput::new(1, "cat blob");
+        output.write(&[0; 1000], &buffer);
+        let mut viewer = HexViewer::new(&output);
+        viewer.handle_key(KeyCode::End);
+        assert_eq!(viewer.top, 63 - VISIBLE);
+        viewer.handle_key(KeyCode::Down);
+        assert_eq!(viewer.top, 63 - VISIBLE);
+        assert_eq!(viewer.handle_key(KeyCode::Char('s')), Some(HexAction::Save(1)));
+    }
+}
@@ -0,0 +1,407 @@
+// History search overlays: Ctrl+R reverse-incremental search (substring,
+// most recent first) and the Ctrl+F fuzzy finder (best match first), with a
+// preview of the selected command. Ctrl+O re-runs it where it ran, Ctrl+X
+// somewhere else (see core::rerun).
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::core::rerun::{Place, Rerun, Target};
+use crate::ui::themes::BorderSet;
+use crate::utils::clock;
+use crate::utils::history::HistoryItem;
+
+// Matches listed in the overlay
+const MAX_ROWS: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    Reverse,
+    Fuzzy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchAction {
+    // Put the command in the input line
+    Insert(String),
+    Rerun(Rerun),
+    // List the hosts or containers to offer with `choose`
+    Choose(Place),
+    Close,
+}
+
+// Ctrl+X: asking where to re-run the selected command
+enum Rerunning {
+    Where,
+    Pick { place: Place, choices: Vec<String>, selected: usize },
+}
+
+struct Match {
+    item: usize,
+    // Char positions of the query in the command, for highlighting
+    positions: Vec<usize>,
+}
+
+pub struct HistorySearch {
+    mode: SearchMode,
+    query: String,
+    // Newest first
+    items: Vec<HistoryItem>,
+    matches: Vec<Match>,
+    selected: usize,
+    rerunning: Option<Rerunning>,
+}
+
+impl HistorySearch {
+    pub fn new(mode: SearchMode, items: Vec<HistoryItem>, query: &str) -> Self {
+        let mut search = Self {
+            mode,
+            query: query.to_string(),
+            items,
+            matches: Vec::new(),
+            selected: 0,
+            rerunning: None,
+        };
+        search.refresh();
+        search
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<SearchAction> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if self.rerunning.is_some() {
+            return self.rerunning_key(key.code);
+        }
+        match key.code {
+            KeyCode::Esc => return Some(SearchAction::Close),
+            KeyCode::Char('o') if ctrl => return self.rerun(Target::Original),
+            KeyCode::Char('x') if ctrl && !self.matches.is_empty() => self.rerunning = Some(Rerunning::Where),
+            KeyCode::Char('g' | 'c') if ctrl => return Some(SearchAction::Close),
+            KeyCode::Enter => {
+                let found = self.matches.get(self.selected)?;
+                return Some(SearchAction::Insert(self.items[found.item].command.clone()));
+            }
+            // Ctrl+R again steps to the next older match, as in readline
+            KeyCode::Char('r') if ctrl => self.step(1),
+            KeyCode::Char('s') if ctrl => self.step(-1),
+            KeyCode::Up => self.step(1),
+            KeyCode::Down => self.step(-1),
+            KeyCode::Tab => {
+                self.mode = match self.mode {
+                    SearchMode::Reverse => SearchMode::Fuzzy,
+                    SearchMode::Fuzzy => SearchMode::Reverse,
+                };
+                self.refresh();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.refresh();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.refresh();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn rerunning_key(&mut self, code: KeyCode) -> Option<SearchAction> {
+        match self.rerunning.as_mut()? {
+            Rerunning::Where => match code {
+                KeyCode::Char('o') => return self.rerun(Target::Original),
+                KeyCode::Char('h') => return self.rerun(Target::Here),
+                KeyCode::Char('s') => return Some(SearchAction::Choose(Place::Host)),
+                KeyCode::Char('c') => return Some(SearchAction::Choose(Place::Container)),
+                KeyCode::Esc => self.rerunning = None,
+                _ => {}
+            },
+            Rerunning::Pick { place, choices, selected } => match code {
+                KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Down => *selected = (*selected + 1).min(choices.len().saturating_sub(1)),
+                KeyCode::Enter => {
+                    let choice = choices.get(*selected)?.clone();
+                    let target = match place {
+                        Place::Host => Target::Ssh(choice),
+                        Place::Container => Target::Container(choice),
+                    };
+                    return self.rerun(target);
+                }
+                KeyCode::Esc => self.rerunning = Some(Rerunning::Where),
+                _ => {}
+            },
+        }
+        None
+    }
+
+    // After SearchAction::Choose
+    pub fn choose(&mut self, place: Place, choices: Vec<String>) {
+        self.rerunning = Some(Rerunning::Pick { place, choices, selected: 0 });
+    }
+
+    fn rerun(&self, target: Target) -> Option<SearchAction> {
+        let item = &self.items[self.matches.get(self.selected)?.item];
+        Some(SearchAction::Rerun(Rerun { command: item.command.clone(), cwd: item.cwd.clone(), target }))
+    }
+
+    fn step(&mut self, delta: isize) {
+        let last = self.matches.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    fn refresh(&mut self) {
+        let query = self.query.to_lowercase();
+        self.matches = match self.mode {
+            SearchMode::Reverse => self
+                .items
+                .iter()
+                .enumerate()
+                .filter_map(|(item, entry)| {
+                    let lower = entry.command.to_lowercase();
+                    let start = lower.find(&query)?;
+                    let first = lower[..start].chars().count();
+                    let positions = (first..first + query.chars().count()).collect();
+                    Some(Match { item, positions })
+                })
+                .collect(),
+            SearchMode::Fuzzy => {
+                let mut scored: Vec<(i64, Match)> = self
+                    .items
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(item, entry)| {
+                        let (score, positions) = fuzzy_match(&query, &entry.command)?;
+                        Some((score, Match { item, positions }))
+                    })
+                    .collect();
+                // Stable, so equal scores stay most recent first
+                scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+                scored.into_iter().map(|(_, found)| found).collect()
+            }
+        };
+        self.selected = 0;
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let (title, prompt) = match self.mode {
+            SearchMode::Reverse => (" History search ", "reverse-i-search"),
+            SearchMode::Fuzzy => (" Fuzzy finder ", "fuzzy"),
+        };
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled(format!("{}: ", prompt), Style::default().fg(Color::Cyan)),
+                Span::styled(self.query.clone(), Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+                Span::styled("▏", Style::default().fg(Color::Cyan)),
+                Span::styled(
+                    format!("  {}/{}", self.matches.len(), self.items.len()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]),
+            Line::from(""),
+        ];
+
+        if let Some(Rerunning::Pick { place, choices, selected }) = &self.rerunning {
+            lines.push(Line::styled(
+                match place {
+                    Place::Host => "Re-run on host:",
+                    Place::Container => "Re-run in container:",
+                },
+                Style::default().fg(Color::Cyan),
+            ));
+            let first = selected.saturating_sub(MAX_ROWS - 1);
+            for (i, choice) in choices.iter().enumerate().skip(first).take(MAX_ROWS) {
+                let (marker, style) = match i == *selected {
+                    true => ("▶ ", Style::default().fg(Color::White).bg(Color::DarkGray)),
+                    false => ("  ", Style::default().fg(Color::Gray)),
+                };
+                lines.push(Line::styled(format!("{}{}", marker, choice), style));
+            }
+        }
+
+        // Keep the selection in view
+        let first = self.selected.saturating_sub(MAX_ROWS - 1);
+        let listed = if matches!(self.rerunning, Some(Rerunning::Pick { .. })) { 0 } else { MAX_ROWS };
+        for (i, found) in self.matches.iter().enumerate().skip(first).take(listed) {
+            let command = &self.items[found.item].command;
+            let mut base = Style::default().fg(Color::Gray);
+            if i == self.selected {
+                base = base.fg(Color::White).bg(Color::DarkGray);
+            }
+            let mut spans = vec![Span::styled(if i == self.selected { "▶ " } else { "  " }, base)];
+            spans.extend(command.chars().enumerate().map(|(n, c)| {
+                let style = if found.positions.contains(&n) {
+                    base.fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                } else {
+                    base
+                };
+                Span::styled(c.to_string(), style)
+            }));
+            lines.push(Line::from(spans));
+        }
+        if self.matches.is_empty() {
+            lines.push(Line::styled("  No matches", Style::default().fg(Color::DarkGray)));
+        }
+
+        if let Some(found) = self.matches.get(self.selected) {
+            let item = &self.items[found.item];
+            lines.push(Line::from(""));
+            lines.push(Line::styled(item.command.clone(), Style::default().fg(Color::White)));
+            lines.push(Line::styled(describe(item), Style::default().fg(Color::DarkGray)));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            match self.rerunning {
+                None => "[↑↓/Ctrl+R] browse   [tab] substring/fuzzy   [enter] insert   [^O] re-run   [^X] re-run in…   [esc] cancel",
+                Some(Rerunning::Where) => "Re-run: [o] where it ran   [h] here   [s] SSH host   [c] container   [esc] back",
+                Some(Rerunning::Pick { .. }) => "[↑↓] browse   [enter] re-run   [esc] back",
+            },
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let width = 90.min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let panel = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            borders
+                .block()
+                .title(title)
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
+
+// Subsequence match of `query` (lowercase) in `text`: the score rewards runs of
+// adjacent characters and matches at word starts, and penalizes gaps
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let mut positions: Vec<usize> = Vec::new();
+    let mut score = 0i64;
+    let mut wanted = query.chars().peekable();
+    let mut previous: Option<char> = None;
+    for (n, c) in text.chars().enumerate() {
+        let Some(&q) = wanted.peek() else {
+            break;
+        };
+        if c.to_lowercase().eq(q.to_lowercase()) {
+            score += 1;
+            match positions.last() {
+                Some(&last) if last + 1 == n => score += 5,
+                Some(&last) => score -= (n - last - 1).min(5) as i64,
+                None => {}
+            }
+            if !previous.is_some_and(|p| p.is_alphanumeric()) {
+                score += 3;
+            }
+            positions.push(n);
+            wanted.next();
+        }
+        previous = Some(c);
+    }
+    if wanted.peek().is_some() {
+        return None;
+    }
+    // Among equal matches, shorter commands are closer
+    Some((score * 100 - text.chars().count() as i64, positions))
+}
+
+// `exit 0 · 3 runs · 2h ago · ~/src/app`
+fn describe(item: &HistoryItem) -> String {
+    let mut parts = Vec::new();
+    if let Some(code) = item.exit_code {
+        parts.push(format!("exit {}", code));
+    }
+    parts.push(if item.runs == 1 { "1 run".to_string() } else { format!("{} runs", item.runs) });
+    parts.push(ago(item.timestamp));
+    if let Some(cwd) = &item.cwd {
+        let home = dirs::home_dir().unwrap_or_default();
+        match cwd.strip_prefix(&home) {
+            Ok(rest) if !home.as_os_str().is_empty() => parts.push(format!("~/{}", rest.display())),
+            _ => parts.push(cwd.display().to_string()),
+        }
+    }
+    parts.join(" · ")
+}
+
+fn ago(timestamp: u64) -> String {
+    let now = clock::unix_secs();
+    let secs = now.saturating_sub(timestamp);
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(command: &str) -> HistoryItem {
+        HistoryItem {
+            command: command.to_string(),
+            timestamp: 0,
+            cwd: None,
+            exit_code: None,
+            runs: 1,
+        }
+    }
+
+    #[test]
+    fn searches_by_substring_and_fuzzy() {
+        let items = vec![item("git status"), item("cargo test --workspace"), item("git stash pop")];
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        let mut search = HistorySearch::new(SearchMode::Reverse, items, "st");
+        assert_eq!(search.matches.len(), 3);
+        search.handle_key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+        assert_eq!(
+            search.handle_key(key(KeyCode::Enter)),
+            Some(SearchAction::Insert("cargo test --workspace".into()))
+        );
+
+        search.handle_key(key(KeyCode::Tab));
+        for c in "ws".chars() {
+            search.handle_key(key(KeyCode::Char(c)));
+        }
+        // "stws": only `cargo test --workspace` has s, t, w, s in order
+        assert_eq!(search.matches.len(), 1);
+        assert_eq!(search.matches[0].positions.len(), 4);
+        assert_eq!(search.handle_key(key(KeyCode::Esc)), Some(SearchAction::Close));
+    }
+
+    #[test]
+    fn picks_where_to_rerun() {
+        let items = vec![HistoryItem { cwd: Some("/src/app".into()), ..item("make test") }];
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let mut search = HistorySearch::new(SearchMode::Reverse, items, "");
+
+        search.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL));
+        assert_eq!(search.handle_key(key(KeyCode::Char('c'))), Some(SearchAction::Choose(Place::Container)));
+        search.choose(Place::Container, vec!["web".into(), "db".into()]);
+        search.handle_key(key(KeyCode::Down));
+        let Some(SearchAction::Rerun(rerun)) = search.handle_key(key(KeyCode::Enter)) else {
+            panic!("expected a re-run");
+        };
+        assert_eq!(rerun.target, Target::Container("db".into()));
+        assert_eq!(rerun.cwd.as_deref(), Some(std::path::Path::new("/src/app")));
+    }
+
+    #[test]
+    fn fuzzy_prefers_word_starts_and_runs() {
+        let (tight, _) = fuzzy_match("gst", "git status").unwrap();
+        let (loose, _) = fuzzy_match("gst", "debug settings").unwrap();
+        assert!(tight > loose);
+        assert!(fuzzy_match("xyz", "git status").is_none());
+    }
+}
@@ -0,0 +1,71 @@
+// Which-key popup: after the prefix of a multi-chord binding ("Ctrl+X" of
+// "Ctrl+X G"), the keys that may follow and what they run, shown over the
+// bottom-right of the output once the next key takes a moment
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+use std::time::{Duration, Instant};
+
+use crate::core::actions::KeyChord;
+use crate::ui::themes::BorderSet;
+use crate::utils::clock;
+
+// Quick typists never see it
+const DELAY: Duration = Duration::from_millis(500);
+
+pub struct KeyHints {
+    pressed: Vec<KeyChord>,
+    // Next chord and what it does
+    continuations: Vec<(String, String)>,
+    since: Instant,
+}
+
+impl KeyHints {
+    pub fn new(pressed: Vec<KeyChord>, continuations: Vec<(String, String)>) -> Self {
+        Self { pressed, continuations, since: clock::now() }
+    }
+
+    pub fn into_pressed(self) -> Vec<KeyChord> {
+        self.pressed
+    }
+
+    // `area` is the output pane
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        if clock::since(self.since) < DELAY {
+            return;
+        }
+        let key_width = self.continuations.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+        let mut lines: Vec<Line> = self
+            .continuations
+            .iter()
+            .map(|(key, what)| {
+                Line::from(vec![
+                    Span::styled(
+                        format!(" {:>width$} ", key, width = key_width),
+                        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(format!("→ {} ", what), Style::default().fg(Color::White)),
+                ])
+            })
+            .collect();
+        lines.push(Line::styled(" esc cancel", Style::default().fg(Color::DarkGray)));
+
+        let widest = lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+        let width = (widest + 2).min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(area.right() - width, area.bottom() - height, width, height);
+        let prefix: Vec<String> = self.pressed.iter().map(KeyChord::label).collect();
+        let panel = Paragraph::new(lines).block(
+            borders
+                .block()
+                .title(format!(" {} … ", prefix.join(" ")))
+                .style(Style::default().fg(Color::Cyan)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
@@ -0,0 +1,105 @@
+// Session lock screen: blanks the UI and collects a masked passphrase
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Clear, Paragraph},
+    Frame,
+};
+
+use crate::ui::themes::BorderSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockMode {
+    // Waiting for the configured passphrase
+    Unlock,
+    // No passphrase configured yet: choose one, then confirm it
+    Choose,
+    Confirm(String),
+}
+
+// What the caller should do after a key press on the lock screen
+pub enum LockInput {
+    Pending,
+    Submit(String),
+    Cancel,
+}
+
+pub struct LockScreen {
+    mode: LockMode,
+    input: String,
+    message: Option<String>,
+}
+
+impl LockScreen {
+    pub fn new(mode: LockMode) -> Self {
+        Self {
+            mode,
+            input: String::new(),
+            message: None,
+        }
+    }
+
+    pub fn mode(&self) -> &LockMode {
+        &self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: LockMode) {
+        self.mode = mode;
+        self.input.clear();
+    }
+
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+        self.input.clear();
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> LockInput {
+        match code {
+            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Enter => return LockInput::Submit(std::mem::take(&mut self.input)),
+            // Only setup can be abandoned; an actual lock must be unlocked
+            KeyCode::Esc if self.mode != LockMode::Unlock => return LockInput::Cancel,
+            _ => {}
+        }
+        LockInput::Pending
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        f.render_widget(Clear, area);
+        f.render_widget(Block::default().style(Style::default().bg(Color::Black)), area);
+
+        let prompt = match self.mode {
+            LockMode::Unlock => "🔒 Session locked — enter passphrase",
+            LockMode::Choose => "Choose a lock passphrase",
+            LockMode::Confirm(_) => "Confirm lock passphrase",
+        };
+
+        let mut lines = vec![
+            Line::styled(prompt, Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+            Line::from(""),
+            Line::styled("•".repeat(self.input.chars().count()), Style::default().fg(Color::Cyan)),
+        ];
+        if let Some(message) = &self.message {
+            lines.push(Line::from(""));
+            lines.push(Line::styled(message.as_str(), Style::default().fg(Color::Red)));
+        }
+
+        let width = 50.min(area.width);
+        let height = 8.min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let dialog = Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .block(borders.block().style(Style::default().fg(Color::DarkGray)));
+        f.render_widget(dialog, rect);
+    }
+}
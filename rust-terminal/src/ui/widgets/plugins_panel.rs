@@ -0,0 +1,106 @@
+// Modal list of discovered plugins: what each provides, whether it loaded,
+// and a toggle to enable or disable it
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::plugins::manager::{PluginInfo, PluginManager};
+use crate::ui::themes::BorderSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginsAction {
+    // Enable or disable the named plugin
+    Toggle(String, bool),
+    Close,
+}
+
+pub struct PluginsPanel {
+    plugins: Vec<PluginInfo>,
+    selected: usize,
+}
+
+impl PluginsPanel {
+    pub fn new(plugins: Vec<PluginInfo>) -> Self {
+        Self { plugins, selected: 0 }
+    }
+
+    // After a toggle, with the selection kept in place
+    pub fn update(&mut self, plugins: Vec<PluginInfo>) {
+        self.selected = self.selected.min(plugins.len().saturating_sub(1));
+        self.plugins = plugins;
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<PluginsAction> {
+        match code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < self.plugins.len() => self.selected += 1,
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                let plugin = self.plugins.get(self.selected)?;
+                return Some(PluginsAction::Toggle(plugin.name.clone(), !plugin.enabled));
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return Some(PluginsAction::Close),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let mut lines = Vec::new();
+        if self.plugins.is_empty() {
+            lines.push(Line::from("No plugins installed."));
+            lines.push(Line::styled(
+                format!("Add one as {}/<name>/plugin.toml", PluginManager::dir().display()),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        for (i, plugin) in self.plugins.iter().enumerate() {
+            let (mark, color) = match (&plugin.error, plugin.enabled) {
+                (Some(_), _) => ("✗", Color::Red),
+                (None, true) => ("●", Color::Green),
+                (None, false) => ("○", Color::DarkGray),
+            };
+            let mut name = Style::default().fg(Color::White).add_modifier(Modifier::BOLD);
+            if i == self.selected {
+                name = name.bg(Color::DarkGray);
+            }
+            lines.push(Line::from(vec![
+                Span::styled(format!("{} ", mark), Style::default().fg(color)),
+                Span::styled(plugin.name.clone(), name),
+                Span::styled(format!("  {}", plugin.description), Style::default().fg(Color::Gray)),
+            ]));
+            let detail = match &plugin.error {
+                Some(error) => error.clone(),
+                None if plugin.commands.is_empty() => plugin.source.clone(),
+                None => format!("commands: {}", plugin.commands.join(", ")),
+            };
+            lines.push(Line::styled(format!("    {}", detail), Style::default().fg(Color::DarkGray)));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "[↑↓] select   [space] enable/disable   [esc] close",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let width = 72.min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let panel = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            borders
+                .block()
+                .title(" Plugins ")
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
@@ -0,0 +1,143 @@
+// Modal problem list: review findings by file and line, filtered to a
+// minimum severity; Enter opens the selected one in the editor
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::ai::review::{Finding, Level};
+use crate::ui::themes::BorderSet;
+
+// Rows of findings shown at once
+const VISIBLE: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProblemsAction {
+    // File and line to open
+    Open(String, Option<u32>),
+    Close,
+}
+
+pub struct ProblemsPanel {
+    findings: Vec<Finding>,
+    minimum: Level,
+    // Into the filtered list
+    selected: usize,
+}
+
+impl ProblemsPanel {
+    pub fn new(findings: Vec<Finding>) -> Self {
+        Self { findings, minimum: Level::Info, selected: 0 }
+    }
+
+    fn shown(&self) -> Vec<&Finding> {
+        self.findings.iter().filter(|f| f.level >= self.minimum).collect()
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<ProblemsAction> {
+        let shown = self.shown().len();
+        match code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < shown => self.selected += 1,
+            KeyCode::Char('f') => {
+                self.minimum = self.minimum.next();
+                self.selected = 0;
+            }
+            KeyCode::Enter => {
+                let finding = self.shown().get(self.selected).copied()?;
+                return Some(ProblemsAction::Open(finding.file.clone(), finding.line));
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return Some(ProblemsAction::Close),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let shown = self.shown();
+        let width = 100.min(area.width);
+        let room = width.saturating_sub(2) as usize;
+        let mut lines = vec![Line::styled(
+            format!("{} of {} findings, {} and up", shown.len(), self.findings.len(), self.minimum.label()),
+            Style::default().fg(Color::DarkGray),
+        )];
+        if shown.is_empty() {
+            lines.push(Line::from("Nothing at this severity."));
+        }
+
+        let first = self.selected.saturating_sub(VISIBLE - 1);
+        for (i, finding) in shown.iter().enumerate().skip(first).take(VISIBLE) {
+            let (mark, color) = match finding.level {
+                Level::Error => ("✖ ", Color::Red),
+                Level::Warning => ("⚠ ", Color::Yellow),
+                Level::Info => ("ℹ ", Color::Blue),
+            };
+            let mut text = Style::default().fg(Color::White);
+            if i == self.selected {
+                text = text.bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+            }
+            let row = format!("{}  {}", finding.location(), finding.message);
+            lines.push(Line::from(vec![
+                Span::styled(mark, Style::default().fg(color)),
+                Span::styled(row.chars().take(room.saturating_sub(2)).collect::<String>(), text),
+            ]));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "[↑↓] select   [enter] open   [f] minimum severity   [esc] close",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let panel = Paragraph::new(lines).block(
+            borders
+                .block()
+                .title(" Review ")
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(file: &str, line: u32, level: Level) -> Finding {
+        Finding { file: file.to_string(), line: Some(line), level, message: String::new() }
+    }
+
+    #[test]
+    fn filters_by_minimum_severity() {
+        let mut panel = ProblemsPanel::new(vec![
+            finding("a.rs", 3, Level::Error),
+            finding("b.rs", 7, Level::Warning),
+            finding("c.rs", 1, Level::Info),
+        ]);
+        panel.handle_key(KeyCode::Down);
+        panel.handle_key(KeyCode::Down);
+        assert_eq!(panel.handle_key(KeyCode::Enter), Some(ProblemsAction::Open("c.rs".to_string(), Some(1))));
+
+        // Warnings and up, back at the top
+        panel.handle_key(KeyCode::Char('f'));
+        panel.handle_key(KeyCode::Down);
+        panel.handle_key(KeyCode::Down);
+        assert_eq!(panel.handle_key(KeyCode::Enter), Some(ProblemsAction::Open("b.rs".to_string(), Some(7))));
+
+        panel.handle_key(KeyCode::Char('f'));
+        assert_eq!(panel.shown().len(), 1);
+        panel.handle_key(KeyCode::Char('f'));
+        assert_eq!(panel.shown().len(), 3);
+    }
+}
@@ -0,0 +1,130 @@
+// Modal list of SSH keys: those loaded in the agent and the ones in ~/.ssh
+// that could be, with a masked passphrase prompt for encrypted keys
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+use std::path::PathBuf;
+
+use crate::ui::themes::BorderSet;
+use crate::utils::ssh_keys::SshKey;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SshKeysAction {
+    Add(PathBuf, Option<String>),
+    Remove(PathBuf),
+    Close,
+}
+
+pub struct SshKeysPanel {
+    keys: Vec<SshKey>,
+    selected: usize,
+    // Being typed for the selected key
+    passphrase: Option<String>,
+}
+
+impl SshKeysPanel {
+    pub fn new(keys: Vec<SshKey>) -> Self {
+        Self { keys, selected: 0, passphrase: None }
+    }
+
+    // Reloaded after adding or removing a key
+    pub fn update(&mut self, keys: Vec<SshKey>) {
+        self.selected = self.selected.min(keys.len().saturating_sub(1));
+        self.keys = keys;
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<SshKeysAction> {
+        if let Some(passphrase) = &mut self.passphrase {
+            match code {
+                KeyCode::Enter => {
+                    let passphrase = self.passphrase.take();
+                    let path = self.keys.get(self.selected)?.path.clone()?;
+                    return Some(SshKeysAction::Add(path, passphrase));
+                }
+                KeyCode::Esc => self.passphrase = None,
+                KeyCode::Backspace => {
+                    passphrase.pop();
+                }
+                KeyCode::Char(c) => passphrase.push(c),
+                _ => {}
+            }
+            return None;
+        }
+        match code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < self.keys.len() => self.selected += 1,
+            KeyCode::Char('a') | KeyCode::Enter => {
+                let key = self.keys.get(self.selected).filter(|k| !k.loaded)?;
+                let path = key.path.clone()?;
+                if key.encrypted {
+                    self.passphrase = Some(String::new());
+                    return None;
+                }
+                return Some(SshKeysAction::Add(path, None));
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                let key = self.keys.get(self.selected).filter(|k| k.loaded)?;
+                return Some(SshKeysAction::Remove(key.path.clone()?));
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return Some(SshKeysAction::Close),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let mut lines = Vec::new();
+        if self.keys.is_empty() {
+            lines.push(Line::from("No keys in the agent or ~/.ssh."));
+        }
+        for (i, key) in self.keys.iter().enumerate() {
+            let (mark, color) = if key.loaded { ("● ", Color::Green) } else { ("○ ", Color::DarkGray) };
+            let mut text = Style::default().fg(if key.loaded { Color::White } else { Color::Gray });
+            if i == self.selected {
+                text = text.bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+            }
+            let mut description = format!("{:<30} {:<8} {}", key.comment, key.kind, key.fingerprint);
+            if !key.loaded && key.encrypted {
+                description.push_str("  (passphrase)");
+            }
+            lines.push(Line::from(vec![
+                Span::styled(mark, Style::default().fg(color)),
+                Span::styled(description, text),
+            ]));
+        }
+
+        lines.push(Line::from(""));
+        match (&self.passphrase, self.keys.get(self.selected)) {
+            (Some(passphrase), Some(key)) => lines.push(Line::styled(
+                format!("Passphrase for {}: {}▏", key.comment, "•".repeat(passphrase.chars().count())),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            )),
+            _ => lines.push(Line::styled(
+                "[↑↓] select   [a] add to agent   [d] remove from agent   [esc] close",
+                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            )),
+        }
+
+        let width = 100.min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let panel = Paragraph::new(lines).block(
+            borders
+                .block()
+                .title(" SSH keys ")
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
@@ -0,0 +1,166 @@
+// Toast overlay for transient notices, plus the notification center history
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, List, ListItem, Paragraph, Wrap},
+    Frame,
+};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::core::terminal::{Notice, Severity};
+use crate::ui::themes::BorderSet;
+use crate::utils::clock;
+
+const MAX_VISIBLE: usize = 3;
+const MAX_HISTORY: usize = 100;
+const TOAST_WIDTH: u16 = 48;
+
+impl Severity {
+    fn timeout(&self) -> Duration {
+        match self {
+            Severity::Info | Severity::Success => Duration::from_secs(3),
+            Severity::Warning => Duration::from_secs(5),
+            Severity::Error => Duration::from_secs(8),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Severity::Info => Color::Cyan,
+            Severity::Success => Color::Green,
+            Severity::Warning => Color::Yellow,
+            Severity::Error => Color::Red,
+        }
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Severity::Info => "ℹ",
+            Severity::Success => "✔",
+            Severity::Warning => "⚠",
+            Severity::Error => "✖",
+        }
+    }
+}
+
+struct Toast {
+    notice: Notice,
+    shown_at: Instant,
+}
+
+pub struct ToastManager {
+    active: VecDeque<Toast>,
+    history: VecDeque<Toast>,
+    show_center: bool,
+}
+
+impl ToastManager {
+    pub fn new() -> Self {
+        Self {
+            active: VecDeque::new(),
+            history: VecDeque::new(),
+            show_center: false,
+        }
+    }
+
+    pub fn push(&mut self, notice: Notice) {
+        let shown_at = clock::now();
+        self.active.push_back(Toast { notice: notice.clone(), shown_at });
+        self.history.push_back(Toast { notice, shown_at });
+        if self.history.len() > MAX_HISTORY {
+            self.history.pop_front();
+        }
+    }
+
+    // Drops toasts whose severity timeout has elapsed
+    pub fn expire(&mut self) {
+        let now = clock::now();
+        self.active
+            .retain(|t| now.duration_since(t.shown_at) < t.notice.severity.timeout());
+    }
+
+    // Something is on screen that changes or goes away by itself
+    pub fn is_showing(&self) -> bool {
+        !self.active.is_empty()
+    }
+
+    pub fn dismiss_all(&mut self) {
+        self.active.clear();
+    }
+
+    pub fn toggle_center(&mut self) {
+        self.show_center = !self.show_center;
+    }
+
+    pub fn is_center_open(&self) -> bool {
+        self.show_center
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        if self.show_center {
+            self.render_center(f, area, borders);
+            return;
+        }
+
+        let width = TOAST_WIDTH.min(area.width);
+        let mut y = area.y + 1;
+        for toast in self.active.iter().rev().take(MAX_VISIBLE) {
+            let height = 3;
+            if y + height > area.bottom() {
+                break;
+            }
+            let rect = Rect::new(area.right().saturating_sub(width + 1), y, width, height);
+            let color = toast.notice.severity.color();
+            let paragraph = Paragraph::new(Line::from(vec![
+                Span::styled(
+                    format!("{} ", toast.notice.severity.icon()),
+                    Style::default().fg(color).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(toast.notice.message.as_str()),
+            ]))
+            .wrap(Wrap { trim: true })
+            .block(borders.block().style(Style::default().fg(color)));
+
+            f.render_widget(Clear, rect);
+            f.render_widget(paragraph, rect);
+            y += height;
+        }
+    }
+
+    fn render_center(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let rect = Rect::new(
+            area.x + area.width / 6,
+            area.y + area.height / 6,
+            area.width * 2 / 3,
+            area.height * 2 / 3,
+        );
+        let now = clock::now();
+        let items: Vec<ListItem> = self.history
+            .iter()
+            .rev()
+            .map(|t| {
+                let color = t.notice.severity.color();
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:>5}s ago ", now.duration_since(t.shown_at).as_secs()),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(format!("{} ", t.notice.severity.icon()), Style::default().fg(color)),
+                    Span::raw(t.notice.message.as_str()),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items).block(
+            borders
+                .block()
+                .title("Notifications (Ctrl+N to close)")
+                .style(Style::default().fg(Color::White)),
+        );
+
+        f.render_widget(Clear, rect);
+        f.render_widget(list, rect);
+    }
+}
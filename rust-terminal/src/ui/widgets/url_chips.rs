@@ -0,0 +1,143 @@
+// "Open http://localhost:3000" chips along the bottom of the output pane for
+// local URLs printed by dev servers; Alt+O selects them from the keyboard
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Alignment,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::block::{Position, Title},
+};
+use regex::Regex;
+
+use crate::ui::themes::Palette;
+
+const MAX_CHIPS: usize = 3;
+
+pub struct UrlChips {
+    pattern: Regex,
+    // Newest first
+    urls: Vec<String>,
+    // Output lines already scanned
+    seen: usize,
+    focused: bool,
+    selected: usize,
+}
+
+impl UrlChips {
+    pub fn new() -> Self {
+        Self {
+            pattern: Regex::new(r#"https?://(?:localhost|127\.0\.0\.1|0\.0\.0\.0|\[::1?\])(?::\d+)?(?:/[^\s"'<>)\]]*)?"#)
+                .expect("valid local URL pattern"),
+            urls: Vec::new(),
+            seen: 0,
+            focused: false,
+            selected: 0,
+        }
+    }
+
+    // Picks up local URLs from output lines added since the last call
+    pub fn scan(&mut self, output: &[String]) {
+        // Cleared in the meantime
+        self.seen = self.seen.min(output.len());
+        for line in &output[self.seen..] {
+            for found in self.pattern.find_iter(line) {
+                let url = found
+                    .as_str()
+                    .trim_end_matches(['.', ',', ';', ':'])
+                    // Servers bound to every interface print 0.0.0.0, which
+                    // browsers don't all accept
+                    .replacen("://0.0.0.0", "://localhost", 1)
+                    .replacen("://[::]", "://localhost", 1);
+                self.urls.retain(|u| *u != url);
+                self.urls.insert(0, url);
+            }
+        }
+        self.urls.truncate(MAX_CHIPS);
+        self.seen = output.len();
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    // Alt+O: moves the keyboard to the chips and back
+    pub fn toggle_focus(&mut self) {
+        self.focused = !self.focused && !self.urls.is_empty();
+        self.selected = 0;
+    }
+
+    // The URL to open, once one is chosen
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<String> {
+        match code {
+            KeyCode::Left => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Right if self.selected + 1 < self.urls.len() => self.selected += 1,
+            KeyCode::Enter => {
+                self.focused = false;
+                return self.urls.get(self.selected).cloned();
+            }
+            // Dismissed until the URL is printed again
+            KeyCode::Delete | KeyCode::Backspace | KeyCode::Char('x') => {
+                if self.selected < self.urls.len() {
+                    self.urls.remove(self.selected);
+                }
+                self.selected = self.selected.min(self.urls.len().saturating_sub(1));
+                self.focused = !self.urls.is_empty();
+            }
+            KeyCode::Esc => self.focused = false,
+            _ => {}
+        }
+        None
+    }
+
+    // The chips as a title on the bottom edge of the output pane
+    pub fn title(&self, palette: &Palette) -> Option<Title<'static>> {
+        if self.urls.is_empty() {
+            return None;
+        }
+        let mut spans = Vec::new();
+        for (i, url) in self.urls.iter().enumerate() {
+            let mut style = Style::default().fg(palette.info);
+            if self.focused && i == self.selected {
+                style = style.fg(palette.background).bg(palette.highlight).add_modifier(Modifier::BOLD);
+            }
+            spans.push(Span::styled(format!(" ↗ Open {} ", url), style));
+            spans.push(Span::raw(" "));
+        }
+        let hint = if self.focused { "←→ enter open · x dismiss · esc " } else { "Alt+O " };
+        spans.push(Span::styled(hint, Style::default().fg(palette.muted)));
+        Some(
+            Title::from(Line::from(spans))
+                .position(Position::Bottom)
+                .alignment(Alignment::Left),
+        )
+    }
+}
+
+impl Default for UrlChips {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_local_urls_newest_first() {
+        let mut chips = UrlChips::new();
+        let mut output = vec![
+            "  ➜  Local:   http://localhost:5173/".to_string(),
+            "see https://example.com/docs".to_string(),
+        ];
+        chips.scan(&output);
+        output.push("Listening on http://0.0.0.0:8000.".to_string());
+        output.push("again http://localhost:5173/".to_string());
+        chips.scan(&output);
+        assert_eq!(chips.urls, vec!["http://localhost:5173/", "http://localhost:8000"]);
+
+        chips.toggle_focus();
+        chips.handle_key(KeyCode::Char('x'));
+        assert_eq!(chips.handle_key(KeyCode::Enter).as_deref(), Some("http://localhost:8000"));
+    }
+}
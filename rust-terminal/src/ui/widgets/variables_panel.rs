@@ -0,0 +1,98 @@
+// Modal list of the session's variables, see core::variables
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+use std::collections::BTreeMap;
+
+use crate::ui::themes::BorderSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariablesAction {
+    // Put `{{name}}` into the input line
+    Insert(String),
+    Unset(String),
+    Close,
+}
+
+pub struct VariablesPanel {
+    variables: Vec<(String, String)>,
+    selected: usize,
+}
+
+impl VariablesPanel {
+    pub fn new(variables: &BTreeMap<String, String>) -> Self {
+        let mut panel = Self { variables: Vec::new(), selected: 0 };
+        panel.update(variables);
+        panel
+    }
+
+    pub fn update(&mut self, variables: &BTreeMap<String, String>) {
+        self.variables = variables.iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+        self.selected = self.selected.min(self.variables.len().saturating_sub(1));
+    }
+
+    fn selected_name(&self) -> Option<String> {
+        self.variables.get(self.selected).map(|(name, _)| name.clone())
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<VariablesAction> {
+        match code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < self.variables.len() => self.selected += 1,
+            KeyCode::Enter => return Some(VariablesAction::Insert(self.selected_name()?)),
+            KeyCode::Char('d') | KeyCode::Delete => return Some(VariablesAction::Unset(self.selected_name()?)),
+            KeyCode::Esc | KeyCode::Char('q') => return Some(VariablesAction::Close),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let mut lines = Vec::new();
+        if self.variables.is_empty() {
+            lines.push(Line::from("No variables in this session."));
+            lines.push(Line::styled(
+                "Set one with `set name=value`, use it as {{name}}",
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        let name_width = self.variables.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+        for (i, (name, value)) in self.variables.iter().enumerate() {
+            let mut text = Style::default().fg(Color::White);
+            if i == self.selected {
+                text = text.bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+            }
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:<width$}", name, width = name_width), text.fg(Color::Cyan)),
+                Span::styled(format!("  {}", value), text),
+            ]));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "[↑↓] select   [enter] insert   [d] unset   [esc] close",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let width = 80.min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let panel = Paragraph::new(lines).block(
+            borders
+                .block()
+                .title(" Session Variables ")
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
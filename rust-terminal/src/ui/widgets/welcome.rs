@@ -0,0 +1,124 @@
+// Startup splash view: auto-wire status, recent sessions and key tips
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::ui::themes::BorderSet;
+use crate::utils::sessions::SessionEntry;
+
+const TIPS: &[(&str, &str)] = &[
+    ("Ctrl+T", "New tab"),
+    ("Ctrl+W", "Close tab"),
+    ("Ctrl+R", "Search history"),
+    ("Ctrl+F", "Fuzzy finder"),
+    ("Tab", "Complete command / flag"),
+    ("Ctrl+Space", "AI suggestions"),
+    ("Alt+I", "Show / hide AI annotations"),
+    ("Ctrl+A", "Auto-wire panel"),
+    ("Ctrl+N", "Notifications"),
+    ("Ctrl+= / Ctrl+-", "Zoom"),
+    ("Ctrl+]", "Type into running command"),
+    ("Ctrl+C", "Exit"),
+];
+
+const AI_COMMANDS: &[(&str, &str)] = &[
+    ("ai <command>", "Process command through AI"),
+    ("autowire list", "List auto-wire services"),
+    ("autowire status", "Show auto-wire status"),
+];
+
+pub struct WelcomeView {
+    autowire_status: String,
+    recent_sessions: Vec<SessionEntry>,
+}
+
+impl WelcomeView {
+    pub fn new(autowire_status: &str, recent_sessions: Vec<SessionEntry>) -> Self {
+        Self {
+            autowire_status: autowire_status.to_string(),
+            recent_sessions,
+        }
+    }
+
+    // Once the background check at startup has an answer
+    pub fn set_autowire_status(&mut self, autowire_status: &str) {
+        self.autowire_status = autowire_status.to_string();
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let width = 64.min(area.width);
+        let height = 24.min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let block = borders
+            .block()
+            .title(" 🚀 NEXTERM ")
+            .title_alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Cyan));
+        let inner = block.inner(rect);
+        f.render_widget(Clear, rect);
+        f.render_widget(block, rect);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(4),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+
+        let header = Paragraph::new(vec![
+            Line::from(Span::styled(
+                "Revolutionary Terminal Experience with AI Auto-Wiring",
+                Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+            )),
+            Line::from(""),
+            Line::from(vec![
+                Span::raw("Auto-Wiring: "),
+                Span::styled(self.autowire_status.as_str(), Style::default().fg(Color::Green)),
+            ]),
+        ])
+        .alignment(Alignment::Center);
+        f.render_widget(header, chunks[0]);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[1]);
+
+        let mut tips = vec![Line::from(Span::styled("Quick Start", Style::default().add_modifier(Modifier::BOLD)))];
+        tips.extend(TIPS.iter().chain(AI_COMMANDS).map(|(key, desc)| {
+            Line::from(vec![
+                Span::styled(format!(" {:<16}", key), Style::default().fg(Color::Yellow)),
+                Span::styled(*desc, Style::default().fg(Color::Gray)),
+            ])
+        }));
+        f.render_widget(Paragraph::new(tips), columns[0]);
+
+        let mut recent = vec![Line::from(Span::styled("Recent Sessions", Style::default().add_modifier(Modifier::BOLD)))];
+        if self.recent_sessions.is_empty() {
+            recent.push(Line::from(Span::styled(" (none yet)", Style::default().fg(Color::DarkGray))));
+        }
+        recent.extend(self.recent_sessions.iter().map(|s| {
+            Line::from(Span::styled(format!(" {}", s.directory), Style::default().fg(Color::Gray)))
+        }));
+        f.render_widget(Paragraph::new(recent), columns[1]);
+
+        f.render_widget(
+            Paragraph::new("Press any key to continue")
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(Color::DarkGray)),
+            chunks[2],
+        );
+    }
+}
@@ -0,0 +1,104 @@
+// Modal dependency graph of a workflow (`workflow graph <name>`): its steps
+// in order, what each reads and writes, which earlier steps it waits on and
+// whether it would run now or be skipped as up to date
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::core::workflow_deps::{Freshness, Node};
+use crate::ui::themes::BorderSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkflowGraphAction {
+    // Run the workflow, skipping what's up to date
+    Run(String),
+    Close,
+}
+
+pub struct WorkflowGraph {
+    workflow: String,
+    nodes: Vec<Node>,
+    // First step shown
+    offset: usize,
+}
+
+impl WorkflowGraph {
+    pub fn new(workflow: &str, nodes: Vec<Node>) -> Self {
+        Self { workflow: workflow.to_string(), nodes, offset: 0 }
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<WorkflowGraphAction> {
+        match code {
+            KeyCode::Up => self.offset = self.offset.saturating_sub(1),
+            KeyCode::Down if self.offset + 1 < self.nodes.len() => self.offset += 1,
+            KeyCode::Char('r') => return Some(WorkflowGraphAction::Run(self.workflow.clone())),
+            KeyCode::Esc | KeyCode::Char('q') => return Some(WorkflowGraphAction::Close),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let dim = Style::default().fg(Color::DarkGray);
+        let width = 100.min(area.width);
+        let room = width.saturating_sub(2) as usize;
+        let clip = |text: String| text.chars().take(room).collect::<String>();
+
+        let mut lines = Vec::new();
+        for (i, node) in self.nodes.iter().enumerate().skip(self.offset) {
+            let (mark, state, color) = match &node.freshness {
+                Freshness::Always => ("●", "runs every time".to_string(), Color::Cyan),
+                Freshness::UpToDate => ("✔", "up to date, skipped".to_string(), Color::Green),
+                Freshness::Stale(why) => ("●", format!("runs: {}", why), Color::Yellow),
+            };
+            if i > self.offset {
+                lines.push(Line::styled("│", dim));
+            }
+            lines.push(Line::from(vec![
+                Span::styled(format!("{} ", mark), Style::default().fg(color)),
+                Span::styled(node.label.clone(), Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+                Span::styled(format!("  {}", state), Style::default().fg(color)),
+            ]));
+            lines.push(Line::styled(clip(format!("│  $ {}", node.run)), dim));
+            if !node.depends_on.is_empty() {
+                let after: Vec<&str> = node.depends_on.iter().map(|&d| self.nodes[d].label.as_str()).collect();
+                let after = clip(format!("│  ◀ after {}", after.join(", ")));
+                lines.push(Line::styled(after, Style::default().fg(Color::Magenta)));
+            }
+            if !node.inputs.is_empty() {
+                lines.push(Line::styled(clip(format!("│  in:  {}", node.inputs.join(", "))), dim));
+            }
+            if !node.outputs.is_empty() {
+                lines.push(Line::styled(clip(format!("│  out: {}", node.outputs.join(", "))), dim));
+            }
+        }
+        // Room for the hint below
+        lines.truncate(area.height.saturating_sub(4) as usize);
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "[↑↓] scroll   [r] run   [esc] close",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let panel = Paragraph::new(lines).block(
+            borders
+                .block()
+                .title(format!(" Workflow {} ", self.workflow))
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
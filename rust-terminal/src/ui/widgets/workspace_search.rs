@@ -0,0 +1,184 @@
+// Modal workspace search (Ctrl+Shift+F): a query line over scrollback,
+// capture buffers and recent session logs, results grouped by where they
+// were found, with the lines around the selected match and Enter to go there
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::core::workspace_search::{Corpus, Group, Target};
+use crate::ui::themes::BorderSet;
+
+// Rows of results shown at once, headers included
+const VISIBLE: usize = 18;
+// Lines of context either side of the selected match
+const PREVIEW_RADIUS: usize = 2;
+
+pub enum WorkspaceSearchAction {
+    Jump(Target),
+    Close,
+}
+
+enum Row {
+    Header(usize),
+    // Group and hit within it
+    Hit(usize, usize),
+}
+
+pub struct WorkspaceSearch {
+    corpus: Corpus,
+    query: String,
+    groups: Vec<Group>,
+    // Into the hits, counted across groups
+    selected: usize,
+}
+
+impl WorkspaceSearch {
+    pub fn new(corpus: Corpus, query: &str) -> Self {
+        let mut search = Self { corpus, query: query.to_string(), groups: Vec::new(), selected: 0 };
+        search.refresh();
+        search
+    }
+
+    fn refresh(&mut self) {
+        self.groups = self.corpus.search(&self.query);
+        self.selected = 0;
+    }
+
+    fn hit_count(&self) -> usize {
+        self.groups.iter().map(|g| g.hits.len()).sum()
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (g, group) in self.groups.iter().enumerate() {
+            rows.push(Row::Header(g));
+            rows.extend((0..group.hits.len()).map(|h| Row::Hit(g, h)));
+        }
+        rows
+    }
+
+    // The selected match as (group, hit)
+    fn current(&self) -> Option<(usize, usize)> {
+        self.rows()
+            .into_iter()
+            .filter_map(|row| match row {
+                Row::Hit(g, h) => Some((g, h)),
+                Row::Header(_) => None,
+            })
+            .nth(self.selected)
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<WorkspaceSearchAction> {
+        match code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < self.hit_count() => self.selected += 1,
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.refresh();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.refresh();
+            }
+            KeyCode::Enter => {
+                let (g, h) = self.current()?;
+                let group = &self.groups[g];
+                return self.corpus.target(&group.source, group.hits[h].line).map(WorkspaceSearchAction::Jump);
+            }
+            KeyCode::Esc => return Some(WorkspaceSearchAction::Close),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect, borders: BorderSet) {
+        let dim = Style::default().fg(Color::DarkGray);
+        let width = 110.min(area.width);
+        let room = width.saturating_sub(2) as usize;
+        let clip = |text: &str, room: usize| text.chars().take(room).collect::<String>();
+
+        let mut lines = vec![Line::from(vec![
+            Span::styled("search: ", Style::default().fg(Color::Cyan)),
+            Span::styled(format!("{}█", self.query), Style::default().fg(Color::White)),
+        ])];
+        let hits = self.hit_count();
+        lines.push(Line::styled(
+            match (self.query.is_empty(), hits) {
+                (true, _) => "Type to search scrollback, buffers and recent session logs".to_string(),
+                (false, 0) => "No matches".to_string(),
+                (false, n) => format!("{} matches in {} places", n, self.groups.len()),
+            },
+            dim,
+        ));
+
+        // Scrolled to keep the selected match in view
+        let rows = self.rows();
+        let current = self.current();
+        let at = rows
+            .iter()
+            .position(|row| matches!(row, Row::Hit(g, h) if Some((*g, *h)) == current))
+            .unwrap_or(0);
+        for row in rows.iter().skip(at.saturating_sub(VISIBLE - 1)).take(VISIBLE) {
+            match *row {
+                Row::Header(g) => {
+                    let group = &self.groups[g];
+                    lines.push(Line::from(vec![
+                        Span::styled(
+                            clip(&group.source.title(), room.saturating_sub(12)),
+                            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(format!("  {}", group.hits.len()), dim),
+                    ]));
+                }
+                Row::Hit(g, h) => {
+                    let hit = &self.groups[g].hits[h];
+                    let mut text = Style::default().fg(Color::White);
+                    if Some((g, h)) == current {
+                        text = text.bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+                    }
+                    let number = format!("  {:>6} ", hit.line + 1);
+                    lines.push(Line::from(vec![
+                        Span::styled(number.clone(), dim),
+                        Span::styled(clip(hit.text.trim_end(), room.saturating_sub(number.len())), text),
+                    ]));
+                }
+            }
+        }
+
+        if let Some((g, h)) = current {
+            let group = &self.groups[g];
+            let line = group.hits[h].line;
+            lines.push(Line::styled("─".repeat(room), dim));
+            for (n, text) in self.corpus.around(&group.source, line, PREVIEW_RADIUS) {
+                let style = if n == line { Style::default().fg(Color::White) } else { dim };
+                lines.push(Line::styled(clip(&format!("{:>6}  {}", n + 1, text), room), style));
+            }
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "[type] search   [↑↓] select   [enter] go there   [esc] close",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let rect = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let panel = Paragraph::new(lines).block(
+            borders
+                .block()
+                .title(" Workspace search ")
+                .style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, rect);
+        f.render_widget(panel, rect);
+    }
+}
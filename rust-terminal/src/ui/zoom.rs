@@ -0,0 +1,80 @@
+// Zoom: the host terminal's font size where it can be driven from inside,
+// NexTerm's own layout density (compact mode) everywhere else
+use base64::Engine;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoomStep {
+    In,
+    Out,
+    Reset,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoomHost {
+    // Remote control over the tty; needs `allow_remote_control` in kitty.conf
+    Kitty,
+    // Sets the NEXTERM_ZOOM user var to in/out/reset, for a wezterm.lua handler:
+    //   wezterm.on('user-var-changed', function(window, pane, name, value)
+    //     if name == 'NEXTERM_ZOOM' then
+    //       local actions = { ['in'] = 'IncreaseFontSize', out = 'DecreaseFontSize', reset = 'ResetFontSize' }
+    //       window:perform_action(wezterm.action[actions[value]], pane)
+    //     end
+    //   end)
+    WezTerm,
+    // OSC 50 relative font sizes
+    Xterm,
+    Unsupported,
+}
+
+impl ZoomHost {
+    // From the variables each host sets in its child environment
+    pub fn detect() -> Self {
+        let set = |name: &str| std::env::var_os(name).is_some();
+        if set("KITTY_WINDOW_ID") {
+            ZoomHost::Kitty
+        } else if set("WEZTERM_PANE") {
+            ZoomHost::WezTerm
+        } else if set("XTERM_VERSION") {
+            ZoomHost::Xterm
+        } else {
+            ZoomHost::Unsupported
+        }
+    }
+
+    // Escape sequence asking the host to change its font size
+    pub fn sequence(self, step: ZoomStep) -> Option<String> {
+        match self {
+            ZoomHost::Kitty => {
+                let payload = match step {
+                    ZoomStep::In => r#"{"size":1,"increment_op":"+"}"#,
+                    ZoomStep::Out => r#"{"size":1,"increment_op":"-"}"#,
+                    // Size 0 is kitty's configured default
+                    ZoomStep::Reset => r#"{"size":0}"#,
+                };
+                Some(format!(
+                    "\x1bP@kitty-cmd{{\"cmd\":\"set-font-size\",\"version\":[0,26,0],\"no_response\":true,\"payload\":{}}}\x1b\\",
+                    payload
+                ))
+            }
+            ZoomHost::WezTerm => {
+                let value = match step {
+                    ZoomStep::In => "in",
+                    ZoomStep::Out => "out",
+                    ZoomStep::Reset => "reset",
+                };
+                let encoded = base64::engine::general_purpose::STANDARD.encode(value);
+                Some(format!("\x1b]1337;SetUserVar=NEXTERM_ZOOM={}\x07", encoded))
+            }
+            ZoomHost::Xterm => {
+                // A bare `#` goes back to the default font
+                let font = match step {
+                    ZoomStep::In => "#+1",
+                    ZoomStep::Out => "#-1",
+                    ZoomStep::Reset => "#",
+                };
+                Some(format!("\x1b]50;{}\x07", font))
+            }
+            ZoomHost::Unsupported => None,
+        }
+    }
+}
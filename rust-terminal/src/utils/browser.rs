@@ -0,0 +1,17 @@
+// Opening URLs in the desktop's default browser
+use anyhow::{Context, Result};
+use std::process::{Command, Stdio};
+
+pub fn open(url: &str) -> Result<()> {
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    let mut child = Command::new(opener)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", opener))?;
+    // Reaped in the background; some openers stay until the browser exits
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,113 @@
+// Named capture buffers, tmux style: pane contents or command output kept
+// under a name for the session, to paste or write out later without going
+// through the system clipboard
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+// Older buffers are dropped past this many
+const MAX_BUFFERS: usize = 50;
+const PREVIEW_CHARS: usize = 50;
+
+pub struct Buffer {
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Default)]
+pub struct CaptureBuffers {
+    // Most recent first
+    buffers: Vec<Buffer>,
+}
+
+impl CaptureBuffers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Stores `content` under `name`, replacing a buffer of that name, or under
+    // the next free `bufferN`; returns the name used
+    pub fn save(&mut self, name: Option<&str>, content: String) -> String {
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => (0..)
+                .map(|n| format!("buffer{}", n))
+                .find(|candidate| self.get(Some(candidate)).is_none())
+                .unwrap_or_default(),
+        };
+        self.buffers.retain(|b| b.name != name);
+        self.buffers.insert(0, Buffer { name: name.clone(), content });
+        self.buffers.truncate(MAX_BUFFERS);
+        name
+    }
+
+    // The named buffer, or the most recent one
+    pub fn get(&self, name: Option<&str>) -> Option<&Buffer> {
+        match name {
+            Some(name) => self.buffers.iter().find(|b| b.name == name),
+            None => self.buffers.first(),
+        }
+    }
+
+    // Most recent first
+    pub fn iter(&self) -> impl Iterator<Item = &Buffer> {
+        self.buffers.iter()
+    }
+
+    pub fn delete(&mut self, name: &str) -> bool {
+        let before = self.buffers.len();
+        self.buffers.retain(|b| b.name != name);
+        self.buffers.len() != before
+    }
+
+    // Writes a buffer to `path`, returning the bytes written
+    pub fn write(&self, name: &str, path: &Path) -> Result<usize> {
+        let buffer = self.get(Some(name)).with_context(|| format!("No buffer named {}", name))?;
+        let mut content = buffer.content.clone();
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        fs::write(path, &content).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(content.len())
+    }
+
+    // `name  lines  bytes: first line`, most recent first
+    pub fn list(&self) -> Vec<String> {
+        self.buffers
+            .iter()
+            .map(|b| {
+                let first = b.content.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+                let mut preview: String = first.trim().chars().take(PREVIEW_CHARS).collect();
+                if first.trim().chars().count() > PREVIEW_CHARS {
+                    preview.push('…');
+                }
+                format!(
+                    "{:<16} {:>5} lines {:>8} bytes: {}",
+                    b.name,
+                    b.content.lines().count(),
+                    b.content.len(),
+                    preview
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_replaces_buffers() {
+        let mut buffers = CaptureBuffers::new();
+        assert_eq!(buffers.save(None, "a".into()), "buffer0");
+        assert_eq!(buffers.save(Some("build-log"), "b".into()), "build-log");
+        assert_eq!(buffers.save(None, "c".into()), "buffer1");
+        buffers.save(Some("build-log"), "d".into());
+
+        assert_eq!(buffers.get(None).map(|b| b.content.as_str()), Some("d"));
+        assert_eq!(buffers.list().len(), 3);
+        assert!(buffers.delete("buffer0"));
+        assert!(!buffers.delete("buffer0"));
+    }
+}
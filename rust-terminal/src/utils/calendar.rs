@@ -0,0 +1,179 @@
+// Activity heatmap of commands per day, from the timestamped history
+use std::collections::BTreeMap;
+
+use crate::utils::clock;
+
+// A year of weeks, one column each
+const WEEKS: i64 = 53;
+const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+const WEEKDAY_LABELS: [&str; 7] = ["Mon ", "    ", "Wed ", "    ", "Fri ", "    ", "    "];
+
+pub struct Calendar {
+    // Local day number -> commands run that day
+    counts: BTreeMap<i64, usize>,
+}
+
+impl Calendar {
+    pub fn new<'a>(history: impl Iterator<Item = (u64, &'a str)>) -> Self {
+        let mut counts = BTreeMap::new();
+        for (timestamp, _) in history {
+            *counts.entry(local_day(timestamp)).or_insert(0) += 1;
+        }
+        Self { counts }
+    }
+
+    // Weeks as columns, Monday to Sunday as rows, ending with the week of `today`
+    pub fn render(&self, today: i64) -> Vec<String> {
+        let first = today - weekday(today) - (WEEKS - 1) * 7;
+        let in_range = || self.counts.range(first..=today);
+        let max = in_range().map(|(_, n)| *n).max().unwrap_or(0);
+
+        // Month names above the first week starting in that month
+        let mut months = vec![' '; WEEKS as usize];
+        let mut free_from = 0;
+        for week in 1..WEEKS {
+            let start = first + week * 7;
+            let month = civil(start).1;
+            if month != civil(start - 7).1 && week >= free_from && week + 3 <= WEEKS {
+                for (i, c) in MONTHS[month as usize - 1].chars().enumerate() {
+                    months[week as usize + i] = c;
+                }
+                free_from = week + 4;
+            }
+        }
+        let mut lines = vec![format!("    {}", months.into_iter().collect::<String>())];
+
+        for (row, label) in WEEKDAY_LABELS.iter().enumerate() {
+            let cells: String = (0..WEEKS)
+                .map(|week| {
+                    let day = first + week * 7 + row as i64;
+                    if day > today {
+                        return ' ';
+                    }
+                    let count = self.counts.get(&day).copied().unwrap_or(0);
+                    SHADES[shade(count, max)]
+                })
+                .collect();
+            lines.push(format!("{}{}", label, cells));
+        }
+
+        lines.push(format!("    Less {} More", SHADES.iter().collect::<String>()));
+        let total: usize = in_range().map(|(_, n)| n).sum();
+        let active = in_range().count();
+        match in_range().max_by_key(|(day, n)| (**n, -**day)) {
+            Some((day, n)) => lines.push(format!(
+                "    {} commands on {} days; busiest {} ({})",
+                total,
+                active,
+                format_date(*day),
+                n
+            )),
+            None => lines.push("    No commands in the last year".to_string()),
+        }
+        lines
+    }
+}
+
+// 0 for no commands, then 1..=4 by share of the busiest day
+fn shade(count: usize, max: usize) -> usize {
+    if count == 0 || max == 0 {
+        return 0;
+    }
+    (count * 4).div_ceil(max).clamp(1, 4)
+}
+
+// `history calendar <date>`: the commands of one local day, with their times
+pub fn day_listing<'a>(history: impl Iterator<Item = (u64, &'a str)>, day: i64) -> Vec<String> {
+    let mut lines: Vec<String> = history
+        .filter(|(timestamp, _)| local_day(*timestamp) == day)
+        .map(|(timestamp, command)| {
+            let tm = local_tm(timestamp);
+            format!("{:02}:{:02}  {}", tm.tm_hour, tm.tm_min, command)
+        })
+        .collect();
+    let header = format!("{} — {} commands", format_date(day), lines.len());
+    lines.insert(0, header);
+    lines
+}
+
+pub fn local_tm(timestamp: u64) -> libc::tm {
+    let time = timestamp as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&time, &mut tm) };
+    tm
+}
+
+// Days since 1970-01-01 in local time
+pub fn local_day(timestamp: u64) -> i64 {
+    let offset = local_tm(timestamp).tm_gmtoff;
+    (timestamp as i64 + offset).div_euclid(86_400)
+}
+
+pub fn today() -> i64 {
+    let now = clock::unix_secs();
+    local_day(now)
+}
+
+// Monday is 0; day 0 was a Thursday
+fn weekday(day: i64) -> i64 {
+    (day + 3).rem_euclid(7)
+}
+
+// Day number -> (year, month, day), proleptic Gregorian
+fn civil(day: i64) -> (i64, u32, u32) {
+    let z = day + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (yoe + era * 400 + i64::from(m <= 2), m, d)
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// YYYY-MM-DD; rejects dates that don't exist
+pub fn parse_date(text: &str) -> Option<i64> {
+    let mut parts = text.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    let number = days_from_civil(year, month, day);
+    (civil(number) == (year, month, day)).then_some(number)
+}
+
+pub fn format_date(day: i64) -> String {
+    let (y, m, d) = civil(day);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_dates() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2024-02-29").map(format_date).as_deref(), Some("2024-02-29"));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(weekday(parse_date("2026-10-12").unwrap()), 0);
+    }
+
+    #[test]
+    fn shades_relative_to_busiest_day() {
+        assert_eq!(shade(0, 10), 0);
+        assert_eq!(shade(1, 10), 1);
+        assert_eq!(shade(10, 10), 4);
+    }
+}
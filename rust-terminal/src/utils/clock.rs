@@ -0,0 +1,121 @@
+// Injectable time: durations, debounces, timeouts and animations ask
+// `clock::now()` rather than `Instant::now()`, so a test can swap in a
+// ManualClock for its thread and step time forward instead of sleeping.
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+    fn system_time(&self) -> SystemTime;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+thread_local! {
+    static OVERRIDE: RefCell<Option<Arc<dyn Clock>>> = const { RefCell::new(None) };
+}
+
+pub fn now() -> Instant {
+    OVERRIDE.with(|o| o.borrow().as_ref().map(|clock| clock.now())).unwrap_or_else(|| SystemClock.now())
+}
+
+pub fn system_time() -> SystemTime {
+    OVERRIDE
+        .with(|o| o.borrow().as_ref().map(|clock| clock.system_time()))
+        .unwrap_or_else(|| SystemClock.system_time())
+}
+
+// Time since `earlier`, by the current clock
+pub fn since(earlier: Instant) -> Duration {
+    now().saturating_duration_since(earlier)
+}
+
+// Seconds since the Unix epoch, for timestamps that get stored
+pub fn unix_secs() -> u64 {
+    system_time().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// Stands still until advanced
+#[cfg(test)]
+pub struct ManualClock {
+    start: Instant,
+    wall: SystemTime,
+    elapsed: std::sync::Mutex<Duration>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + *self.elapsed.lock().unwrap()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.wall + *self.elapsed.lock().unwrap()
+    }
+}
+
+// Restores the real clock on this thread when dropped
+#[cfg(test)]
+pub struct MockGuard;
+
+#[cfg(test)]
+impl Drop for MockGuard {
+    fn drop(&mut self) {
+        OVERRIDE.with(|o| o.borrow_mut().take());
+    }
+}
+
+// A ManualClock for the calling thread only, so tests running in parallel
+// keep their own time. It starts at a fixed wall-clock time.
+#[cfg(test)]
+pub fn mock() -> (Arc<ManualClock>, MockGuard) {
+    let clock = Arc::new(ManualClock {
+        start: Instant::now(),
+        wall: std::time::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        elapsed: std::sync::Mutex::new(Duration::ZERO),
+    });
+    OVERRIDE.with(|o| *o.borrow_mut() = Some(clock.clone()));
+    (clock, MockGuard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock_only_moves_when_advanced() {
+        let (clock, guard) = mock();
+        let start = now();
+        assert_eq!(since(start), Duration::ZERO);
+        assert_eq!(unix_secs(), 1_700_000_000);
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(since(start), Duration::from_millis(1500));
+        assert_eq!(unix_secs(), 1_700_000_001);
+
+        // Other threads keep the real clock
+        let real = std::thread::spawn(unix_secs).join().unwrap();
+        assert!(real > 1_700_000_001);
+
+        drop(guard);
+        assert!(unix_secs() > 1_700_000_001);
+    }
+}
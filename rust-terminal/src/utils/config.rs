@@ -1,18 +1,161 @@
 // Configuration management
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
+use crate::ai::autowire_daemon::AutoWireConfig;
+use crate::ai::community_specs::CompletionsConfig;
+use crate::core::bell::BellConfig;
+use crate::core::encoding::Encoding;
+use crate::core::hooks::HooksConfig;
+use crate::core::impact::ImpactConfig;
+use crate::core::modes::ModeConfig;
+use crate::core::policy::RestrictedConfig;
+use crate::core::preflight::ScriptCheckConfig;
+use crate::core::postprocess::PostProcessConfig;
+use crate::core::prompts::PromptConfig;
+use crate::core::retention::RetentionConfig;
+use crate::core::roaming::RoamingConfig;
+use crate::core::sinks::SinkConfig;
+use crate::core::tee::TeeConfig;
+use crate::core::triggers::TriggerConfig;
+use crate::plugins::manager::PluginsConfig;
+use crate::ui::anonymizer::DemoConfig;
+use crate::ui::compat::CompatMode;
+use crate::ui::glyphs::GlyphMode;
+use crate::ui::themes::AppearanceConfig;
+use crate::utils::durable;
+use crate::utils::error::NexTermError;
+use crate::utils::history::HistoryConfig;
+use crate::utils::storage::StorageConfig;
+use crate::utils::sync::SyncConfig;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub shell: String,
     pub editor: String,
     pub theme: String,
+    // Light/dark switching, which picks between its own two themes
+    #[serde(default)]
+    pub appearance: AppearanceConfig,
     pub ai_enabled: bool,
     pub autowire_enabled: bool,
+    // Request timeout and restart limit of the Python auto-wiring worker
+    #[serde(default)]
+    pub autowire: AutoWireConfig,
     pub font_size: u16,
+    // Nerd Font icons in the status bar: auto, nerd or ascii
+    #[serde(default)]
+    pub glyphs: GlyphMode,
+    // ASCII, 16 colors, no emoji or motion: auto, on or off, see ui::compat
+    #[serde(default)]
+    pub compat: CompatMode,
+    // Start with borderless tab and status rows
+    #[serde(default)]
+    pub compact: bool,
+    // Send Ctrl+= / Ctrl+- to kitty, WezTerm or xterm as font size changes
+    // instead of toggling compact mode
+    #[serde(default = "default_true")]
+    pub forward_zoom: bool,
+    // Right-click menus and wheel scrolling; off (the default) leaves the
+    // mouse, and text selection, to the terminal emulator
+    #[serde(default)]
+    pub mouse: bool,
     pub show_status_bar: bool,
+    // Spinners, transitions and other motion; off for reduced-motion setups
+    #[serde(default = "default_true")]
+    pub animations: bool,
+    // Lock the session after this many idle seconds (needs a passphrase)
+    #[serde(default)]
+    pub lock_timeout_secs: Option<u64>,
+    // Stop the auto-wiring worker and background watchers after this many
+    // idle minutes, until the next key press; 0 never does
+    #[serde(default = "default_idle_suspend_mins")]
+    pub idle_suspend_mins: u64,
+    // Set through the `lock` command, see utils::lock
+    #[serde(default)]
+    pub lock_passphrase_hash: Option<String>,
+    // Kiosk/teaching mode; only editable in the config file
+    #[serde(default)]
+    pub restricted: RestrictedConfig,
+    // Explain each executed command below its output
+    #[serde(default)]
+    pub teaching_mode: bool,
+    // Masks users, hosts, IPs and secrets in output for screenshots/streams
+    #[serde(default)]
+    pub demo: DemoConfig,
+    // User-defined pre-flight checks, run before matching commands
+    #[serde(default)]
+    pub preflight: Vec<ScriptCheckConfig>,
+    // Backend and durability of history/session storage
+    #[serde(default)]
+    pub storage: StorageConfig,
+    // Size cap and duplicate handling of command history
+    #[serde(default)]
+    pub history: HistoryConfig,
+    // Encrypted history sync server, see utils::sync
+    #[serde(default)]
+    pub sync: SyncConfig,
+    // pre_command / post_command shell hooks, see core::hooks
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    // Run commands on a pseudo-terminal (colors, prompts, full-screen programs);
+    // off falls back to plain pipes
+    #[serde(default = "default_true")]
+    pub pty: bool,
+    // Run them in one shell per tab that keeps `cd`, exports and functions,
+    // see core::shell; off starts a shell per command
+    #[serde(default = "default_true")]
+    pub persistent_shell: bool,
+    // Of command output: utf-8, latin-1 or shift-jis, see core::encoding
+    #[serde(default)]
+    pub encoding: Encoding,
+    // mosh / reconnect-and-resume for interactive `ssh <host>`, see core::roaming
+    #[serde(default)]
+    pub roaming: RoamingConfig,
+    // Plugins under plugins/ that stay unloaded
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+    // Regexes over command output that fire an action, see core::triggers
+    #[serde(default)]
+    pub triggers: Vec<TriggerConfig>,
+    // Per-command output kept and history persistence, see core::retention
+    #[serde(default)]
+    pub retention: Vec<RetentionConfig>,
+    // Output post-processing steps per command, see core::postprocess
+    #[serde(default)]
+    pub postprocess: Vec<PostProcessConfig>,
+    // Rotation of `:tee` and pane log files, see core::tee
+    #[serde(default)]
+    pub tee: TeeConfig,
+    // Flash, sound or desktop notification for BEL and finished commands, see core::bell
+    #[serde(default)]
+    pub bell: BellConfig,
+    // Webhook, Slack and email notifications for finished commands, see core::sinks
+    #[serde(default)]
+    pub notify: Vec<SinkConfig>,
+    // Where `completions install` fetches community specs from
+    #[serde(default)]
+    pub completions: CompletionsConfig,
+    // Custom modal key layers by name, see core::modes
+    #[serde(default)]
+    pub modes: BTreeMap<String, ModeConfig>,
+    // How to find prompts in shells run as commands, see core::prompts
+    #[serde(default)]
+    pub prompts: Vec<PromptConfig>,
+    // Preview of what rm, mv, cp -r and the like would touch, see core::impact
+    #[serde(default)]
+    pub impact: ImpactConfig,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_idle_suspend_mins() -> u64 {
+    10
 }
 
 impl Default for Config {
@@ -21,10 +164,44 @@ impl Default for Config {
             shell: std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string()),
             editor: std::env::var("EDITOR").unwrap_or_else(|_| "vim".to_string()),
             theme: "dracula".to_string(),
+            appearance: AppearanceConfig::default(),
             ai_enabled: true,
             autowire_enabled: true,
+            autowire: AutoWireConfig::default(),
             font_size: 14,
+            glyphs: GlyphMode::default(),
+            compat: CompatMode::default(),
+            compact: false,
+            forward_zoom: true,
+            mouse: false,
             show_status_bar: true,
+            animations: true,
+            lock_timeout_secs: None,
+            idle_suspend_mins: default_idle_suspend_mins(),
+            lock_passphrase_hash: None,
+            restricted: RestrictedConfig::default(),
+            teaching_mode: false,
+            demo: DemoConfig::default(),
+            preflight: Vec::new(),
+            storage: StorageConfig::default(),
+            history: HistoryConfig::default(),
+            sync: SyncConfig::default(),
+            hooks: HooksConfig::default(),
+            pty: true,
+            persistent_shell: true,
+            encoding: Encoding::default(),
+            roaming: RoamingConfig::default(),
+            plugins: PluginsConfig::default(),
+            triggers: Vec::new(),
+            retention: Vec::new(),
+            postprocess: Vec::new(),
+            tee: TeeConfig::default(),
+            bell: BellConfig::default(),
+            notify: Vec::new(),
+            completions: CompletionsConfig::default(),
+            modes: BTreeMap::new(),
+            prompts: Vec::new(),
+            impact: ImpactConfig::default(),
         }
     }
 }
@@ -35,7 +212,9 @@ impl Config {
         
         if config_path.exists() {
             let content = fs::read_to_string(&config_path)?;
-            Ok(toml::from_str(&content)?)
+            let config = toml::from_str(&content)
+                .map_err(|e| NexTermError::ConfigInvalid { path: config_path.clone(), message: e.to_string() })?;
+            Ok(config)
         } else {
             let config = Self::default();
             config.save()?;
@@ -45,21 +224,18 @@ impl Config {
 
     pub fn save(&self) -> Result<()> {
         let config_path = Self::get_config_path();
-        
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        
         let content = toml::to_string_pretty(self)?;
-        fs::write(&config_path, content)?;
-        
-        Ok(())
+        durable::write_atomic(&config_path, content.as_bytes())
     }
 
-    fn get_config_path() -> PathBuf {
+    // Directory holding config.toml and the other user-editable files
+    pub fn dir() -> PathBuf {
         let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
         path.push("nexterm");
-        path.push("config.toml");
         path
     }
+
+    fn get_config_path() -> PathBuf {
+        Self::dir().join("config.toml")
+    }
 }
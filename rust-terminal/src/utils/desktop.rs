@@ -0,0 +1,30 @@
+// Desktop notifications, through notify-send on Linux and osascript on macOS
+use anyhow::{Context, Result};
+use std::process::{Command, Stdio};
+
+use crate::core::terminal::Severity;
+
+pub fn notify(title: &str, message: &str, severity: Severity) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!("display notification {} with title {}", quote(message), quote(title)));
+        command
+    } else {
+        let urgency = match severity {
+            Severity::Error => "critical",
+            _ => "normal",
+        };
+        let mut command = Command::new("notify-send");
+        command.args(["-u", urgency, "-a", "NexTerm", title, message]);
+        command
+    };
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {:?}", command.get_program()))?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,244 @@
+// `nexterm doctor`: environment checks with actionable fixes
+use anyhow::Result;
+use std::io::IsTerminal;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::ai::autowire_bridge::AutoWireBridge;
+use crate::ai::autowire_daemon::{self, AutoWireConfig};
+use crate::ai::protocol;
+use crate::ai::transport;
+use crate::ui::tui::{MIN_HEIGHT, MIN_WIDTH};
+use crate::utils::config::Config;
+use crate::utils::sync;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+pub struct Diagnosis {
+    pub check: &'static str,
+    pub status: Status,
+    pub detail: String,
+    // What the user should do about it
+    pub advice: Option<String>,
+    // Set when `doctor --fix` can repair it unattended
+    pub fix: Option<fn() -> Result<()>>,
+}
+
+impl Diagnosis {
+    fn ok(check: &'static str, detail: impl Into<String>) -> Self {
+        Self { check, status: Status::Ok, detail: detail.into(), advice: None, fix: None }
+    }
+
+    fn problem(check: &'static str, status: Status, detail: impl Into<String>, advice: impl Into<String>) -> Self {
+        Self { check, status, detail: detail.into(), advice: Some(advice.into()), fix: None }
+    }
+
+    fn fixable(mut self, fix: fn() -> Result<()>) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self.status {
+            Status::Ok => "✔",
+            Status::Warn => "⚠",
+            Status::Fail => "✖",
+        }
+    }
+}
+
+pub fn run_checks() -> Vec<Diagnosis> {
+    let (config, mut diagnoses) = match Config::load() {
+        Ok(config) => (config, vec![Diagnosis::ok("config", "config.toml loads")]),
+        Err(e) => (
+            Config::default(),
+            vec![Diagnosis::problem(
+                "config",
+                Status::Fail,
+                format!("config.toml does not load: {}", e),
+                "Fix the file by hand, or run `nexterm doctor --fix` to move it aside and start from defaults",
+            )
+            .fixable(reset_config)],
+        ),
+    };
+
+    diagnoses.push(check_shell(&config));
+    diagnoses.push(check_python_bridge(&config.autowire));
+    diagnoses.push(check_data_dir());
+    diagnoses.push(check_sync_key());
+    diagnoses.extend(check_terminal());
+    diagnoses
+}
+
+fn check_shell(config: &Config) -> Diagnosis {
+    let runs = Command::new(&config.shell)
+        .args(["-c", "true"])
+        .status()
+        .is_ok_and(|s| s.success());
+    if runs {
+        Diagnosis::ok("shell", format!("{} runs commands", config.shell))
+    } else {
+        Diagnosis::problem(
+            "shell",
+            Status::Fail,
+            format!("{} can't run `-c true`", config.shell),
+            "Set `shell` in config.toml to an installed shell, e.g. /bin/bash",
+        )
+    }
+}
+
+fn check_python_bridge(config: &AutoWireConfig) -> Diagnosis {
+    let python = autowire_daemon::interpreter(config);
+    if !Command::new(&python).arg("--version").output().is_ok_and(|o| o.status.success()) {
+        return Diagnosis::problem(
+            "python bridge",
+            Status::Warn,
+            format!("{} not found; auto-wiring and AI suggestions are off", python.display()),
+            "Install Python 3, or set `python` under [autowire] to an interpreter or virtualenv",
+        );
+    }
+    let Some(package) = autowire_daemon::package_dir(config) else {
+        return Diagnosis::problem(
+            "python bridge",
+            Status::Warn,
+            "the auto-wiring package (core/autowire.py) wasn't found",
+            "Set `package_path` under [autowire] to the directory containing core/autowire.py",
+        );
+    };
+    // The worker answers the handshake before the command, so services are known by then
+    let _ = transport::configure(config);
+    let responds = matches!(AutoWireBridge::run_autowire_script("true", None), Ok(Some(_)));
+    if responds && transport::shared().supports(protocol::SERVICES) {
+        return Diagnosis::ok("python bridge", "auto-wiring engine responds");
+    }
+    Diagnosis::problem(
+        "python bridge",
+        Status::Warn,
+        format!("{} works but the auto-wiring engine doesn't load", python.display()),
+        format!(
+            "Check that {} imports with {}; the worker's log is in the autowire panel (press l there)",
+            package.join("core").join("autowire.py").display(),
+            python.display()
+        ),
+    )
+}
+
+fn check_data_dir() -> Diagnosis {
+    let dir = data_dir();
+    let probe = dir.join(".doctor");
+    let writable = std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(&probe, b""))
+        .and_then(|_| std::fs::remove_file(&probe));
+    match writable {
+        Ok(()) => Diagnosis::ok("data dir", format!("{} is writable", dir.display())),
+        Err(e) => Diagnosis::problem(
+            "data dir",
+            Status::Fail,
+            format!("{} is not writable: {}", dir.display(), e),
+            format!("Check ownership and permissions of {}", dir.display()),
+        ),
+    }
+}
+
+// The sync key is the only secret NexTerm keeps on disk
+fn check_sync_key() -> Diagnosis {
+    let path = sync::key_path();
+    let Ok(metadata) = std::fs::metadata(&path) else {
+        return Diagnosis::ok("keyring", "no sync key (sync not set up)");
+    };
+    let mode = metadata.permissions().mode() & 0o777;
+    if mode & 0o077 == 0 {
+        Diagnosis::ok("keyring", format!("{} is private", path.display()))
+    } else {
+        Diagnosis::problem(
+            "keyring",
+            Status::Fail,
+            format!("{} is readable by others (mode {:o})", path.display(), mode),
+            format!("chmod 600 {}", path.display()),
+        )
+        .fixable(restrict_sync_key)
+    }
+}
+
+fn check_terminal() -> Vec<Diagnosis> {
+    let mut diagnoses = Vec::new();
+
+    if !std::io::stdout().is_terminal() {
+        diagnoses.push(Diagnosis::problem(
+            "terminal",
+            Status::Warn,
+            "stdout is not a terminal; capability checks skipped",
+            "Run `nexterm doctor` directly in the terminal you use NexTerm in",
+        ));
+        return diagnoses;
+    }
+
+    match std::env::var("TERM").ok().filter(|t| !t.is_empty() && t != "dumb") {
+        Some(term) => diagnoses.push(Diagnosis::ok("terminal", format!("TERM={}", term))),
+        None => diagnoses.push(Diagnosis::problem(
+            "terminal",
+            Status::Fail,
+            "TERM is unset or dumb",
+            "Run NexTerm in a terminal emulator, or export TERM=xterm-256color",
+        )),
+    }
+
+    let truecolor = std::env::var("COLORTERM").is_ok_and(|c| c == "truecolor" || c == "24bit");
+    if !truecolor {
+        diagnoses.push(Diagnosis::problem(
+            "colors",
+            Status::Warn,
+            "no truecolor support advertised; themes fall back to 256 colors",
+            "Use a truecolor terminal or export COLORTERM=truecolor if yours supports it",
+        ));
+    }
+
+    let utf8 = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+        .is_some_and(|locale| locale.to_uppercase().replace('-', "").contains("UTF8"));
+    if !utf8 {
+        diagnoses.push(Diagnosis::problem(
+            "locale",
+            Status::Warn,
+            "locale is not UTF-8; icons and box drawing may be garbled",
+            "export LANG=en_US.UTF-8 (or another UTF-8 locale)",
+        ));
+    }
+
+    if let Ok((width, height)) = crossterm::terminal::size() {
+        if width < MIN_WIDTH || height < MIN_HEIGHT {
+            diagnoses.push(Diagnosis::problem(
+                "size",
+                Status::Warn,
+                format!("terminal is {}x{}, NexTerm needs at least {}x{}", width, height, MIN_WIDTH, MIN_HEIGHT),
+                "Enlarge the window",
+            ));
+        }
+    }
+
+    diagnoses
+}
+
+fn reset_config() -> Result<()> {
+    let path = Config::dir().join("config.toml");
+    std::fs::rename(&path, path.with_extension("toml.bak"))?;
+    Config::default().save()
+}
+
+fn restrict_sync_key() -> Result<()> {
+    std::fs::set_permissions(sync::key_path(), std::fs::Permissions::from_mode(0o600))?;
+    Ok(())
+}
+
+fn data_dir() -> PathBuf {
+    let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("nexterm");
+    path
+}
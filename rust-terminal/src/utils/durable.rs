@@ -0,0 +1,168 @@
+// Crash-safe persistence: atomic JSON snapshots and an append-only JSONL journal
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+// Replaces `path` with `contents` so a crash leaves either the old or the new
+// file, never a truncated one: write a sibling temp file, fsync, rename over
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    replace(path, contents, OpenOptions::new().write(true).create(true).truncate(true))
+}
+
+// write_atomic for files holding credentials: readable by the owner only
+pub fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    use std::os::unix::fs::OpenOptionsExt;
+    replace(path, contents, OpenOptions::new().write(true).create(true).truncate(true).mode(0o600))
+}
+
+fn replace(path: &Path, contents: &[u8], options: &OpenOptions) -> Result<()> {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)?;
+
+    let tmp = path.with_extension("tmp");
+    // A leftover temp file would keep its old permissions
+    let _ = fs::remove_file(&tmp);
+    let mut file = options.open(&tmp).with_context(|| format!("Failed to create {}", tmp.display()))?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+
+    // Persist the rename itself
+    File::open(parent).and_then(|dir| dir.sync_all()).ok();
+    Ok(())
+}
+
+// Reads a JSON snapshot; a file that no longer parses is moved aside as
+// `<name>.corrupt` so the caller starts from defaults instead of failing forever
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("{} is corrupt ({}), starting fresh", path.display(), e);
+            quarantine(path);
+            None
+        }
+    }
+}
+
+fn quarantine(path: &Path) {
+    let mut corrupt = path.as_os_str().to_owned();
+    corrupt.push(".corrupt");
+    if let Err(e) = fs::rename(path, PathBuf::from(corrupt)) {
+        warn!("Failed to move aside {}: {}", path.display(), e);
+    }
+}
+
+// Append-only JSON-lines log. Entries only ever go at the end, so the worst a
+// crash can do is tear the last line; `open` detects that and truncates it.
+pub struct Journal {
+    file: File,
+    fsync_every: u32,
+    unsynced: u32,
+}
+
+impl Journal {
+    // Opens (or creates) the journal, checks its integrity and returns the
+    // entries that survived
+    pub fn open<T: DeserializeOwned>(path: &Path, fsync_every: u32) -> Result<(Self, Vec<T>)> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let entries = Self::repair(path)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let journal = Self { file, fsync_every, unsynced: 0 };
+        Ok((journal, entries))
+    }
+
+    pub fn append<T: Serialize>(&mut self, entry: &T) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+
+        self.unsynced += 1;
+        if self.fsync_every > 0 && self.unsynced >= self.fsync_every {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    pub fn sync(&mut self) -> Result<()> {
+        self.file.sync_data()?;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    // Keeps the longest prefix of valid lines; anything after the first bad
+    // line is copied to `<name>.corrupt` and cut off
+    fn repair<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+        let Ok(file) = File::open(path) else {
+            return Ok(Vec::new());
+        };
+
+        let mut entries = Vec::new();
+        let mut valid_len = 0u64;
+        let mut reader = BufReader::new(file);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line)?;
+            if read == 0 {
+                return Ok(entries);
+            }
+            let complete = line.ends_with(b"\n");
+            match serde_json::from_slice(&line).ok().filter(|_| complete) {
+                Some(entry) => {
+                    entries.push(entry);
+                    valid_len += read as u64;
+                }
+                None => break,
+            }
+        }
+
+        warn!("{} has a damaged tail, truncating to {} entries", path.display(), entries.len());
+        let mut corrupt = path.as_os_str().to_owned();
+        corrupt.push(".corrupt");
+        fs::copy(path, PathBuf::from(corrupt)).ok();
+        let file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(valid_len)?;
+        file.sync_all()?;
+        Ok(entries)
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        if self.unsynced > 0 {
+            self.file.sync_data().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn torn_tail_is_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        fs::write(&path, "\"ls\"\n\"git status\"\n\"car").unwrap();
+
+        let (mut journal, entries) = Journal::open::<String>(&path, 1).unwrap();
+        assert_eq!(entries, vec!["ls", "git status"]);
+
+        journal.append(&"cargo build").unwrap();
+        drop(journal);
+        let (_, entries) = Journal::open::<String>(&path, 1).unwrap();
+        assert_eq!(entries, vec!["ls", "git status", "cargo build"]);
+    }
+}
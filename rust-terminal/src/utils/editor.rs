@@ -0,0 +1,109 @@
+// `edit-wait <file>`: opens the file in the configured editor (`editor` in
+// config.toml, $EDITOR by default) with the UI suspended until it exits, then
+// says what came of it. A workflow step `run = "edit-wait CHANGELOG.md"` waits
+// for it the same way and fails if the editor does; `ai do` edit_file actions
+// hand the result (and what the file says now) back to the AI.
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+
+pub const BUILTIN: &str = "edit-wait";
+
+// The file in `edit-wait <file>`, `~` expanded; the builtin and workflow
+// steps both go by this
+pub fn requested(command: &str) -> Option<PathBuf> {
+    let file = command.trim().strip_prefix(BUILTIN)?;
+    if !file.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let file = file.trim();
+    (!file.is_empty()).then(|| PathBuf::from(shellexpand::tilde(file).into_owned()))
+}
+
+// The program and its arguments: editors like `code --wait` need theirs
+pub fn command(editor: &str, path: &Path) -> (String, Vec<String>) {
+    let mut words = editor.split_whitespace().map(str::to_string);
+    let program = words.next().unwrap_or_else(|| "vi".to_string());
+    (program, words.chain([path.display().to_string()]).collect())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EditResult {
+    pub path: PathBuf,
+    // None when the editor was killed by a signal
+    pub exit_code: Option<i32>,
+    pub changed: bool,
+    // The file didn't exist before
+    pub created: bool,
+}
+
+impl EditResult {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    pub fn describe(&self) -> String {
+        let path = self.path.display();
+        match (self.exit_code, self.changed, self.created) {
+            (Some(0), true, true) => format!("Created {}", path),
+            (Some(0), true, false) => format!("Saved {}", path),
+            (Some(0), false, _) => format!("{} unchanged", path),
+            (Some(code), _, _) => format!("Editor exited with {} on {}", code, path),
+            (None, _, _) => format!("Editor was killed editing {}", path),
+        }
+    }
+}
+
+// The file as it was before the editor opened it
+pub struct Edit {
+    path: PathBuf,
+    before: Option<Vec<u8>>,
+}
+
+impl Edit {
+    pub fn start(path: &Path) -> Self {
+        Self { path: path.to_path_buf(), before: std::fs::read(path).ok() }
+    }
+
+    pub fn finish(self, status: ExitStatus) -> EditResult {
+        let after = std::fs::read(&self.path).ok();
+        EditResult {
+            exit_code: status.code(),
+            changed: after != self.before,
+            created: self.before.is_none() && after.is_some(),
+            path: self.path,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_what_the_editor_did() {
+        assert_eq!(requested("edit-wait  notes.md "), Some(PathBuf::from("notes.md")));
+        assert_eq!(requested("edit-wait ~/a.md"), dirs::home_dir().map(|home| home.join("a.md")));
+        assert_eq!(requested("edit-wait"), None);
+        assert_eq!(requested("edit-waiting x"), None);
+        assert_eq!(
+            command("code --wait", Path::new("a.md")),
+            ("code".to_string(), vec!["--wait".to_string(), "a.md".to_string()])
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        let run = |script: &str| std::process::Command::new("sh").args(["-c", script]).status().unwrap();
+
+        let edit = Edit::start(&path);
+        let result = edit.finish(run(&format!("echo hi > {}", path.display())));
+        assert!(result.succeeded() && result.changed && result.created);
+
+        let edit = Edit::start(&path);
+        let result = edit.finish(run("exit 0"));
+        assert_eq!(result.describe(), format!("{} unchanged", path.display()));
+
+        let edit = Edit::start(&path);
+        assert!(!edit.finish(run("exit 3")).succeeded());
+    }
+}
@@ -0,0 +1,139 @@
+// Environment and tool versions in effect for a command, kept with its history entry
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use crate::utils::clock;
+use crate::utils::startup::Pending;
+
+// Probed with `<tool> --version`
+const TOOLS: &[&str] = &["rustc", "cargo", "node", "python3", "go"];
+
+// Tool versions are re-probed when PATH changes or after this long
+const TOOLS_TTL: Duration = Duration::from_secs(300);
+
+// Per-session or cosmetic variables that would only add noise to a diff
+const IGNORED_VARS: &[&str] = &["_", "SHLVL", "OLDPWD", "LS_COLORS", "TERM_SESSION_ID", "WINDOWID"];
+
+const SECRET_MARKERS: &[&str] = &["TOKEN", "SECRET", "PASSWORD", "PASSWD", "KEY", "CREDENTIAL"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EnvSnapshot {
+    pub vars: BTreeMap<String, String>,
+    pub tools: BTreeMap<String, String>,
+}
+
+impl EnvSnapshot {
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .tools
+            .iter()
+            .map(|(tool, version)| format!("🔧 {}: {}", tool, version))
+            .collect();
+        lines.extend(self.vars.iter().map(|(name, value)| format!("{}={}", name, value)));
+        lines
+    }
+
+    // `+ added`, `- removed` and `~ changed` entries going from `self` to `other`
+    pub fn diff(&self, other: &EnvSnapshot) -> Vec<String> {
+        let mut lines = diff_maps("🔧 ", &self.tools, &other.tools);
+        lines.extend(diff_maps("", &self.vars, &other.vars));
+        lines
+    }
+}
+
+fn diff_maps(prefix: &str, old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (name, value) in old {
+        match new.get(name) {
+            None => lines.push(format!("- {}{}={}", prefix, name, value)),
+            Some(changed) if changed != value => {
+                lines.push(format!("~ {}{}: {} → {}", prefix, name, value, changed))
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, value) in new {
+        if !old.contains_key(name) {
+            lines.push(format!("+ {}{}={}", prefix, name, value));
+        }
+    }
+    lines
+}
+
+#[derive(Default)]
+pub struct EnvRecorder {
+    // (PATH the versions were probed with, when, versions)
+    tools: Option<(String, Instant, BTreeMap<String, String>)>,
+    // Running `--version` five times takes too long to do per command, so
+    // probes run on a thread and entries get the versions once they're in
+    probing: Option<(String, Pending<BTreeMap<String, String>>)>,
+}
+
+impl EnvRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `extra` are variables set for the command on top of NexTerm's own environment
+    pub fn capture(&mut self, extra: &BTreeMap<String, String>) -> EnvSnapshot {
+        let mut vars: BTreeMap<String, String> = std::env::vars().collect();
+        vars.extend(extra.iter().map(|(k, v)| (k.clone(), v.clone())));
+        vars.retain(|name, _| !IGNORED_VARS.contains(&name.as_str()));
+        for (name, value) in vars.iter_mut() {
+            if is_secret(name) {
+                *value = "<redacted>".to_string();
+            }
+        }
+
+        let path = vars.get("PATH").cloned().unwrap_or_default();
+        EnvSnapshot { tools: self.tool_versions(&path), vars }
+    }
+
+    // Fills in the tool versions of a snapshot taken while they were being probed
+    pub fn complete(&mut self, snapshot: &mut EnvSnapshot) {
+        if snapshot.tools.is_empty() {
+            let path = snapshot.vars.get("PATH").map(String::as_str).unwrap_or_default();
+            snapshot.tools = self.tool_versions(path);
+        }
+    }
+
+    // The versions last probed with `path`, empty until a first probe is in;
+    // stale ones are served while a fresh probe runs
+    fn tool_versions(&mut self, path: &str) -> BTreeMap<String, String> {
+        if let Some((probed_path, probe)) = &mut self.probing {
+            if let Poll::Ready(tools) = probe.poll() {
+                let probed_path = std::mem::take(probed_path);
+                self.probing = None;
+                self.tools = tools.map(|tools| (probed_path, clock::now(), tools));
+            }
+        }
+        let known = self.tools.as_ref().filter(|(probed_path, _, _)| probed_path == path);
+        let fresh = known.is_some_and(|(_, at, _)| clock::since(*at) < TOOLS_TTL);
+        let probing = self.probing.as_ref().is_some_and(|(probed_path, _)| probed_path == path);
+        if !fresh && !probing {
+            let owned = path.to_string();
+            self.probing = Some((path.to_string(), Pending::spawn("Tool versions", move || probe_tools(&owned))));
+        }
+        known.map(|(_, _, tools)| tools.clone()).unwrap_or_default()
+    }
+}
+
+pub fn is_secret(name: &str) -> bool {
+    let name = name.to_uppercase();
+    SECRET_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+fn probe_tools(path: &str) -> BTreeMap<String, String> {
+    TOOLS
+        .iter()
+        .filter_map(|tool| {
+            let output = Command::new(tool).arg("--version").env("PATH", path).output().ok()?;
+            let version = String::from_utf8_lossy(&output.stdout);
+            let version = version.lines().next()?.trim();
+            (output.status.success() && !version.is_empty()).then(|| (tool.to_string(), version.to_string()))
+        })
+        .collect()
+}
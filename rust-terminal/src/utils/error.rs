@@ -0,0 +1,79 @@
+// Errors users are meant to act on: each has a stable code, to search logs
+// and docs with, and a hint on what to do about it. They travel inside
+// anyhow errors; `describe` finds them again for toasts, logs and the message
+// printed on exit.
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum NexTermError {
+    #[error("failed to execute `{command}`: {source}")]
+    ExecutionFailed {
+        command: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("auto-wiring unavailable: {0}")]
+    BridgeUnavailable(String),
+    #[error("auto-wiring protocol mismatch: {0}")]
+    ProtocolMismatch(String),
+    #[error("auto-wiring ({transport}) timed out on {method}")]
+    BridgeTimeout { transport: String, method: String },
+    #[error("invalid config {}: {message}", path.display())]
+    ConfigInvalid { path: PathBuf, message: String },
+    #[error("could not open a pseudo-terminal: {0}")]
+    PtyError(String),
+}
+
+impl NexTermError {
+    // NT1xx commands, NT2xx auto-wiring, NT3xx configuration, NT4xx terminal I/O
+    pub fn code(&self) -> &'static str {
+        match self {
+            NexTermError::ExecutionFailed { .. } => "NT101",
+            NexTermError::BridgeUnavailable(_) => "NT201",
+            NexTermError::ProtocolMismatch(_) => "NT202",
+            NexTermError::BridgeTimeout { .. } => "NT203",
+            NexTermError::ConfigInvalid { .. } => "NT301",
+            NexTermError::PtyError(_) => "NT401",
+        }
+    }
+
+    pub fn hint(&self) -> &'static str {
+        match self {
+            NexTermError::ExecutionFailed { .. } => "check that `shell` in config.toml is installed and executable",
+            NexTermError::BridgeUnavailable(_) => "run `nexterm doctor` to check Python and the auto-wiring worker",
+            NexTermError::ProtocolMismatch(_) => "upgrade NexTerm and the auto-wiring system to matching releases",
+            NexTermError::BridgeTimeout { .. } => "raise timeout_ms under [autowire], or see the panel's log (l)",
+            NexTermError::ConfigInvalid { .. } => "fix the file, or move it aside to start over from the defaults",
+            NexTermError::PtyError(_) => "set `pty = false` in config.toml to always use pipes",
+        }
+    }
+}
+
+// The whole message chain, plus the code and hint of the first NexTermError
+// in it, on one line
+pub fn describe(error: &anyhow::Error) -> String {
+    let Some(known) = error.chain().find_map(|e| e.downcast_ref::<NexTermError>()) else {
+        return format!("{:#}", error);
+    };
+    format!("{:#} [{}] ({})", error, known.code(), known.hint())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn describes_wrapped_errors() {
+        let error = Err::<(), _>(NexTermError::BridgeUnavailable("python3 not found".into()))
+            .context("Could not list services")
+            .unwrap_err();
+        assert_eq!(
+            describe(&error),
+            "Could not list services: auto-wiring unavailable: python3 not found [NT201] \
+             (run `nexterm doctor` to check Python and the auto-wiring worker)"
+        );
+        assert_eq!(describe(&anyhow::anyhow!("plain")), "plain");
+    }
+}
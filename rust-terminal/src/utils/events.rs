@@ -0,0 +1,50 @@
+// Recent internal events, for `nexterm report`. Logging goes to stderr, which
+// the TUI's alternate screen hides, so info and above also go to events.log
+// in the data directory, trimmed to the newest MAX_EVENTS lines at startup.
+// Panics are recorded there too before the default hook prints them. Commands
+// end up in it, so only the user may read it.
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::error;
+
+use crate::utils::storage;
+
+const MAX_EVENTS: usize = 2000;
+
+pub fn path() -> PathBuf {
+    storage::data_dir().join("events.log")
+}
+
+// Where the log layer writes; None when the data directory isn't writable
+pub fn open() -> Option<Mutex<File>> {
+    let path = path();
+    if let Ok(content) = fs::read_to_string(&path) {
+        let lines: Vec<&str> = content.lines().collect();
+        if lines.len() > MAX_EVENTS {
+            let kept = lines[lines.len() - MAX_EVENTS..].join("\n") + "\n";
+            let _ = fs::write(&path, kept);
+        }
+    }
+    fs::create_dir_all(path.parent()?).ok()?;
+    let file = OpenOptions::new().create(true).append(true).mode(0o600).open(&path).ok()?;
+    // Logs from before it was created private
+    let _ = file.set_permissions(fs::Permissions::from_mode(0o600));
+    Some(Mutex::new(file))
+}
+
+pub fn record_panics() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        error!(target: "panic", "{}", info);
+        previous(info);
+    }));
+}
+
+// The newest `count` lines, oldest first
+pub fn recent(count: usize) -> Vec<String> {
+    let content = fs::read_to_string(path()).unwrap_or_default();
+    let lines: Vec<&str> = content.lines().collect();
+    lines[lines.len().saturating_sub(count)..].iter().map(|line| line.to_string()).collect()
+}
@@ -0,0 +1,143 @@
+// Recurring failure patterns and the fixes that resolved them
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::utils::{durable, storage};
+
+// Commands remembered between a failure and the successful re-run
+const MAX_ATTEMPTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorClass {
+    PermissionDenied,
+    NotFound,
+    CommandNotFound,
+    ConnectionRefused,
+    AlreadyInUse,
+    Other,
+}
+
+impl ErrorClass {
+    pub fn classify(stderr: &str, exit_code: i32) -> Self {
+        let stderr = stderr.to_lowercase();
+        if exit_code == 127 || stderr.contains("command not found") {
+            ErrorClass::CommandNotFound
+        } else if exit_code == 126 || stderr.contains("permission denied") {
+            ErrorClass::PermissionDenied
+        } else if stderr.contains("no such file") || stderr.contains("not found") {
+            ErrorClass::NotFound
+        } else if stderr.contains("connection refused") {
+            ErrorClass::ConnectionRefused
+        } else if stderr.contains("already in use") {
+            ErrorClass::AlreadyInUse
+        } else {
+            ErrorClass::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FailurePattern {
+    command_key: String,
+    error_class: ErrorClass,
+    occurrences: u32,
+    fix: Option<String>,
+}
+
+// A failure still waiting for its command to succeed again
+struct OpenFailure {
+    command_key: String,
+    error_class: ErrorClass,
+    attempts: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct FailureTracker {
+    patterns: Vec<FailurePattern>,
+    open: Option<OpenFailure>,
+}
+
+impl FailureTracker {
+    pub fn load() -> Self {
+        let patterns = durable::load_json(&Self::get_path()).unwrap_or_default();
+        Self { patterns, open: None }
+    }
+
+    // Feeds a finished command; returns a hint when this failure was fixed before
+    pub fn record(&mut self, command: &str, exit_code: i32, stderr: &str) -> Option<String> {
+        let command_key = command_key(command);
+
+        if exit_code != 0 {
+            let error_class = ErrorClass::classify(stderr, exit_code);
+            if self.pattern_mut(&command_key, error_class).is_none() {
+                self.patterns.push(FailurePattern {
+                    command_key: command_key.clone(),
+                    error_class,
+                    occurrences: 0,
+                    fix: None,
+                });
+            }
+            let pattern = self.pattern_mut(&command_key, error_class)?;
+            pattern.occurrences += 1;
+            let hint = pattern.fix.as_ref().map(|fix| {
+                format!("Last time you resolved this with `{}`", fix)
+            });
+
+            self.open = Some(OpenFailure { command_key, error_class, attempts: Vec::new() });
+            self.save_quietly();
+            return hint;
+        }
+
+        let open = self.open.as_mut()?;
+        if open.command_key != command_key {
+            if open.attempts.len() == MAX_ATTEMPTS {
+                open.attempts.remove(0);
+            }
+            open.attempts.push(command.to_string());
+            return None;
+        }
+
+        // The failing command works now: whatever ran last in between fixed it
+        let open = self.open.take()?;
+        if let Some(fix) = open.attempts.last() {
+            if let Some(pattern) = self.pattern_mut(&open.command_key, open.error_class) {
+                pattern.fix = Some(fix.clone());
+            }
+            self.save_quietly();
+        }
+        None
+    }
+
+    fn pattern_mut(&mut self, command_key: &str, error_class: ErrorClass) -> Option<&mut FailurePattern> {
+        self.patterns
+            .iter_mut()
+            .find(|p| p.command_key == command_key && p.error_class == error_class)
+    }
+
+    fn save(&self) -> Result<()> {
+        durable::write_atomic(&Self::get_path(), serde_json::to_string_pretty(&self.patterns)?.as_bytes())
+    }
+
+    fn save_quietly(&self) {
+        if let Err(e) = self.save() {
+            tracing::warn!("Failed to save failure patterns: {}", e);
+        }
+    }
+
+    fn get_path() -> PathBuf {
+        storage::data_dir().join("failures.json")
+    }
+}
+
+// Program plus its subcommand, e.g. `git push origin main` -> `git push`
+fn command_key(command: &str) -> String {
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    match words.next() {
+        Some(sub) if !sub.starts_with('-') && !sub.contains('/') && !sub.contains('.') => {
+            format!("{} {}", program, sub)
+        }
+        _ => program.to_string(),
+    }
+}
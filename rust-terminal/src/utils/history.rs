@@ -1,22 +1,269 @@
-// Command history
-use anyhow::Result;
+// Command history, persisted through the configured storage backend
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::utils::clock;
+use crate::utils::env_snapshot::{EnvRecorder, EnvSnapshot};
+use crate::utils::storage::{self, HistoryEntry, StorageBackend, StorageConfig};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryConfig {
+    // Older entries are dropped from the history file at startup past this many
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+    // A command identical to the one before it is not stored again
+    #[serde(default = "default_ignore_duplicates")]
+    pub ignore_duplicates: bool,
+}
+
+fn default_max_entries() -> usize {
+    10_000
+}
+
+fn default_ignore_duplicates() -> bool {
+    true
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: default_max_entries(),
+            ignore_duplicates: default_ignore_duplicates(),
+        }
+    }
+}
+
+// One command as the history search overlays show it: its latest run
+#[derive(Debug, Clone)]
+pub struct HistoryItem {
+    pub command: String,
+    pub timestamp: u64,
+    pub cwd: Option<PathBuf>,
+    pub exit_code: Option<i32>,
+    pub runs: usize,
+}
 
 pub struct History {
     entries: Vec<String>,
+    // Unix seconds each entry ran at, by index
+    timestamps: Vec<u64>,
+    dirs: Vec<Option<PathBuf>>,
+    // Exit status of each entry, by index; None while running or for older entries
+    exit_codes: Vec<Option<i32>>,
+    // Environment each entry ran with, by index; None for entries from before snapshots
+    snapshots: Vec<Option<EnvSnapshot>>,
+    // The latest entry, written out once its exit code is known
+    pending: Option<HistoryEntry>,
+    // The pending entry stays in memory only (retention rules)
+    private: bool,
+    config: HistoryConfig,
+    recorder: EnvRecorder,
+    backend: Option<Box<dyn StorageBackend>>,
 }
 
 impl History {
     pub fn new() -> Self {
         Self {
             entries: Vec::new(),
+            timestamps: Vec::new(),
+            dirs: Vec::new(),
+            exit_codes: Vec::new(),
+            snapshots: Vec::new(),
+            pending: None,
+            private: false,
+            config: HistoryConfig::default(),
+            recorder: EnvRecorder::new(),
+            backend: None,
         }
     }
 
-    pub fn add(&mut self, command: String) {
+    // Loads stored history; it stays in memory only if the backend can't be opened
+    pub fn open(storage: &StorageConfig, config: &HistoryConfig) -> Self {
+        let loaded = storage::open_backend(storage).and_then(|mut backend| {
+            let mut entries = backend.load_history()?;
+            if entries.len() > config.max_entries {
+                entries.drain(..entries.len() - config.max_entries);
+                backend.replace_history(&entries)?;
+            }
+            Ok((backend, entries))
+        });
+        let mut history = Self::new();
+        history.config = config.clone();
+        match loaded {
+            Ok((backend, entries)) => {
+                history.timestamps = entries.iter().map(|e| e.timestamp).collect();
+                history.dirs = entries.iter().map(|e| e.cwd.clone()).collect();
+                history.exit_codes = entries.iter().map(|e| e.exit_code).collect();
+                (history.entries, history.snapshots) = entries.into_iter().map(|e| (e.command, e.env)).unzip();
+                history.backend = Some(backend);
+            }
+            Err(e) => warn!("Failed to open command history: {}", e),
+        }
+        history
+    }
+
+    // `extra_env` is what the command gets on top of NexTerm's environment
+    pub fn add(&mut self, command: String, extra_env: &BTreeMap<String, String>) {
+        self.flush();
+        if self.config.ignore_duplicates && self.entries.last() == Some(&command) {
+            return;
+        }
+        let snapshot = self.recorder.capture(extra_env);
+        let timestamp = clock::unix_secs();
+        let cwd = std::env::current_dir().ok();
+        self.pending = Some(HistoryEntry {
+            command: command.clone(),
+            timestamp,
+            cwd: cwd.clone(),
+            exit_code: None,
+            env: Some(snapshot.clone()),
+        });
         self.entries.push(command);
+        self.timestamps.push(timestamp);
+        self.dirs.push(cwd);
+        self.exit_codes.push(None);
+        self.snapshots.push(Some(snapshot));
+    }
+
+    // The latest entry is kept for this session but never stored
+    pub fn skip_persist(&mut self) {
+        self.private = self.pending.is_some();
+    }
+
+    // Records how the latest entry exited and stores it
+    pub fn finish(&mut self, exit_code: i32) {
+        let Some(pending) = &mut self.pending else {
+            return;
+        };
+        pending.exit_code = Some(exit_code);
+        if let Some(last) = self.exit_codes.last_mut() {
+            *last = Some(exit_code);
+        }
+        self.flush();
+    }
+
+    // Stores the pending entry as it is; a command still running when the
+    // next starts, or at exit, keeps no exit code
+    fn flush(&mut self) {
+        let private = std::mem::take(&mut self.private);
+        let Some(mut entry) = self.pending.take().filter(|_| !private) else {
+            return;
+        };
+        // The pending entry is always the latest one
+        if let (Some(env), Some(Some(snapshot))) = (&mut entry.env, self.snapshots.last_mut()) {
+            self.recorder.complete(env);
+            snapshot.tools = env.tools.clone();
+        }
+        if let Some(backend) = &mut self.backend {
+            if let Err(e) = backend.append_history(&entry) {
+                warn!("Failed to save command history: {}", e);
+            }
+        }
     }
 
     pub fn get_all(&self) -> &[String] {
         &self.entries
     }
+
+    // (unix seconds, command) for every entry, oldest first
+    pub fn timed(&self) -> impl Iterator<Item = (u64, &str)> {
+        self.timestamps.iter().copied().zip(self.entries.iter().map(String::as_str))
+    }
+
+    // Each distinct command once, most recently run first
+    pub fn unique(&self) -> Vec<HistoryItem> {
+        let mut runs: HashMap<&str, usize> = HashMap::new();
+        for command in &self.entries {
+            *runs.entry(command.trim()).or_insert(0) += 1;
+        }
+        let mut seen = HashSet::new();
+        (0..self.entries.len())
+            .rev()
+            .filter(|&i| seen.insert(self.entries[i].trim()))
+            .map(|i| HistoryItem {
+                command: self.entries[i].trim().to_string(),
+                timestamp: self.timestamps[i],
+                cwd: self.dirs[i].clone(),
+                exit_code: self.exit_codes[i],
+                runs: runs[self.entries[i].trim()],
+            })
+            .collect()
+    }
+
+    // Every entry run at or after `from` (unix seconds), oldest first
+    pub fn since(&self, from: u64) -> Vec<HistoryItem> {
+        (0..self.entries.len()).filter(|&i| self.timestamps[i] >= from).map(|i| self.item(i)).collect()
+    }
+
+    // The latest `count` entries, oldest first
+    pub fn last(&self, count: usize) -> Vec<HistoryItem> {
+        (self.entries.len().saturating_sub(count)..self.entries.len()).map(|i| self.item(i)).collect()
+    }
+
+    fn item(&self, i: usize) -> HistoryItem {
+        HistoryItem {
+            command: self.entries[i].trim().to_string(),
+            timestamp: self.timestamps[i],
+            cwd: self.dirs[i].clone(),
+            exit_code: self.exit_codes[i],
+            runs: 1,
+        }
+    }
+
+    // Most frequent commands, most common first; with `under`, only those run
+    // in that directory or below it
+    pub fn top(&self, under: Option<&Path>, limit: usize) -> Vec<(&str, usize)> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (command, dir) in self.entries.iter().zip(&self.dirs) {
+            let matches = match (under, dir) {
+                (None, _) => true,
+                (Some(root), Some(dir)) => dir.starts_with(root),
+                (Some(_), None) => false,
+            };
+            if matches {
+                *counts.entry(command.trim()).or_insert(0) += 1;
+            }
+        }
+        let mut top: Vec<(&str, usize)> = counts.into_iter().collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        top.truncate(limit);
+        top
+    }
+
+    // Entries are numbered from 1, as `history` lists them
+    pub fn snapshot(&self, id: usize) -> Option<(&str, &EnvSnapshot)> {
+        let index = id.checked_sub(1)?;
+        let snapshot = self.snapshots.get(index)?.as_ref()?;
+        Some((&self.entries[index], snapshot))
+    }
+}
+
+impl Drop for History {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_keeps_latest_run() {
+        let mut history = History::new();
+        for command in ["ls", "ls", "make", "ls", "git status"] {
+            history.add(command.to_string(), &BTreeMap::new());
+            history.finish(0);
+        }
+        // The second `ls` in a row isn't stored
+        assert_eq!(history.get_all().len(), 4);
+
+        let unique = history.unique();
+        let commands: Vec<&str> = unique.iter().map(|i| i.command.as_str()).collect();
+        assert_eq!(commands, vec!["git status", "ls", "make"]);
+        assert_eq!(unique[1].runs, 2);
+        assert_eq!(unique[0].exit_code, Some(0));
+    }
 }
@@ -0,0 +1,32 @@
+// Idle suspension: after `idle_suspend_mins` without input, background work
+// (the auto-wiring worker, the appearance watcher, frequent redraws) stops
+// until the next key press. Watcher threads park in `wait_active`.
+use std::sync::{Condvar, Mutex};
+
+static SUSPENDED: Mutex<bool> = Mutex::new(false);
+static RESUMED: Condvar = Condvar::new();
+
+pub fn is_suspended() -> bool {
+    SUSPENDED.lock().map(|s| *s).unwrap_or(false)
+}
+
+pub fn suspend() {
+    if let Ok(mut suspended) = SUSPENDED.lock() {
+        *suspended = true;
+    }
+}
+
+pub fn resume() {
+    if let Ok(mut suspended) = SUSPENDED.lock() {
+        if std::mem::take(&mut *suspended) {
+            RESUMED.notify_all();
+        }
+    }
+}
+
+// Blocks the calling thread while suspended
+pub fn wait_active() {
+    if let Ok(suspended) = SUSPENDED.lock() {
+        drop(RESUMED.wait_while(suspended, |s| *s));
+    }
+}
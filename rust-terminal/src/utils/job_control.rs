@@ -0,0 +1,65 @@
+// Ctrl+Z for NexTerm itself. In raw mode the key arrives as a key event, not
+// as SIGTSTP, so the frontend gives the terminal back and calls `stop`; a
+// SIGTSTP sent from elsewhere (`kill -TSTP`) only raises a flag for it to do
+// the same between frames. After SIGCONT (`fg`), however NexTerm was stopped,
+// the frontend sets the terminal up again and redraws everything.
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static SUSPEND: AtomicBool = AtomicBool::new(false);
+static RESUMED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_tstp(_: libc::c_int) {
+    SUSPEND.store(true, Ordering::SeqCst);
+}
+
+extern "C" fn on_cont(_: libc::c_int) {
+    RESUMED.store(true, Ordering::SeqCst);
+}
+
+pub fn install() {
+    // SAFETY: the handlers only store to an atomic, which is async-signal-safe
+    unsafe {
+        libc::signal(libc::SIGTSTP, on_tstp as *const () as libc::sighandler_t);
+        libc::signal(libc::SIGCONT, on_cont as *const () as libc::sighandler_t);
+    }
+}
+
+// A SIGTSTP came in since the last call
+pub fn take_suspend() -> bool {
+    SUSPEND.swap(false, Ordering::SeqCst)
+}
+
+// A SIGCONT came in since the last call
+pub fn take_resumed() -> bool {
+    RESUMED.swap(false, Ordering::SeqCst)
+}
+
+// Stops the process the way the shell expects of Ctrl+Z, so it shows up in
+// `jobs`; returns once it is continued
+pub fn stop() {
+    // SAFETY: signal and raise on our own process; the handler put back is
+    // the one install() set
+    unsafe {
+        libc::signal(libc::SIGTSTP, libc::SIG_DFL);
+        libc::raise(libc::SIGTSTP);
+        libc::signal(libc::SIGTSTP, on_tstp as *const () as libc::sighandler_t);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signals_raise_flags() {
+        install();
+        // SAFETY: install() set handlers for both, so neither stops the test
+        unsafe {
+            libc::raise(libc::SIGTSTP);
+            libc::raise(libc::SIGCONT);
+        }
+        assert!(take_suspend());
+        assert!(!take_suspend());
+        assert!(take_resumed());
+    }
+}
@@ -0,0 +1,52 @@
+// Passphrase hashing for the session lock screen
+use anyhow::{bail, Context, Result};
+use openssl::hash::MessageDigest;
+use openssl::pkcs5::pbkdf2_hmac;
+
+const ITERATIONS: usize = 200_000;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const SCHEME: &str = "pbkdf2-sha256";
+
+// Stored as `pbkdf2-sha256$<iterations>$<salt hex>$<hash hex>`
+pub fn hash_passphrase(passphrase: &str) -> Result<String> {
+    let mut salt = [0u8; SALT_LEN];
+    openssl::rand::rand_bytes(&mut salt)?;
+    let key = derive(passphrase, &salt, ITERATIONS)?;
+    Ok(format!("{}${}${}${}", SCHEME, ITERATIONS, to_hex(&salt), to_hex(&key)))
+}
+
+pub fn verify_passphrase(passphrase: &str, stored: &str) -> Result<bool> {
+    let parts: Vec<&str> = stored.split('$').collect();
+    let [scheme, iterations, salt, expected] = parts[..] else {
+        bail!("Malformed passphrase hash");
+    };
+    if scheme != SCHEME {
+        bail!("Unsupported passphrase hash scheme: {}", scheme);
+    }
+
+    let iterations = iterations.parse().context("Malformed passphrase hash")?;
+    let expected = from_hex(expected)?;
+    let key = derive(passphrase, &from_hex(salt)?, iterations)?;
+    Ok(key.len() == expected.len() && openssl::memcmp::eq(&key, &expected))
+}
+
+fn derive(passphrase: &str, salt: &[u8], iterations: usize) -> Result<Vec<u8>> {
+    let mut key = vec![0u8; KEY_LEN];
+    pbkdf2_hmac(passphrase.as_bytes(), salt, iterations, MessageDigest::sha256(), &mut key)?;
+    Ok(key)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        bail!("Malformed passphrase hash");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).context("Malformed passphrase hash"))
+        .collect()
+}
@@ -1,3 +1,30 @@
 // Utils module
 pub mod config;
 pub mod history;
+pub mod sessions;
+pub mod lock;
+pub mod failures;
+pub mod durable;
+pub mod storage;
+pub mod sync;
+pub mod profile;
+pub mod doctor;
+pub mod env_snapshot;
+pub mod shell_escape;
+pub mod calendar;
+pub mod buffers;
+pub mod browser;
+pub mod desktop;
+pub mod ssh_keys;
+pub mod idle;
+pub mod error;
+pub mod supervisor;
+pub mod profiling;
+pub mod clock;
+pub mod rng;
+pub mod startup;
+pub mod patch;
+pub mod events;
+pub mod report;
+pub mod editor;
+pub mod job_control;
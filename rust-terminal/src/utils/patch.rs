@@ -0,0 +1,180 @@
+// Applies unified diffs (one file's worth, as `diff -u` or `git diff` print
+// them) without calling out to `patch`. Each hunk's old lines have to be
+// found in the file: at the line the header says, or the nearest place they
+// match within MAX_OFFSET lines if the file moved on. Nothing is written unless every hunk applies,
+// and the file as it was is copied to the backup directory first.
+use anyhow::{bail, Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::utils::clock;
+
+// How far from its header's line a hunk may land. Further than this, a
+// repeated block elsewhere is likelier than the file having moved
+const MAX_OFFSET: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Hunk {
+    // 1-based, as in the header; 0 for a new file
+    old_start: usize,
+    old: Vec<String>,
+    new: Vec<String>,
+}
+
+fn hunks(diff: &str) -> Result<Vec<Hunk>> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for line in diff.lines() {
+        if let Some(header) = line.strip_prefix("@@ ") {
+            let old = header.split_whitespace().next().unwrap_or_default();
+            let start = old.trim_start_matches('-').split(',').next().unwrap_or_default();
+            let old_start = start.parse().with_context(|| format!("Unreadable hunk header: {}", line))?;
+            hunks.push(Hunk { old_start, old: Vec::new(), new: Vec::new() });
+            continue;
+        }
+        let Some(hunk) = hunks.last_mut() else {
+            // `diff --git`, `---`, `+++` and the like
+            continue;
+        };
+        match line.chars().next() {
+            Some(' ') => {
+                hunk.old.push(line[1..].to_string());
+                hunk.new.push(line[1..].to_string());
+            }
+            // Context lines that lost their space to trimming
+            None => {
+                hunk.old.push(String::new());
+                hunk.new.push(String::new());
+            }
+            Some('-') => hunk.old.push(line[1..].to_string()),
+            Some('+') => hunk.new.push(line[1..].to_string()),
+            // "\ No newline at end of file"
+            Some('\\') => {}
+            _ => bail!("Unexpected line in a hunk: {}", line),
+        }
+    }
+    if hunks.is_empty() {
+        bail!("No hunks in the diff");
+    }
+    Ok(hunks)
+}
+
+// `original` with the diff applied, keeping its line endings
+pub fn apply(original: &str, diff: &str) -> Result<String> {
+    let newline = if original.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<String> = original.lines().map(str::to_string).collect();
+    // Where hunks land has shifted by what earlier ones added or removed
+    let mut shift: isize = 0;
+    for (n, hunk) in hunks(diff)?.iter().enumerate() {
+        let expected = (hunk.old_start.saturating_sub(1) as isize + shift).max(0) as usize;
+        let at = locate(&lines, &hunk.old, expected)
+            .with_context(|| format!("Hunk {} doesn't match the file (expected near line {})", n + 1, hunk.old_start))?;
+        lines.splice(at..at + hunk.old.len(), hunk.new.iter().cloned());
+        shift += hunk.new.len() as isize - hunk.old.len() as isize;
+    }
+    let mut patched = lines.join(newline);
+    if !lines.is_empty() && (original.ends_with('\n') || original.is_empty()) {
+        patched.push_str(newline);
+    }
+    Ok(patched)
+}
+
+// Where `old` occurs within MAX_OFFSET of `expected`, nearest first
+fn locate(lines: &[String], old: &[String], expected: usize) -> Option<usize> {
+    if old.is_empty() {
+        return Some(expected.min(lines.len()));
+    }
+    let last = lines.len().checked_sub(old.len())?;
+    let matches = |at: usize| lines[at..at + old.len()] == *old;
+    let expected = expected.min(last);
+    (0..=MAX_OFFSET)
+        .flat_map(|distance| [expected.checked_sub(distance), Some(expected + distance)])
+        .flatten()
+        .filter(|&at| at <= last)
+        .find(|&at| matches(at))
+}
+
+// Patches `path` in place (creating it if the diff starts from nothing) and
+// returns the backup of what it replaced
+pub fn apply_file(path: &Path, diff: &str, backups: &Path) -> Result<Option<PathBuf>> {
+    let original = match fs::read_to_string(path) {
+        Ok(text) => Some(text),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let patched = apply(original.as_deref().unwrap_or_default(), diff)?;
+    let backup = match &original {
+        Some(text) => {
+            fs::create_dir_all(backups)?;
+            let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            Some(back_up(backups, &name, text)?)
+        }
+        None => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            None
+        }
+    };
+    fs::write(path, patched).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(backup)
+}
+
+// A backup no earlier one is overwritten by: same-named files patched in
+// the same second get -1, -2, ...
+fn back_up(backups: &Path, name: &str, text: &str) -> Result<PathBuf> {
+    let stamp = clock::unix_secs();
+    for n in 0.. {
+        let backup = match n {
+            0 => backups.join(format!("{}-{}", stamp, name)),
+            n => backups.join(format!("{}-{}-{}", stamp, n, name)),
+        };
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&backup) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to back up to {}", backup.display())),
+        };
+        file.write_all(text.as_bytes()).with_context(|| format!("Failed to back up to {}", backup.display()))?;
+        return Ok(backup);
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_hunks_where_they_fit() {
+        let original = "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n";
+        let diff = "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -2,2 +2,3 @@\n     let x = 1;\n-    println!(\"{}\", x);\n+    let y = x + 1;\n+    println!(\"{}\", y);\n";
+        let patched = apply(original, diff).unwrap();
+        assert_eq!(patched, "fn main() {\n    let x = 1;\n    let y = x + 1;\n    println!(\"{}\", y);\n}\n");
+
+        // Two lines added above since the diff was made
+        let moved = format!("// a\n// b\n{}", original);
+        assert!(apply(&moved, diff).unwrap().contains("let y = x + 1;"));
+        assert!(apply("unrelated\n", diff).is_err());
+        let crlf = original.replace('\n', "\r\n");
+        assert_eq!(apply(&crlf, diff).unwrap(), patched.replace('\n', "\r\n"));
+        // Too far from where the header says to be a move
+        let far = format!("{}{}", "//\n".repeat(MAX_OFFSET + 5), original);
+        assert!(apply(&far, diff).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("main.rs");
+        fs::write(&file, original).unwrap();
+        let backup = apply_file(&file, diff, &dir.path().join("backups")).unwrap().unwrap();
+        assert_eq!(fs::read_to_string(backup).unwrap(), original);
+        assert_eq!(fs::read_to_string(&file).unwrap(), patched);
+
+        // Same name again: a second backup beside the first
+        let again = back_up(&dir.path().join("backups"), "main.rs", "older").unwrap();
+        assert_eq!(fs::read_dir(dir.path().join("backups")).unwrap().count(), 2);
+        assert_eq!(fs::read_to_string(again).unwrap(), "older");
+
+        let created = dir.path().join("new/notes.md");
+        assert_eq!(apply_file(&created, "@@ -0,0 +1,1 @@\n+hello\n", dir.path()).unwrap(), None);
+        assert_eq!(fs::read_to_string(created).unwrap(), "hello\n");
+    }
+}
@@ -0,0 +1,152 @@
+// Configuration bundles (.tar.zst) for moving a NexTerm setup between machines
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use crate::utils::clock;
+use crate::utils::config::Config;
+
+const MANIFEST: &str = "manifest.json";
+const BUNDLE_VERSION: u32 = 1;
+
+// Everything under the config directory that makes up a profile
+const PROFILE_FILES: &[&str] = &[
+    "config.toml",
+    "keybindings.toml",
+    "plugins.toml",
+    "themes",
+    "snippets",
+    "workflows",
+    "cheatsheets",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    created: u64,
+    files: Vec<String>,
+}
+
+// Packs the profile files that exist into `bundle`; the lock passphrase hash
+// is machine-specific and left out
+pub fn export(bundle: &Path) -> Result<Vec<String>> {
+    let dir = Config::dir();
+    let file = File::create(bundle).with_context(|| format!("Failed to create {}", bundle.display()))?;
+    let mut archive = tar::Builder::new(zstd::Encoder::new(file, 0)?.auto_finish());
+
+    let mut files = Vec::new();
+    for name in PROFILE_FILES {
+        let path = dir.join(name);
+        if *name == "config.toml" {
+            let mut config = Config::load()?;
+            config.lock_passphrase_hash = None;
+            append_bytes(&mut archive, name, toml::to_string_pretty(&config)?.as_bytes())?;
+        } else if path.is_dir() {
+            archive.append_dir_all(name, &path)?;
+        } else if path.is_file() {
+            archive.append_path_with_name(&path, name)?;
+        } else {
+            continue;
+        }
+        files.push(name.to_string());
+    }
+
+    let manifest = Manifest {
+        version: BUNDLE_VERSION,
+        created: clock::unix_secs(),
+        files: files.clone(),
+    };
+    append_bytes(&mut archive, MANIFEST, &serde_json::to_vec_pretty(&manifest)?)?;
+    archive.finish()?;
+    Ok(files)
+}
+
+// Unpacks `bundle` into the config directory. Files it replaces are kept
+// as `<name>.bak`; the local lock passphrase survives the import.
+pub fn import(bundle: &Path) -> Result<Vec<String>> {
+    let dir = Config::dir();
+    let previous_lock = Config::load().ok().and_then(|c| c.lock_passphrase_hash);
+
+    let manifest = read_manifest(bundle)?;
+    if manifest.version > BUNDLE_VERSION {
+        bail!("Bundle version {} is newer than this NexTerm supports", manifest.version);
+    }
+    if let Some(name) = manifest.files.iter().find(|f| !PROFILE_FILES.contains(&f.as_str())) {
+        bail!("Bundle lists unexpected file {:?}", name);
+    }
+
+    for name in &manifest.files {
+        let target = dir.join(name);
+        if target.exists() {
+            let backup = backup_path(&target);
+            if backup.exists() {
+                remove(&backup)?;
+            }
+            fs::rename(&target, &backup)?;
+        }
+    }
+
+    fs::create_dir_all(&dir)?;
+    let mut archive = open(bundle)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let listed = path
+            .components()
+            .next()
+            .is_some_and(|top| manifest.files.iter().any(|f| top.as_os_str() == f.as_str()));
+        if !listed {
+            continue;
+        }
+        // unpack_in refuses entries that would land outside `dir`
+        entry.unpack_in(&dir)?;
+    }
+
+    if previous_lock.is_some() {
+        let mut config = Config::load()?;
+        config.lock_passphrase_hash = previous_lock;
+        config.save()?;
+    }
+    Ok(manifest.files)
+}
+
+fn read_manifest(bundle: &Path) -> Result<Manifest> {
+    let mut archive = open(bundle)?;
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if entry.path()?.as_os_str() == MANIFEST {
+            return Ok(serde_json::from_reader(entry)?);
+        }
+    }
+    bail!("{} is not a NexTerm profile bundle (no manifest)", bundle.display())
+}
+
+fn open(bundle: &Path) -> Result<tar::Archive<zstd::Decoder<'static, std::io::BufReader<File>>>> {
+    let file = File::open(bundle).with_context(|| format!("Failed to open {}", bundle.display()))?;
+    Ok(tar::Archive::new(zstd::Decoder::new(file)?))
+}
+
+pub fn append_bytes<W: std::io::Write>(archive: &mut tar::Builder<W>, name: &str, data: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive.append_data(&mut header, name, data)?;
+    Ok(())
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+fn remove(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
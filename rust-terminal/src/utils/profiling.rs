@@ -0,0 +1,47 @@
+// Logging setup, plus Chrome / Perfetto traces of the hot paths when built
+// with `--features profiling`: `nexterm --trace-out trace.json` records the
+// spans around command execution, rendering and AI calls. Open the file in
+// chrome://tracing or https://ui.perfetto.dev.
+use anyhow::Result;
+use std::path::Path;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+
+use crate::utils::events;
+
+// Writes out the trace file when dropped
+pub struct TraceGuard {
+    #[cfg(feature = "profiling")]
+    _flush: Option<tracing_chrome::FlushGuard>,
+}
+
+pub fn init(trace_out: Option<&Path>) -> Result<TraceGuard> {
+    // On stderr, so subcommands can print results (`status --json -`) on stdout
+    let log = tracing_subscriber::fmt::layer().with_writer(std::io::stderr).with_filter(LevelFilter::INFO);
+    // The same into events.log, for `nexterm report`
+    let events = events::open()
+        .map(|file| tracing_subscriber::fmt::layer().with_ansi(false).with_writer(file).with_filter(LevelFilter::INFO));
+    events::record_panics();
+
+    #[cfg(feature = "profiling")]
+    {
+        let (chrome, flush) = match trace_out {
+            Some(path) => {
+                let (layer, flush) = tracing_chrome::ChromeLayerBuilder::new().file(path).build();
+                (Some(layer), Some(flush))
+            }
+            None => (None, None),
+        };
+        tracing_subscriber::registry().with(log).with(events).with(chrome).init();
+        Ok(TraceGuard { _flush: flush })
+    }
+
+    #[cfg(not(feature = "profiling"))]
+    {
+        if trace_out.is_some() {
+            anyhow::bail!("--trace-out needs a build with `--features profiling`");
+        }
+        tracing_subscriber::registry().with(log).with(events).init();
+        Ok(TraceGuard {})
+    }
+}
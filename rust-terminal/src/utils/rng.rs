@@ -0,0 +1,53 @@
+// Seedable randomness for jitter and the like (SplitMix64). Fixed seeds make
+// runs reproducible; NEXTERM_SEED sets one for the whole process. Not for
+// secrets: keys and salts come from openssl.
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    // From NEXTERM_SEED when set, otherwise from the OS
+    pub fn new() -> Self {
+        match std::env::var("NEXTERM_SEED").ok().and_then(|seed| seed.parse().ok()) {
+            Some(seed) => Self::seeded(seed),
+            None => Self::seeded(std::collections::hash_map::RandomState::new().build_hasher().finish()),
+        }
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in 0..=max, at millisecond resolution
+    pub fn jitter(&mut self, max: Duration) -> Duration {
+        let max_ms = max.as_millis() as u64;
+        Duration::from_millis(self.next_u64() % (max_ms + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let (mut a, mut b) = (Rng::seeded(42), Rng::seeded(42));
+        let first: Vec<u64> = (0..4).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..4).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(first[0], Rng::seeded(43).next_u64());
+
+        let max = Duration::from_millis(250);
+        assert!((0..100).all(|_| a.jitter(max) <= max));
+    }
+}
@@ -0,0 +1,67 @@
+// Recently used sessions, shown on the welcome screen
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use crate::utils::clock;
+use crate::utils::storage::{self, StorageBackend, StorageConfig};
+
+pub use crate::utils::storage::SessionEntry;
+
+const MAX_SESSIONS: usize = 10;
+
+#[derive(Default)]
+pub struct RecentSessions {
+    pub entries: Vec<SessionEntry>,
+    backend: Option<Box<dyn StorageBackend>>,
+}
+
+impl RecentSessions {
+    pub fn load(config: &StorageConfig) -> Self {
+        let Ok(mut backend) = storage::open_backend(config) else {
+            return Self::default();
+        };
+        let entries = backend.load_sessions().unwrap_or_default();
+        Self { entries, backend: Some(backend) }
+    }
+
+    // Moves the current directory to the front of the list and persists it
+    pub fn record_current(&mut self) -> Result<()> {
+        let variables = self.current_variables();
+        self.record(variables)
+    }
+
+    // The session variables kept for the current directory
+    pub fn current_variables(&self) -> BTreeMap<String, String> {
+        let Ok(directory) = std::env::current_dir() else {
+            return BTreeMap::new();
+        };
+        let directory = directory.display().to_string();
+        self.entries
+            .iter()
+            .find(|e| e.directory == directory)
+            .map(|e| e.variables.clone())
+            .unwrap_or_default()
+    }
+
+    // Keeps `variables` with the current directory's session
+    pub fn save_variables(&mut self, variables: &BTreeMap<String, String>) -> Result<()> {
+        self.record(variables.clone())
+    }
+
+    fn record(&mut self, variables: BTreeMap<String, String>) -> Result<()> {
+        let directory = std::env::current_dir()?.display().to_string();
+        let last_used = clock::unix_secs();
+
+        self.entries.retain(|e| e.directory != directory);
+        self.entries.insert(0, SessionEntry { directory, last_used, variables });
+        self.entries.truncate(MAX_SESSIONS);
+        self.save()
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        match &mut self.backend {
+            Some(backend) => backend.save_sessions(&self.entries),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+// Quoting of paths and words inserted into the command line, per shell
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShellKind {
+    // sh, bash, zsh, dash, ksh
+    #[default]
+    Posix,
+    Fish,
+    PowerShell,
+}
+
+impl ShellKind {
+    // From the configured shell path, e.g. /usr/bin/fish
+    pub fn from_shell(shell: &str) -> Self {
+        let name = Path::new(shell)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match name.trim_end_matches(".exe") {
+            "fish" => ShellKind::Fish,
+            "pwsh" | "powershell" => ShellKind::PowerShell,
+            _ => ShellKind::Posix,
+        }
+    }
+
+    // Bare when every character is safe, otherwise single-quoted with the
+    // shell's own rule for quotes (and backslashes) inside
+    pub fn quote(self, word: &str) -> String {
+        if !word.is_empty() && word.chars().all(|c| self.is_safe(c)) {
+            return word.to_string();
+        }
+        match self {
+            ShellKind::Posix => format!("'{}'", word.replace('\'', r"'\''")),
+            ShellKind::Fish => format!("'{}'", word.replace('\\', r"\\").replace('\'', r"\'")),
+            // PowerShell also treats typographic quotes as quotes; doubling escapes them all
+            ShellKind::PowerShell => {
+                let escaped: String = word
+                    .chars()
+                    .flat_map(|c| match c {
+                        '\'' | '‘' | '’' | '‚' | '‛' => vec![c, c],
+                        _ => vec![c],
+                    })
+                    .collect();
+                format!("'{}'", escaped)
+            }
+        }
+    }
+
+    // Characters that never need quoting in this shell
+    fn is_safe(self, c: char) -> bool {
+        let punctuation = match self {
+            ShellKind::Posix => "@%+=:,./-_",
+            // %name was process expansion before fish 3.0
+            ShellKind::Fish => "@+=:,./-_",
+            // `,` builds arrays and `@` splats
+            ShellKind::PowerShell => ":./-_",
+        };
+        c.is_ascii_alphanumeric() || punctuation.contains(c)
+    }
+
+    // Best-effort inverse of `quote` for a word being typed, so completion can
+    // match `'My Fi` against `My Files`
+    pub fn unquote_partial(self, word: &str) -> String {
+        if let Some(inner) = word.strip_prefix('\'') {
+            let inner = inner.strip_suffix('\'').unwrap_or(inner);
+            return match self {
+                ShellKind::Posix => inner.replace(r"'\''", "'"),
+                ShellKind::Fish => inner.replace(r"\'", "'").replace(r"\\", "\\"),
+                ShellKind::PowerShell => inner.replace("''", "'"),
+            };
+        }
+        if let Some(inner) = word.strip_prefix('"') {
+            return inner.strip_suffix('"').unwrap_or(inner).to_string();
+        }
+        match self {
+            ShellKind::PowerShell => word.replace('`', ""),
+            _ => {
+                let mut out = String::with_capacity(word.len());
+                let mut chars = word.chars();
+                while let Some(c) = chars.next() {
+                    out.extend(if c == '\\' { chars.next() } else { Some(c) });
+                }
+                out
+            }
+        }
+    }
+}
+
+// Byte offset where the word under the cursor (at the end of `input`) starts;
+// whitespace inside quotes or after a backslash doesn't end a word
+pub fn current_word_start(input: &str) -> usize {
+    let (mut start, mut quote, mut escaped) = (0, None, false);
+    for (i, c) in input.char_indices() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (None, '\\') => escaped = true,
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, _) if c.is_whitespace() => start = i + c.len_utf8(),
+            _ => {}
+        }
+    }
+    start
+}
+
+// Text pasted by a file drop: one `file://` URI or absolute path per line (or
+// space-separated URIs). Returns the paths when every item is one; ordinary
+// pastes give None and are inserted as typed.
+pub fn dropped_paths(text: &str) -> Option<Vec<String>> {
+    let items: Vec<&str> = text
+        .lines()
+        .flat_map(|line| {
+            let line = line.trim();
+            if line.starts_with("file://") {
+                line.split_whitespace().collect()
+            } else {
+                vec![line]
+            }
+        })
+        .filter(|item| !item.is_empty())
+        .collect();
+    if items.is_empty() {
+        return None;
+    }
+
+    items
+        .into_iter()
+        .map(|item| match item.strip_prefix("file://") {
+            Some(uri) => {
+                // file://host/path: the path starts at the first slash
+                let path = &uri[uri.find('/')?..];
+                Some(percent_decode(path))
+            }
+            None => {
+                let path = Path::new(item);
+                (path.is_absolute() && path.exists()).then(|| item.to_string())
+            }
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = text.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_per_shell() {
+        assert_eq!(ShellKind::Posix.quote("src/main.rs"), "src/main.rs");
+        assert_eq!(ShellKind::Posix.quote("My Files/it's"), r"'My Files/it'\''s'");
+        assert_eq!(ShellKind::Fish.quote(r"a\b's"), r"'a\\b\'s'");
+        assert_eq!(ShellKind::PowerShell.quote("it's"), "'it''s'");
+        assert_eq!(ShellKind::from_shell("/usr/local/bin/fish"), ShellKind::Fish);
+    }
+
+    #[test]
+    fn finds_quoted_word_start() {
+        assert_eq!(current_word_start("ls 'My Fi"), 3);
+        assert_eq!(current_word_start(r"ls My\ Fi"), 3);
+        assert_eq!(current_word_start("ls "), 3);
+    }
+
+    #[test]
+    fn unquotes_partial_words() {
+        assert_eq!(ShellKind::Posix.unquote_partial("'My Fi"), "My Fi");
+        assert_eq!(ShellKind::Posix.unquote_partial(r"My\ Fi"), "My Fi");
+    }
+
+    #[test]
+    fn decodes_file_uris() {
+        let paths = dropped_paths("file:///tmp/a%20b.txt\nfile://host/etc/hosts\n").unwrap();
+        assert_eq!(paths, vec!["/tmp/a b.txt", "/etc/hosts"]);
+        assert_eq!(dropped_paths("echo hello"), None);
+    }
+}
@@ -0,0 +1,340 @@
+// SSH agent and key helpers over ssh-add / ssh-keygen: the keys loaded in the
+// agent next to the private keys in ~/.ssh, adding (with a passphrase handed
+// over through SSH_ASKPASS) and removing them, and the identities
+// ~/.ssh/config names for a host, and the hosts it names
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// ssh options that take a value, so the host is found after them
+const OPTIONS_WITH_VALUE: &str = "BbcDEeFIiJLlmOoPpQRSWw";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshKey {
+    pub fingerprint: String,
+    // Key comment, or the file name for keys that aren't loaded
+    pub comment: String,
+    pub kind: String,
+    // Private key file; None for agent keys with no file here (forwarded ones)
+    pub path: Option<PathBuf>,
+    pub loaded: bool,
+    // Needs a passphrase to load
+    pub encrypted: bool,
+}
+
+// Agent keys first, then the private keys in ~/.ssh that aren't loaded
+pub fn list() -> Result<Vec<SshKey>> {
+    let mut keys = agent_keys()?;
+    for path in local_keys() {
+        let Some(fingerprint) = fingerprint(&path) else {
+            continue;
+        };
+        match keys.iter_mut().find(|k| k.fingerprint == fingerprint) {
+            Some(key) => key.path = Some(path),
+            None => keys.push(SshKey {
+                fingerprint,
+                comment: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                kind: String::new(),
+                encrypted: is_encrypted(&path),
+                path: Some(path),
+                loaded: false,
+            }),
+        }
+    }
+    Ok(keys)
+}
+
+// `ssh-add -l`: "256 SHA256:abc… user@host (ED25519)" per key
+fn agent_keys() -> Result<Vec<SshKey>> {
+    let output = Command::new("ssh-add").arg("-l").output().context("Failed to run ssh-add")?;
+    match output.status.code() {
+        Some(0) => {}
+        // No identities
+        Some(1) => return Ok(Vec::new()),
+        _ => bail!("No SSH agent is running (is SSH_AUTH_SOCK set?)"),
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let _bits = words.next()?;
+            let fingerprint = words.next()?.to_string();
+            let rest: Vec<&str> = words.collect();
+            let (kind, comment) = match rest.split_last() {
+                Some((kind, comment)) if kind.starts_with('(') => (kind.trim_matches(['(', ')']), comment.join(" ")),
+                _ => ("", rest.join(" ")),
+            };
+            Some(SshKey {
+                fingerprint,
+                comment,
+                kind: kind.to_string(),
+                path: None,
+                loaded: true,
+                encrypted: false,
+            })
+        })
+        .collect())
+}
+
+// Private keys in ~/.ssh: files with a matching .pub next to them
+fn local_keys() -> Vec<PathBuf> {
+    let Some(dir) = dirs::home_dir().map(|home| home.join(".ssh")) else {
+        return Vec::new();
+    };
+    let mut keys: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_none() && path.with_extension("pub").is_file())
+        .collect();
+    keys.sort();
+    keys
+}
+
+fn fingerprint(path: &Path) -> Option<String> {
+    let output = Command::new("ssh-keygen").arg("-lf").arg(path).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    output.status.success().then(|| text.split_whitespace().nth(1).map(String::from))?
+}
+
+// An encrypted key can't be read with an empty passphrase
+fn is_encrypted(path: &Path) -> bool {
+    let status = Command::new("ssh-keygen")
+        .args(["-y", "-P", "", "-f"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    !status.is_ok_and(|status| status.success())
+}
+
+// Loads `path` into the agent. ssh-add has no tty to prompt on, so the
+// passphrase reaches it through a one-off SSH_ASKPASS script reading it from
+// the environment
+pub fn add(path: &Path, passphrase: Option<&str>) -> Result<()> {
+    let mut command = Command::new("ssh-add");
+    command.arg(path).stdin(Stdio::null());
+    let askpass = match passphrase {
+        Some(passphrase) => {
+            // In a fresh directory only we can enter, so nobody else on a
+            // shared /tmp can put their own script in its place
+            let dir = tempfile::Builder::new()
+                .prefix("nexterm-askpass-")
+                .permissions(fs::Permissions::from_mode(0o700))
+                .tempdir()
+                .context("Failed to create a directory for ssh-add")?;
+            let script = dir.path().join("askpass");
+            fs::OpenOptions::new()
+                .create_new(true)
+                .write(true)
+                .mode(0o700)
+                .open(&script)?
+                .write_all(b"#!/bin/sh\nprintf '%s\\n' \"$NEXTERM_SSH_PASSPHRASE\"\n")?;
+            command
+                .env("SSH_ASKPASS", &script)
+                .env("SSH_ASKPASS_REQUIRE", "force")
+                // Older ssh-add only uses SSH_ASKPASS with a DISPLAY set
+                .env("DISPLAY", std::env::var("DISPLAY").unwrap_or_else(|_| ":0".to_string()))
+                .env("NEXTERM_SSH_PASSPHRASE", passphrase);
+            Some(dir)
+        }
+        None => None,
+    };
+    let output = command.output();
+    // Removed with its script
+    drop(askpass);
+    let output = output.context("Failed to run ssh-add")?;
+    if !output.status.success() {
+        bail!("ssh-add failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+pub fn remove(path: &Path) -> Result<()> {
+    let output = Command::new("ssh-add").arg("-d").arg(path).output().context("Failed to run ssh-add")?;
+    if !output.status.success() {
+        bail!("ssh-add failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+// An `ssh [options] destination [command]` invocation, split into words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshCommand<'a> {
+    // Flags with their values, in order
+    pub options: Vec<&'a str>,
+    // `host` or `user@host`
+    pub destination: &'a str,
+    pub remote: Vec<&'a str>,
+}
+
+impl SshCommand<'_> {
+    pub fn host(&self) -> &str {
+        self.destination.rsplit('@').next().unwrap_or(self.destination)
+    }
+
+    // Values of every `-i`
+    pub fn identity_files(&self) -> Vec<PathBuf> {
+        self.options.windows(2).filter(|pair| pair[0] == "-i").map(|pair| expand(pair[1])).collect()
+    }
+}
+
+pub fn parse(command: &str) -> Option<SshCommand<'_>> {
+    let mut words = command.split_whitespace();
+    if words.next()? != "ssh" {
+        return None;
+    }
+    let mut options = Vec::new();
+    while let Some(word) = words.next() {
+        match word.strip_prefix('-') {
+            Some(flag) if flag.len() == 1 && OPTIONS_WITH_VALUE.contains(flag) => {
+                options.push(word);
+                options.push(words.next()?);
+            }
+            Some(_) => options.push(word),
+            None => {
+                return Some(SshCommand {
+                    options,
+                    destination: word,
+                    remote: words.collect(),
+                })
+            }
+        }
+    }
+    None
+}
+
+// For `ssh <host>` to a host ~/.ssh/config gives an IdentityFile, the warning
+// that none of its keys are in the agent. Nothing when there's no agent.
+pub fn check_connect(command: &str) -> Option<String> {
+    let ssh = parse(command)?;
+    let host = ssh.host();
+    let explicit = ssh.identity_files();
+    let config = fs::read_to_string(dirs::home_dir()?.join(".ssh/config")).ok()?;
+    let identities = if explicit.is_empty() { host_identities(&config, host) } else { explicit };
+    if identities.is_empty() {
+        return None;
+    }
+    let loaded = agent_keys().ok()?;
+    let wanted: Vec<String> = identities.iter().filter_map(|path| fingerprint(path)).collect();
+    if wanted.is_empty() || loaded.iter().any(|key| wanted.contains(&key.fingerprint)) {
+        return None;
+    }
+    let names: Vec<String> = identities.iter().map(|p| p.display().to_string()).collect();
+    Some(format!(
+        "{}'s key ({}) isn't loaded in the SSH agent; `ssh-keys` adds it",
+        host,
+        names.join(", ")
+    ))
+}
+
+// IdentityFile entries of every `Host` block matching `host`
+fn host_identities(config: &str, host: &str) -> Vec<PathBuf> {
+    let mut matching = false;
+    let mut identities = Vec::new();
+    for line in config.lines() {
+        let line = line.trim();
+        let (key, value) = match line.split_once(|c: char| c.is_whitespace() || c == '=') {
+            Some((key, value)) => (key.to_lowercase(), value.trim().trim_start_matches('=').trim()),
+            None => continue,
+        };
+        match key.as_str() {
+            "host" => {
+                let patterns: Vec<&str> = value.split_whitespace().collect();
+                let excluded = patterns.iter().any(|p| p.strip_prefix('!').is_some_and(|p| glob(p, host)));
+                matching = !excluded && patterns.iter().any(|p| !p.starts_with('!') && glob(p, host));
+            }
+            "match" => matching = false,
+            "identityfile" if matching => identities.push(expand(value.trim_matches('"'))),
+            _ => {}
+        }
+    }
+    identities
+}
+
+// Hosts named in ~/.ssh/config, leaving out patterns
+pub fn config_hosts() -> Vec<String> {
+    let config = dirs::home_dir().and_then(|home| fs::read_to_string(home.join(".ssh/config")).ok());
+    named_hosts(&config.unwrap_or_default())
+}
+
+fn named_hosts(config: &str) -> Vec<String> {
+    let mut hosts: Vec<String> = Vec::new();
+    for line in config.lines() {
+        let line = line.trim();
+        let Some((key, value)) = line.split_once(|c: char| c.is_whitespace() || c == '=') else {
+            continue;
+        };
+        if !key.eq_ignore_ascii_case("host") {
+            continue;
+        }
+        for host in value.trim().trim_start_matches('=').split_whitespace() {
+            if !host.contains(['*', '?', '!']) && !hosts.iter().any(|h| h == host) {
+                hosts.push(host.to_string());
+            }
+        }
+    }
+    hosts
+}
+
+// ssh_config patterns: `*` and `?`
+fn glob(pattern: &str, text: &str) -> bool {
+    match pattern.chars().next() {
+        None => text.is_empty(),
+        Some('*') => (0..=text.len())
+            .filter(|&i| text.is_char_boundary(i))
+            .any(|i| glob(&pattern[1..], &text[i..])),
+        Some(c) => {
+            let mut chars = text.chars();
+            match chars.next() {
+                Some(t) if c == '?' || c.eq_ignore_ascii_case(&t) => glob(&pattern[c.len_utf8()..], chars.as_str()),
+                _ => false,
+            }
+        }
+    }
+}
+
+fn expand(path: &str) -> PathBuf {
+    PathBuf::from(shellexpand::tilde(path).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_identities_for_a_host() {
+        let config = "\
+Host github.com
+    IdentityFile ~/.ssh/id_github
+
+Host *.prod !bastion.prod
+    User deploy
+    IdentityFile=/keys/prod
+
+Host *
+    AddKeysToAgent yes
+";
+        assert_eq!(host_identities(config, "github.com").len(), 1);
+        assert_eq!(host_identities(config, "db.prod"), vec![PathBuf::from("/keys/prod")]);
+        assert!(host_identities(config, "bastion.prod").is_empty());
+        assert!(host_identities(config, "example.org").is_empty());
+        assert_eq!(named_hosts(config), ["github.com"]);
+    }
+
+    #[test]
+    fn parses_ssh_commands() {
+        let ssh = parse("ssh -p 2222 -i ~/.ssh/deploy -A deploy@db.prod uptime").unwrap();
+        assert_eq!(ssh.options, vec!["-p", "2222", "-i", "~/.ssh/deploy", "-A"]);
+        assert_eq!(ssh.host(), "db.prod");
+        assert_eq!(ssh.remote, vec!["uptime"]);
+        assert_eq!(ssh.identity_files().len(), 1);
+        assert!(parse("ssh -v").is_none());
+        assert!(parse("scp a b:").is_none());
+    }
+}
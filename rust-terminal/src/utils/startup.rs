@@ -0,0 +1,79 @@
+// Startup off the critical path: setup that runs programs or reads the disk
+// (probing Python, loading plugins, connecting auto-wiring, looking up the git
+// branch) happens on a thread of its own while the prompt is already usable.
+// The main loop polls for the result and flips the status bar indicator when
+// it's in.
+use std::future::Future;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::task::Poll;
+use std::time::Instant;
+use tokio::task::AbortHandle;
+use tracing::info;
+
+use crate::utils::{clock, supervisor};
+
+// Something started in the background, handed over once by `poll`
+pub struct Pending<T> {
+    name: &'static str,
+    started: Instant,
+    rx: Receiver<T>,
+}
+
+impl<T: Send + 'static> Pending<T> {
+    pub fn spawn(name: &'static str, work: impl FnOnce() -> T + Send + 'static) -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || match supervisor::catch(work) {
+            Ok(result) => {
+                let _ = tx.send(result);
+            }
+            Err(message) => supervisor::fault(name, &message),
+        });
+        Self { name, started: clock::now(), rx }
+    }
+
+    // `work` as a task on the runtime instead, so it can be aborted
+    pub fn task(name: &'static str, work: impl Future<Output = T> + Send + 'static) -> (Self, AbortHandle) {
+        let (tx, rx) = mpsc::channel();
+        let task = tokio::spawn(async move {
+            match supervisor::catch_async(work).await {
+                Ok(result) => {
+                    let _ = tx.send(result);
+                }
+                Err(message) => supervisor::fault(name, &message),
+            }
+        });
+        (Self { name, started: clock::now(), rx }, task.abort_handle())
+    }
+
+    // Ready(None) if the work panicked (the fault is reported already) or was aborted
+    pub fn poll(&mut self) -> Poll<Option<T>> {
+        match self.rx.try_recv() {
+            Ok(result) => {
+                info!("{} ready after {}ms", self.name, clock::since(self.started).as_millis());
+                Poll::Ready(Some(result))
+            }
+            Err(TryRecvError::Empty) => Poll::Pending,
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait<T: Send + 'static>(mut pending: Pending<T>) -> Option<T> {
+        loop {
+            if let Poll::Ready(result) = pending.poll() {
+                return result;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn hands_over_results_and_survives_panics() {
+        assert_eq!(wait(Pending::spawn("Answer", || 42)), Some(42));
+        assert_eq!(wait(Pending::spawn("Broken", || -> u32 { panic!("no disk") })), None);
+    }
+}
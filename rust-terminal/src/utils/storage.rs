@@ -0,0 +1,160 @@
+// Pluggable persistence for command history and sessions
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::utils::durable::{self, Journal};
+use crate::utils::env_snapshot::EnvSnapshot;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    // JSONL journal + JSON snapshots under the data directory
+    #[default]
+    File,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: BackendKind,
+    // Journal appends between fsyncs: 1 syncs every write, 0 leaves it to the OS
+    #[serde(default = "default_fsync_every")]
+    pub fsync_every: u32,
+}
+
+fn default_fsync_every() -> u32 {
+    1
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: BackendKind::default(),
+            fsync_every: default_fsync_every(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub command: String,
+    pub timestamp: u64,
+    // Working directory the command ran in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<EnvSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEntry {
+    pub directory: String,
+    pub last_used: u64,
+    // Set with `set name=value`, see core::variables
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+}
+
+// Where history and sessions live. Implementations must be crash-safe: an
+// interrupted write may lose the entry in flight but never earlier ones.
+pub trait StorageBackend: Send {
+    fn load_history(&mut self) -> Result<Vec<HistoryEntry>>;
+    fn append_history(&mut self, entry: &HistoryEntry) -> Result<()>;
+    // Swaps the whole history for `entries`, e.g. to drop the oldest ones
+    fn replace_history(&mut self, entries: &[HistoryEntry]) -> Result<()>;
+    fn load_sessions(&mut self) -> Result<Vec<SessionEntry>>;
+    fn save_sessions(&mut self, sessions: &[SessionEntry]) -> Result<()>;
+}
+
+pub fn open_backend(config: &StorageConfig) -> Result<Box<dyn StorageBackend>> {
+    match config.backend {
+        BackendKind::File => Ok(Box::new(FileBackend::new(data_dir(), config.fsync_every))),
+    }
+}
+
+// Where NexTerm keeps its data: history, sessions, daemon logs
+// Replaces the data directory for this process, e.g. so `nexterm demo`
+// leaves the user's history alone
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn set_data_dir(path: PathBuf) {
+    let _ = DATA_DIR.set(path);
+}
+
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = DATA_DIR.get() {
+        return dir.clone();
+    }
+    let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("nexterm");
+    path
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SessionsFile {
+    entries: Vec<SessionEntry>,
+}
+
+pub struct FileBackend {
+    dir: PathBuf,
+    fsync_every: u32,
+    journal: Option<Journal>,
+}
+
+impl FileBackend {
+    pub fn new(dir: PathBuf, fsync_every: u32) -> Self {
+        Self { dir, fsync_every, journal: None }
+    }
+
+    fn history_path(&self) -> PathBuf {
+        self.dir.join("history.jsonl")
+    }
+
+    fn sessions_path(&self) -> PathBuf {
+        self.dir.join("sessions.json")
+    }
+}
+
+impl StorageBackend for FileBackend {
+    fn load_history(&mut self) -> Result<Vec<HistoryEntry>> {
+        let (journal, entries) = Journal::open(&self.history_path(), self.fsync_every)?;
+        self.journal = Some(journal);
+        Ok(entries)
+    }
+
+    fn append_history(&mut self, entry: &HistoryEntry) -> Result<()> {
+        if self.journal.is_none() {
+            self.load_history()?;
+        }
+        match &mut self.journal {
+            Some(journal) => journal.append(entry),
+            None => Ok(()),
+        }
+    }
+
+    fn replace_history(&mut self, entries: &[HistoryEntry]) -> Result<()> {
+        let mut contents = Vec::new();
+        for entry in entries {
+            serde_json::to_writer(&mut contents, entry)?;
+            contents.push(b'\n');
+        }
+        // Reopened on the next append, after the file is swapped
+        self.journal = None;
+        durable::write_atomic(&self.history_path(), &contents)
+    }
+
+    fn load_sessions(&mut self) -> Result<Vec<SessionEntry>> {
+        let file: SessionsFile = durable::load_json(&self.sessions_path()).unwrap_or_default();
+        Ok(file.entries)
+    }
+
+    fn save_sessions(&mut self, sessions: &[SessionEntry]) -> Result<()> {
+        let file = SessionsFile { entries: sessions.to_vec() };
+        durable::write_atomic(&self.sessions_path(), serde_json::to_string_pretty(&file)?.as_bytes())
+    }
+}
@@ -0,0 +1,204 @@
+// Panic isolation for plugin hooks and AI work. A panic inside `catch` is
+// caught instead of unwinding into the main loop, and isn't printed over the
+// TUI; a Supervisor counts them per component, reports each one to the
+// notification center and disables the component after MAX_PANICS in a row.
+use std::any::Any;
+use std::cell::Cell;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+use std::task::Poll;
+use tracing::error;
+
+// Panics in a row before a component is disabled
+const MAX_PANICS: u32 = 3;
+
+// For the notification center. Tests run in parallel and the terminal's drain
+// it, so under test each thread has its own
+#[cfg(not(test))]
+static FAULTS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+static HOOK: Once = Once::new();
+
+thread_local! {
+    static CATCHING: Cell<bool> = const { Cell::new(false) };
+    #[cfg(test)]
+    static FAULTS: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+// Runs `f`, turning a panic into its message
+pub fn catch<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    install_hook();
+    let outer = CATCHING.with(|c| c.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.with(|c| c.set(outer));
+    result.map_err(|payload| message(payload.as_ref()))
+}
+
+// `catch` for async work: a panic in any poll of `future` ends it
+pub async fn catch_async<T>(future: impl Future<Output = T>) -> Result<T, String> {
+    let mut future = Box::pin(future);
+    std::future::poll_fn(|cx| match catch(|| future.as_mut().poll(cx)) {
+        Ok(Poll::Ready(value)) => Poll::Ready(Ok(value)),
+        Ok(Poll::Pending) => Poll::Pending,
+        Err(message) => Poll::Ready(Err(message)),
+    })
+    .await
+}
+
+fn message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => payload.downcast_ref::<String>().cloned().unwrap_or_else(|| "unknown panic".to_string()),
+    }
+}
+
+// Caught panics are reported by whoever caught them, not printed by the hook
+fn install_hook() {
+    HOOK.call_once(|| {
+        let default = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !CATCHING.with(Cell::get) {
+                default(info);
+            }
+        }));
+    });
+}
+
+// Logs a caught panic and queues it for the notification center
+pub fn fault(component: &str, message: &str) {
+    error!("{} panicked: {}", component, message);
+    push_fault(format!("{} panicked: {}", component, message));
+}
+
+#[cfg(not(test))]
+fn push_fault(fault: String) {
+    if let Ok(mut faults) = FAULTS.lock() {
+        faults.push(fault);
+    }
+}
+
+#[cfg(test)]
+fn push_fault(fault: String) {
+    FAULTS.with(|faults| faults.borrow_mut().push(fault));
+}
+
+// Reported faults since the last call, oldest first
+#[cfg(not(test))]
+pub fn take_faults() -> Vec<String> {
+    FAULTS.lock().map(|mut f| std::mem::take(&mut *f)).unwrap_or_default()
+}
+
+#[cfg(test)]
+pub fn take_faults() -> Vec<String> {
+    FAULTS.with(|faults| faults.take())
+}
+
+pub struct Supervisor {
+    component: String,
+    panics: u32,
+    disabled: bool,
+}
+
+impl Supervisor {
+    pub fn new(component: impl Into<String>) -> Self {
+        Self { component: component.into(), panics: 0, disabled: false }
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    // Gives a disabled component another chance, e.g. when the user turns it back on
+    pub fn reset(&mut self) {
+        self.panics = 0;
+        self.disabled = false;
+    }
+
+    // Runs `f` unless the component is disabled; None if it panicked
+    pub fn run<T>(&mut self, f: impl FnOnce() -> T) -> Option<T> {
+        if self.disabled {
+            return None;
+        }
+        match catch(f) {
+            Ok(value) => {
+                self.panics = 0;
+                Some(value)
+            }
+            Err(message) => {
+                self.panicked(&message);
+                None
+            }
+        }
+    }
+
+    // `run` for async work
+    pub async fn run_async<T>(&mut self, future: impl Future<Output = T>) -> Option<T> {
+        if self.disabled {
+            return None;
+        }
+        match catch_async(future).await {
+            Ok(value) => {
+                self.panics = 0;
+                Some(value)
+            }
+            Err(message) => {
+                self.panicked(&message);
+                None
+            }
+        }
+    }
+
+    // Records a panic caught elsewhere, e.g. on a worker thread
+    pub fn panicked(&mut self, message: &str) {
+        self.panics += 1;
+        fault(&self.component, message);
+        if self.panics >= MAX_PANICS && !self.disabled {
+            self.disabled = true;
+            error!("{} disabled after {} panics in a row", self.component, self.panics);
+            push_fault(format!("{} disabled after {} panics in a row", self.component, self.panics));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disables_after_repeated_panics() {
+        let mut supervisor = Supervisor::new("test component");
+        assert_eq!(supervisor.run(|| 1), Some(1));
+        for _ in 0..MAX_PANICS - 1 {
+            assert_eq!(supervisor.run(|| -> i32 { panic!("boom") }), None);
+        }
+        // A success in between starts the count over
+        assert_eq!(supervisor.run(|| 2), Some(2));
+        for _ in 0..MAX_PANICS {
+            assert_eq!(supervisor.run(|| -> i32 { panic!("boom") }), None);
+        }
+        assert!(supervisor.is_disabled());
+        assert_eq!(supervisor.run(|| 3), None);
+
+        let faults = take_faults();
+        assert_eq!(faults.len(), 2 * MAX_PANICS as usize);
+        assert_eq!(faults.last().unwrap(), "test component disabled after 3 panics in a row");
+
+        supervisor.reset();
+        assert_eq!(supervisor.run(|| 4), Some(4));
+    }
+
+    #[tokio::test]
+    async fn catches_panics_in_futures() {
+        let mut supervisor = Supervisor::new("async component");
+        let value = supervisor.run_async(async {
+            tokio::task::yield_now().await;
+            5
+        });
+        assert_eq!(value.await, Some(5));
+        let panicked = supervisor.run_async(async {
+            tokio::task::yield_now().await;
+            panic!("late boom")
+        });
+        assert_eq!(panicked.await, None::<()>);
+    }
+}
@@ -0,0 +1,369 @@
+// End-to-end encrypted history sync with a self-hosted server.
+// Entries are sealed with AES-256-GCM before they leave the machine; the server
+// only ever stores opaque blobs. Server API (JSON over HTTP):
+//   POST   /api/devices            {name} -> {id, token}
+//   GET    /api/devices            -> [{id, name}]
+//   DELETE /api/devices/<id>
+//   POST   /api/history            {records: [{id, data}]}
+//   GET    /api/history?after=<c>  -> {records: [{id, data, device_id}], cursor}
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::utils::durable;
+use crate::utils::storage::{self, HistoryEntry, StorageConfig};
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncConfig {
+    // e.g. https://sync.example.com; sync is off while unset
+    #[serde(default)]
+    pub server: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Device {
+    id: String,
+    name: String,
+    token: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemoteDevice {
+    pub id: String,
+    pub name: String,
+}
+
+// Progress through the local journal and the server's record stream
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    device: Option<Device>,
+    // Records sent so far; numbers the next record's id
+    pushed: usize,
+    // The journal's last entry when it was last synced. Found again by content,
+    // since trimming history at startup shifts every index
+    #[serde(default)]
+    synced: Option<Mark>,
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Mark {
+    index: usize,
+    timestamp: u64,
+    command: String,
+}
+
+impl SyncState {
+    // Entries added to the journal since the last sync
+    fn pending<'a>(&self, local: &'a [HistoryEntry]) -> &'a [HistoryEntry] {
+        let start = match &self.synced {
+            // Trimming only moves entries towards the front
+            Some(mark) => local[..local.len().min(mark.index + 1)]
+                .iter()
+                .rposition(|e| e.timestamp == mark.timestamp && e.command == mark.command)
+                .map_or(0, |i| i + 1),
+            // State from before marks: `pushed` was the journal position
+            None => self.pushed.min(local.len()),
+        };
+        &local[start..]
+    }
+
+    fn mark(&mut self, index: usize, last: Option<&HistoryEntry>) {
+        self.synced = last.map(|e| Mark { index, timestamp: e.timestamp, command: e.command.clone() });
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    id: String,
+    data: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    device_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PullResponse {
+    records: Vec<Record>,
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub pushed: usize,
+    pub pulled: usize,
+}
+
+pub struct SyncClient {
+    server: String,
+    http: reqwest::Client,
+    key: Vec<u8>,
+    state: SyncState,
+}
+
+impl SyncClient {
+    pub fn new(config: &SyncConfig) -> Result<Self> {
+        let Some(server) = &config.server else {
+            bail!("No sync server configured; set [sync] server in config.toml");
+        };
+        Ok(Self {
+            server: server.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+            key: load_or_create_key()?,
+            state: durable::load_json(&state_path()).unwrap_or_default(),
+        })
+    }
+
+    pub async fn register(&mut self, name: &str) -> Result<String> {
+        #[derive(Deserialize)]
+        struct Registered {
+            id: String,
+            token: String,
+        }
+        let registered: Registered = self
+            .http
+            .post(format!("{}/api/devices", self.server))
+            .json(&serde_json::json!({ "name": name }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        self.state = SyncState {
+            device: Some(Device { id: registered.id.clone(), name: name.to_string(), token: registered.token }),
+            ..SyncState::default()
+        };
+        self.save_state()?;
+        Ok(registered.id)
+    }
+
+    pub async fn devices(&self) -> Result<Vec<RemoteDevice>> {
+        let device = self.device()?;
+        Ok(self
+            .http
+            .get(format!("{}/api/devices", self.server))
+            .bearer_auth(&device.token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    pub async fn remove_device(&mut self, id: &str) -> Result<()> {
+        let device = self.device()?;
+        self.http
+            .delete(format!("{}/api/devices/{}", self.server, id))
+            .bearer_auth(&device.token)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        if id == device.id {
+            self.state = SyncState::default();
+            self.save_state()?;
+        }
+        Ok(())
+    }
+
+    // Pushes entries recorded since the last sync, then appends what other
+    // devices pushed to the local history
+    pub async fn sync(&mut self, storage_config: &StorageConfig) -> Result<SyncReport> {
+        let device = self.device()?.clone();
+        let mut backend = storage::open_backend(storage_config)?;
+        let local = backend.load_history()?;
+        let mut report = SyncReport::default();
+
+        let pending = self.state.pending(&local);
+        if !pending.is_empty() {
+            let records = pending
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| {
+                    Ok(Record {
+                        id: format!("{}-{}", device.id, self.state.pushed + i),
+                        data: self.seal(entry)?,
+                        device_id: None,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            self.http
+                .post(format!("{}/api/history", self.server))
+                .bearer_auth(&device.token)
+                .json(&serde_json::json!({ "records": records }))
+                .send()
+                .await?
+                .error_for_status()?;
+            report.pushed = records.len();
+        }
+
+        let mut request = self
+            .http
+            .get(format!("{}/api/history", self.server))
+            .bearer_auth(&device.token);
+        if let Some(cursor) = &self.state.cursor {
+            request = request.query(&[("after", cursor)]);
+        }
+        let pulled: PullResponse = request.send().await?.error_for_status()?.json().await?;
+        let mut last = local.last().cloned();
+        for record in pulled.records {
+            if record.device_id.as_deref() == Some(device.id.as_str()) {
+                continue;
+            }
+            let entry = self.open(&record.data)?;
+            backend.append_history(&entry)?;
+            last = Some(entry);
+            report.pulled += 1;
+        }
+
+        // Pulled entries are now in the journal too; they must not be pushed back
+        self.state.pushed += report.pushed;
+        self.state.mark((local.len() + report.pulled).saturating_sub(1), last.as_ref());
+        self.state.cursor = pulled.cursor.or(self.state.cursor.take());
+        self.save_state()?;
+        Ok(report)
+    }
+
+    pub fn device_name(&self) -> Option<&str> {
+        self.state.device.as_ref().map(|d| d.name.as_str())
+    }
+
+    pub fn pushed(&self) -> usize {
+        self.state.pushed
+    }
+
+    fn device(&self) -> Result<&Device> {
+        self.state
+            .device
+            .as_ref()
+            .context("This machine is not registered; run `nexterm sync register <name>`")
+    }
+
+    // base64(nonce || ciphertext || tag)
+    fn seal(&self, entry: &HistoryEntry) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        openssl::rand::rand_bytes(&mut nonce)?;
+        let mut tag = [0u8; TAG_LEN];
+        let plaintext = serde_json::to_vec(entry)?;
+        let ciphertext = encrypt_aead(Cipher::aes_256_gcm(), &self.key, Some(&nonce), &[], &plaintext, &mut tag)?;
+        Ok(BASE64.encode([&nonce[..], &ciphertext, &tag].concat()))
+    }
+
+    fn open(&self, data: &str) -> Result<HistoryEntry> {
+        let sealed = BASE64.decode(data).context("Malformed sync record")?;
+        if sealed.len() < NONCE_LEN + TAG_LEN {
+            bail!("Malformed sync record");
+        }
+        let (nonce, rest) = sealed.split_at(NONCE_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+        let plaintext = decrypt_aead(Cipher::aes_256_gcm(), &self.key, Some(nonce), &[], ciphertext, tag)
+            .context("Failed to decrypt sync record; is the same key installed on every device?")?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    fn save_state(&self) -> Result<()> {
+        // Holds the device's bearer token
+        durable::write_private(&state_path(), serde_json::to_string_pretty(&self.state)?.as_bytes())
+    }
+}
+
+// The key shared by all devices, base64 encoded; print it on one machine and
+// `sync key import` it on the others
+pub fn export_key() -> Result<String> {
+    Ok(BASE64.encode(load_or_create_key()?))
+}
+
+pub fn import_key(encoded: &str) -> Result<()> {
+    let key = BASE64.decode(encoded.trim()).context("Sync key is not valid base64")?;
+    if key.len() != KEY_LEN {
+        bail!("Sync key must be {} bytes", KEY_LEN);
+    }
+    write_key(&key)
+}
+
+fn load_or_create_key() -> Result<Vec<u8>> {
+    if let Ok(encoded) = std::fs::read_to_string(key_path()) {
+        return BASE64.decode(encoded.trim()).context("Sync key file is corrupt");
+    }
+    let mut key = vec![0u8; KEY_LEN];
+    openssl::rand::rand_bytes(&mut key)?;
+    write_key(&key)?;
+    Ok(key)
+}
+
+fn write_key(key: &[u8]) -> Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let path = key_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    file.write_all(BASE64.encode(key).as_bytes())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+pub fn key_path() -> PathBuf {
+    let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("nexterm");
+    path.push("sync.key");
+    path
+}
+
+fn state_path() -> PathBuf {
+    let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("nexterm");
+    path.push("sync.json");
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::storage::{FileBackend, StorageBackend};
+
+    fn entry(command: &str, timestamp: u64) -> HistoryEntry {
+        HistoryEntry { command: command.to_string(), timestamp, cwd: None, exit_code: None, env: None }
+    }
+
+    #[test]
+    fn pending_survives_history_trimming() {
+        let dir = std::env::temp_dir().join(format!("nexterm-sync-{}", std::process::id()));
+        let mut backend = FileBackend::new(dir.clone(), 0);
+        for (i, command) in ["ls", "make", "ls", "git status"].iter().enumerate() {
+            backend.append_history(&entry(command, i as u64)).unwrap();
+        }
+        let mut state = SyncState::default();
+        let local = backend.load_history().unwrap();
+        assert_eq!(state.pending(&local).len(), 4);
+        state.mark(local.len() - 1, local.last());
+        assert!(state.pending(&local).is_empty());
+
+        // What History::open does past max_entries, then two more commands
+        backend.replace_history(&local[3..]).unwrap();
+        backend.append_history(&entry("cargo test", 10)).unwrap();
+        backend.append_history(&entry("ls", 11)).unwrap();
+        let local = backend.load_history().unwrap();
+        let commands: Vec<&str> = state.pending(&local).iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["cargo test", "ls"]);
+
+        // Trimmed past the mark: everything left is new
+        backend.replace_history(&local[2..]).unwrap();
+        assert_eq!(state.pending(&backend.load_history().unwrap()).len(), 1);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}